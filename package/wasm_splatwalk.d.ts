@@ -392,6 +392,41 @@ export function splatwalk_api_version(): number;
 /** Additive capability flags advertised by this build. */
 export function splatwalk_capabilities(): string[];
 
+/** Thread support `init_splatwalk` detected (capability `thread_support`). */
+export interface SplatwalkThreadSupport {
+  /** `multi_thread` once `splatwalk_start_threads` has the bake pool up. */
+  execution: 'multi_thread' | 'single_thread';
+  /** What the build and the page allow. */
+  supported_mode: 'multi_thread' | 'single_thread';
+  usable_threads: number;
+  /** `reduced` when the build or the page keeps threads off a multi-core device. */
+  performance_class: 'full' | 'reduced';
+  reason?:
+    | 'single_threaded_build'
+    | 'no_shared_array_buffer'
+    | 'not_cross_origin_isolated'
+    | 'main_thread'
+    | 'single_core';
+  threaded_build: boolean;
+  cross_origin_isolated: boolean;
+  shared_array_buffer: boolean;
+  /** A worker or non-browser host, where a bake may wait on the pool. */
+  can_block: boolean;
+  hardware_concurrency: number;
+  /** Workers in the running bake pool, 0 before it starts. */
+  pool_threads: number;
+}
+
+/** The thread support `init_splatwalk` detected. */
+export function splatwalk_thread_support(): SplatwalkThreadSupport;
+
+/**
+ * Start the bake pool when `splatwalk_thread_support` allows threads (builds
+ * with the `threads` feature only). Settles with the updated report once the
+ * pool is up, or at once when it is not allowed. Await it before the first bake.
+ */
+export function splatwalk_start_threads(): Promise<SplatwalkThreadSupport>;
+
 /**
  * Register (or, with `undefined`, clear) an opt-in progress callback invoked as
  * `callback(stage, fraction)` at the same boundaries as the `@progress` line
@@ -479,6 +514,50 @@ export function build_room_floor_mesh(data: Uint8Array, settings: RoomFloorSetti
  */
 export function mesh_to_glb(positions: Float32Array, indices: Uint32Array): Uint8Array;
 
+/**
+ * Serialize a result's mesh as Wavefront OBJ text. `options` opt into `vn`
+ * normals, `vt` uvs, per-vertex colours on `v` lines and an object name.
+ */
+export function export_obj(
+  mesh: { vertices: Float32Array | number[]; indices: Uint32Array | number[]; normals?: Float32Array | number[]; colors?: Float32Array | number[]; uvs?: Float32Array | number[] },
+  options?: { normals?: boolean; colors?: boolean; uvs?: boolean; name?: string },
+): string;
+
+/**
+ * Serialize a result's mesh as Stanford PLY bytes, binary little-endian
+ * unless `format` is `'ascii'`. `options` opt into normals and colours.
+ */
+export function export_ply(
+  mesh: { vertices: Float32Array | number[]; indices: Uint32Array | number[]; normals?: Float32Array | number[]; colors?: Float32Array | number[] },
+  options?: { format?: 'binary_little_endian' | 'ascii'; normals?: boolean; colors?: boolean },
+): Uint8Array;
+
+/**
+ * Wrap a result's mesh in a left-handed `.babylon` JSON scene for the Babylon
+ * sandbox or `SceneLoader`. `space` is the result's `space`.
+ */
+export function export_babylon(
+  mesh: { vertices: Float32Array | number[]; indices: Uint32Array | number[]; normals?: Float32Array | number[]; colors?: Float32Array | number[]; uvs?: Float32Array | number[] },
+  options?: { space?: string; name?: string; colors?: boolean; uvs?: boolean; check_collisions?: boolean; hidden?: boolean; metadata?: unknown },
+): string;
+
+/**
+ * Identify a saved project, bake manifest or `NavMeshQuery.save` blob without
+ * loading it. `readable` is false for files from a newer build.
+ */
+export function inspect_artifact(bytes: Uint8Array): {
+  kind: string;
+  container_version: number;
+  version: number;
+  current_version: number | null;
+  legacy: boolean;
+  readable: boolean;
+  chunks: { tag: string; required: boolean; byte_length: number }[];
+};
+
+/** Rewrite a saved artifact in the current container and schema version. */
+export function migrate_artifact(bytes: Uint8Array): Uint8Array;
+
 /** Slice a `.ply`/`.spz` splat into a streamed-SOG bundle. */
 export function slice_splat(data: Uint8Array, settings: SliceSettings): SliceManifest;
 
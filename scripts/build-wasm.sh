@@ -4,6 +4,8 @@ set -e
 # Build a single WASM crate
 # Usage: ./build-wasm.sh <crate-name> <output-dir>
 # Example: ./build-wasm.sh wasm-astar pkg/wasm_astar
+# Set CARGO_FEATURES to enable optional crate features, e.g. CARGO_FEATURES=draco
+# The `threads` feature builds with atomics, which needs nightly and rust-src
 
 if [ $# -lt 2 ]; then
     echo "Usage: $0 <crate-name> <output-dir>"
@@ -66,7 +68,18 @@ rm -f "$TARGET_DIR/wasm32-unknown-unknown/release/deps/${WASM_FILENAME}*" 2>/dev
 # Remove incremental compilation artifacts for this package
 rm -rf "$TARGET_DIR/wasm32-unknown-unknown/release/incremental/${WASM_FILENAME}*" 2>/dev/null || true
 
-if ! cargo build --target wasm32-unknown-unknown --release --package "$CRATE_NAME"; then
+# Threaded builds share linear memory between workers, so std is rebuilt with
+# atomics and bulk memory (wasm-bindgen-rayon's documented setup).
+CARGO_TOOLCHAIN=()
+BUILD_STD=()
+if [[ ",$CARGO_FEATURES," == *",threads,"* || " $CARGO_FEATURES " == *" threads "* ]]; then
+    echo "Building with threads (nightly, atomics)..."
+    export RUSTFLAGS="${RUSTFLAGS:+$RUSTFLAGS }-C target-feature=+atomics,+bulk-memory"
+    CARGO_TOOLCHAIN=(+nightly)
+    BUILD_STD=(-Z build-std=panic_abort,std)
+fi
+
+if ! cargo "${CARGO_TOOLCHAIN[@]}" build --target wasm32-unknown-unknown --release --package "$CRATE_NAME" "${BUILD_STD[@]}" ${CARGO_FEATURES:+--features "$CARGO_FEATURES"}; then
     echo "ERROR: cargo build failed for $CRATE_NAME" >&2
     exit 1
 fi
@@ -80,12 +80,17 @@ export interface FloorSheetNavMeshResult {
  * Unlike {@link generateSoloNavMesh}, this skips `filterLedgeSpans`. Floor-field
  * meshes are intentional thin sheets with holes/borders; Recast treats every
  * missing neighbor as a cliff and can cull the entire walkable surface.
+ *
+ * `triangleAreas` (e.g. WASM `path_costs.face_areas`) assigns a Detour area id
+ * per input triangle. Area 0 keeps the default walkable area; other ids survive
+ * into the navmesh polygons so a query filter can price them with `setAreaCost`.
  */
 export const generateFloorSheetSoloNavMesh = (
   positions: ArrayLike<number>,
   indices: ArrayLike<number>,
   config: FloorSheetNavMeshConfig,
-  keepIntermediates = true
+  keepIntermediates = true,
+  triangleAreas?: ArrayLike<number>
 ): FloorSheetNavMeshResult => {
   const buildContext = new RecastBuildContext();
   const intermediates: FloorSheetNavMeshIntermediates = {
@@ -190,6 +195,19 @@ export const generateFloorSheetSoloNavMesh = (
     numTriangles,
     triangleAreasArray
   );
+  if (triangleAreas) {
+    // Only re-label triangles Recast kept walkable; steep ones stay null.
+    for (let i = 0; i < numTriangles && i < triangleAreas.length; i++) {
+      const area = triangleAreas[i];
+      if (
+        area > 0 &&
+        area < Recast.RC_WALKABLE_AREA &&
+        triangleAreasArray.get(i) === Recast.RC_WALKABLE_AREA
+      ) {
+        triangleAreasArray.set(i, area);
+      }
+    }
+  }
   if (
     !rasterizeTriangles(
       buildContext,
@@ -297,7 +315,7 @@ export const generateFloorSheetSoloNavMesh = (
     if (polyMesh.areas(i) === Recast.RC_WALKABLE_AREA) {
       polyMesh.setAreas(i, 0);
     }
-    if (polyMesh.areas(i) === 0) {
+    if (polyMesh.areas(i) < Recast.RC_WALKABLE_AREA) {
       polyMesh.setFlags(i, 1);
     }
   }
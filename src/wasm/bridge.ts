@@ -10,20 +10,221 @@ interface SliceResultRaw {
     chunkCount: number;
 }
 
+/**
+ * Flat mesh buffers. Typed arrays when the call set `typed_buffers: true`,
+ * plain number arrays otherwise; `new Float32Array(mesh.vertices)` accepts both.
+ */
 export interface MeshBuffers {
     vertices: Float32Array;
     indices: Uint32Array;
     vertex_count: number;
     face_count: number;
+    /** Angle-weighted unit normal per vertex (capability `vertex_normals`). */
+    normals: Float32Array;
+    /** Per-vertex RGB in [0, 1], present when the call set `emit_colors: true`. */
+    colors?: Float32Array;
+    /** Per-vertex UVs into `walkability_texture`, or from `generate_uvs`. */
+    uvs?: Float32Array;
+    /** Per-vertex metres to the nearest splat (-1 = none in reach), present with `emit_deviation`. */
+    vertex_deviation?: Float32Array;
+    /** `vertex_deviation` as RGB in [0, 1], green to red at `deviation_range`. */
+    deviation_colors?: Float32Array;
+}
+
+/** Splat coverage of each floor-field cell as a grayscale texture (capability `walkability_texture`). */
+export interface WalkabilityTexture {
+    width: number;
+    height: number;
+    /** `width * height` bytes, row-major from row 0; 255 = well covered, 0 = uncovered / off floor. */
+    pixels: Uint8Array;
+    /** Coverage that maps to 255 (the 90th percentile of the floor cells). */
+    saturation: number;
+}
+
+/** One ground-field attribute as an RGBA image for a 2D canvas (capability `field_preview`). */
+export interface FieldPreview {
+    channel: 'height' | 'coverage' | 'slope';
+    width: number;
+    height: number;
+    /** `width * height * 4` RGBA bytes, row-major from cell row 0 at the top; void cells transparent. */
+    pixels: Uint8Array;
+    /** Attribute values at the ends of the color ramp (metres, surface confidence or degrees). */
+    min: number;
+    max: number;
+}
+
+/** Camera and size of `emit_thumbnail` (capability `thumbnail`); points in `splatwalk_oriented`. */
+export interface ThumbnailSettings {
+    /** Pixels, 1..4096. Default 256 x 256. */
+    width?: number;
+    height?: number;
+    /** Orbit camera: degrees about `+Y` from `+Z` towards `+X` (default 45) and above the horizon (default 35). */
+    azimuth?: number;
+    elevation?: number;
+    /** Vertical field of view in degrees. Default 40. */
+    fov?: number;
+    /** Camera position, replacing the orbit. */
+    eye?: [number, number, number];
+    /** Point looked at. Default the centre of the mesh bounds. */
+    target?: [number, number, number];
+    /** RGBA 0-255. Default transparent. */
+    background?: [number, number, number, number];
+}
+
+/** `mesh` rendered in software (capability `thumbnail`). */
+export interface Thumbnail {
+    width: number;
+    height: number;
+    /** `width * height * 4` RGBA bytes, row-major from the top row; background where no face was drawn. */
+    pixels: Uint8Array;
+    /** The same image as a PNG file. */
+    png: Uint8Array;
+    /** The camera used, in `splatwalk_oriented`. */
+    eye: [number, number, number];
+    target: [number, number, number];
+}
+
+/** Encoding of `emit_heightmap` (capability `heightmap_export`). */
+export interface HeightmapSettings {
+    /** `'png'` (default): 16-bit grayscale PNG; `'raw'`: little-endian `u16` samples. */
+    format?: 'png' | 'raw';
+}
+
+/** Mode 2 floor heights at every voxel corner, in `splatwalk_oriented` (capability `heightmap_export`). */
+export interface Heightmap {
+    format: 'png' | 'raw';
+    /** Samples along `+X`, and rows from `max.z` down to `min.z`. */
+    width: number;
+    height: number;
+    /** Metres between neighbouring samples. */
+    cell_size: number;
+    /** `x`/`z`: first and last sample; `y`: the heights of sample values 0 and 65535. */
+    min: [number, number, number];
+    max: [number, number, number];
+    /** The PNG file, or `width * height` raw samples. */
+    data: Uint8Array;
+}
+
+/** Quantization of `emit_draco` (capability `draco_export`); 0 leaves an optional attribute out. */
+export interface DracoSettings {
+    /** Default 14, 1 to 30. */
+    position_bits?: number;
+    /** Default 10. */
+    normal_bits?: number;
+    /** Default 8, when the mesh has `colors`. */
+    color_bits?: number;
+    /** Default 12, when the mesh has `uvs`. */
+    uv_bits?: number;
+}
+
+/** `mesh` as a Draco bitstream (capability `draco_export`). */
+export interface DracoMesh {
+    /** Draco sequential mesh encoding, bitstream 2.2 (`.drc`). */
+    bytes: Uint8Array;
+    vertex_count: number;
+    face_count: number;
+    attributes: {
+        semantic: 'POSITION' | 'NORMAL' | 'COLOR_0' | 'TEXCOORD_0';
+        /** Draco unique id, as `KHR_draco_mesh_compression.attributes` maps it. */
+        unique_id: number;
+        quantization_bits: number;
+        min: number[];
+        range: number;
+    }[];
+    /** Size of the same buffers as Float32Array / Uint32Array. */
+    raw_byte_length: number;
+}
+
+/** Settings ranked by how much they move the ground field (capability `parameter_sensitivity`). */
+export interface SensitivityReport {
+    step: number;
+    surrogate_cell_size: number;
+    /** Walkable area (m^2) and component count of the surrogate baseline. */
+    baseline_area: number;
+    baseline_components: number;
+    /** Most sensitive first. */
+    parameters: ParameterSensitivity[];
+}
+
+export interface ParameterSensitivity {
+    setting: string;
+    /** The setting as used, or its default when unset. */
+    value: number;
+    /** The nudged values below and above `value`. */
+    lower: number;
+    upper: number;
+    /** At `lower` and `upper`; `null` when no field could be built there. */
+    area: [number | null, number | null];
+    components: [number | null, number | null];
+    /** Largest relative change of the area or component count; 1 when a nudge lost the field. */
+    score: number;
+}
+
+/** Options for {@link SplatWalkBridge.meshToGlb} (capability `glb_normals`). */
+export interface GlbOptions {
+    /** Emit angle-weighted vertex normals as a `NORMAL` attribute. */
+    normals?: boolean;
+    /** glTF mesh and node name. */
+    name?: string;
+}
+
+/** Options for {@link SplatWalkBridge.meshToObj} (capability `obj_export`). */
+export interface ObjOptions {
+    /** Write `vn` normals: the mesh's own, else angle-weighted ones from the winding. */
+    normals?: boolean;
+    /** Append the mesh's `colors` to each `v` line (MeshLab / Blender vertex-colour extension). */
+    colors?: boolean;
+    /** Write the mesh's `uvs` as `vt` lines. */
+    uvs?: boolean;
+    /** OBJ object name (`o` line). */
+    name?: string;
+}
+
+/** Options for {@link SplatWalkBridge.meshToPly} (capability `ply_export`). */
+export interface PlyOptions {
+    /** Encoding; default `'binary_little_endian'`. */
+    format?: 'binary_little_endian' | 'ascii';
+    /** Write `nx ny nz`: the mesh's own normals, else angle-weighted ones from the winding. */
+    normals?: boolean;
+    /** Write the mesh's `colors` as `red green blue` bytes. */
+    colors?: boolean;
+}
+
+/** Options for {@link SplatWalkBridge.meshToBabylon} (capability `babylon_export`). */
+export interface BabylonOptions {
+    /** The result's `space`; default `'splatwalk_oriented'`. `engine_output` is refused. */
+    space?: 'splatwalk_oriented' | CoordinateSystem;
+    /** Mesh name and id. Default `'navmesh'`. */
+    name?: string;
+    /** Write the mesh's `colors` as vertex colours. */
+    colors?: boolean;
+    /** Write the mesh's `uvs`. */
+    uvs?: boolean;
+    /** Mark the mesh as a Babylon collider (`checkCollisions`). */
+    check_collisions?: boolean;
+    /** Load the mesh hidden, e.g. as an invisible collider. */
+    hidden?: boolean;
+    /** Stored as the mesh's `metadata`. */
+    metadata?: unknown;
 }
 
 export interface CoordinateSpace {
-    /** `splatwalk_oriented` for default output, `engine_output` when an `output_space` conversion was applied. */
-    space: 'splatwalk_oriented' | 'engine_output' | string;
+    /**
+     * `splatwalk_oriented` for default output, `engine_output` when an `output_space` conversion was applied,
+     * or the {@link CoordinateSystem} name under `coordinate_system`.
+     */
+    space: 'splatwalk_oriented' | 'engine_output' | CoordinateSystem | string;
     up_axis: 'y' | 'z' | string;
     handedness: 'right' | 'left' | string;
 }
 
+/**
+ * Host convention preset for {@link MeshSettings.coordinate_system} (capability
+ * `coordinate_system`): output axes and winding, the `flip_y` default, and the
+ * space region settings are read in.
+ */
+export type CoordinateSystem = 'babylon-lh' | 'threejs-rh' | 'gltf' | 'z-up';
+
 /**
  * Opt-in output coordinate convention for {@link MeshSettings.output_space}.
  *
@@ -48,6 +249,25 @@ export interface FloorPlane {
     d: number;
 }
 
+/** One ranked RANSAC ground hypothesis; pass `index` back as `chosen_ground_index`. */
+export interface GroundCandidate {
+    index: number;
+    plane: FloorPlane;
+    height: number;
+    inliers: number;
+    extent_min: [number, number, number];
+    extent_max: [number, number, number];
+}
+
+/** Floor / wall / clutter group from `normal_clusters`. */
+export interface NormalCluster {
+    label: 'floor' | 'wall' | 'clutter';
+    splat_count: number;
+    mean_normal: [number, number, number];
+    min: [number, number, number];
+    max: [number, number, number];
+}
+
 export interface FieldBasis {
     origin: [number, number, number];
     tangent: [number, number, number];
@@ -55,6 +275,20 @@ export interface FieldBasis {
     up: [number, number, number];
 }
 
+/** `ReconstructionResult.frame`. */
+export interface GroundFrame {
+    /** Modes 1, 5 and untiled 2; normal faces up. */
+    floor_plane?: FloorPlane;
+    /** Mode 2: the voxel grid. Otherwise a basis on `floor_plane`. */
+    basis?: FieldBasis;
+    /** Cell edge of the mode 2 grid basis. */
+    cell_size?: number;
+    /** Column-major 4x4 from the result's space to the splat file's. */
+    to_splat: number[];
+    /** The inverse of `to_splat`: from the splat file's space to the result's. */
+    from_splat: number[];
+}
+
 export type GroundFieldCellState =
     | 'walkable'
     | 'low_confidence'
@@ -73,6 +307,8 @@ export interface GroundFieldCell {
     obstacle_score: number;
     primary_layer_height: number;
     layer_count: number;
+    /** Which density layer (bottom first) is the floor. */
+    primary_layer: number;
     peak_density: number;
     surface_confidence: number;
     signed_distance: number;
@@ -93,6 +329,7 @@ export interface ReconstructionDiagnostics {
     points_region_discarded: number;
     points_after_filter: number;
     ransac_inliers: number;
+    ransac_iterations: number;
     grid_width: number;
     grid_height: number;
     cell_size: number;
@@ -103,6 +340,8 @@ export interface ReconstructionDiagnostics {
     connected_components: number;
     largest_component_faces: number;
     holes_filled: number;
+    holes_inpainted: number;
+    cells_inpainted: number;
     rejected_cells: number;
     cells_rejected_low_confidence: number;
     cells_rejected_height_variance: number;
@@ -140,6 +379,75 @@ export interface ReconstructionDiagnostics {
     collision_external_fill_leaked: boolean;
     collision_failure_reason?: string;
     floor_plane?: FloorPlane;
+    ground_candidates: GroundCandidate[];
+    chosen_ground_index?: number;
+    normal_clusters: NormalCluster[];
+    cells_rejected_wall: number;
+    tsdf_voxel_size: number;
+    tsdf_grid_width: number;
+    tsdf_grid_height: number;
+    tsdf_grid_depth: number;
+    tsdf_observed_voxels: number;
+    /** Mode 5 candidate cell edge, candidate count and the height error left (m). */
+    terrain_cell_size: number;
+    terrain_candidates: number;
+    terrain_max_error: number;
+    /** Mode 6 ball radii used (m), edges left open and splats in no triangle. */
+    bpa_radii: number[];
+    bpa_boundary_edges: number;
+    bpa_unused_points: number;
+    /** Mode 7 hull vertices, enclosed volume (m³) and points left outside by `hull_max_vertices`. */
+    hull_vertices: number;
+    hull_volume: number;
+    hull_points_outside: number;
+    /** Mode 8 vertices placed on a crease or corner, and those clamped back into their cell. */
+    dc_sharp_vertices: number;
+    dc_clamped_vertices: number;
+    /** Mode 9 voxel edge (m) and the voxels the splats occupied. */
+    surface_nets_voxel_size: number;
+    surface_nets_occupied_voxels: number;
+    /** Mode 10 wall segments extruded, the floor and ceiling heights they span, and the ceiling's source. */
+    wall_segments: number;
+    wall_floor_y: number;
+    wall_ceiling_y: number;
+    wall_ceiling_source: '' | 'wall_height' | 'ceiling' | 'wall_cluster' | 'default';
+    /** `downsample_voxel_size` applied by `convertSplatToMesh` (0 = off). */
+    downsample_voxel_size: number;
+    points_after_downsample: number;
+    /** Triangles before / after `decimate_target_faces` (0 = off). */
+    decimate_input_faces: number;
+    decimate_output_faces: number;
+    /** Mean / largest `emit_deviation` distance (m) over vertices with a splat in reach (0 = off). */
+    deviation_mean: number;
+    deviation_max: number;
+    /** Laplacian passes applied to the `walkable_floors` navmesh heights (0 = off). */
+    smooth_iterations: number;
+    /** Base tolerance (m) and its source. */
+    epsilon: number;
+    epsilon_source: 'fixed' | 'scene' | 'explicit';
+    /** Vertices removed by `weld_vertices` (0 = off). */
+    welded_vertices: number;
+    /** Seed the bake's stochastic steps used. */
+    seed: number;
+    /** Faces kept / split off by `reachable_from` (0 = off). */
+    reachable_faces: number;
+    unreachable_faces: number;
+    unreachable_islands: number;
+    /** Regions / polygons of `navmesh` (0 = `emit_poly_navmesh` off). */
+    navmesh_regions: number;
+    navmesh_polys: number;
+    /** Floor voxels rejected by `agent_height` (0 = off). */
+    collision_low_headroom_floors: number;
+    /** Floor voxels ramped by `max_step_height` (0 = off). */
+    collision_stair_ramps: number;
+    /** Layers in `floor_layers` (0 = `emit_floor_layers` off). */
+    floor_layers: number;
+    /** Faces rejected by the `set_face_filter` callback (0 = none registered). */
+    face_filter_rejected: number;
+    /** `post_process` steps run (0 = unset). */
+    post_process_steps: number;
+    /** Tiles in `tiles` that hold faces (0 = no `tile_size`). */
+    tile_count: number;
 }
 
 /**
@@ -154,10 +462,447 @@ export interface ResultContract {
     capabilities: string[];
 }
 
+/**
+ * Thread support the worker's `init_splatwalk` detected, after `init()` has
+ * started the bake pool where it could. `execution` is `'multi_thread'` once
+ * the pool is up; the rest says whether the build and the page allow it. Warn
+ * on `performance_class: 'reduced'`: the device has cores the deployment
+ * keeps off, usually because the page is not cross-origin isolated.
+ */
+export interface SplatwalkThreadSupport {
+    execution: 'multi_thread' | 'single_thread';
+    supported_mode: 'multi_thread' | 'single_thread';
+    usable_threads: number;
+    performance_class: 'full' | 'reduced';
+    reason?:
+        | 'single_threaded_build'
+        | 'no_shared_array_buffer'
+        | 'not_cross_origin_isolated'
+        | 'main_thread'
+        | 'single_core';
+    threaded_build: boolean;
+    cross_origin_isolated: boolean;
+    shared_array_buffer: boolean;
+    can_block: boolean;
+    hardware_concurrency: number;
+    pool_threads: number;
+}
+
+/** One plane from `mode: 4`, with its own quad. */
+export interface DetectedPlane {
+    index: number;
+    /** Horizontal planes face up; the others face the cloud centroid. */
+    plane: FloorPlane;
+    orientation: 'horizontal' | 'vertical' | 'oblique';
+    inliers: number;
+    mesh: MeshBuffers;
+}
+
+/**
+ * Set on a result when a stage panicked after earlier stages produced output
+ * (capability `partial_results`); outputs from `failed_stage` on are empty.
+ */
+export interface PartialResult {
+    failed_stage: string;
+    message: string;
+}
+
+/** Faces the `reachable_from` flood did not reach (capability `reachability`). */
+export interface UnreachableSet {
+    mesh: MeshBuffers;
+    /** Per face: island index (0 = largest), or -1 when too steep to walk on. */
+    island: number[];
+    islands: number;
+    /** Walkable area of the islands (m^2). */
+    area: number;
+}
+
+/** Convex floor polygons from `emit_poly_navmesh` (capability `poly_navmesh`). */
+export interface PolyNavmesh {
+    /** xyz per vertex. */
+    vertices: number[];
+    /** Corner indices of every polygon, concatenated; wound like `mesh`. */
+    polys: number[];
+    /** Corners per polygon. */
+    poly_sizes: number[];
+    /** Parallel to `polys`: polygon across the edge to the next corner, or -1. */
+    neighbors: number[];
+    poly_regions: number[];
+    region_count: number;
+    max_verts_per_poly: number;
+}
+
+/** One storey of `floor_layers` (capability `floor_layers`). */
+export interface FloorLayer {
+    mesh: MeshBuffers;
+    /** Lowest / highest floor in the layer (m, `splatwalk_oriented` y). */
+    min_y: number;
+    max_y: number;
+    area: number;
+}
+
+export interface FloorLayers {
+    /** Bottom up; a layer's ID is its index. */
+    layers: FloorLayer[];
+    /** Layer ID pairs (lower first) joined by stairs or ramps. */
+    connections: [number, number][];
+    /** Layers under `floor_layer_min_area` left out. */
+    dropped: number;
+}
+
+/** The carved grid as a ROS `map_server` map (capability `occupancy_grid`). */
+export interface OccupancyGrid {
+    /** Cells along `+X`, and rows from `max z` down to `min z`. */
+    width: number;
+    height: number;
+    /** Metres per cell (the collision voxel size). */
+    resolution: number;
+    /** World `x`, `z` of the image's lower-left corner, and yaw 0. */
+    origin: [number, number, number];
+    /** Cells of each class. */
+    free: number;
+    occupied: number;
+    unknown: number;
+    /** Binary PGM: 254 free, 0 occupied, 205 unknown. */
+    pgm: Uint8Array;
+    /** `map_server` YAML for `pgm` saved as `occupancy.pgm`. */
+    yaml: string;
+}
+
+/** One tile of `tiles` (capability `tiled_navmesh`). */
+export interface NavTile {
+    /** Tile (column, row) covers `origin + [column, row] * tile_size`. */
+    column: number;
+    row: number;
+    /** The tile's square, over the height of its faces. */
+    min: [number, number, number];
+    max: [number, number, number];
+    mesh: MeshBuffers;
+    /** Index in the result's `mesh` of each face of the tile's `mesh`. */
+    faces: number[];
+}
+
+export interface TileIndex {
+    /** After snapping to the voxel size and clamping to 4096 tiles (m). */
+    tile_size: number;
+    tile_border: number;
+    /** `[x, z]` corner of tile (0, 0), `splatwalk_oriented`. */
+    origin: [number, number];
+    columns: number;
+    rows: number;
+    /** Tiles that hold faces, row by row. */
+    tiles: NavTile[];
+}
+
+/** A box whose walkable surface may have changed (capability `streaming_tiles`). */
+export interface DirtyRegion {
+    /** Session source whose edit dirtied the box. */
+    source: string;
+    min: [number, number, number];
+    max: [number, number, number];
+}
+
+/** `SplatSession.append_chunk` result. */
+export interface ChunkUpdate {
+    point_count: number;
+    total_points: number;
+    /** `null` for a disabled source or an empty chunk. */
+    dirty: DirtyRegion | null;
+}
+
+/** One tile rebuilt by `bake_dirty_tiles`. */
+export interface TilePatch {
+    /** Anchored tile: covers `[column, column + 1) * tile_size` from the plan origin. */
+    column: number;
+    row: number;
+    min: [number, number, number];
+    max: [number, number, number];
+    /** Empty when the tile no longer holds walkable floor. */
+    mesh: MeshBuffers;
+}
+
+export interface TilePatches extends ResultContract {
+    tile_size: number;
+    tile_border: number;
+    /** The consumed dirty regions, in the bake frame. */
+    regions: DirtyRegion[];
+    /** Row by row. */
+    patches: TilePatch[];
+    space: CoordinateSpace;
+    diagnostics: ReconstructionDiagnostics;
+    partial?: PartialResult;
+}
+
+/** Walking distances from `distance_sources` (capability `distance_field`). */
+export interface DistanceField {
+    /** Per `mesh` vertex: metres to the nearest source, or -1 when unreached. */
+    distances: number[];
+    max_distance: number;
+    reached: number;
+}
+
+/** Steering directions toward `flow_goal` (capability `flow_field`). */
+export interface FlowField {
+    /** xyz unit direction per `mesh` face; zeros where the goal is out of reach. */
+    directions: number[];
+    /** Per face: metres to the goal, or -1 when out of reach. */
+    distances: number[];
+    reached_faces: number;
+}
+
+/** Per-face hazard flags (capability `hazard_tags`). */
+export interface HazardTags {
+    /** Per `mesh` face: 1 = under water, 2 = in a hazard volume (bits), 0 = safe. */
+    flags: number[];
+    hazard_faces: number;
+    /** Faces removed by `hazard_cut`. */
+    cut_faces: number;
+}
+
+/** A `hazard_volumes` entry, shaped like a `regions` rule. */
+export interface HazardVolume {
+    shape?: 'box' | 'sphere' | 'polygon' | 'frustum';
+    min?: [number, number, number];
+    max?: [number, number, number];
+    transform?: number[];
+    half_extents?: [number, number, number];
+    center?: [number, number, number];
+    radius?: number;
+    /** Flat XZ footprint `[x0, z0, x1, z1, ...]`. */
+    points?: number[];
+    y_min?: number;
+    y_max?: number;
+    /** `frustum`: camera-from-world and projection matrices, column-major. */
+    view?: number[];
+    projection?: number[];
+    /** `frustum`: screen rectangle `[x0, y0, x1, y1]`, Y down, in `viewport` units. */
+    rect?: [number, number, number, number];
+    /** `frustum`: canvas size. Default `[1, 1]` (rect as fractions). */
+    viewport?: [number, number];
+    /** `frustum`: distance bounds from the camera (m). Default 0 / unbounded. */
+    near?: number;
+    far?: number;
+}
+
+/** One `regions` rule (capabilities `region_rules`, `screen_region`). */
+export type RegionRule = HazardVolume & { mode?: 'include' | 'exclude' };
+
+/** One `post_process` step (capability `post_process`); each step reads only its own parameters. */
+export interface PostProcessStep {
+    step: 'weld' | 'smooth' | 'decimate' | 'skirt' | 'clip' | 'transfer_colors';
+    /** `weld`: merge distance (m). Default: the bake tolerance. */
+    distance?: number;
+    /** `smooth`: passes (default 1), step (0, 1] (default 0.5), stacked-corner link (m, default 0). */
+    iterations?: number;
+    lambda?: number;
+    step_link?: number;
+    /** `decimate`: face budget (required) and optional error bound (m). */
+    target_faces?: number;
+    max_error?: number;
+    /** `skirt`: drop below each open edge (m). Default 0.1. */
+    depth?: number;
+    /** `clip`: keep the faces inside (`mode: 'include'`) or outside (`'exclude'`) this rule. */
+    region?: RegionRule;
+    /** `transfer_colors`: splat color sample radius (m). Default: `color_sample_radius`. */
+    radius?: number;
+}
+
+/**
+ * What a `set_face_filter` callback receives (capability `face_filter`): up to
+ * 4096 consecutive output faces starting at `first_face`. Answer with one
+ * verdict per face (cost > 0 accepts, `0` / `false` rejects, `true` = cost 1)
+ * or `undefined` to accept the batch. The callback runs on the WASM thread.
+ */
+export interface FaceFilterBatch {
+    first_face: number;
+    count: number;
+    /** Degrees from horizontal. */
+    slope: Float32Array;
+    /** Centroid height in metres. */
+    height: Float32Array;
+    /** Summed opacity of the splats near the centroid. */
+    coverage: Float32Array;
+    /** RGB in [0, 1], three values per face. */
+    color: Float32Array;
+}
+
+/** One `off_mesh_links` entry (capability `off_mesh_links`). */
+export interface OffMeshLinkSetting {
+    start: [number, number, number];
+    end: [number, number, number];
+    /** How far across an end may be from the mesh, in metres (default 0.5). */
+    radius?: number;
+    /** Also traversable from `end` to `start` (default true). */
+    bidirectional?: boolean;
+}
+
+/** A link whose ends landed on `mesh`; ends snapped and in `output_space`. */
+export interface OffMeshLink {
+    /** Position in the `off_mesh_links` setting. */
+    index: number;
+    start: [number, number, number];
+    end: [number, number, number];
+    radius: number;
+    bidirectional: boolean;
+    start_face: number;
+    end_face: number;
+    /** `navmesh` polygons under the ends, with `emit_poly_navmesh`. */
+    start_poly?: number;
+    end_poly?: number;
+}
+
+export interface OffMeshLinks {
+    links: OffMeshLink[];
+    /** Links left out because an end had no walkable face in reach. */
+    dropped: { index: number; end: 'start' | 'end' }[];
+}
+
+/** What `NavMeshQuery.raycast` returns (capability `navmesh_raycast`); points in `output_space`. */
+export interface NavMeshRaycastHit {
+    /** False when the ray ran `max_distance` without reaching a boundary. */
+    hit: boolean;
+    point: [number, number, number];
+    /** Plan-view distance from the origin. */
+    distance: number;
+    /** Outward horizontal normal of the boundary edge hit, when `hit`. */
+    normal?: [number, number, number];
+}
+
+/** What `NavMeshQuery.simulate_agents` returns (capability `agent_walk_test`); points in `output_space`. */
+export interface NavMeshWalkReport {
+    agents: number;
+    steps: number;
+    /** No agent fell or got stuck and every target was reachable. */
+    playable: boolean;
+    falls: number;
+    stuck: number;
+    unreachable_targets: number;
+    /** Mean plan-view distance an agent covered, in metres. */
+    mean_distance: number;
+    /** The first 64 incidents, in the order they happened. */
+    incidents: {
+        agent: number;
+        /** 0 for unreachable targets, checked before the walk. */
+        step: number;
+        kind: 'fall' | 'stuck' | 'unreachable';
+        position: [number, number, number];
+        target?: [number, number, number];
+    }[];
+}
+
+/**
+ * One progress message from `bake_journal().events` (capability `event_catalog`).
+ * Key localized strings and UI off `code`; unknown codes may appear in later builds.
+ */
+export type SplatwalkEvent =
+    | { code: 'gzip_inflated'; compressed_bytes: number; inflated_bytes: number }
+    | { code: 'format_detected'; format: string }
+    | { code: 'splats_decoded'; count: number; format: string; sh_degree: number | null }
+    | { code: 'spz_decoded_natively'; version: number; reason: string }
+    | { code: 'parse_cache_reused'; count: number }
+    | { code: 'floater_prune_skipped'; reason: string; count: number }
+    | { code: 'floaters_pruned'; removed: number; k: number; std_ratio: number; input: number; kept: number }
+    | { code: 'splats_ingested'; count: number; flip_y: boolean }
+    | { code: 'splats_received'; count: number; mode: number }
+    | { code: 'reconstruction_started'; mode: number }
+    | { code: 'voxel_downsampled'; voxel_size: number; input: number; kept: number }
+    | { code: 'mesh_decimated'; input_faces: number; output_faces: number; target: number }
+    | { code: 'mesh_reconstructed'; vertices: number }
+    | { code: 'grid_coarsened'; voxels: number; cap: number; voxel_size: number; next_voxel_size: number }
+    | {
+          code: 'grid_configured';
+          cols: number;
+          layers: number;
+          rows: number;
+          voxels: number;
+          cell_size: number;
+          splats: number;
+          region_pinned: boolean;
+      }
+    | { code: 'cluster_filtered'; kept: number; removed: number }
+    | {
+          code: 'collision_carved';
+          cols: number;
+          layers: number;
+          rows: number;
+          cell_size: number;
+          occupied: number;
+          kept: number;
+          discarded: number;
+          filled: number;
+          carved: number;
+          faces: number;
+      }
+    | {
+          code: 'column_field_built';
+          cols: number;
+          rows: number;
+          cell_size: number;
+          y_bins: number;
+          clearance_min: number;
+          clearance_max: number;
+          surfaces: number;
+          multi_layer: number;
+          floor_bins: number;
+          obstacle_bins: number;
+          holes_filled: number;
+          eroded: number;
+          discarded: number;
+          rejected_confidence: number;
+          rejected_discontinuity: number;
+          rejected_obstacle: number;
+          void: number;
+      }
+    | { code: 'walls_fitted'; segments: number; dominant_angle_deg: number }
+    | { code: 'safe_area_found'; area: number; shape: string; inscribed_radius: number }
+    | { code: 'chokepoints_found'; count: number }
+    | { code: 'floor_polygons_extracted'; polygons: number; holes: number }
+    | { code: 'ellipsoids_built'; count: number; kept: number; discarded: number }
+    | { code: 'slice_started'; splats: number; sh_degree: number; lod_levels: number; splats_per_chunk: number }
+    | { code: 'slice_finished'; chunks: number }
+    | { code: 'tiles_patched'; regions: number; tiles: number; empty: number };
+
 export interface ReconstructionResult extends ResultContract {
     mesh: MeshBuffers;
+    /** `mode: 4` only: detected planes, largest first; `mesh` is their union. */
+    planes?: DetectedPlane[];
     space: CoordinateSpace;
     diagnostics: ReconstructionDiagnostics;
+    /** Present with `reachable_from`. */
+    unreachable?: UnreachableSet;
+    /** Mode 2 with `emit_poly_navmesh`. */
+    navmesh?: PolyNavmesh;
+    /** `navmesh` as a Recast/Detour sample `.bin`, with `emit_detour_navmesh`. */
+    detour_navmesh?: Uint8Array;
+    /** Placed and dropped `off_mesh_links`, when set. */
+    off_mesh_links?: OffMeshLinks;
+    /** Mode 2 with `emit_floor_layers`. */
+    floor_layers?: FloorLayers;
+    /** Mode 2 with `emit_occupancy_grid`. */
+    occupancy_grid?: OccupancyGrid;
+    /** Present with `distance_sources`. */
+    distance_field?: DistanceField;
+    /** Present with `flow_goal`. */
+    flow_field?: FlowField;
+    /** Present with `hazard_water_height` or `hazard_volumes`. */
+    hazards?: HazardTags;
+    /** Present while a `set_face_filter` callback is registered. */
+    path_costs?: PathCosts;
+    /** Mode 2 with `tile_size`. */
+    tiles?: TileIndex;
+    /** Ground plane, basis and the maps to and from the splat file. */
+    frame: GroundFrame;
+    /** `mesh` in the splat file's space, only with `emit_source_mesh`. */
+    source_mesh?: MeshBuffers;
+    /** `mesh` as a Draco bitstream, only with `emit_draco` on a `draco_export` build. */
+    draco?: DracoMesh;
+    partial?: PartialResult;
+    /** `mesh` as an image, only with `emit_thumbnail`. */
+    thumbnail?: Thumbnail;
+    /** Floor height grid, only with `emit_heightmap` on mode 2 untiled `walkable_floors` meshing. */
+    heightmap?: Heightmap;
+    /** Zip of every artifact of the bake, only with `emit_manifest`. */
+    manifest?: Uint8Array;
 }
 
 export interface SplatBounds extends ResultContract {
@@ -183,6 +928,7 @@ export interface NavmeshBasisResult extends ResultContract {
     basis: FieldBasis;
     floor_plane: FloorPlane;
     diagnostics: ReconstructionDiagnostics;
+    partial?: PartialResult;
 }
 
 /** Packed dense voxel volume for PC-style runtime walk (capability `collision_voxel_volume`). */
@@ -208,6 +954,7 @@ export interface CollisionVoxelBoundaryResult extends ResultContract {
     basis: FieldBasis;
     floor_plane: FloorPlane;
     diagnostics: ReconstructionDiagnostics;
+    partial?: PartialResult;
 }
 
 export interface WalkableGroundFieldResult extends ResultContract {
@@ -219,6 +966,10 @@ export interface WalkableGroundFieldResult extends ResultContract {
     floor_plane: FloorPlane;
     space: CoordinateSpace;
     diagnostics: ReconstructionDiagnostics;
+    /** Canvas preview of one cell attribute, only with `emit_preview`. */
+    preview?: FieldPreview;
+    /** Settings ranked by how much they move the field, only with `emit_sensitivity`. */
+    sensitivity?: SensitivityReport;
 }
 
 /** A single attempt in the optional WASM-side room-floor recovery ladder. */
@@ -263,11 +1014,235 @@ export interface RoomFloorMeshResult extends ResultContract {
     fallback_used: boolean;
     step_label: string;
     diagnostics: ReconstructionDiagnostics;
+    /** Source cell of each triangle, present only when `emit_face_cells` was set. */
+    face_cells?: FaceCells;
+    /** Editable floor heights (open with the WASM `FloorEditor`), only with `emit_heightfield`. */
+    heightfield?: Heightfield;
+    /** Per-triangle path costs, only with `emit_path_costs`. */
+    path_costs?: PathCosts;
+    /** Coverage texture sampled through `mesh.uvs`, only with `emit_walkability_texture`. */
+    walkability_texture?: WalkabilityTexture;
+    /** `mesh` in the splat file's space, only with `emit_source_mesh`. */
+    source_mesh?: MeshBuffers;
+    /** Zip of every artifact of the bake, only with `emit_manifest`. */
+    manifest?: Uint8Array;
+}
+
+/** `manifest.json` of a bake manifest, as `read_bake_manifest` returns it (capability `bake_manifest`). */
+export interface BakeManifestIndex {
+    format: 'splatwalk_bake';
+    version: number;
+    api_version: number;
+    semver: string;
+    entry_point: string;
+    space: CoordinateSpace;
+    files: { name: string; media_type: string; byte_length: number }[];
+}
+
+/** What `inspect_artifact` reports about a saved blob (capability `artifact_container`). */
+export interface ArtifactInfo {
+    /** `project`, `bake_manifest`, `navmesh_query`, or the raw tag of a kind this build does not know. */
+    kind: string;
+    /** 0 for files written before the container. */
+    container_version: number;
+    version: number;
+    /** Schema version this build writes; `null` for an unknown kind. */
+    current_version: number | null;
+    /** A pre-container `SWPJ` project blob or bare manifest zip. */
+    legacy: boolean;
+    /** False when the file needs a newer build. */
+    readable: boolean;
+    chunks: { tag: string; required: boolean; byte_length: number }[];
+}
+
+/**
+ * Path cost multiplier per floor triangle, quantized into Detour area ids. Feed
+ * `face_areas` to `generateFloorSheetSoloNavMesh` and call
+ * `filter.setAreaCost(area, area_costs[area])` on the query filter.
+ */
+export interface PathCosts {
+    face_costs: number[];
+    /** Detour area per triangle; 0 is the default area (cost 1). */
+    face_areas: number[];
+    area_costs: number[];
+}
+
+/** Room floor as per-cell heights along `basis.up`, always in `splatwalk_oriented`. */
+export interface Heightfield {
+    width: number;
+    height: number;
+    cell_size: number;
+    basis: FieldBasis;
+    /** Emitted height per cell (`row * width + col`); `NaN` where the cell is not floor. */
+    heights: number[];
+    /** Floor density layer per cell, bottom first. */
+    layers: number[];
+    /** Path cost multiplier per cell; may be omitted (all 1). */
+    costs?: number[];
+}
+
+/**
+ * One `FloorEditor.apply_brush` stroke (capability `floor_brush`). Points are in the
+ * editor's `output_space`, the same space as its re-emitted mesh.
+ */
+export interface FloorBrushStroke {
+    shape?: 'circle' | 'box';
+    op: 'add' | 'remove' | 'raise' | 'lower' | 'smooth' | 'cost';
+    center?: [number, number, number];
+    radius?: number;
+    min?: [number, number, number];
+    max?: [number, number, number];
+    /** `raise` / `lower` amount in metres. */
+    delta?: number;
+    /** `add` height along `basis.up`; defaults to the picked point's height. */
+    height?: number;
+    /** `smooth` blend toward the 3 x 3 neighbourhood mean, `(0, 1]`, default 0.5. */
+    strength?: number;
+    /** `cost` path cost multiplier, > 0. */
+    cost?: number;
+}
+
+/** Triangle-to-cell mapping of a room floor; grid as in `buildWalkableGroundField`. */
+export interface FaceCells {
+    grid_width: number;
+    grid_height: number;
+    cell_size: number;
+    /** `[row, col, layer]` per triangle, in index-buffer order. */
+    cells: number[];
+}
+
+/** One fitted wall line in oriented-space `[x, z]` metres. */
+export interface WallSegment {
+    start: [number, number];
+    end: [number, number];
+    length: number;
+    support: number;
+    snapped: boolean;
+}
+
+/** Result of {@link SplatWalkBridge.buildFloorPlan}: a vector floor plan of the walls. */
+export interface FloorPlanResult extends ResultContract {
+    floor_y: number;
+    /** Dominant wall orientation in [0, 90) degrees from +x towards +z. */
+    dominant_angle_deg: number;
+    wall_splat_count: number;
+    wall_cell_count: number;
+    segments: WallSegment[];
+    /** Outer boundary of the walkable floor, counter-clockwise [x, z]; empty without a ground field. */
+    floor_outline: [number, number][];
+    /** Floor area enclosed by `floor_outline` (m^2). */
+    floor_area: number;
+    bounds_min: [number, number];
+    bounds_max: [number, number];
+    /** Top view in metres, +z down the page. */
+    svg: string;
+    /** ASCII DXF: walls as LINEs on layer WALLS, outline as a closed POLYLINE on FLOOR; plan y = -z. */
+    dxf: string;
+    /** IFC4 STEP file (IfcWall per segment + floor IfcSlab), present when `emit_ifc` was set. */
+    ifc?: string;
+    space: CoordinateSpace;
+    diagnostics: ReconstructionDiagnostics;
+}
+
+/** Result of {@link SplatWalkBridge.buildSafeArea}: an obstacle-free play area (capability `safe_area`). */
+export interface SafeAreaResult extends ResultContract {
+    /** Standing point the area was grown from, on the floor. */
+    center: [number, number, number];
+    floor_y: number;
+    shape: 'convex' | 'star';
+    /** Boundary polygon at `floor_y`, counter-clockwise [x, z]. */
+    boundary: [number, number][];
+    /** Area enclosed by `boundary` (m^2). */
+    area: number;
+    /** Largest circle about `center` inside `boundary` (m). */
+    inscribed_radius: number;
+    clearance: number;
+    ceiling_height: number;
+    space: CoordinateSpace;
+    diagnostics: ReconstructionDiagnostics;
+}
+
+/** A narrow passage between two open areas of floor. */
+export interface Chokepoint {
+    /** Narrowest point of the passage, on the floor. */
+    position: [number, number, number];
+    /** Width there (m). */
+    width: number;
+    /** Line across the passage, `width` apart, e.g. for a door trigger. */
+    ends: [[number, number, number], [number, number, number]];
+    /** Floor area on either side (m^2), larger first. */
+    sides: [number, number];
+}
+
+/** Result of {@link SplatWalkBridge.findChokepoints} (capability `chokepoints`). */
+export interface ChokepointResult extends ResultContract {
+    /** Narrowest first. */
+    chokepoints: Chokepoint[];
+    min_width: number;
+    max_width: number;
+    space: CoordinateSpace;
+    diagnostics: ReconstructionDiagnostics;
+}
+
+/** One island of walkable floor, in plan `[x, z]` (`splatwalk_oriented`). */
+export interface FloorPolygon {
+    /** Outer boundary, counter-clockwise. */
+    outer: [number, number][];
+    /** Furniture, pillars and voids inside it, clockwise, largest first. */
+    holes: [number, number][][];
+    /** Walkable floor area of the island (m^2). */
+    area: number;
+    /** Lowest / highest floor under the island (m). */
+    min_y: number;
+    max_y: number;
+}
+
+/** Result of {@link SplatWalkBridge.extractFloorPolygons} (capability `floor_polygons`). */
+export interface FloorPolygonsResult extends ResultContract {
+    /** Largest island first. */
+    polygons: FloorPolygon[];
+    /** Islands and holes left out under `min_area`. */
+    dropped_islands: number;
+    dropped_holes: number;
+    tolerance: number;
+    min_area: number;
+    space: CoordinateSpace;
+    diagnostics: ReconstructionDiagnostics;
+}
+
+/**
+ * Result of {@link SplatWalkBridge.buildSplatEllipsoids} (capability
+ * `splat_ellipsoids`): the top splats as instances of a unit sphere.
+ */
+export interface SplatEllipsoidsResult extends ResultContract {
+    /** Ellipsoids returned, top ranked first. */
+    count: number;
+    /** Splats the filters kept and dropped, over the whole cloud. */
+    splats_kept: number;
+    splats_discarded: number;
+    /** Column-major 4x4 per ellipsoid mapping `template` onto the splat; translation at 12..14. */
+    matrices: Float32Array | number[];
+    /** Splat RGB in [0, 1], three per ellipsoid. */
+    colors: Float32Array | number[];
+    /** Per ellipsoid: 0 kept, 1 outside the region, 2 below `min_alpha` or above `max_scale`. */
+    verdicts: Uint8Array;
+    /** Each ellipsoid's splat, by its position in the source file. */
+    source_indices: number[];
+    /** Unit sphere, 18 vertices and 32 triangles. */
+    template: MeshBuffers;
+    /** `ellipsoid_mesh`: every ellipsoid merged, 18 vertices each in `count` order, with `colors`. */
+    mesh?: MeshBuffers;
+    space: CoordinateSpace;
 }
 
 export interface MeshSettings {
     mode: number;
     voxel_target?: number;
+    /** Cells wanted along X / Z instead of the `voxel_target` area heuristic. Cells stay square; the finer edge wins. */
+    voxel_target_u?: number;
+    voxel_target_v?: number;
+    /** Fixed grid resolution; beats every `voxel_target*`. */
+    cells_per_meter?: number;
     sdf_cell_size?: number;
     sdf_vertical_cell_size?: number;
     sdf_density_threshold?: number;
@@ -282,6 +1257,104 @@ export interface MeshSettings {
     collision_carve_height?: number;
     collision_carve_radius?: number;
     collision_mesh_mode?: 'faces' | 'obstacle_shell' | 'smooth' | 'walkable_floors';
+    /** `walkable_floors`: reject floor with splat mass less than this far above it (m). Off by default. */
+    agent_height?: number;
+    /** `walkable_floors`: ramp stair treads up to neighbours at most this much higher (m). Off by default. */
+    max_step_height?: number;
+    /** Mode 3 TSDF voxel edge (m). Default: derived from `voxel_target`. */
+    tsdf_voxel_size?: number;
+    /** Mode 3 truncation band half-width in voxels. Default 3. */
+    tsdf_truncation_cells?: number;
+    /** Mode 5 candidate cell edge (m). Default from `voxel_target`. */
+    terrain_cell_size?: number;
+    /** Mode 5 height error tolerance (m). Default 0.05. */
+    terrain_max_error?: number;
+    /** Mode 5 vertex budget. Default 20000. */
+    terrain_max_vertices?: number;
+    /** Mode 5 largest splat normal angle from the ground normal (degrees). Default 60. */
+    terrain_max_slope?: number;
+    /** Mode 6 ball radii (m), smallest first. Default 1.5 and 3 times the mean splat spacing. */
+    bpa_radii?: number[];
+    /** Mode 7 stops adding hull vertices at this many (at least 4). No limit by default. */
+    hull_max_vertices?: number;
+    /** Mode 8 keeps creases where planes meet at more than this (degrees, in (0, 90)). Default 30. */
+    dc_feature_angle?: number;
+    /** Mode 9 relaxation passes over the surface net (0-16). Default 2. */
+    surface_nets_smoothing?: number;
+    /** `convertSplatToMesh`: keep one averaged point per voxel of this edge (m). Off by default. */
+    downsample_voxel_size?: number;
+    /** `convertSplatToMesh`: also return `mesh.colors` sampled from nearby splat colors. */
+    /** `buildRoomFloorMesh`: also return `walkability_texture` and `mesh.uvs`. */
+    emit_walkability_texture?: boolean;
+    emit_colors?: boolean;
+    /** Splat color sampling radius (m). Default: twice the mean point spacing. */
+    color_sample_radius?: number;
+    /** `convertSplatToMesh`: also return `mesh.uvs`. */
+    generate_uvs?: boolean;
+    /** `generate_uvs` mapping; `grid` needs mode 2 without `tile_size`. Default `auto`. */
+    uv_projection?: 'auto' | 'planar' | 'box' | 'grid';
+    /** Metres per texture repeat for `planar` and `box`. Default 1. */
+    uv_scale?: number;
+    /** `convertSplatToMesh`: also return `mesh.vertex_deviation` and `mesh.deviation_colors`. */
+    emit_deviation?: boolean;
+    /** Deviation drawn fully red (m). Default 0.1. */
+    deviation_range?: number;
+    /** `convertSplatToMesh`: decimate to at most this many triangles (not mode 4). Off by default. */
+    decimate_target_faces?: number;
+    /** Stop decimating once a collapse would move the surface more than this (m). Default unbounded. */
+    decimate_max_error?: number;
+    /** `walkable_floors` Laplacian height smoothing passes. Off by default. */
+    smooth_iterations?: number;
+    /** Per-pass step toward the neighbour mean height, in (0, 1]. Default 0.5. */
+    smooth_lambda?: number;
+    /** Base tolerance source: 'fixed' (1 mm, default) or 'scene' (bounding-box diagonal). */
+    epsilon_policy?: 'fixed' | 'scene';
+    /** Explicit base tolerance in metres; overrides `epsilon_policy`. */
+    epsilon?: number;
+    /** `convertSplatToMesh`: merge vertices within the tolerance (not mode 4). */
+    weld_vertices?: boolean;
+    /** `convertSplatToMesh`: ordered post-process steps run on the output mesh (not mode 4). */
+    post_process?: PostProcessStep[];
+    /** `convertSplatToMesh`: keep only the walkable faces reachable from this [x, y, z]. */
+    reachable_from?: [number, number, number];
+    /** Reachability: tallest step between floors (m). Default 0.5. */
+    agent_max_climb?: number;
+    /** Reachability: steepest walkable face (deg). Default 40. */
+    agent_max_slope?: number;
+    /** Exits / spawn points to measure walking distance from. */
+    distance_sources?: [number, number, number][];
+    /** Goal to build a per-face flow field toward. */
+    flow_goal?: [number, number, number];
+    /** Flag faces whose centre is below this height. */
+    hazard_water_height?: number;
+    /** Flag faces whose centre is inside any of these volumes. */
+    hazard_volumes?: HazardVolume[];
+    /** Remove hazard faces from `mesh` instead of only flagging them. */
+    hazard_cut?: boolean;
+    /** Mode 2 (`walkable_floors`): also return `navmesh`, convex polygons with neighbours. */
+    emit_poly_navmesh?: boolean;
+    /** Poly navmesh: drop isolated regions below this area (m^2). Default 0.5. */
+    navmesh_min_region_area?: number;
+    /** Poly navmesh: merge regions below this area into a neighbour (m^2). Default 2. */
+    navmesh_merge_region_area?: number;
+    /** Poly navmesh: wall simplification tolerance (voxels). Default 1.3. */
+    navmesh_max_edge_error?: number;
+    /** Poly navmesh: most corners per polygon, 3..12. Default 6. */
+    navmesh_max_verts_per_poly?: number;
+    /** With `emit_poly_navmesh`: also return `detour_navmesh` (needs at most 6 corners per polygon). */
+    emit_detour_navmesh?: boolean;
+    /** Jumps, ladders and drops between two points of the walkable mesh (`splatwalk_oriented`). */
+    off_mesh_links?: OffMeshLinkSetting[];
+    /** Mode 2 (`walkable_floors`): also return `floor_layers`, one surface per storey. */
+    emit_floor_layers?: boolean;
+    /** Floor layers below this area are left out (m^2). Default 1. */
+    floor_layer_min_area?: number;
+    /** Mode 2: also return `occupancy_grid`, a ROS map of free, occupied and unknown cells. */
+    emit_occupancy_grid?: boolean;
+    /** Mode 2: build the navmesh in square tiles this wide (m) and return `tiles`. */
+    tile_size?: number;
+    /** Overlap each tile is reconstructed with beyond its edges (m). Default 1. */
+    tile_border?: number;
     /** PlayCanvas `--filter-cluster` on splats before fine voxelize (default true in WASM). */
     collision_filter_cluster?: boolean;
     /** Cap padded voxel grid size; WASM coarsens voxel_size when exceeded. */
@@ -290,6 +1363,103 @@ export interface MeshSettings {
     max_scale?: number;
     normal_align?: number;
     ransac_thresh?: number;
+    ransac_sample_size?: number;
+    /** Distinct ground planes reported in `diagnostics.ground_candidates` (default 4). */
+    ground_candidate_count?: number;
+    /** Bake against `diagnostics.ground_candidates[i]` from a previous call. */
+    chosen_ground_index?: number;
+    /** Older RANSAC-only seed; used when `seed` is unset. */
+    ransac_seed?: number;
+    /** Seed for every stochastic step (at most Number.MAX_SAFE_INTEGER). Fixed default. */
+    seed?: number;
+    /** `mode: 4`: most planes to extract. Default 8, clamped to 1..=32. */
+    plane_count?: number;
+    /** `mode: 4`: inlier distance (m). Default 0.2. */
+    plane_threshold?: number;
+    /** `mode: 4`: stop below this many inliers. Default 0.5% of the points, at least 50. */
+    plane_min_inliers?: number;
+    normal_clusters?: boolean;
+    floor_cone_deg?: number;
+    wall_cone_deg?: number;
+    floor_band_height?: number;
+    /** Return mesh buffers as Float32Array / Uint32Array instead of plain arrays. */
+    typed_buffers?: boolean;
+    /** `convertSplatToMesh` / `buildRoomFloorMesh`: also return `manifest`, a zip of every artifact. */
+    emit_manifest?: boolean;
+    /** `convertSplatToMesh` / `buildRoomFloorMesh`: also return `source_mesh`, the mesh in the splat file's coordinates. */
+    emit_source_mesh?: boolean;
+    /** `buildRoomFloorMesh`: also return `face_cells` (source cell per triangle). */
+    emit_face_cells?: boolean;
+    /** `buildRoomFloorMesh`: also return the editable `heightfield`. */
+    emit_heightfield?: boolean;
+    /** `buildRoomFloorMesh`: also return `path_costs` (cost + Detour area per triangle). */
+    emit_path_costs?: boolean;
+    /** Cell attribute path costs derive from. Default `'uniform'` (every cell costs 1). */
+    path_cost_from?: 'uniform' | 'confidence' | 'slope';
+    /** Cost added where the attribute is 0: `1 + scale * (1 - attribute)`. Default 4. */
+    path_cost_scale?: number;
+    /** `convertSplatToMesh`: also return `thumbnail`, `mesh` rendered from this camera. */
+    emit_thumbnail?: ThumbnailSettings;
+    /** `convertSplatToMesh`: also return `draco`, the mesh as a Draco bitstream. Needs a `draco_export` build. */
+    emit_draco?: DracoSettings;
+    /** `convertSplatToMesh` mode 2: also return `heightmap`, the floor's height grid. */
+    emit_heightmap?: HeightmapSettings;
+    /** `buildWalkableGroundField`: also return `preview`, this attribute as an RGBA image. */
+    emit_preview?: 'height' | 'coverage' | 'slope';
+    /** `buildWalkableGroundField`: also return `sensitivity`, the settings ranked by impact. */
+    emit_sensitivity?: boolean;
+    /** Relative nudge of the sensitivity report, 0..1. Default 0.25. */
+    sensitivity_step?: number;
+    /** `buildFloorPlan`: plan raster cell size in metres. Default 0.05. */
+    floor_plan_cell_size?: number;
+    /** `buildFloorPlan`: wall splats from this height above the floor... Default 0.3. */
+    floor_plan_min_height?: number;
+    /** ...up to this one. Default `floor_plan_min_height + 1.7`. */
+    floor_plan_max_height?: number;
+    /** `buildFloorPlan`: inlier distance of a wall line. Default: the cell size. */
+    wall_line_tolerance?: number;
+    /** `buildFloorPlan`: shortest wall segment kept (m). Default 0.3. */
+    wall_min_length?: number;
+    /** `buildFloorPlan`: gap that splits a wall line into two segments (m). Default 0.3. */
+    wall_max_gap?: number;
+    /** `buildFloorPlan`: snap window around the dominant orientation (deg). Default 10; 0 disables. */
+    wall_snap_deg?: number;
+    /** `buildFloorPlan`: maximum segments fitted. Default 64. */
+    wall_max_segments?: number;
+    /** `buildFloorPlan`: also return an IFC4 file of the walls and floor slab. */
+    emit_ifc?: boolean;
+    /** IFC and mode 10 wall height (m). Default: mode 10 the detected ceiling, then the top of the wall cluster above the floor, else 2.5. */
+    wall_height?: number;
+    /** IFC and mode 10 wall thickness (m). Default 0.1. */
+    wall_thickness?: number;
+    /** `buildSafeArea`: standing point [x, y, z]. Default: the most open floor cell. */
+    safe_area_center?: [number, number, number];
+    /** `buildSafeArea`: distance kept from obstacles and floor edges (m). Default 0.3. */
+    safe_area_clearance?: number;
+    /** `buildSafeArea`: required headroom above the floor (m). Default 2.2. */
+    safe_area_ceiling_height?: number;
+    /** `buildSafeArea`: farthest reach of the boundary from the standing point (m). Default 4. */
+    safe_area_max_radius?: number;
+    /** `buildSafeArea`: polygon shape. Default `convex`. */
+    safe_area_shape?: 'convex' | 'star';
+    /** `buildSafeArea`: rays cast from the standing point (8..720). Default 72. */
+    safe_area_rays?: number;
+    /** `findChokepoints`: passages narrower than this are closed (m). Default 0.4. */
+    chokepoint_min_width?: number;
+    /** `findChokepoints`: floor at least this wide is open (m). Default 1.5. */
+    chokepoint_max_width?: number;
+    /** `extractFloorPolygons`: farthest an outline strays from the floor cells (m). Default one cell. */
+    floor_polygon_tolerance?: number;
+    /** `extractFloorPolygons`: islands and holes below this area are dropped (m^2). Default 0.25. */
+    floor_polygon_min_area?: number;
+    /** `buildSplatEllipsoids`: ellipsoids returned (1..=100000). Default 2000. */
+    ellipsoid_count?: number;
+    /** `buildSplatEllipsoids`: rank splats by volume or opacity. Default `largest`. */
+    ellipsoid_rank?: 'largest' | 'opaque';
+    /** `buildSplatEllipsoids`: which filter verdicts to return. Default `all`. */
+    ellipsoid_filter?: 'all' | 'kept' | 'discarded';
+    /** `buildSplatEllipsoids`: also merge the ellipsoids into `mesh`. */
+    ellipsoid_mesh?: boolean;
     floor_projection_epsilon?: number;
     height_projection_epsilon?: number;
     obstacle_height_epsilon?: number;
@@ -298,10 +1468,13 @@ export interface MeshSettings {
     max_local_height_variance?: number;
     min_floor_confidence?: number;
     hole_fill_radius?: number;
+    fill_holes_max_area?: number;
     agent_radius_erode?: number;
     component_mode?: 'largest' | 'nearest_region_center' | 'all';
     region_min?: number[];
     region_max?: number[];
+    /** Ordered include/exclude rules; the last matching rule decides. */
+    regions?: RegionRule[];
     /**
      * Statistical outlier removal ("prune floaters"). When true (the default),
      * stray sparse splats far from the dense surface are removed before any
@@ -321,6 +1494,11 @@ export interface MeshSettings {
      * output (right-handed, `+Y` up, CCW). See {@link OutputSpaceSettings}.
      */
     output_space?: OutputSpaceSettings;
+    /**
+     * Host convention preset, instead of `output_space`. Also defaults `flip_y`
+     * and reads `region_*` / `crop_transform` / `regions` in the host's space.
+     */
+    coordinate_system?: CoordinateSystem;
     flip_y?: boolean;
     /**
      * Uniform world scale for oriented splat positions / gaussian scales (default 1).
@@ -329,6 +1507,21 @@ export interface MeshSettings {
     environment_scale?: number;
 }
 
+/** Stable `code` on errors thrown by the WASM entry points. */
+export type SplatwalkErrorCode =
+    | 'parse_error'
+    | 'unsupported_format'
+    | 'empty_cloud'
+    | 'invalid_settings'
+    | 'reconstruction_failed'
+    | 'internal_error';
+
+/** Rejection value of bridge calls: an `Error` plus the WASM error code and detail. */
+export interface SplatwalkError extends Error {
+    code?: SplatwalkErrorCode;
+    detail?: unknown;
+}
+
 interface PendingCall {
     resolve: (value: unknown) => void;
     reject: (reason: Error) => void;
@@ -349,7 +1542,7 @@ export class SplatWalkBridge {
     private worker: Worker | null = null;
     private seq = 0;
     private readonly pending = new Map<number, PendingCall>();
-    private initPromise: Promise<void> | null = null;
+    private initPromise: Promise<SplatwalkThreadSupport> | null = null;
     /** The Uint8Array reference currently loaded in the worker (identity check). */
     private activeData: Uint8Array | null = null;
     /** Number of in-flight worker calls; drives the busy indicator. */
@@ -399,7 +1592,12 @@ export class SplatWalkBridge {
                     if (!call) return;
                     this.pending.delete(data.id);
                     if (data.ok) call.resolve(data.result);
-                    else call.reject(new Error(data.error));
+                    else {
+                        const error: SplatwalkError = new Error(data.error);
+                        error.code = data.code;
+                        error.detail = data.detail;
+                        call.reject(error);
+                    }
                 }
             };
         }
@@ -429,14 +1627,15 @@ export class SplatWalkBridge {
         if (this.inflight === 0) this.onBusyChange?.(false);
     }
 
-    public async init(): Promise<void> {
+    /** Start the worker's WASM once; resolves to the execution mode it chose. */
+    public async init(): Promise<SplatwalkThreadSupport> {
         if (!this.initPromise) {
-            this.initPromise = this.call<void>('init', null).catch((error) => {
+            this.initPromise = this.call<SplatwalkThreadSupport>('init', null).catch((error) => {
                 this.initPromise = null;
                 throw error;
             });
         }
-        await this.initPromise;
+        return this.initPromise;
     }
 
     /** Transfer the splat bytes to the worker once; reuse for subsequent ops. */
@@ -488,20 +1687,123 @@ export class SplatWalkBridge {
         return this.call<RoomFloorMeshResult>('buildRoomFloorMesh', { settings });
     }
 
+    /**
+     * Fit regularized wall line segments to the wall normal cluster and export
+     * them as SVG and DXF (a CAD-ready floor plan).
+     */
+    public async buildFloorPlan(data: Uint8Array, settings: MeshSettings): Promise<FloorPlanResult> {
+        await this.ensureLoaded(data);
+        return this.call<FloorPlanResult>('buildFloorPlan', { settings });
+    }
+
+    /**
+     * Grow an obstacle-free play area (clearance + headroom) around a standing
+     * point and return its boundary polygon, e.g. for a VR guardian.
+     */
+    public async buildSafeArea(data: Uint8Array, settings: MeshSettings): Promise<SafeAreaResult> {
+        await this.ensureLoaded(data);
+        return this.call<SafeAreaResult>('buildSafeArea', { settings });
+    }
+
+    /**
+     * Find narrow passages between open areas of floor (doorways, gaps
+     * between furniture) with their widths, narrowest first.
+     */
+    public async findChokepoints(data: Uint8Array, settings: MeshSettings): Promise<ChokepointResult> {
+        await this.ensureLoaded(data);
+        return this.call<ChokepointResult>('findChokepoints', { settings });
+    }
+
+    /**
+     * The walkable floor as simplified 2D polygons with holes, one per
+     * island of floor, largest first: an automatic floor plan.
+     */
+    public async extractFloorPolygons(data: Uint8Array, settings: MeshSettings): Promise<FloorPolygonsResult> {
+        await this.ensureLoaded(data);
+        return this.call<FloorPolygonsResult>('extractFloorPolygons', { settings });
+    }
+
+    /**
+     * The largest (or most opaque) splats as low-poly ellipsoids tagged with
+     * the splat filters' verdict, for debug views of what the filters keep.
+     */
+    public async buildSplatEllipsoids(data: Uint8Array, settings: MeshSettings): Promise<SplatEllipsoidsResult> {
+        await this.ensureLoaded(data);
+        return this.call<SplatEllipsoidsResult>('buildSplatEllipsoids', { settings });
+    }
+
     /**
      * Serialize a positions + indices triangle mesh into minimal GLB bytes via the
      * WASM glTF writer (no 3D engine needed). Caller arrays are copied, not detached.
+     * Pass `options` to add vertex normals or a mesh name (capability `glb_normals`).
      */
-    public async meshToGlb(positions: Float32Array, indices: Uint32Array): Promise<Uint8Array> {
+    public async meshToGlb(
+        positions: Float32Array,
+        indices: Uint32Array,
+        options?: GlbOptions
+    ): Promise<Uint8Array> {
         const positionsBuffer = positions.slice().buffer;
         const indicesBuffer = indices.slice().buffer;
         return this.call<Uint8Array>(
             'meshToGlb',
-            { positions: positionsBuffer, indices: indicesBuffer },
+            { positions: positionsBuffer, indices: indicesBuffer, options },
             [positionsBuffer, indicesBuffer]
         );
     }
 
+    /**
+     * Serialize a result's mesh as Wavefront OBJ text via the WASM writer, for an
+     * `.obj` download. Extras are opt-in through `options` (capability `obj_export`).
+     */
+    public async meshToObj(
+        mesh: Pick<MeshBuffers, 'vertices' | 'indices'> & Partial<Pick<MeshBuffers, 'normals' | 'colors' | 'uvs'>>,
+        options?: ObjOptions
+    ): Promise<string> {
+        return this.call<string>('meshToObj', { mesh, options });
+    }
+
+    /**
+     * Serialize a result's mesh as Stanford PLY bytes via the WASM writer, for
+     * MeshLab / CloudCompare. Binary little-endian unless `options.format` is
+     * `'ascii'`; normals and colours are opt-in (capability `ply_export`).
+     */
+    public async meshToPly(
+        mesh: Pick<MeshBuffers, 'vertices' | 'indices'> & Partial<Pick<MeshBuffers, 'normals' | 'colors'>>,
+        options?: PlyOptions
+    ): Promise<Uint8Array> {
+        return this.call<Uint8Array>('meshToPly', { mesh, options });
+    }
+
+    /**
+     * Wrap a result's mesh in a `.babylon` JSON scene via the WASM writer, for
+     * the Babylon sandbox or `SceneLoader`. The scene is left-handed with the
+     * mesh in `babylon-lh`; normals are always written (capability `babylon_export`).
+     */
+    public async meshToBabylon(
+        mesh: Pick<MeshBuffers, 'vertices' | 'indices'> & Partial<Pick<MeshBuffers, 'normals' | 'colors' | 'uvs'>>,
+        options?: BabylonOptions
+    ): Promise<string> {
+        return this.call<string>('meshToBabylon', { mesh, options });
+    }
+
+    /**
+     * Identify a saved project, bake manifest or navmesh query without loading
+     * it: kind, schema versions and chunks (capability `artifact_container`).
+     */
+    public async inspectArtifact(bytes: Uint8Array): Promise<ArtifactInfo> {
+        const buffer = bytes.slice().buffer;
+        return this.call<ArtifactInfo>('inspectArtifact', { bytes: buffer }, [buffer]);
+    }
+
+    /**
+     * Rewrite a saved artifact in the current container and schema version,
+     * including pre-container files (capability `artifact_container`).
+     */
+    public async migrateArtifact(bytes: Uint8Array): Promise<Uint8Array> {
+        const buffer = bytes.slice().buffer;
+        return this.call<Uint8Array>('migrateArtifact', { bytes: buffer }, [buffer]);
+    }
+
     /**
      * Slice a splat into a streamed-SOG bundle (`lod-meta.json` + per-chunk SOG
      * datasets with lossless WebP planes). Returns the universal path-keyed file
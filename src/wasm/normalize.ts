@@ -15,7 +15,7 @@ export function isSupportedSplatFile(name: string): boolean {
  * with PLY. This is the single ingest seam:
  *
  * - `.ply`   -> returned unchanged.
- * - `.spz`   -> gunzipped in-browser (`DecompressionStream`) then converted via WASM.
+ * - `.spz`   -> converted via WASM (gzip-wrapped files are inflated there).
  * - `.splat` -> converted via WASM (antimatter15 fixed 32-byte records).
  *
  * `.spz` / `.splat` require the WASM core to be initialized.
@@ -24,13 +24,8 @@ export async function normalizeSplatToPly(file: File): Promise<Uint8Array> {
   const name = file.name.toLowerCase();
 
   if (name.endsWith('.spz')) {
-    if (!('DecompressionStream' in globalThis)) {
-      throw new Error('Browser does not support DecompressionStream; cannot read .spz files.');
-    }
-    const ds = new DecompressionStream('gzip');
-    const stream = file.stream().pipeThrough(ds);
-    const decompressed = new Uint8Array(await new Response(stream).arrayBuffer());
-    return splatwalk.spzToPly(decompressed);
+    const bytes = new Uint8Array(await file.arrayBuffer());
+    return splatwalk.spzToPly(bytes);
   }
 
   if (name.endsWith('.splat')) {
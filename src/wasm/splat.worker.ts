@@ -1,16 +1,29 @@
 /// <reference lib="webworker" />
 import init, {
     build_collision_voxel_boundary,
+    build_floor_plan,
     init_splatwalk,
     build_room_floor_mesh,
+    build_safe_area,
+    build_splat_ellipsoids,
     build_walkable_ground_field,
     convert_splat_to_mesh,
     convert_splat_to_navmesh_basis,
     convert_to_sog,
+    export_babylon,
+    export_obj,
+    export_ply,
+    extract_floor_polygons,
+    find_chokepoints,
     get_splat_bounds,
+    inspect_artifact,
     mesh_to_glb,
+    mesh_to_glb_with_options,
+    migrate_artifact,
     slice_splat,
     splat_to_ply,
+    splatwalk_start_threads,
+    splatwalk_thread_support,
     spz_to_ply,
     suggest_region,
 } from '@splatwalk/core';
@@ -55,6 +68,28 @@ const ctx: Worker = self as unknown as Worker;
 // The Rust side emits diagnostics via `console::log`. Forward all worker console
 // output to the main thread so existing log capture (homepage System Logs) keeps
 // working now that the WASM runs off the main thread.
+/**
+ * Buffers of `typed_buffers` mesh output (`mesh` and `void_mesh.mesh`), so the
+ * typed arrays move to the main thread instead of being structured-cloned.
+ * Plain-array meshes yield nothing.
+ */
+const meshTransfers = (result: unknown): Transferable[] => {
+    const root = result as {
+        mesh?: Record<string, unknown>;
+        void_mesh?: { mesh?: Record<string, unknown> };
+        planes?: { mesh?: Record<string, unknown> }[];
+    } | null;
+    const transfer: Transferable[] = [];
+    const planeMeshes = (root?.planes ?? []).map((plane) => plane.mesh);
+    for (const mesh of [root?.mesh, root?.void_mesh?.mesh, ...planeMeshes]) {
+        for (const key of ['vertices', 'indices', 'normals', 'vertex_alpha', 'vertex_confidence', 'colors']) {
+            const value = mesh?.[key];
+            if (ArrayBuffer.isView(value)) transfer.push(value.buffer as ArrayBuffer);
+        }
+    }
+    return transfer;
+};
+
 const forward = (level: 'log' | 'warn' | 'error', args: unknown[]): void => {
     const message = args.map((a) => (typeof a === 'string' ? a : String(a))).join(' ');
     // Progress ticks are emitted by Rust as "@progress <stage> [<fraction>]" and
@@ -84,9 +119,12 @@ ctx.onmessage = async (e: MessageEvent): Promise<void> => {
             if (!ready) {
                 await init();
                 init_splatwalk();
+                // Spawns the bake pool on threaded builds of isolated pages;
+                // settles at once everywhere else.
+                await splatwalk_start_threads();
                 ready = true;
             }
-            ctx.postMessage({ kind: 'result', id, ok: true, result: null });
+            ctx.postMessage({ kind: 'result', id, ok: true, result: splatwalk_thread_support() });
             return;
         }
 
@@ -102,11 +140,43 @@ ctx.onmessage = async (e: MessageEvent): Promise<void> => {
         if (type === 'meshToGlb') {
             const positions = new Float32Array(payload.positions as ArrayBuffer);
             const indices = new Uint32Array(payload.indices as ArrayBuffer);
-            const glb = mesh_to_glb(positions, indices);
+            const glb = payload.options
+                ? mesh_to_glb_with_options(positions, indices, payload.options)
+                : mesh_to_glb(positions, indices);
             ctx.postMessage({ kind: 'result', id, ok: true, result: glb }, [glb.buffer as ArrayBuffer]);
             return;
         }
 
+        if (type === 'meshToObj') {
+            const obj = export_obj(payload.mesh, payload.options);
+            ctx.postMessage({ kind: 'result', id, ok: true, result: obj });
+            return;
+        }
+
+        if (type === 'meshToBabylon') {
+            const scene = export_babylon(payload.mesh, payload.options);
+            ctx.postMessage({ kind: 'result', id, ok: true, result: scene });
+            return;
+        }
+
+        if (type === 'inspectArtifact') {
+            const info = inspect_artifact(new Uint8Array(payload.bytes as ArrayBuffer));
+            ctx.postMessage({ kind: 'result', id, ok: true, result: info });
+            return;
+        }
+
+        if (type === 'migrateArtifact') {
+            const migrated = migrate_artifact(new Uint8Array(payload.bytes as ArrayBuffer));
+            ctx.postMessage({ kind: 'result', id, ok: true, result: migrated }, [migrated.buffer as ArrayBuffer]);
+            return;
+        }
+
+        if (type === 'meshToPly') {
+            const ply = export_ply(payload.mesh, payload.options);
+            ctx.postMessage({ kind: 'result', id, ok: true, result: ply }, [ply.buffer as ArrayBuffer]);
+            return;
+        }
+
         if (!currentData) throw new Error('No splat loaded in worker');
 
         const settings = payload.settings;
@@ -170,12 +240,30 @@ ctx.onmessage = async (e: MessageEvent): Promise<void> => {
             case 'buildRoomFloorMesh':
                 result = build_room_floor_mesh(currentData, settings);
                 break;
+            case 'buildFloorPlan':
+                result = build_floor_plan(currentData, settings);
+                break;
+            case 'buildSafeArea':
+                result = build_safe_area(currentData, settings);
+                break;
+            case 'findChokepoints':
+                result = find_chokepoints(currentData, settings);
+                break;
+            case 'extractFloorPolygons':
+                result = extract_floor_polygons(currentData, settings);
+                break;
+            case 'buildSplatEllipsoids':
+                result = build_splat_ellipsoids(currentData, settings);
+                break;
             default:
                 throw new Error(`Unknown splat worker op: ${type}`);
         }
-        ctx.postMessage({ kind: 'result', id, ok: true, result });
+        ctx.postMessage({ kind: 'result', id, ok: true, result }, meshTransfers(result));
     } catch (err) {
-        const error = err instanceof Error ? err.message : String(err);
-        ctx.postMessage({ kind: 'result', id, ok: false, error });
+        // WASM errors are `Error`s carrying `code` / `detail`; the room-floor
+        // failure is a plain object with `code` / `message`. Forward both.
+        const info = (err ?? {}) as { code?: string; detail?: unknown; message?: string };
+        const error = err instanceof Error ? err.message : (info.message ?? String(err));
+        ctx.postMessage({ kind: 'result', id, ok: false, error, code: info.code, detail: info.detail });
     }
 };
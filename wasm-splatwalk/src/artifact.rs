@@ -0,0 +1,431 @@
+//! The versioned container every serialized artifact is written in.
+//!
+//! Project files, bake manifests and saved `NavMeshQuery`s each used to pick
+//! their own framing (a magic plus a version, a bare zip, nothing), so every
+//! format change needed its own compatibility story. They now share one
+//! chunked layout, little-endian throughout:
+//!
+//! - a 20-byte header: the magic `SWAF`, the container version (`u16`), a
+//!   reserved `u16`, the artifact kind (four ASCII bytes such as `PROJ`), the
+//!   kind's schema version (`u32`) and the chunk count (`u32`);
+//! - per chunk, a 16-byte header (four-byte tag, `u32` flags, `u32` payload
+//!   length, CRC-32 of the payload) followed by the payload. The CRC comes
+//!   before the payload so a payload may run to the end of the file, which
+//!   keeps a bake manifest's zip openable by unzip tools.
+//!
+//! The rules that keep old artifacts loading and new ones failing loudly:
+//!
+//! 1. The container version changes only if this framing does; readers
+//!    refuse a newer one.
+//! 2. A kind's schema version goes up when an existing chunk's meaning
+//!    changes. Readers refuse a newer version and bring an older one up to
+//!    date by running the kind's [`Migration`]s in order, one per version.
+//! 3. New data goes in new chunks, without a version bump. A reader skips a
+//!    chunk it does not know unless the chunk's [`REQUIRED`] flag is set, in
+//!    which case the artifact needs a newer build and is refused by name.
+//!
+//! Formats written before the container (the `SWPJ` project blob and the
+//! bare manifest zip) are recognized by [`inspect`] and read by their
+//! modules as schema version 1.
+
+use flate2::Crc;
+use serde::Serialize;
+
+pub const MAGIC: &[u8; 4] = b"SWAF";
+
+/// Current container (framing) version.
+pub const CONTAINER_VERSION: u16 = 1;
+
+const HEADER_LEN: usize = 20;
+const CHUNK_HEADER_LEN: usize = 16;
+
+/// Where the first chunk's payload starts, for payloads that record their
+/// own absolute offsets (the manifest zip).
+pub const FIRST_PAYLOAD: usize = HEADER_LEN + CHUNK_HEADER_LEN;
+
+/// Chunk flag: readers that do not know the tag must refuse the artifact.
+pub const REQUIRED: u32 = 1;
+
+/// Brings an artifact from one schema version to the next.
+pub type Migration = fn(&mut Artifact) -> Result<(), String>;
+
+/// One artifact kind and its current schema.
+pub struct Kind {
+    pub tag: [u8; 4],
+    /// What `inspect_artifact` reports, e.g. `project`.
+    pub name: &'static str,
+    pub version: u32,
+    /// Chunk tags this build reads.
+    pub chunks: &'static [[u8; 4]],
+    /// `migrations[i]` upgrades version `i + 1` to `i + 2`.
+    pub migrations: &'static [Migration],
+}
+
+/// `SplatSession.save_project` blobs: one gzip-compressed JSON chunk.
+pub const PROJECT: Kind = Kind {
+    tag: *b"PROJ",
+    name: "project",
+    version: crate::project::PROJECT_VERSION,
+    chunks: &[*b"JSON"],
+    migrations: &[],
+};
+
+/// `emit_manifest` archives: one zip chunk.
+pub const MANIFEST: Kind = Kind {
+    tag: *b"BAKE",
+    name: "bake_manifest",
+    version: crate::manifest::MANIFEST_VERSION,
+    chunks: &[*b"ZIP "],
+    migrations: &[],
+};
+
+/// `NavMeshQuery.save` blobs: the mesh and the settings it was indexed with.
+pub const NAVMESH_QUERY: Kind = Kind {
+    tag: *b"NAVQ",
+    name: "navmesh_query",
+    version: crate::nav_query::NAVMESH_QUERY_VERSION,
+    chunks: &[*b"MESH", *b"SETT"],
+    migrations: &[],
+};
+
+pub const KINDS: &[&Kind] = &[&PROJECT, &MANIFEST, &NAVMESH_QUERY];
+
+#[derive(Clone, Debug)]
+pub struct Chunk {
+    pub tag: [u8; 4],
+    pub required: bool,
+    pub data: Vec<u8>,
+}
+
+/// A decoded artifact: its kind, schema version and chunks in file order.
+#[derive(Clone, Debug)]
+pub struct Artifact {
+    pub kind: [u8; 4],
+    pub version: u32,
+    pub chunks: Vec<Chunk>,
+}
+
+impl Artifact {
+    /// An empty artifact at `kind`'s current version.
+    pub fn new(kind: &Kind) -> Self {
+        Artifact {
+            kind: kind.tag,
+            version: kind.version,
+            chunks: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, tag: [u8; 4], required: bool, data: Vec<u8>) {
+        self.chunks.push(Chunk {
+            tag,
+            required,
+            data,
+        });
+    }
+
+    /// The first chunk tagged `tag`.
+    pub fn chunk(&self, tag: &[u8; 4]) -> Option<&[u8]> {
+        self.chunks
+            .iter()
+            .find(|c| &c.tag == tag)
+            .map(|c| c.data.as_slice())
+    }
+
+    /// [`Artifact::chunk`], or an error naming the missing chunk.
+    pub fn require(&self, tag: &[u8; 4]) -> Result<&[u8], String> {
+        self.chunk(tag).ok_or_else(|| {
+            format!(
+                "{} artifact has no {} chunk",
+                tag_text(&self.kind),
+                tag_text(tag)
+            )
+        })
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>, String> {
+        let length = |n: usize, what: &str| {
+            u32::try_from(n).map_err(|_| format!("{} exceeds the 4 GiB artifact limit", what))
+        };
+        let size: usize = self
+            .chunks
+            .iter()
+            .map(|c| CHUNK_HEADER_LEN + c.data.len())
+            .sum();
+        let mut out = Vec::with_capacity(HEADER_LEN + size);
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&CONTAINER_VERSION.to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes());
+        out.extend_from_slice(&self.kind);
+        out.extend_from_slice(&self.version.to_le_bytes());
+        out.extend_from_slice(&length(self.chunks.len(), "chunk count")?.to_le_bytes());
+        for chunk in &self.chunks {
+            let mut crc = Crc::new();
+            crc.update(&chunk.data);
+            out.extend_from_slice(&chunk.tag);
+            out.extend_from_slice(&(if chunk.required { REQUIRED } else { 0 }).to_le_bytes());
+            out.extend_from_slice(&length(chunk.data.len(), &tag_text(&chunk.tag))?.to_le_bytes());
+            out.extend_from_slice(&crc.sum().to_le_bytes());
+            out.extend_from_slice(&chunk.data);
+        }
+        Ok(out)
+    }
+}
+
+/// Whether `bytes` start with the container magic.
+pub fn is_artifact(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Parse `bytes` as a `kind` artifact and migrate it to the current schema
+/// version. Chunk CRCs are checked; unknown optional chunks are kept.
+pub fn decode(bytes: &[u8], kind: &Kind) -> Result<Artifact, String> {
+    let mut artifact = parse(bytes)?;
+    if artifact.kind != kind.tag {
+        return Err(format!(
+            "Expected a {} artifact ({}), got {}",
+            kind.name,
+            tag_text(&kind.tag),
+            tag_text(&artifact.kind)
+        ));
+    }
+    if artifact.version == 0 || artifact.version > kind.version {
+        return Err(format!(
+            "Unsupported {} version {} (this build reads up to {})",
+            kind.name, artifact.version, kind.version
+        ));
+    }
+    // Migrate first, so older chunks are checked against the current schema.
+    while artifact.version < kind.version {
+        let migration = kind.migrations[artifact.version as usize - 1];
+        migration(&mut artifact)?;
+        artifact.version += 1;
+    }
+    if let Some(chunk) = artifact
+        .chunks
+        .iter()
+        .find(|c| c.required && !kind.chunks.contains(&c.tag))
+    {
+        return Err(format!(
+            "{} chunk {} is required but unknown to this build; load it with a newer one",
+            kind.name,
+            tag_text(&chunk.tag)
+        ));
+    }
+    Ok(artifact)
+}
+
+/// The framing alone: header and CRC-checked chunks, no kind checks.
+fn parse(bytes: &[u8]) -> Result<Artifact, String> {
+    if bytes.len() < HEADER_LEN || !is_artifact(bytes) {
+        return Err("Not a splatwalk artifact (missing SWAF header)".to_string());
+    }
+    let u32_at =
+        |at: usize| u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]);
+    let container = u16::from_le_bytes([bytes[4], bytes[5]]);
+    if container == 0 || container > CONTAINER_VERSION {
+        return Err(format!(
+            "Unsupported artifact container version {} (this build reads up to {})",
+            container, CONTAINER_VERSION
+        ));
+    }
+    let kind = [bytes[8], bytes[9], bytes[10], bytes[11]];
+    let version = u32_at(12);
+    let count = u32_at(16) as usize;
+    let mut chunks = Vec::with_capacity(count.min(64));
+    let mut at = HEADER_LEN;
+    for _ in 0..count {
+        if bytes.len() < at + CHUNK_HEADER_LEN {
+            return Err("Truncated artifact chunk header".to_string());
+        }
+        let tag = [bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]];
+        let flags = u32_at(at + 4);
+        let length = u32_at(at + 8) as usize;
+        let crc = u32_at(at + 12);
+        let data = bytes
+            .get(at + CHUNK_HEADER_LEN..at + CHUNK_HEADER_LEN + length)
+            .ok_or_else(|| format!("Truncated artifact chunk {}", tag_text(&tag)))?;
+        let mut check = Crc::new();
+        check.update(data);
+        if check.sum() != crc {
+            return Err(format!("Artifact chunk {} is corrupt", tag_text(&tag)));
+        }
+        chunks.push(Chunk {
+            tag,
+            required: flags & REQUIRED != 0,
+            data: data.to_vec(),
+        });
+        at += CHUNK_HEADER_LEN + length;
+    }
+    Ok(Artifact {
+        kind,
+        version,
+        chunks,
+    })
+}
+
+/// A chunk as `inspect_artifact` lists it.
+#[derive(Serialize)]
+pub struct ChunkInfo {
+    pub tag: String,
+    pub required: bool,
+    pub byte_length: usize,
+}
+
+/// What `inspect_artifact` returns.
+#[derive(Serialize)]
+pub struct ArtifactInfo {
+    /// The kind's name, e.g. `project`, or its tag when this build does not
+    /// know it.
+    pub kind: String,
+    /// 0 for formats written before the container.
+    pub container_version: u16,
+    pub version: u32,
+    /// The version this build writes; `null` for an unknown kind.
+    pub current_version: Option<u32>,
+    /// Written before the container; `migrate_artifact` rewraps it.
+    pub legacy: bool,
+    /// Whether this build can load it, possibly after migrating.
+    pub readable: bool,
+    pub chunks: Vec<ChunkInfo>,
+}
+
+/// Identify `bytes` without decoding the payloads: container artifacts of
+/// any kind, and the legacy project blob and manifest zip.
+pub fn inspect(bytes: &[u8]) -> Result<ArtifactInfo, String> {
+    if bytes.starts_with(b"SWPJ") && bytes.len() >= 8 {
+        let version = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+        return Ok(legacy(&PROJECT, version, b"JSON", bytes.len() - 8));
+    }
+    if bytes.starts_with(b"PK\x03\x04") {
+        return Ok(legacy(&MANIFEST, 1, b"ZIP ", bytes.len()));
+    }
+    let artifact = parse(bytes)?;
+    let container_version = u16::from_le_bytes([bytes[4], bytes[5]]);
+    let known = KINDS.iter().find(|k| k.tag == artifact.kind);
+    let readable = known.is_some_and(|kind| {
+        artifact.version >= 1
+            && (artifact.version < kind.version
+                || artifact.version == kind.version
+                    && artifact
+                        .chunks
+                        .iter()
+                        .all(|c| !c.required || kind.chunks.contains(&c.tag)))
+    });
+    Ok(ArtifactInfo {
+        kind: known.map_or_else(|| tag_text(&artifact.kind), |k| k.name.to_string()),
+        container_version,
+        version: artifact.version,
+        current_version: known.map(|k| k.version),
+        legacy: false,
+        readable,
+        chunks: artifact
+            .chunks
+            .iter()
+            .map(|c| ChunkInfo {
+                tag: tag_text(&c.tag),
+                required: c.required,
+                byte_length: c.data.len(),
+            })
+            .collect(),
+    })
+}
+
+fn legacy(kind: &Kind, version: u32, tag: &[u8; 4], byte_length: usize) -> ArtifactInfo {
+    ArtifactInfo {
+        kind: kind.name.to_string(),
+        container_version: 0,
+        version,
+        current_version: Some(kind.version),
+        legacy: true,
+        readable: version >= 1 && version <= kind.version,
+        chunks: vec![ChunkInfo {
+            tag: tag_text(tag),
+            required: true,
+            byte_length,
+        }],
+    }
+}
+
+fn tag_text(tag: &[u8; 4]) -> String {
+    String::from_utf8_lossy(tag).trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Version 2 of a test kind renamed chunk `OLD ` to `NEW `.
+    const RENAMED: Kind = Kind {
+        tag: *b"TEST",
+        name: "test",
+        version: 2,
+        chunks: &[*b"NEW "],
+        migrations: &[rename_old],
+    };
+
+    fn rename_old(artifact: &mut Artifact) -> Result<(), String> {
+        for chunk in &mut artifact.chunks {
+            if &chunk.tag == b"OLD " {
+                chunk.tag = *b"NEW ";
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn old_versions_migrate_and_unknown_chunks_follow_their_flag() {
+        let mut old = Artifact {
+            kind: *b"TEST",
+            version: 1,
+            chunks: Vec::new(),
+        };
+        old.push(*b"OLD ", true, b"payload".to_vec());
+        old.push(*b"XTRA", false, vec![1, 2, 3]);
+        let bytes = old.encode().unwrap();
+        assert_eq!(&bytes[..4], MAGIC);
+
+        let loaded = decode(&bytes, &RENAMED).unwrap();
+        assert_eq!(loaded.version, 2);
+        assert_eq!(loaded.require(b"NEW ").unwrap(), b"payload");
+        // Optional chunks this build does not know ride along untouched.
+        assert_eq!(loaded.chunk(b"XTRA"), Some(&[1u8, 2, 3][..]));
+
+        let mut newer = loaded.clone();
+        newer.version = 3;
+        assert!(decode(&newer.encode().unwrap(), &RENAMED)
+            .unwrap_err()
+            .contains("reads up to 2"));
+        let mut required = loaded;
+        required.push(*b"XTRA", true, Vec::new());
+        required.chunks.remove(1);
+        assert!(decode(&required.encode().unwrap(), &RENAMED)
+            .unwrap_err()
+            .contains("chunk XTRA"));
+        assert!(decode(&bytes, &PROJECT).unwrap_err().contains("got TEST"));
+    }
+
+    #[test]
+    fn corruption_is_caught_and_legacy_formats_are_recognized() {
+        let mut artifact = Artifact::new(&NAVMESH_QUERY);
+        artifact.push(*b"MESH", true, vec![7; 32]);
+        let mut bytes = artifact.encode().unwrap();
+        let info = inspect(&bytes).unwrap();
+        assert_eq!(info.kind, "navmesh_query");
+        assert!(info.readable && !info.legacy);
+        assert_eq!(info.chunks[0].byte_length, 32);
+        bytes[FIRST_PAYLOAD + 3] ^= 1;
+        assert!(decode(&bytes, &NAVMESH_QUERY)
+            .unwrap_err()
+            .contains("corrupt"));
+        assert!(decode(&bytes[..30], &NAVMESH_QUERY).is_err());
+
+        let legacy_project = inspect(b"SWPJ\x01\0\0\0gzip").unwrap();
+        assert!(legacy_project.legacy && legacy_project.readable);
+        assert_eq!(
+            (legacy_project.kind.as_str(), legacy_project.version),
+            ("project", 1)
+        );
+        assert_eq!(inspect(b"PK\x03\x04rest").unwrap().kind, "bake_manifest");
+        assert!(inspect(b"nothing").is_err());
+    }
+}
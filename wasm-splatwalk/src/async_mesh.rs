@@ -0,0 +1,162 @@
+//! `convert_splat_to_mesh_async`: a time-sliced bake on the calling thread.
+//!
+//! `convert_splat_to_mesh` holds the event loop for the whole bake, which on
+//! the main thread freezes the page for seconds. The async entry point runs
+//! the same pipeline as a chain of [`Step`]s, each on its own `setTimeout(0)`
+//! task, so input, rendering and `@progress` listeners get a turn between
+//! them. Meshing is cut into [`SLICE_MS`] slices with
+//! [`MeshJob::mesh_slice`]: RANSAC yields between hypotheses, voxel
+//! navmeshing between batches of splatted points, and Poisson between its
+//! solve and its surface extraction. Parsing, preprocessing, the Poisson solve
+//! itself, the other meshers and post-processing each still run as one task,
+//! so a worker remains the way to take the bake off the main thread entirely.
+//!
+//! The bake stays bound to the context handle it was started under and keeps
+//! its own journal between steps, so other calls on the instance while it is
+//! waiting neither see its half-written journal nor reset it. Once it settles
+//! its journal becomes the context's latest.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use js_sys::{Function, Promise};
+use wasm_bindgen::prelude::*;
+
+use crate::context::{self, ContextState};
+use crate::journal::{self, Journal};
+use crate::mesh::MeshJob;
+use crate::splat::PointNormal;
+use crate::stage;
+use crate::MeshSettings;
+
+/// Milliseconds of meshing per event-loop task, about one frame at 60 Hz.
+pub const SLICE_MS: f64 = 12.0;
+
+#[wasm_bindgen]
+extern "C" {
+    /// The global `setTimeout`, present on `Window` and `WorkerGlobalScope`.
+    #[wasm_bindgen(js_name = setTimeout)]
+    fn set_timeout(handler: &JsValue, timeout: i32) -> JsValue;
+}
+
+/// What the next event-loop task of a bake does.
+enum Step {
+    Parse(Vec<u8>),
+    Prepare(Vec<PointNormal>),
+    Mesh(MeshJob),
+    Finish(MeshJob),
+}
+
+struct Bake {
+    context: Rc<ContextState>,
+    journal: Journal,
+    settings: MeshSettings,
+    step: Option<Step>,
+    resolve: Function,
+    reject: Function,
+}
+
+enum Outcome {
+    Next(Box<Step>),
+    Done(JsValue),
+}
+
+/// Start a bake of `data` that settles the returned promise with the
+/// `convert_splat_to_mesh` result, or rejects with its structured error.
+pub fn convert(data: Vec<u8>, settings: JsValue) -> Promise {
+    let mut data = Some(data);
+    let mut settings = Some(settings);
+    Promise::new(&mut |resolve, reject| {
+        journal::begin("convert_splat_to_mesh_async");
+        match bake_settings(settings.take().unwrap_or_default()) {
+            Ok(settings) => schedule(Rc::new(RefCell::new(Bake {
+                context: context::current(),
+                journal: journal::snapshot(),
+                settings,
+                step: data.take().map(Step::Parse),
+                resolve,
+                reject,
+            }))),
+            Err(err) => {
+                let _ = reject.call1(&JsValue::NULL, &err);
+            }
+        }
+    })
+}
+
+/// `convert_splat_to_mesh`'s settings checks, ahead of the first step.
+fn bake_settings(settings: JsValue) -> Result<MeshSettings, JsValue> {
+    let settings = crate::parse_settings(settings)?;
+    if settings.mode == 2 {
+        crate::validate_collision_mesh_mode(&settings)?;
+    }
+    Ok(settings)
+}
+
+/// Run the bake's next step on a fresh event-loop task.
+fn schedule(bake: Rc<RefCell<Bake>>) {
+    let task = Closure::once_into_js(move || advance(bake));
+    set_timeout(&task, 0);
+}
+
+fn advance(bake: Rc<RefCell<Bake>>) {
+    let mut state = bake.borrow_mut();
+    let Some(step) = state.step.take() else {
+        return;
+    };
+    // Swap the bake's journal in for the step and back out after it.
+    let journal = std::mem::take(&mut state.journal);
+    let parked = state.context.journal.replace(journal);
+    let outcome = context::enter(&state.context, || run_step(&state.settings, step));
+    if let Ok(Outcome::Next(_)) = outcome {
+        state.journal = state.context.journal.replace(parked);
+    }
+    match outcome {
+        Ok(Outcome::Next(step)) => {
+            state.step = Some(*step);
+            drop(state);
+            schedule(bake);
+        }
+        Ok(Outcome::Done(value)) => {
+            let _ = state.resolve.call1(&JsValue::NULL, &value);
+        }
+        Err(err) => {
+            let _ = state.reject.call1(&JsValue::NULL, &err);
+        }
+    }
+}
+
+fn run_step(settings: &MeshSettings, step: Step) -> Result<Outcome, JsValue> {
+    Ok(match step {
+        Step::Parse(data) => {
+            let splats = crate::parse_splats(&data, settings)?;
+            crate::log(&format!(
+                "Received {} splats (Mode: {})",
+                splats.len(),
+                settings.mode
+            ));
+            Outcome::Next(Box::new(Step::Prepare(splats)))
+        }
+        Step::Prepare(splats) => {
+            let job = stage::run("reconstruct", || MeshJob::prepare(&splats, settings))?;
+            Outcome::Next(Box::new(Step::Mesh(job)))
+        }
+        Step::Mesh(mut job) => {
+            let deadline = js_sys::Date::now() + SLICE_MS;
+            let done = stage::run("reconstruct", || {
+                job.mesh_slice(settings, &mut || js_sys::Date::now() >= deadline)
+            })?;
+            let next = if done {
+                Step::Finish(job)
+            } else {
+                Step::Mesh(job)
+            };
+            Outcome::Next(Box::new(next))
+        }
+        Step::Finish(job) => {
+            let result = stage::run("reconstruct", || job.finish(settings))?;
+            let value = crate::reconstruction_to_value(settings, result)?;
+            Outcome::Done(value)
+        }
+    })
+}
@@ -0,0 +1,267 @@
+//! Babylon.js `.babylon` scene writer.
+//!
+//! [`mesh_to_babylon`] wraps a result's mesh in the JSON scene format
+//! Babylon's own loader reads, so a bake can be dragged into the Babylon
+//! sandbox or loaded with `SceneLoader` without any glue code. The scene holds
+//! one mesh with one standard material and no cameras or lights; the sandbox
+//! and `createDefaultCameraOrLight` add those.
+//!
+//! The scene is left-handed (`useRightHandedSystem: false`) and the vertices
+//! are written in the `babylon-lh` `coordinate_system`, so the mesh lines up
+//! with the splat as Babylon's splat loader shows it. A mesh returned in
+//! another preset's space is mapped back first; `engine_output` meshes carry
+//! no record of their axes and are refused.
+//!
+//! Normals are always written (the mesh's own, else angle-weighted ones)
+//! because Babylon does not compute them for loaded meshes. Colours (as RGBA),
+//! UVs, collisions and host `metadata` are opt-in.
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::normals;
+use crate::obj::{self, ObjMesh};
+use crate::output_space::{self, COORDINATE_SYSTEMS};
+
+/// Optional `.babylon` content and the mesh's source space.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct BabylonOptions {
+    /// The result's `space`: `"splatwalk_oriented"` (default) or a
+    /// `coordinate_system` preset name.
+    #[serde(default)]
+    pub space: Option<String>,
+    /// Mesh name and id. Default `"navmesh"`.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Write the mesh's `colors` as vertex colours.
+    #[serde(default)]
+    pub colors: bool,
+    /// Write the mesh's `uvs`.
+    #[serde(default)]
+    pub uvs: bool,
+    /// Set `checkCollisions`, so Babylon's collision system treats the mesh
+    /// as a collider.
+    #[serde(default)]
+    pub check_collisions: bool,
+    /// Load the mesh hidden, e.g. as an invisible collider.
+    #[serde(default)]
+    pub hidden: bool,
+    /// Stored as the mesh's `metadata`, e.g. navmesh statistics.
+    #[serde(default)]
+    pub metadata: Option<Value>,
+}
+
+/// Serialize `mesh` as a `.babylon` scene with `options`' extras.
+///
+/// Errors on the same malformed input as `export_obj`, on an unknown or
+/// `engine_output` `space`, and when an extra is asked for but the mesh has
+/// no matching per-vertex buffer.
+pub fn mesh_to_babylon(mesh: &ObjMesh, options: &BabylonOptions) -> Result<String, String> {
+    const CALLER: &str = "export_babylon";
+    let vertex_count = obj::check_mesh(CALLER, mesh)?;
+    let space = options.space.as_deref().unwrap_or("splatwalk_oriented");
+    let transform = match space {
+        // `babylon-lh` is `splatwalk_oriented` unchanged, as is `gltf`.
+        "splatwalk_oriented" => None,
+        name if COORDINATE_SYSTEMS.contains(&name) => Some(output_space::preset(name)),
+        _ => {
+            return Err(format!(
+                "{}: cannot map space \"{}\" to babylon-lh (expected splatwalk_oriented, {})",
+                CALLER,
+                space,
+                COORDINATE_SYSTEMS.join(", ")
+            ))
+        }
+    };
+
+    let colors = if options.colors {
+        Some(obj::per_vertex(
+            CALLER,
+            "colors",
+            &mesh.colors,
+            vertex_count * 3,
+        )?)
+    } else {
+        None
+    };
+    let uvs = if options.uvs {
+        Some(obj::per_vertex(CALLER, "uvs", &mesh.uvs, vertex_count * 2)?)
+    } else {
+        None
+    };
+    let own_normals = if mesh.normals.as_ref().is_some_and(|n| !n.is_empty()) {
+        Some(obj::per_vertex(
+            CALLER,
+            "normals",
+            &mesh.normals,
+            vertex_count * 3,
+        )?)
+    } else {
+        None
+    };
+
+    let to_lh = |v: &[f32]| -> [f32; 3] {
+        let v = [v[0] as f64, v[1] as f64, v[2] as f64];
+        let o = transform.as_ref().map_or(v, |t| t.unapply(v));
+        [o[0] as f32, o[1] as f32, o[2] as f32]
+    };
+    let positions: Vec<f32> = mesh.vertices.chunks_exact(3).flat_map(to_lh).collect();
+    let mut indices = mesh.indices.clone();
+    if transform.as_ref().is_some_and(|t| t.flips_winding()) {
+        for face in indices.chunks_exact_mut(3) {
+            face.swap(1, 2);
+        }
+    }
+    let normals = match own_normals {
+        Some(normals) => normals.chunks_exact(3).flat_map(to_lh).collect(),
+        None => normals::angle_weighted(&positions, &indices),
+    };
+
+    let name = options.name.as_deref().unwrap_or("navmesh");
+    let material_id = format!("{}-material", name);
+    let mut babylon_mesh = json!({
+        "name": name,
+        "id": name,
+        "materialId": material_id,
+        "position": [0.0, 0.0, 0.0],
+        "rotation": [0.0, 0.0, 0.0],
+        "scaling": [1.0, 1.0, 1.0],
+        "isVisible": !options.hidden,
+        "isEnabled": true,
+        "pickable": true,
+        "checkCollisions": options.check_collisions,
+        "billboardMode": 0,
+        "receiveShadows": false,
+        "positions": positions,
+        "normals": normals,
+        "indices": indices,
+        "subMeshes": [{
+            "materialIndex": 0,
+            "verticesStart": 0,
+            "verticesCount": vertex_count,
+            "indexStart": 0,
+            "indexCount": indices.len(),
+        }],
+    });
+    if let Some(colors) = colors {
+        let rgba: Vec<f32> = colors
+            .chunks_exact(3)
+            .flat_map(|c| [c[0], c[1], c[2], 1.0])
+            .collect();
+        babylon_mesh["colors"] = json!(rgba);
+        babylon_mesh["hasVertexAlpha"] = json!(false);
+    }
+    if let Some(uvs) = uvs {
+        babylon_mesh["uvs"] = json!(uvs);
+    }
+    if let Some(metadata) = &options.metadata {
+        babylon_mesh["metadata"] = metadata.clone();
+    }
+
+    let scene = json!({
+        "producer": {
+            "name": "splatwalk",
+            "version": crate::core_semver(),
+            "exporter_version": crate::core_semver(),
+            "file": format!("{}.babylon", name),
+        },
+        "useRightHandedSystem": false,
+        "autoClear": true,
+        "clearColor": [0.2, 0.2, 0.3],
+        "ambientColor": [0.0, 0.0, 0.0],
+        "gravity": [0.0, -9.81, 0.0],
+        "cameras": [],
+        "lights": [],
+        "materials": [{
+            "name": material_id,
+            "id": material_id,
+            "diffuse": [0.8, 0.8, 0.8],
+            "specular": [0.1, 0.1, 0.1],
+            "emissive": [0.0, 0.0, 0.0],
+            "alpha": 1.0,
+            "backFaceCulling": true,
+            "wireframe": false,
+        }],
+        "multiMaterials": [],
+        "geometries": {},
+        "meshes": [babylon_mesh],
+    });
+    Ok(scene.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triangle() -> ObjMesh {
+        ObjMesh {
+            vertices: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, -1.0],
+            indices: vec![0, 1, 2],
+            normals: None,
+            colors: Some(vec![1.0, 0.0, 0.0, 0.0, 0.5, 0.0, 0.0, 0.0, 1.0]),
+            uvs: None,
+        }
+    }
+
+    fn parse(mesh: &ObjMesh, options: &BabylonOptions) -> Value {
+        serde_json::from_str(&mesh_to_babylon(mesh, options).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn oriented_meshes_pass_through_into_a_left_handed_scene() {
+        let options = BabylonOptions {
+            colors: true,
+            check_collisions: true,
+            metadata: Some(json!({ "walkable_area": 0.5 })),
+            ..BabylonOptions::default()
+        };
+        let scene = parse(&triangle(), &options);
+        assert_eq!(scene["useRightHandedSystem"], json!(false));
+        let mesh = &scene["meshes"][0];
+        assert_eq!(mesh["materialId"], scene["materials"][0]["id"]);
+        assert_eq!(
+            mesh["positions"],
+            json!([0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, -1.0])
+        );
+        assert_eq!(mesh["indices"], json!([0, 1, 2]));
+        // Counter-clockwise from above: the computed normal faces +Y.
+        assert_eq!(mesh["normals"][1], json!(1.0));
+        assert_eq!(mesh["colors"].as_array().unwrap().len(), 12);
+        assert_eq!(mesh["colors"][7], json!(1.0));
+        assert_eq!(mesh["checkCollisions"], json!(true));
+        assert_eq!(mesh["metadata"]["walkable_area"], json!(0.5));
+        assert_eq!(mesh["subMeshes"][0]["indexCount"], json!(3));
+        assert!(mesh.get("uvs").is_none());
+    }
+
+    #[test]
+    fn preset_spaces_are_mapped_back_and_unknown_ones_refused() {
+        // The three.js preset mirrors Z and flips winding; both are undone.
+        let mut threejs = triangle();
+        threejs.vertices[8] = 1.0;
+        threejs.indices = vec![0, 2, 1];
+        let options = BabylonOptions {
+            space: Some("threejs-rh".to_string()),
+            ..BabylonOptions::default()
+        };
+        let mesh = &parse(&threejs, &options)["meshes"][0];
+        assert_eq!(mesh["positions"][8], json!(-1.0));
+        assert_eq!(mesh["indices"], json!([0, 1, 2]));
+        assert_eq!(mesh["normals"][1], json!(1.0));
+
+        let engine = BabylonOptions {
+            space: Some("engine_output".to_string()),
+            ..BabylonOptions::default()
+        };
+        assert!(mesh_to_babylon(&triangle(), &engine)
+            .unwrap_err()
+            .contains("cannot map space"));
+        let uvs = BabylonOptions {
+            uvs: true,
+            ..BabylonOptions::default()
+        };
+        assert!(mesh_to_babylon(&triangle(), &uvs)
+            .unwrap_err()
+            .contains("no uvs"));
+    }
+}
@@ -0,0 +1,552 @@
+//! Ball-pivoting surface reconstruction (`mode = 6`).
+//!
+//! Poisson fits one smooth implicit surface, so railings, chair legs and table
+//! edges thinner than its cells melt into blobs. The ball-pivoting algorithm
+//! (Bernardini et al. 1999) triangulates the splat centres directly instead: a
+//! ball of radius `r` that rests on three points without containing any other
+//! spans a triangle, and rolling it over each open edge until it touches a
+//! fourth point grows the surface from there. Every vertex of the mesh is a
+//! splat centre, so thin structures survive as long as they are sampled more
+//! densely than the ball. Several radii run smallest first: the small ball
+//! keeps the detail, and the larger ones pick up the open edges it left and
+//! close the gaps it fell through.
+//!
+//! Splat normals are flipped towards the `collision_scene_type` viewpoint as
+//! in mode 3 (see [`Viewpoint`]); the ball rolls on that side and triangles
+//! face it.
+
+use std::collections::{HashMap, HashSet};
+
+use nalgebra::Vector3;
+
+use crate::splat::PointNormal;
+use crate::tsdf::Viewpoint;
+use crate::MeshSettings;
+
+/// Most entries accepted in `bpa_radii`.
+pub const MAX_RADII: usize = 8;
+
+/// Default radii as multiples of the mean nearest-neighbour spacing.
+const DEFAULT_RADII: [f64; 2] = [1.5, 3.0];
+
+/// Nearest unused neighbours paired up when looking for a seed triangle.
+const SEED_NEIGHBOURS: usize = 16;
+
+/// Points sampled to estimate the spacing behind the default radii.
+const SPACING_SAMPLES: usize = 2000;
+
+/// `bpa_radii` must hold 1 to [`MAX_RADII`] positive finite lengths.
+pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+    let Some(radii) = &settings.bpa_radii else {
+        return Ok(());
+    };
+    if radii.is_empty() || radii.len() > MAX_RADII {
+        return Err(format!(
+            "Invalid bpa_radii: expected 1 to {} radii, got {}",
+            MAX_RADII,
+            radii.len()
+        ));
+    }
+    match radii.iter().find(|r| !(r.is_finite() && **r > 0.0)) {
+        Some(r) => Err(format!("Invalid bpa_radii entry: {}", r)),
+        None => Ok(()),
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BpaOptions {
+    /// Ball radii in metres, ascending.
+    pub radii: Vec<f64>,
+    pub viewpoint: Viewpoint,
+}
+
+impl BpaOptions {
+    /// `bpa_radii` in ascending order, or [`DEFAULT_RADII`] times the mean
+    /// nearest-neighbour spacing of `points`.
+    pub fn from_settings(settings: &MeshSettings, points: &[PointNormal]) -> Self {
+        let mut radii = settings.bpa_radii.clone().unwrap_or_else(|| {
+            let positions: Vec<Vector3<f64>> = points
+                .iter()
+                .map(|p| Vector3::new(p.point.x, p.point.y, p.point.z))
+                .collect();
+            let spacing = mean_spacing(&positions);
+            DEFAULT_RADII.iter().map(|k| k * spacing).collect()
+        });
+        radii.sort_by(f64::total_cmp);
+        radii.dedup();
+        Self {
+            radii,
+            viewpoint: Viewpoint::from_settings(settings),
+        }
+    }
+}
+
+pub struct BpaBuild {
+    pub vertices: Vec<f32>,
+    pub indices: Vec<u32>,
+    pub radii: Vec<f64>,
+    /// Open edges no ball could pivot over: holes and the mesh border.
+    pub boundary_edges: usize,
+    /// Points with a usable normal that ended up in no triangle.
+    pub unused_points: usize,
+}
+
+/// Points hashed into cubes for radius queries.
+struct Grid {
+    cell: f64,
+    cells: HashMap<(i64, i64, i64), Vec<u32>>,
+}
+
+impl Grid {
+    fn new(positions: &[Vector3<f64>], usable: &[bool], cell: f64) -> Self {
+        let mut grid = Self {
+            cell,
+            cells: HashMap::new(),
+        };
+        for (i, p) in positions.iter().enumerate() {
+            if usable[i] {
+                grid.cells.entry(grid.key(p)).or_default().push(i as u32);
+            }
+        }
+        grid
+    }
+
+    fn key(&self, p: &Vector3<f64>) -> (i64, i64, i64) {
+        (
+            (p.x / self.cell).floor() as i64,
+            (p.y / self.cell).floor() as i64,
+            (p.z / self.cell).floor() as i64,
+        )
+    }
+
+    /// Indices of the points within `radius` of `centre`, into `out`.
+    fn within(
+        &self,
+        positions: &[Vector3<f64>],
+        centre: &Vector3<f64>,
+        radius: f64,
+        out: &mut Vec<u32>,
+    ) {
+        out.clear();
+        let reach = Vector3::repeat(radius);
+        let (lo, hi) = (self.key(&(centre - reach)), self.key(&(centre + reach)));
+        let radius_sq = radius * radius;
+        for x in lo.0..=hi.0 {
+            for y in lo.1..=hi.1 {
+                for z in lo.2..=hi.2 {
+                    let Some(members) = self.cells.get(&(x, y, z)) else {
+                        continue;
+                    };
+                    out.extend(
+                        members.iter().copied().filter(|&i| {
+                            (positions[i as usize] - centre).norm_squared() <= radius_sq
+                        }),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Mean distance from a sample of `positions` to their nearest other point
+/// (0.05 m when the cloud has fewer than two distinct points).
+fn mean_spacing(positions: &[Vector3<f64>]) -> f64 {
+    let mut min = Vector3::repeat(f64::MAX);
+    let mut max = Vector3::repeat(f64::MIN);
+    for p in positions {
+        min = min.inf(p);
+        max = max.sup(p);
+    }
+    if positions.len() < 2 {
+        return 0.05;
+    }
+    // A cube per point over the bounds is coarser than the spacing on a
+    // surface, so the nearest neighbour is almost always one cell away.
+    let extent = (max - min).map(|e| e.max(0.01));
+    let cell = (extent.x * extent.y * extent.z / positions.len() as f64).cbrt();
+    let grid = Grid::new(positions, &vec![true; positions.len()], cell);
+    let step = (positions.len() / SPACING_SAMPLES).max(1);
+    let mut near = Vec::new();
+    let (mut sum, mut count) = (0.0, 0usize);
+    for (i, p) in positions.iter().enumerate().step_by(step) {
+        for reach in [cell, 4.0 * cell] {
+            grid.within(positions, p, reach, &mut near);
+            let nearest = near
+                .iter()
+                .filter(|&&j| j as usize != i)
+                .map(|&j| (positions[j as usize] - p).norm())
+                .filter(|d| *d > 0.0)
+                .fold(f64::INFINITY, f64::min);
+            if nearest.is_finite() {
+                sum += nearest;
+                count += 1;
+                break;
+            }
+        }
+    }
+    if count == 0 {
+        0.05
+    } else {
+        sum / count as f64
+    }
+}
+
+/// Centre of the radius-`r` ball through `a`, `b`, `c` on the side their
+/// counter-clockwise normal points to, or `None` when the triangle is
+/// degenerate or wider than the ball.
+fn ball_centre(
+    a: &Vector3<f64>,
+    b: &Vector3<f64>,
+    c: &Vector3<f64>,
+    r: f64,
+) -> Option<Vector3<f64>> {
+    let (ab, ac) = (b - a, c - a);
+    let n = ab.cross(&ac);
+    let n_sq = n.norm_squared();
+    if n_sq <= 1e-12 * ab.norm_squared() * ac.norm_squared() {
+        return None;
+    }
+    let offset =
+        (n.cross(&ab) * ac.norm_squared() + ac.cross(&n) * ab.norm_squared()) / (2.0 * n_sq);
+    let height_sq = r * r - offset.norm_squared();
+    if height_sq < 0.0 {
+        return None;
+    }
+    Some(a + offset + n / n_sq.sqrt() * height_sq.sqrt())
+}
+
+/// Unit normal of the counter-clockwise triangle `a`, `b`, `c`.
+fn face_normal(a: &Vector3<f64>, b: &Vector3<f64>, c: &Vector3<f64>) -> Vector3<f64> {
+    (b - a).cross(&(c - a)).normalize()
+}
+
+/// An edge whose other side has no triangle yet, keyed by its direction in
+/// the triangle that owns it.
+struct OpenEdge {
+    opposite: u32,
+    /// Still to be pivoted over; false once a ball failed to (boundary).
+    active: bool,
+}
+
+struct Front {
+    triangles: Vec<[u32; 3]>,
+    directed: HashSet<(u32, u32)>,
+    open: HashMap<(u32, u32), OpenEdge>,
+    /// Open edges at each point; a used point with none is inside the mesh.
+    open_at: Vec<u32>,
+    used: Vec<bool>,
+    queue: Vec<(u32, u32)>,
+}
+
+impl Front {
+    /// Whether a new triangle may use `k`: not yet meshed, or on the front.
+    fn reachable(&self, k: u32) -> bool {
+        !self.used[k as usize] || self.open_at[k as usize] > 0
+    }
+
+    /// Add `t` unless one of its edges would get a second triangle on the
+    /// same side or a third triangle overall. Edges it closes leave the
+    /// front; the others join it.
+    fn add(&mut self, t: [u32; 3]) -> bool {
+        let edges = [(t[0], t[1], t[2]), (t[1], t[2], t[0]), (t[2], t[0], t[1])];
+        for &(a, b, _) in &edges {
+            if self.directed.contains(&(a, b))
+                || (self.directed.contains(&(b, a)) && !self.open.contains_key(&(b, a)))
+            {
+                return false;
+            }
+        }
+        for (a, b, opposite) in edges {
+            self.directed.insert((a, b));
+            if self.open.remove(&(b, a)).is_some() {
+                self.open_at[a as usize] -= 1;
+                self.open_at[b as usize] -= 1;
+            } else {
+                self.open.insert(
+                    (a, b),
+                    OpenEdge {
+                        opposite,
+                        active: true,
+                    },
+                );
+                self.open_at[a as usize] += 1;
+                self.open_at[b as usize] += 1;
+                self.queue.push((a, b));
+            }
+        }
+        for v in t {
+            self.used[v as usize] = true;
+        }
+        self.triangles.push(t);
+        true
+    }
+}
+
+/// Triangulate `points` with each radius of `options` in turn. `progress`
+/// receives `("bpa_pivot", fraction)` as the seed search advances.
+pub fn reconstruct(
+    points: &[PointNormal],
+    options: &BpaOptions,
+    progress: &dyn Fn(&str, f64),
+) -> BpaBuild {
+    let positions: Vec<Vector3<f64>> = points
+        .iter()
+        .map(|p| Vector3::new(p.point.x, p.point.y, p.point.z))
+        .collect();
+    let mut min = [f64::MAX; 3];
+    let mut max = [f64::MIN; 3];
+    for p in &positions {
+        for axis in 0..3 {
+            min[axis] = min[axis].min(p[axis]);
+            max[axis] = max[axis].max(p[axis]);
+        }
+    }
+    let view = options.viewpoint.position(min, max);
+    let usable: Vec<bool> = points
+        .iter()
+        .zip(&positions)
+        .map(|(p, pos)| {
+            let norm = p.normal.norm();
+            pos.iter().all(|c| c.is_finite()) && norm.is_finite() && norm > 1e-9
+        })
+        .collect();
+    let normals: Vec<Vector3<f64>> = points
+        .iter()
+        .zip(&positions)
+        .zip(&usable)
+        .map(|((p, pos), &ok)| {
+            if ok {
+                options.viewpoint.orient(view, *pos, p.normal.normalize())
+            } else {
+                Vector3::zeros()
+            }
+        })
+        .collect();
+
+    let mut front = Front {
+        triangles: Vec::new(),
+        directed: HashSet::new(),
+        open: HashMap::new(),
+        open_at: vec![0; points.len()],
+        used: vec![false; points.len()],
+        queue: Vec::new(),
+    };
+    let agrees = |n: &Vector3<f64>, vertices: [u32; 3]| {
+        vertices.iter().all(|&v| n.dot(&normals[v as usize]) > 0.0)
+    };
+    let report_every = (points.len() / 50).max(1);
+    let mut near = Vec::new();
+    for (pass, &r) in options.radii.iter().enumerate() {
+        let grid = Grid::new(&positions, &usable, 2.0 * r);
+        // Edges an earlier, smaller ball left open get another go.
+        for (&edge, open) in front.open.iter_mut() {
+            open.active = true;
+            front.queue.push(edge);
+        }
+        expand(&mut front, &positions, &grid, r, &agrees, &mut near);
+
+        for seed in 0..points.len() {
+            if seed % report_every == 0 {
+                let fraction =
+                    (pass as f64 + seed as f64 / points.len() as f64) / options.radii.len() as f64;
+                progress("bpa_pivot", fraction);
+            }
+            if !usable[seed] || front.used[seed] {
+                continue;
+            }
+            let p = &positions[seed];
+            grid.within(&positions, p, 2.0 * r, &mut near);
+            near.retain(|&k| k as usize != seed && !front.used[k as usize]);
+            near.sort_by(|&i, &j| {
+                let di = (positions[i as usize] - p).norm_squared();
+                let dj = (positions[j as usize] - p).norm_squared();
+                di.total_cmp(&dj)
+            });
+            near.truncate(SEED_NEIGHBOURS);
+            let neighbours = near.clone();
+            let mut seeded = false;
+            'pairs: for (i, &q) in neighbours.iter().enumerate() {
+                for &s in &neighbours[i + 1..] {
+                    let mut t = [seed as u32, q, s];
+                    let mut n = face_normal(p, &positions[q as usize], &positions[s as usize]);
+                    if n.dot(&normals[seed]) < 0.0 {
+                        t.swap(1, 2);
+                        n = -n;
+                    }
+                    if !n.iter().all(|c| c.is_finite()) || !agrees(&n, t) {
+                        continue;
+                    }
+                    let [a, b, c] = t.map(|v| &positions[v as usize]);
+                    let Some(centre) = ball_centre(a, b, c, r) else {
+                        continue;
+                    };
+                    // The ball may touch other points but not swallow them.
+                    grid.within(&positions, &centre, r * (1.0 - 1e-9), &mut near);
+                    if near.iter().any(|v| !t.contains(v)) {
+                        continue;
+                    }
+                    seeded = front.add(t);
+                    break 'pairs;
+                }
+            }
+            if seeded {
+                expand(&mut front, &positions, &grid, r, &agrees, &mut near);
+            }
+        }
+    }
+    progress("bpa_pivot", 1.0);
+
+    let mut remap = vec![u32::MAX; points.len()];
+    let mut vertices = Vec::new();
+    for (i, p) in positions.iter().enumerate() {
+        if front.used[i] {
+            remap[i] = (vertices.len() / 3) as u32;
+            vertices.extend([p.x as f32, p.y as f32, p.z as f32]);
+        }
+    }
+    let indices = front
+        .triangles
+        .iter()
+        .flat_map(|t| t.map(|v| remap[v as usize]))
+        .collect();
+    BpaBuild {
+        vertices,
+        indices,
+        radii: options.radii.clone(),
+        boundary_edges: front.open.len(),
+        unused_points: (0..points.len())
+            .filter(|&i| usable[i] && !front.used[i])
+            .count(),
+    }
+}
+
+/// Pivot the radius-`r` ball over every queued open edge until the queue
+/// runs dry. The first point the ball touches as it rolls outward over edge
+/// `(a, b)` forms triangle `(b, a, k)`; when that triangle can't be added
+/// the edge stays open as boundary.
+fn expand(
+    front: &mut Front,
+    positions: &[Vector3<f64>],
+    grid: &Grid,
+    r: f64,
+    agrees: &dyn Fn(&Vector3<f64>, [u32; 3]) -> bool,
+    near: &mut Vec<u32>,
+) {
+    while let Some((a, b)) = front.queue.pop() {
+        let Some(edge) = front.open.get(&(a, b)).filter(|e| e.active) else {
+            continue;
+        };
+        let o = edge.opposite;
+        let (pa, pb) = (&positions[a as usize], &positions[b as usize]);
+        let Some(centre) = ball_centre(pa, pb, &positions[o as usize], r) else {
+            front.open.get_mut(&(a, b)).unwrap().active = false;
+            continue;
+        };
+        let mid = (pa + pb) * 0.5;
+        let axis = (pb - pa).normalize();
+        let from = centre - mid;
+
+        grid.within(positions, &mid, from.norm() + r, near);
+        let mut best: Option<(f64, u32, Vector3<f64>)> = None;
+        for &k in near.iter() {
+            if k == a || k == b || k == o {
+                continue;
+            }
+            let Some(to) = ball_centre(pb, pa, &positions[k as usize], r) else {
+                continue;
+            };
+            let to = to - mid;
+            let mut angle = axis.dot(&from.cross(&to)).atan2(from.dot(&to));
+            if angle < 0.0 {
+                angle += std::f64::consts::TAU;
+            }
+            if best
+                .as_ref()
+                .is_none_or(|(best_angle, _, _)| angle < *best_angle)
+            {
+                best = Some((angle, k, face_normal(pb, pa, &positions[k as usize])));
+            }
+        }
+        let added = best.is_some_and(|(_, k, n)| {
+            front.reachable(k) && agrees(&n, [b, a, k]) && front.add([b, a, k])
+        });
+        if !added {
+            if let Some(edge) = front.open.get_mut(&(a, b)) {
+                edge.active = false;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Point3;
+
+    /// A `width` x `depth` grid of points `spacing` apart on `y = 0`, normals
+    /// up, leaving out the columns in `gap`.
+    fn sheet(width: usize, depth: usize, spacing: f64, gap: &[usize]) -> Vec<PointNormal> {
+        let mut points = Vec::new();
+        for x in (0..width).filter(|x| !gap.contains(x)) {
+            for z in 0..depth {
+                // A little jitter keeps four points off a common circle.
+                let jitter = ((x * 7 + z * 13) % 5) as f64 * 0.002;
+                points.push(PointNormal {
+                    point: Point3::new(x as f64 * spacing + jitter, 0.0, z as f64 * spacing),
+                    normal: Vector3::new(0.0, 1.0, 0.0),
+                    scale: Vector3::new(0.01, 0.01, 0.01),
+                    opacity: 1.0,
+                    color: [0.5; 3],
+                    index: points.len() as u32,
+                });
+            }
+        }
+        points
+    }
+
+    fn options(radii: &[f64]) -> BpaOptions {
+        BpaOptions {
+            radii: radii.to_vec(),
+            viewpoint: Viewpoint::Above,
+        }
+    }
+
+    #[test]
+    fn sheet_is_fully_triangulated_facing_up() {
+        let points = sheet(12, 10, 0.1, &[]);
+        let build = reconstruct(&points, &options(&[0.1]), &|_, _| {});
+        assert_eq!(build.unused_points, 0);
+        assert_eq!(build.indices.len() / 3, 2 * 11 * 9);
+        for t in build.indices.chunks_exact(3) {
+            let p = |v: u32| {
+                let v = v as usize * 3;
+                Vector3::new(
+                    build.vertices[v] as f64,
+                    build.vertices[v + 1] as f64,
+                    build.vertices[v + 2] as f64,
+                )
+            };
+            assert!(face_normal(&p(t[0]), &p(t[1]), &p(t[2])).y > 0.99);
+        }
+        let mut edges: HashMap<(u32, u32), usize> = HashMap::new();
+        for t in build.indices.chunks_exact(3) {
+            for (a, b) in [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])] {
+                *edges.entry((a.min(b), a.max(b))).or_default() += 1;
+            }
+        }
+        assert!(edges.values().all(|&n| n <= 2));
+        // The border of an 11 x 9 sheet.
+        assert_eq!(build.boundary_edges, 2 * (11 + 9));
+    }
+
+    #[test]
+    fn larger_ball_bridges_what_the_small_one_falls_through() {
+        // Columns 5 and 6 missing: a 0.3 m gap between two 0.1 m sheets.
+        let points = sheet(12, 6, 0.1, &[5, 6]);
+        let small = reconstruct(&points, &options(&[0.1]), &|_, _| {});
+        let both = reconstruct(&points, &options(&[0.1, 0.25]), &|_, _| {});
+        assert_eq!(small.indices.len() / 3, 2 * (4 + 4) * 5);
+        assert_eq!(both.indices.len() / 3, 2 * (4 + 1 + 4) * 5);
+        assert!(both.boundary_edges < small.boundary_edges);
+    }
+}
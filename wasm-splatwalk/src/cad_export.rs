@@ -0,0 +1,340 @@
+//! SVG, DXF and IFC writers for the fitted floor plan.
+//!
+//! All three take plan coordinates (oriented-space `[x, z]` metres):
+//!
+//! - SVG keeps them as-is, a top view with `+z` down the page;
+//! - DXF writes `(x, -z)` so CAD packages with `+y` up show the same top view
+//!   unmirrored; walls are `LINE`s on layer `WALLS`, the outline a closed
+//!   `POLYLINE` on layer `FLOOR`;
+//! - IFC (IFC4, STEP physical file) maps plan `(x, -z)` to IFC `X` / `Y` and
+//!   oriented height to `Z`: one storey at the floor height holding an
+//!   extruded `IfcWall` per segment and an `IfcSlab` from the outline.
+//!
+//! The IFC is deliberately minimal (no materials, openings or property sets):
+//! enough for BIM tools to import the walls and slab as real elements.
+
+use crate::floor_plan::WallSegment;
+
+/// Dimensions for the IFC elements.
+#[derive(Clone, Copy, Debug)]
+pub struct IfcOptions {
+    pub floor_y: f64,
+    pub wall_height: f64,
+    pub wall_thickness: f64,
+    pub slab_thickness: f64,
+}
+
+/// SVG in metres: outline filled light grey, walls as black lines.
+pub fn to_svg(
+    segments: &[WallSegment],
+    outline: &[[f64; 2]],
+    min: [f64; 2],
+    max: [f64; 2],
+) -> String {
+    let margin = 0.25;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{:.3} {:.3} {:.3} {:.3}\">\n",
+        min[0] - margin,
+        min[1] - margin,
+        max[0] - min[0] + 2.0 * margin,
+        max[1] - min[1] + 2.0 * margin
+    );
+    if !outline.is_empty() {
+        let points: Vec<String> = outline
+            .iter()
+            .map(|p| format!("{:.3},{:.3}", p[0], p[1]))
+            .collect();
+        svg.push_str(&format!(
+            "<polygon fill=\"#e8e8e8\" stroke=\"none\" points=\"{}\"/>\n",
+            points.join(" ")
+        ));
+    }
+    svg.push_str("<g stroke=\"black\" stroke-width=\"0.05\" stroke-linecap=\"square\">\n");
+    for s in segments {
+        svg.push_str(&format!(
+            "<line x1=\"{:.3}\" y1=\"{:.3}\" x2=\"{:.3}\" y2=\"{:.3}\"/>\n",
+            s.start[0], s.start[1], s.end[0], s.end[1]
+        ));
+    }
+    svg.push_str("</g>\n</svg>\n");
+    svg
+}
+
+/// ASCII DXF (R12 entities, `$INSUNITS` metres).
+pub fn to_dxf(segments: &[WallSegment], outline: &[[f64; 2]]) -> String {
+    let mut dxf = String::from(
+        "0\nSECTION\n2\nHEADER\n9\n$INSUNITS\n70\n6\n0\nENDSEC\n0\nSECTION\n2\nENTITIES\n",
+    );
+    for s in segments {
+        dxf.push_str(&format!(
+            "0\nLINE\n8\nWALLS\n10\n{:.4}\n20\n{:.4}\n30\n0.0\n11\n{:.4}\n21\n{:.4}\n31\n0.0\n",
+            s.start[0], -s.start[1], s.end[0], -s.end[1]
+        ));
+    }
+    if !outline.is_empty() {
+        dxf.push_str("0\nPOLYLINE\n8\nFLOOR\n66\n1\n70\n1\n10\n0.0\n20\n0.0\n30\n0.0\n");
+        for p in outline {
+            dxf.push_str(&format!(
+                "0\nVERTEX\n8\nFLOOR\n10\n{:.4}\n20\n{:.4}\n30\n0.0\n",
+                p[0], -p[1]
+            ));
+        }
+        dxf.push_str("0\nSEQEND\n8\nFLOOR\n");
+    }
+    dxf.push_str("0\nENDSEC\n0\nEOF\n");
+    dxf
+}
+
+/// STEP entity writer handing out `#n` ids in order.
+struct Step {
+    body: String,
+    next: usize,
+}
+
+impl Step {
+    fn add(&mut self, entity: String) -> usize {
+        self.next += 1;
+        self.body.push_str(&format!("#{}={};\n", self.next, entity));
+        self.next
+    }
+
+    /// Deterministic IFC GlobalId (22 chars of IFC base64 over 128 bits).
+    fn guid(&self) -> String {
+        const CHARS: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz_$";
+        let mix = |mut z: u64| {
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^ (z >> 31)
+        };
+        let seed = 0x5a1a_u64.wrapping_add(self.next as u64);
+        let value = ((mix(seed) as u128) << 64) | mix(seed ^ 0x9e37_79b9_7f4a_7c15) as u128;
+        let mut id = String::with_capacity(22);
+        id.push(CHARS[(value >> 126) as usize & 3] as char);
+        for i in 0..21 {
+            id.push(CHARS[(value >> (120 - 6 * i)) as usize & 63] as char);
+        }
+        id
+    }
+}
+
+fn real(v: f64) -> String {
+    format!("{:.6}", v)
+}
+
+/// IFC4 STEP file with one wall per segment and a slab from `outline`.
+pub fn to_ifc(segments: &[WallSegment], outline: &[[f64; 2]], options: &IfcOptions) -> String {
+    let mut step = Step {
+        body: String::new(),
+        next: 0,
+    };
+    let length = step.add("IFCSIUNIT(*,.LENGTHUNIT.,$,.METRE.)".into());
+    let angle = step.add("IFCSIUNIT(*,.PLANEANGLEUNIT.,$,.RADIAN.)".into());
+    let units = step.add(format!("IFCUNITASSIGNMENT((#{},#{}))", length, angle));
+    let origin = step.add("IFCCARTESIANPOINT((0.,0.,0.))".into());
+    let world = step.add(format!("IFCAXIS2PLACEMENT3D(#{},$,$)", origin));
+    let context = step.add(format!(
+        "IFCGEOMETRICREPRESENTATIONCONTEXT($,'Model',3,1.E-05,#{},$)",
+        world
+    ));
+    let body = step.add(format!(
+        "IFCGEOMETRICREPRESENTATIONSUBCONTEXT('Body','Model',*,*,*,*,#{},$,.MODEL_VIEW.,$)",
+        context
+    ));
+    let project = step.add(format!(
+        "IFCPROJECT('{}',$,'SplatWalk scan',$,$,$,$,(#{}),#{})",
+        step.guid(),
+        context,
+        units
+    ));
+    let site_place = step.add(format!("IFCLOCALPLACEMENT($,#{})", world));
+    let site = step.add(format!(
+        "IFCSITE('{}',$,'Site',$,$,#{},$,$,.ELEMENT.,$,$,$,$,$)",
+        step.guid(),
+        site_place
+    ));
+    let building_place = step.add(format!("IFCLOCALPLACEMENT(#{},#{})", site_place, world));
+    let building = step.add(format!(
+        "IFCBUILDING('{}',$,'Building',$,$,#{},$,$,.ELEMENT.,$,$,$)",
+        step.guid(),
+        building_place
+    ));
+    let storey_origin = step.add(format!(
+        "IFCCARTESIANPOINT((0.,0.,{}))",
+        real(options.floor_y)
+    ));
+    let storey_axes = step.add(format!("IFCAXIS2PLACEMENT3D(#{},$,$)", storey_origin));
+    let storey_place = step.add(format!(
+        "IFCLOCALPLACEMENT(#{},#{})",
+        building_place, storey_axes
+    ));
+    let storey = step.add(format!(
+        "IFCBUILDINGSTOREY('{}',$,'Scanned floor',$,$,#{},$,$,.ELEMENT.,{})",
+        step.guid(),
+        storey_place,
+        real(options.floor_y)
+    ));
+    for (parent, child) in [(project, site), (site, building), (building, storey)] {
+        step.add(format!(
+            "IFCRELAGGREGATES('{}',$,$,$,#{},(#{}))",
+            step.guid(),
+            parent,
+            child
+        ));
+    }
+
+    let up = step.add("IFCDIRECTION((0.,0.,1.))".into());
+    let mut contained = Vec::new();
+    for (i, s) in segments.iter().enumerate() {
+        let mid = [
+            (s.start[0] + s.end[0]) * 0.5,
+            -(s.start[1] + s.end[1]) * 0.5,
+        ];
+        let dir = [
+            (s.end[0] - s.start[0]) / s.length,
+            -(s.end[1] - s.start[1]) / s.length,
+        ];
+        let point = step.add(format!(
+            "IFCCARTESIANPOINT(({},{},0.))",
+            real(mid[0]),
+            real(mid[1])
+        ));
+        let axis = step.add(format!(
+            "IFCDIRECTION(({},{},0.))",
+            real(dir[0]),
+            real(dir[1])
+        ));
+        let axes = step.add(format!("IFCAXIS2PLACEMENT3D(#{},#{},#{})", point, up, axis));
+        let place = step.add(format!("IFCLOCALPLACEMENT(#{},#{})", storey_place, axes));
+        let profile = step.add(format!(
+            "IFCRECTANGLEPROFILEDEF(.AREA.,$,$,{},{})",
+            real(s.length),
+            real(options.wall_thickness)
+        ));
+        let solid = step.add(format!(
+            "IFCEXTRUDEDAREASOLID(#{},$,#{},{})",
+            profile,
+            up,
+            real(options.wall_height)
+        ));
+        let shape = step.add(format!(
+            "IFCSHAPEREPRESENTATION(#{},'Body','SweptSolid',(#{}))",
+            body, solid
+        ));
+        let product = step.add(format!("IFCPRODUCTDEFINITIONSHAPE($,$,(#{}))", shape));
+        contained.push(step.add(format!(
+            "IFCWALL('{}',$,'Wall {}',$,$,#{},#{},$,.NOTDEFINED.)",
+            step.guid(),
+            i + 1,
+            place,
+            product
+        )));
+    }
+
+    if outline.len() >= 3 {
+        let mut corners: Vec<usize> = outline
+            .iter()
+            .map(|p| {
+                step.add(format!(
+                    "IFCCARTESIANPOINT(({},{}))",
+                    real(p[0]),
+                    real(-p[1])
+                ))
+            })
+            .collect();
+        corners.push(corners[0]);
+        let refs: Vec<String> = corners.iter().map(|c| format!("#{}", c)).collect();
+        let polyline = step.add(format!("IFCPOLYLINE(({}))", refs.join(",")));
+        let profile = step.add(format!(
+            "IFCARBITRARYCLOSEDPROFILEDEF(.AREA.,$,#{})",
+            polyline
+        ));
+        let down = step.add("IFCDIRECTION((0.,0.,-1.))".into());
+        let solid = step.add(format!(
+            "IFCEXTRUDEDAREASOLID(#{},$,#{},{})",
+            profile,
+            down,
+            real(options.slab_thickness)
+        ));
+        let shape = step.add(format!(
+            "IFCSHAPEREPRESENTATION(#{},'Body','SweptSolid',(#{}))",
+            body, solid
+        ));
+        let product = step.add(format!("IFCPRODUCTDEFINITIONSHAPE($,$,(#{}))", shape));
+        let place = step.add(format!("IFCLOCALPLACEMENT(#{},#{})", storey_place, world));
+        contained.push(step.add(format!(
+            "IFCSLAB('{}',$,'Floor',$,$,#{},#{},$,.FLOOR.)",
+            step.guid(),
+            place,
+            product
+        )));
+    }
+    if !contained.is_empty() {
+        let refs: Vec<String> = contained.iter().map(|c| format!("#{}", c)).collect();
+        step.add(format!(
+            "IFCRELCONTAINEDINSPATIALSTRUCTURE('{}',$,$,$,({}),#{})",
+            step.guid(),
+            refs.join(","),
+            storey
+        ));
+    }
+
+    format!(
+        "ISO-10303-21;\nHEADER;\nFILE_DESCRIPTION(('ViewDefinition [ReferenceView]'),'2;1');\n\
+         FILE_NAME('floor_plan.ifc','',(''),(''),'splatwalk','splatwalk','');\n\
+         FILE_SCHEMA(('IFC4'));\nENDSEC;\nDATA;\n{}ENDSEC;\nEND-ISO-10303-21;\n",
+        step.body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(start: [f64; 2], end: [f64; 2]) -> WallSegment {
+        let length = ((end[0] - start[0]).powi(2) + (end[1] - start[1]).powi(2)).sqrt();
+        WallSegment {
+            start,
+            end,
+            length,
+            support: 10,
+            snapped: false,
+        }
+    }
+
+    #[test]
+    fn exports_walls_and_outline() {
+        let segments = [
+            segment([0.0, 0.0], [4.0, 0.0]),
+            segment([4.0, 0.0], [4.0, 3.0]),
+        ];
+        let outline = [[0.0, 0.0], [4.0, 0.0], [4.0, 3.0], [0.0, 3.0]];
+
+        let dxf = to_dxf(&segments, &outline);
+        assert_eq!(dxf.matches("\nLINE\n").count(), 2);
+        assert_eq!(dxf.matches("\nVERTEX\n").count(), 4);
+        // Plan z = 3 is DXF y = -3.
+        assert!(dxf.contains("21\n-3.0000\n"));
+
+        let ifc = to_ifc(
+            &segments,
+            &outline,
+            &IfcOptions {
+                floor_y: 0.5,
+                wall_height: 2.5,
+                wall_thickness: 0.1,
+                slab_thickness: 0.2,
+            },
+        );
+        assert_eq!(ifc.matches("=IFCWALL(").count(), 2);
+        assert_eq!(ifc.matches("=IFCSLAB(").count(), 1);
+        assert!(ifc.ends_with("END-ISO-10303-21;\n"));
+        // GlobalIds are 22 characters and unique.
+        let ids: std::collections::HashSet<&str> = ifc
+            .split("('")
+            .skip(1)
+            .filter_map(|s| s.split('\'').next())
+            .filter(|s| s.len() == 22)
+            .collect();
+        assert_eq!(ids.len(), 11);
+    }
+}
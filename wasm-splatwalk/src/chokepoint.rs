@@ -0,0 +1,206 @@
+//! Chokepoints: narrow passages between open floor (`find_chokepoints`).
+//!
+//! Level scripts put door triggers and AI funnels on the narrow spots of a
+//! map, and on a scan nobody placed those by hand. [`find`] reads them off
+//! the ground field's clearance (distance to the nearest blocked cell):
+//! floor cells are switched on from the most open down, and each cell that
+//! first joins two areas which both open up to `chokepoint_max_width` is the
+//! narrowest point of a passage between them. Dead-end alcoves and
+//! corridors never join two open areas, so they are not reported; a passage
+//! whose narrowest point is under `chokepoint_min_width` counts as closed.
+
+use crate::MeshSettings;
+
+#[derive(Clone, Copy, Debug)]
+pub struct ChokepointOptions {
+    /// Passages narrower than this are too tight to walk (default 0.4 m).
+    pub min_width: f64,
+    /// Passages at least this wide are open floor (default 1.5 m).
+    pub max_width: f64,
+}
+
+impl ChokepointOptions {
+    pub fn from_settings(settings: &MeshSettings) -> Self {
+        Self {
+            min_width: settings.chokepoint_min_width.unwrap_or(0.4),
+            max_width: settings.chokepoint_max_width.unwrap_or(1.5),
+        }
+    }
+}
+
+/// Widths must be finite and non-negative, the maximum above the minimum.
+pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+    let options = ChokepointOptions::from_settings(settings);
+    if !(options.min_width.is_finite() && options.min_width >= 0.0) {
+        return Err(format!(
+            "Invalid chokepoint_min_width: {}",
+            options.min_width
+        ));
+    }
+    if !(options.max_width.is_finite() && options.max_width > options.min_width) {
+        return Err(format!(
+            "chokepoint_max_width ({}) must be finite and above chokepoint_min_width ({})",
+            options.max_width, options.min_width
+        ));
+    }
+    Ok(())
+}
+
+/// The narrowest cell of a passage, in field-local metres.
+pub struct Passage {
+    pub cell: usize,
+    /// Passage width through the cell: twice its clearance.
+    pub width: f64,
+    /// Unit `[u, v]` direction across the passage, towards the nearest wall.
+    pub across: [f64; 2],
+    /// Floor area of the two sides the passage joins, larger first, counting
+    /// only floor wider than the passage itself.
+    pub sides: [f64; 2],
+}
+
+/// Chokepoints of a `width x height` clearance field, narrowest first.
+pub fn find(
+    clearance: &[f64],
+    width: usize,
+    height: usize,
+    cell_size: f64,
+    options: &ChokepointOptions,
+) -> Vec<Passage> {
+    let open = |c: f64| c * 2.0 >= options.max_width;
+    let mut order: Vec<usize> = (0..clearance.len())
+        .filter(|&i| clearance[i] > 0.0 && clearance[i] * 2.0 >= options.min_width)
+        .collect();
+    order.sort_by(|&a, &b| clearance[b].total_cmp(&clearance[a]).then(a.cmp(&b)));
+
+    let mut parent: Vec<usize> = (0..clearance.len()).collect();
+    let mut cells = vec![0usize; clearance.len()];
+    let mut peak = vec![0.0f64; clearance.len()];
+    let mut active = vec![false; clearance.len()];
+    let mut found = Vec::new();
+    for &i in &order {
+        active[i] = true;
+        cells[i] = 1;
+        peak[i] = clearance[i];
+        let (col, row) = (i % width, i / width);
+        let mut roots: Vec<usize> = [
+            (col > 0).then(|| i - 1),
+            (col + 1 < width).then(|| i + 1),
+            (row > 0).then(|| i - width),
+            (row + 1 < height).then(|| i + width),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|&n| active[n])
+        .map(|n| find_root(&mut parent, n))
+        .collect();
+        roots.sort_unstable();
+        roots.dedup();
+
+        let mut sides: Vec<usize> = roots.iter().copied().filter(|&r| open(peak[r])).collect();
+        if sides.len() >= 2 && !open(clearance[i]) {
+            sides.sort_by(|&a, &b| cells[b].cmp(&cells[a]).then(a.cmp(&b)));
+            let area = |r: usize| cells[r] as f64 * cell_size * cell_size;
+            found.push(Passage {
+                cell: i,
+                width: clearance[i] * 2.0,
+                across: across(clearance, width, height, i),
+                sides: [area(sides[0]), area(sides[1])],
+            });
+        }
+        for r in roots {
+            let root = find_root(&mut parent, i);
+            if root != r {
+                parent[r] = root;
+                cells[root] += cells[r];
+                peak[root] = peak[root].max(peak[r]);
+            }
+        }
+    }
+    found.sort_by(|a, b| a.width.total_cmp(&b.width).then(a.cell.cmp(&b.cell)));
+    found
+}
+
+/// Direction from cell `i` to the nearest blocked cell (or off the field).
+fn across(clearance: &[f64], width: usize, height: usize, i: usize) -> [f64; 2] {
+    let (col, row) = ((i % width) as isize, (i / width) as isize);
+    let blocked = |c: isize, r: isize| {
+        c < 0
+            || r < 0
+            || c as usize >= width
+            || r as usize >= height
+            || clearance[r as usize * width + c as usize] <= 0.0
+    };
+    let mut best: Option<(isize, isize)> = None;
+    for radius in 1..=(width.max(height) as isize) {
+        for dr in -radius..=radius {
+            for dc in -radius..=radius {
+                if dr.abs().max(dc.abs()) != radius || !blocked(col + dc, row + dr) {
+                    continue;
+                }
+                let closer = best.is_none_or(|(bc, br)| dc * dc + dr * dr < bc * bc + br * br);
+                if closer {
+                    best = Some((dc, dr));
+                }
+            }
+        }
+        // The next ring starts radius + 1 away; stop once the best hit is
+        // no farther than that.
+        if let Some((bc, br)) = best {
+            if bc * bc + br * br <= (radius + 1) * (radius + 1) {
+                break;
+            }
+        }
+    }
+    let (dc, dr) = best.unwrap_or((1, 0));
+    let len = ((dc * dc + dr * dr) as f64).sqrt();
+    [dc as f64 / len, dr as f64 / len]
+}
+
+fn find_root(parent: &mut [usize], mut x: usize) -> usize {
+    while parent[x] != x {
+        parent[x] = parent[parent[x]];
+        x = parent[x];
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::safe_area::clearance_field;
+
+    #[test]
+    fn doorway_between_rooms_is_found_and_alcove_is_not() {
+        // Two 9x9 rooms joined by a 3-cell doorway through a one-cell wall at
+        // column 9, plus a narrow dead-end alcove off the left room.
+        let (width, height) = (19, 13);
+        let free: Vec<bool> = (0..width * height)
+            .map(|i| {
+                let (col, row) = (i % width, i / width);
+                let room = row < 9 && col != 9;
+                let door = col == 9 && (3..6).contains(&row);
+                let alcove = col == 2 && row >= 9;
+                room || door || alcove
+            })
+            .collect();
+        let clearance = clearance_field(&free, width, height, 0.25);
+        let options = ChokepointOptions {
+            min_width: 0.2,
+            max_width: 1.5,
+        };
+        let found = find(&clearance, width, height, 0.25, &options);
+        assert_eq!(found.len(), 1);
+        let door = &found[0];
+        assert_eq!(door.cell, 4 * width + 9);
+        assert!((door.width - 0.75).abs() < 1e-9);
+        assert!(door.across[1].abs() > 0.99);
+        assert!(door.sides.iter().all(|&a| a > 1.0));
+
+        // A minimum width above the door's closes it.
+        let closed = ChokepointOptions {
+            min_width: 1.0,
+            ..options
+        };
+        assert!(find(&clearance, width, height, 0.25, &closed).is_empty());
+    }
+}
@@ -0,0 +1,78 @@
+//! `SplatCloud`: one parsed splat file kept in WASM memory.
+//!
+//! Tuning navmesh settings through the byte-based entry points re-parses (and
+//! re-prunes) the whole file on every attempt. A `SplatCloud` parses once and
+//! then bakes as often as the host likes. It is a single-source
+//! [`SplatSession`] underneath, so its bakes match the byte-based entry points
+//! and `reset_module` releases it like any session.
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::session::SplatSession;
+use crate::SplatwalkError;
+
+/// Session source name the cloud's file is stored under.
+const SOURCE: &str = "cloud";
+
+/// A parsed splat cloud that settings can be iterated against.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct SplatCloud {
+    session: SplatSession,
+}
+
+impl SplatCloud {
+    fn ensure_parsed(&self) -> Result<(), JsValue> {
+        if self.session.point_count() == 0 {
+            return Err(SplatwalkError::EmptyCloud(
+                "SplatCloud holds no splats; call parse(data) first.".to_string(),
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// `settings`, or `{ mode: 0 }` when the host omitted them.
+fn settings_or_default(settings: JsValue) -> Result<JsValue, JsValue> {
+    if settings.is_undefined() || settings.is_null() {
+        let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+        return Ok(serde_json::json!({ "mode": 0 }).serialize(&serializer)?);
+    }
+    Ok(settings)
+}
+
+#[wasm_bindgen]
+impl SplatCloud {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> SplatCloud {
+        SplatCloud::default()
+    }
+
+    /// Parse `data` (PLY / SPZ / ...) into the cloud, replacing whatever it
+    /// held. `settings` is optional and only its ingest fields
+    /// (`prune_floaters*`, `flip_y`, `coordinate_system`) are read. Returns the parsed point count.
+    pub fn parse(&mut self, data: &[u8], settings: JsValue) -> Result<usize, JsValue> {
+        self.session.add_source(SOURCE, data, settings)
+    }
+
+    /// Points kept by the last `parse` (0 before the first).
+    pub fn point_count(&self) -> usize {
+        self.session.point_count()
+    }
+
+    /// `get_splat_bounds` of the parsed cloud; `settings` is optional.
+    pub fn bounds(&self, settings: JsValue) -> Result<JsValue, JsValue> {
+        self.ensure_parsed()?;
+        self.session
+            .get_splat_bounds(settings_or_default(settings)?)
+    }
+
+    /// `convert_splat_to_mesh` on the parsed cloud. Ingest fields of
+    /// `settings` are ignored; call `parse` again to change them.
+    pub fn reconstruct(&self, settings: JsValue) -> Result<JsValue, JsValue> {
+        self.ensure_parsed()?;
+        self.session.convert_splat_to_mesh(settings)
+    }
+}
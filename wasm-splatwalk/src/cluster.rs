@@ -0,0 +1,198 @@
+//! Normal-cone clustering of splats into floor, wall, and clutter groups.
+//!
+//! The ground field used to weigh every splat by how upward its normal points,
+//! so a wall splat still added a third of its opacity to the floor density and a
+//! tabletop counted as floor. With `normal_clusters` on, splats are binned by
+//! normal direction and height before the bake instead:
+//!
+//! - `floor`: normal within `floor_cone_deg` of vertical and no higher than
+//!   `floor_band_height` above the floor estimate;
+//! - `wall`: normal within `wall_cone_deg` of horizontal;
+//! - `clutter`: everything else (tabletops, slanted or noisy normals).
+//!
+//! The floor group feeds the ground-field density at full weight, the wall group
+//! becomes obstacle evidence in its column, and clutter keeps the legacy
+//! weighting so furniture still shows up as layered obstacles.
+
+use serde::Serialize;
+
+use crate::splat::PointNormal;
+use crate::MeshSettings;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NormalGroup {
+    Floor,
+    Wall,
+    Clutter,
+}
+
+impl NormalGroup {
+    pub fn name(self) -> &'static str {
+        match self {
+            NormalGroup::Floor => "floor",
+            NormalGroup::Wall => "wall",
+            NormalGroup::Clutter => "clutter",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct NormalClusterOptions {
+    /// `cos(floor_cone_deg)`: minimum `|normal.y|` for the floor group.
+    pub floor_min_normal_y: f64,
+    /// `sin(wall_cone_deg)`: maximum `|normal.y|` for the wall group.
+    pub wall_max_normal_y: f64,
+    pub floor_band_height: f64,
+}
+
+impl NormalClusterOptions {
+    /// Options from `floor_cone_deg` (default 30), `wall_cone_deg` (default 20)
+    /// and `floor_band_height` (default 0.3), or `None` when `normal_clusters`
+    /// is off.
+    pub fn from_settings(settings: &MeshSettings) -> Option<Self> {
+        settings
+            .normal_clusters
+            .unwrap_or(false)
+            .then(|| Self::resolve(settings))
+    }
+
+    /// The same options regardless of `normal_clusters`, for consumers that
+    /// always cluster (the floor plan).
+    pub fn resolve(settings: &MeshSettings) -> Self {
+        let floor_cone = settings
+            .floor_cone_deg
+            .filter(|v| v.is_finite())
+            .unwrap_or(30.0)
+            .clamp(1.0, 89.0);
+        let wall_cone = settings
+            .wall_cone_deg
+            .filter(|v| v.is_finite())
+            .unwrap_or(20.0)
+            .clamp(1.0, 89.0);
+        Self {
+            floor_min_normal_y: floor_cone.to_radians().cos(),
+            wall_max_normal_y: wall_cone.to_radians().sin(),
+            floor_band_height: settings
+                .floor_band_height
+                .filter(|v| v.is_finite() && *v > 0.0)
+                .unwrap_or(0.3),
+        }
+    }
+}
+
+/// Summary of one group, reported in `diagnostics.normal_clusters`.
+#[derive(Clone, Serialize)]
+pub struct NormalCluster {
+    pub label: String,
+    pub splat_count: usize,
+    /// Opacity-weighted mean of the (up-facing) normals.
+    pub mean_normal: [f64; 3],
+    pub min: [f64; 3],
+    pub max: [f64; 3],
+}
+
+/// Label every splat. Normals are unsigned (a splat's normal is its shortest
+/// gaussian axis, so its sign carries no meaning).
+pub fn classify(
+    points: &[PointNormal],
+    floor_y: f64,
+    options: &NormalClusterOptions,
+) -> Vec<NormalGroup> {
+    let floor_top = floor_y + options.floor_band_height;
+    points
+        .iter()
+        .map(|p| {
+            let len = p.normal.norm();
+            let normal_y = if len > 0.0 {
+                (p.normal.y / len).abs()
+            } else {
+                0.0
+            };
+            if normal_y >= options.floor_min_normal_y && p.point.y <= floor_top {
+                NormalGroup::Floor
+            } else if normal_y <= options.wall_max_normal_y {
+                NormalGroup::Wall
+            } else {
+                NormalGroup::Clutter
+            }
+        })
+        .collect()
+}
+
+/// Per-group counts, bounds and mean normal, in floor / wall / clutter order.
+/// Empty groups are omitted.
+pub fn summarize(points: &[PointNormal], labels: &[NormalGroup]) -> Vec<NormalCluster> {
+    [NormalGroup::Floor, NormalGroup::Wall, NormalGroup::Clutter]
+        .into_iter()
+        .filter_map(|group| {
+            let mut count = 0;
+            let mut normal = [0.0_f64; 3];
+            let mut min = [f64::MAX; 3];
+            let mut max = [f64::MIN; 3];
+            for (p, _) in points.iter().zip(labels).filter(|(_, &l)| l == group) {
+                count += 1;
+                let sign = if p.normal.y < 0.0 { -1.0 } else { 1.0 };
+                let weight = 1.0 / (1.0 + (-p.opacity).exp());
+                let coords = [p.point.x, p.point.y, p.point.z];
+                for axis in 0..3 {
+                    normal[axis] += p.normal[axis] * sign * weight;
+                    min[axis] = min[axis].min(coords[axis]);
+                    max[axis] = max[axis].max(coords[axis]);
+                }
+            }
+            if count == 0 {
+                return None;
+            }
+            let len = (normal[0] * normal[0] + normal[1] * normal[1] + normal[2] * normal[2])
+                .sqrt()
+                .max(1e-12);
+            Some(NormalCluster {
+                label: group.name().to_string(),
+                splat_count: count,
+                mean_normal: normal.map(|c| c / len),
+                min,
+                max,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::splat::test_splat;
+
+    #[test]
+    fn groups_follow_normal_cone_and_height() {
+        let options = NormalClusterOptions {
+            floor_min_normal_y: 30.0_f64.to_radians().cos(),
+            wall_max_normal_y: 20.0_f64.to_radians().sin(),
+            floor_band_height: 0.3,
+        };
+        let points = [
+            test_splat([0.0, 0.05, 0.0], [0.0, 1.0, 0.0]),
+            test_splat([0.0, 0.1, 0.0], [0.1, -0.98, 0.0]),
+            test_splat([0.0, 0.8, 0.0], [0.0, 1.0, 0.0]),
+            test_splat([0.0, 1.2, 0.0], [1.0, 0.05, 0.0]),
+            test_splat([0.0, 0.4, 0.0], [0.7, 0.7, 0.0]),
+        ];
+        let labels = classify(&points, 0.0, &options);
+        assert_eq!(
+            labels,
+            [
+                NormalGroup::Floor,
+                NormalGroup::Floor,
+                NormalGroup::Clutter,
+                NormalGroup::Wall,
+                NormalGroup::Clutter,
+            ]
+        );
+        let groups = summarize(&points, &labels);
+        assert_eq!(groups.len(), 3);
+        assert_eq!(
+            (groups[0].label.as_str(), groups[0].splat_count),
+            ("floor", 2)
+        );
+        assert!(groups[0].mean_normal[1] > 0.99);
+    }
+}
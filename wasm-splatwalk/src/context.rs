@@ -0,0 +1,147 @@
+//! Explicit owners for the state the core keeps between calls.
+//!
+//! The progress callback, face filter, bake journal, job caches, the session
+//! registry and the thread support report used to be thread-local singletons.
+//! Every caller on an instance shared them, so a sync bake that ran while an async bake was
+//! between steps reset the async bake's journal, and a host running two
+//! pipelines had to re-register callbacks around every call. They now live in
+//! a [`ContextState`] owned by a [`SplatwalkContext`] handle. A host that runs
+//! several independent pipelines on one instance (or wants per-tenant state in
+//! a worker pool) creates a handle for each and calls through it. The free
+//! functions keep working against the instance's default handle.
+//!
+//! While a call runs, its handle is [`enter`]ed, and pipeline code reaches the
+//! running call's state through [`current`]. The thread-locals left here are
+//! the stack of entered handles, which owns nothing past the call, and the
+//! default handle behind the free functions. The allocator's byte counters
+//! (see `memory`, `alloc_stats` builds only) stay global: there is one
+//! allocator per instance.
+
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+use wasm_bindgen::prelude::*;
+
+use crate::journal::Journal;
+use crate::memory::Jobs;
+use crate::session::SessionNodes;
+use crate::thread_support::Environment;
+
+/// Everything one handle keeps between calls.
+#[derive(Default)]
+pub(crate) struct ContextState {
+    /// `set_progress_callback`.
+    pub(crate) progress: RefCell<Option<js_sys::Function>>,
+    /// `set_face_filter`.
+    pub(crate) face_filter: RefCell<Option<js_sys::Function>>,
+    /// The most recent bake's journal.
+    pub(crate) journal: RefCell<Journal>,
+    /// Retained buffers per `job_id`.
+    pub(crate) jobs: Jobs,
+    /// Sessions created under this handle, for `reset`.
+    pub(crate) sessions: RefCell<Vec<Weak<SessionNodes>>>,
+    /// The environment the latest `init_splatwalk` thread support probe saw.
+    pub(crate) thread_support: RefCell<Option<Environment>>,
+}
+
+thread_local! {
+    static DEFAULT: Rc<ContextState> = Rc::new(ContextState::default());
+    static ENTERED: RefCell<Vec<Rc<ContextState>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The state of the innermost entered handle, or the default one.
+pub(crate) fn current() -> Rc<ContextState> {
+    ENTERED
+        .with(|entered| entered.borrow().last().cloned())
+        .unwrap_or_else(|| DEFAULT.with(Rc::clone))
+}
+
+/// Leaves the entered handle when dropped, including on unwind.
+struct Entered;
+
+impl Drop for Entered {
+    fn drop(&mut self) {
+        ENTERED.with(|entered| entered.borrow_mut().pop());
+    }
+}
+
+/// Run `f` with `state` as the [`current`] state.
+pub(crate) fn enter<R>(state: &Rc<ContextState>, f: impl FnOnce() -> R) -> R {
+    ENTERED.with(|entered| entered.borrow_mut().push(Rc::clone(state)));
+    let _entered = Entered;
+    f()
+}
+
+/// An independent set of core state: progress callback, face filter, bake
+/// journal, jobs and sessions. `ctx.run(() => convert_splat_to_mesh(...))`
+/// runs any entry point against this handle instead of the default one, and
+/// an async bake or `SplatSession` started inside `run` stays bound to it.
+#[wasm_bindgen]
+pub struct SplatwalkContext {
+    state: Rc<ContextState>,
+}
+
+impl Default for SplatwalkContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[wasm_bindgen]
+impl SplatwalkContext {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> SplatwalkContext {
+        SplatwalkContext {
+            state: Rc::new(ContextState::default()),
+        }
+    }
+
+    /// Call `f()` with this handle entered and return what it returns.
+    pub fn run(&self, f: &js_sys::Function) -> Result<JsValue, JsValue> {
+        enter(&self.state, || f.call0(&JsValue::NULL))
+    }
+
+    /// `set_progress_callback` for this handle.
+    pub fn set_progress_callback(&self, callback: Option<js_sys::Function>) {
+        enter(&self.state, || crate::set_progress_callback(callback));
+    }
+
+    /// `set_face_filter` for this handle.
+    pub fn set_face_filter(&self, callback: Option<js_sys::Function>) {
+        enter(&self.state, || {
+            crate::face_filter::set_face_filter(callback)
+        });
+    }
+
+    /// `bake_journal()` of the latest bake run through this handle.
+    pub fn bake_journal(&self) -> Result<JsValue, JsValue> {
+        enter(&self.state, crate::bake_journal)
+    }
+
+    /// `reset_module()` for this handle's state only. Peak tracking restarts
+    /// for the whole instance.
+    pub fn reset(&self) -> Result<JsValue, JsValue> {
+        enter(&self.state, crate::reset_module)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::journal;
+
+    #[test]
+    fn entered_handles_keep_their_own_journal() {
+        journal::begin("default");
+        let other = Rc::new(ContextState::default());
+        enter(&other, || {
+            journal::begin("other");
+            enter(&Rc::new(ContextState::default()), || {
+                journal::begin("inner")
+            });
+            assert_eq!(journal::snapshot().entry_point, "other");
+        });
+        assert_eq!(journal::snapshot().entry_point, "default");
+        assert_eq!(other.journal.borrow().entry_point, "other");
+    }
+}
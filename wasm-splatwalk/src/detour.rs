@@ -0,0 +1,566 @@
+//! Recast/Detour navmesh binary (`emit_detour_navmesh`).
+//!
+//! Engines that already ship Detour (Babylon's Recast plugin,
+//! recast-navigation-js, Unity and native Detour) had to rebuild the polygon
+//! navmesh from `navmesh` JSON before they could query it. [`navmesh_set`]
+//! writes it as the recastnavigation sample's `.bin`: an `MSET` header with
+//! the `dtNavMeshParams`, then one tile in the `dtNavMesh` tile layout that
+//! `dtCreateNavMeshData` produces (format version 7, 32-bit polygon
+//! references, native little-endian).
+//!
+//! The tile holds the polygons with Detour's winding (clockwise seen from
+//! above, the reverse of `navmesh`), a triangle fan per polygon as the detail
+//! mesh, zeroed link space that Detour fills in when the tile is added, and a
+//! bounding-volume tree quantized to the collision voxel size. Placed
+//! `off_mesh_links` follow as off-mesh connection polygons and
+//! `dtOffMeshConnection` records. Detour wants `+Y` up, so the binary is
+//! always in `splatwalk_oriented`.
+
+use crate::navmesh::PolyNavmesh;
+use crate::offmesh::OffMeshLink;
+use crate::MeshSettings;
+
+/// `DT_NAVMESH_MAGIC`: `'DNAV'`.
+const NAVMESH_MAGIC: u32 = u32::from_be_bytes(*b"DNAV");
+/// `DT_NAVMESH_VERSION`.
+const NAVMESH_VERSION: u32 = 7;
+/// `NAVMESHSET_MAGIC` of the recastnavigation samples: `'MSET'`.
+const NAVMESHSET_MAGIC: u32 = u32::from_be_bytes(*b"MSET");
+const NAVMESHSET_VERSION: u32 = 1;
+/// `DT_VERTS_PER_POLYGON`.
+pub const MAX_VERTS_PER_POLY: usize = 6;
+/// Polygon flags of every polygon (the samples' `SAMPLE_POLYFLAGS_WALK`), so
+/// the default `dtQueryFilter` includes them.
+const POLY_FLAGS: u16 = 1;
+/// Area id of every polygon (`SAMPLE_POLYAREA_GROUND`, polygon type ground).
+const POLY_AREA: u8 = 0;
+/// `DT_POLYTYPE_OFFMESH_CONNECTION`, stored in the top two bits of
+/// `areaAndtype`.
+const POLYTYPE_OFFMESH_CONNECTION: u8 = 1;
+/// `DT_OFFMESH_CON_BIDIR`.
+const OFFMESH_CON_BIDIR: u8 = 1;
+/// Byte offset of the tile data in [`navmesh_set`]'s output: the set header
+/// (12 bytes plus 28 of `dtNavMeshParams`) and the tile header (8).
+pub const TILE_DATA_OFFSET: usize = 48;
+
+/// Agent dimensions stored in the tile header.
+#[derive(Clone, Copy, Debug)]
+pub struct DetourAgent {
+    pub height: f64,
+    pub radius: f64,
+    pub climb: f64,
+}
+
+impl DetourAgent {
+    pub fn from_settings(settings: &MeshSettings) -> Self {
+        Self {
+            height: settings
+                .agent_height
+                .or(settings.collision_carve_height)
+                .unwrap_or(1.6),
+            radius: 0.0,
+            climb: settings.agent_max_climb.unwrap_or(0.5),
+        }
+    }
+}
+
+/// `emit_detour_navmesh` exports the polygon navmesh, so it needs
+/// `emit_poly_navmesh` and Detour's six corners per polygon at most.
+pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+    if !settings.emit_detour_navmesh.unwrap_or(false) {
+        return Ok(());
+    }
+    if !settings.emit_poly_navmesh.unwrap_or(false) {
+        return Err("emit_detour_navmesh requires emit_poly_navmesh".to_string());
+    }
+    match settings.navmesh_max_verts_per_poly {
+        Some(n) if n > MAX_VERTS_PER_POLY => Err(format!(
+            "emit_detour_navmesh needs navmesh_max_verts_per_poly of at most {}, got {}",
+            MAX_VERTS_PER_POLY, n
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Little-endian writer for the C structs.
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u8(&mut self, v: u8) {
+        self.0.push(v);
+    }
+    fn u16(&mut self, v: u16) {
+        self.0.extend_from_slice(&v.to_le_bytes());
+    }
+    fn u32(&mut self, v: u32) {
+        self.0.extend_from_slice(&v.to_le_bytes());
+    }
+    fn i32(&mut self, v: i32) {
+        self.0.extend_from_slice(&v.to_le_bytes());
+    }
+    fn f32(&mut self, v: f32) {
+        self.0.extend_from_slice(&v.to_le_bytes());
+    }
+    fn zeros(&mut self, n: usize) {
+        self.0.resize(self.0.len() + n, 0);
+    }
+}
+
+/// `dtBVNode`: quantized bounds, and the polygon index of a leaf or the
+/// negated escape offset of an inner node.
+#[derive(Clone, Copy)]
+struct BvNode {
+    min: [u16; 3],
+    max: [u16; 3],
+    i: i32,
+}
+
+/// `dtNavMesh` tile data and the bounds it covers.
+struct Tile {
+    data: Vec<u8>,
+    bmin: [f32; 3],
+    bmax: [f32; 3],
+}
+
+/// The navmesh and its off-mesh `links` as the recastnavigation sample
+/// `.bin`: `NavMeshSetHeader`, then one `NavMeshTileHeader` and its tile data
+/// (at [`TILE_DATA_OFFSET`]).
+pub fn navmesh_set(
+    navmesh: &PolyNavmesh,
+    links: &[OffMeshLink],
+    agent: &DetourAgent,
+    cell_size: f64,
+) -> Result<Vec<u8>, String> {
+    let Tile { data, bmin, bmax } = tile_data(navmesh, links, agent, cell_size)?;
+    let polys = (navmesh.poly_sizes.len() + links.len()) as u32;
+    let mut w = Writer(Vec::with_capacity(TILE_DATA_OFFSET + data.len()));
+    w.u32(NAVMESHSET_MAGIC);
+    w.u32(NAVMESHSET_VERSION);
+    w.i32(1);
+    // dtNavMeshParams of a single-tile mesh, as `dtNavMesh::init(data)` sets them.
+    for v in bmin {
+        w.f32(v);
+    }
+    w.f32(bmax[0] - bmin[0]);
+    w.f32(bmax[2] - bmin[2]);
+    w.i32(1);
+    w.i32(polys as i32);
+    // Reference of tile 0 with the initial salt 1; no tile bits for one tile.
+    let poly_bits = polys.next_power_of_two().trailing_zeros();
+    w.u32(1 << poly_bits);
+    w.i32(data.len() as i32);
+    w.0.extend_from_slice(&data);
+    Ok(w.0)
+}
+
+/// The `dtNavMesh` tile of `navmesh`, with one off-mesh connection per link.
+fn tile_data(
+    navmesh: &PolyNavmesh,
+    links: &[OffMeshLink],
+    agent: &DetourAgent,
+    cell_size: f64,
+) -> Result<Tile, String> {
+    let vert_count = navmesh.vertices.len() / 3;
+    let poly_count = navmesh.poly_sizes.len();
+    if poly_count == 0 {
+        return Err("The navmesh has no polygons".to_string());
+    }
+    // Each link adds a two-vertex polygon. Vertex indices are u16; neighbour
+    // references keep 0x8000 for links to other tiles.
+    let total_verts = vert_count + links.len() * 2;
+    let total_polys = poly_count + links.len();
+    if total_verts > 0xffff || total_polys >= 0x7fff {
+        return Err(format!(
+            "The navmesh has {} vertices and {} polygons; a Detour tile holds at most 65535 and 32766",
+            total_verts, total_polys
+        ));
+    }
+    if let Some(&n) = navmesh
+        .poly_sizes
+        .iter()
+        .find(|&&n| n as usize > MAX_VERTS_PER_POLY)
+    {
+        return Err(format!(
+            "A navmesh polygon has {} corners; Detour allows at most {}",
+            n, MAX_VERTS_PER_POLY
+        ));
+    }
+
+    let vertex = |i: usize| [0, 1, 2].map(|k| navmesh.vertices[i * 3 + k]);
+    let mut bmin = [f32::INFINITY; 3];
+    let mut bmax = [f32::NEG_INFINITY; 3];
+    let link_ends: Vec<[f32; 3]> = links
+        .iter()
+        .flat_map(|link| [link.start, link.end])
+        .map(|p| p.map(|c| c as f32))
+        .collect();
+    // Detour only keeps connections whose start lies inside the tile bounds.
+    for v in (0..vert_count).map(vertex).chain(link_ends.iter().copied()) {
+        for k in 0..3 {
+            bmin[k] = bmin[k].min(v[k]);
+            bmax[k] = bmax[k].max(v[k]);
+        }
+    }
+
+    // Corners and neighbours reversed into Detour's winding: corner k is
+    // corner n - 1 - k, and edge k is the reversed edge n - 2 - k.
+    let mut polys = Vec::with_capacity(poly_count);
+    let mut start = 0;
+    for &size in &navmesh.poly_sizes {
+        let n = size as usize;
+        let corners = &navmesh.polys[start..start + n];
+        let neighbors = &navmesh.neighbors[start..start + n];
+        let verts: Vec<u16> = (0..n).map(|k| corners[n - 1 - k] as u16).collect();
+        let neis: Vec<u16> = (0..n)
+            .map(|k| match neighbors[(2 * n - 2 - k) % n] {
+                -1 => 0,
+                p => p as u16 + 1,
+            })
+            .collect();
+        polys.push((verts, neis));
+        start += n;
+    }
+    let edge_count: usize = navmesh.poly_sizes.iter().map(|&n| n as usize).sum();
+    let tri_count: usize = navmesh.poly_sizes.iter().map(|&n| n as usize - 2).sum();
+
+    let extent = (0..3).map(|k| bmax[k] - bmin[k]).fold(0.0f32, f32::max) as f64;
+    let quant = (1.0 / cell_size.max(1e-3)).min(65534.0 / extent.max(1e-6)) as f32;
+    let nodes = bv_tree(&polys, &vertex, bmin, quant);
+
+    // dtLink space: one internal link per edge, and two for each end of a
+    // link, as `dtCreateNavMeshData` counts them.
+    let link_count = edge_count + links.len() * 4;
+
+    let mut w = Writer(Vec::new());
+    // dtMeshHeader
+    w.u32(NAVMESH_MAGIC);
+    w.u32(NAVMESH_VERSION);
+    w.i32(0); // x
+    w.i32(0); // y
+    w.i32(0); // layer
+    w.u32(0); // userId
+    w.i32(total_polys as i32);
+    w.i32(total_verts as i32);
+    w.i32(link_count as i32); // maxLinkCount
+    w.i32(poly_count as i32); // detailMeshCount
+    w.i32(0); // detailVertCount
+    w.i32(tri_count as i32);
+    w.i32(nodes.len() as i32);
+    w.i32(links.len() as i32); // offMeshConCount
+    w.i32(poly_count as i32); // offMeshBase
+    w.f32(agent.height as f32);
+    w.f32(agent.radius as f32);
+    w.f32(agent.climb as f32);
+    for v in bmin.iter().chain(&bmax) {
+        w.f32(*v);
+    }
+    w.f32(quant);
+
+    for v in (0..vert_count).map(vertex).chain(link_ends.iter().copied()) {
+        for c in v {
+            w.f32(c);
+        }
+    }
+    // dtPoly: firstLink, verts[6], neis[6], flags, vertCount, areaAndtype
+    for (verts, neis) in &polys {
+        w.u32(0);
+        for k in 0..MAX_VERTS_PER_POLY {
+            w.u16(verts.get(k).copied().unwrap_or(0));
+        }
+        for k in 0..MAX_VERTS_PER_POLY {
+            w.u16(neis.get(k).copied().unwrap_or(0));
+        }
+        w.u16(POLY_FLAGS);
+        w.u8(verts.len() as u8);
+        w.u8(POLY_AREA);
+    }
+    // Off-mesh connection polygons: the link's two ends, no neighbours.
+    for i in 0..links.len() {
+        let first = (vert_count + i * 2) as u16;
+        w.u32(0);
+        w.u16(first);
+        w.u16(first + 1);
+        w.zeros((MAX_VERTS_PER_POLY - 2) * 2 + MAX_VERTS_PER_POLY * 2);
+        w.u16(POLY_FLAGS);
+        w.u8(2);
+        w.u8(POLY_AREA | (POLYTYPE_OFFMESH_CONNECTION << 6));
+    }
+    // dtLink space, filled in by dtNavMesh::addTile.
+    w.zeros(link_count * 12);
+    // dtPolyDetail: a fan over the polygon's own corners, no extra vertices.
+    let mut tri_base = 0u32;
+    for (verts, _) in &polys {
+        let tris = verts.len() as u32 - 2;
+        w.u32(0);
+        w.u32(tri_base);
+        w.u8(0);
+        w.u8(tris as u8);
+        w.zeros(2);
+        tri_base += tris;
+    }
+    // Detail triangles with the flags `dtCreateNavMeshData` gives a fan: bit
+    // pairs mark the edges on the polygon boundary.
+    for (verts, _) in &polys {
+        let n = verts.len();
+        for j in 2..n {
+            let mut flags = 1 << 2;
+            if j == 2 {
+                flags |= 1;
+            }
+            if j == n - 1 {
+                flags |= 1 << 4;
+            }
+            w.u8(0);
+            w.u8(j as u8 - 1);
+            w.u8(j as u8);
+            w.u8(flags);
+        }
+    }
+    // The tree is allocated for 2 * polyCount nodes, as Detour sizes it.
+    for k in 0..poly_count * 2 {
+        let node = nodes.get(k).copied().unwrap_or(BvNode {
+            min: [0; 3],
+            max: [0; 3],
+            i: 0,
+        });
+        for v in node.min.iter().chain(&node.max) {
+            w.u16(*v);
+        }
+        w.i32(node.i);
+    }
+    // dtOffMeshConnection: pos[6], rad, poly, flags, side, userId. Both ends
+    // are inside the tile (side 0xff); userId is the link's `index`.
+    for (i, link) in links.iter().enumerate() {
+        for c in link_ends[i * 2].iter().chain(&link_ends[i * 2 + 1]) {
+            w.f32(*c);
+        }
+        w.f32(link.radius as f32);
+        w.u16((poly_count + i) as u16);
+        w.u8(if link.bidirectional {
+            OFFMESH_CON_BIDIR
+        } else {
+            0
+        });
+        w.u8(0xff);
+        w.u32(link.index as u32);
+    }
+    Ok(Tile {
+        data: w.0,
+        bmin,
+        bmax,
+    })
+}
+
+/// Detour's `createBVTree`: median splits along the longest axis, nodes in
+/// depth-first order.
+fn bv_tree(
+    polys: &[(Vec<u16>, Vec<u16>)],
+    vertex: &impl Fn(usize) -> [f32; 3],
+    bmin: [f32; 3],
+    quant: f32,
+) -> Vec<BvNode> {
+    let mut items: Vec<BvNode> = polys
+        .iter()
+        .enumerate()
+        .map(|(i, (verts, _))| {
+            let mut lo = [f32::INFINITY; 3];
+            let mut hi = [f32::NEG_INFINITY; 3];
+            for v in verts.iter().map(|&v| vertex(v as usize)) {
+                for k in 0..3 {
+                    lo[k] = lo[k].min(v[k]);
+                    hi[k] = hi[k].max(v[k]);
+                }
+            }
+            BvNode {
+                min: [0, 1, 2]
+                    .map(|k| ((lo[k] - bmin[k]) * quant).floor().clamp(0.0, 65535.0) as u16),
+                max: [0, 1, 2]
+                    .map(|k| ((hi[k] - bmin[k]) * quant).ceil().clamp(0.0, 65535.0) as u16),
+                i: i as i32,
+            }
+        })
+        .collect();
+    let mut nodes = Vec::with_capacity(items.len() * 2);
+    subdivide(&mut items, &mut nodes);
+    nodes
+}
+
+fn subdivide(items: &mut [BvNode], nodes: &mut Vec<BvNode>) {
+    if let [item] = items {
+        nodes.push(*item);
+        return;
+    }
+    let mut node = BvNode {
+        min: [u16::MAX; 3],
+        max: [0; 3],
+        i: 0,
+    };
+    for item in items.iter() {
+        for k in 0..3 {
+            node.min[k] = node.min[k].min(item.min[k]);
+            node.max[k] = node.max[k].max(item.max[k]);
+        }
+    }
+    let size = [0, 1, 2].map(|k| node.max[k] - node.min[k]);
+    let mut axis = 0;
+    if size[1] > size[axis] {
+        axis = 1;
+    }
+    if size[2] > size[axis] {
+        axis = 2;
+    }
+    items.sort_by_key(|item| item.min[axis]);
+
+    let at = nodes.len();
+    nodes.push(node);
+    let (left, right) = items.split_at_mut(items.len() / 2);
+    subdivide(left, nodes);
+    subdivide(right, nodes);
+    nodes[at].i = -((nodes.len() - at) as i32);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_u32(bytes: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+    }
+
+    fn read_u16(bytes: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes(bytes[at..at + 2].try_into().unwrap())
+    }
+
+    /// Two unit squares side by side along x, wound normal up, sharing the
+    /// edge from corner 2 to corner 3 of the first.
+    fn two_squares() -> PolyNavmesh {
+        PolyNavmesh {
+            vertices: vec![
+                0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 0.0, 1.0, 1.0, 0.0, 0.0, 2.0, 0.0, 1.0, 2.0,
+                0.5, 0.0,
+            ],
+            polys: vec![0, 1, 2, 3, 3, 2, 4, 5],
+            poly_sizes: vec![4, 4],
+            neighbors: vec![-1, -1, 1, -1, 0, -1, -1, -1],
+            poly_regions: vec![0, 0],
+            region_count: 1,
+            max_verts_per_poly: 6,
+        }
+    }
+
+    #[test]
+    fn two_squares_make_a_detour_tile() {
+        let navmesh = two_squares();
+        let agent = DetourAgent {
+            height: 2.0,
+            radius: 0.0,
+            climb: 0.5,
+        };
+        let bytes = navmesh_set(&navmesh, &[], &agent, 0.1).unwrap();
+        assert_eq!(&bytes[..4], b"TESM");
+        assert_eq!(read_u32(&bytes, 8), 1);
+        // One poly bit for two polygons.
+        assert_eq!(read_u32(&bytes, 40), 2);
+
+        let tile = &bytes[TILE_DATA_OFFSET..];
+        assert_eq!(read_u32(&bytes, 44) as usize, tile.len());
+        assert_eq!(&tile[..4], b"VAND");
+        assert_eq!(read_u32(tile, 4), NAVMESH_VERSION);
+        assert_eq!(read_u32(tile, 24), 2); // polyCount
+        assert_eq!(read_u32(tile, 28), 6); // vertCount
+        assert_eq!(read_u32(tile, 32), 8); // maxLinkCount
+        assert_eq!(read_u32(tile, 44), 4); // detailTriCount
+        assert_eq!(read_u32(tile, 48), 3); // bvNodeCount
+
+        let header = 100;
+        let polys = header + 6 * 12;
+        // First polygon reversed: 3, 2, 1, 0. Its shared edge 2 -> 3 is now
+        // edge 0 (3 -> 2), linked to polygon 1 (stored + 1).
+        let verts: Vec<u16> = (0..4).map(|k| read_u16(tile, polys + 4 + k * 2)).collect();
+        assert_eq!(verts, [3, 2, 1, 0]);
+        let neis: Vec<u16> = (0..4).map(|k| read_u16(tile, polys + 16 + k * 2)).collect();
+        assert_eq!(neis, [2, 0, 0, 0]);
+        assert_eq!(tile[polys + 30], 4);
+        // Second polygon: 5, 4, 2, 3; edge 2 (2 -> 3) leads back to polygon 0.
+        let second = polys + 32;
+        let neis: Vec<u16> = (0..4)
+            .map(|k| read_u16(tile, second + 16 + k * 2))
+            .collect();
+        assert_eq!(neis, [0, 0, 1, 0]);
+
+        let detail = polys + 2 * 32 + 8 * 12;
+        let tris = detail + 2 * 12;
+        assert_eq!(read_u32(tile, detail + 12 + 4), 2); // second triBase
+        assert_eq!(&tile[tris..tris + 8], &[0, 1, 2, 0b101, 0, 2, 3, 0b10100]);
+        let bv = tris + 4 * 4;
+        assert_eq!(tile.len(), bv + 4 * 16);
+        // Root spans both leaves and escapes past all three nodes.
+        assert_eq!(read_u32(tile, bv + 12) as i32, -3);
+        assert_eq!(read_u16(tile, bv + 6), 20); // max x: 2 m at 0.1 m
+        assert_eq!(read_u16(tile, bv + 8), 5); // max y: 0.5 m
+    }
+
+    #[test]
+    fn links_become_off_mesh_connections() {
+        let link = OffMeshLink {
+            index: 3,
+            start: [0.5, 0.0, 0.5],
+            end: [1.5, 0.0, 0.5],
+            radius: 0.25,
+            bidirectional: false,
+            start_face: 0,
+            end_face: 2,
+            start_poly: Some(0),
+            end_poly: Some(1),
+        };
+        let agent = DetourAgent {
+            height: 2.0,
+            radius: 0.0,
+            climb: 0.5,
+        };
+        let bytes = navmesh_set(&two_squares(), &[link], &agent, 0.1).unwrap();
+        // Three polygons still fit in two poly bits.
+        assert_eq!(read_u32(&bytes, 40), 4);
+        let tile = &bytes[TILE_DATA_OFFSET..];
+        assert_eq!(read_u32(tile, 24), 3); // polyCount
+        assert_eq!(read_u32(tile, 28), 8); // vertCount
+        assert_eq!(read_u32(tile, 32), 12); // maxLinkCount
+        assert_eq!(read_u32(tile, 36), 2); // detailMeshCount
+        assert_eq!(read_u32(tile, 52), 1); // offMeshConCount
+        assert_eq!(read_u32(tile, 56), 2); // offMeshBase
+
+        // The connection polygon after the two squares joins the link's ends.
+        let third = 100 + 8 * 12 + 2 * 32;
+        assert_eq!(read_u16(tile, third + 4), 6);
+        assert_eq!(read_u16(tile, third + 6), 7);
+        assert_eq!(tile[third + 30], 2);
+        assert_eq!(tile[third + 31], 0x40);
+
+        let con = tile.len() - 36;
+        let f32_at = |at: usize| f32::from_le_bytes(tile[at..at + 4].try_into().unwrap());
+        assert_eq!(f32_at(con + 12), 1.5);
+        assert_eq!(f32_at(con + 24), 0.25);
+        assert_eq!(read_u16(tile, con + 28), 2);
+        assert_eq!(&tile[con + 30..con + 32], &[0, 0xff]);
+        assert_eq!(read_u32(tile, con + 32), 3);
+    }
+
+    #[test]
+    fn large_polygons_are_rejected() {
+        let navmesh = PolyNavmesh {
+            vertices: vec![0.0; 21],
+            polys: (0..7).collect(),
+            poly_sizes: vec![7],
+            neighbors: vec![-1; 7],
+            poly_regions: vec![0],
+            region_count: 1,
+            max_verts_per_poly: 7,
+        };
+        let agent = DetourAgent {
+            height: 2.0,
+            radius: 0.0,
+            climb: 0.5,
+        };
+        assert!(navmesh_set(&navmesh, &[], &agent, 0.1).is_err());
+    }
+}
@@ -0,0 +1,153 @@
+//! Mesh-to-splat deviation overlay (`emit_deviation`).
+//!
+//! A reconstruction can look plausible and still drift from the capture:
+//! Poisson closes holes with smooth bubbles, decimation and smoothing round
+//! off steps. [`measure`] gives every output vertex its distance to the
+//! nearest splat centre and a color on a green-to-red ramp, so a viewer can
+//! paint the mesh with it and see at a glance where the surface has no
+//! support in the scan.
+
+use std::collections::HashMap;
+
+use crate::preview::ramp;
+use crate::splat::PointNormal;
+use crate::MeshSettings;
+
+/// Distance mapped to the red end of the ramp when `deviation_range` is unset.
+pub const DEFAULT_RANGE: f64 = 0.1;
+
+/// Grid rings searched around a vertex, in cells of `range` edge, before it
+/// counts as unsupported.
+const MAX_RINGS: i64 = 4;
+
+/// Close to the capture, half the range, at or past the range.
+const DEVIATION_RAMP: [[f64; 3]; 3] = [[0.10, 0.70, 0.25], [0.95, 0.80, 0.15], [0.85, 0.15, 0.10]];
+
+/// `deviation_range` must be a positive finite length when set.
+pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+    match settings.deviation_range {
+        Some(range) if !(range.is_finite() && range > 0.0) => {
+            Err(format!("Invalid deviation_range: {}", range))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Per-vertex deviation of a mesh from its splats.
+pub struct Deviation {
+    /// Metres to the nearest splat centre, one per vertex; -1 when no splat
+    /// lies within [`MAX_RINGS`] cells.
+    pub distances: Vec<f32>,
+    /// RGB in `[0, 1]`, three per vertex: `distance / range` along the ramp,
+    /// red for unsupported vertices.
+    pub colors: Vec<f32>,
+    /// Mean and largest distance over the supported vertices (0 when none).
+    pub mean: f64,
+    pub max: f64,
+}
+
+/// Measure `vertices` (xyz triplets) against the splat centres in `points`,
+/// coloring distances in `[0, range]`. Points are hashed into cubes of edge
+/// `range`; the search widens ring by ring until the best distance found is
+/// no larger than the radius the visited rings are sure to cover.
+pub fn measure(vertices: &[f32], points: &[PointNormal], range: f64) -> Deviation {
+    let cell_of = |x: f64, y: f64, z: f64| {
+        (
+            (x / range).floor() as i64,
+            (y / range).floor() as i64,
+            (z / range).floor() as i64,
+        )
+    };
+    let mut grid: HashMap<(i64, i64, i64), Vec<u32>> = HashMap::new();
+    for (i, p) in points.iter().enumerate() {
+        if p.point.x.is_finite() && p.point.y.is_finite() && p.point.z.is_finite() {
+            grid.entry(cell_of(p.point.x, p.point.y, p.point.z))
+                .or_default()
+                .push(i as u32);
+        }
+    }
+
+    let vertex_count = vertices.len() / 3;
+    let mut distances = Vec::with_capacity(vertex_count);
+    let mut colors = Vec::with_capacity(vertex_count * 3);
+    let (mut sum, mut max, mut supported) = (0.0_f64, 0.0_f64, 0usize);
+    for v in vertices.chunks_exact(3) {
+        let (x, y, z) = (v[0] as f64, v[1] as f64, v[2] as f64);
+        let (cx, cy, cz) = cell_of(x, y, z);
+        let mut best_sq = f64::INFINITY;
+        for ring in 0..=MAX_RINGS {
+            for dx in -ring..=ring {
+                for dy in -ring..=ring {
+                    for dz in -ring..=ring {
+                        // Only the shell: inner cells were visited by earlier rings.
+                        if dx.abs().max(dy.abs()).max(dz.abs()) != ring {
+                            continue;
+                        }
+                        let Some(members) = grid.get(&(cx + dx, cy + dy, cz + dz)) else {
+                            continue;
+                        };
+                        for &i in members {
+                            let p = &points[i as usize].point;
+                            let d_sq = (p.x - x).powi(2) + (p.y - y).powi(2) + (p.z - z).powi(2);
+                            best_sq = best_sq.min(d_sq);
+                        }
+                    }
+                }
+            }
+            // Rings 0..=ring hold every point within `ring * range`.
+            let covered = ring as f64 * range;
+            if best_sq <= covered * covered {
+                break;
+            }
+        }
+        if best_sq.is_finite() {
+            let distance = best_sq.sqrt();
+            sum += distance;
+            max = max.max(distance);
+            supported += 1;
+            distances.push(distance as f32);
+            colors.extend(ramp(&DEVIATION_RAMP, (distance / range).min(1.0)).map(|c| c as f32));
+        } else {
+            distances.push(-1.0);
+            colors.extend(DEVIATION_RAMP[2].map(|c| c as f32));
+        }
+    }
+    Deviation {
+        distances,
+        colors,
+        mean: if supported > 0 {
+            sum / supported as f64
+        } else {
+            0.0
+        },
+        max,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::splat::test_splat;
+
+    #[test]
+    fn distances_reach_the_nearest_splat_across_cells() {
+        let points = [0.0, 0.25, 1.0]
+            .map(|x| test_splat([x, 0.0, 0.0], [0.0, 1.0, 0.0]))
+            .to_vec();
+        // On a splat, between two, out past the range, and off the grid.
+        let vertices: [f32; 12] = [
+            0.0, 0.0, 0.0, 0.15, 0.0, 0.0, 0.25, 0.32, 0.0, 50.0, 0.0, 0.0,
+        ];
+        let deviation = measure(&vertices, &points, 0.1);
+        let d = &deviation.distances;
+        assert!(d[0].abs() < 1e-6);
+        assert!((d[1] - 0.1).abs() < 1e-5, "{}", d[1]);
+        assert!((d[2] - 0.32).abs() < 1e-5, "{}", d[2]);
+        assert_eq!(d[3], -1.0);
+        // Green on the capture, red past the range and off it.
+        assert!(deviation.colors[1] > deviation.colors[0]);
+        assert!(deviation.colors[6] > deviation.colors[7]);
+        assert_eq!(&deviation.colors[6..9], &deviation.colors[9..12]);
+        assert!((deviation.max - 0.32).abs() < 1e-5);
+    }
+}
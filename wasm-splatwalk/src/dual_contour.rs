@@ -0,0 +1,531 @@
+//! Dual contouring over a signed field built from the splats (`mode = 8`).
+//!
+//! Mode 3 puts its vertices on grid edges, so a wall corner or a table edge
+//! that falls inside a cell comes out chamfered, and Poisson rounds it off
+//! entirely. Fusing distances also blurs the field itself: outside a convex
+//! edge the splats of both faces vote, and the surface bulges. Mode 8 instead
+//! gives every grid point the signed distance to the tangent plane of its
+//! nearest splat, which stays exact on both sides of an edge, and places one
+//! vertex inside each cell the surface crosses. The vertex minimises the
+//! quadric error against the cell's hermite data: the zero crossings on its
+//! edges, each paired with the normal of the splat nearest to it. Where
+//! planes in a cell meet at more than `dc_feature_angle` the quadric pins the
+//! vertex to their intersection, which rebuilds the crease. Planes closer
+//! than that are treated as one, so scan noise does not pull spikes out of
+//! flat walls.
+//!
+//! Every grid edge with a sign change emits a quad joining the vertices of
+//! the four cells around it, facing the positive (free-space) side. A vertex
+//! that the quadric places outside its cell is clamped back to the cell,
+//! which keeps the quads from folding over their neighbours.
+
+use std::collections::HashMap;
+
+use nalgebra::{Matrix3, SymmetricEigen, Vector3};
+
+use crate::splat::PointNormal;
+use crate::tsdf::{TsdfOptions, Viewpoint};
+use crate::MeshSettings;
+
+#[derive(Clone, Debug)]
+pub struct DualContourOptions {
+    pub voxel_size: f64,
+    /// Grid points farther than this from every splat are unobserved.
+    pub truncation: f64,
+    /// Splats below this opacity are ignored.
+    pub min_opacity: f64,
+    pub viewpoint: Viewpoint,
+    /// Quadric eigenvalues below this fraction of the largest are dropped.
+    /// Two planes meeting at angle `a` give a ratio of `tan(a / 2)^2`.
+    pub min_eigen_ratio: f64,
+}
+
+impl DualContourOptions {
+    /// Options for a cloud spanning `min..max`: the mode 3 grid
+    /// (`tsdf_voxel_size`, `tsdf_truncation_cells`) plus `dc_feature_angle`
+    /// (default 30 degrees).
+    pub fn from_settings(settings: &MeshSettings, min: [f64; 3], max: [f64; 3]) -> Self {
+        let grid = TsdfOptions::from_settings(settings, min, max);
+        Self {
+            voxel_size: grid.voxel_size,
+            truncation: grid.truncation,
+            min_opacity: grid.min_weight,
+            viewpoint: grid.viewpoint,
+            min_eigen_ratio: (settings.dc_feature_angle.unwrap_or(30.0).to_radians() / 2.0)
+                .tan()
+                .powi(2),
+        }
+    }
+}
+
+/// Checks `dc_feature_angle`.
+pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+    match settings.dc_feature_angle {
+        Some(angle) if !(angle.is_finite() && angle > 0.0 && angle < 90.0) => Err(format!(
+            "Invalid dc_feature_angle: {}. Expected degrees in (0, 90).",
+            angle
+        )),
+        _ => Ok(()),
+    }
+}
+
+pub struct DualContourBuild {
+    pub vertices: Vec<f32>,
+    pub indices: Vec<u32>,
+    pub voxel_size: f64,
+    pub dims: [usize; 3],
+    pub observed_voxels: usize,
+    /// Vertices solved from two or more independent planes.
+    pub sharp_vertices: usize,
+    /// Vertices the quadric put outside their cell.
+    pub clamped_vertices: usize,
+}
+
+/// A zero crossing and the surface normal there.
+type Hermite = (Vector3<f64>, Vector3<f64>);
+
+/// Nearest-tangent-plane distances on a grid over the cloud.
+struct Field {
+    origin: Vector3<f64>,
+    voxel_size: f64,
+    dims: [usize; 3],
+    /// Signed distance to the nearest splat's plane.
+    sdf: Vec<f32>,
+    /// Squared distance to the nearest splat, infinite when unobserved.
+    nearest_sq: Vec<f32>,
+    /// Index into `splats` of the nearest splat.
+    nearest: Vec<u32>,
+    /// Centre and oriented unit normal of every usable splat.
+    splats: Vec<(Vector3<f64>, Vector3<f64>)>,
+}
+
+impl Field {
+    fn idx(&self, x: usize, y: usize, z: usize) -> usize {
+        x + self.dims[0] * (y + self.dims[1] * z)
+    }
+
+    fn point(&self, x: usize, y: usize, z: usize) -> Vector3<f64> {
+        self.origin + Vector3::new(x as f64, y as f64, z as f64) * self.voxel_size
+    }
+
+    fn observed(&self, i: usize) -> bool {
+        self.nearest_sq[i].is_finite()
+    }
+}
+
+/// Build the field over the bounds of `points` and contour its zero surface.
+/// `progress` receives `(stage, fraction)` for `dc_field` and `dc_mesh`.
+pub fn reconstruct(
+    points: &[PointNormal],
+    options: &DualContourOptions,
+    progress: &dyn Fn(&str, f64),
+) -> DualContourBuild {
+    let mut build = DualContourBuild {
+        vertices: vec![],
+        indices: vec![],
+        voxel_size: options.voxel_size,
+        dims: [0; 3],
+        observed_voxels: 0,
+        sharp_vertices: 0,
+        clamped_vertices: 0,
+    };
+    let Some(field) = build_field(points, options, progress) else {
+        return build;
+    };
+    build.dims = field.dims;
+    build.observed_voxels = field.nearest_sq.iter().filter(|d| d.is_finite()).count();
+    let [nx, ny, nz] = field.dims;
+
+    let mut contour = Contour {
+        field: &field,
+        min_eigen_ratio: options.min_eigen_ratio,
+        crossings: HashMap::new(),
+        cell_vertex: HashMap::new(),
+        build: &mut build,
+    };
+    for z in 0..nz {
+        progress("dc_mesh", z as f64 / nz as f64);
+        for y in 0..ny {
+            for x in 0..nx {
+                for axis in 0..3 {
+                    contour.quad([x, y, z], axis);
+                }
+            }
+        }
+    }
+    progress("dc_mesh", 1.0);
+    build
+}
+
+fn build_field(
+    points: &[PointNormal],
+    options: &DualContourOptions,
+    progress: &dyn Fn(&str, f64),
+) -> Option<Field> {
+    let mut min = [f64::MAX; 3];
+    let mut max = [f64::MIN; 3];
+    for p in points {
+        let c = [p.point.x, p.point.y, p.point.z];
+        for axis in 0..3 {
+            min[axis] = min[axis].min(c[axis]);
+            max[axis] = max[axis].max(c[axis]);
+        }
+    }
+    let viewpoint = options.viewpoint.position(min, max);
+    let splats: Vec<(Vector3<f64>, Vector3<f64>)> = points
+        .iter()
+        .filter_map(|p| {
+            let norm = p.normal.norm();
+            if p.opacity < options.min_opacity || !norm.is_finite() || norm < 1e-9 {
+                return None;
+            }
+            let centre = p.point.coords;
+            Some((
+                centre,
+                options.viewpoint.orient(viewpoint, centre, p.normal / norm),
+            ))
+        })
+        .collect();
+    if splats.is_empty() {
+        return None;
+    }
+
+    // Pad by the band so surfaces on the bounds still get both signs.
+    let voxel = options.voxel_size;
+    let pad = options.truncation + voxel;
+    let origin = Vector3::new(min[0] - pad, min[1] - pad, min[2] - pad);
+    let dims =
+        [0, 1, 2].map(|a| ((max[a] - min[a] + 2.0 * pad).max(0.0) / voxel).ceil() as usize + 1);
+    let len: usize = dims.iter().product();
+    let mut field = Field {
+        origin,
+        voxel_size: voxel,
+        dims,
+        sdf: vec![0.0; len],
+        nearest_sq: vec![f32::INFINITY; len],
+        nearest: vec![u32::MAX; len],
+        splats,
+    };
+
+    let trunc = options.truncation;
+    let reach = (trunc / voxel).ceil() as isize;
+    let report_every = (field.splats.len() / 50).max(1);
+    for si in 0..field.splats.len() {
+        if si % report_every == 0 {
+            progress("dc_field", si as f64 / field.splats.len() as f64);
+        }
+        let (centre, normal) = field.splats[si];
+        let base = (centre - origin) / voxel;
+        let (bx, by, bz) = (
+            base.x.round() as isize,
+            base.y.round() as isize,
+            base.z.round() as isize,
+        );
+        for z in (bz - reach).max(0)..=(bz + reach).min(dims[2] as isize - 1) {
+            for y in (by - reach).max(0)..=(by + reach).min(dims[1] as isize - 1) {
+                for x in (bx - reach).max(0)..=(bx + reach).min(dims[0] as isize - 1) {
+                    let (x, y, z) = (x as usize, y as usize, z as usize);
+                    let offset = field.point(x, y, z) - centre;
+                    let distance_sq = offset.norm_squared();
+                    let idx = field.idx(x, y, z);
+                    if distance_sq > trunc * trunc
+                        || distance_sq >= f64::from(field.nearest_sq[idx])
+                    {
+                        continue;
+                    }
+                    field.nearest_sq[idx] = distance_sq as f32;
+                    field.nearest[idx] = si as u32;
+                    field.sdf[idx] = offset.dot(&normal) as f32;
+                }
+            }
+        }
+    }
+    Some(field)
+}
+
+struct Contour<'a> {
+    field: &'a Field,
+    min_eigen_ratio: f64,
+    /// Hermite data per grid edge, keyed by its lower grid point and axis.
+    crossings: HashMap<(usize, usize), Option<Hermite>>,
+    /// Vertex per cell, keyed by its lower grid point.
+    cell_vertex: HashMap<usize, Option<u32>>,
+    build: &'a mut DualContourBuild,
+}
+
+impl Contour<'_> {
+    /// The quad around the grid edge from `g` along `axis`, if the surface
+    /// crosses it and all four cells around it are observed.
+    fn quad(&mut self, g: [usize; 3], axis: usize) {
+        let mut h = g;
+        h[axis] += 1;
+        if h[axis] >= self.field.dims[axis] {
+            return;
+        }
+        let (a, b) = (
+            self.field.idx(g[0], g[1], g[2]),
+            self.field.idx(h[0], h[1], h[2]),
+        );
+        if !self.field.observed(a) || !self.field.observed(b) {
+            return;
+        }
+        let (va, vb) = (self.field.sdf[a], self.field.sdf[b]);
+        if (va < 0.0) == (vb < 0.0) {
+            return;
+        }
+        let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+        if g[u] == 0 || g[v] == 0 {
+            return;
+        }
+        let mut quad = [0u32; 4];
+        for (k, (du, dv)) in [(1, 1), (0, 1), (0, 0), (1, 0)].into_iter().enumerate() {
+            let mut cell = g;
+            cell[u] -= du;
+            cell[v] -= dv;
+            if cell[u] + 1 >= self.field.dims[u] || cell[v] + 1 >= self.field.dims[v] {
+                return;
+            }
+            let Some(vertex) = self.vertex(cell) else {
+                return;
+            };
+            quad[k] = vertex;
+        }
+
+        // Face the free-space side along the edge, split on the shorter
+        // diagonal.
+        let mut outward = Vector3::zeros();
+        outward[axis] = if va < 0.0 { 1.0 } else { -1.0 };
+        let [p0, p1, p2, p3] = quad.map(|i| vertex_at(&self.build.vertices, i));
+        let triangles = if (p2 - p0).norm_squared() <= (p3 - p1).norm_squared() {
+            [[quad[0], quad[1], quad[2]], [quad[0], quad[2], quad[3]]]
+        } else {
+            [[quad[0], quad[1], quad[3]], [quad[1], quad[2], quad[3]]]
+        };
+        for mut tri in triangles {
+            let [a, b, c] = tri.map(|i| vertex_at(&self.build.vertices, i));
+            if (b - a).cross(&(c - a)).dot(&outward) < 0.0 {
+                tri.swap(1, 2);
+            }
+            self.build.indices.extend_from_slice(&tri);
+        }
+    }
+
+    /// Zero crossing and hermite normal on the grid edge from `g` along
+    /// `axis`: the normal of whichever end's nearest splat is closer to the
+    /// crossing.
+    fn crossing(&mut self, g: [usize; 3], axis: usize) -> Option<Hermite> {
+        let key = (self.field.idx(g[0], g[1], g[2]), axis);
+        if let Some(cached) = self.crossings.get(&key) {
+            return *cached;
+        }
+        let mut h = g;
+        h[axis] += 1;
+        let (a, b) = (key.0, self.field.idx(h[0], h[1], h[2]));
+        let (va, vb) = (self.field.sdf[a], self.field.sdf[b]);
+        let hermite = ((va < 0.0) != (vb < 0.0)).then(|| {
+            let t = f64::from(va / (va - vb)).clamp(0.0, 1.0);
+            let (pa, pb) = (
+                self.field.point(g[0], g[1], g[2]),
+                self.field.point(h[0], h[1], h[2]),
+            );
+            let point = pa + (pb - pa) * t;
+            let [sa, sb] = [a, b].map(|i| self.field.splats[self.field.nearest[i] as usize]);
+            let (_, normal) = if (sa.0 - point).norm_squared() <= (sb.0 - point).norm_squared() {
+                sa
+            } else {
+                sb
+            };
+            (point, normal)
+        });
+        self.crossings.insert(key, hermite);
+        hermite
+    }
+
+    /// The vertex of the cell whose lower corner is `c`, solved on first use.
+    /// `None` when a corner is unobserved.
+    fn vertex(&mut self, c: [usize; 3]) -> Option<u32> {
+        let key = self.field.idx(c[0], c[1], c[2]);
+        if let Some(cached) = self.cell_vertex.get(&key) {
+            return *cached;
+        }
+        let corners_observed = (0..8).all(|k| {
+            let [x, y, z] = [0, 1, 2].map(|axis| c[axis] + ((k >> axis) & 1));
+            self.field.observed(self.field.idx(x, y, z))
+        });
+        let vertex = if corners_observed {
+            let mut hermite = Vec::with_capacity(12);
+            for axis in 0..3 {
+                let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+                for (du, dv) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let mut g = c;
+                    g[u] += du;
+                    g[v] += dv;
+                    hermite.extend(self.crossing(g, axis));
+                }
+            }
+            let lo = self.field.point(c[0], c[1], c[2]);
+            let hi = lo + Vector3::repeat(self.field.voxel_size);
+            let (p, sharp) = solve_qef(&hermite, self.min_eigen_ratio);
+            let clamped = p.zip_zip_map(&lo, &hi, |p, lo, hi| p.clamp(lo, hi));
+            self.build.sharp_vertices += usize::from(sharp);
+            self.build.clamped_vertices += usize::from(clamped != p);
+            let index = (self.build.vertices.len() / 3) as u32;
+            self.build.vertices.extend_from_slice(&[
+                clamped.x as f32,
+                clamped.y as f32,
+                clamped.z as f32,
+            ]);
+            Some(index)
+        } else {
+            None
+        };
+        self.cell_vertex.insert(key, vertex);
+        vertex
+    }
+}
+
+/// The point nearest the mass point of `hermite` that minimises the squared
+/// distance to its planes, and whether two or more independent planes fixed
+/// it. Directions with eigenvalues under `min_eigen_ratio` of the largest
+/// stay at the mass point.
+fn solve_qef(hermite: &[Hermite], min_eigen_ratio: f64) -> (Vector3<f64>, bool) {
+    let mass = hermite.iter().map(|(p, _)| p).sum::<Vector3<f64>>() / hermite.len() as f64;
+    let mut ata = Matrix3::zeros();
+    let mut atb = Vector3::zeros();
+    for (p, n) in hermite {
+        ata += n * n.transpose();
+        atb += n * n.dot(&(p - mass));
+    }
+
+    let eigen = SymmetricEigen::new(ata);
+    let largest = eigen.eigenvalues.max();
+    let mut offset = Vector3::zeros();
+    let mut rank = 0;
+    for (k, &lambda) in eigen.eigenvalues.iter().enumerate() {
+        if lambda <= largest * min_eigen_ratio || lambda <= 0.0 {
+            continue;
+        }
+        let axis = eigen.eigenvectors.column(k);
+        offset += axis * (axis.dot(&atb) / lambda);
+        rank += 1;
+    }
+    (mass + offset, rank >= 2)
+}
+
+fn vertex_at(vertices: &[f32], index: u32) -> Vector3<f64> {
+    let i = index as usize * 3;
+    Vector3::new(
+        f64::from(vertices[i]),
+        f64::from(vertices[i + 1]),
+        f64::from(vertices[i + 2]),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Point3;
+
+    /// Splats on the faces of the unit cube centred at the origin, normals
+    /// out.
+    fn cube(spacing: f64) -> Vec<PointNormal> {
+        let steps = (1.0 / spacing).round() as usize;
+        let mut points = Vec::new();
+        for axis in 0..3 {
+            for side in [-0.5, 0.5] {
+                for i in 0..=steps {
+                    for j in 0..=steps {
+                        let mut p = Vector3::repeat(0.0);
+                        p[axis] = side;
+                        p[(axis + 1) % 3] = -0.5 + i as f64 * spacing;
+                        p[(axis + 2) % 3] = -0.5 + j as f64 * spacing;
+                        let mut n = Vector3::zeros();
+                        n[axis] = side.signum();
+                        points.push(PointNormal {
+                            point: Point3::from(p),
+                            normal: n,
+                            scale: Vector3::new(0.02, 0.02, 0.005),
+                            opacity: 1.0,
+                            color: [0.5; 3],
+                            index: points.len() as u32,
+                        });
+                    }
+                }
+            }
+        }
+        points
+    }
+
+    fn options() -> DualContourOptions {
+        DualContourOptions {
+            voxel_size: 0.1,
+            truncation: 0.25,
+            min_opacity: 0.1,
+            viewpoint: Viewpoint::Outside,
+            min_eigen_ratio: 15f64.to_radians().tan().powi(2),
+        }
+    }
+
+    #[test]
+    fn cube_keeps_its_corners() {
+        let points = cube(0.02);
+        let build = reconstruct(&points, &options(), &|_, _| {});
+        assert!(!build.indices.is_empty());
+        assert!(build.sharp_vertices > 0);
+
+        let vertices: Vec<Vector3<f64>> = (0..build.vertices.len() / 3)
+            .map(|i| vertex_at(&build.vertices, i as u32))
+            .collect();
+        for v in &vertices {
+            let out = v.abs().max();
+            assert!((out - 0.5).abs() < 0.05, "vertex {:?} off the cube", v);
+        }
+        // Every corner has a vertex on it; grid marching would chamfer it.
+        for k in 0..8 {
+            let corner = Vector3::new(0, 1, 2).map(|a| if k >> a & 1 == 0 { -0.5 } else { 0.5 });
+            let nearest = vertices
+                .iter()
+                .map(|v| (v - corner).norm())
+                .fold(f64::INFINITY, f64::min);
+            assert!(
+                nearest < 0.03,
+                "corner {:?} is {} from the mesh",
+                corner,
+                nearest
+            );
+        }
+
+        // Faces point away from the centre.
+        for tri in build.indices.chunks(3) {
+            let [a, b, c] = [0, 1, 2].map(|k| vertices[tri[k] as usize]);
+            let normal = (b - a).cross(&(c - a));
+            if normal.norm() > 1e-9 {
+                assert!(normal.dot(&(a + b + c)) > 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn feature_angle_decides_the_crease() {
+        // A floor (y = 0) at x = 1 meeting a plane tilted 40 degrees at x = 0:
+        // the planes cross on the line x = y = 0.
+        let tilt = 40f64.to_radians();
+        let tilted = Vector3::new(tilt.sin(), tilt.cos(), 0.0);
+        let hermite = [
+            (Vector3::new(0.0, 0.0, 0.0), tilted),
+            (Vector3::new(0.0, 0.0, 1.0), tilted),
+            (Vector3::new(1.0, 0.0, 0.0), Vector3::y()),
+            (Vector3::new(1.0, 0.0, 1.0), Vector3::y()),
+        ];
+        let ratio = |degrees: f64| (degrees.to_radians() / 2.0).tan().powi(2);
+
+        let (p, sharp) = solve_qef(&hermite, ratio(30.0));
+        assert!(sharp);
+        assert!((p - Vector3::new(0.0, 0.0, 0.5)).norm() < 1e-9, "{:?}", p);
+
+        // Above 40 degrees the two are one noisy plane: no crease.
+        let (p, sharp) = solve_qef(&hermite, ratio(60.0));
+        assert!(!sharp);
+        assert!(p.x > 0.4, "{:?}", p);
+    }
+}
@@ -0,0 +1,390 @@
+//! Splat ellipsoids for debugging the splat filters.
+//!
+//! With only the finished mesh to look at, tuning `min_alpha`, `max_scale`
+//! and the region rules is guesswork. `build_splat_ellipsoids` shows the
+//! splats themselves. It takes the `ellipsoid_count` largest (or most opaque)
+//! splats and returns them as low-poly ellipsoids. Each one is tagged as kept
+//! by the filters or with the reason it was dropped, so a debug view can
+//! colour them and show what the mesher sees as solid.
+//!
+//! Each ellipsoid is the unit [`template`] sphere mapped through the splat's
+//! instance matrix. The matrix scales the sphere by the gaussian's standard
+//! deviations, turns it by the splat's rotation and moves it to the splat's
+//! centre in the bake frame. Hosts draw the matrices as GPU instances of the
+//! template, or ask for `ellipsoid_mesh` to get the ellipsoids merged into
+//! one mesh.
+
+use std::cmp::Ordering;
+
+use nalgebra::{Matrix3, Point3, Vector3};
+use serde::Serialize;
+
+use crate::output_space;
+use crate::{CoordinateSpace, MeshBuffers, MeshSettings};
+
+/// Most ellipsoids one call returns.
+pub const MAX_ELLIPSOIDS: usize = 100_000;
+
+/// What the filters did with a splat. The codes are its `verdicts` byte.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SplatVerdict {
+    Kept = 0,
+    /// Outside `region_min`/`region_max` or the region rules.
+    OutsideRegion = 1,
+    /// At most `min_alpha` opaque, or an axis at least `max_scale` long.
+    FaintOrLarge = 2,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EllipsoidRank {
+    /// By volume, the product of the three standard deviations.
+    Largest,
+    /// By opacity.
+    Opaque,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EllipsoidFilter {
+    All,
+    Kept,
+    Discarded,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct EllipsoidOptions {
+    pub count: usize,
+    pub rank: EllipsoidRank,
+    pub filter: EllipsoidFilter,
+    pub mesh: bool,
+}
+
+impl EllipsoidOptions {
+    pub fn from_settings(settings: &MeshSettings) -> Self {
+        Self {
+            count: settings.ellipsoid_count.unwrap_or(2000),
+            rank: match settings.ellipsoid_rank.as_deref() {
+                Some("opaque") => EllipsoidRank::Opaque,
+                _ => EllipsoidRank::Largest,
+            },
+            filter: match settings.ellipsoid_filter.as_deref() {
+                Some("kept") => EllipsoidFilter::Kept,
+                Some("discarded") => EllipsoidFilter::Discarded,
+                _ => EllipsoidFilter::All,
+            },
+            mesh: settings.ellipsoid_mesh.unwrap_or(false),
+        }
+    }
+
+    fn admits(&self, verdict: SplatVerdict) -> bool {
+        match self.filter {
+            EllipsoidFilter::All => true,
+            EllipsoidFilter::Kept => verdict == SplatVerdict::Kept,
+            EllipsoidFilter::Discarded => verdict != SplatVerdict::Kept,
+        }
+    }
+}
+
+/// `ellipsoid_count` must be 1..=[`MAX_ELLIPSOIDS`], `ellipsoid_rank` one of
+/// `largest` / `opaque`, and `ellipsoid_filter` one of `all` / `kept` /
+/// `discarded`.
+pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+    if let Some(count) = settings.ellipsoid_count {
+        if !(1..=MAX_ELLIPSOIDS).contains(&count) {
+            return Err(format!(
+                "Invalid ellipsoid_count: {}. Expected 1 to {}.",
+                count, MAX_ELLIPSOIDS
+            ));
+        }
+    }
+    if let Some(rank) = settings.ellipsoid_rank.as_deref() {
+        if !matches!(rank, "largest" | "opaque") {
+            return Err(format!(
+                "Invalid ellipsoid_rank: {}. Expected largest or opaque.",
+                rank
+            ));
+        }
+    }
+    if let Some(filter) = settings.ellipsoid_filter.as_deref() {
+        if !matches!(filter, "all" | "kept" | "discarded") {
+            return Err(format!(
+                "Invalid ellipsoid_filter: {}. Expected all, kept or discarded.",
+                filter
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The map ingest applies to splat positions and normals before the bake
+/// frame (see `parse_splats`): the `z-up` preset's inverse, then `flip_y`.
+pub fn ingest_frame(settings: &MeshSettings) -> Matrix3<f64> {
+    let to_y_up = output_space::ingest_transform(settings);
+    let column = |axis: usize| {
+        let mut v = [0.0; 3];
+        v[axis] = 1.0;
+        let mut v = to_y_up.as_ref().map_or(v, |t| t.unapply(v));
+        if output_space::flip_y(settings) {
+            v[1] = -v[1];
+        }
+        Vector3::from(v)
+    };
+    Matrix3::from_columns(&[column(0), column(1), column(2)])
+}
+
+/// One splat that passed ingest, in the bake frame.
+pub struct SplatShape {
+    pub center: Point3<f64>,
+    /// Columns are the ellipsoid's semi-axes: unit directions times the
+    /// gaussian's standard deviations.
+    pub axes: Matrix3<f64>,
+    /// Opacity logit.
+    pub opacity: f64,
+    pub color: [f32; 3],
+    pub index: u32,
+    pub verdict: SplatVerdict,
+}
+
+impl SplatShape {
+    fn rank(&self, rank: EllipsoidRank) -> f64 {
+        match rank {
+            EllipsoidRank::Largest => {
+                self.axes.column(0).norm() * self.axes.column(1).norm() * self.axes.column(2).norm()
+            }
+            EllipsoidRank::Opaque => self.opacity,
+        }
+    }
+}
+
+/// Result of `build_splat_ellipsoids`.
+#[derive(Serialize)]
+pub struct SplatEllipsoids {
+    pub api_version: u8,
+    pub semver: String,
+    pub capabilities: Vec<String>,
+    /// Ellipsoids returned, largest (or most opaque) first.
+    pub count: usize,
+    /// Splats the filters kept and dropped, over the whole cloud.
+    pub splats_kept: usize,
+    pub splats_discarded: usize,
+    /// A column-major 4x4 matrix per ellipsoid mapping the unit template onto
+    /// the splat, with the translation in elements 12 to 14.
+    pub matrices: Vec<f32>,
+    /// Splat RGB in `[0, 1]`, three values per ellipsoid.
+    pub colors: Vec<f32>,
+    /// One [`SplatVerdict`] code per ellipsoid.
+    pub verdicts: serde_bytes::ByteBuf,
+    /// Each ellipsoid's splat, by its position in the source file.
+    pub source_indices: Vec<u32>,
+    /// The low-poly unit sphere the matrices place.
+    pub template: MeshBuffers,
+    /// `ellipsoid_mesh`: every ellipsoid in one mesh, with the template's
+    /// vertex count per ellipsoid in `count` order and the splat colours.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mesh: Option<MeshBuffers>,
+    pub space: CoordinateSpace,
+}
+
+/// A unit sphere: an octahedron split once, 18 vertices and 32 triangles
+/// wound counter-clockwise seen from outside.
+pub fn template() -> (Vec<Vector3<f64>>, Vec<[u32; 3]>) {
+    let mut vertices: Vec<Vector3<f64>> = vec![
+        Vector3::x(),
+        -Vector3::x(),
+        Vector3::y(),
+        -Vector3::y(),
+        Vector3::z(),
+        -Vector3::z(),
+    ];
+    let mut faces = Vec::with_capacity(8);
+    for sx in [0u32, 1] {
+        for sy in [2u32, 3] {
+            for sz in [4u32, 5] {
+                // Odd sign flips mirror the face, which reverses its winding.
+                if (sx + sy + sz) % 2 == 0 {
+                    faces.push([sx, sy, sz]);
+                } else {
+                    faces.push([sx, sz, sy]);
+                }
+            }
+        }
+    }
+    let mut midpoints = std::collections::HashMap::new();
+    let mut midpoint = |a: u32, b: u32, vertices: &mut Vec<Vector3<f64>>| {
+        *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+            vertices.push((vertices[a as usize] + vertices[b as usize]).normalize());
+            vertices.len() as u32 - 1
+        })
+    };
+    let mut split = Vec::with_capacity(faces.len() * 4);
+    for [a, b, c] in faces {
+        let ab = midpoint(a, b, &mut vertices);
+        let bc = midpoint(b, c, &mut vertices);
+        let ca = midpoint(c, a, &mut vertices);
+        split.extend([[a, ab, ca], [ab, b, bc], [ca, bc, c], [ab, bc, ca]]);
+    }
+    (vertices, split)
+}
+
+/// The top ellipsoids of `shapes` under `options`.
+pub fn build(
+    shapes: Vec<SplatShape>,
+    options: &EllipsoidOptions,
+    space: CoordinateSpace,
+) -> SplatEllipsoids {
+    let splats_kept = shapes
+        .iter()
+        .filter(|s| s.verdict == SplatVerdict::Kept)
+        .count();
+    let splats_discarded = shapes.len() - splats_kept;
+    let mut chosen: Vec<SplatShape> = shapes
+        .into_iter()
+        .filter(|s| options.admits(s.verdict))
+        .collect();
+    let order = |a: &SplatShape, b: &SplatShape| {
+        b.rank(options.rank)
+            .partial_cmp(&a.rank(options.rank))
+            .unwrap_or(Ordering::Equal)
+            .then(a.index.cmp(&b.index))
+    };
+    if chosen.len() > options.count {
+        chosen.select_nth_unstable_by(options.count, order);
+        chosen.truncate(options.count);
+    }
+    chosen.sort_unstable_by(order);
+
+    let (sphere, faces) = template();
+    let mut matrices = Vec::with_capacity(chosen.len() * 16);
+    let mut colors = Vec::with_capacity(chosen.len() * 3);
+    let mut verdicts = Vec::with_capacity(chosen.len());
+    let mut source_indices = Vec::with_capacity(chosen.len());
+    let mut merged = options.mesh.then(|| {
+        (
+            Vec::with_capacity(chosen.len() * sphere.len() * 3),
+            Vec::with_capacity(chosen.len() * faces.len() * 3),
+            Vec::with_capacity(chosen.len() * sphere.len() * 3),
+        )
+    });
+    for shape in &chosen {
+        let mut axes = shape.axes;
+        // `flip_y` mirrors the frame. An ellipsoid is symmetric, so turning
+        // one axis round keeps it and keeps the matrix a proper rotation.
+        if axes.determinant() < 0.0 {
+            axes.set_column(2, &-axes.column(2));
+        }
+        for column in 0..3 {
+            let axis = axes.column(column);
+            matrices.extend([axis.x as f32, axis.y as f32, axis.z as f32, 0.0]);
+        }
+        let c = shape.center;
+        matrices.extend([c.x as f32, c.y as f32, c.z as f32, 1.0]);
+        colors.extend(shape.color);
+        verdicts.push(shape.verdict as u8);
+        source_indices.push(shape.index);
+        if let Some((vertices, indices, vertex_colors)) = merged.as_mut() {
+            let base = (vertices.len() / 3) as u32;
+            for v in &sphere {
+                let p = c + axes * v;
+                vertices.extend([p.x as f32, p.y as f32, p.z as f32]);
+                vertex_colors.extend(shape.color);
+            }
+            indices.extend(faces.iter().flatten().map(|&i| base + i));
+        }
+    }
+
+    let template = MeshBuffers::new(
+        sphere
+            .iter()
+            .flat_map(|v| [v.x as f32, v.y as f32, v.z as f32])
+            .collect(),
+        faces.into_iter().flatten().collect(),
+    );
+    SplatEllipsoids {
+        api_version: crate::API_VERSION,
+        semver: crate::core_semver(),
+        capabilities: crate::capabilities(),
+        count: chosen.len(),
+        splats_kept,
+        splats_discarded,
+        matrices,
+        colors,
+        verdicts: serde_bytes::ByteBuf::from(verdicts),
+        source_indices,
+        template,
+        mesh: merged.map(|(vertices, indices, colors)| {
+            let mut mesh = MeshBuffers::new(vertices, indices);
+            mesh.colors = Some(colors);
+            mesh
+        }),
+        space,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shape(index: u32, radius: f64, verdict: SplatVerdict) -> SplatShape {
+        SplatShape {
+            center: Point3::new(index as f64, 0.0, 0.0),
+            axes: Matrix3::from_diagonal_element(radius),
+            opacity: -radius,
+            color: [0.5; 3],
+            index,
+            verdict,
+        }
+    }
+
+    #[test]
+    fn template_is_a_closed_outward_sphere() {
+        let (vertices, faces) = template();
+        assert_eq!((vertices.len(), faces.len()), (18, 32));
+        assert!(vertices.iter().all(|v| (v.norm() - 1.0).abs() < 1e-12));
+        for [a, b, c] in faces {
+            let [a, b, c] = [a, b, c].map(|i| vertices[i as usize]);
+            assert!((b - a).cross(&(c - a)).dot(&(a + b + c)) > 0.0);
+        }
+    }
+
+    #[test]
+    fn picks_the_top_splats_the_filter_admits() {
+        let shapes = || {
+            vec![
+                shape(0, 0.1, SplatVerdict::Kept),
+                shape(1, 0.4, SplatVerdict::FaintOrLarge),
+                shape(2, 0.3, SplatVerdict::Kept),
+                shape(3, 0.2, SplatVerdict::OutsideRegion),
+            ]
+        };
+        let options = |rank, filter| EllipsoidOptions {
+            count: 2,
+            rank,
+            filter,
+            mesh: true,
+        };
+        let space = CoordinateSpace::splatwalk_oriented;
+        let all = build(
+            shapes(),
+            &options(EllipsoidRank::Largest, EllipsoidFilter::All),
+            space(),
+        );
+        assert_eq!(all.source_indices, [1, 2]);
+        assert_eq!(all.verdicts.to_vec(), [2, 0]);
+        assert_eq!((all.splats_kept, all.splats_discarded), (2, 2));
+        assert_eq!(&all.matrices[..4], &[0.4, 0.0, 0.0, 0.0]);
+        assert_eq!(&all.matrices[12..16], &[1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(all.mesh.unwrap().vertices.len(), 2 * 18 * 3);
+
+        let kept = build(
+            shapes(),
+            &options(EllipsoidRank::Opaque, EllipsoidFilter::Kept),
+            space(),
+        );
+        assert_eq!(kept.source_indices, [0, 2]);
+        let dropped = build(
+            shapes(),
+            &options(EllipsoidRank::Opaque, EllipsoidFilter::Discarded),
+            space(),
+        );
+        assert_eq!(dropped.source_indices, [3, 1]);
+    }
+}
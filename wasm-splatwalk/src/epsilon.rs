@@ -0,0 +1,164 @@
+//! Scale-aware tolerances (`epsilon_policy`, `epsilon`).
+//!
+//! Welding distances, degenerate-triangle checks and RANSAC inlier thresholds
+//! used to be absolute numbers tuned on room-sized captures: 0.2 m swallows
+//! every object on a 2 m tabletop scan and is noise on a 200 m plaza. All of
+//! them now derive from one base length, the [`Tolerance`] epsilon. The
+//! default `fixed` policy keeps the room-scale numbers (1 mm), `scene` takes
+//! a fraction of the filtered cloud's bounding-box diagonal, and an explicit
+//! `epsilon` overrides both.
+
+use crate::journal::{self, JournalKind};
+use crate::splat::PointNormal;
+use crate::MeshSettings;
+
+/// Epsilon the fixed tolerances were tuned at; every metre-valued default is
+/// a multiple of it.
+pub const REFERENCE_EPSILON: f64 = 1e-3;
+
+/// `scene` epsilon as a fraction of the bounding-box diagonal: 1 mm on a
+/// 20 m room, 0.1 mm on a 2 m tabletop, 1 cm on a 200 m plaza.
+const SCENE_FRACTION: f64 = 5e-5;
+
+/// Bounds on a `scene` epsilon, so a near-empty or runaway cloud can't push
+/// the thresholds to zero or to the size of a building.
+const SCENE_MIN: f64 = 1e-5;
+const SCENE_MAX: f64 = 0.05;
+
+/// The base length every tolerance of a bake derives from.
+#[derive(Clone, Copy, Debug)]
+pub struct Tolerance {
+    pub epsilon: f64,
+    /// `"fixed"`, `"scene"` or `"explicit"`.
+    pub source: &'static str,
+}
+
+impl Tolerance {
+    pub fn fixed() -> Self {
+        Tolerance {
+            epsilon: REFERENCE_EPSILON,
+            source: "fixed",
+        }
+    }
+
+    /// Vertices closer than this are the same vertex.
+    pub fn weld(&self) -> f64 {
+        self.epsilon
+    }
+
+    /// Triangles (and RANSAC triples) whose edge cross product is shorter than
+    /// this are degenerate. `epsilon^2`, i.e. 1e-6 under the fixed policy.
+    pub fn degenerate_area(&self) -> f64 {
+        self.epsilon * self.epsilon
+    }
+
+    /// A length tuned as `metres` at [`REFERENCE_EPSILON`], rescaled to this
+    /// tolerance. Identity under the fixed policy.
+    pub fn scaled(&self, metres: f64) -> f64 {
+        metres * self.epsilon / REFERENCE_EPSILON
+    }
+}
+
+/// `epsilon_policy` must be `fixed` or `scene`, and `epsilon` a positive
+/// finite length, when set.
+pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+    if let Some(policy) = settings.epsilon_policy.as_deref() {
+        if !matches!(policy, "fixed" | "scene") {
+            return Err(format!(
+                "Invalid epsilon_policy: {}. Expected fixed or scene.",
+                policy
+            ));
+        }
+    }
+    match settings.epsilon {
+        Some(epsilon) if !(epsilon.is_finite() && epsilon > 0.0) => {
+            Err(format!("Invalid epsilon: {}", epsilon))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The tolerance for a bake over `points` (the filtered cloud).
+pub fn resolve(settings: &MeshSettings, points: &[PointNormal]) -> Tolerance {
+    if let Some(epsilon) = settings.epsilon {
+        return Tolerance {
+            epsilon,
+            source: "explicit",
+        };
+    }
+    if settings.epsilon_policy.as_deref() != Some("scene") || points.is_empty() {
+        return Tolerance::fixed();
+    }
+
+    let mut min = [f64::MAX; 3];
+    let mut max = [f64::MIN; 3];
+    for p in points {
+        let c = [p.point.x, p.point.y, p.point.z];
+        for axis in 0..3 {
+            min[axis] = min[axis].min(c[axis]);
+            max[axis] = max[axis].max(c[axis]);
+        }
+    }
+    let diagonal = (0..3)
+        .map(|axis| (max[axis] - min[axis]).powi(2))
+        .sum::<f64>()
+        .sqrt();
+    let requested = diagonal * SCENE_FRACTION;
+    let epsilon = requested.clamp(SCENE_MIN, SCENE_MAX);
+    journal::clamped("tolerance", "epsilon", requested, epsilon);
+    journal::record(
+        "tolerance",
+        JournalKind::Decision,
+        format!(
+            "Scene epsilon {:.6} m from a {:.2} m bounding-box diagonal",
+            epsilon, diagonal
+        ),
+        serde_json::json!({ "epsilon": epsilon, "diagonal": diagonal }),
+    );
+    Tolerance {
+        epsilon,
+        source: "scene",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{Point3, Vector3};
+
+    fn cloud(extent: f64) -> Vec<PointNormal> {
+        [0.0, extent]
+            .iter()
+            .map(|&x| PointNormal {
+                point: Point3::new(x, 0.0, 0.0),
+                normal: Vector3::y(),
+                scale: Vector3::new(0.01, 0.01, 0.01),
+                opacity: 1.0,
+                color: [0.5; 3],
+                index: 0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn scene_epsilon_follows_the_bounding_box() {
+        let mut settings: MeshSettings =
+            serde_json::from_value(serde_json::json!({ "mode": 0 })).unwrap();
+        assert_eq!(resolve(&settings, &cloud(200.0)).source, "fixed");
+        assert_eq!(Tolerance::fixed().scaled(0.2), 0.2);
+
+        settings.epsilon_policy = Some("scene".to_string());
+        let tabletop = resolve(&settings, &cloud(2.0));
+        let plaza = resolve(&settings, &cloud(200.0));
+        assert!((tabletop.epsilon - 1e-4).abs() < 1e-12);
+        assert!((plaza.epsilon - 1e-2).abs() < 1e-12);
+        assert!((plaza.scaled(0.2) - 2.0).abs() < 1e-9);
+
+        settings.epsilon = Some(0.004);
+        let explicit = resolve(&settings, &cloud(2.0));
+        assert_eq!((explicit.epsilon, explicit.source), (0.004, "explicit"));
+        assert!(validate(&settings).is_ok());
+        settings.epsilon_policy = Some("bbox".to_string());
+        assert!(validate(&settings).is_err());
+    }
+}
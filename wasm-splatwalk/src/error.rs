@@ -0,0 +1,110 @@
+//! Structured errors for the WASM entry points.
+//!
+//! Entry points used to reject with `JsValue::from_str(..)`, so JS could only
+//! string-match prose to tell a corrupt file from a bad setting. Every failure
+//! now carries a stable `code`:
+//!
+//! - `parse_error`: the bytes look like a known format but failed to decode;
+//! - `unsupported_format`: the bytes match no supported splat format;
+//! - `empty_cloud`: nothing left to work on (no splats, or none survived
+//!   pruning and region filters);
+//! - `invalid_settings`: a settings object failed to deserialize or validate;
+//! - `reconstruction_failed`: the input was fine but the bake produced nothing;
+//! - `internal_error`: a pipeline stage panicked (see `stage`).
+//!
+//! On the JS side the value is a regular `Error` (so `instanceof Error` and
+//! `error.message` keep working) with `code` and `detail` properties attached.
+
+use std::fmt;
+
+use wasm_bindgen::JsValue;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SplatwalkError {
+    ParseError(String),
+    UnsupportedFormat(String),
+    EmptyCloud(String),
+    InvalidSettings(String),
+    ReconstructionFailed(String),
+    InternalError(String),
+}
+
+impl SplatwalkError {
+    /// Stable machine code, safe to branch on.
+    pub fn code(&self) -> &'static str {
+        match self {
+            SplatwalkError::ParseError(_) => "parse_error",
+            SplatwalkError::UnsupportedFormat(_) => "unsupported_format",
+            SplatwalkError::EmptyCloud(_) => "empty_cloud",
+            SplatwalkError::InvalidSettings(_) => "invalid_settings",
+            SplatwalkError::ReconstructionFailed(_) => "reconstruction_failed",
+            SplatwalkError::InternalError(_) => "internal_error",
+        }
+    }
+
+    /// Short human-readable summary of the code.
+    pub fn summary(&self) -> &'static str {
+        match self {
+            SplatwalkError::ParseError(_) => "Failed to parse splat data",
+            SplatwalkError::UnsupportedFormat(_) => "Unsupported splat format",
+            SplatwalkError::EmptyCloud(_) => "No splats to process",
+            SplatwalkError::InvalidSettings(_) => "Invalid settings",
+            SplatwalkError::ReconstructionFailed(_) => "Reconstruction failed",
+            SplatwalkError::InternalError(_) => "Internal error",
+        }
+    }
+
+    /// The specific cause (parser message, offending setting, ...).
+    pub fn detail(&self) -> &str {
+        match self {
+            SplatwalkError::ParseError(d)
+            | SplatwalkError::UnsupportedFormat(d)
+            | SplatwalkError::EmptyCloud(d)
+            | SplatwalkError::InvalidSettings(d)
+            | SplatwalkError::ReconstructionFailed(d)
+            | SplatwalkError::InternalError(d) => d,
+        }
+    }
+}
+
+impl fmt::Display for SplatwalkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.detail().is_empty() {
+            f.write_str(self.summary())
+        } else {
+            write!(f, "{}: {}", self.summary(), self.detail())
+        }
+    }
+}
+
+impl std::error::Error for SplatwalkError {}
+
+impl From<SplatwalkError> for JsValue {
+    fn from(error: SplatwalkError) -> JsValue {
+        let js = js_sys::Error::new(&error.to_string());
+        js.set_name("SplatwalkError");
+        // Setting an own property on a fresh `Error` cannot fail.
+        let _ = js_sys::Reflect::set(&js, &"code".into(), &error.code().into());
+        let _ = js_sys::Reflect::set(&js, &"detail".into(), &error.detail().into());
+        js.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_combines_summary_and_detail() {
+        let error = SplatwalkError::InvalidSettings("Invalid void_plane: moat".to_string());
+        assert_eq!(error.code(), "invalid_settings");
+        assert_eq!(
+            error.to_string(),
+            "Invalid settings: Invalid void_plane: moat"
+        );
+        assert_eq!(
+            SplatwalkError::EmptyCloud(String::new()).to_string(),
+            "No splats to process"
+        );
+    }
+}
@@ -0,0 +1,417 @@
+//! Catalog of structured progress events.
+//!
+//! Progress used to be free-form English on the console, which a host app can
+//! neither translate nor key UI off without scraping. Every message now starts
+//! as an [`Event`]: a stable snake_case `code` plus typed parameters. [`emit`]
+//! prints the English rendering to the console as before and appends the
+//! event to the bake journal, where `bake_journal().events` hands it to the
+//! host to localize from the code and parameters.
+//!
+//! Codes and parameter names are part of the binary contract: new events and
+//! new parameters may be added, existing ones are never renamed or removed.
+
+use serde::Serialize;
+
+use crate::journal;
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "code", rename_all = "snake_case")]
+pub enum Event {
+    /// A gzip-wrapped input was inflated before parsing.
+    GzipInflated {
+        compressed_bytes: usize,
+        inflated_bytes: usize,
+    },
+    /// The input format was recognized from its header (`spz`, `splat`,
+    /// `compressed_ply`).
+    FormatDetected {
+        format: &'static str,
+    },
+    /// Splats were decoded from one format. `sh_degree` is `null` for the
+    /// position-only parse the mesh entry points use.
+    SplatsDecoded {
+        count: usize,
+        format: &'static str,
+        sh_degree: Option<usize>,
+    },
+    /// A `.spz` payload was decoded by the built-in reader instead of
+    /// `spz_rs`: a newer container `version`, or one `spz_rs` rejected.
+    SpzDecodedNatively {
+        version: u32,
+        reason: String,
+    },
+    /// The job's cached parse was reused.
+    ParseCacheReused {
+        count: usize,
+    },
+    /// The floater prune was skipped; all splats were kept.
+    FloaterPruneSkipped {
+        reason: String,
+        count: usize,
+    },
+    FloatersPruned {
+        removed: usize,
+        k: usize,
+        std_ratio: f64,
+        input: usize,
+        kept: usize,
+    },
+    /// Ingest finished with `count` splats, Y-flipped into render space when
+    /// `flip_y` is set.
+    SplatsIngested {
+        count: usize,
+        flip_y: bool,
+    },
+    /// A mesh bake was handed `count` splats.
+    SplatsReceived {
+        count: usize,
+        mode: u8,
+    },
+    ReconstructionStarted {
+        mode: u8,
+    },
+    VoxelDownsampled {
+        voxel_size: f64,
+        input: usize,
+        kept: usize,
+    },
+    MeshDecimated {
+        input_faces: usize,
+        output_faces: usize,
+        target: usize,
+    },
+    MeshReconstructed {
+        vertices: usize,
+    },
+    /// The collision grid exceeded the voxel cap and its voxels grow from
+    /// `voxel_size` to `next_voxel_size`.
+    GridCoarsened {
+        voxels: usize,
+        cap: usize,
+        voxel_size: f64,
+        next_voxel_size: f64,
+    },
+    /// The collision grid, with `cols` x `layers` x `rows` voxels along the
+    /// oriented X, Y and Z axes.
+    GridConfigured {
+        cols: usize,
+        layers: usize,
+        rows: usize,
+        voxels: usize,
+        cell_size: f64,
+        splats: usize,
+        region_pinned: bool,
+    },
+    /// The coarse cluster filter removed splats disconnected from the seed.
+    ClusterFiltered {
+        kept: usize,
+        removed: usize,
+    },
+    CollisionCarved {
+        cols: usize,
+        layers: usize,
+        rows: usize,
+        cell_size: f64,
+        occupied: usize,
+        kept: usize,
+        discarded: usize,
+        filled: usize,
+        carved: usize,
+        faces: usize,
+    },
+    /// The 2.5D column field of the walkable ground, `cols` x `rows` cells.
+    ColumnFieldBuilt {
+        cols: usize,
+        rows: usize,
+        cell_size: f64,
+        y_bins: usize,
+        clearance_min: f64,
+        clearance_max: f64,
+        surfaces: usize,
+        multi_layer: usize,
+        floor_bins: usize,
+        obstacle_bins: usize,
+        holes_filled: usize,
+        eroded: usize,
+        discarded: usize,
+        rejected_confidence: usize,
+        rejected_discontinuity: usize,
+        rejected_obstacle: usize,
+        void: usize,
+    },
+    WallsFitted {
+        segments: usize,
+        dominant_angle_deg: f64,
+    },
+    SafeAreaFound {
+        area: f64,
+        shape: String,
+        inscribed_radius: f64,
+    },
+    ChokepointsFound {
+        count: usize,
+    },
+    /// `extract_floor_polygons` traced `polygons` islands of floor with
+    /// `holes` holes between them.
+    FloorPolygonsExtracted {
+        polygons: usize,
+        holes: usize,
+    },
+    /// `build_splat_ellipsoids` returned `count` ellipsoids; the filters kept
+    /// `kept` splats of the cloud and dropped `discarded`.
+    EllipsoidsBuilt {
+        count: usize,
+        kept: usize,
+        discarded: usize,
+    },
+    SliceStarted {
+        splats: usize,
+        sh_degree: usize,
+        lod_levels: usize,
+        splats_per_chunk: usize,
+    },
+    SliceFinished {
+        chunks: usize,
+    },
+    /// `bake_dirty_tiles` rebuilt the `tiles` that `regions` dirty regions
+    /// reach; `empty` of them no longer hold walkable faces.
+    TilesPatched {
+        regions: usize,
+        tiles: usize,
+        empty: usize,
+    },
+}
+
+impl Event {
+    /// The English console rendering.
+    pub fn message(&self) -> String {
+        match self {
+            Event::GzipInflated {
+                compressed_bytes,
+                inflated_bytes,
+            } => format!(
+                "Inflated gzip input: {} -> {} bytes",
+                compressed_bytes, inflated_bytes
+            ),
+            Event::FormatDetected { format } => format!("Detected {} format", format),
+            Event::SplatsDecoded {
+                count,
+                format,
+                sh_degree: Some(degree),
+            } => format!("Parsed {} splats from {} (SH degree {})", count, format, degree),
+            Event::SplatsDecoded { count, format, .. } => {
+                format!("Parsed {} points from {}", count, format)
+            }
+            Event::SpzDecodedNatively { version, reason } => {
+                format!("Decoded SPZ v{} with the built-in reader ({})", version, reason)
+            }
+            Event::ParseCacheReused { count } => {
+                format!("Reusing cached splats ({} points)", count)
+            }
+            Event::FloaterPruneSkipped { reason, count } => {
+                format!("Floater prune skipped ({}); kept all {} splats", reason, count)
+            }
+            Event::FloatersPruned {
+                removed,
+                k,
+                std_ratio,
+                input,
+                kept,
+            } => format!(
+                "Pruned {} floater splats (k={}, std_ratio={:.2}): {} -> {}",
+                removed, k, std_ratio, input, kept
+            ),
+            Event::SplatsIngested { count, flip_y: true } => {
+                format!("Parsed {} splats (Y-flipped to render space)", count)
+            }
+            Event::SplatsIngested { count, .. } => format!("Parsed {} splats", count),
+            Event::SplatsReceived { count, mode } => {
+                format!("Received {} splats (Mode: {})", count, mode)
+            }
+            Event::ReconstructionStarted { mode } => {
+                format!("Reconstructing mesh (Mode: {})...", mode)
+            }
+            Event::VoxelDownsampled {
+                voxel_size,
+                input,
+                kept,
+            } => format!(
+                "Voxel downsample ({} m): {} -> {} points",
+                voxel_size, input, kept
+            ),
+            Event::MeshDecimated {
+                input_faces,
+                output_faces,
+                target,
+            } => format!(
+                "Decimated mesh: {} -> {} faces (target {})",
+                input_faces, output_faces, target
+            ),
+            Event::MeshReconstructed { vertices } => {
+                format!("Reconstructed mesh with {} vertices", vertices)
+            }
+            Event::GridCoarsened {
+                voxels,
+                cap,
+                voxel_size,
+                next_voxel_size,
+            } => format!(
+                "Collision grid {} voxels exceeds cap {} — coarsening voxel {:.3}m → {:.3}m",
+                voxels, cap, voxel_size, next_voxel_size
+            ),
+            Event::GridConfigured {
+                cols,
+                layers,
+                rows,
+                voxels,
+                cell_size,
+                splats,
+                region_pinned,
+            } => format!(
+                "Collision grid: {}x{}x{} ({} voxels), voxel={:.3}m, splats={}, region_pinned={}",
+                cols, layers, rows, voxels, cell_size, splats, region_pinned
+            ),
+            Event::ClusterFiltered { kept, removed } => format!(
+                "Coarse filter-cluster (PC --filter-cluster): kept {} splats, removed {} disconnected",
+                kept, removed
+            ),
+            Event::CollisionCarved {
+                cols,
+                layers,
+                rows,
+                cell_size,
+                occupied,
+                kept,
+                discarded,
+                filled,
+                carved,
+                faces,
+            } => format!(
+                "Collision carve: grid={}x{}x{}, voxel={:.3}, occupied={}, kept={}, discarded={}, filled={}, carved={}, faces={}",
+                cols, layers, rows, cell_size, occupied, kept, discarded, filled, carved, faces
+            ),
+            Event::ColumnFieldBuilt {
+                cols,
+                rows,
+                cell_size,
+                y_bins,
+                clearance_min,
+                clearance_max,
+                surfaces,
+                multi_layer,
+                floor_bins,
+                obstacle_bins,
+                holes_filled,
+                eroded,
+                discarded,
+                rejected_confidence,
+                rejected_discontinuity,
+                rejected_obstacle,
+                void,
+            } => format!(
+                "2.5D SDF column field: {}x{}, cell_size={:.3}, y_bins={}, clearance=[{:.2},{:.2}], surfaces={}, multi_layer={}, floor_bins={}, obstacleBand_bins={}, holes_filled={}, eroded={}, discarded={}, rejected(conf={}, discontinuity={}, obs={}, void={})",
+                cols,
+                rows,
+                cell_size,
+                y_bins,
+                clearance_min,
+                clearance_max,
+                surfaces,
+                multi_layer,
+                floor_bins,
+                obstacle_bins,
+                holes_filled,
+                eroded,
+                discarded,
+                rejected_confidence,
+                rejected_discontinuity,
+                rejected_obstacle,
+                void
+            ),
+            Event::WallsFitted {
+                segments,
+                dominant_angle_deg,
+            } => format!(
+                "Fitted {} wall segment(s), dominant angle {:.1} deg",
+                segments, dominant_angle_deg
+            ),
+            Event::SafeAreaFound {
+                area,
+                shape,
+                inscribed_radius,
+            } => format!(
+                "Safe area: {:.2} m2 {} polygon, {:.2} m inscribed radius",
+                area, shape, inscribed_radius
+            ),
+            Event::ChokepointsFound { count } => format!("Found {} chokepoint(s)", count),
+            Event::FloorPolygonsExtracted { polygons, holes } => format!(
+                "Traced {} floor polygon(s) with {} hole(s)",
+                polygons, holes
+            ),
+            Event::EllipsoidsBuilt {
+                count,
+                kept,
+                discarded,
+            } => format!(
+                "Built {} splat ellipsoid(s); the filters kept {} splats and dropped {}",
+                count, kept, discarded
+            ),
+            Event::SliceStarted {
+                splats,
+                sh_degree,
+                lod_levels,
+                splats_per_chunk,
+            } => format!(
+                "Slicing {} splats (SH degree {}, {} LOD level(s), ~{} splats/chunk)",
+                splats, sh_degree, lod_levels, splats_per_chunk
+            ),
+            Event::SliceFinished { chunks } => format!("Sliced into {} chunk(s)", chunks),
+            Event::TilesPatched {
+                regions,
+                tiles,
+                empty,
+            } => format!(
+                "Rebuilt {} navmesh tile(s) for {} dirty region(s), {} now empty",
+                tiles, regions, empty
+            ),
+        }
+    }
+}
+
+/// Print `event` to the console and append it to the bake journal.
+pub fn emit(event: Event) {
+    crate::log(&event.message());
+    journal::event(event);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_serialize_as_code_and_parameters() {
+        let event = Event::GridConfigured {
+            cols: 40,
+            layers: 12,
+            rows: 30,
+            voxels: 14400,
+            cell_size: 0.1,
+            splats: 5000,
+            region_pinned: false,
+        };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["code"], "grid_configured");
+        assert_eq!(json["cols"], 40);
+        assert_eq!(json["cell_size"], 0.1);
+        assert!(event.message().starts_with("Collision grid: 40x12x30"));
+
+        // `emit` also logs through the wasm console import, which native
+        // test builds cannot call.
+        journal::begin("test");
+        journal::event(Event::ChokepointsFound { count: 2 });
+        let journal = journal::snapshot();
+        assert_eq!(journal.events, vec![Event::ChokepointsFound { count: 2 }]);
+        journal::begin("test");
+        assert!(journal::snapshot().events.is_empty());
+    }
+}
@@ -0,0 +1,277 @@
+//! Host-scored face acceptance (`set_face_filter`).
+//!
+//! The walkability rules the core ships (slope, climb, hazards, regions)
+//! cover the common scans, but a host with its own policy (no walking on
+//! anything blue, higher cost on sparse floor, a height band per level) used
+//! to have to fork the crate or post-filter the returned buffers. A callback
+//! registered with [`set_face_filter`] sees every output face of
+//! `convert_splat_to_mesh` in batches, with the face's slope, height, splat
+//! coverage and color, and answers per face: reject it, or accept it at a
+//! path cost. Rejected faces are dropped from `mesh`; the costs come back as
+//! `path_costs`, like the room floor's.
+
+use std::collections::HashMap;
+
+use js_sys::{Float32Array, Object, Reflect};
+use nalgebra::Vector3;
+use wasm_bindgen::prelude::*;
+
+use crate::context;
+use crate::mesh::ReconstructedMesh;
+use crate::reachability;
+use crate::splat::PointNormal;
+use crate::vertex_color;
+
+/// Faces per callback invocation: few enough calls that crossing into JS is
+/// noise, small enough arrays that a slow callback can still yield progress.
+const BATCH_FACES: usize = 4096;
+
+/// Register (or, with `None`/`undefined`, clear) a JS face filter. It is
+/// invoked as `callback(batch)` with `batch = { first_face, count, slope,
+/// height, coverage, color }` and returns one verdict per face: a cost
+/// multiplier (> 0 accepts), `0` or `false` to reject, `true` for cost 1,
+/// or `undefined` to accept the whole batch at cost 1.
+#[wasm_bindgen]
+pub fn set_face_filter(callback: Option<js_sys::Function>) {
+    *context::current().face_filter.borrow_mut() = callback;
+}
+
+/// Whether a face filter is registered.
+pub fn is_set() -> bool {
+    context::current().face_filter.borrow().is_some()
+}
+
+/// Drop the face filter, for `reset_module`. Returns false when a trapped call
+/// still holds it.
+pub fn try_clear() -> bool {
+    match context::current().face_filter.try_borrow_mut() {
+        Ok(mut filter) => {
+            *filter = None;
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+/// What the filter sees of each face.
+pub struct FaceAttributes {
+    /// Degrees from horizontal.
+    pub slope: Vec<f32>,
+    /// Height of the centroid, in metres.
+    pub height: Vec<f32>,
+    /// Summed opacity of the splats within the color sample radius of the
+    /// centroid.
+    pub coverage: Vec<f32>,
+    /// RGB in `[0, 1]` at the centroid, three values per face, as `emit_colors`
+    /// samples it.
+    pub color: Vec<f32>,
+}
+
+/// The attributes of every face of `mesh`. `radius` is the color sample
+/// radius. Faces with a corner out of range get zeros.
+pub fn attributes(mesh: &ReconstructedMesh, points: &[PointNormal], radius: f64) -> FaceAttributes {
+    let positions: Vec<Vector3<f64>> = mesh
+        .vertices
+        .chunks_exact(3)
+        .map(|v| Vector3::new(v[0] as f64, v[1] as f64, v[2] as f64))
+        .collect();
+    let corners: Vec<Option<[Vector3<f64>; 3]>> = mesh
+        .indices
+        .chunks_exact(3)
+        .map(|f| {
+            let corner = |i: u32| positions.get(i as usize).copied();
+            Some([corner(f[0])?, corner(f[1])?, corner(f[2])?])
+        })
+        .collect();
+    let centroids: Vec<Vector3<f64>> = corners
+        .iter()
+        .map(|c| c.map_or(Vector3::zeros(), |[a, b, c]| (a + b + c) / 3.0))
+        .collect();
+    let slope = corners
+        .iter()
+        .map(|c| {
+            c.map_or(0.0, |[a, b, c]| {
+                let n = (b - a).cross(&(c - a));
+                let up = if n.norm() > 0.0 {
+                    (n.y.abs() / n.norm()).min(1.0)
+                } else {
+                    1.0
+                };
+                up.acos().to_degrees() as f32
+            })
+        })
+        .collect();
+
+    let cell_of = |p: &Vector3<f64>| {
+        (
+            (p.x / radius).floor() as i64,
+            (p.y / radius).floor() as i64,
+            (p.z / radius).floor() as i64,
+        )
+    };
+    let mut grid: HashMap<(i64, i64, i64), Vec<u32>> = HashMap::new();
+    for (i, p) in points.iter().enumerate() {
+        if p.point.iter().all(|c| c.is_finite()) {
+            grid.entry(cell_of(&p.point.coords))
+                .or_default()
+                .push(i as u32);
+        }
+    }
+    let coverage = centroids
+        .iter()
+        .map(|centroid| {
+            let (cx, cy, cz) = cell_of(centroid);
+            let mut sum = 0.0;
+            for dx in -1..=1 {
+                for dy in -1..=1 {
+                    for dz in -1..=1 {
+                        for &i in grid.get(&(cx + dx, cy + dy, cz + dz)).into_iter().flatten() {
+                            let p = &points[i as usize];
+                            if (p.point.coords - centroid).norm() <= radius {
+                                sum += 1.0 / (1.0 + (-p.opacity).exp());
+                            }
+                        }
+                    }
+                }
+            }
+            sum as f32
+        })
+        .collect();
+
+    let flat: Vec<f32> = centroids
+        .iter()
+        .flat_map(|c| [c.x as f32, c.y as f32, c.z as f32])
+        .collect();
+    FaceAttributes {
+        slope,
+        height: centroids.iter().map(|c| c.y as f32).collect(),
+        coverage,
+        color: vertex_color::sample_vertex_colors(&flat, points, radius),
+    }
+}
+
+/// Run the registered filter over `attributes`: one verdict per face, a cost
+/// where accepted and `0` where rejected. `Ok(None)` without a filter; `Err`
+/// when the callback throws or answers with the wrong shape.
+pub fn score(attributes: &FaceAttributes) -> Result<Option<Vec<f32>>, String> {
+    // Cloned out, so a callback that re-registers the filter doesn't find it
+    // borrowed.
+    let Some(callback) = context::current().face_filter.borrow().clone() else {
+        return Ok(None);
+    };
+    let faces = attributes.slope.len();
+    let mut verdicts = Vec::with_capacity(faces);
+    for first in (0..faces).step_by(BATCH_FACES) {
+        let end = (first + BATCH_FACES).min(faces);
+        let batch = Object::new();
+        let set = |key: &str, value: JsValue| Reflect::set(&batch, &key.into(), &value).map(|_| ());
+        let array = |values: &[f32]| JsValue::from(Float32Array::from(values));
+        set("first_face", (first as f64).into())
+            .and_then(|_| set("count", ((end - first) as f64).into()))
+            .and_then(|_| set("slope", array(&attributes.slope[first..end])))
+            .and_then(|_| set("height", array(&attributes.height[first..end])))
+            .and_then(|_| set("coverage", array(&attributes.coverage[first..end])))
+            .and_then(|_| set("color", array(&attributes.color[first * 3..end * 3])))
+            .map_err(describe)?;
+        let answer = callback
+            .call1(&JsValue::NULL, &batch)
+            .map_err(|e| format!("face filter threw: {}", describe(e)))?;
+        if answer.is_undefined() {
+            verdicts.resize(end, 1.0);
+            continue;
+        }
+        let iter = js_sys::try_iter(&answer)
+            .map_err(describe)?
+            .ok_or("face filter must return an array of verdicts or undefined")?;
+        for value in iter {
+            verdicts.push(verdict(&value.map_err(describe)?)?);
+        }
+        if verdicts.len() != end {
+            return Err(format!(
+                "face filter returned {} verdicts for faces {}..{}",
+                verdicts.len() - first,
+                first,
+                end
+            ));
+        }
+    }
+    Ok(Some(verdicts))
+}
+
+fn verdict(value: &JsValue) -> Result<f32, String> {
+    let cost = match (value.as_bool(), value.as_f64()) {
+        (Some(accept), _) => f64::from(u8::from(accept)),
+        (_, Some(cost)) => cost,
+        _ => return Err("face filter verdicts must be numbers or booleans".to_string()),
+    };
+    Ok(if cost.is_finite() && cost > 0.0 {
+        cost as f32
+    } else {
+        0.0
+    })
+}
+
+fn describe(error: JsValue) -> String {
+    error.as_string().unwrap_or_else(|| format!("{:?}", error))
+}
+
+/// `mesh` without the faces whose verdict is `0`, the costs of the faces
+/// kept, and how many were rejected.
+pub fn apply(mesh: &ReconstructedMesh, verdicts: &[f32]) -> (ReconstructedMesh, Vec<f32>, usize) {
+    let positions: Vec<Vector3<f64>> = mesh
+        .vertices
+        .chunks_exact(3)
+        .map(|v| Vector3::new(v[0] as f64, v[1] as f64, v[2] as f64))
+        .collect();
+    let faces: Vec<[usize; 3]> = mesh
+        .indices
+        .chunks_exact(3)
+        .map(|f| [f[0] as usize, f[1] as usize, f[2] as usize])
+        .collect();
+    let keep: Vec<usize> = (0..faces.len())
+        .filter(|&fi| verdicts.get(fi).is_some_and(|&v| v > 0.0))
+        .filter(|&fi| faces[fi].iter().all(|&i| i < positions.len()))
+        .collect();
+    let costs = keep.iter().map(|&fi| verdicts[fi]).collect();
+    let rejected = faces.len() - keep.len();
+    let kept = reachability::submesh(&positions, &faces, &keep);
+    (kept, costs, rejected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attributes_and_verdicts_follow_the_faces() {
+        // A flat unit square and a wall beside it, one red splat on the floor.
+        let mesh = ReconstructedMesh {
+            vertices: vec![
+                0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 1.0, //
+                2.0, 0.0, 0.0, 2.0, 1.0, 0.0, 2.0, 1.0, 1.0,
+            ],
+            indices: vec![0, 2, 1, 0, 3, 2, 4, 5, 6],
+        };
+        let splat = PointNormal {
+            point: nalgebra::Point3::new(0.6, 0.0, 0.4),
+            normal: Vector3::y(),
+            scale: Vector3::repeat(0.01),
+            opacity: 10.0,
+            color: [1.0, 0.0, 0.0],
+            index: 0,
+        };
+        let attributes = attributes(&mesh, &[splat], 0.5);
+        assert_eq!(attributes.slope[0], 0.0);
+        assert!((attributes.slope[2] - 90.0).abs() < 1e-3);
+        assert!((attributes.height[2] - 2.0 / 3.0).abs() < 1e-6);
+        assert!(attributes.coverage[0] > 0.99);
+        assert_eq!(attributes.coverage[2], 0.0);
+        assert_eq!(attributes.color[..3], [1.0, 0.0, 0.0]);
+
+        let (kept, costs, rejected) = apply(&mesh, &[2.0, 1.0, 0.0]);
+        assert_eq!(rejected, 1);
+        assert_eq!(costs, [2.0, 1.0]);
+        assert_eq!(kept.indices.len(), 6);
+        assert_eq!(kept.vertices.len(), 4 * 3);
+    }
+}
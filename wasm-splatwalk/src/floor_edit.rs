@@ -0,0 +1,751 @@
+//! Cell-level editing of a baked room floor.
+//!
+//! `build_room_floor_mesh` with `emit_heightfield: true` returns the floor as a
+//! heightfield: the ground-field grid, its basis, and one emitted height per
+//! floor cell (`NaN` elsewhere). A [`FloorEditor`] wraps that heightfield so a
+//! level designer can patch scan errors in place: set a cell's height (adding
+//! it to the floor if needed), clear it, or flatten a rectangle. Each floor cell
+//! is an independent quad, so an edit only invalidates the cells it touches;
+//! `remesh` rebuilds those quads and reuses the cached rest.
+//!
+//! Brushes ([`BrushStroke`]) do the same edits from world-space picks for a
+//! "paint the navmesh" workflow: a circle or box is projected onto the grid
+//! and every cell whose centre it covers is added, removed, raised, lowered,
+//! smoothed or given a path cost. Costs only change the exported
+//! `path_costs`, never the geometry.
+//!
+//! Heights are along `basis.up` from `basis.origin` and are the emitted heights,
+//! after `relief_scale`. The heightfield is always in `splatwalk_oriented`; the
+//! editor applies the settings' `output_space` when it re-emits the mesh.
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::output_space::{self, OutputTransform};
+use crate::path_cost::{self, PathCosts};
+use crate::typed_mesh::TypedMesh;
+use crate::{CoordinateSpace, FaceCells, FieldBasis, MeshBuffers, MeshSettings, SplatwalkError};
+
+/// A room floor as a grid of cell heights. Cells are `row * width + col`, as in
+/// `build_walkable_ground_field`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Heightfield {
+    pub width: usize,
+    pub height: usize,
+    pub cell_size: f64,
+    pub basis: FieldBasis,
+    /// Emitted floor height per cell; `NaN` marks a cell that is not floor.
+    pub heights: Vec<f32>,
+    /// Floor density layer per cell (bottom first), carried into `face_cells`.
+    pub layers: Vec<u32>,
+    /// Path cost multiplier per cell (1 = normal floor). May be omitted, in
+    /// which case every cell costs 1.
+    #[serde(default)]
+    pub costs: Vec<f32>,
+}
+
+impl Heightfield {
+    pub(crate) fn validate(&self) -> Result<(), SplatwalkError> {
+        let cells = self.width.checked_mul(self.height).unwrap_or(0);
+        if cells == 0 || self.heights.len() != cells || self.layers.len() != cells {
+            return Err(SplatwalkError::InvalidSettings(format!(
+                "Heightfield needs width * height ({} x {}) heights and layers, got {} and {}",
+                self.width,
+                self.height,
+                self.heights.len(),
+                self.layers.len()
+            )));
+        }
+        if !self.costs.is_empty() && self.costs.len() != cells {
+            return Err(SplatwalkError::InvalidSettings(format!(
+                "Heightfield costs must be empty or width * height ({}), got {}",
+                cells,
+                self.costs.len()
+            )));
+        }
+        if let Some(cost) = self
+            .costs
+            .iter()
+            .find(|&&c| !path_cost::valid_cost(c as f64))
+        {
+            return Err(SplatwalkError::InvalidSettings(format!(
+                "Invalid heightfield cost: {}",
+                cost
+            )));
+        }
+        if !(self.cell_size.is_finite() && self.cell_size > 0.0) {
+            return Err(SplatwalkError::InvalidSettings(format!(
+                "Invalid heightfield cell_size: {}",
+                self.cell_size
+            )));
+        }
+        Ok(())
+    }
+
+    fn cell(&self, row: usize, col: usize) -> Result<usize, SplatwalkError> {
+        if row >= self.height || col >= self.width {
+            return Err(SplatwalkError::InvalidSettings(format!(
+                "Cell ({}, {}) is outside the {} x {} heightfield",
+                row, col, self.height, self.width
+            )));
+        }
+        Ok(row * self.width + col)
+    }
+
+    /// Grid coordinates `(col, row)` and height along `basis.up` of a
+    /// `splatwalk_oriented` point. The basis axes are orthonormal.
+    fn project(&self, p: [f64; 3]) -> (f64, f64, f64) {
+        let b = &self.basis;
+        let d = [0, 1, 2].map(|a| p[a] - b.origin[a]);
+        let dot = |axis: &[f64; 3]| d[0] * axis[0] + d[1] * axis[1] + d[2] * axis[2];
+        (
+            dot(&b.tangent) / self.cell_size,
+            dot(&b.bitangent) / self.cell_size,
+            dot(&b.up),
+        )
+    }
+
+    /// Cells whose centres lie inside `shape`.
+    fn cells_in(&self, shape: &BrushShape) -> Vec<usize> {
+        let (u0, u1, v0, v1) = match *shape {
+            BrushShape::Circle { u, v, radius } => (u - radius, u + radius, v - radius, v + radius),
+            BrushShape::Box { u, v } => (u.0, u.1, v.0, v.1),
+        };
+        // Centre of cell `i` is `i + 0.5`, so `i` spans `ceil(lo - 0.5)..=floor(hi - 0.5)`.
+        let span = |lo: f64, hi: f64, n: usize| {
+            let first = (lo - 0.5).ceil().max(0.0);
+            let last = (hi - 0.5).floor().min(n as f64 - 1.0);
+            (first <= last).then_some(first as usize..=last as usize)
+        };
+        let (Some(cols), Some(rows)) = (span(u0, u1, self.width), span(v0, v1, self.height)) else {
+            return Vec::new();
+        };
+        let mut cells = Vec::new();
+        for row in rows {
+            for col in cols.clone() {
+                if let BrushShape::Circle { u, v, radius } = *shape {
+                    let (du, dv) = (col as f64 + 0.5 - u, row as f64 + 0.5 - v);
+                    if du * du + dv * dv > radius * radius {
+                        continue;
+                    }
+                }
+                cells.push(row * self.width + col);
+            }
+        }
+        cells
+    }
+
+    /// Mean height of the floor cells in the 3 x 3 block around floor cell
+    /// `idx` (including itself).
+    fn neighbourhood_mean(&self, idx: usize) -> f32 {
+        let (row, col) = (idx / self.width, idx % self.width);
+        let mut sum = 0.0;
+        let mut count = 0;
+        for r in row.saturating_sub(1)..=(row + 1).min(self.height - 1) {
+            for c in col.saturating_sub(1)..=(col + 1).min(self.width - 1) {
+                let h = self.heights[r * self.width + c];
+                if h.is_finite() {
+                    sum += h;
+                    count += 1;
+                }
+            }
+        }
+        sum / count as f32
+    }
+
+    /// Corner positions of the cell quad, in the same order as the room-floor
+    /// bake.
+    fn quad(&self, idx: usize) -> [f32; 12] {
+        let row = (idx / self.width) as f64;
+        let col = (idx % self.width) as f64;
+        let h = self.heights[idx] as f64;
+        let cs = self.cell_size;
+        let b = &self.basis;
+        let point_at = |col: f64, row: f64| -> [f64; 3] {
+            [0, 1, 2].map(|a| {
+                b.origin[a] + b.tangent[a] * col * cs + b.bitangent[a] * row * cs + b.up[a] * h
+            })
+        };
+        let mut out = [0.0; 12];
+        for (k, p) in [
+            point_at(col, row),
+            point_at(col, row + 1.0),
+            point_at(col + 1.0, row + 1.0),
+            point_at(col + 1.0, row),
+        ]
+        .iter()
+        .enumerate()
+        {
+            out[k * 3] = p[0] as f32;
+            out[k * 3 + 1] = p[1] as f32;
+            out[k * 3 + 2] = p[2] as f32;
+        }
+        out
+    }
+}
+
+/// One brush application for [`FloorEditor::apply_brush`]. Points are world
+/// space in the editor's `output_space`, i.e. the space of the re-emitted mesh,
+/// so a hit on that mesh can be passed straight through.
+#[derive(Deserialize)]
+pub struct BrushStroke {
+    /// `"circle"` (default) or `"box"`.
+    #[serde(default)]
+    pub shape: Option<String>,
+    /// `"add"`, `"remove"`, `"raise"`, `"lower"`, `"smooth"` or `"cost"`.
+    pub op: String,
+    /// Circle centre.
+    #[serde(default)]
+    pub center: Option<[f64; 3]>,
+    /// Circle radius in metres, measured in the floor plane.
+    #[serde(default)]
+    pub radius: Option<f64>,
+    /// Opposite box corners; the box is their bounding rectangle on the grid.
+    #[serde(default)]
+    pub min: Option<[f64; 3]>,
+    #[serde(default)]
+    pub max: Option<[f64; 3]>,
+    /// `raise` / `lower` amount in metres along the floor's up axis.
+    #[serde(default)]
+    pub delta: Option<f64>,
+    /// `add` height along `basis.up`. Defaults to the height of the picked
+    /// point (the circle centre, or the mean of the box corners).
+    #[serde(default)]
+    pub height: Option<f64>,
+    /// `smooth` blend toward the 3 x 3 neighbourhood mean, in `(0, 1]`
+    /// (default 0.5).
+    #[serde(default)]
+    pub strength: Option<f64>,
+    /// `cost` path cost multiplier (> 0).
+    #[serde(default)]
+    pub cost: Option<f64>,
+}
+
+/// Brush footprint in grid units (cell `col` spans `col..col + 1`).
+enum BrushShape {
+    Circle { u: f64, v: f64, radius: f64 },
+    Box { u: (f64, f64), v: (f64, f64) },
+}
+
+enum BrushOp {
+    Add(f32),
+    Remove,
+    Offset(f32),
+    Smooth(f32),
+    Cost(f32),
+}
+
+fn invalid_brush(detail: String) -> SplatwalkError {
+    SplatwalkError::InvalidSettings(format!("Invalid brush: {}", detail))
+}
+
+fn finite_point(point: Option<[f64; 3]>, name: &str) -> Result<[f64; 3], SplatwalkError> {
+    match point {
+        Some(p) if p.iter().all(|c| c.is_finite()) => Ok(p),
+        Some(p) => Err(invalid_brush(format!("{} {:?} is not finite", name, p))),
+        None => Err(invalid_brush(format!("{} is required", name))),
+    }
+}
+
+impl BrushStroke {
+    /// Resolve against `field`, with `to_field` mapping input points into
+    /// `splatwalk_oriented`.
+    fn resolve(
+        &self,
+        field: &Heightfield,
+        to_field: &dyn Fn([f64; 3]) -> [f64; 3],
+    ) -> Result<(BrushShape, BrushOp), SplatwalkError> {
+        let (shape, picked) = match self.shape.as_deref().unwrap_or("circle") {
+            "circle" => {
+                let (u, v, h) = field.project(to_field(finite_point(self.center, "center")?));
+                let radius = self.radius.unwrap_or(f64::NAN);
+                if !(radius.is_finite() && radius > 0.0) {
+                    return Err(invalid_brush(format!("radius {} must be > 0", radius)));
+                }
+                let radius = radius / field.cell_size;
+                (BrushShape::Circle { u, v, radius }, h)
+            }
+            "box" => {
+                let a = field.project(to_field(finite_point(self.min, "min")?));
+                let b = field.project(to_field(finite_point(self.max, "max")?));
+                let shape = BrushShape::Box {
+                    u: (a.0.min(b.0), a.0.max(b.0)),
+                    v: (a.1.min(b.1), a.1.max(b.1)),
+                };
+                (shape, (a.2 + b.2) * 0.5)
+            }
+            other => return Err(invalid_brush(format!("unknown shape {:?}", other))),
+        };
+        let finite = |value: Option<f64>, name: &str| match value {
+            Some(x) if !x.is_finite() => {
+                Err(invalid_brush(format!("{} {} is not finite", name, x)))
+            }
+            _ => Ok(value),
+        };
+        let op = match self.op.as_str() {
+            "add" => BrushOp::Add(finite(self.height, "height")?.unwrap_or(picked) as f32),
+            "remove" => BrushOp::Remove,
+            "raise" | "lower" => {
+                let delta = finite(self.delta, "delta")?
+                    .ok_or_else(|| invalid_brush(format!("{} needs delta", self.op)))?;
+                let sign = if self.op == "raise" { 1.0 } else { -1.0 };
+                BrushOp::Offset((sign * delta) as f32)
+            }
+            "smooth" => {
+                let strength = self.strength.unwrap_or(0.5);
+                if !(strength > 0.0 && strength <= 1.0) {
+                    return Err(invalid_brush(format!(
+                        "strength {} must be in (0, 1]",
+                        strength
+                    )));
+                }
+                BrushOp::Smooth(strength as f32)
+            }
+            "cost" => match self.cost {
+                Some(cost) if path_cost::valid_cost(cost) => BrushOp::Cost(cost as f32),
+                cost => return Err(invalid_brush(format!("cost {:?} must be > 0", cost))),
+            },
+            other => return Err(invalid_brush(format!("unknown op {:?}", other))),
+        };
+        Ok((shape, op))
+    }
+}
+
+/// Result of [`FloorEditor::remesh`].
+#[derive(Serialize)]
+pub struct FloorRemeshResult {
+    pub api_version: u8,
+    pub semver: String,
+    pub capabilities: Vec<String>,
+    pub mesh: MeshBuffers,
+    pub face_cells: FaceCells,
+    /// Per-triangle path costs and their Detour areas.
+    pub path_costs: PathCosts,
+    pub space: CoordinateSpace,
+    /// Floor cells in the mesh.
+    pub cell_count: usize,
+    /// Cells whose quads were rebuilt by this call (the rest were reused).
+    pub remeshed_cells: usize,
+}
+
+/// Output of [`FloorEdit::remesh`].
+struct Remeshed {
+    mesh: MeshBuffers,
+    /// `[row, col, layer]` per triangle.
+    face_cells: Vec<u32>,
+    /// Path cost per triangle.
+    face_costs: Vec<f32>,
+    /// Quads rebuilt by this call.
+    remeshed: usize,
+}
+
+/// Editing state: the heightfield plus one cached quad per floor cell.
+struct FloorEdit {
+    field: Heightfield,
+    quads: Vec<Option<[f32; 12]>>,
+    dirty: Vec<bool>,
+}
+
+impl FloorEdit {
+    fn new(mut field: Heightfield) -> Self {
+        let cells = field.heights.len();
+        if field.costs.is_empty() {
+            field.costs = vec![1.0; cells];
+        }
+        Self {
+            field,
+            quads: vec![None; cells],
+            dirty: vec![true; cells],
+        }
+    }
+
+    fn set_height(&mut self, idx: usize, height: f32) {
+        if self.field.heights[idx].to_bits() != height.to_bits() {
+            self.field.heights[idx] = height;
+            self.dirty[idx] = true;
+        }
+    }
+
+    /// Set every floor cell in the inclusive rectangle to `height`, or to the
+    /// mean floor height inside it. Returns the number of floor cells covered.
+    fn flatten(
+        &mut self,
+        rows: (usize, usize),
+        cols: (usize, usize),
+        height: Option<f32>,
+    ) -> usize {
+        let cells: Vec<usize> = (rows.0.min(rows.1)..=rows.0.max(rows.1))
+            .flat_map(|r| (cols.0.min(cols.1)..=cols.0.max(cols.1)).map(move |c| (r, c)))
+            .filter(|&(r, c)| r < self.field.height && c < self.field.width)
+            .map(|(r, c)| r * self.field.width + c)
+            .filter(|&idx| self.field.heights[idx].is_finite())
+            .collect();
+        if cells.is_empty() {
+            return 0;
+        }
+        let target = height.unwrap_or_else(|| {
+            cells.iter().map(|&i| self.field.heights[i]).sum::<f32>() / cells.len() as f32
+        });
+        for &idx in &cells {
+            self.set_height(idx, target);
+        }
+        cells.len()
+    }
+
+    /// Set a cell's path cost. Geometry is unchanged, so the cell is not
+    /// marked dirty. Returns whether the cost changed.
+    fn set_cost(&mut self, idx: usize, cost: f32) -> bool {
+        let changed = self.field.costs[idx] != cost;
+        self.field.costs[idx] = cost;
+        changed
+    }
+
+    /// Apply `op` to the cells under `shape`. Returns the number of cells
+    /// changed.
+    fn brush(&mut self, shape: &BrushShape, op: &BrushOp) -> usize {
+        let cells = self.field.cells_in(shape);
+        let heights = &self.field.heights;
+        let targets: Vec<(usize, f32)> = match *op {
+            BrushOp::Cost(cost) => {
+                let floor: Vec<usize> = cells
+                    .into_iter()
+                    .filter(|&i| heights[i].is_finite())
+                    .collect();
+                return floor
+                    .into_iter()
+                    .filter(|&i| self.set_cost(i, cost))
+                    .count();
+            }
+            BrushOp::Add(h) => cells.iter().map(|&i| (i, h)).collect(),
+            BrushOp::Remove => cells.iter().map(|&i| (i, f32::NAN)).collect(),
+            BrushOp::Offset(delta) => cells
+                .iter()
+                .filter(|&&i| heights[i].is_finite())
+                .map(|&i| (i, heights[i] + delta))
+                .collect(),
+            // Every target reads the pre-stroke heights, so the result does not
+            // depend on cell order.
+            BrushOp::Smooth(strength) => cells
+                .iter()
+                .filter(|&&i| heights[i].is_finite())
+                .map(|&i| {
+                    let mean = self.field.neighbourhood_mean(i);
+                    (i, heights[i] + (mean - heights[i]) * strength)
+                })
+                .collect(),
+        };
+        let mut changed = 0;
+        for (idx, height) in targets {
+            if self.field.heights[idx].to_bits() != height.to_bits() {
+                changed += 1;
+            }
+            self.set_height(idx, height);
+        }
+        changed
+    }
+
+    /// Rebuild the quads of dirty cells and assemble the mesh.
+    fn remesh(&mut self, transform: Option<&OutputTransform>) -> Remeshed {
+        let mut remeshed = 0;
+        for idx in 0..self.quads.len() {
+            if !self.dirty[idx] {
+                continue;
+            }
+            self.dirty[idx] = false;
+            self.quads[idx] = self.field.heights[idx].is_finite().then(|| {
+                remeshed += 1;
+                self.field.quad(idx)
+            });
+        }
+
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        let mut face_cells = Vec::new();
+        let mut face_costs = Vec::new();
+        for (idx, quad) in self.quads.iter().enumerate() {
+            let Some(quad) = quad else {
+                continue;
+            };
+            let base = (vertices.len() / 3) as u32;
+            vertices.extend_from_slice(quad);
+            indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+            let cell = [
+                (idx / self.field.width) as u32,
+                (idx % self.field.width) as u32,
+                self.field.layers[idx],
+            ];
+            face_cells.extend_from_slice(&cell);
+            face_cells.extend_from_slice(&cell);
+            face_costs.extend_from_slice(&[self.field.costs[idx]; 2]);
+        }
+        let mut mesh = MeshBuffers::new(vertices, indices);
+        if let Some(transform) = transform {
+            output_space::apply_mesh_buffers(transform, &mut mesh);
+        }
+        Remeshed {
+            mesh,
+            face_cells,
+            face_costs,
+            remeshed,
+        }
+    }
+}
+
+/// Cell-level editor over a baked room-floor heightfield.
+#[wasm_bindgen]
+pub struct FloorEditor {
+    edit: FloorEdit,
+    settings: MeshSettings,
+}
+
+#[wasm_bindgen]
+impl FloorEditor {
+    /// Open `heightfield` (the `heightfield` of a room-floor result) for
+    /// editing. `settings` supplies `output_space` and `typed_buffers` for
+    /// `remesh`; pass the bake settings.
+    #[wasm_bindgen(constructor)]
+    pub fn new(heightfield: JsValue, settings: JsValue) -> Result<FloorEditor, JsValue> {
+        let field: Heightfield = serde_wasm_bindgen::from_value(heightfield)
+            .map_err(|e| SplatwalkError::InvalidSettings(format!("Invalid heightfield: {}", e)))?;
+        field.validate()?;
+        Ok(FloorEditor {
+            edit: FloorEdit::new(field),
+            settings: crate::parse_settings(settings)?,
+        })
+    }
+
+    /// Set a cell's floor height, making it floor if it was not.
+    pub fn set_height(&mut self, row: usize, col: usize, height: f64) -> Result<(), JsValue> {
+        if !height.is_finite() {
+            return Err(SplatwalkError::InvalidSettings(format!(
+                "Invalid floor height: {}",
+                height
+            ))
+            .into());
+        }
+        let idx = self.edit.field.cell(row, col)?;
+        self.edit.set_height(idx, height as f32);
+        Ok(())
+    }
+
+    /// Remove a cell from the floor (unwalkable). Returns whether it was floor.
+    pub fn clear_cell(&mut self, row: usize, col: usize) -> Result<bool, JsValue> {
+        let idx = self.edit.field.cell(row, col)?;
+        let was_floor = self.edit.field.heights[idx].is_finite();
+        self.edit.set_height(idx, f32::NAN);
+        Ok(was_floor)
+    }
+
+    /// Flatten the floor cells in the inclusive rectangle `row0..=row1` x
+    /// `col0..=col1` to `height`, or to their mean height when omitted. Cells
+    /// that are not floor stay cleared. Returns the number of cells flattened.
+    pub fn flatten_region(
+        &mut self,
+        row0: usize,
+        col0: usize,
+        row1: usize,
+        col1: usize,
+        height: Option<f64>,
+    ) -> Result<usize, JsValue> {
+        if let Some(h) = height.filter(|h| !h.is_finite()) {
+            return Err(
+                SplatwalkError::InvalidSettings(format!("Invalid floor height: {}", h)).into(),
+            );
+        }
+        Ok(self
+            .edit
+            .flatten((row0, row1), (col0, col1), height.map(|h| h as f32)))
+    }
+
+    /// Apply a brush stroke (`{ shape, op, center, radius, min, max, delta,
+    /// height, strength }`) at world-space points in the editor's
+    /// `output_space`. Returns the number of cells whose height changed.
+    pub fn apply_brush(&mut self, brush: JsValue) -> Result<usize, JsValue> {
+        let stroke: BrushStroke =
+            serde_wasm_bindgen::from_value(brush).map_err(|e| invalid_brush(e.to_string()))?;
+        let transform = output_space::transform_for(&self.settings);
+        let to_field = |p: [f64; 3]| transform.as_ref().map_or(p, |t| t.unapply(p));
+        let (shape, op) = stroke.resolve(&self.edit.field, &to_field)?;
+        Ok(self.edit.brush(&shape, &op))
+    }
+
+    /// Set a floor cell's path cost multiplier (> 0). Returns whether it
+    /// changed. Cells that are not floor keep their cost for if they are
+    /// added back.
+    pub fn set_cost(&mut self, row: usize, col: usize, cost: f64) -> Result<bool, JsValue> {
+        if !path_cost::valid_cost(cost) {
+            return Err(
+                SplatwalkError::InvalidSettings(format!("Invalid path cost: {}", cost)).into(),
+            );
+        }
+        let idx = self.edit.field.cell(row, col)?;
+        Ok(self.edit.set_cost(idx, cost as f32))
+    }
+
+    /// Cells edited since the last `remesh`.
+    pub fn dirty_count(&self) -> usize {
+        self.edit.dirty.iter().filter(|&&d| d).count()
+    }
+
+    /// Re-emit the floor mesh, rebuilding only the quads of edited cells.
+    pub fn remesh(&mut self) -> Result<JsValue, JsValue> {
+        let transform = output_space::transform_for(&self.settings);
+        let out = self.edit.remesh(transform.as_ref());
+        let field = &self.edit.field;
+        let mut result = FloorRemeshResult {
+            api_version: crate::API_VERSION,
+            semver: crate::core_semver(),
+            capabilities: crate::capabilities(),
+            cell_count: out.mesh.face_count / 2,
+            mesh: out.mesh,
+            face_cells: FaceCells {
+                grid_width: field.width,
+                grid_height: field.height,
+                cell_size: field.cell_size,
+                cells: out.face_cells,
+            },
+            path_costs: PathCosts::from_face_costs(out.face_costs),
+            space: transform
+                .map(|t| t.coordinate_space())
+                .unwrap_or_else(CoordinateSpace::splatwalk_oriented),
+            remeshed_cells: out.remeshed,
+        };
+        let typed = TypedMesh::take(&self.settings, &mut result.mesh);
+        let value = serde_wasm_bindgen::to_value(&result)?;
+        if let Some(typed) = typed {
+            typed.attach(&value, &["mesh"])?;
+        }
+        Ok(value)
+    }
+
+    /// The edited heightfield, to save or reopen later.
+    pub fn heightfield(&self) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.edit.field)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field() -> Heightfield {
+        // 3 x 4 grid, the last column not floor.
+        let mut heights = vec![0.0_f32; 12];
+        for row in 0..3 {
+            heights[row * 4 + 3] = f32::NAN;
+        }
+        Heightfield {
+            width: 4,
+            height: 3,
+            cell_size: 0.5,
+            basis: FieldBasis {
+                origin: [0.0; 3],
+                tangent: [1.0, 0.0, 0.0],
+                bitangent: [0.0, 0.0, 1.0],
+                up: [0.0, 1.0, 0.0],
+            },
+            heights,
+            layers: vec![0; 12],
+            costs: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn edits_only_remesh_touched_cells() {
+        let mut edit = FloorEdit::new(field());
+        let out = edit.remesh(None);
+        let (mesh, cells, remeshed) = (out.mesh, out.face_cells, out.remeshed);
+        assert_eq!(remeshed, 9);
+        assert_eq!(mesh.face_count, 18);
+        assert_eq!(cells.len(), 18 * 3);
+
+        // Raise (0, 0), clear (1, 1), add (2, 3).
+        edit.set_height(0, 0.25);
+        edit.set_height(5, f32::NAN);
+        edit.set_height(11, 0.1);
+        let out = edit.remesh(None);
+        let (mesh, cells, remeshed) = (out.mesh, out.face_cells, out.remeshed);
+        assert_eq!(remeshed, 2);
+        assert_eq!(mesh.face_count, 18);
+        assert!(cells.chunks(3).all(|c| (c[0], c[1]) != (1, 1)));
+        assert!(cells.chunks(3).any(|c| (c[0], c[1]) == (2, 3)));
+        assert_eq!(mesh.vertices[1], 0.25);
+
+        // Flatten the top two rows to their mean; the cleared cell stays out.
+        assert_eq!(edit.flatten((0, 1), (0, 3), None), 5);
+        assert_eq!(edit.remesh(None).remeshed, 5);
+        let mean = 0.25 / 5.0;
+        assert!((edit.field.heights[0] - mean).abs() < 1e-6);
+        assert!(edit.field.heights[5].is_nan());
+        assert_eq!(edit.remesh(None).remeshed, 0);
+    }
+
+    fn stroke(op: &str) -> BrushStroke {
+        BrushStroke {
+            shape: None,
+            op: op.to_string(),
+            center: Some([1.0, 0.0, 0.75]),
+            radius: Some(0.6),
+            min: None,
+            max: None,
+            delta: Some(0.2),
+            height: None,
+            strength: None,
+            cost: None,
+        }
+    }
+
+    #[test]
+    fn brushes_edit_cells_under_world_footprint() {
+        let mut edit = FloorEdit::new(field());
+        let identity = |p: [f64; 3]| p;
+        let apply = |edit: &mut FloorEdit, stroke: &BrushStroke| {
+            let (shape, op) = stroke.resolve(&edit.field, &identity).unwrap();
+            edit.brush(&shape, &op)
+        };
+
+        // Circle at grid (2, 1.5) with radius 1.2 cells covers the centres of
+        // cols 1..=2 in rows 0..=2 (distance 0.5 or ~1.12) but not col 0 / 3.
+        assert_eq!(apply(&mut edit, &stroke("raise")), 6);
+        assert_eq!(edit.field.heights[1], 0.2);
+        assert_eq!(edit.field.heights[0], 0.0);
+        assert_eq!(apply(&mut edit, &stroke("lower")), 6);
+        assert_eq!(edit.field.heights[1], 0.0);
+
+        // Box over the non-floor column adds it at the picked height.
+        let mut add = stroke("add");
+        add.shape = Some("box".to_string());
+        add.min = Some([1.6, 0.3, 0.0]);
+        add.max = Some([2.0, 0.3, 1.5]);
+        assert_eq!(apply(&mut edit, &add), 3);
+        assert!((edit.field.heights[7] - 0.3).abs() < 1e-6);
+
+        // Smoothing pulls a spike toward its neighbours; removal clears cells.
+        edit.set_height(5, 1.0);
+        let mut smooth = stroke("smooth");
+        smooth.radius = Some(0.1);
+        smooth.center = Some([0.75, 0.0, 0.75]);
+        smooth.strength = Some(1.0);
+        assert_eq!(apply(&mut edit, &smooth), 1);
+        assert!((edit.field.heights[5] - 1.0 / 9.0).abs() < 1e-6);
+        assert_eq!(apply(&mut edit, &stroke("remove")), 6);
+        assert!(edit.field.heights[6].is_nan());
+
+        // Cost painting leaves geometry alone and skips cells that are not floor.
+        let mut cost = stroke("cost");
+        cost.cost = Some(5.0);
+        cost.center = Some([0.25, 0.0, 0.25]);
+        edit.remesh(None);
+        // Covers (0, 0), (1, 0) and the removed (0, 1).
+        assert_eq!(apply(&mut edit, &cost), 2);
+        let out = edit.remesh(None);
+        assert_eq!(out.remeshed, 0);
+        assert_eq!(out.face_costs.iter().filter(|&&c| c == 5.0).count(), 4);
+
+        let mut bad = stroke("paint");
+        assert!(bad.resolve(&edit.field, &identity).is_err());
+        bad = stroke("raise");
+        bad.radius = Some(0.0);
+        assert!(bad.resolve(&edit.field, &identity).is_err());
+    }
+}
@@ -0,0 +1,256 @@
+//! Floor layers of multi-storey scans (`emit_floor_layers`).
+//!
+//! The `walkable_floors` mesh holds every storey at once, and a host that
+//! shows, edits or streams one floor at a time has to pull them apart again.
+//! [`build`] does that on the carved span field, the way Recast builds its
+//! heightfield layers: starting from the lowest unassigned span, a layer
+//! floods to neighbouring spans within `agent_max_climb`, but never takes a
+//! second span of a column it already holds. Each layer is therefore a
+//! single surface per column: a storey, with the stairs joined to whichever
+//! floor reached them first. Layers are numbered bottom up.
+
+use std::collections::VecDeque;
+
+use serde::Serialize;
+
+use crate::navmesh::SpanField;
+use crate::{MeshBuffers, MeshSettings};
+
+/// Column offsets of the four neighbours.
+const NEIGHBOURS: [(i64, i64); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+
+/// One storey's walkable surface.
+#[derive(Serialize)]
+pub struct FloorLayer {
+    /// Two triangles per floor voxel, wound like `mesh`.
+    pub mesh: MeshBuffers,
+    /// Lowest and highest floor in the layer, in metres.
+    pub min_y: f64,
+    pub max_y: f64,
+    /// Floor area in square metres.
+    pub area: f64,
+}
+
+/// The floor layers of a span field.
+#[derive(Serialize)]
+pub struct FloorLayers {
+    /// Bottom up; a layer's ID is its index.
+    pub layers: Vec<FloorLayer>,
+    /// Pairs of layer IDs (lower first) with floor linked within
+    /// `agent_max_climb` across a column edge: where stairs or ramps join.
+    pub connections: Vec<[u32; 2]>,
+    /// Layers under `floor_layer_min_area` left out of `layers`.
+    pub dropped: usize,
+}
+
+/// `floor_layer_min_area` must be finite and non-negative.
+pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+    if let Some(area) = settings.floor_layer_min_area {
+        if !(area.is_finite() && area >= 0.0) {
+            return Err(format!("Invalid floor_layer_min_area: {}", area));
+        }
+    }
+    Ok(())
+}
+
+/// Split `field` into layers. `max_climb` is in voxels, `min_area` in
+/// square metres.
+pub fn build(field: &SpanField, max_climb: i32, min_area: f64) -> FloorLayers {
+    // Spans flattened column by column; `first[c]..first[c + 1]` is column c.
+    let mut first = Vec::with_capacity(field.columns.len() + 1);
+    let mut floors = Vec::new();
+    let mut column_of = Vec::new();
+    for (c, column) in field.columns.iter().enumerate() {
+        first.push(floors.len());
+        for span in column {
+            floors.push(span.floor);
+            column_of.push(c);
+        }
+    }
+    first.push(floors.len());
+    let neighbours = |s: usize| {
+        let c = column_of[s];
+        let (x, z) = ((c % field.width) as i64, (c / field.width) as i64);
+        NEIGHBOURS.iter().filter_map(move |&(dx, dz)| {
+            let (nx, nz) = (x + dx, z + dz);
+            let inside =
+                nx >= 0 && nz >= 0 && (nx as usize) < field.width && (nz as usize) < field.depth;
+            inside.then(|| nz as usize * field.width + nx as usize)
+        })
+    };
+
+    let mut order: Vec<usize> = (0..floors.len()).collect();
+    order.sort_by_key(|&s| (floors[s], s));
+    let mut layer_of = vec![u32::MAX; floors.len()];
+    // Per column: the last layer that took one of its spans.
+    let mut column_layer = vec![u32::MAX; field.columns.len()];
+    let mut sizes: Vec<usize> = Vec::new();
+    for &seed in &order {
+        if layer_of[seed] != u32::MAX {
+            continue;
+        }
+        let layer = sizes.len() as u32;
+        let mut size = 1;
+        layer_of[seed] = layer;
+        column_layer[column_of[seed]] = layer;
+        let mut queue = VecDeque::from([seed]);
+        while let Some(s) = queue.pop_front() {
+            for nc in neighbours(s) {
+                if column_layer[nc] == layer {
+                    continue;
+                }
+                let next = (first[nc]..first[nc + 1])
+                    .filter(|&t| layer_of[t] == u32::MAX)
+                    .filter(|&t| (floors[t] - floors[s]).abs() <= max_climb)
+                    .min_by_key(|&t| (floors[t] - floors[s]).abs());
+                if let Some(t) = next {
+                    layer_of[t] = layer;
+                    column_layer[nc] = layer;
+                    size += 1;
+                    queue.push_back(t);
+                }
+            }
+        }
+        sizes.push(size);
+    }
+
+    // Keep the layers at least `min_area`, renumbered bottom up by mean floor.
+    let cell_area = field.cell_size * field.cell_size;
+    let mut mean = vec![0.0f64; sizes.len()];
+    for (s, &layer) in layer_of.iter().enumerate() {
+        mean[layer as usize] += floors[s] as f64 / sizes[layer as usize] as f64;
+    }
+    let mut kept: Vec<usize> = (0..sizes.len())
+        .filter(|&l| sizes[l] as f64 * cell_area >= min_area)
+        .collect();
+    kept.sort_by(|&a, &b| mean[a].total_cmp(&mean[b]).then(a.cmp(&b)));
+    let mut id = vec![u32::MAX; sizes.len()];
+    for (new, &old) in kept.iter().enumerate() {
+        id[old] = new as u32;
+    }
+
+    let cs = field.cell_size;
+    let [ox, oy, oz] = field.origin;
+    let mut layers: Vec<FloorLayer> = kept
+        .iter()
+        .map(|&old| FloorLayer {
+            mesh: MeshBuffers::new(Vec::new(), Vec::new()),
+            min_y: f64::INFINITY,
+            max_y: f64::NEG_INFINITY,
+            area: sizes[old] as f64 * cell_area,
+        })
+        .collect();
+    let mut connections = Vec::new();
+    for s in 0..floors.len() {
+        let Some(layer) = layers.get_mut(id[layer_of[s] as usize] as usize) else {
+            continue;
+        };
+        let c = column_of[s];
+        let (x, z) = ((c % field.width) as f64, (c / field.width) as f64);
+        let y = oy + floors[s] as f64 * cs;
+        layer.min_y = layer.min_y.min(y);
+        layer.max_y = layer.max_y.max(y);
+        let base = (layer.mesh.vertices.len() / 3) as u32;
+        for (dx, dz) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)] {
+            layer.mesh.vertices.extend_from_slice(&[
+                (ox + (x + dx) * cs) as f32,
+                y as f32,
+                (oz + (z + dz) * cs) as f32,
+            ]);
+        }
+        layer
+            .mesh
+            .indices
+            .extend([0, 2, 1, 0, 3, 2].iter().map(|i| base + i));
+
+        let a = id[layer_of[s] as usize];
+        for nc in neighbours(s) {
+            for t in first[nc]..first[nc + 1] {
+                let b = id[layer_of[t] as usize];
+                if b != a && b != u32::MAX && (floors[t] - floors[s]).abs() <= max_climb {
+                    connections.push([a.min(b), a.max(b)]);
+                }
+            }
+        }
+    }
+    connections.sort_unstable();
+    connections.dedup();
+    // Rebuild each mesh from its finished buffers for the counts and normals.
+    for layer in &mut layers {
+        let vertices = std::mem::take(&mut layer.mesh.vertices);
+        let indices = std::mem::take(&mut layer.mesh.indices);
+        layer.mesh = MeshBuffers::new(vertices, indices);
+    }
+    FloorLayers {
+        dropped: sizes.len() - layers.len(),
+        layers,
+        connections,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::navmesh::Span;
+
+    #[test]
+    fn stacked_floors_become_layers_joined_by_stairs() {
+        // A 12 x 2 ground floor at level 1, a 6 x 2 upper floor at level 9
+        // over its left half, stairs down from the upper floor's right edge
+        // to a landing beside the ground floor two levels above it, and a
+        // lone shelf at level 20.
+        let (width, depth) = (13, 2);
+        let mut columns = vec![Vec::new(); width * depth];
+        let mut floor = |x: usize, z: usize, level: i32| {
+            columns[z * width + x].push(Span {
+                floor: level,
+                ceiling: level + 20,
+            });
+        };
+        for z in 0..depth {
+            for x in 0..12 {
+                floor(x, z, 1);
+            }
+            for x in 0..6 {
+                floor(x, z, 9);
+            }
+            for (i, x) in (6..12).enumerate() {
+                floor(x, z, 8 - i as i32);
+            }
+            floor(12, z, 3);
+        }
+        floor(10, 0, 20);
+        for column in &mut columns {
+            column.sort_by_key(|s| s.floor);
+        }
+        let field = SpanField {
+            width,
+            depth,
+            origin: [0.0, 0.0, 0.0],
+            cell_size: 0.5,
+            columns,
+        };
+        let layers = build(&field, 1, 0.5);
+        assert_eq!(layers.layers.len(), 2);
+        assert_eq!(layers.dropped, 1);
+        let [ground, upper] = [&layers.layers[0], &layers.layers[1]];
+        assert_eq!((ground.min_y, ground.max_y), (0.5, 0.5));
+        assert!((ground.area - 12.0 * 2.0 * 0.25).abs() < 1e-9);
+        // One quad per cell, facing up.
+        assert_eq!(ground.mesh.vertex_count, 24 * 4);
+        assert_eq!(ground.mesh.face_count, 24 * 2);
+        assert!(ground
+            .mesh
+            .normals
+            .chunks_exact(3)
+            .all(|n| n == [0.0, 1.0, 0.0]));
+        // The upper floor takes its stairs and the landing, two levels above
+        // the ground floor, so the layers do not connect.
+        assert_eq!((upper.min_y, upper.max_y), (1.5, 4.5));
+        assert!(layers.connections.is_empty());
+
+        // With a climb of two voxels the landing steps down to the ground.
+        let layers = build(&field, 2, 0.5);
+        assert_eq!(layers.connections, [[0, 1]]);
+    }
+}
@@ -0,0 +1,594 @@
+//! Vector floor plan from the wall normal cluster.
+//!
+//! Wall-group splats (see `cluster`) between `floor_plan_min_height` and
+//! `floor_plan_max_height` above the floor are projected onto the floor plane
+//! and rasterized into `floor_plan_cell_size` cells; cells with less than one
+//! opaque splat's worth of weight are dropped as noise. Line segments are then
+//! fitted to the cell centres by sequential RANSAC (fit the best line, split
+//! its inliers into runs at `wall_max_gap`, keep runs of at least
+//! `wall_min_length`, remove the inliers, repeat).
+//!
+//! Regularization finds the dominant orientation as the length-weighted mean of
+//! the segment angles modulo 90 degrees, and rotates every segment within
+//! `wall_snap_deg` of it (or of its perpendicular) about its midpoint onto that
+//! axis, so near-orthogonal rooms come out square.
+//!
+//! The floor outline is the outer boundary of the walkable ground-field
+//! component, traced along cell edges and simplified with Douglas-Peucker at
+//! one cell, so diagonal walls do not come out as staircases.
+//!
+//! Plan coordinates are oriented-space `[x, z]` in metres; `cad_export` turns
+//! them into SVG, DXF and IFC.
+
+use nalgebra::Point3;
+use poisson_reconstruction::Real;
+use rand::Rng;
+use serde::Serialize;
+
+use crate::cluster::NormalGroup;
+use crate::ransac::{self, RansacOptions, Score};
+use crate::splat::PointNormal;
+use crate::MeshSettings;
+
+/// Minimum accumulated opacity for a plan cell to count as wall.
+const MIN_CELL_WEIGHT: f64 = 1.0;
+
+/// Consecutive lines without a usable run before fitting gives up.
+const MAX_BARREN_LINES: usize = 3;
+
+#[derive(Clone, Copy, Debug)]
+pub struct FloorPlanOptions {
+    pub cell_size: f64,
+    pub min_height: f64,
+    pub max_height: f64,
+    pub line_tolerance: f64,
+    pub min_length: f64,
+    pub max_gap: f64,
+    /// Snap window in degrees; 0 disables regularization.
+    pub snap_deg: f64,
+    pub max_segments: usize,
+}
+
+impl FloorPlanOptions {
+    pub fn from_settings(settings: &MeshSettings) -> Self {
+        let positive = |v: Option<f64>, default: f64| {
+            v.filter(|v| v.is_finite() && *v > 0.0).unwrap_or(default)
+        };
+        let cell_size = positive(settings.floor_plan_cell_size, 0.05);
+        let min_height = settings
+            .floor_plan_min_height
+            .filter(|v| v.is_finite())
+            .unwrap_or(0.3);
+        Self {
+            cell_size,
+            min_height,
+            max_height: settings
+                .floor_plan_max_height
+                .filter(|v| v.is_finite() && *v > min_height)
+                .unwrap_or(min_height.max(0.0) + 1.7),
+            line_tolerance: positive(settings.wall_line_tolerance, cell_size),
+            min_length: positive(settings.wall_min_length, 0.3),
+            max_gap: positive(settings.wall_max_gap, 0.3),
+            snap_deg: settings
+                .wall_snap_deg
+                .filter(|v| v.is_finite())
+                .unwrap_or(10.0)
+                .clamp(0.0, 45.0),
+            max_segments: settings.wall_max_segments.unwrap_or(64).max(1),
+        }
+    }
+}
+
+/// One fitted wall line, in oriented-space `[x, z]` metres.
+#[derive(Clone, Debug, Serialize)]
+pub struct WallSegment {
+    pub start: [f64; 2],
+    pub end: [f64; 2],
+    pub length: f64,
+    /// Plan cells supporting the segment.
+    pub support: usize,
+    /// Whether regularization rotated it onto a dominant axis.
+    pub snapped: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct FloorPlan {
+    pub floor_y: f64,
+    /// Dominant wall orientation in `[0, 90)` degrees from `+x` towards `+z`.
+    pub dominant_angle_deg: f64,
+    pub wall_splat_count: usize,
+    pub wall_cell_count: usize,
+    pub segments: Vec<WallSegment>,
+}
+
+/// Infinite 2D line through `origin` along unit `dir`.
+#[derive(Clone, Copy)]
+struct Line {
+    origin: [f64; 2],
+    dir: [f64; 2],
+}
+
+impl Line {
+    fn through(a: [f64; 2], b: [f64; 2]) -> Option<Self> {
+        let (dx, dz) = (b[0] - a[0], b[1] - a[1]);
+        let len = (dx * dx + dz * dz).sqrt();
+        (len > 1e-9).then(|| Line {
+            origin: a,
+            dir: [dx / len, dz / len],
+        })
+    }
+
+    fn distance(&self, p: [f64; 2]) -> f64 {
+        ((p[0] - self.origin[0]) * self.dir[1] - (p[1] - self.origin[1]) * self.dir[0]).abs()
+    }
+
+    fn along(&self, p: [f64; 2]) -> f64 {
+        (p[0] - self.origin[0]) * self.dir[0] + (p[1] - self.origin[1]) * self.dir[1]
+    }
+
+    fn at(&self, t: f64) -> [f64; 2] {
+        [
+            self.origin[0] + self.dir[0] * t,
+            self.origin[1] + self.dir[1] * t,
+        ]
+    }
+}
+
+/// Centres of plan cells holding at least [`MIN_CELL_WEIGHT`] of wall splats.
+pub fn wall_cells(
+    points: &[PointNormal],
+    labels: &[NormalGroup],
+    floor_y: f64,
+    options: &FloorPlanOptions,
+) -> (Vec<[f64; 2]>, usize) {
+    let lo = floor_y + options.min_height;
+    let hi = floor_y + options.max_height;
+    let mut weights: std::collections::HashMap<(i64, i64), f64> = Default::default();
+    let mut splats = 0;
+    for (p, _) in points
+        .iter()
+        .zip(labels)
+        .filter(|(p, &l)| l == NormalGroup::Wall && p.point.y >= lo && p.point.y <= hi)
+    {
+        splats += 1;
+        let key = (
+            (p.point.x / options.cell_size).floor() as i64,
+            (p.point.z / options.cell_size).floor() as i64,
+        );
+        *weights.entry(key).or_default() += 1.0 / (1.0 + (-p.opacity).exp());
+    }
+    let mut cells: Vec<[f64; 2]> = weights
+        .into_iter()
+        .filter(|(_, w)| *w >= MIN_CELL_WEIGHT)
+        .map(|((i, k), _)| {
+            [
+                (i as f64 + 0.5) * options.cell_size,
+                (k as f64 + 0.5) * options.cell_size,
+            ]
+        })
+        .collect();
+    // HashMap order is arbitrary; sort so seeded RANSAC is reproducible.
+    cells.sort_by(|a, b| a[0].total_cmp(&b[0]).then(a[1].total_cmp(&b[1])));
+    (cells, splats)
+}
+
+/// Total-least-squares line through `points` (centroid + principal axis).
+fn refit(points: &[[f64; 2]]) -> Option<Line> {
+    let n = points.len() as f64;
+    if points.len() < 2 {
+        return None;
+    }
+    let cx = points.iter().map(|p| p[0]).sum::<f64>() / n;
+    let cz = points.iter().map(|p| p[1]).sum::<f64>() / n;
+    let (mut sxx, mut sxz, mut szz) = (0.0, 0.0, 0.0);
+    for p in points {
+        let (dx, dz) = (p[0] - cx, p[1] - cz);
+        sxx += dx * dx;
+        sxz += dx * dz;
+        szz += dz * dz;
+    }
+    let angle = 0.5 * (2.0 * sxz).atan2(sxx - szz);
+    Some(Line {
+        origin: [cx, cz],
+        dir: [angle.cos(), angle.sin()],
+    })
+}
+
+/// Sequential RANSAC line extraction over plan cell centres.
+pub fn fit_segments(cells: &[[f64; 2]], options: &FloorPlanOptions, seed: u64) -> Vec<WallSegment> {
+    let min_support = ((options.min_length / options.cell_size).ceil() as usize).max(2);
+    let mut remaining = cells.to_vec();
+    let mut segments = Vec::new();
+    let mut barren = 0;
+    while segments.len() < options.max_segments
+        && remaining.len() >= min_support
+        && barren < MAX_BARREN_LINES
+    {
+        let coords: Vec<Point3<Real>> = remaining
+            .iter()
+            .map(|p| Point3::new(p[0], 0.0, p[1]))
+            .collect();
+        let mut ransac_options = RansacOptions::new(400, Some(0));
+        ransac_options.verify_top = 1;
+        ransac_options.seed = seed.wrapping_add(segments.len() as u64 + barren as u64);
+        let n = remaining.len();
+        let outcome = ransac::run(
+            &coords,
+            ransac_options,
+            |rng| {
+                let a = rng.gen_range(0..n);
+                let b = rng.gen_range(0..n);
+                if a == b {
+                    return None;
+                }
+                Line::through(remaining[a], remaining[b])
+            },
+            |line, pts, weight| {
+                let inliers = pts
+                    .iter()
+                    .filter(|p| line.distance([p.x, p.z]) < options.line_tolerance)
+                    .count();
+                Score {
+                    score: inliers as f64 * weight,
+                    inliers,
+                }
+            },
+            |_, _| false,
+        );
+        let Some((line, _)) = outcome.candidates.first() else {
+            break;
+        };
+        let (inliers, rest): (Vec<[f64; 2]>, Vec<[f64; 2]>) = remaining
+            .iter()
+            .partition(|p| line.distance(**p) < options.line_tolerance);
+        let line = refit(&inliers).unwrap_or(*line);
+        let before = segments.len();
+        split_runs(&line, &inliers, min_support, options, &mut segments);
+        barren = if segments.len() > before {
+            0
+        } else {
+            barren + 1
+        };
+        remaining = rest;
+    }
+    segments.truncate(options.max_segments);
+    segments
+}
+
+/// Split the inliers of `line` into runs separated by more than `max_gap`,
+/// keeping the runs long and dense enough to be walls.
+fn split_runs(
+    line: &Line,
+    inliers: &[[f64; 2]],
+    min_support: usize,
+    options: &FloorPlanOptions,
+    out: &mut Vec<WallSegment>,
+) {
+    let mut ts: Vec<f64> = inliers.iter().map(|p| line.along(*p)).collect();
+    ts.sort_by(f64::total_cmp);
+    let half = options.cell_size * 0.5;
+    let mut start = 0;
+    for i in 1..=ts.len() {
+        if i < ts.len() && ts[i] - ts[i - 1] <= options.max_gap {
+            continue;
+        }
+        let (t0, t1) = (ts[start] - half, ts[i - 1] + half);
+        let support = i - start;
+        if support >= min_support && t1 - t0 >= options.min_length {
+            out.push(WallSegment {
+                start: line.at(t0),
+                end: line.at(t1),
+                length: t1 - t0,
+                support,
+                snapped: false,
+            });
+        }
+        start = i;
+    }
+}
+
+/// Dominant orientation (degrees in `[0, 90)`): the length-weighted circular
+/// mean of the segment angles on the 4-fold (90 degree periodic) circle.
+pub fn dominant_angle(segments: &[WallSegment]) -> f64 {
+    let (mut s, mut c) = (0.0, 0.0);
+    for seg in segments {
+        let angle = (seg.end[1] - seg.start[1]).atan2(seg.end[0] - seg.start[0]);
+        s += seg.length * (4.0 * angle).sin();
+        c += seg.length * (4.0 * angle).cos();
+    }
+    if s == 0.0 && c == 0.0 {
+        return 0.0;
+    }
+    (s.atan2(c) / 4.0).to_degrees().rem_euclid(90.0)
+}
+
+/// Rotate segments within `snap_deg` of the dominant axes onto them.
+pub fn regularize(segments: &mut [WallSegment], dominant_deg: f64, snap_deg: f64) {
+    if snap_deg <= 0.0 {
+        return;
+    }
+    for seg in segments {
+        let angle = (seg.end[1] - seg.start[1])
+            .atan2(seg.end[0] - seg.start[0])
+            .to_degrees();
+        let offset = (angle - dominant_deg).rem_euclid(90.0);
+        let delta = if offset > 45.0 {
+            90.0 - offset
+        } else {
+            -offset
+        };
+        if delta.abs() > snap_deg || delta == 0.0 {
+            continue;
+        }
+        let target = (angle + delta).to_radians();
+        let mid = [
+            (seg.start[0] + seg.end[0]) * 0.5,
+            (seg.start[1] + seg.end[1]) * 0.5,
+        ];
+        let half = [
+            target.cos() * seg.length * 0.5,
+            target.sin() * seg.length * 0.5,
+        ];
+        seg.start = [mid[0] - half[0], mid[1] - half[1]];
+        seg.end = [mid[0] + half[0], mid[1] + half[1]];
+        seg.snapped = true;
+    }
+}
+
+/// Closed outer boundary of the `true` cells of a `width` x `height` mask, as
+/// grid-corner `[col, row]` coordinates, counter-clockwise, collinear corners
+/// removed. When the mask has several islands the one with the largest
+/// enclosed area wins; holes are not reported.
+pub fn trace_outline(mask: &[bool], width: usize, height: usize) -> Vec<[f64; 2]> {
+    let mut best: Vec<[f64; 2]> = Vec::new();
+    let mut best_area = 0.0;
+    for (ring, _) in trace_rings(mask, width, height) {
+        let area = signed_area(&ring);
+        if area > best_area {
+            best_area = area;
+            best = ring;
+        }
+    }
+    best
+}
+
+/// Every closed boundary of the `true` cells of a `width` x `height` mask,
+/// as grid-corner `[col, row]` rings with collinear corners removed, each
+/// with the index of a cell it bounds. Rings keep their cells on the left,
+/// so outer boundaries run counter-clockwise and holes clockwise. Cells that
+/// touch only at a corner get separate rings.
+pub fn trace_rings(mask: &[bool], width: usize, height: usize) -> Vec<(Vec<[f64; 2]>, usize)> {
+    let inside = |c: isize, r: isize| {
+        c >= 0
+            && r >= 0
+            && (c as usize) < width
+            && (r as usize) < height
+            && mask[r as usize * width + c as usize]
+    };
+    // Directed boundary edges, keeping each cell on the left.
+    type Corner = (usize, usize);
+    let mut edges: std::collections::HashMap<Corner, Vec<(Corner, usize)>> = Default::default();
+    for r in 0..height {
+        for c in 0..width {
+            let cell = r * width + c;
+            if !mask[cell] {
+                continue;
+            }
+            let (ci, ri) = (c as isize, r as isize);
+            let mut edge = |from: Corner, to: Corner| {
+                edges.entry(from).or_default().push((to, cell));
+            };
+            if !inside(ci, ri - 1) {
+                edge((c, r), (c + 1, r));
+            }
+            if !inside(ci + 1, ri) {
+                edge((c + 1, r), (c + 1, r + 1));
+            }
+            if !inside(ci, ri + 1) {
+                edge((c + 1, r + 1), (c, r + 1));
+            }
+            if !inside(ci - 1, ri) {
+                edge((c, r + 1), (c, r));
+            }
+        }
+    }
+
+    let step = |a: Corner, b: Corner| [b.0 as isize - a.0 as isize, b.1 as isize - a.1 as isize];
+    let mut starts: Vec<Corner> = edges.keys().copied().collect();
+    starts.sort_unstable();
+    let mut rings = Vec::new();
+    for start in starts {
+        while let Some((mut next, cell)) = edges.get_mut(&start).and_then(|v| v.pop()) {
+            let mut ring = vec![start];
+            let mut from = start;
+            while next != start {
+                ring.push(next);
+                let Some(out) = edges.get_mut(&next).filter(|v| !v.is_empty()) else {
+                    break;
+                };
+                // Where two cells meet at a corner, turn left to stay on the
+                // cell the ring came along.
+                let turn = |to: Corner| {
+                    let (d, e) = (step(from, next), step(next, to));
+                    d[0] * e[1] - d[1] * e[0]
+                };
+                let pick = (0..out.len()).max_by_key(|&i| turn(out[i].0)).unwrap_or(0);
+                let to = out.swap_remove(pick).0;
+                from = next;
+                next = to;
+            }
+            let ring: Vec<[f64; 2]> = ring.iter().map(|&(c, r)| [c as f64, r as f64]).collect();
+            rings.push((drop_collinear(&ring), cell));
+        }
+    }
+    rings
+}
+
+/// Shoelace area; positive for counter-clockwise rings (in `[col, row]`).
+pub fn signed_area(ring: &[[f64; 2]]) -> f64 {
+    let n = ring.len();
+    (0..n)
+        .map(|i| {
+            let (a, b) = (ring[i], ring[(i + 1) % n]);
+            a[0] * b[1] - b[0] * a[1]
+        })
+        .sum::<f64>()
+        * 0.5
+}
+
+fn drop_collinear(ring: &[[f64; 2]]) -> Vec<[f64; 2]> {
+    let n = ring.len();
+    (0..n)
+        .filter(|&i| {
+            let (a, b, c) = (ring[(i + n - 1) % n], ring[i], ring[(i + 1) % n]);
+            ((b[0] - a[0]) * (c[1] - b[1]) - (b[1] - a[1]) * (c[0] - b[0])).abs() > 1e-9
+        })
+        .map(|i| ring[i])
+        .collect()
+}
+
+/// Douglas-Peucker over a closed ring: split at the vertex farthest from the
+/// first one and simplify both chains, so staircase diagonals collapse into
+/// single edges.
+pub fn simplify_ring(ring: &[[f64; 2]], tolerance: f64) -> Vec<[f64; 2]> {
+    if ring.len() <= 4 {
+        return ring.to_vec();
+    }
+    let d2 = |a: [f64; 2], b: [f64; 2]| (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2);
+    let far = (1..ring.len())
+        .max_by(|&i, &j| d2(ring[0], ring[i]).total_cmp(&d2(ring[0], ring[j])))
+        .unwrap_or(1);
+    let mut first = ring[..=far].to_vec();
+    let mut second = ring[far..].to_vec();
+    second.push(ring[0]);
+    douglas_peucker(&mut first, tolerance);
+    douglas_peucker(&mut second, tolerance);
+    first.pop();
+    second.pop();
+    first.extend(second);
+    first
+}
+
+fn douglas_peucker(chain: &mut Vec<[f64; 2]>, tolerance: f64) {
+    if chain.len() <= 2 {
+        return;
+    }
+    let (a, b) = (chain[0], chain[chain.len() - 1]);
+    let Some(line) = Line::through(a, b) else {
+        return;
+    };
+    let (index, dist) = (1..chain.len() - 1)
+        .map(|i| (i, line.distance(chain[i])))
+        .max_by(|x, y| x.1.total_cmp(&y.1))
+        .unwrap_or((0, 0.0));
+    if dist <= tolerance {
+        *chain = vec![a, b];
+        return;
+    }
+    let mut head = chain[..=index].to_vec();
+    let mut tail = chain[index..].to_vec();
+    douglas_peucker(&mut head, tolerance);
+    douglas_peucker(&mut tail, tolerance);
+    head.pop();
+    head.extend(tail);
+    *chain = head;
+}
+
+/// Fit and regularize the wall segments of labelled, oriented splats.
+pub fn build(
+    points: &[PointNormal],
+    labels: &[NormalGroup],
+    floor_y: f64,
+    options: &FloorPlanOptions,
+    seed: u64,
+) -> FloorPlan {
+    let (cells, wall_splat_count) = wall_cells(points, labels, floor_y, options);
+    let mut segments = fit_segments(&cells, options, seed);
+    let dominant_angle_deg = dominant_angle(&segments);
+    regularize(&mut segments, dominant_angle_deg, options.snap_deg);
+    segments.sort_by(|a, b| b.length.total_cmp(&a.length));
+    FloorPlan {
+        floor_y,
+        dominant_angle_deg,
+        wall_splat_count,
+        wall_cell_count: cells.len(),
+        segments,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> FloorPlanOptions {
+        FloorPlanOptions {
+            cell_size: 0.05,
+            min_height: 0.3,
+            max_height: 2.0,
+            line_tolerance: 0.05,
+            min_length: 0.3,
+            max_gap: 0.3,
+            snap_deg: 10.0,
+            max_segments: 16,
+        }
+    }
+
+    /// Cell centres along a wall from `a` to `b`, one per 5 cm.
+    fn wall(a: [f64; 2], b: [f64; 2]) -> Vec<[f64; 2]> {
+        let len = ((b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)).sqrt();
+        let n = (len / 0.05) as usize;
+        (0..=n)
+            .map(|i| {
+                let t = i as f64 / n as f64;
+                [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fits_and_squares_a_skewed_room() {
+        // A 4 x 3 room whose walls are 3 degrees off square, with a door gap.
+        let skew = 3.0_f64.to_radians();
+        let r = |p: [f64; 2]| {
+            [
+                p[0] * skew.cos() - p[1] * skew.sin(),
+                p[0] * skew.sin() + p[1] * skew.cos(),
+            ]
+        };
+        let mut cells = Vec::new();
+        cells.extend(wall(r([0.0, 0.0]), r([4.0, 0.0])));
+        cells.extend(wall(r([0.0, 3.0]), r([1.5, 3.0])));
+        cells.extend(wall(r([2.5, 3.0]), r([4.0, 3.0])));
+        cells.extend(wall(r([0.0, 0.1]), r([0.0, 2.9])));
+        let mut segments = fit_segments(&cells, &options(), 7);
+        assert_eq!(segments.len(), 4);
+        let dominant = dominant_angle(&segments);
+        assert!((dominant - 3.0).abs() < 0.5, "dominant {}", dominant);
+
+        // Rotate one wall further off-axis; regularization squares it again.
+        let seg = &mut segments[0];
+        seg.end[1] += 0.1;
+        regularize(&mut segments, 3.0, 10.0);
+        for s in &segments {
+            let angle = (s.end[1] - s.start[1])
+                .atan2(s.end[0] - s.start[0])
+                .to_degrees();
+            let offset = (angle - 3.0).rem_euclid(90.0);
+            assert!(offset.min(90.0 - offset) < 1e-6, "angle {}", angle);
+        }
+    }
+
+    #[test]
+    fn traces_and_simplifies_an_l_shaped_floor() {
+        // 4 x 4 cells with the top-right 2 x 2 quadrant missing.
+        let mask: Vec<bool> = (0..16).map(|i| !(i / 4 >= 2 && i % 4 >= 2)).collect();
+        let ring = trace_outline(&mask, 4, 4);
+        assert_eq!(ring.len(), 6);
+        assert_eq!(signed_area(&ring), 12.0);
+
+        // A one-cell staircase collapses to a single diagonal at tolerance 1.
+        let stairs: Vec<bool> = (0..16).map(|i| i % 4 <= i / 4).collect();
+        let ring = simplify_ring(&trace_outline(&stairs, 4, 4), 1.0);
+        assert_eq!(ring.len(), 3, "{:?}", ring);
+    }
+}
@@ -0,0 +1,200 @@
+//! Floor polygons: the walkable area as 2D outlines (`extract_floor_polygons`).
+//!
+//! Minimaps, trigger volumes and level design tools want the floor as a few
+//! polygons, not as a mesh or a grid of cells. [`extract`] splits the walkable
+//! ground-field cells into islands (cells joined edge to edge), traces each
+//! island's outer boundary and its holes (furniture, pillars, voids) along the
+//! cell edges, and simplifies every ring with Douglas-Peucker at
+//! `floor_polygon_tolerance`. Islands and holes under `floor_polygon_min_area`
+//! are dropped: a dropped hole is simply left out of its island's polygon.
+//!
+//! `floor_plan`'s outline is the same trace cut down to the largest island
+//! with no holes.
+
+use std::collections::VecDeque;
+
+use crate::floor_plan;
+use crate::MeshSettings;
+
+#[derive(Clone, Copy, Debug)]
+pub struct FloorPolygonOptions {
+    /// Farthest a simplified ring may stray from the cell edges, in metres
+    /// (default: one cell).
+    pub tolerance: Option<f64>,
+    /// Smallest island or hole kept, in square metres (default 0.25).
+    pub min_area: f64,
+}
+
+impl FloorPolygonOptions {
+    pub fn from_settings(settings: &MeshSettings) -> Self {
+        Self {
+            tolerance: settings.floor_polygon_tolerance,
+            min_area: settings.floor_polygon_min_area.unwrap_or(0.25),
+        }
+    }
+}
+
+/// Tolerance and minimum area must be finite and non-negative.
+pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+    let options = FloorPolygonOptions::from_settings(settings);
+    if let Some(tolerance) = options.tolerance {
+        if !(tolerance.is_finite() && tolerance >= 0.0) {
+            return Err(format!("Invalid floor_polygon_tolerance: {}", tolerance));
+        }
+    }
+    if !(options.min_area.is_finite() && options.min_area >= 0.0) {
+        return Err(format!(
+            "Invalid floor_polygon_min_area: {}",
+            options.min_area
+        ));
+    }
+    Ok(())
+}
+
+/// One island of floor in grid-corner `[col, row]` units.
+pub struct Island {
+    /// Counter-clockwise.
+    pub outer: Vec<[f64; 2]>,
+    /// Clockwise, largest first.
+    pub holes: Vec<Vec<[f64; 2]>>,
+    /// Indices of the island's cells.
+    pub cells: Vec<usize>,
+}
+
+/// The islands of a `width` x `height` floor mask, largest first.
+pub struct Islands {
+    pub islands: Vec<Island>,
+    /// Islands and holes under the minimum area.
+    pub dropped_islands: usize,
+    pub dropped_holes: usize,
+}
+
+/// Trace and simplify the islands of `mask`, whose cells are `cell_size`
+/// metres wide.
+pub fn extract(
+    mask: &[bool],
+    width: usize,
+    height: usize,
+    cell_size: f64,
+    options: &FloorPolygonOptions,
+) -> Islands {
+    // Label the islands, edge-connected like the traced rings.
+    let mut label = vec![usize::MAX; mask.len()];
+    let mut cells: Vec<Vec<usize>> = Vec::new();
+    for seed in 0..mask.len() {
+        if !mask[seed] || label[seed] != usize::MAX {
+            continue;
+        }
+        let id = cells.len();
+        let mut members = Vec::new();
+        let mut queue = VecDeque::from([seed]);
+        label[seed] = id;
+        while let Some(cell) = queue.pop_front() {
+            members.push(cell);
+            let (c, r) = (cell % width, cell / width);
+            let neighbours = [
+                (c > 0).then(|| cell - 1),
+                (c + 1 < width).then(|| cell + 1),
+                (r > 0).then(|| cell - width),
+                (r + 1 < height).then(|| cell + width),
+            ];
+            for n in neighbours.into_iter().flatten() {
+                if mask[n] && label[n] == usize::MAX {
+                    label[n] = id;
+                    queue.push_back(n);
+                }
+            }
+        }
+        cells.push(members);
+    }
+
+    let cell_area = cell_size * cell_size;
+    let min_cells = options.min_area / cell_area;
+    let mut outers: Vec<Vec<[f64; 2]>> = vec![Vec::new(); cells.len()];
+    let mut holes: Vec<Vec<Vec<[f64; 2]>>> = vec![Vec::new(); cells.len()];
+    let mut dropped_holes = 0;
+    for (ring, cell) in floor_plan::trace_rings(mask, width, height) {
+        let id = label[cell];
+        let area = floor_plan::signed_area(&ring);
+        if area > 0.0 {
+            if area > floor_plan::signed_area(&outers[id]) {
+                outers[id] = ring;
+            }
+        } else if -area < min_cells {
+            dropped_holes += 1;
+        } else {
+            holes[id].push(ring);
+        }
+    }
+
+    let tolerance = options.tolerance.map_or(1.0, |t| t / cell_size);
+    let mut islands = Vec::new();
+    let mut dropped_islands = 0;
+    for ((outer, mut holes), cells) in outers.into_iter().zip(holes).zip(cells) {
+        if (cells.len() as f64) < min_cells {
+            dropped_islands += 1;
+            dropped_holes += holes.len();
+            continue;
+        }
+        holes.sort_by(|a, b| floor_plan::signed_area(a).total_cmp(&floor_plan::signed_area(b)));
+        islands.push(Island {
+            outer: floor_plan::simplify_ring(&outer, tolerance),
+            holes: holes
+                .iter()
+                .map(|hole| floor_plan::simplify_ring(hole, tolerance))
+                .collect(),
+            cells,
+        });
+    }
+    islands.sort_by_key(|island| std::cmp::Reverse(island.cells.len()));
+    Islands {
+        islands,
+        dropped_islands,
+        dropped_holes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(min_area: f64) -> FloorPolygonOptions {
+        FloorPolygonOptions {
+            tolerance: None,
+            min_area,
+        }
+    }
+
+    #[test]
+    fn islands_come_with_their_holes() {
+        // A 5 x 5 ring of floor around a one-cell hole, and a separate
+        // two-cell island touching it only at a corner.
+        let (width, height) = (8, 6);
+        let mut mask = vec![false; width * height];
+        for r in 0..5 {
+            for c in 0..5 {
+                mask[r * width + c] = !(r == 2 && c == 2);
+            }
+        }
+        mask[5 * width + 5] = true;
+        mask[5 * width + 6] = true;
+
+        let found = extract(&mask, width, height, 1.0, &options(0.0));
+        assert_eq!(found.islands.len(), 2);
+        let room = &found.islands[0];
+        assert_eq!(room.cells.len(), 24);
+        assert_eq!(room.outer.len(), 4);
+        assert!(floor_plan::signed_area(&room.outer) > 0.0);
+        assert_eq!(room.holes.len(), 1);
+        assert_eq!(floor_plan::signed_area(&room.holes[0]), -1.0);
+        let step = &found.islands[1];
+        assert_eq!(step.cells, vec![5 * width + 5, 5 * width + 6]);
+        assert_eq!(floor_plan::signed_area(&step.outer), 2.0);
+
+        // At 0.5 m cells the hole is 0.25 m^2 and the step 0.5 m^2.
+        let small = extract(&mask, width, height, 0.5, &options(0.6));
+        assert_eq!(small.islands.len(), 1);
+        assert!(small.islands[0].holes.is_empty());
+        assert_eq!((small.dropped_islands, small.dropped_holes), (1, 1));
+    }
+}
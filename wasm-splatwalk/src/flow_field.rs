@@ -0,0 +1,154 @@
+//! Flow field toward a goal (`flow_goal`).
+//!
+//! Hundreds of simple agents can share one precomputed field instead of each
+//! running a path query: an agent looks up the face it stands on and steers
+//! along that face's direction. [`build`] takes the walking distance to the
+//! goal at every vertex (see `geodesic`) and points each walkable face down
+//! its gradient, so the directions follow the floor around walls and up
+//! steps rather than heading straight for the goal.
+
+use nalgebra::Vector3;
+use serde::Serialize;
+
+use crate::geodesic;
+use crate::mesh::ReconstructedMesh;
+use crate::reachability::{self, AgentLimits};
+use crate::MeshSettings;
+
+/// Per-face steering directions toward `flow_goal`.
+#[derive(Serialize)]
+pub struct FlowField {
+    /// Three floats per face of `mesh`: the unit direction to walk, in the
+    /// face's plane, or zeros where the goal is out of reach.
+    pub directions: Vec<f32>,
+    /// Per face: walking distance from its centre to the goal in metres, or
+    /// -1 where the goal is out of reach.
+    pub distances: Vec<f32>,
+    /// Faces with a direction.
+    pub reached_faces: usize,
+}
+
+/// `flow_goal` must be a finite `[x, y, z]` point.
+pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+    if let Some(goal) = settings.flow_goal.as_ref() {
+        if goal.len() < 3 || goal.iter().any(|c| !c.is_finite()) {
+            return Err("flow_goal must be a finite [x, y, z] point".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// The flow field of `mesh` toward `goal`. `link` is the distance under which
+/// corners count as stacked.
+pub fn build(
+    mesh: &ReconstructedMesh,
+    goal: [f64; 3],
+    limits: &AgentLimits,
+    link: f64,
+) -> FlowField {
+    let distance = geodesic::distances(mesh, &[goal], limits, link).distances;
+    let positions: Vec<Vector3<f64>> = mesh
+        .vertices
+        .chunks_exact(3)
+        .map(|v| Vector3::new(v[0] as f64, v[1] as f64, v[2] as f64))
+        .collect();
+    let faces: Vec<[usize; 3]> = mesh
+        .indices
+        .chunks_exact(3)
+        .map(|f| [f[0] as usize, f[1] as usize, f[2] as usize])
+        .collect();
+    let walkable = reachability::walkable_faces(&positions, &faces, limits);
+
+    let mut directions = vec![0.0f32; faces.len() * 3];
+    let mut distances = vec![-1.0f32; faces.len()];
+    let mut reached_faces = 0;
+    for (fi, f) in faces.iter().enumerate() {
+        if !walkable[fi] || f.iter().any(|&v| distance[v] < 0.0) {
+            continue;
+        }
+        let [p0, p1, p2] = f.map(|v| positions[v]);
+        let [d0, d1, d2] = f.map(|v| distance[v] as f64);
+        // Gradient of the distance interpolated linearly over the face.
+        let (e1, e2) = (p1 - p0, p2 - p0);
+        let n = e1.cross(&e2);
+        let gradient = (e2.cross(&n) * (d1 - d0) + n.cross(&e1) * (d2 - d0)) / n.norm_squared();
+        distances[fi] = ((d0 + d1 + d2) / 3.0) as f32;
+        reached_faces += 1;
+        // A level face (the goal's own, or a plateau) has nowhere to go.
+        if gradient.norm() > 1e-9 {
+            let step = -gradient.normalize();
+            directions[fi * 3..fi * 3 + 3].copy_from_slice(&[
+                step.x as f32,
+                step.y as f32,
+                step.z as f32,
+            ]);
+        }
+    }
+    FlowField {
+        directions,
+        distances,
+        reached_faces,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directions_lead_around_a_wall_to_the_goal() {
+        // A U of unit squares: two 1x4 arms joined along the bottom row, with
+        // the goal at the top of the right arm.
+        let cells = [
+            (0, 0),
+            (1, 0),
+            (2, 0),
+            (0, 1),
+            (0, 2),
+            (0, 3),
+            (2, 1),
+            (2, 2),
+            (2, 3),
+        ];
+        let mut mesh = ReconstructedMesh {
+            vertices: vec![],
+            indices: vec![],
+        };
+        let mut corner = std::collections::HashMap::new();
+        for &(x, z) in &cells {
+            let mut at = |i: u32, j: u32| {
+                *corner.entry((x + i, z + j)).or_insert_with(|| {
+                    mesh.vertices
+                        .extend_from_slice(&[(x + i) as f32, 0.0, (z + j) as f32]);
+                    (mesh.vertices.len() / 3 - 1) as u32
+                })
+            };
+            let (a, b, c, d) = (at(0, 0), at(1, 0), at(1, 1), at(0, 1));
+            mesh.indices.extend([a, c, b, a, d, c]);
+        }
+        let limits = AgentLimits {
+            max_climb: 0.5,
+            max_slope_deg: 40.0,
+            max_step: 0.0,
+        };
+        let field = build(&mesh, [2.5, 0.0, 3.5], &limits, 1e-3);
+        assert_eq!(field.reached_faces, cells.len() * 2);
+        let direction = |cell: usize| {
+            let fi = cell * 2;
+            Vector3::new(
+                field.directions[fi * 3] as f64,
+                field.directions[fi * 3 + 1] as f64,
+                field.directions[fi * 3 + 2] as f64,
+            )
+        };
+        // Up the left arm the flow heads away from the goal's row, back to
+        // the bottom; along the bottom it cuts right towards the corner of
+        // the right arm; up the right arm, +z.
+        assert!(direction(5).z < -0.5);
+        assert!(direction(1).x > 0.3 && direction(1).z > 0.3);
+        assert!(direction(7).z > 0.5);
+        assert!(direction(4).y.abs() < 1e-9);
+        // Distances grow with the walk, not the straight line.
+        assert!(field.distances[10] > field.distances[2]);
+    }
+}
@@ -0,0 +1,314 @@
+//! Storage-format sizing and parse-time estimates for a parsed splat cloud.
+//!
+//! Given the composed cloud of a `SplatSession`, estimate the encoded size and
+//! load cost of every supported delivery format and recommend one for the
+//! caller's constraints. PLY and `.splat` are measured: a sample of the cloud is
+//! encoded and parsed back, and the timing is scaled to the full splat count.
+//! SPZ and compressed (quantized) PLY have no encoder in this crate, so their
+//! sizes follow the published layouts and their parse cost is modelled relative
+//! to the measured `.splat` decode; those entries report `measured: false`.
+
+use serde::{Deserialize, Serialize};
+
+use crate::splat::{self, FullSplatCloud, PointNormal};
+
+/// Typical gzip ratio of an SPZ payload (quantized fields compress poorly).
+const SPZ_GZIP_RATIO: f64 = 0.85;
+/// Modelled decode cost per splat relative to `.splat`: SPZ pays for inflate +
+/// fixed-point dequantization, compressed PLY for chunk dequantization.
+const SPZ_PARSE_FACTOR: f64 = 1.6;
+const COMPRESSED_PLY_PARSE_FACTOR: f64 = 1.2;
+/// Splats per compressed-PLY chunk and the chunk's min/max table size in bytes
+/// (18 floats: position, scale and color ranges).
+const COMPRESSED_PLY_CHUNK: usize = 256;
+const COMPRESSED_PLY_CHUNK_BYTES: usize = 18 * 4;
+
+#[derive(Deserialize, Default)]
+pub struct FormatReportOptions {
+    /// Splats encoded/parsed for the timing sample (default 20 000).
+    pub sample_size: Option<usize>,
+    /// `"balanced"` (default), `"size"`, `"parse_speed"`, or `"fidelity"`.
+    pub priority: Option<String>,
+    /// Reject formats whose estimated size exceeds this many bytes.
+    pub max_bytes: Option<usize>,
+    /// Reject formats that drop higher-order spherical harmonics.
+    pub require_sh: Option<bool>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct FormatEstimate {
+    /// `"ply"`, `"spz"`, `"splat"`, or `"compressed_ply"`.
+    pub format: String,
+    pub estimated_bytes: usize,
+    pub estimated_parse_ms: f64,
+    /// True when size and parse time come from encoding and parsing a sample.
+    pub measured: bool,
+    /// SH degree the format would retain for this cloud.
+    pub sh_degree: usize,
+    /// True when the format quantizes attributes.
+    pub lossy: bool,
+}
+
+#[derive(Serialize)]
+pub struct FormatReport {
+    pub api_version: u8,
+    pub semver: String,
+    pub capabilities: Vec<String>,
+    pub splat_count: usize,
+    pub sh_degree: usize,
+    pub sample_size: usize,
+    pub formats: Vec<FormatEstimate>,
+    pub recommended: String,
+    pub reason: String,
+}
+
+/// Milliseconds spent in `f`. `js_sys::Date` is the only clock on
+/// `wasm32-unknown-unknown`; native test builds use `Instant`.
+fn timed<R>(f: impl FnOnce() -> R) -> (R, f64) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let start = js_sys::Date::now();
+        let out = f();
+        (out, js_sys::Date::now() - start)
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let start = std::time::Instant::now();
+        let out = f();
+        (out, start.elapsed().as_secs_f64() * 1000.0)
+    }
+}
+
+/// Evenly strided sample of `points` as a full-fidelity cloud with `sh_degree`
+/// worth of (zeroed) SH coefficients, so encoded sizes match the real layout.
+fn sample_cloud(points: &[PointNormal], sample_size: usize, sh_degree: usize) -> FullSplatCloud {
+    let stride = (points.len() / sample_size.max(1)).max(1);
+    let mut cloud = FullSplatCloud {
+        sh_degree,
+        ..Default::default()
+    };
+    let rest = cloud.sh_rest_stride();
+    for p in points.iter().step_by(stride).take(sample_size) {
+        cloud
+            .positions
+            .push([p.point.x as f32, p.point.y as f32, p.point.z as f32]);
+        cloud
+            .scales
+            .push([p.scale.x as f32, p.scale.y as f32, p.scale.z as f32]);
+        cloud.rotations.push([1.0, 0.0, 0.0, 0.0]);
+        cloud.opacity_logit.push(p.opacity as f32);
+        cloud.sh0.push([0.0; 3]);
+        cloud.sh_rest.extend(std::iter::repeat_n(0.0, rest));
+    }
+    cloud
+}
+
+/// Encoded size of a full cloud of `n` splats in each layout-derived format.
+fn modelled_sizes(n: usize, sh_degree: usize) -> (usize, usize) {
+    let sh_bytes = 3 * splat::sh_rest_coeffs_for_degree(sh_degree);
+    // SPZ v2: 16-byte header; per splat 24-bit fixed-point position (9), alpha
+    // (1), color (3), log scale (3), rotation (3), SH coefficients (1 byte each).
+    let spz = 16 + ((n * (9 + 1 + 3 + 3 + 3 + sh_bytes)) as f64 * SPZ_GZIP_RATIO) as usize;
+    // Compressed PLY: ~1 KiB header, per-chunk range table, four packed u32 per
+    // splat (position, rotation, scale, color), SH as u8.
+    let chunks = n.div_ceil(COMPRESSED_PLY_CHUNK);
+    let compressed = 1024 + chunks * COMPRESSED_PLY_CHUNK_BYTES + n * (16 + sh_bytes);
+    (spz, compressed)
+}
+
+pub fn build_format_report(
+    points: &[PointNormal],
+    sh_degree: usize,
+    options: &FormatReportOptions,
+) -> Result<FormatReport, String> {
+    let n = points.len();
+    if n == 0 {
+        return Err("Format report needs at least one splat".to_string());
+    }
+    let sample_size = options.sample_size.unwrap_or(20_000).clamp(1, n);
+    let cloud = sample_cloud(points, sample_size, sh_degree);
+    let sampled = cloud.len();
+    let scale_to_full = n as f64 / sampled as f64;
+
+    let ply = splat::write_ply(&cloud);
+    let header_len = ply
+        .windows(11)
+        .position(|w| w == b"end_header\n")
+        .map(|i| i + 11)
+        .unwrap_or(0);
+    let ply_stride = (ply.len() - header_len) / sampled;
+    let (parsed, ply_ms) = timed(|| splat::parse_ply(&ply));
+    parsed?;
+
+    let splat_bytes = splat::write_splat_buffer(&cloud);
+    let (parsed, splat_ms) = timed(|| splat::parse_splat_buffer(&splat_bytes));
+    parsed?;
+
+    let (spz_bytes, compressed_bytes) = modelled_sizes(n, sh_degree);
+    let splat_ms_full = splat_ms * scale_to_full;
+    let formats = vec![
+        FormatEstimate {
+            format: "ply".to_string(),
+            estimated_bytes: header_len + n * ply_stride,
+            estimated_parse_ms: ply_ms * scale_to_full,
+            measured: true,
+            sh_degree,
+            lossy: false,
+        },
+        FormatEstimate {
+            format: "spz".to_string(),
+            estimated_bytes: spz_bytes,
+            estimated_parse_ms: splat_ms_full * SPZ_PARSE_FACTOR,
+            measured: false,
+            sh_degree,
+            lossy: true,
+        },
+        FormatEstimate {
+            format: "splat".to_string(),
+            estimated_bytes: n * 32,
+            estimated_parse_ms: splat_ms_full,
+            measured: true,
+            sh_degree: 0,
+            lossy: true,
+        },
+        FormatEstimate {
+            format: "compressed_ply".to_string(),
+            estimated_bytes: compressed_bytes,
+            estimated_parse_ms: splat_ms_full * COMPRESSED_PLY_PARSE_FACTOR,
+            measured: false,
+            sh_degree,
+            lossy: true,
+        },
+    ];
+
+    let (recommended, reason) = recommend(&formats, sh_degree, options);
+    Ok(FormatReport {
+        api_version: crate::API_VERSION,
+        semver: crate::core_semver(),
+        capabilities: crate::capabilities(),
+        splat_count: n,
+        sh_degree,
+        sample_size: sampled,
+        formats,
+        recommended,
+        reason,
+    })
+}
+
+fn recommend(
+    formats: &[FormatEstimate],
+    sh_degree: usize,
+    options: &FormatReportOptions,
+) -> (String, String) {
+    let require_sh = options.require_sh.unwrap_or(false) && sh_degree > 0;
+    let candidates: Vec<&FormatEstimate> = formats
+        .iter()
+        .filter(|f| !require_sh || f.sh_degree == sh_degree)
+        .filter(|f| options.max_bytes.is_none_or(|max| f.estimated_bytes <= max))
+        .collect();
+    let smallest = |list: &[&FormatEstimate]| {
+        list.iter()
+            .min_by_key(|f| f.estimated_bytes)
+            .map(|f| f.format.clone())
+    };
+    if candidates.is_empty() {
+        let all: Vec<&FormatEstimate> = formats.iter().collect();
+        return (
+            smallest(&all).unwrap_or_default(),
+            "no format meets the constraints; recommending the smallest".to_string(),
+        );
+    }
+
+    let priority = options.priority.as_deref().unwrap_or("balanced");
+    let pick = match priority {
+        "size" => smallest(&candidates),
+        "parse_speed" => candidates
+            .iter()
+            .min_by(|a, b| a.estimated_parse_ms.total_cmp(&b.estimated_parse_ms))
+            .map(|f| f.format.clone()),
+        "fidelity" => candidates
+            .iter()
+            .find(|f| !f.lossy)
+            .or_else(|| candidates.iter().max_by_key(|f| f.sh_degree))
+            .map(|f| f.format.clone()),
+        _ => {
+            // Balanced: sum of size and parse time, each normalized to the best
+            // candidate, so neither unit dominates.
+            let min_bytes = candidates
+                .iter()
+                .map(|f| f.estimated_bytes)
+                .min()
+                .unwrap_or(1)
+                .max(1) as f64;
+            let min_ms = candidates
+                .iter()
+                .map(|f| f.estimated_parse_ms)
+                .fold(f64::MAX, f64::min)
+                .max(1e-6);
+            let score = |f: &FormatEstimate| {
+                f.estimated_bytes as f64 / min_bytes + f.estimated_parse_ms.max(1e-6) / min_ms
+            };
+            candidates
+                .iter()
+                .min_by(|a, b| score(a).total_cmp(&score(b)))
+                .map(|f| f.format.clone())
+        }
+    };
+    (
+        pick.unwrap_or_default(),
+        format!(
+            "best {} among {} candidate format(s)",
+            priority,
+            candidates.len()
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn estimate(format: &str, bytes: usize, ms: f64, sh: usize, lossy: bool) -> FormatEstimate {
+        FormatEstimate {
+            format: format.to_string(),
+            estimated_bytes: bytes,
+            estimated_parse_ms: ms,
+            measured: true,
+            sh_degree: sh,
+            lossy,
+        }
+    }
+
+    #[test]
+    fn recommendation_honours_constraints() {
+        let formats = [
+            estimate("ply", 2_480, 4.0, 3, false),
+            estimate("spz", 600, 6.0, 3, true),
+            estimate("splat", 320, 2.0, 0, true),
+        ];
+        let size = FormatReportOptions {
+            priority: Some("size".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(recommend(&formats, 3, &size).0, "splat");
+        let with_sh = FormatReportOptions {
+            require_sh: Some(true),
+            ..size
+        };
+        assert_eq!(recommend(&formats, 3, &with_sh).0, "spz");
+        let fidelity = FormatReportOptions {
+            priority: Some("fidelity".to_string()),
+            max_bytes: Some(1_000),
+            ..Default::default()
+        };
+        assert_eq!(recommend(&formats, 3, &fidelity).0, "spz");
+    }
+
+    #[test]
+    fn modelled_sizes_track_sh_degree() {
+        let (spz0, compressed0) = modelled_sizes(1_000, 0);
+        let (spz3, compressed3) = modelled_sizes(1_000, 3);
+        assert!(spz3 > spz0 && compressed3 > compressed0);
+        assert_eq!(compressed0, 1024 + 4 * COMPRESSED_PLY_CHUNK_BYTES + 16_000);
+    }
+}
@@ -0,0 +1,300 @@
+//! The ground frame of a mesh result.
+//!
+//! A host app placing content on a bake has three spaces to reconcile: the
+//! bake's grid, the oriented space the mesh is written in, and the splat
+//! file its renderer draws. The mesh alone carries none of the maps between
+//! them, and rebuilding them from `diagnostics` means re-deriving `rotation`,
+//! `environment_scale`, `flip_y` and the coordinate preset by hand.
+//!
+//! [`GroundFrame`] carries them instead: the ground plane the mode fitted,
+//! a basis on it (the voxel grid's, for mode 2), and a matrix back to the
+//! splat file, and its inverse. Every bake map is linear (rotation, uniform
+//! scale, axis flips), so the matrices have no translation and the inverse is
+//! cheap. With `emit_source_mesh` the result also carries the mesh itself in
+//! the splat file's space ([`mesh_to_splat`]), for tools that re-export or
+//! align against the capture rather than draw in the host's scene.
+
+use nalgebra::{Point3, Vector3};
+use serde::Serialize;
+
+use crate::{ellipsoid, mesh, FieldBasis, FloorPlane, MeshBuffers, MeshSettings};
+
+/// The ground plane, basis and splat-file map of a mesh result.
+#[derive(Clone, Serialize)]
+pub struct GroundFrame {
+    /// The ground plane the mode fitted, `normal · p + d = 0` with the
+    /// normal facing up. Absent for modes that fit none.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub floor_plane: Option<FloorPlane>,
+    /// Mode 2: the voxel grid's min corner and axes, so voxel `(x, y, z)`
+    /// starts at `origin + (x * tangent + y * up + z * bitangent) *
+    /// cell_size`. Otherwise, with a ground plane: a basis on it, origin at
+    /// the point of the plane nearest the oriented origin, tangent along `+X`
+    /// where the plane allows.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub basis: Option<FieldBasis>,
+    /// The grid's cell edge in metres, present only with a grid basis.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cell_size: Option<f64>,
+    /// Column-major 4x4 matrix from the result's space back to the splat
+    /// file's, translation in elements 12 to 14 (always zero).
+    pub to_splat: [f64; 16],
+    /// The inverse of `to_splat`: from the splat file's space into the
+    /// result's.
+    pub from_splat: [f64; 16],
+}
+
+impl GroundFrame {
+    /// The frame for a bake with `settings`, in `splatwalk_oriented` space.
+    /// `grid` is the voxel grid's basis and cell edge, when there is one.
+    pub fn new(
+        settings: &MeshSettings,
+        floor_plane: Option<FloorPlane>,
+        grid: Option<(FieldBasis, f64)>,
+    ) -> Self {
+        let (basis, cell_size) = match grid {
+            Some((basis, cell_size)) => (Some(basis), Some(cell_size)),
+            None => (floor_plane.as_ref().and_then(plane_basis), None),
+        };
+        let to_splat = to_splat(settings);
+        Self {
+            floor_plane,
+            basis,
+            cell_size,
+            to_splat,
+            from_splat: invert(&to_splat),
+        }
+    }
+}
+
+/// `plane` with its normal and offset negated if the normal faces down.
+pub fn facing_up(plane: FloorPlane) -> FloorPlane {
+    if plane.normal[1] < 0.0 {
+        FloorPlane {
+            normal: plane.normal.map(|c| -c),
+            d: -plane.d,
+        }
+    } else {
+        plane
+    }
+}
+
+/// A right-handed basis on `plane` (`bitangent = tangent × up`), or `None`
+/// for a degenerate normal.
+pub fn plane_basis(plane: &FloorPlane) -> Option<FieldBasis> {
+    let normal = Vector3::from(plane.normal);
+    let length = normal.norm();
+    if !(length > 1e-12 && length.is_finite()) {
+        return None;
+    }
+    let plane = facing_up(FloorPlane {
+        normal: (normal / length).into(),
+        d: plane.d / length,
+    });
+    let up = Vector3::from(plane.normal);
+    let along = |axis: Vector3<f64>| {
+        let projected = axis - up * axis.dot(&up);
+        (projected.norm() > 1e-6).then(|| projected.normalize())
+    };
+    let tangent = along(Vector3::x()).or_else(|| along(Vector3::z()))?;
+    Some(FieldBasis {
+        origin: (-up * plane.d).into(),
+        tangent: tangent.into(),
+        bitangent: tangent.cross(&up).into(),
+        up: up.into(),
+    })
+}
+
+/// The matrix mapping `splatwalk_oriented` points back to the splat file:
+/// the inverse of ingest (`coordinate_system`, `flip_y`) then `rotation`
+/// and `environment_scale`.
+pub fn to_splat(settings: &MeshSettings) -> [f64; 16] {
+    let ingest = ellipsoid::ingest_frame(settings);
+    let forward = |v: Vector3<f64>| {
+        let oriented = mesh::orient_point(&Point3::from(ingest * v), settings);
+        oriented.coords
+    };
+    // A rotation, a uniform scale and axis flips: the inverse is the
+    // transpose over the squared scale.
+    let columns = [
+        forward(Vector3::x()),
+        forward(Vector3::y()),
+        forward(Vector3::z()),
+    ];
+    let scale_squared = columns[0].norm_squared();
+    linear_matrix(|v| {
+        let v = Vector3::from(v);
+        if scale_squared > 0.0 {
+            columns.map(|column| column.dot(&v) / scale_squared)
+        } else {
+            [0.0; 3]
+        }
+    })
+}
+
+/// The inverse of a `to_splat`-style matrix: a rotation, a uniform scale and
+/// axis flips with no translation, so the transpose over the squared scale.
+pub fn invert(matrix: &[f64; 16]) -> [f64; 16] {
+    let scale_squared: f64 = matrix[..3].iter().map(|c| c * c).sum();
+    linear_matrix(|v| {
+        if scale_squared > 0.0 {
+            [0, 1, 2].map(|column| {
+                let c = &matrix[column * 4..column * 4 + 3];
+                (c[0] * v[0] + c[1] * v[1] + c[2] * v[2]) / scale_squared
+            })
+        } else {
+            [0.0; 3]
+        }
+    })
+}
+
+/// `mesh` mapped through `frame.to_splat` into the splat file's space.
+/// Normals follow the map and are renormalized, and the winding flips when
+/// the map mirrors, so faces stay front-facing in the file's own basis.
+/// Per-vertex extras carry over unchanged.
+pub fn mesh_to_splat(frame: &GroundFrame, mesh: &MeshBuffers) -> MeshBuffers {
+    let matrix = &frame.to_splat;
+    let mut out = mesh.clone();
+    for v in out.vertices.chunks_exact_mut(3) {
+        let p = transform(matrix, [v[0] as f64, v[1] as f64, v[2] as f64]);
+        v.copy_from_slice(&p.map(|c| c as f32));
+    }
+    for n in out.normals.chunks_exact_mut(3) {
+        let d = Vector3::from(transform(matrix, [n[0] as f64, n[1] as f64, n[2] as f64]));
+        let d = if d.norm() > 0.0 { d.normalize() } else { d };
+        n.copy_from_slice(&[d.x as f32, d.y as f32, d.z as f32]);
+    }
+    let m = |row: usize, column: usize| matrix[column * 4 + row];
+    let det = m(0, 0) * (m(1, 1) * m(2, 2) - m(2, 1) * m(1, 2))
+        - m(0, 1) * (m(1, 0) * m(2, 2) - m(2, 0) * m(1, 2))
+        + m(0, 2) * (m(1, 0) * m(2, 1) - m(2, 0) * m(1, 1));
+    if det < 0.0 {
+        for face in out.indices.chunks_exact_mut(3) {
+            face.swap(1, 2);
+        }
+    }
+    out
+}
+
+/// The column-major 4x4 matrix of the linear map `map`.
+pub fn linear_matrix(map: impl Fn([f64; 3]) -> [f64; 3]) -> [f64; 16] {
+    let mut matrix = [0.0; 16];
+    for axis in 0..3 {
+        let mut unit = [0.0; 3];
+        unit[axis] = 1.0;
+        matrix[axis * 4..axis * 4 + 3].copy_from_slice(&map(unit));
+    }
+    matrix[15] = 1.0;
+    matrix
+}
+
+/// `point` through the column-major 4x4 `matrix`.
+pub fn transform(matrix: &[f64; 16], point: [f64; 3]) -> [f64; 3] {
+    [0, 1, 2].map(|row| {
+        matrix[row] * point[0]
+            + matrix[4 + row] * point[1]
+            + matrix[8 + row] * point[2]
+            + matrix[12 + row]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(json: serde_json::Value) -> MeshSettings {
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn close(a: [f64; 3], b: [f64; 3]) -> bool {
+        (0..3).all(|i| (a[i] - b[i]).abs() < 1e-9)
+    }
+
+    #[test]
+    fn plane_basis_sits_on_the_plane_facing_up() {
+        // y = 0.5 x + 2, written with a downward normal.
+        let raw = Vector3::new(0.5_f64, -1.0, 0.0);
+        let normal = raw / raw.norm();
+        let plane = FloorPlane {
+            normal: normal.into(),
+            d: 2.0 / raw.norm(),
+        };
+        let basis = plane_basis(&plane).unwrap();
+        let [up, tangent, bitangent] =
+            [basis.up, basis.tangent, basis.bitangent].map(Vector3::from);
+        let origin = Vector3::from(basis.origin);
+        assert!(up.y > 0.0 && (up + normal).norm() < 1e-9);
+        // The origin and steps along both axes stay on the plane.
+        for p in [origin, origin + tangent * 3.0, origin + bitangent * 3.0] {
+            assert!((p.y - (0.5 * p.x + 2.0)).abs() < 1e-9, "{:?}", p);
+        }
+        assert!(tangent.x > 0.0 && tangent.z.abs() < 1e-9);
+        assert!((tangent.cross(&up) - bitangent).norm() < 1e-9);
+        assert!(tangent.dot(&up).abs() < 1e-9 && bitangent.norm() > 0.999);
+
+        // A wall facing +X has no X tangent to project; Z stands in.
+        let wall = plane_basis(&FloorPlane {
+            normal: [1.0, 0.0, 0.0],
+            d: 0.0,
+        })
+        .unwrap();
+        assert!(close(wall.tangent, [0.0, 0.0, 1.0]));
+        assert!(plane_basis(&FloorPlane {
+            normal: [0.0; 3],
+            d: 1.0,
+        })
+        .is_none());
+    }
+
+    #[test]
+    fn to_splat_undoes_ingest() {
+        let file_point = [1.0, 2.0, 3.0];
+        for json in [
+            serde_json::json!({ "mode": 0 }),
+            serde_json::json!({ "mode": 0, "flip_y": true }),
+            serde_json::json!({ "mode": 0, "coordinate_system": "z-up" }),
+            serde_json::json!({ "mode": 0, "coordinate_system": "threejs-rh" }),
+        ] {
+            let settings = settings(json);
+            let ingested = ellipsoid::ingest_frame(&settings) * Vector3::from(file_point);
+            let matrix = to_splat(&settings);
+            assert_eq!(matrix[12..], [0.0, 0.0, 0.0, 1.0]);
+            assert!(close(transform(&matrix, ingested.into()), file_point));
+            assert!(close(
+                transform(&invert(&matrix), file_point),
+                ingested.into()
+            ));
+        }
+    }
+
+    #[test]
+    fn source_mesh_lands_on_the_file_with_faces_kept_up() {
+        let settings = settings(serde_json::json!({
+            "mode": 0,
+            "coordinate_system": "babylon-lh",
+            "environment_scale": 2.0,
+        }));
+        let frame = GroundFrame::new(&settings, None, None);
+        // A counter-clockwise floor triangle, normals +Y.
+        let mesh = MeshBuffers::new(
+            vec![0.0, 0.0, 0.0, 0.0, 0.0, -1.0, 1.0, 0.0, 0.0],
+            vec![0, 2, 1],
+        );
+        let source = mesh_to_splat(&frame, &mesh);
+        let ingest = ellipsoid::ingest_frame(&settings);
+        for (file, oriented) in source
+            .vertices
+            .chunks_exact(3)
+            .zip(mesh.vertices.chunks_exact(3))
+        {
+            let file = Vector3::new(file[0] as f64, file[1] as f64, file[2] as f64);
+            let back = mesh::orient_point(&Point3::from(ingest * file), &settings);
+            let oriented = Vector3::new(oriented[0] as f64, oriented[1] as f64, oriented[2] as f64);
+            assert!((back.coords - oriented).norm() < 1e-6);
+        }
+        // Babylon flips Y on ingest: the file's floor faces -Y, and the
+        // mirror flips the winding to match.
+        assert_eq!(&source.normals[..3], &[0.0, -1.0, 0.0]);
+        assert_eq!(source.indices, [0, 1, 2]);
+    }
+}
@@ -0,0 +1,278 @@
+//! Walking distance over the output mesh (`distance_sources`).
+//!
+//! Flow-field movement and evacuation-style maps need the distance from the
+//! exits to every point of the floor along the floor, not through walls.
+//! [`distances`] runs Dijkstra from the sources over the walkable faces of the
+//! output mesh (the `agent_max_slope` test of `reachable_from`). Vertices are
+//! reached along face edges, through step links (corners stacked within
+//! `agent_max_climb`, where the voxel floors meet across a riser) and across
+//! faces: once two corners of a face are settled, the third is reached along
+//! the straight line from a virtual source unfolded through their edge. On a
+//! flat floor that makes distances straight-line exact rather than following
+//! the triangulation's zigzag.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use nalgebra::Vector3;
+use serde::Serialize;
+
+use crate::mesh::ReconstructedMesh;
+use crate::reachability::{self, AgentLimits};
+use crate::MeshSettings;
+
+/// Walking distances from `distance_sources`.
+#[derive(Serialize)]
+pub struct DistanceField {
+    /// Per vertex of `mesh`: distance to the nearest source in metres, or -1
+    /// where no source reaches.
+    pub distances: Vec<f32>,
+    /// Largest distance reached.
+    pub max_distance: f64,
+    /// Vertices a source reaches.
+    pub reached: usize,
+}
+
+/// `distance_sources` must be a non-empty list of finite `[x, y, z]` points.
+pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+    if let Some(sources) = settings.distance_sources.as_ref() {
+        let point = |s: &Vec<f64>| s.len() >= 3 && s.iter().all(|c| c.is_finite());
+        if sources.is_empty() || !sources.iter().all(point) {
+            return Err(
+                "distance_sources must be a non-empty list of finite [x, y, z] points".to_string(),
+            );
+        }
+    }
+    Ok(())
+}
+
+struct Frontier {
+    distance: f64,
+    vertex: usize,
+}
+
+impl PartialEq for Frontier {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Frontier {}
+
+impl PartialOrd for Frontier {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Frontier {
+    // Reversed so `BinaryHeap` pops the nearest vertex first.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .distance
+            .total_cmp(&self.distance)
+            .then_with(|| other.vertex.cmp(&self.vertex))
+    }
+}
+
+/// Distances over `mesh` from each source, seeded at the corners of the
+/// walkable face whose centroid is nearest it. `link` is the distance under
+/// which corners count as stacked.
+pub fn distances(
+    mesh: &ReconstructedMesh,
+    sources: &[[f64; 3]],
+    limits: &AgentLimits,
+    link: f64,
+) -> DistanceField {
+    let positions: Vec<Vector3<f64>> = mesh
+        .vertices
+        .chunks_exact(3)
+        .map(|v| Vector3::new(v[0] as f64, v[1] as f64, v[2] as f64))
+        .collect();
+    let faces: Vec<[usize; 3]> = mesh
+        .indices
+        .chunks_exact(3)
+        .map(|f| [f[0] as usize, f[1] as usize, f[2] as usize])
+        .filter(|f| f.iter().all(|&i| i < positions.len()))
+        .collect();
+    let walkable = reachability::walkable_faces(&positions, &faces, limits);
+
+    let n = positions.len();
+    let mut vertex_faces: Vec<Vec<usize>> = vec![Vec::new(); n];
+    let mut links: Vec<Vec<(usize, f64)>> = vec![Vec::new(); n];
+    for (fi, f) in faces.iter().enumerate().filter(|&(fi, _)| walkable[fi]) {
+        for k in 0..3 {
+            let (a, b) = (f[k], f[(k + 1) % 3]);
+            let length = (positions[a] - positions[b]).norm();
+            links[a].push((b, length));
+            links[b].push((a, length));
+            vertex_faces[a].push(fi);
+        }
+    }
+    let link = link.max(1e-6);
+    let quantize = |v: f64| (v / link).round() as i64;
+    let mut columns: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+    for (i, p) in positions.iter().enumerate() {
+        if !vertex_faces[i].is_empty() {
+            columns
+                .entry((quantize(p.x), quantize(p.z)))
+                .or_default()
+                .push(i);
+        }
+    }
+    for column in columns.values_mut() {
+        column.sort_by(|&a, &b| positions[a].y.total_cmp(&positions[b].y).then(a.cmp(&b)));
+        for pair in column.windows(2) {
+            let rise = positions[pair[1]].y - positions[pair[0]].y;
+            if rise <= limits.max_climb {
+                links[pair[0]].push((pair[1], rise));
+                links[pair[1]].push((pair[0], rise));
+            }
+        }
+    }
+
+    let mut dist = vec![f64::INFINITY; n];
+    let mut settled = vec![false; n];
+    let mut heap = BinaryHeap::new();
+    let relax = |dist: &mut [f64], heap: &mut BinaryHeap<Frontier>, vertex: usize, d: f64| {
+        if d < dist[vertex] {
+            dist[vertex] = d;
+            heap.push(Frontier {
+                distance: d,
+                vertex,
+            });
+        }
+    };
+    for source in sources {
+        let source = Vector3::new(source[0], source[1], source[2]);
+        let centroid_distance = |fi: usize| {
+            let [a, b, c] = faces[fi].map(|v| positions[v]);
+            ((a + b + c) / 3.0 - source).norm()
+        };
+        let nearest = (0..faces.len())
+            .filter(|&fi| walkable[fi])
+            .min_by(|&a, &b| centroid_distance(a).total_cmp(&centroid_distance(b)));
+        for v in nearest.map(|fi| faces[fi]).into_iter().flatten() {
+            relax(&mut dist, &mut heap, v, (positions[v] - source).norm());
+        }
+    }
+
+    while let Some(next) = heap.pop() {
+        let u = next.vertex;
+        if settled[u] || next.distance > dist[u] {
+            continue;
+        }
+        settled[u] = true;
+        for &(v, length) in &links[u] {
+            if !settled[v] {
+                relax(&mut dist, &mut heap, v, next.distance + length);
+            }
+        }
+        for &fi in &vertex_faces[u] {
+            let f = faces[fi];
+            let k = f.iter().position(|&v| v == u).unwrap_or(0);
+            let (v, w) = (f[(k + 1) % 3], f[(k + 2) % 3]);
+            for (settled_corner, open) in [(v, w), (w, v)] {
+                if settled[settled_corner] && !settled[open] {
+                    let (a, b, c) = (positions[u], positions[settled_corner], positions[open]);
+                    if let Some(d) = unfold(a, b, c, next.distance, dist[settled_corner]) {
+                        relax(&mut dist, &mut heap, open, d);
+                    }
+                }
+            }
+        }
+    }
+
+    let reached: Vec<f64> = dist.iter().copied().filter(|d| d.is_finite()).collect();
+    DistanceField {
+        distances: dist
+            .iter()
+            .map(|&d| if d.is_finite() { d as f32 } else { -1.0 })
+            .collect(),
+        max_distance: reached.iter().copied().fold(0.0, f64::max),
+        reached: reached.len(),
+    }
+}
+
+/// Distance to `c` along a straight line from the virtual source that lies
+/// `da` from `a` and `db` from `b`, on the far side of edge `ab`; `None` when
+/// that line does not cross the edge.
+fn unfold(a: Vector3<f64>, b: Vector3<f64>, c: Vector3<f64>, da: f64, db: f64) -> Option<f64> {
+    let ab = b - a;
+    let edge = ab.norm();
+    if edge <= 0.0 {
+        return None;
+    }
+    // `a` at the origin, `b` on +x, `c` at +y in the face's plane.
+    let ex = ab / edge;
+    let ac = c - a;
+    let cx = ac.dot(&ex);
+    let cy = (ac - ex * cx).norm();
+    if cy <= 0.0 {
+        return None;
+    }
+    let sx = (da * da - db * db + edge * edge) / (2.0 * edge);
+    let sy2 = da * da - sx * sx;
+    if sy2 < 0.0 {
+        return None;
+    }
+    let sy = -sy2.sqrt();
+    let t = -sy / (cy - sy);
+    let x = sx + t * (cx - sx);
+    if !(0.0..=edge).contains(&x) {
+        return None;
+    }
+    Some((cx - sx).hypot(cy - sy))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `size x size` grid of unit squares at height `y`, from (`x`, `z`).
+    fn grid(mesh: &mut ReconstructedMesh, x: f32, y: f32, z: f32, size: u32) {
+        let base = (mesh.vertices.len() / 3) as u32;
+        for j in 0..=size {
+            for i in 0..=size {
+                mesh.vertices
+                    .extend_from_slice(&[x + i as f32, y, z + j as f32]);
+            }
+        }
+        let at = |i: u32, j: u32| base + j * (size + 1) + i;
+        for j in 0..size {
+            for i in 0..size {
+                let (a, b, c, d) = (at(i, j), at(i + 1, j), at(i + 1, j + 1), at(i, j + 1));
+                mesh.indices.extend([a, c, b, a, d, c]);
+            }
+        }
+    }
+
+    #[test]
+    fn distances_are_straight_on_the_flat_and_climb_steps() {
+        let mut mesh = ReconstructedMesh {
+            vertices: vec![],
+            indices: vec![],
+        };
+        grid(&mut mesh, 0.0, 0.0, 0.0, 10);
+        // A 0.3 m step up beyond x = 10, and a far-off unreachable island.
+        grid(&mut mesh, 10.0, 0.3, 0.0, 2);
+        grid(&mut mesh, 30.0, 0.0, 0.0, 1);
+        let limits = AgentLimits {
+            max_climb: 0.5,
+            max_slope_deg: 40.0,
+            max_step: 0.0,
+        };
+        let field = distances(&mesh, &[[0.0, 0.0, 0.0]], &limits, 1e-3);
+        let at = |i: usize| field.distances[i] as f64;
+
+        // (10, 0, 5): straight line 11.18, edges alone would give 12.07.
+        assert!((at(5 * 11 + 10) - 125f64.sqrt()).abs() < 1e-3);
+        assert!((at(10 * 11 + 10) - 200f64.sqrt()).abs() < 1e-3);
+        // Across the step: the raised grid's (12, 0.3, 0) corner.
+        let step = 121 + 2;
+        assert!(at(step) > 12.0 && at(step) < 12.4);
+        // The island is never reached.
+        assert!(field.distances[121 + 9..].iter().all(|&d| d == -1.0));
+        assert_eq!(field.reached, 121 + 9);
+    }
+}
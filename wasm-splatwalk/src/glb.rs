@@ -1,13 +1,15 @@
 //! Minimal, dependency-free binary glTF (GLB) writer.
 //!
-//! Serializes a positions + indices triangle mesh (no materials, no normals) into
-//! a single-buffer GLB so a binary integrator can turn vertex/index buffers into
+//! Serializes a positions + indices triangle mesh (no materials) into a
+//! single-buffer GLB so a binary integrator can turn vertex/index buffers into
 //! GLB bytes without standing up a full 3D engine per call. This is intentionally
-//! tiny: one buffer, two buffer views (indices + positions), two accessors, one
-//! mesh/node/scene.
+//! tiny: one buffer, a buffer view + accessor each for indices, positions and
+//! (optionally) smooth vertex normals, one mesh/node/scene.
 
 use serde_json::json;
 
+use crate::normals;
+
 const GLB_MAGIC: u32 = 0x46546C67; // "glTF"
 const GLB_VERSION: u32 = 2;
 const CHUNK_JSON: u32 = 0x4E4F534A; // "JSON"
@@ -19,11 +21,32 @@ const TARGET_ARRAY_BUFFER: u32 = 34962;
 const TARGET_ELEMENT_ARRAY_BUFFER: u32 = 34963;
 const MODE_TRIANGLES: u32 = 4;
 
+/// Optional GLB content beyond positions + indices.
+#[derive(Clone, Debug, Default, serde::Deserialize)]
+pub struct GlbOptions {
+    /// Emit a `NORMAL` attribute: angle-weighted vertex normals following the
+    /// triangle winding (counter-clockwise front faces, as glTF expects).
+    #[serde(default)]
+    pub normals: bool,
+    /// Mesh and node name, so engines show something better than `mesh_0`.
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
 /// Serialize `positions` (xyz triplets) and `indices` (`u32`) into GLB bytes.
 ///
 /// Errors when the inputs are empty or malformed (positions length not a multiple
 /// of 3, indices length not a multiple of 3, or an index out of range).
 pub fn mesh_to_glb(positions: &[f32], indices: &[u32]) -> Result<Vec<u8>, String> {
+    mesh_to_glb_with(positions, indices, &GlbOptions::default())
+}
+
+/// [`mesh_to_glb`] with [`GlbOptions`]. Default options produce the same bytes.
+pub fn mesh_to_glb_with(
+    positions: &[f32],
+    indices: &[u32],
+    options: &GlbOptions,
+) -> Result<Vec<u8>, String> {
     if positions.is_empty() || indices.is_empty() {
         return Err("mesh_to_glb: empty positions or indices".to_string());
     }
@@ -64,10 +87,13 @@ pub fn mesh_to_glb(positions: &[f32], indices: &[u32]) -> Result<Vec<u8>, String
         }
     }
 
-    // BIN: indices first (already 4-byte aligned), then positions.
+    // BIN: indices first (already 4-byte aligned), then positions, then normals.
     let indices_byte_len = indices.len() * 4;
     let positions_byte_len = positions.len() * 4;
-    let mut bin: Vec<u8> = Vec::with_capacity(indices_byte_len + positions_byte_len);
+    let normals = options
+        .normals
+        .then(|| normals::angle_weighted(positions, indices));
+    let mut bin: Vec<u8> = Vec::with_capacity(indices_byte_len + positions_byte_len * 2);
     for &i in indices {
         bin.extend_from_slice(&i.to_le_bytes());
     }
@@ -75,9 +101,13 @@ pub fn mesh_to_glb(positions: &[f32], indices: &[u32]) -> Result<Vec<u8>, String
     for &p in positions {
         bin.extend_from_slice(&p.to_le_bytes());
     }
+    let normals_offset = bin.len();
+    for &n in normals.iter().flatten() {
+        bin.extend_from_slice(&n.to_le_bytes());
+    }
     pad_to_4(&mut bin, 0x00);
 
-    let gltf = json!({
+    let mut gltf = json!({
         "asset": { "version": "2.0", "generator": "splatwalk" },
         "buffers": [ { "byteLength": bin.len() } ],
         "bufferViews": [
@@ -127,6 +157,26 @@ pub fn mesh_to_glb(positions: &[f32], indices: &[u32]) -> Result<Vec<u8>, String
         "scenes": [ { "nodes": [0] } ],
         "scene": 0
     });
+    if normals.is_some() {
+        gltf["bufferViews"].as_array_mut().unwrap().push(json!({
+            "buffer": 0,
+            "byteOffset": normals_offset,
+            "byteLength": positions_byte_len,
+            "target": TARGET_ARRAY_BUFFER
+        }));
+        gltf["accessors"].as_array_mut().unwrap().push(json!({
+            "bufferView": 2,
+            "byteOffset": 0,
+            "componentType": COMPONENT_TYPE_FLOAT,
+            "count": vertex_count,
+            "type": "VEC3"
+        }));
+        gltf["meshes"][0]["primitives"][0]["attributes"]["NORMAL"] = json!(2);
+    }
+    if let Some(name) = &options.name {
+        gltf["meshes"][0]["name"] = json!(name);
+        gltf["nodes"][0]["name"] = json!(name);
+    }
 
     let mut json_bytes = serde_json::to_vec(&gltf).map_err(|e| e.to_string())?;
     pad_to_4(&mut json_bytes, b' ');
@@ -158,3 +208,44 @@ fn pad_to_4(buf: &mut Vec<u8>, fill: u8) {
         buf.push(fill);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn json_chunk(glb: &[u8]) -> serde_json::Value {
+        let len = u32::from_le_bytes(glb[12..16].try_into().unwrap()) as usize;
+        serde_json::from_slice(&glb[20..20 + len]).unwrap()
+    }
+
+    #[test]
+    fn normals_are_optional_and_follow_winding() {
+        // One counter-clockwise triangle in the XZ plane, seen from +Y.
+        let positions = [0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0];
+        let indices = [0, 1, 2];
+        let plain = mesh_to_glb(&positions, &indices).unwrap();
+        assert!(
+            json_chunk(&plain)["meshes"][0]["primitives"][0]["attributes"]
+                .get("NORMAL")
+                .is_none()
+        );
+
+        let options = GlbOptions {
+            normals: true,
+            name: Some("floor".to_string()),
+        };
+        let glb = mesh_to_glb_with(&positions, &indices, &options).unwrap();
+        assert_eq!(glb.len() % 4, 0);
+        let gltf = json_chunk(&glb);
+        assert_eq!(
+            gltf["meshes"][0]["primitives"][0]["attributes"]["NORMAL"],
+            2
+        );
+        assert_eq!(gltf["nodes"][0]["name"], "floor");
+        assert_eq!(gltf["accessors"][2]["count"], 3);
+        assert_eq!(
+            normals::angle_weighted(&positions, &indices)[..3],
+            [0.0, 1.0, 0.0]
+        );
+    }
+}
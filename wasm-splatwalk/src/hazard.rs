@@ -0,0 +1,165 @@
+//! Hazard tagging: water and no-go volumes (`hazard_water_height`,
+//! `hazard_volumes`).
+//!
+//! A scan of a dock, a pool or a fountain bakes the water surface, or the
+//! pool floor under it, as floor. [`tag`] flags every output face whose centre
+//! lies below the water height or inside one of the hazard volumes, so agents
+//! keep out of them by default; with `hazard_cut` [`cut`] drops those faces
+//! before the reachability split, so they never enter the walkable surface.
+
+use nalgebra::{Point3, Vector3};
+use serde::Serialize;
+
+use crate::mesh::ReconstructedMesh;
+use crate::reachability;
+use crate::region::RegionVolume;
+use crate::MeshSettings;
+
+/// Flag bit: the face's centre is below `hazard_water_height`.
+pub const WATER: u8 = 1;
+/// Flag bit: the face's centre is inside a `hazard_volumes` entry.
+pub const VOLUME: u8 = 2;
+
+/// Hazard flags of the output mesh.
+#[derive(Serialize)]
+pub struct HazardTags {
+    /// Per face of `mesh`: `WATER | VOLUME` bits, 0 when safe.
+    pub flags: Vec<u8>,
+    /// Faces with any flag.
+    pub hazard_faces: usize,
+    /// Faces `hazard_cut` removed before tagging.
+    pub cut_faces: usize,
+}
+
+/// The hazards of one bake.
+pub struct Hazards {
+    water: Option<f64>,
+    volumes: Vec<RegionVolume>,
+}
+
+impl Hazards {
+    /// `None` when neither a water height nor a volume was given.
+    pub fn from_settings(settings: &MeshSettings) -> Option<Self> {
+        let volumes: Vec<RegionVolume> = settings
+            .hazard_volumes
+            .as_deref()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|rule| rule.volume())
+            .collect();
+        let water = settings.hazard_water_height;
+        (water.is_some() || !volumes.is_empty()).then_some(Self { water, volumes })
+    }
+
+    fn flag(&self, centre: &Point3<f64>) -> u8 {
+        let mut flags = 0;
+        if self.water.is_some_and(|h| centre.y < h) {
+            flags |= WATER;
+        }
+        if self.volumes.iter().any(|v| v.contains(centre)) {
+            flags |= VOLUME;
+        }
+        flags
+    }
+}
+
+/// `hazard_water_height` must be finite and every `hazard_volumes` entry a
+/// well-formed region shape.
+pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+    if let Some(height) = settings.hazard_water_height {
+        if !height.is_finite() {
+            return Err(format!("Invalid hazard_water_height: {}", height));
+        }
+    }
+    for (i, rule) in settings
+        .hazard_volumes
+        .as_deref()
+        .unwrap_or_default()
+        .iter()
+        .enumerate()
+    {
+        if rule.volume().is_none() {
+            return Err(format!(
+                "hazard_volumes[{}] is not a valid box, sphere, polygon or frustum",
+                i
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn positions(mesh: &ReconstructedMesh) -> Vec<Vector3<f64>> {
+    mesh.vertices
+        .chunks_exact(3)
+        .map(|v| Vector3::new(v[0] as f64, v[1] as f64, v[2] as f64))
+        .collect()
+}
+
+/// Per face of `mesh`: its hazard flags. Faces with a corner out of range
+/// are left safe.
+pub fn tag(mesh: &ReconstructedMesh, hazards: &Hazards) -> Vec<u8> {
+    let positions = positions(mesh);
+    mesh.indices
+        .chunks_exact(3)
+        .map(|f| {
+            let corners: Option<Vec<Vector3<f64>>> = f
+                .iter()
+                .map(|&i| positions.get(i as usize).copied())
+                .collect();
+            corners.map_or(0, |c| {
+                hazards.flag(&Point3::from((c[0] + c[1] + c[2]) / 3.0))
+            })
+        })
+        .collect()
+}
+
+/// `mesh` without its hazard faces, and how many were removed.
+pub fn cut(mesh: &ReconstructedMesh, hazards: &Hazards) -> (ReconstructedMesh, usize) {
+    let flags = tag(mesh, hazards);
+    let positions = positions(mesh);
+    let faces: Vec<[usize; 3]> = mesh
+        .indices
+        .chunks_exact(3)
+        .map(|f| [f[0] as usize, f[1] as usize, f[2] as usize])
+        .collect();
+    let keep: Vec<usize> = (0..faces.len())
+        .filter(|&fi| flags[fi] == 0 && faces[fi].iter().all(|&i| i < positions.len()))
+        .collect();
+    let removed = faces.len() - keep.len();
+    (reachability::submesh(&positions, &faces, &keep), removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn faces_under_water_or_in_a_volume_are_flagged_and_cut() {
+        // Three unit squares along x: a pool floor at y = -1, a deck at 0 and
+        // a deck at 0 with a fountain sphere on it.
+        let mut mesh = ReconstructedMesh {
+            vertices: vec![],
+            indices: vec![],
+        };
+        for (x, y) in [(0.0f32, -1.0f32), (1.0, 0.0), (2.0, 0.0)] {
+            let base = (mesh.vertices.len() / 3) as u32;
+            for (dx, dz) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)] {
+                mesh.vertices.extend_from_slice(&[x + dx, y, dz]);
+            }
+            mesh.indices
+                .extend([0, 2, 1, 0, 3, 2].iter().map(|i| base + i));
+        }
+        let hazards = Hazards {
+            water: Some(-0.2),
+            volumes: vec![RegionVolume::Sphere {
+                center: [2.5, 0.0, 0.5],
+                radius: 0.6,
+            }],
+        };
+        assert_eq!(tag(&mesh, &hazards), [WATER, WATER, 0, 0, VOLUME, VOLUME]);
+        let (kept, removed) = cut(&mesh, &hazards);
+        assert_eq!(removed, 4);
+        assert_eq!(kept.indices.len(), 6);
+        assert_eq!(kept.vertices.len(), 4 * 3);
+    }
+}
@@ -0,0 +1,176 @@
+//! Heightmap export of the mode 2 voxel floor (`emit_heightmap`).
+//!
+//! Mode 2 builds its floor on the voxel grid: every vertex sits on a grid
+//! corner, at the height of the floor there. That height grid used to be
+//! thrown away once the faces were built, so an engine wanting a terrain
+//! collider or a Babylon `CreateGroundFromHeightMap` ground had to rasterize
+//! the mesh again. [`from_floor_mesh`] reads it back off the mesh before
+//! decimation and returns it as 16-bit samples, either a grayscale PNG or raw
+//! little-endian `u16`, with the world-space extents the samples span.
+//!
+//! Where floors overlap, the highest one wins. Corners with no floor are
+//! written as 0, the lowest height.
+
+use serde::{Deserialize, Serialize};
+
+use crate::thumbnail;
+use crate::MeshSettings;
+
+/// Encodings `emit_heightmap.format` accepts.
+pub const FORMATS: &[&str] = &["png", "raw"];
+
+/// `emit_heightmap`.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct HeightmapSettings {
+    /// `"png"` (default): a 16-bit grayscale PNG; `"raw"`: the samples as
+    /// little-endian `u16`.
+    pub format: Option<String>,
+}
+
+/// The floor's height grid, in `splatwalk_oriented`.
+#[derive(Clone, Serialize)]
+pub struct Heightmap {
+    pub format: String,
+    /// Samples per row, along `+X`, and rows, from `max.z` down to `min.z`
+    /// (image order for a map seen from above).
+    pub width: usize,
+    pub height: usize,
+    /// Metres between neighbouring samples.
+    pub cell_size: f64,
+    /// `x` and `z` of the first and last samples; `y` the heights the sample
+    /// values 0 and 65535 stand for.
+    pub min: [f64; 3],
+    pub max: [f64; 3],
+    /// The PNG file, or `width * height` little-endian `u16` samples.
+    pub data: serde_bytes::ByteBuf,
+}
+
+/// The format must be one of [`FORMATS`].
+pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+    let Some(format) = settings
+        .emit_heightmap
+        .as_ref()
+        .and_then(|h| h.format.as_deref())
+    else {
+        return Ok(());
+    };
+    if !FORMATS.contains(&format) {
+        return Err(format!(
+            "Invalid emit_heightmap.format: \"{}\" (expected one of {})",
+            format,
+            FORMATS.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+/// The height grid of a floor mesh whose vertices lie on the corners of a
+/// grid at `origin` with `cell_size` edges, or `None` when the mesh is empty.
+pub fn from_floor_mesh(
+    vertices: &[f32],
+    origin: [f64; 3],
+    cell_size: f64,
+    settings: &HeightmapSettings,
+) -> Result<Option<Heightmap>, String> {
+    let corner = |v: &[f32]| {
+        let column = ((v[0] as f64 - origin[0]) / cell_size).round() as i64;
+        let row = ((v[2] as f64 - origin[2]) / cell_size).round() as i64;
+        (column, row, v[1] as f64)
+    };
+    let corners: Vec<(i64, i64, f64)> = vertices.chunks_exact(3).map(corner).collect();
+    let Some(&(first_column, first_row, _)) = corners.first() else {
+        return Ok(None);
+    };
+    let (mut lo, mut hi) = ([first_column, first_row], [first_column, first_row]);
+    for &(column, row, _) in &corners {
+        lo = [lo[0].min(column), lo[1].min(row)];
+        hi = [hi[0].max(column), hi[1].max(row)];
+    }
+    let width = (hi[0] - lo[0] + 1) as usize;
+    let height = (hi[1] - lo[1] + 1) as usize;
+    let mut heights = vec![f64::NEG_INFINITY; width * height];
+    for &(column, row, y) in &corners {
+        // Row 0 is the far (`max.z`) edge.
+        let cell = (hi[1] - row) as usize * width + (column - lo[0]) as usize;
+        heights[cell] = heights[cell].max(y);
+    }
+    let kept = heights.iter().copied().filter(|y| y.is_finite());
+    let low_y = kept.clone().fold(f64::INFINITY, f64::min);
+    let high_y = kept.fold(f64::NEG_INFINITY, f64::max);
+    let range = high_y - low_y;
+    let samples: Vec<u16> = heights
+        .iter()
+        .map(|&y| {
+            if y.is_finite() && range > 0.0 {
+                ((y - low_y) / range * 65535.0).round() as u16
+            } else {
+                0
+            }
+        })
+        .collect();
+
+    let format = settings.format.as_deref().unwrap_or(FORMATS[0]);
+    let data = if format == "raw" {
+        samples.iter().flat_map(|s| s.to_le_bytes()).collect()
+    } else {
+        thumbnail::encode_gray16_png(width, height, &samples)?
+    };
+    Ok(Some(Heightmap {
+        format: format.to_string(),
+        width,
+        height,
+        cell_size,
+        min: [
+            origin[0] + lo[0] as f64 * cell_size,
+            low_y,
+            origin[2] + lo[1] as f64 * cell_size,
+        ],
+        max: [
+            origin[0] + hi[0] as f64 * cell_size,
+            high_y,
+            origin[2] + hi[1] as f64 * cell_size,
+        ],
+        data: serde_bytes::ByteBuf::from(data),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corners_become_samples_with_the_far_row_first() {
+        // Two by one cells on a 0.5 m grid at (1, 0, 2), stepping up 0.25 m
+        // towards +X and, in the middle, towards +Z. The floor below one
+        // corner is hidden by the one above.
+        let vertices = [
+            1.0, 0.0, 2.0, 1.5, 0.0, 2.0, 2.0, 0.25, 2.0, //
+            1.0, 0.0, 2.5, 1.5, 0.25, 2.5, 2.0, 0.25, 2.5, //
+            2.0, -1.0, 2.5,
+        ];
+        let raw = HeightmapSettings {
+            format: Some("raw".to_string()),
+        };
+        let map = from_floor_mesh(&vertices, [1.0, 0.0, 2.0], 0.5, &raw)
+            .unwrap()
+            .unwrap();
+        assert_eq!((map.width, map.height), (3, 2));
+        assert_eq!(map.min, [1.0, 0.0, 2.0]);
+        assert_eq!(map.max, [2.0, 0.25, 2.5]);
+        let samples: Vec<u16> = map
+            .data
+            .chunks_exact(2)
+            .map(|s| u16::from_le_bytes([s[0], s[1]]))
+            .collect();
+        assert_eq!(samples, vec![0, 65535, 65535, 0, 0, 65535]);
+
+        let png = from_floor_mesh(&vertices, [1.0, 0.0, 2.0], 0.5, &Default::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(png.format, "png");
+        assert_eq!(&png.data[12..16], b"IHDR");
+        // Bit depth 16, grayscale.
+        assert_eq!(&png.data[24..26], &[16, 0]);
+        assert!(from_floor_mesh(&[], [0.0; 3], 0.5, &raw).unwrap().is_none());
+    }
+}
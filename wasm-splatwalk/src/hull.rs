@@ -0,0 +1,370 @@
+//! Convex hull of the filtered cloud (`mode = 7`).
+//!
+//! Physics engines want a convex collider for a scanned chair or crate, not a
+//! dense reconstruction of it. Quickhull builds the hull from the splat
+//! centres directly: start from a tetrahedron of extreme points, then keep
+//! adding the point farthest outside the current hull, replacing the faces it
+//! sees with a fan from their horizon. The farthest point always goes first,
+//! so stopping early at `hull_max_vertices` leaves the best hull of that size
+//! the greedy order finds, with the few points it misses just outside.
+
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use nalgebra::Vector3;
+
+use crate::MeshSettings;
+
+/// `hull_max_vertices` must leave room for the starting tetrahedron.
+pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+    match settings.hull_max_vertices {
+        Some(n) if n < 4 => Err(format!(
+            "Invalid hull_max_vertices: {}. Expected at least 4.",
+            n
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// A hull triangle, wound counter-clockwise seen from outside.
+struct Face {
+    v: [usize; 3],
+    normal: Vector3<f64>,
+    offset: f64,
+    /// Points above this face that no earlier face claimed.
+    outside: Vec<usize>,
+    /// The point of `outside` farthest from the plane and its distance.
+    farthest: Option<(usize, f64)>,
+    alive: bool,
+}
+
+impl Face {
+    fn new(points: &[Vector3<f64>], v: [usize; 3]) -> Self {
+        let [a, b, c] = v.map(|i| points[i]);
+        let normal = (b - a).cross(&(c - a));
+        let normal = normal.try_normalize(0.0).unwrap_or(normal);
+        Face {
+            v,
+            normal,
+            offset: normal.dot(&a),
+            outside: Vec::new(),
+            farthest: None,
+            alive: true,
+        }
+    }
+
+    fn distance(&self, p: &Vector3<f64>) -> f64 {
+        self.normal.dot(p) - self.offset
+    }
+
+    /// Claim point `i` if it lies more than `eps` above the face.
+    fn claim(&mut self, points: &[Vector3<f64>], i: usize, eps: f64) -> bool {
+        let d = self.distance(&points[i]);
+        if d <= eps {
+            return false;
+        }
+        self.outside.push(i);
+        if self.farthest.is_none_or(|(_, best)| d > best) {
+            self.farthest = Some((i, d));
+        }
+        true
+    }
+
+    fn edges(&self) -> [(usize, usize); 3] {
+        let [a, b, c] = self.v;
+        [(a, b), (b, c), (c, a)]
+    }
+}
+
+/// The hull as a mesh.
+pub struct HullBuild {
+    pub vertices: Vec<f32>,
+    pub indices: Vec<u32>,
+    pub volume: f64,
+    /// Points left outside because `max_vertices` stopped the build early.
+    pub points_outside: usize,
+}
+
+/// The convex hull of `points` with at most `max_vertices` vertices, or
+/// `None` when the points span no volume (fewer than four, or all on one
+/// plane). `progress` receives the `hull` stage and the share of the
+/// initially outside points processed.
+pub fn build(
+    points: &[Vector3<f64>],
+    max_vertices: usize,
+    progress: &dyn Fn(&str, f64),
+) -> Option<HullBuild> {
+    let points: Vec<Vector3<f64>> = points
+        .iter()
+        .filter(|p| p.iter().all(|v| v.is_finite()))
+        .copied()
+        .collect();
+    let (v, eps) = initial_simplex(&points)?;
+
+    let mut faces: Vec<Face> = Vec::new();
+    let centroid = v.iter().map(|&i| points[i]).sum::<Vector3<f64>>() / 4.0;
+    for tri in [
+        [v[0], v[1], v[2]],
+        [v[0], v[3], v[1]],
+        [v[1], v[3], v[2]],
+        [v[2], v[3], v[0]],
+    ] {
+        let mut face = Face::new(&points, tri);
+        if face.distance(&centroid) > 0.0 {
+            face = Face::new(&points, [tri[0], tri[2], tri[1]]);
+        }
+        faces.push(face);
+    }
+    let mut edges: HashMap<(usize, usize), usize> = HashMap::new();
+    for (fi, face) in faces.iter().enumerate() {
+        for edge in face.edges() {
+            edges.insert(edge, fi);
+        }
+    }
+    let mut initially_outside = 0;
+    for i in (0..points.len()).filter(|i| !v.contains(i)) {
+        if let Some(face) = faces.iter_mut().find(|f| f.distance(&points[i]) > eps) {
+            face.claim(&points, i, eps);
+            initially_outside += 1;
+        }
+    }
+
+    // Faces by their farthest outside point. A face's outside set is fixed
+    // when it is created, so entries only go stale by the face dying.
+    // Distances are positive, so their bit patterns order like the values.
+    let mut queue: BinaryHeap<(u64, usize)> = faces
+        .iter()
+        .enumerate()
+        .filter_map(|(fi, f)| f.farthest.map(|(_, d)| (d.to_bits(), fi)))
+        .collect();
+    let mut added = 4;
+    let mut processed = 0;
+    while added < max_vertices {
+        let Some((_, start)) = std::iter::from_fn(|| queue.pop()).find(|&(_, fi)| faces[fi].alive)
+        else {
+            break;
+        };
+        let Some((eye, _)) = faces[start].farthest else {
+            break;
+        };
+        let apex = points[eye];
+
+        // The faces the eye sees form a connected cap; flood it from `start`.
+        let mut visible = HashSet::from([start]);
+        let mut stack = vec![start];
+        while let Some(fi) = stack.pop() {
+            for (a, b) in faces[fi].edges() {
+                let Some(&twin) = edges.get(&(b, a)) else {
+                    continue;
+                };
+                if !visible.contains(&twin) && faces[twin].distance(&apex) > eps {
+                    visible.insert(twin);
+                    stack.push(twin);
+                }
+            }
+        }
+        let horizon: Vec<(usize, usize)> = visible
+            .iter()
+            .flat_map(|&fi| faces[fi].edges())
+            .filter(|&(a, b)| {
+                edges
+                    .get(&(b, a))
+                    .is_none_or(|twin| !visible.contains(twin))
+            })
+            .collect();
+
+        let mut orphans = Vec::new();
+        for &fi in &visible {
+            let face = &mut faces[fi];
+            face.alive = false;
+            orphans.append(&mut face.outside);
+            for edge in face.edges() {
+                edges.remove(&edge);
+            }
+        }
+        let first_new = faces.len();
+        for (a, b) in horizon {
+            let face = Face::new(&points, [a, b, eye]);
+            for edge in face.edges() {
+                edges.insert(edge, faces.len());
+            }
+            faces.push(face);
+        }
+        for i in orphans {
+            if i == eye {
+                continue;
+            }
+            if !faces[first_new..]
+                .iter_mut()
+                .any(|f| f.claim(&points, i, eps))
+            {
+                processed += 1;
+            }
+        }
+        for (fi, face) in faces.iter().enumerate().skip(first_new) {
+            if let Some((_, d)) = face.farthest {
+                queue.push((d.to_bits(), fi));
+            }
+        }
+        processed += 1;
+        added += 1;
+        if added % 256 == 0 {
+            let fraction = processed as f64 / initially_outside.max(1) as f64;
+            progress("hull", fraction.min(1.0));
+        }
+    }
+    progress("hull", 1.0);
+
+    let alive: Vec<&Face> = faces.iter().filter(|f| f.alive).collect();
+    let mut remap = HashMap::new();
+    let mut vertices = Vec::new();
+    let mut indices = Vec::with_capacity(alive.len() * 3);
+    for face in &alive {
+        for &i in &face.v {
+            let next = remap.len() as u32;
+            let index = *remap.entry(i).or_insert_with(|| {
+                vertices.extend(points[i].iter().map(|&c| c as f32));
+                next
+            });
+            indices.push(index);
+        }
+    }
+    // Signed tetrahedra against an inside point, so large coordinates don't
+    // cancel.
+    let volume = alive
+        .iter()
+        .map(|f| {
+            let [a, b, c] = f.v.map(|i| points[i] - centroid);
+            a.dot(&b.cross(&c)) / 6.0
+        })
+        .sum();
+    Some(HullBuild {
+        vertices,
+        indices,
+        volume,
+        points_outside: alive.iter().map(|f| f.outside.len()).sum(),
+    })
+}
+
+/// Four points spanning the largest tetrahedron the extremes suggest, and the
+/// distance tolerance for the cloud's scale.
+fn initial_simplex(points: &[Vector3<f64>]) -> Option<([usize; 4], f64)> {
+    if points.len() < 4 {
+        return None;
+    }
+    let mut extremes = [0usize; 6];
+    for (i, p) in points.iter().enumerate() {
+        for axis in 0..3 {
+            if p[axis] < points[extremes[axis * 2]][axis] {
+                extremes[axis * 2] = i;
+            }
+            if p[axis] > points[extremes[axis * 2 + 1]][axis] {
+                extremes[axis * 2 + 1] = i;
+            }
+        }
+    }
+    let extent = (0..3)
+        .map(|axis| points[extremes[axis * 2 + 1]][axis] - points[extremes[axis * 2]][axis])
+        .fold(0.0, f64::max);
+    let eps = extent * 1e-9;
+    if extent <= 0.0 {
+        return None;
+    }
+
+    let (a, b) = extremes
+        .iter()
+        .flat_map(|&i| extremes.iter().map(move |&j| (i, j)))
+        .max_by(|x, y| {
+            let dx = (points[x.0] - points[x.1]).norm_squared();
+            let dy = (points[y.0] - points[y.1]).norm_squared();
+            dx.total_cmp(&dy)
+        })?;
+    let axis = (points[b] - points[a]).normalize();
+    let from_line = |p: &Vector3<f64>| {
+        let r = p - points[a];
+        (r - axis * r.dot(&axis)).norm()
+    };
+    let c = (0..points.len())
+        .max_by(|&i, &j| from_line(&points[i]).total_cmp(&from_line(&points[j])))?;
+    if from_line(&points[c]) <= eps {
+        return None;
+    }
+    let normal = (points[b] - points[a])
+        .cross(&(points[c] - points[a]))
+        .normalize();
+    let from_plane = |p: &Vector3<f64>| normal.dot(&(p - points[a])).abs();
+    let d = (0..points.len())
+        .max_by(|&i, &j| from_plane(&points[i]).total_cmp(&from_plane(&points[j])))?;
+    if from_plane(&points[d]) <= eps {
+        return None;
+    }
+    Some(([a, b, c, d], eps))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cube_cloud_hulls_to_its_corners() {
+        // A 2 m cube's corners plus interior and face points.
+        let mut points = Vec::new();
+        for i in 0..8 {
+            points.push(Vector3::new(
+                (i & 1) as f64 * 2.0,
+                ((i >> 1) & 1) as f64 * 2.0,
+                ((i >> 2) & 1) as f64 * 2.0,
+            ));
+        }
+        for i in 0..200 {
+            let t = i as f64 / 200.0;
+            points.push(Vector3::new(0.1 + 1.8 * t, 1.0, 0.3 + 1.4 * (1.0 - t)));
+            points.push(Vector3::new(1.0, 2.0, 2.0 * t));
+        }
+        let hull = build(&points, usize::MAX, &|_, _| {}).unwrap();
+        assert_eq!(hull.vertices.len() / 3, 8);
+        assert_eq!(hull.indices.len() / 3, 12);
+        assert!((hull.volume - 8.0).abs() < 1e-9, "{}", hull.volume);
+        assert_eq!(hull.points_outside, 0);
+
+        // Every face points away from the centre.
+        let v = |i: u32| {
+            let i = i as usize * 3;
+            Vector3::new(
+                hull.vertices[i] as f64,
+                hull.vertices[i + 1] as f64,
+                hull.vertices[i + 2] as f64,
+            )
+        };
+        for f in hull.indices.chunks_exact(3) {
+            let n = (v(f[1]) - v(f[0])).cross(&(v(f[2]) - v(f[0])));
+            assert!(n.dot(&(v(f[0]) - Vector3::new(1.0, 1.0, 1.0))) > 0.0);
+        }
+    }
+
+    #[test]
+    fn vertex_cap_and_flat_clouds() {
+        let sphere: Vec<Vector3<f64>> = (0..500)
+            .map(|i| {
+                // Fibonacci sphere.
+                let y = 1.0 - 2.0 * (i as f64 + 0.5) / 500.0;
+                let phi = i as f64 * 2.399963;
+                let r = (1.0 - y * y).sqrt();
+                Vector3::new(r * phi.cos(), y, r * phi.sin())
+            })
+            .collect();
+        let full = build(&sphere, usize::MAX, &|_, _| {}).unwrap();
+        assert_eq!(full.vertices.len() / 3, 500);
+        assert_eq!(full.indices.len() / 3, 2 * 500 - 4);
+        assert!((full.volume - 4.0 / 3.0 * std::f64::consts::PI).abs() < 0.05);
+
+        let capped = build(&sphere, 32, &|_, _| {}).unwrap();
+        assert!(capped.vertices.len() / 3 <= 32);
+        assert!(capped.points_outside > 0);
+        assert!(capped.volume < full.volume);
+
+        let flat: Vec<Vector3<f64>> = (0..50)
+            .map(|i| Vector3::new(i as f64 % 7.0, 0.5, i as f64 / 7.0))
+            .collect();
+        assert!(build(&flat, usize::MAX, &|_, _| {}).is_none());
+    }
+}
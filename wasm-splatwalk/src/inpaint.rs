@@ -0,0 +1,250 @@
+//! Heightfield inpainting for large holes in the ground field.
+//!
+//! Floor hidden under furniture or behind an occluder gets no splats, so its
+//! cells come out void and the navmesh has a hole exactly where a player
+//! expects to walk. `hole_fill_radius` only closes pockets a few cells wide,
+//! and only with one flat height. [`inpaint_holes`] takes enclosed void and
+//! low-confidence regions up to `fill_holes_max_area` and solves Laplace's
+//! equation over them, with the surrounding floor heights as the boundary.
+//! The filled heights blend smoothly between the edges, so a hole in a ramp
+//! stays on the ramp instead of becoming a flat shelf.
+//!
+//! A region qualifies when it does not touch the grid border and at least
+//! half of its boundary is accepted floor. Obstacle cells on the rest of the
+//! boundary (a sofa's footprint next to the floor it hid) do not pin any
+//! height; the diffusion just does not flow across them.
+
+use std::collections::VecDeque;
+
+use crate::{GroundFieldCell, GroundFieldCellState, MeshSettings};
+
+/// Share of a region's boundary that must be accepted floor.
+const MIN_FLOOR_BOUNDARY: f64 = 0.5;
+
+/// Successive over-relaxation factor and limits for the Laplace solve.
+const RELAXATION: f32 = 1.8;
+const MAX_ITERATIONS: usize = 500;
+const TOLERANCE: f32 = 1e-4;
+
+/// `fill_holes_max_area` must be a finite, non-negative area when set.
+pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+    match settings.fill_holes_max_area {
+        Some(area) if !(area.is_finite() && area >= 0.0) => {
+            Err(format!("Invalid fill_holes_max_area: {}", area))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// What [`inpaint_holes`] filled.
+#[derive(Debug, Default, PartialEq)]
+pub struct Inpainted {
+    pub regions: usize,
+    pub cells: usize,
+}
+
+fn is_hole(state: &GroundFieldCellState) -> bool {
+    matches!(
+        state,
+        GroundFieldCellState::LowConfidence | GroundFieldCellState::Void
+    )
+}
+
+fn is_floor(state: &GroundFieldCellState) -> bool {
+    matches!(
+        state,
+        GroundFieldCellState::Walkable | GroundFieldCellState::Filled
+    )
+}
+
+/// Fill every qualifying hole region of at most `max_area` square metres
+/// with harmonic heights and mark its cells `Filled`.
+pub fn inpaint_holes(
+    cells: &mut [GroundFieldCell],
+    width: usize,
+    height: usize,
+    cell_size: f64,
+    max_area: f64,
+) -> Inpainted {
+    let mut inpainted = Inpainted::default();
+    let max_cells = (max_area / (cell_size * cell_size)).floor() as usize;
+    if max_cells == 0 || width == 0 || height == 0 {
+        return inpainted;
+    }
+    let neighbours = |idx: usize| {
+        let (row, col) = (idx / width, idx % width);
+        [
+            (row > 0).then(|| idx - width),
+            (row + 1 < height).then(|| idx + width),
+            (col > 0).then(|| idx - 1),
+            (col + 1 < width).then(|| idx + 1),
+        ]
+        .into_iter()
+        .flatten()
+    };
+
+    let mut visited = vec![false; cells.len()];
+    for start in 0..cells.len() {
+        if visited[start] || !is_hole(&cells[start].state) {
+            continue;
+        }
+        let mut region = Vec::new();
+        let mut queue = VecDeque::from([start]);
+        visited[start] = true;
+        let mut on_border = false;
+        let (mut floor, mut blocked) = (0usize, 0usize);
+        let mut floor_sum = 0.0_f32;
+        while let Some(idx) = queue.pop_front() {
+            region.push(idx);
+            let (row, col) = (idx / width, idx % width);
+            on_border |= row == 0 || col == 0 || row + 1 == height || col + 1 == width;
+            for n in neighbours(idx) {
+                if is_hole(&cells[n].state) {
+                    if !visited[n] {
+                        visited[n] = true;
+                        queue.push_back(n);
+                    }
+                } else if is_floor(&cells[n].state) {
+                    floor += 1;
+                    floor_sum += cells[n].height;
+                } else {
+                    blocked += 1;
+                }
+            }
+        }
+        if on_border
+            || region.len() > max_cells
+            || floor == 0
+            || (floor as f64) < MIN_FLOOR_BOUNDARY * (floor + blocked) as f64
+        {
+            continue;
+        }
+
+        // Start from the boundary mean, then relax towards the harmonic
+        // solution. Once marked filled the region's cells count as floor, so
+        // each cell averages its region and floor neighbours, updated in
+        // place (Gauss-Seidel).
+        let start_height = floor_sum / floor as f32;
+        for &idx in &region {
+            cells[idx].height = start_height;
+            cells[idx].state = GroundFieldCellState::Filled;
+        }
+        for _ in 0..MAX_ITERATIONS {
+            let mut change = 0.0_f32;
+            for &idx in &region {
+                let (mut sum, mut count) = (0.0_f32, 0usize);
+                for n in neighbours(idx) {
+                    if is_floor(&cells[n].state) {
+                        sum += cells[n].height;
+                        count += 1;
+                    }
+                }
+                if count == 0 {
+                    continue;
+                }
+                let step = sum / count as f32 - cells[idx].height;
+                cells[idx].height += RELAXATION * step;
+                change = change.max(step.abs());
+            }
+            if change < TOLERANCE {
+                break;
+            }
+        }
+        inpainted.regions += 1;
+        inpainted.cells += region.len();
+    }
+    inpainted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(height: f32, state: GroundFieldCellState) -> GroundFieldCell {
+        GroundFieldCell {
+            height,
+            confidence: 1.0,
+            variance: 0.0,
+            normal_alignment: 1.0,
+            obstacle_score: 0.0,
+            primary_layer_height: height,
+            layer_count: 1,
+            primary_layer: 0,
+            peak_density: 1.0,
+            surface_confidence: 1.0,
+            signed_distance: 0.0,
+            gradient: [0.0, 0.0],
+            component_id: -1,
+            state,
+        }
+    }
+
+    /// A 10 x 10 ramp rising 0.1 m per column with a void block at rows and
+    /// columns `3..7`.
+    fn ramp_with_hole() -> Vec<GroundFieldCell> {
+        (0..100)
+            .map(|idx| {
+                let (row, col) = (idx / 10, idx % 10);
+                if (3..7).contains(&row) && (3..7).contains(&col) {
+                    cell(f32::NAN, GroundFieldCellState::Void)
+                } else {
+                    cell(col as f32 * 0.1, GroundFieldCellState::Walkable)
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn hole_in_a_ramp_follows_the_ramp() {
+        let mut cells = ramp_with_hole();
+        // 16 cells of 0.25 m: 1 m^2.
+        let inpainted = inpaint_holes(&mut cells, 10, 10, 0.25, 1.0);
+        assert_eq!(
+            inpainted,
+            Inpainted {
+                regions: 1,
+                cells: 16
+            }
+        );
+        for (idx, c) in cells.iter().enumerate() {
+            assert_eq!(matches!(c.state, GroundFieldCellState::Filled), {
+                let (row, col) = (idx / 10, idx % 10);
+                (3..7).contains(&row) && (3..7).contains(&col)
+            });
+            assert!((c.height - (idx % 10) as f32 * 0.1).abs() < 1e-3, "{}", idx);
+        }
+    }
+
+    #[test]
+    fn large_or_open_holes_stay_holes() {
+        let mut cells = ramp_with_hole();
+        assert_eq!(
+            inpaint_holes(&mut cells, 10, 10, 0.25, 0.9),
+            Inpainted::default()
+        );
+
+        // A void column reaching the border is outside the scan, not a hole.
+        let mut cells = ramp_with_hole();
+        for row in 0..10 {
+            cells[row * 10 + 5].state = GroundFieldCellState::Void;
+        }
+        assert_eq!(
+            inpaint_holes(&mut cells, 10, 10, 0.25, 10.0),
+            Inpainted::default()
+        );
+
+        // Mostly walled in by obstacles.
+        let mut cells = ramp_with_hole();
+        for (idx, c) in cells.iter_mut().enumerate() {
+            let (row, col) = (idx / 10, idx % 10);
+            if matches!(c.state, GroundFieldCellState::Walkable) && (2..8).contains(&row) && col < 7
+            {
+                c.state = GroundFieldCellState::Obstacle;
+            }
+        }
+        assert_eq!(
+            inpaint_holes(&mut cells, 10, 10, 0.25, 10.0),
+            Inpainted::default()
+        );
+    }
+}
@@ -0,0 +1,166 @@
+//! Machine-readable journal of the automatic decisions a bake makes.
+//!
+//! The pipeline picks floor planes, clamps out-of-range parameters, falls back
+//! to degraded stages and drops components on its own, and until now the only
+//! trace was prose on the console. Each entry point starts a fresh journal
+//! with [`begin`]; stages append entries as they decide, and `bake_journal()`
+//! returns the last bake's entries as plain JSON for a support ticket.
+//! The bake's progress [`Event`]s are kept alongside the entries. Each
+//! context handle keeps its own journal (see [`crate::context`]).
+
+use serde::Serialize;
+
+use crate::context;
+use crate::events::Event;
+
+/// What kind of automatic decision an entry records.
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JournalKind {
+    /// A choice between alternatives (floor plane source, selected component).
+    Decision,
+    /// A parameter was outside its supported range and was adjusted.
+    Clamped,
+    /// A stage fell back to a weaker strategy or was skipped.
+    Degraded,
+    /// Input points, cells or components were discarded.
+    Dropped,
+}
+
+#[derive(Clone, Serialize)]
+pub struct JournalEntry {
+    /// Position in the bake, from 0.
+    pub seq: usize,
+    pub stage: &'static str,
+    pub kind: JournalKind,
+    pub message: String,
+    /// Stage-specific values (counts, requested / used parameters, reasons).
+    pub data: serde_json::Value,
+}
+
+#[derive(Clone, Default, Serialize)]
+pub struct Journal {
+    /// Entry point that started the bake (`""` before the first one).
+    pub entry_point: &'static str,
+    pub entries: Vec<JournalEntry>,
+    /// Progress events, in the order they were emitted.
+    pub events: Vec<Event>,
+}
+
+/// Clear the journal for a new bake started by `entry_point`.
+pub fn begin(entry_point: &'static str) {
+    *context::current().journal.borrow_mut() = Journal {
+        entry_point,
+        entries: Vec::new(),
+        events: Vec::new(),
+    };
+}
+
+/// Append an entry to the current bake's journal.
+pub fn record(stage: &'static str, kind: JournalKind, message: String, data: serde_json::Value) {
+    let context = context::current();
+    let mut journal = context.journal.borrow_mut();
+    let seq = journal.entries.len();
+    journal.entries.push(JournalEntry {
+        seq,
+        stage,
+        kind,
+        message,
+        data,
+    });
+}
+
+/// Record a clamp of `parameter` from `requested` to `used`; no-op when they
+/// are equal.
+pub fn clamped(stage: &'static str, parameter: &str, requested: f64, used: f64) {
+    if requested == used {
+        return;
+    }
+    record(
+        stage,
+        JournalKind::Clamped,
+        format!("{} {} adjusted to {}", parameter, requested, used),
+        serde_json::json!({
+            "parameter": parameter,
+            "requested": requested,
+            "used": used,
+        }),
+    );
+}
+
+/// Append a progress event; see [`crate::events::emit`].
+pub fn event(event: Event) {
+    context::current().journal.borrow_mut().events.push(event);
+}
+
+/// How far the journal had grown, for a later [`rewind`].
+#[derive(Clone, Copy)]
+pub struct Position {
+    entries: usize,
+    events: usize,
+}
+
+pub fn position() -> Position {
+    let context = context::current();
+    let journal = context.journal.borrow();
+    Position {
+        entries: journal.entries.len(),
+        events: journal.events.len(),
+    }
+}
+
+/// Drop the entries and events recorded after `position`, for trial runs
+/// whose decisions are not the bake's.
+pub fn rewind(position: Position) {
+    let context = context::current();
+    let mut journal = context.journal.borrow_mut();
+    journal.entries.truncate(position.entries);
+    journal.events.truncate(position.events);
+}
+
+/// Empty the journal, for `reset_module`. Returns false when a trapped call
+/// still holds it.
+pub fn try_clear() -> bool {
+    match context::current().journal.try_borrow_mut() {
+        Ok(mut journal) => {
+            *journal = Journal::default();
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+/// The journal of the most recent bake under the current context.
+pub fn snapshot() -> Journal {
+    context::current().journal.borrow().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn begin_resets_and_entries_are_sequenced() {
+        begin("first");
+        record(
+            "ingest",
+            JournalKind::Dropped,
+            "x".to_string(),
+            serde_json::Value::Null,
+        );
+        begin("second");
+        clamped("ground_field", "sdf_cell_size", 0.01, 0.03);
+        clamped("ground_field", "sdf_cell_size", 0.1, 0.1);
+        record(
+            "ground_plane",
+            JournalKind::Decision,
+            "y".to_string(),
+            serde_json::Value::Null,
+        );
+        let journal = snapshot();
+        assert_eq!(journal.entry_point, "second");
+        assert_eq!(journal.entries.len(), 2);
+        assert_eq!(journal.entries[1].seq, 1);
+        assert_eq!(journal.entries[0].data["used"], 0.03);
+    }
+}
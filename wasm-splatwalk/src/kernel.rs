@@ -0,0 +1,127 @@
+//! Splat-to-grid falloff kernel shared by the ground field and the collision
+//! voxelizer.
+//!
+//! Both grids spread each splat's opacity over nearby cells. The support radius
+//! follows the splat's mean gaussian scale, clamped between a minimum and
+//! `max_radius_cells` cells; the weight inside the support comes from the kernel
+//! type. Dense indoor scans want tight kernels (`nearest`, or a small radius) so
+//! thin walls stay thin, while sparse drone scans need broad ones to close gaps.
+
+use crate::MeshSettings;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KernelType {
+    /// `exp(-d^2 / (2 (sigma * r)^2))` inside the support (the legacy kernel).
+    Gaussian,
+    /// `1 - d / r`: a cone that reaches zero at the support edge.
+    Linear,
+    /// Only the cell containing the splat centre, at full weight.
+    Nearest,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SplatKernel {
+    pub kind: KernelType,
+    /// Gaussian sigma as a fraction of the support radius.
+    pub sigma: f64,
+    /// Support radius cap, in cells.
+    pub max_radius_cells: f64,
+}
+
+impl SplatKernel {
+    /// Kernel from `kernel_type` / `kernel_sigma` / `kernel_max_radius_cells`,
+    /// falling back to the grid's legacy `default_max_cells`. Unknown kernel
+    /// names are rejected by `validate`, so this only sees valid ones.
+    pub fn from_settings(settings: &MeshSettings, default_max_cells: f64) -> Self {
+        let kind = match settings.kernel_type.as_deref() {
+            Some("linear") => KernelType::Linear,
+            Some("nearest") => KernelType::Nearest,
+            _ => KernelType::Gaussian,
+        };
+        Self {
+            kind,
+            sigma: settings
+                .kernel_sigma
+                .filter(|v| v.is_finite() && *v > 0.0)
+                .unwrap_or(1.0)
+                .clamp(0.05, 4.0),
+            max_radius_cells: settings
+                .kernel_max_radius_cells
+                .filter(|v| v.is_finite() && *v > 0.0)
+                .unwrap_or(default_max_cells)
+                .clamp(0.5, 16.0),
+        }
+    }
+
+    pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+        match settings.kernel_type.as_deref() {
+            None | Some("gaussian") | Some("linear") | Some("nearest") => Ok(()),
+            Some(other) => Err(format!(
+                "Invalid kernel_type: {}. Expected \"gaussian\", \"linear\", or \"nearest\".",
+                other
+            )),
+        }
+    }
+
+    /// Support radius for a splat whose scale-derived radius is `wanted`, on a
+    /// grid of `cell` spacing with a floor of `min_cells` cells. `nearest` has
+    /// no spatial support beyond its own cell.
+    pub fn radius(&self, wanted: f64, cell: f64, min_cells: f64) -> f64 {
+        if self.kind == KernelType::Nearest {
+            return 0.0;
+        }
+        let max_cells = self.max_radius_cells.max(min_cells);
+        wanted.max(cell * min_cells).min(cell * max_cells)
+    }
+
+    /// Weight at squared distance `dist_sq` from the splat centre, for a support
+    /// of `radius`. Zero outside the support; `nearest` callers only visit the
+    /// splat's own cell, which always gets full weight.
+    pub fn weight(&self, dist_sq: f64, radius: f64) -> f64 {
+        if self.kind == KernelType::Nearest {
+            return 1.0;
+        }
+        if dist_sq > radius * radius {
+            return 0.0;
+        }
+        match self.kind {
+            KernelType::Gaussian => {
+                let s = self.sigma * radius;
+                (-dist_sq / (2.0 * s * s)).exp()
+            }
+            KernelType::Linear => 1.0 - dist_sq.sqrt() / radius,
+            KernelType::Nearest => 1.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kernels_shape_falloff() {
+        let gaussian = SplatKernel {
+            kind: KernelType::Gaussian,
+            sigma: 1.0,
+            max_radius_cells: 4.0,
+        };
+        // Legacy falloff: exp(-1/2) one radius out.
+        assert!((gaussian.weight(1.0, 1.0) - (-0.5_f64).exp()).abs() < 1e-12);
+        assert_eq!(gaussian.radius(10.0, 0.5, 0.5), 2.0);
+
+        let linear = SplatKernel {
+            kind: KernelType::Linear,
+            ..gaussian
+        };
+        assert!((linear.weight(0.25, 1.0) - 0.5).abs() < 1e-12);
+        assert_eq!(linear.weight(1.21, 1.0), 0.0);
+
+        let nearest = SplatKernel {
+            kind: KernelType::Nearest,
+            ..gaussian
+        };
+        assert_eq!(nearest.radius(10.0, 0.5, 0.5), 0.0);
+        assert_eq!(nearest.weight(0.02, 0.0), 1.0);
+    }
+}
@@ -1,26 +1,121 @@
 use serde::{Deserialize, Serialize};
-use std::cell::RefCell;
 use wasm_bindgen::prelude::*;
 
+mod artifact;
+mod async_mesh;
+mod babylon;
+mod bpa;
+mod cad_export;
+mod chokepoint;
+mod cloud;
+mod cluster;
+mod context;
+mod detour;
+mod deviation;
+mod draco;
+mod dual_contour;
+mod ellipsoid;
+mod epsilon;
+mod error;
+mod events;
+mod face_filter;
+mod floor_edit;
+mod floor_layers;
+mod floor_plan;
+mod floor_polygons;
+mod flow_field;
+mod format_report;
+mod frame;
+mod geodesic;
 mod glb;
+mod hazard;
+mod heightmap;
+mod hull;
+mod inpaint;
+mod journal;
+mod kernel;
+mod manifest;
+mod memory;
 mod mesh;
+mod nav_query;
+mod navmesh;
+mod normals;
+mod obj;
+mod occupancy;
+mod offmesh;
 mod output_space;
+mod parallel;
+mod path_cost;
+mod ply_export;
+mod postprocess;
+mod preprocess;
+mod preview;
+mod project;
+mod quality_report;
+mod ransac;
+mod reachability;
+mod region;
+mod resolution;
+mod safe_area;
+mod sensitivity;
+mod session;
 mod slice;
 mod sog;
 mod splat;
-
+mod stage;
+mod streaming;
+mod surface_nets;
+mod terrain;
+mod thread_support;
+mod thumbnail;
+mod tiling;
+mod tsdf;
+mod typed_mesh;
+mod uv;
+mod vertex_color;
+mod void_plane;
+mod walk_sim;
+mod walkability;
+mod wall_extrude;
+
+pub use cloud::SplatCloud;
+pub use context::SplatwalkContext;
+pub use error::SplatwalkError;
+use events::Event;
+pub use floor_edit::FloorEditor;
+pub use nav_query::NavMeshQuery;
 use output_space::OutputSpaceSettings;
+use postprocess::PostProcessStep;
+use region::RegionRuleSettings;
+pub use session::SplatSession;
+use typed_mesh::TypedMesh;
 
+#[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 extern "C" {
     #[wasm_bindgen(js_namespace = console)]
     fn log(s: &str);
 }
 
+/// Native test builds have no console to log to.
+#[cfg(not(target_arch = "wasm32"))]
+fn log(_s: &str) {}
+
 #[wasm_bindgen]
 pub fn init_splatwalk() -> String {
     console_error_panic_hook::set_once();
-    log(&format!("💩🚶 SplatWalk v{} (api {})", core_semver(), API_VERSION));
+    let support = thread_support::probe();
+    log(&format!(
+        "💩🚶 SplatWalk v{} (api {}, {}; threads supported: {} x{}{})",
+        core_semver(),
+        API_VERSION,
+        support.execution,
+        support.supported_mode,
+        support.usable_threads,
+        support
+            .reason
+            .map_or_else(String::new, |reason| format!(": {}", reason))
+    ));
     "Ready".to_string()
 }
 
@@ -46,6 +141,99 @@ pub const CAPABILITIES: &[&str] = &[
     "recast_config",
     "progress_callback",
     "splat_ingest",
+    "crop_transform",
+    "region_rules",
+    "splat_session",
+    "splat_backprojection",
+    "ply_passthrough",
+    "format_report",
+    "splat_kernel",
+    "boundary_feathering",
+    "relief_scale",
+    "void_plane",
+    "memory_jobs",
+    "ksplat_ingest",
+    "ground_candidates",
+    "quality_report",
+    "normal_clusters",
+    "compressed_ply_ingest",
+    "confidence_buffers",
+    "structured_errors",
+    "floor_plan",
+    "typed_buffers",
+    "cad_export",
+    "tsdf_mesh",
+    "multi_plane",
+    "face_cells",
+    "floor_editor",
+    "glb_normals",
+    "floor_brush",
+    "path_costs",
+    "project_state",
+    "voxel_downsample",
+    "vertex_colors",
+    "bake_journal",
+    "partial_results",
+    "decimation",
+    "reset_module",
+    "navmesh_smoothing",
+    "epsilon_policy",
+    "bake_seed",
+    "walkability_texture",
+    "splat_cloud",
+    "async_mesh",
+    "safe_area",
+    "reachability",
+    "poly_navmesh",
+    "chokepoints",
+    "agent_height",
+    "distance_field",
+    "flow_field",
+    "max_step_height",
+    "hazard_tags",
+    "floor_layers",
+    "navmesh_query",
+    "face_filter",
+    "path_smoothing",
+    "post_process",
+    "field_preview",
+    "navmesh_raycast",
+    "bake_manifest",
+    "tiled_navmesh",
+    "parameter_sensitivity",
+    "detour_navmesh",
+    "event_catalog",
+    "off_mesh_links",
+    "coordinate_system",
+    "thumbnail",
+    "terrain_mesh",
+    "deviation_overlay",
+    "ball_pivoting",
+    "context_handles",
+    "streaming_tiles",
+    "convex_hull",
+    "splat_ellipsoids",
+    "doubled_surfaces",
+    "dual_contouring",
+    "surface_nets",
+    "floor_inpainting",
+    "grid_resolution",
+    "vertex_normals",
+    "ground_frame",
+    "thread_support",
+    "uv_generation",
+    "spz_native_decode",
+    "obj_export",
+    "agent_walk_test",
+    "source_space_mesh",
+    "ply_export",
+    "screen_region",
+    "babylon_export",
+    "artifact_container",
+    "heightmap_export",
+    "occupancy_grid",
+    "floor_polygons",
+    "wall_extrusion",
 ];
 
 /// Semantic version of the WASM core build. Tracks `Cargo.toml`'s `version` so a
@@ -56,7 +244,16 @@ pub fn core_semver() -> String {
 
 /// Capability flags as owned strings, ready to serialize into a result.
 pub fn capabilities() -> Vec<String> {
-    CAPABILITIES.iter().map(|s| s.to_string()).collect()
+    let mut capabilities: Vec<String> = CAPABILITIES.iter().map(|s| s.to_string()).collect();
+    // Built only with the `draco` cargo feature.
+    if cfg!(feature = "draco") {
+        capabilities.push("draco_export".to_string());
+    }
+    // Built only with the `threads` cargo feature.
+    if cfg!(feature = "threads") {
+        capabilities.push("threaded_bakes".to_string());
+    }
+    capabilities
 }
 
 /// JS-reachable semantic version of the core build. Lets an integrator do cheap
@@ -80,20 +277,38 @@ pub fn splatwalk_capabilities() -> Result<JsValue, JsValue> {
     Ok(serde_wasm_bindgen::to_value(&capabilities())?)
 }
 
-thread_local! {
-    static PROGRESS_CALLBACK: RefCell<Option<js_sys::Function>> = RefCell::new(None);
+/// JS-reachable thread support detected by `init_splatwalk`: whether the
+/// build and the page allow threads, how many, the performance class, the
+/// reason they don't, and whether bakes run on a pool yet.
+#[wasm_bindgen]
+pub fn splatwalk_thread_support() -> Result<JsValue, JsValue> {
+    Ok(serde_wasm_bindgen::to_value(&thread_support::current())?)
 }
 
+/// Start the bake thread pool when `splatwalk_thread_support` allows threads.
+/// The promise settles with the updated report once the pool is up, or at
+/// once, still single-threaded, when it does not. Await it after
+/// `init_splatwalk` and before the first bake.
+#[wasm_bindgen]
+pub fn splatwalk_start_threads() -> js_sys::Promise {
+    thread_support::start()
+}
+
+/// `wasm-bindgen-rayon`'s `initThreadPool`, which its worker script expects
+/// the module to export. Prefer `splatwalk_start_threads`.
+#[cfg(all(feature = "threads", target_arch = "wasm32"))]
+pub use wasm_bindgen_rayon::init_thread_pool;
+
 /// Register (or, with `None`/`undefined`, clear) an opt-in JS progress callback.
 /// It is invoked as `callback(stage: string, fraction: number | undefined)` at
 /// the same stage boundaries as the `@progress` line protocol. The line protocol
 /// is still emitted as a fallback, so `progress_protocol_v1` consumers keep
 /// working and integrators no longer need to monkey-patch the global console.
+/// The callback belongs to the current context handle (the default one outside
+/// `SplatwalkContext.run`).
 #[wasm_bindgen]
 pub fn set_progress_callback(callback: Option<js_sys::Function>) {
-    PROGRESS_CALLBACK.with(|cb| {
-        *cb.borrow_mut() = callback;
-    });
+    *context::current().progress.borrow_mut() = callback;
 }
 
 /// Emit a progress event to the registered JS callback (if any) AND to the
@@ -101,16 +316,17 @@ pub fn set_progress_callback(callback: Option<js_sys::Function>) {
 /// `progress_protocol_v1` fallback). `fraction` is an optional 0..1 completion
 /// ratio for the stage.
 pub(crate) fn emit_progress(stage: &str, fraction: Option<f64>) {
-    PROGRESS_CALLBACK.with(|cb| {
-        if let Some(func) = cb.borrow().as_ref() {
-            let stage_val = JsValue::from_str(stage);
-            let frac_val = match fraction {
-                Some(f) => JsValue::from_f64(f),
-                None => JsValue::UNDEFINED,
-            };
-            let _ = func.call2(&JsValue::NULL, &stage_val, &frac_val);
-        }
-    });
+    // Cloned out, so a callback that re-registers itself doesn't find it
+    // borrowed.
+    let callback = context::current().progress.borrow().clone();
+    if let Some(func) = callback {
+        let stage_val = JsValue::from_str(stage);
+        let frac_val = match fraction {
+            Some(f) => JsValue::from_f64(f),
+            None => JsValue::UNDEFINED,
+        };
+        let _ = func.call2(&JsValue::NULL, &stage_val, &frac_val);
+    }
     match fraction {
         Some(f) => log(&format!("@progress {} {:.4}", stage, f)),
         None => log(&format!("@progress {}", stage)),
@@ -175,6 +391,10 @@ struct RecastConfigInput {
     walkable_climb: Option<f64>,
     walkable_radius: Option<f64>,
     walkable_slope_angle: Option<f64>,
+    /// Vertical relief factor the mesh was baked with (`relief_scale`). Slope and
+    /// climb limits are rescaled so Recast rejects the same faces it would on
+    /// the measured geometry.
+    relief_scale: Option<f64>,
     /// Optional highest floor-cell Y (metres) so the helper can suggest a padded
     /// `bmax.y` that keeps the open-sky floor sheet above Recast's low-height cull.
     max_floor_y: Option<f64>,
@@ -213,7 +433,8 @@ pub fn recast_config(settings: JsValue) -> Result<JsValue, JsValue> {
     let input: RecastConfigInput = if settings.is_undefined() || settings.is_null() {
         RecastConfigInput::default()
     } else {
-        serde_wasm_bindgen::from_value(settings).map_err(|e| JsValue::from_str(&e.to_string()))?
+        serde_wasm_bindgen::from_value(settings)
+            .map_err(|e| SplatwalkError::InvalidSettings(e.to_string()))?
     };
 
     let cs = input.cs.unwrap_or(0.12);
@@ -222,6 +443,22 @@ pub fn recast_config(settings: JsValue) -> Result<JsValue, JsValue> {
     let walkable_climb_m = input.walkable_climb.unwrap_or(0.5);
     let walkable_radius_m = input.walkable_radius.unwrap_or(0.5);
     let walkable_slope_angle = input.walkable_slope_angle.unwrap_or(40.0);
+    let relief = input
+        .relief_scale
+        .filter(|s| s.is_finite() && *s >= 0.0)
+        .unwrap_or(1.0)
+        .min(10.0);
+    // A slope of angle a becomes atan(k * tan a) once heights are scaled by k.
+    // A fully flattened mesh has no slopes left, and Recast's strict
+    // `normal.y > cos(angle)` test would reject flat faces at 0 degrees.
+    let walkable_slope_angle = if relief > 0.0 {
+        (relief * walkable_slope_angle.to_radians().tan())
+            .atan()
+            .to_degrees()
+    } else {
+        walkable_slope_angle
+    };
+    let walkable_climb_m = walkable_climb_m * relief;
 
     let walkable_height = ((walkable_height_m / ch).ceil() as i64).max(1);
     let walkable_climb = ((walkable_climb_m / ch).floor() as i64).max(0);
@@ -245,12 +482,28 @@ pub fn recast_config(settings: JsValue) -> Result<JsValue, JsValue> {
 pub struct MeshSettings {
     pub mode: u8,
     pub voxel_target: Option<f64>,
+    /// Cells wanted along the footprint's first (X / tangent) and second
+    /// (Z / bitangent) axis instead of the `voxel_target` area heuristic.
+    /// Cells stay square; the finer edge wins when both are set.
+    pub voxel_target_u: Option<f64>,
+    pub voxel_target_v: Option<f64>,
+    /// Fixed grid resolution for footprint-sized grids; beats every
+    /// `voxel_target*`.
+    pub cells_per_meter: Option<f64>,
     pub sdf_cell_size: Option<f64>,
     pub sdf_vertical_cell_size: Option<f64>,
     pub sdf_density_threshold: Option<f64>,
     pub sdf_max_layers: Option<usize>,
     pub sdf_smoothing_radius: Option<usize>,
     pub sdf_influence_radius_scale: Option<f64>,
+    /// Splat-to-grid falloff for the ground field and collision voxelizer:
+    /// `"gaussian"` (default), `"linear"`, or `"nearest"` (own cell only).
+    pub kernel_type: Option<String>,
+    /// Gaussian sigma as a fraction of the splat's support radius (default 1).
+    pub kernel_sigma: Option<f64>,
+    /// Support radius cap in cells (default 4 for the ground field, 6 for
+    /// collision voxels).
+    pub kernel_max_radius_cells: Option<f64>,
     pub collision_voxel_size: Option<f64>,
     pub collision_opacity_threshold: Option<f64>,
     pub collision_scene_type: Option<String>,
@@ -259,13 +512,263 @@ pub struct MeshSettings {
     pub collision_carve_height: Option<f64>,
     pub collision_carve_radius: Option<f64>,
     pub collision_mesh_mode: Option<String>,
+    /// Mode 3 TSDF voxel edge in metres (default: from `voxel_target`).
+    pub tsdf_voxel_size: Option<f64>,
+    /// Mode 3 truncation band half-width in voxels (default 3).
+    pub tsdf_truncation_cells: Option<f64>,
+    /// Mode 5 candidate cell edge in metres (default: from `voxel_target`).
+    pub terrain_cell_size: Option<f64>,
+    /// Mode 5 stops inserting vertices once every candidate height is within
+    /// this many metres of the surface (default 0.05).
+    pub terrain_max_error: Option<f64>,
+    /// Mode 5 vertex budget (default 20000).
+    pub terrain_max_vertices: Option<usize>,
+    /// Mode 5 keeps splats whose normal is within this many degrees of the
+    /// ground normal (default 60).
+    pub terrain_max_slope: Option<f64>,
+    /// Mode 6 ball radii in metres, run smallest first (default 1.5 and 3
+    /// times the mean splat spacing).
+    pub bpa_radii: Option<Vec<f64>>,
+    /// Mode 7 stops adding hull vertices at this many (default: no limit).
+    pub hull_max_vertices: Option<usize>,
+    /// Mode 8 keeps a crease where planes in a cell meet at more than this
+    /// many degrees (default 30).
+    pub dc_feature_angle: Option<f64>,
+    /// Mode 9 relaxation passes over the surface net, at most 16 (default 2).
+    pub surface_nets_smoothing: Option<usize>,
+    /// `convert_splat_to_mesh` voxel downsampling: after region filtering,
+    /// keep one averaged point per cube of this edge (metres). Unset = off.
+    pub downsample_voxel_size: Option<f64>,
+    /// When true, `convert_splat_to_mesh` also returns `mesh.colors`: per-vertex
+    /// RGB sampled from the base color of nearby splats.
+    pub emit_colors: Option<bool>,
+    /// Splat color sampling radius in metres (default: twice the mean point
+    /// spacing, clamped to `[0.01, 1]`).
+    pub color_sample_radius: Option<f64>,
+    /// When true, `convert_splat_to_mesh` also returns `mesh.uvs`.
+    pub generate_uvs: Option<bool>,
+    /// `generate_uvs` mapping: `"auto"` (default), `"planar"`, `"box"` or
+    /// `"grid"`.
+    pub uv_projection: Option<String>,
+    /// Metres per texture repeat for the planar and box projections
+    /// (default 1).
+    pub uv_scale: Option<f64>,
+    /// When true, `convert_splat_to_mesh` also returns `mesh.vertex_deviation`
+    /// (metres from each vertex to the nearest splat) and
+    /// `mesh.deviation_colors`, the same distances on a green-to-red ramp.
+    pub emit_deviation: Option<bool>,
+    /// Deviation drawn fully red, in metres (default 0.1).
+    pub deviation_range: Option<f64>,
+    /// `convert_splat_to_mesh` quadric edge-collapse decimation: reduce the
+    /// mesh to at most this many triangles. Unset = off.
+    pub decimate_target_faces: Option<usize>,
+    /// Stop decimating once a collapse would move the surface by more than
+    /// about this many metres (default: unbounded, only the target counts).
+    pub decimate_max_error: Option<f64>,
+    /// Laplacian smoothing passes over the heights of the voxel navmesh
+    /// (`walkable_floors`), relaxing one-voxel stair steps. Unset = off.
+    pub smooth_iterations: Option<usize>,
+    /// Fraction of the way each pass moves a vertex toward its neighbours'
+    /// mean height, in `(0, 1]` (default 0.5).
+    pub smooth_lambda: Option<f64>,
+    /// Where the base tolerance comes from: `"fixed"` (default, 1 mm) or
+    /// `"scene"` (a fraction of the filtered cloud's bounding-box diagonal).
+    /// Welding, degenerate checks and default RANSAC thresholds scale with it.
+    pub epsilon_policy: Option<String>,
+    /// Explicit base tolerance in metres; overrides `epsilon_policy`.
+    pub epsilon: Option<f64>,
+    /// When true, `convert_splat_to_mesh` merges vertices closer than the
+    /// tolerance and drops the triangles that collapse.
+    pub weld_vertices: Option<bool>,
+    /// `convert_splat_to_mesh`: ordered post-process steps (`weld`, `smooth`,
+    /// `decimate`, `skirt`, `clip`, `transfer_colors`) run on the output mesh
+    /// after decimation. Unset = off.
+    pub post_process: Option<Vec<PostProcessStep>>,
+    /// `convert_splat_to_mesh`: start position `[x, y, z]`. Only the
+    /// walkable faces reachable from it stay in `mesh`; the rest come back as
+    /// `unreachable`. Unset = off.
+    pub reachable_from: Option<Vec<f64>>,
+    /// Tallest step the reachability flood and the polygon navmesh climb
+    /// between floors, in metres (default 0.5, Recast's `walkableClimb`).
+    pub agent_max_climb: Option<f64>,
+    /// Steepest face the reachability flood walks on, in degrees from
+    /// horizontal (default 40, Recast's `walkableSlopeAngle`).
+    pub agent_max_slope: Option<f64>,
+    /// Mode 2 `walkable_floors`: stair treads with a neighbouring floor at
+    /// most this much higher, in metres, are ramped up to meet it, and faces
+    /// rising no more than this count as walkable however steep. Unset = off.
+    pub max_step_height: Option<f64>,
+    /// Mode 2 `walkable_floors`: floor voxels with splat mass less than this
+    /// far above them (tables, low ceilings, archways) are not walkable, in
+    /// metres (Recast's `walkableHeight`). Unset = off.
+    pub agent_height: Option<f64>,
+    /// `convert_splat_to_mesh`: source points `[x, y, z]` (exits, spawn
+    /// points). The result gains `distance_field`, the walking distance from
+    /// the nearest source at every `mesh` vertex. Unset = off.
+    pub distance_sources: Option<Vec<Vec<f64>>>,
+    /// `convert_splat_to_mesh`: goal point `[x, y, z]`. The result gains
+    /// `flow_field`, a walking direction toward it per `mesh` face. Unset =
+    /// off.
+    pub flow_goal: Option<Vec<f64>>,
+    /// `convert_splat_to_mesh`: faces whose centre is below this height
+    /// (water, a pool floor) are flagged in `hazards`. Unset = off.
+    pub hazard_water_height: Option<f64>,
+    /// `convert_splat_to_mesh`: no-go volumes, shaped like `regions` entries
+    /// (`mode` is ignored). Faces whose centre is inside one are flagged in
+    /// `hazards`.
+    pub hazard_volumes: Option<Vec<RegionRuleSettings>>,
+    /// When true, hazard faces are removed from `mesh` instead of only
+    /// flagged (default false).
+    pub hazard_cut: Option<bool>,
+    /// Mode 2 (`walkable_floors`): also return `navmesh`, convex polygons
+    /// with neighbour links built Recast-style from the carved voxels.
+    pub emit_poly_navmesh: Option<bool>,
+    /// Isolated navmesh regions smaller than this are dropped, in square
+    /// metres (default 0.5, Recast's `minRegionArea`).
+    pub navmesh_min_region_area: Option<f64>,
+    /// Navmesh regions smaller than this merge into a neighbour, in square
+    /// metres (default 2, Recast's `mergeRegionArea`).
+    pub navmesh_merge_region_area: Option<f64>,
+    /// Farthest a simplified navmesh wall may stray from the voxel outline,
+    /// in voxels (default 1.3, Recast's `maxSimplificationError`).
+    pub navmesh_max_edge_error: Option<f64>,
+    /// Most corners per navmesh polygon, 3 to 12 (default 6).
+    pub navmesh_max_verts_per_poly: Option<usize>,
+    /// With `emit_poly_navmesh`: also return `detour_navmesh`, the polygon
+    /// navmesh as a Recast/Detour `.bin` (at most 6 corners per polygon).
+    pub emit_detour_navmesh: Option<bool>,
+    /// `convert_splat_to_mesh`: jumps, ladders and drops between two points
+    /// of the walkable mesh. Each end is snapped onto a walkable face within
+    /// `radius`; the result gains `off_mesh_links`, and `detour_navmesh`
+    /// carries them as off-mesh connections. Unset = none.
+    pub off_mesh_links: Option<Vec<offmesh::OffMeshLinkSettings>>,
+    /// Mode 2 (`walkable_floors`): also return `floor_layers`, the carved
+    /// floors split into one surface per storey.
+    pub emit_floor_layers: Option<bool>,
+    /// Floor layers smaller than this are left out, in square metres
+    /// (default 1).
+    pub floor_layer_min_area: Option<f64>,
+    /// Mode 2: also return `occupancy_grid`, the carved grid flattened into
+    /// a ROS `map_server` map (PGM and YAML) of free, occupied and unknown
+    /// cells.
+    pub emit_occupancy_grid: Option<bool>,
+    /// Mode 2: build the navmesh in square tiles this many metres wide and
+    /// return them in `tiles`, for scenes too large for one collision grid.
+    pub tile_size: Option<f64>,
+    /// Overlap each tile is reconstructed with beyond its edges, in metres
+    /// (default 1).
+    pub tile_border: Option<f64>,
     collision_filter_cluster: Option<bool>,
     /// Cap dense collision grid voxels (default 1_500_000). Lower under memory pressure.
     pub collision_max_voxels: Option<usize>,
     pub min_alpha: Option<f64>,
     pub max_scale: Option<f64>,
     pub normal_align: Option<f64>,
+    /// Split splats into floor / wall / clutter groups by normal cone and
+    /// height before the ground-field bake (default false). Floor splats build
+    /// the walkable surface; wall splats mark their columns as obstacles.
+    pub normal_clusters: Option<bool>,
+    /// Floor group: normal within this many degrees of vertical (default 30).
+    pub floor_cone_deg: Option<f64>,
+    /// Wall group: normal within this many degrees of horizontal (default 20).
+    pub wall_cone_deg: Option<f64>,
+    /// Floor group: at most this high above the floor estimate (default 0.3).
+    pub floor_band_height: Option<f64>,
+    /// `build_floor_plan`: plan raster cell size in metres (default 0.05).
+    pub floor_plan_cell_size: Option<f64>,
+    /// `build_floor_plan`: wall splats are taken from this height above the
+    /// floor (default 0.3) ...
+    pub floor_plan_min_height: Option<f64>,
+    /// ... up to this one (default `floor_plan_min_height + 1.7`).
+    pub floor_plan_max_height: Option<f64>,
+    /// `build_floor_plan`: inlier distance of a wall line (default: the cell size).
+    pub wall_line_tolerance: Option<f64>,
+    /// `build_floor_plan`: shortest wall segment kept, in metres (default 0.3).
+    pub wall_min_length: Option<f64>,
+    /// `build_floor_plan`: gap along a line that splits it into two segments
+    /// (default 0.3, so doorways break walls).
+    pub wall_max_gap: Option<f64>,
+    /// `build_floor_plan`: segments within this many degrees of the dominant
+    /// orientation (or its perpendicular) are snapped onto it (default 10; 0
+    /// disables).
+    pub wall_snap_deg: Option<f64>,
+    /// `build_floor_plan`: maximum segments fitted (default 64).
+    pub wall_max_segments: Option<usize>,
+    /// `build_floor_plan`: also return an IFC4 file of the walls and floor slab.
+    pub emit_ifc: Option<bool>,
+    /// IFC and mode 10 wall extrusion height (default: mode 10 the detected
+    /// ceiling, then the top of the wall cluster above the floor, else 2.5).
+    pub wall_height: Option<f64>,
+    /// IFC and mode 10 wall thickness in metres (default 0.1).
+    pub wall_thickness: Option<f64>,
+    /// `build_safe_area`: standing point `[x, y, z]` to grow the area from
+    /// (default: the floor cell farthest from any obstacle).
+    pub safe_area_center: Option<Vec<f64>>,
+    /// `build_safe_area`: distance kept from every obstacle, wall and floor
+    /// edge, in metres (default 0.3).
+    pub safe_area_clearance: Option<f64>,
+    /// `build_safe_area`: headroom required above the floor; splats below it
+    /// (table tops, shelves, low ceilings) block the cell (default 2.2).
+    pub safe_area_ceiling_height: Option<f64>,
+    /// `build_safe_area`: farthest the boundary reaches from the standing
+    /// point, in metres (default 4).
+    pub safe_area_max_radius: Option<f64>,
+    /// `build_safe_area`: `"convex"` (default) or `"star"` (star-shaped about
+    /// the standing point; larger, but may be concave).
+    pub safe_area_shape: Option<String>,
+    /// `build_safe_area`: rays cast from the standing point (default 72,
+    /// clamped to 8..=720).
+    pub safe_area_rays: Option<usize>,
+    /// `find_chokepoints`: passages narrower than this are closed, in metres
+    /// (default 0.4).
+    pub chokepoint_min_width: Option<f64>,
+    /// `find_chokepoints`: floor at least this wide is open, and narrower
+    /// passages between two such areas are chokepoints (default 1.5).
+    pub chokepoint_max_width: Option<f64>,
+    /// `extract_floor_polygons`: farthest a simplified outline may stray
+    /// from the walkable cells, in metres (default: one cell).
+    pub floor_polygon_tolerance: Option<f64>,
+    /// `extract_floor_polygons`: islands and holes smaller than this are
+    /// dropped, in square metres (default 0.25).
+    pub floor_polygon_min_area: Option<f64>,
+    /// `build_splat_ellipsoids`: ellipsoids returned (default 2000, at most
+    /// 100000).
+    pub ellipsoid_count: Option<usize>,
+    /// `build_splat_ellipsoids`: `"largest"` (default, by volume) or
+    /// `"opaque"` splats first.
+    pub ellipsoid_rank: Option<String>,
+    /// `build_splat_ellipsoids`: `"all"` (default), `"kept"` or `"discarded"`
+    /// splats.
+    pub ellipsoid_filter: Option<String>,
+    /// `build_splat_ellipsoids`: also merge the ellipsoids into one mesh.
+    pub ellipsoid_mesh: Option<bool>,
+    /// Ground-field floor RANSAC inlier distance (default 0.1 m, scaled by the
+    /// epsilon policy).
     pub ransac_thresh: Option<f64>,
+    /// Points each RANSAC plane hypothesis is scored on before the best few are
+    /// verified against the full cloud (default 10000; 0 scores every hypothesis
+    /// on the full cloud).
+    pub ransac_sample_size: Option<usize>,
+    /// Distinct ground planes reported in `ground_candidates` (default 4, 1..=8).
+    pub ground_candidate_count: Option<usize>,
+    /// Bake against this entry of a previous call's `ground_candidates` instead
+    /// of the best-scoring plane. Only meaningful with the same cloud, region
+    /// and RANSAC settings; out-of-range indices fall back to the best plane.
+    pub chosen_ground_index: Option<usize>,
+    /// Older RANSAC-only seed, used when `seed` is unset.
+    pub ransac_seed: Option<u32>,
+    /// Seed for every stochastic step (RANSAC floor, plane and wall fits).
+    /// The same file and settings give the same mesh for a fixed seed; unset
+    /// uses a fixed default, so bakes are reproducible either way.
+    pub seed: Option<u64>,
+    /// Mode 4: most planes to extract (default 8, 1..=32).
+    pub plane_count: Option<usize>,
+    /// Mode 4: inlier distance in metres (default 0.2 scaled by the epsilon
+    /// policy, as mode 1).
+    pub plane_threshold: Option<f64>,
+    /// Mode 4: stop once the best remaining plane has fewer inliers (default
+    /// 0.5% of the filtered points, at least 50).
+    pub plane_min_inliers: Option<usize>,
     pub floor_projection_epsilon: Option<f64>,
     pub height_projection_epsilon: Option<f64>,
     pub obstacle_height_epsilon: Option<f64>,
@@ -274,13 +777,27 @@ pub struct MeshSettings {
     pub max_local_height_variance: Option<f64>,
     pub min_floor_confidence: Option<f64>,
     pub hole_fill_radius: Option<usize>,
+    /// Inpaint enclosed floor holes of up to this many square metres with
+    /// heights diffused from the surrounding floor. Unset = off.
+    pub fill_holes_max_area: Option<f64>,
     pub agent_radius_erode: Option<f64>,
     pub component_mode: Option<String>,
     pub region_min: Option<Vec<f64>>,
     pub region_max: Option<Vec<f64>>,
+    /// Oriented crop box as a 16-value column-major world-from-box matrix (the
+    /// crop gizmo's transform, in `splatwalk_oriented` space). Requires
+    /// `half_extents` (one without the other is rejected); the box takes
+    /// precedence over `region_min` / `region_max`. See `region::CropBox`.
+    pub crop_transform: Option<Vec<f64>>,
+    /// Half extents of the crop box along its local X/Y/Z axes.
+    pub half_extents: Option<Vec<f64>>,
+    /// Ordered include/exclude rules (boxes, spheres, XZ polygons, screen
+    /// frustums) applied after the base region. The last matching rule decides; see `region::RegionFilter`.
+    pub regions: Option<Vec<RegionRuleSettings>>,
     /// Statistical outlier removal ("prune floaters"). When true (the default),
     /// stray sparse splats far from the dense surface are removed before any
-    /// geometry/region/seed computation. See `splat::prune_floaters`.
+    /// geometry/region/seed computation, so every reconstruction mode sees the
+    /// cleaned cloud. See `splat::prune_floaters`.
     pub prune_floaters: Option<bool>,
     /// Neighbours sampled per splat for outlier removal (default 16). Higher =
     /// smoother/more conservative estimate.
@@ -296,18 +813,116 @@ pub struct MeshSettings {
     /// in `splatwalk_oriented` space, byte-for-byte unchanged. Per-cell ground-field
     /// scalars and `diagnostics` stay in `splatwalk_oriented` space.
     pub output_space: Option<OutputSpaceSettings>,
+    /// Host convention preset instead of `output_space`: `"babylon-lh"`,
+    /// `"threejs-rh"`, `"gltf"` or `"z-up"`. It picks the output axes and
+    /// winding, the `flip_y` default, and the space `region_*`,
+    /// `crop_transform` and `regions` are compared in. See
+    /// `output_space::transform_for`.
+    pub coordinate_system: Option<String>,
     /// When true, negate the Y axis of every parsed splat (position and normal) so that
     /// WASM operates in the same world space the renderer displays. Gaussian-splat loaders
     /// (e.g. Babylon) flip Y on import; passing that flip here keeps the navmesh, basis,
     /// spawn points and agents co-located with the rendered splat and makes the +Y-up
-    /// floor/clearance heuristics valid.
+    /// floor/clearance heuristics valid. Unset, it follows `coordinate_system`.
     pub flip_y: Option<bool>,
+    /// When true, `build_room_floor_mesh` also returns a per-splat
+    /// `backprojection` recording which splats landed on the emitted floor.
+    pub emit_backprojection: Option<bool>,
+    /// When true, `build_room_floor_mesh` also returns `mesh.vertex_alpha`: a
+    /// per-vertex alpha that fades the floor out towards its boundary, faster
+    /// where splat coverage is thin. Render-proxy only; positions are unchanged.
+    pub feather_boundary: Option<bool>,
+    /// Width of the feathered band in field cells (default 3).
+    pub feather_width_cells: Option<f64>,
+    /// When true, `build_room_floor_mesh` also returns
+    /// `mesh.vertex_confidence`: per-vertex surface confidence from the
+    /// accumulated splat weights, histogram-equalized to `[0, 1]` for heat maps.
+    pub emit_confidence: Option<bool>,
+    /// When true, `build_room_floor_mesh` also returns `walkability_texture`
+    /// (one grayscale texel of splat coverage per floor-field cell) and
+    /// `mesh.uvs` into it.
+    pub emit_walkability_texture: Option<bool>,
+    /// When true, `build_room_floor_mesh` also returns `face_cells`: the
+    /// source ground-field cell (row, col, layer) of every floor triangle.
+    pub emit_face_cells: Option<bool>,
+    /// When true, `build_room_floor_mesh` also returns `heightfield`, the floor
+    /// cell heights to open in a `FloorEditor`.
+    pub emit_heightfield: Option<bool>,
+    /// When true, `build_room_floor_mesh` also returns `path_costs`: a cost
+    /// multiplier per floor triangle plus Detour area ids and area costs.
+    pub emit_path_costs: Option<bool>,
+    /// Cell attribute path costs are derived from: `"uniform"` (default, all
+    /// 1), `"confidence"` or `"slope"` (normal alignment). A cell costs
+    /// `1 + path_cost_scale * (1 - attribute)`.
+    pub path_cost_from: Option<String>,
+    /// Cost added at attribute 0 (default 4).
+    pub path_cost_scale: Option<f64>,
+    /// When set to `"height"`, `"coverage"` or `"slope"`,
+    /// `build_walkable_ground_field` also returns `preview`: that cell
+    /// attribute painted into an RGBA image for a 2D canvas.
+    pub emit_preview: Option<String>,
+    /// When true, `build_walkable_ground_field` also returns `sensitivity`:
+    /// the numeric settings ranked by how far nudging each moves the
+    /// walkable area and component count.
+    pub emit_sensitivity: Option<bool>,
+    /// Relative nudge of the sensitivity report, between 0 and 1 (default
+    /// 0.25).
+    pub sensitivity_step: Option<f64>,
+    /// When true, mesh `vertices` / `indices` (and `vertex_alpha` /
+    /// `vertex_confidence` / `colors`) come back as `Float32Array` /
+    /// `Uint32Array` instead of plain arrays, skipping per-element
+    /// serialization for large meshes.
+    pub typed_buffers: Option<bool>,
+    /// When set, `convert_splat_to_mesh` also returns `thumbnail`: `mesh`
+    /// rendered in software from an orbit or explicit camera, as RGBA pixels
+    /// and a PNG. See `thumbnail::ThumbnailSettings`.
+    pub emit_thumbnail: Option<thumbnail::ThumbnailSettings>,
+    /// When set, `convert_splat_to_mesh` also returns `draco`: `mesh` as a
+    /// Draco bitstream with per-attribute quantization. Needs a build with
+    /// the `draco` cargo feature. See `draco::DracoSettings`.
+    pub emit_draco: Option<draco::DracoSettings>,
+    /// When set, mode 2 `convert_splat_to_mesh` also returns `heightmap`:
+    /// the floor's voxel-corner heights as a 16-bit PNG or raw buffer with
+    /// their world-space extents. See `heightmap::HeightmapSettings`.
+    pub emit_heightmap: Option<heightmap::HeightmapSettings>,
+    /// When true, `convert_splat_to_mesh` and `build_room_floor_mesh` also
+    /// return `manifest`: a zip of every artifact of the bake (GLBs, sidecar
+    /// JSON, diagnostics, settings, journal) with a `manifest.json` index.
+    pub emit_manifest: Option<bool>,
+    /// When true, `convert_splat_to_mesh` and `build_room_floor_mesh` also
+    /// return `source_mesh`: `mesh` mapped back into the splat file's own
+    /// coordinates (undoing `output_space` / `coordinate_system`, `flip_y`,
+    /// `rotation` and `environment_scale`), for re-export or alignment with
+    /// other scans while `mesh` stays in the host's space.
+    pub emit_source_mesh: Option<bool>,
+    /// The settings object as the host passed it, kept for `emit_manifest`.
+    #[serde(skip)]
+    pub host_settings: Option<serde_json::Value>,
+    /// Vertical relief factor for the room-floor mesh (default 1): heights are
+    /// scaled about the floor plane before meshing. 0 flattens carpet-level
+    /// noise away; values above 1 exaggerate relief for visualization. Cell
+    /// classification always uses measured heights; pass the same factor to
+    /// `recast_config` as `reliefScale` so Recast's slope/climb limits match.
+    pub relief_scale: Option<f64>,
+    /// Catch geometry for floors that drop off into nothing, returned by
+    /// `build_room_floor_mesh` as a separate `void_mesh`: `"kill_plane"` (one
+    /// flat quad under the whole floor) or `"apron"` (flat cells extending out
+    /// from open edges only). Unset emits nothing.
+    pub void_plane: Option<String>,
+    /// Metres below the floor plane (default 2 for `kill_plane`, 0 for `apron`).
+    pub void_plane_depth: Option<f64>,
+    /// Kill-plane margin around the floor, or apron width, in field cells
+    /// (default 8).
+    pub void_plane_extent_cells: Option<f64>,
     /// Uniform world scale applied to oriented splat positions and per-splat gaussian
     /// scales inside `build_context` (default 1). Matches the renderer's environment
     /// scale so collision / floor / navmesh bake in the same meters as the visible splat.
     /// Region bounds and collision seeds are expected in this scaled world space already
     /// (do not pre-scale them again here).
     pub environment_scale: Option<f64>,
+    /// Job (from `create_job`) whose cache keeps this call's parsed splats,
+    /// so `free_job` releases it. Unset uses the default job 0.
+    pub job_id: Option<u32>,
 }
 
 #[derive(Clone, Serialize)]
@@ -316,17 +931,53 @@ pub struct MeshBuffers {
     pub indices: Vec<u32>,
     pub vertex_count: usize,
     pub face_count: usize,
+    /// Per-vertex unit normals (three values per vertex), angle-weighted over
+    /// the faces around each vertex and following their winding.
+    pub normals: Vec<f32>,
+    /// Per-vertex alpha in `[0, 1]` (one value per vertex), present only on
+    /// feathered render proxies.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vertex_alpha: Option<Vec<f32>>,
+    /// Per-vertex confidence in `[0, 1]`, histogram-equalized across the mesh
+    /// so equal steps are equal fractions of its vertices.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vertex_confidence: Option<Vec<f32>>,
+    /// Per-vertex RGB in `[0, 1]` (three values per vertex) baked from the
+    /// splats' SH DC colors.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub colors: Option<Vec<f32>>,
+    /// Per-vertex texture coordinates (two values per vertex): into the
+    /// result's `walkability_texture` on room floors, or from
+    /// `generate_uvs`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uvs: Option<Vec<f32>>,
+    /// Per-vertex distance in metres to the nearest input splat (-1 with none
+    /// in reach), present only with `emit_deviation`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vertex_deviation: Option<Vec<f32>>,
+    /// `vertex_deviation` as RGB in `[0, 1]` (three values per vertex), green
+    /// on the capture to red at `deviation_range`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deviation_colors: Option<Vec<f32>>,
 }
 
 impl MeshBuffers {
     pub fn new(vertices: Vec<f32>, indices: Vec<u32>) -> Self {
         let vertex_count = vertices.len() / 3;
         let face_count = indices.len() / 3;
+        let normals = normals::angle_weighted(&vertices, &indices);
         Self {
             vertices,
             indices,
             vertex_count,
             face_count,
+            normals,
+            vertex_alpha: None,
+            vertex_confidence: None,
+            colors: None,
+            uvs: None,
+            vertex_deviation: None,
+            deviation_colors: None,
         }
     }
 }
@@ -354,7 +1005,33 @@ pub struct FloorPlane {
     pub d: f64,
 }
 
+/// One ranked RANSAC ground hypothesis, verified on the full cloud.
 #[derive(Clone, Serialize)]
+pub struct GroundCandidate {
+    pub index: usize,
+    pub plane: FloorPlane,
+    /// Mean height of the plane's inliers.
+    pub height: f64,
+    pub inliers: usize,
+    /// Inlier bounding box, for previewing the candidate.
+    pub extent_min: [f64; 3],
+    pub extent_max: [f64; 3],
+}
+
+/// One plane peeled off by mode 4, with its own quad.
+#[derive(Serialize)]
+pub struct DetectedPlane {
+    pub index: usize,
+    /// Horizontal planes face up; the others face the cloud centroid.
+    pub plane: FloorPlane,
+    /// `"horizontal"`, `"vertical"`, or `"oblique"` (15 degree tolerance).
+    pub orientation: String,
+    /// Points claimed by this plane (not counted again by later planes).
+    pub inliers: usize,
+    pub mesh: MeshBuffers,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct FieldBasis {
     pub origin: [f64; 3],
     pub tangent: [f64; 3],
@@ -384,6 +1061,8 @@ pub struct GroundFieldCell {
     pub obstacle_score: f32,
     pub primary_layer_height: f32,
     pub layer_count: usize,
+    /// Which of the column's density layers (bottom first) is the floor.
+    pub primary_layer: usize,
     pub peak_density: f32,
     pub surface_confidence: f32,
     pub signed_distance: f32,
@@ -405,6 +1084,8 @@ pub struct ReconstructionDiagnostics {
     pub points_region_discarded: usize,
     pub points_after_filter: usize,
     pub ransac_inliers: usize,
+    /// RANSAC hypotheses drawn before the fit converged (early termination).
+    pub ransac_iterations: usize,
     pub grid_width: usize,
     pub grid_height: usize,
     pub cell_size: f64,
@@ -415,6 +1096,9 @@ pub struct ReconstructionDiagnostics {
     pub connected_components: usize,
     pub largest_component_faces: usize,
     pub holes_filled: usize,
+    /// Regions and cells closed by `fill_holes_max_area` inpainting.
+    pub holes_inpainted: usize,
+    pub cells_inpainted: usize,
     pub rejected_cells: usize,
     pub cells_rejected_low_confidence: usize,
     pub cells_rejected_height_variance: usize,
@@ -452,6 +1136,93 @@ pub struct ReconstructionDiagnostics {
     pub collision_external_fill_leaked: bool,
     pub collision_failure_reason: Option<String>,
     pub floor_plane: Option<FloorPlane>,
+    /// Distinct ground planes, best first. Pass an index back as
+    /// `chosen_ground_index` to bake against it.
+    pub ground_candidates: Vec<GroundCandidate>,
+    /// Candidate actually used, when `chosen_ground_index` was honoured.
+    pub chosen_ground_index: Option<usize>,
+    /// Floor / wall / clutter groups when `normal_clusters` is on.
+    pub normal_clusters: Vec<cluster::NormalCluster>,
+    /// Ground-field cells marked obstacle by wall-group splats.
+    pub cells_rejected_wall: usize,
+    /// Mode 3 and 8 TSDF voxel edge and grid size (grid points per axis).
+    pub tsdf_voxel_size: f64,
+    pub tsdf_grid_width: usize,
+    pub tsdf_grid_height: usize,
+    pub tsdf_grid_depth: usize,
+    /// Mode 3 voxels whose fused weight reached the observed threshold;
+    /// mode 8 grid points within the truncation band of a splat.
+    pub tsdf_observed_voxels: usize,
+    /// Mode 5 candidate cell edge, candidate count, and the largest height
+    /// gap left between a candidate and the terrain.
+    pub terrain_cell_size: f64,
+    pub terrain_candidates: usize,
+    pub terrain_max_error: f64,
+    /// Mode 6 ball radii used, edges left open and splats left out.
+    pub bpa_radii: Vec<f64>,
+    pub bpa_boundary_edges: usize,
+    pub bpa_unused_points: usize,
+    /// Mode 7 hull vertices, enclosed volume (cubic metres) and the points
+    /// left outside when `hull_max_vertices` stopped the hull early.
+    pub hull_vertices: usize,
+    pub hull_volume: f64,
+    pub hull_points_outside: usize,
+    /// Mode 8 vertices placed on a crease or corner, and those pulled back
+    /// into their cell.
+    pub dc_sharp_vertices: usize,
+    pub dc_clamped_vertices: usize,
+    /// Mode 9 voxel edge and the voxels the splats occupied.
+    pub surface_nets_voxel_size: f64,
+    pub surface_nets_occupied_voxels: usize,
+    /// Mode 10 wall segments extruded, the floor and ceiling heights they
+    /// span, and where the ceiling came from (`wall_height`, `ceiling`,
+    /// `wall_cluster` or `default`; empty in other modes).
+    pub wall_segments: usize,
+    pub wall_floor_y: f64,
+    pub wall_ceiling_y: f64,
+    pub wall_ceiling_source: String,
+    /// `downsample_voxel_size` applied (0 when off) and the points left.
+    pub downsample_voxel_size: f64,
+    pub points_after_downsample: usize,
+    /// Triangles before and after `decimate_target_faces` (both 0 when off).
+    pub decimate_input_faces: usize,
+    pub decimate_output_faces: usize,
+    /// Mean and largest `emit_deviation` distance in metres over the vertices
+    /// with a splat in reach (both 0 when off).
+    pub deviation_mean: f64,
+    pub deviation_max: f64,
+    /// Laplacian passes applied to the voxel navmesh heights (0 when off).
+    pub smooth_iterations: usize,
+    /// Base tolerance of the bake and where it came from (`fixed`, `scene`,
+    /// `explicit`).
+    pub epsilon: f64,
+    pub epsilon_source: String,
+    /// Vertices removed by `weld_vertices`, merged or left unreferenced (0
+    /// when off).
+    pub welded_vertices: usize,
+    /// Seed the bake's stochastic steps used.
+    pub seed: u64,
+    /// Faces kept and split off by `reachable_from` (both 0 when off), and
+    /// the unreachable walkable islands among the latter.
+    pub reachable_faces: usize,
+    pub unreachable_faces: usize,
+    pub unreachable_islands: usize,
+    /// Regions and polygons of `navmesh` (0 when `emit_poly_navmesh` is off).
+    pub navmesh_regions: usize,
+    pub navmesh_polys: usize,
+    /// Floor voxels rejected by `agent_height` (0 when off).
+    pub collision_low_headroom_floors: usize,
+    /// Floor voxels ramped up to a neighbour by `max_step_height` (0 when
+    /// off).
+    pub collision_stair_ramps: usize,
+    /// Layers in `floor_layers` (0 when `emit_floor_layers` is off).
+    pub floor_layers: usize,
+    /// Output faces the `set_face_filter` callback rejected (0 without one).
+    pub face_filter_rejected: usize,
+    /// `post_process` steps run (0 when unset).
+    pub post_process_steps: usize,
+    /// Tiles in `tiles` that hold faces (0 without `tile_size`).
+    pub tile_count: usize,
 }
 
 impl ReconstructionDiagnostics {
@@ -468,6 +1239,7 @@ impl ReconstructionDiagnostics {
             points_region_discarded: 0,
             points_after_filter: 0,
             ransac_inliers: 0,
+            ransac_iterations: 0,
             grid_width: 0,
             grid_height: 0,
             cell_size: 0.0,
@@ -478,6 +1250,8 @@ impl ReconstructionDiagnostics {
             connected_components: 0,
             largest_component_faces: 0,
             holes_filled: 0,
+            holes_inpainted: 0,
+            cells_inpainted: 0,
             rejected_cells: 0,
             cells_rejected_low_confidence: 0,
             cells_rejected_height_variance: 0,
@@ -515,6 +1289,54 @@ impl ReconstructionDiagnostics {
             collision_external_fill_leaked: false,
             collision_failure_reason: None,
             floor_plane: None,
+            ground_candidates: Vec::new(),
+            chosen_ground_index: None,
+            normal_clusters: Vec::new(),
+            cells_rejected_wall: 0,
+            tsdf_voxel_size: 0.0,
+            tsdf_grid_width: 0,
+            tsdf_grid_height: 0,
+            tsdf_grid_depth: 0,
+            tsdf_observed_voxels: 0,
+            terrain_cell_size: 0.0,
+            terrain_candidates: 0,
+            terrain_max_error: 0.0,
+            bpa_radii: Vec::new(),
+            bpa_boundary_edges: 0,
+            bpa_unused_points: 0,
+            hull_vertices: 0,
+            hull_volume: 0.0,
+            hull_points_outside: 0,
+            dc_sharp_vertices: 0,
+            dc_clamped_vertices: 0,
+            surface_nets_voxel_size: 0.0,
+            surface_nets_occupied_voxels: 0,
+            wall_segments: 0,
+            wall_floor_y: 0.0,
+            wall_ceiling_y: 0.0,
+            wall_ceiling_source: String::new(),
+            downsample_voxel_size: 0.0,
+            points_after_downsample: 0,
+            decimate_input_faces: 0,
+            decimate_output_faces: 0,
+            deviation_mean: 0.0,
+            deviation_max: 0.0,
+            smooth_iterations: 0,
+            epsilon: epsilon::REFERENCE_EPSILON,
+            epsilon_source: "fixed".to_string(),
+            welded_vertices: 0,
+            seed: ransac::DEFAULT_SEED,
+            reachable_faces: 0,
+            unreachable_faces: 0,
+            unreachable_islands: 0,
+            navmesh_regions: 0,
+            navmesh_polys: 0,
+            collision_low_headroom_floors: 0,
+            collision_stair_ramps: 0,
+            floor_layers: 0,
+            face_filter_rejected: 0,
+            post_process_steps: 0,
+            tile_count: 0,
         }
     }
 }
@@ -525,8 +1347,83 @@ pub struct ReconstructionResult {
     pub semver: String,
     pub capabilities: Vec<String>,
     pub mesh: MeshBuffers,
+    /// Mode 4 only: each detected plane, largest first. `mesh` is their union.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub planes: Option<Vec<DetectedPlane>>,
+    /// Faces not reachable from `reachable_from`, present only when it was
+    /// set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unreachable: Option<reachability::UnreachableSet>,
+    /// Mode 2 with `emit_poly_navmesh`: the polygon navmesh of the carved
+    /// floors. Built from the voxels, so `reachable_from`, welding and
+    /// decimation of `mesh` do not apply to it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub navmesh: Option<navmesh::PolyNavmesh>,
+    /// `navmesh` in the Recast/Detour sample `.bin` layout, present only when
+    /// `emit_detour_navmesh` was set. Always `splatwalk_oriented`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detour_navmesh: Option<serde_bytes::ByteBuf>,
+    /// The `off_mesh_links` that landed on the mesh, with snapped ends, and
+    /// the ones dropped. Present only when `off_mesh_links` was set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub off_mesh_links: Option<offmesh::OffMeshLinks>,
+    /// Mode 2 with `emit_floor_layers`: the walkable floors split into
+    /// storeys.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub floor_layers: Option<floor_layers::FloorLayers>,
+    /// Mode 2 with `emit_occupancy_grid`: free, occupied and unknown cells
+    /// seen from above, as a ROS map. Always `splatwalk_oriented`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub occupancy_grid: Option<occupancy::OccupancyGrid>,
+    /// Walking distance per `mesh` vertex, present only when
+    /// `distance_sources` was set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub distance_field: Option<geodesic::DistanceField>,
+    /// Steering direction per `mesh` face, present only when `flow_goal` was
+    /// set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flow_field: Option<flow_field::FlowField>,
+    /// Hazard flags per `mesh` face, present only when `hazard_water_height`
+    /// or `hazard_volumes` was set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hazards: Option<hazard::HazardTags>,
+    /// Per-triangle path costs from the `set_face_filter` callback, present
+    /// only when one is registered.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path_costs: Option<path_cost::PathCosts>,
+    /// The mesh split into streamable tiles, present only when `tile_size`
+    /// was set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tiles: Option<tiling::TileIndex>,
+    /// The ground plane, a basis on it and the maps to and from the splat
+    /// file.
+    pub frame: frame::GroundFrame,
+    /// `mesh` in the splat file's space, present only when
+    /// `emit_source_mesh` was set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_mesh: Option<MeshBuffers>,
+    /// `mesh` as a Draco bitstream, present only when `emit_draco` was set
+    /// (cargo feature `draco`).
+    #[cfg(feature = "draco")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub draco: Option<draco::DracoMesh>,
     pub space: CoordinateSpace,
     pub diagnostics: ReconstructionDiagnostics,
+    /// `mesh` as an image, present only when `emit_thumbnail` was set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<thumbnail::Thumbnail>,
+    /// The floor's height grid, present only when `emit_heightmap` was set
+    /// and mode 2 built untiled walkable floors. Always `splatwalk_oriented`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heightmap: Option<heightmap::Heightmap>,
+    /// Present when a stage after filtering panicked: `mesh` (and `planes` /
+    /// `mesh.colors`) are empty from that stage on.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partial: Option<stage::PartialResult>,
+    /// Zip of every artifact of the bake, present only when `emit_manifest`
+    /// was set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<serde_bytes::ByteBuf>,
 }
 
 #[derive(Serialize)]
@@ -564,6 +1461,9 @@ pub struct NavmeshBasisResult {
     pub basis: FieldBasis,
     pub floor_plane: FloorPlane,
     pub diagnostics: ReconstructionDiagnostics,
+    /// Present when collision meshing panicked after the grid was carved.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partial: Option<stage::PartialResult>,
 }
 
 /// Packed dense voxel volume for runtime walk (solid + carved nav).
@@ -592,6 +1492,10 @@ pub struct CollisionVoxelBoundaryResult {
     pub basis: FieldBasis,
     pub floor_plane: FloorPlane,
     pub diagnostics: ReconstructionDiagnostics,
+    /// Present when collision meshing panicked after the grid was carved;
+    /// `volume` is still returned when requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partial: Option<stage::PartialResult>,
 }
 
 #[derive(Serialize)]
@@ -607,6 +1511,132 @@ pub struct WalkableGroundFieldResult {
     pub floor_plane: FloorPlane,
     pub space: CoordinateSpace,
     pub diagnostics: ReconstructionDiagnostics,
+    /// `emit_preview` was set: one pixel per cell, in `cells` order.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preview: Option<preview::FieldPreview>,
+    /// Settings ranked by how much they move the field, present only when
+    /// `emit_sensitivity` was set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sensitivity: Option<sensitivity::SensitivityReport>,
+}
+
+/// Result of `build_floor_plan`: regularized wall segments in oriented-space
+/// `[x, z]` metres plus ready-to-save SVG and DXF renderings. `output_space`
+/// is not applied; the plan is always a top view of `splatwalk_oriented`.
+#[derive(Serialize)]
+pub struct FloorPlanResult {
+    pub api_version: u8,
+    pub semver: String,
+    pub capabilities: Vec<String>,
+    pub floor_y: f64,
+    pub dominant_angle_deg: f64,
+    pub wall_splat_count: usize,
+    pub wall_cell_count: usize,
+    pub segments: Vec<floor_plan::WallSegment>,
+    /// Outer boundary of the walkable floor, counter-clockwise in `[x, z]`;
+    /// empty when no ground field could be built.
+    pub floor_outline: Vec<[f64; 2]>,
+    /// Walkable floor area enclosed by `floor_outline`, in square metres.
+    pub floor_area: f64,
+    pub bounds_min: [f64; 2],
+    pub bounds_max: [f64; 2],
+    pub svg: String,
+    pub dxf: String,
+    /// IFC4 STEP file, present only when `emit_ifc` was set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ifc: Option<String>,
+    pub space: CoordinateSpace,
+    pub diagnostics: ReconstructionDiagnostics,
+}
+
+/// `build_safe_area` output: an obstacle-free play area around a standing
+/// point, e.g. for a VR guardian boundary. Like the floor plan it is a top
+/// view of `splatwalk_oriented`; `output_space` is not applied.
+#[derive(Serialize)]
+pub struct SafeAreaResult {
+    pub api_version: u8,
+    pub semver: String,
+    pub capabilities: Vec<String>,
+    /// Standing point the area was grown from, dropped onto the floor.
+    pub center: [f64; 3],
+    /// Floor height at `center`; the boundary lies at this height.
+    pub floor_y: f64,
+    /// `"convex"` or `"star"`.
+    pub shape: String,
+    /// Boundary polygon, counter-clockwise in `[x, z]`.
+    pub boundary: Vec<[f64; 2]>,
+    /// Area enclosed by `boundary`, in square metres.
+    pub area: f64,
+    /// Radius of the largest circle about `center` inside `boundary`.
+    pub inscribed_radius: f64,
+    /// Clearance and headroom the area was grown with.
+    pub clearance: f64,
+    pub ceiling_height: f64,
+    pub space: CoordinateSpace,
+    pub diagnostics: ReconstructionDiagnostics,
+}
+
+/// A narrow passage between two open areas of floor.
+#[derive(Serialize)]
+pub struct Chokepoint {
+    /// Narrowest point of the passage, on the floor.
+    pub position: [f64; 3],
+    /// Passage width there, in metres (to within a field cell).
+    pub width: f64,
+    /// Ends of the cross-section through `position`, `width` apart: a line
+    /// across the passage for a door trigger.
+    pub ends: [[f64; 3]; 2],
+    /// Floor area of the two sides the passage joins, larger first, in
+    /// square metres.
+    pub sides: [f64; 2],
+}
+
+/// `find_chokepoints` output, narrowest first. Like the safe area it is in
+/// `splatwalk_oriented`; `output_space` is not applied.
+#[derive(Serialize)]
+pub struct ChokepointResult {
+    pub api_version: u8,
+    pub semver: String,
+    pub capabilities: Vec<String>,
+    pub chokepoints: Vec<Chokepoint>,
+    /// Width limits the search ran with.
+    pub min_width: f64,
+    pub max_width: f64,
+    pub space: CoordinateSpace,
+    pub diagnostics: ReconstructionDiagnostics,
+}
+
+/// One island of walkable floor: an outline with holes, in plan `[x, z]`.
+#[derive(Serialize)]
+pub struct FloorPolygon {
+    /// Outer boundary, counter-clockwise.
+    pub outer: Vec<[f64; 2]>,
+    /// Furniture, pillars and voids inside it, clockwise, largest first.
+    pub holes: Vec<Vec<[f64; 2]>>,
+    /// Walkable floor area of the island, in square metres.
+    pub area: f64,
+    /// Lowest and highest floor under the island (`y`, metres).
+    pub min_y: f64,
+    pub max_y: f64,
+}
+
+/// `extract_floor_polygons` output, largest island first. Always
+/// `splatwalk_oriented`; `output_space` is not applied.
+#[derive(Serialize)]
+pub struct FloorPolygonsResult {
+    pub api_version: u8,
+    pub semver: String,
+    pub capabilities: Vec<String>,
+    pub polygons: Vec<FloorPolygon>,
+    /// Islands and holes left out for being under `min_area`.
+    pub dropped_islands: usize,
+    pub dropped_holes: usize,
+    /// Simplification tolerance and minimum area the outlines were traced
+    /// with.
+    pub tolerance: f64,
+    pub min_area: f64,
+    pub space: CoordinateSpace,
+    pub diagnostics: ReconstructionDiagnostics,
 }
 
 #[derive(Serialize)]
@@ -630,6 +1660,81 @@ pub struct RoomFloorMeshResult {
     pub fallback_used: bool,
     pub step_label: String,
     pub diagnostics: ReconstructionDiagnostics,
+    /// Per-splat floor mapping, present only when `emit_backprojection` was set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backprojection: Option<SplatBackprojection>,
+    /// Invisible catch geometry below open floor edges, present only when
+    /// `void_plane` was set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub void_mesh: Option<VoidMesh>,
+    /// Triangle-to-cell mapping, present only when `emit_face_cells` was set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub face_cells: Option<FaceCells>,
+    /// Editable floor heightfield (open with `FloorEditor`), present only when
+    /// `emit_heightfield` was set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heightfield: Option<floor_edit::Heightfield>,
+    /// Per-triangle path costs, present only when `emit_path_costs` was set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path_costs: Option<path_cost::PathCosts>,
+    /// Splat coverage baked per floor-field cell, present only when
+    /// `emit_walkability_texture` was set; `mesh.uvs` index into it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub walkability_texture: Option<walkability::WalkabilityTexture>,
+    /// `mesh` in the splat file's space, present only when
+    /// `emit_source_mesh` was set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_mesh: Option<MeshBuffers>,
+    /// Zip of every artifact of the bake, present only when `emit_manifest`
+    /// was set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<serde_bytes::ByteBuf>,
+}
+
+/// Source ground-field cell of every output triangle, so hosts can map a hit
+/// triangle back to the heightfield. Rows and columns index the same grid as
+/// `build_walkable_ground_field` (`cells[row * grid_width + col]`).
+#[derive(Serialize)]
+pub struct FaceCells {
+    pub grid_width: usize,
+    pub grid_height: usize,
+    pub cell_size: f64,
+    /// `[row, col, layer]` per triangle, in index-buffer order; `layer` is the
+    /// cell's `primary_layer`.
+    pub cells: Vec<u32>,
+}
+
+/// Physics-only catch geometry emitted alongside a room floor, in the same
+/// output space as `mesh`.
+#[derive(Serialize)]
+pub struct VoidMesh {
+    /// `"kill_plane"` or `"apron"`.
+    pub name: String,
+    pub mesh: MeshBuffers,
+    /// GLB bytes, present only when `emit_glb` was set and the mesh is non-empty.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub glb: Option<serde_bytes::ByteBuf>,
+    /// Distance below the floor plane, in metres.
+    pub depth: f64,
+    /// Floor cell edges facing a void cell or the edge of the field.
+    pub open_boundary_edges: usize,
+}
+
+/// Which input splats ended up under the walkable floor. Indices follow the
+/// source file order (`PointNormal::index`); splats pruned or filtered before the
+/// bake read as not contributing.
+#[derive(Serialize)]
+pub struct SplatBackprojection {
+    /// Length of `component` and bit length of `contributed`.
+    pub splat_count: usize,
+    /// Packed LSB-first bitmask: bit `i` set when splat `i` lies on an emitted
+    /// floor cell.
+    pub contributed: serde_bytes::ByteBuf,
+    pub contributed_count: usize,
+    /// Floor component id per splat (`-1` when it landed on no floor component).
+    pub component: Vec<i32>,
+    /// Component id of the emitted floor.
+    pub selected_component: i32,
 }
 
 /// One attempt in the WASM-side room-floor recovery ladder. `settings` is a raw
@@ -704,23 +1809,84 @@ fn default_room_floor_recovery() -> Vec<RoomFloorStepCfg> {
     ]
 }
 
-fn parse_settings(settings: JsValue) -> Result<MeshSettings, JsValue> {
-    serde_wasm_bindgen::from_value(settings).map_err(|e| JsValue::from_str(&e.to_string()))
+pub(crate) fn parse_settings(settings: JsValue) -> Result<MeshSettings, JsValue> {
+    let value = settings.clone();
+    let mut settings: MeshSettings = serde_wasm_bindgen::from_value(settings)
+        .map_err(|e| SplatwalkError::InvalidSettings(e.to_string()))?;
+    if settings.emit_manifest.unwrap_or(false) {
+        settings.host_settings = serde_wasm_bindgen::from_value(value).ok();
+    }
+    validate_settings(&settings)?;
+    Ok(settings)
 }
 
-fn validate_collision_mesh_mode(settings: &MeshSettings) -> Result<(), JsValue> {
+/// Every per-feature settings check, for `parse_settings` and for each merged
+/// room-floor recovery step.
+pub(crate) fn validate_settings(settings: &MeshSettings) -> Result<(), SplatwalkError> {
+    kernel::SplatKernel::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    path_cost::CostSource::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    preprocess::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    vertex_color::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    deviation::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    bpa::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    hull::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    dual_contour::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    surface_nets::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    inpaint::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    resolution::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    uv::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    postprocess::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    preview::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    sensitivity::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    tiling::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    detour::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    offmesh::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    output_space::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    thumbnail::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    draco::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    heightmap::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    epsilon::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    ransac::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    safe_area::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    reachability::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    navmesh::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    chokepoint::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    floor_polygons::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    ellipsoid::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    geodesic::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    flow_field::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    hazard::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    region::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    floor_layers::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    void_plane::validate(settings).map_err(SplatwalkError::InvalidSettings)?;
+    Ok(())
+}
+
+fn validate_collision_mesh_mode(settings: &MeshSettings) -> Result<(), SplatwalkError> {
     match settings.collision_mesh_mode.as_deref().unwrap_or("walkable_floors") {
         "faces" | "obstacle_shell" | "walkable_floors" => Ok(()),
-        "smooth" => Err(JsValue::from_str(
-            "collision_mesh_mode=\"smooth\" is reserved but not implemented; use \"walkable_floors\".",
+        "smooth" => Err(SplatwalkError::InvalidSettings(
+            "collision_mesh_mode=\"smooth\" is reserved but not implemented; use \"walkable_floors\".".to_string(),
         )),
-        other => Err(JsValue::from_str(&format!(
+        other => Err(SplatwalkError::InvalidSettings(format!(
             "Invalid collision_mesh_mode: {}. Expected \"walkable_floors\", \"obstacle_shell\", or \"faces\".",
             other
         ))),
     }
 }
 
+/// Classify a splat decode failure: bytes that match no known format are
+/// `unsupported_format`, anything else that failed to decode is `parse_error`.
+fn parse_error<T>(data: &[u8], result: Result<T, String>) -> Result<T, SplatwalkError> {
+    result.map_err(|e| {
+        if splat::is_known_format(data) {
+            SplatwalkError::ParseError(e)
+        } else {
+            SplatwalkError::UnsupportedFormat(e)
+        }
+    })
+}
+
 /// Identity of a parsed+pruned+oriented point set, so repeated WASM calls on the
 /// same bytes+settings within a session can reuse the result instead of
 /// re-parsing the PLY and re-running the (expensive) floater prune every time.
@@ -732,21 +1898,19 @@ struct ParseKey {
     k: usize,
     std_ratio_bits: u64,
     flip_y: bool,
+    z_up: bool,
 }
 
-struct ParseCacheEntry {
+pub(crate) struct ParseCacheEntry {
     key: ParseKey,
-    points: Vec<splat::PointNormal>,
-}
-
-thread_local! {
-    static PARSE_CACHE: RefCell<Option<ParseCacheEntry>> = RefCell::new(None);
+    pub(crate) points: Vec<splat::PointNormal>,
+    splat_count: u32,
 }
 
 /// Cheap content fingerprint: FNV-1a over the length plus a strided sample of the
 /// bytes. Full hashing of tens of MB on every call would itself be costly; a
 /// sampled hash is more than enough to detect "same file" within a session.
-fn fingerprint(data: &[u8]) -> u64 {
+pub(crate) fn fingerprint(data: &[u8]) -> u64 {
     const FNV_OFFSET: u64 = 0xcbf29ce484222325;
     const FNV_PRIME: u64 = 0x100000001b3;
     let mut hash = FNV_OFFSET ^ (data.len() as u64);
@@ -761,11 +1925,37 @@ fn fingerprint(data: &[u8]) -> u64 {
     hash
 }
 
-fn parse_splats(data: &[u8], settings: &MeshSettings) -> Result<Vec<splat::PointNormal>, JsValue> {
+/// Where an entry point gets its parsed splats from: the ingest path
+/// (`parse_splats` over raw bytes) or an already-parsed source such as a
+/// `SplatSession`'s composed cloud. Called once per settings variant.
+pub(crate) type SplatSource<'a> =
+    &'a dyn Fn(&MeshSettings) -> Result<Vec<splat::PointNormal>, JsValue>;
+
+/// A [`SplatSource`] that also reports how many splat records its point
+/// indices address. Pruning drops records, so that count can exceed the
+/// largest surviving index; per-splat outputs such as the back-projection are
+/// sized from it.
+pub(crate) type CountedSplatSource<'a> =
+    &'a dyn Fn(&MeshSettings) -> Result<(Vec<splat::PointNormal>, u32), JsValue>;
+
+pub(crate) fn parse_splats(
+    data: &[u8],
+    settings: &MeshSettings,
+) -> Result<Vec<splat::PointNormal>, JsValue> {
+    parse_splat_records(data, settings).map(|(points, _)| points)
+}
+
+/// [`parse_splats`] plus the number of records in the file, counted before
+/// floater pruning.
+pub(crate) fn parse_splat_records(
+    data: &[u8],
+    settings: &MeshSettings,
+) -> Result<(Vec<splat::PointNormal>, u32), JsValue> {
     let prune = settings.prune_floaters.unwrap_or(true);
     let k = settings.prune_floaters_k.unwrap_or(16);
     let std_ratio = settings.prune_floaters_std_ratio.unwrap_or(2.0);
-    let flip_y = settings.flip_y.unwrap_or(false);
+    let flip_y = output_space::flip_y(settings);
+    let to_y_up = output_space::ingest_transform(settings);
 
     let key = ParseKey {
         len: data.len(),
@@ -774,142 +1964,511 @@ fn parse_splats(data: &[u8], settings: &MeshSettings) -> Result<Vec<splat::Point
         k,
         std_ratio_bits: std_ratio.to_bits(),
         flip_y,
+        z_up: to_y_up.is_some(),
     };
 
     // Cache hit: reuse the previously parsed+pruned+oriented points.
-    if let Some(points) = PARSE_CACHE.with(|cache| {
-        cache
-            .borrow()
+    if let Some((points, splat_count)) = memory::with_job(settings.job_id, |job| {
+        job.parse_cache
             .as_ref()
             .filter(|entry| entry.key == key)
-            .map(|entry| entry.points.clone())
-    }) {
-        log(&format!("Reusing cached splats ({} points)", points.len()));
-        return Ok(points);
+            .map(|entry| (entry.points.clone(), entry.splat_count))
+    })? {
+        events::emit(Event::ParseCacheReused {
+            count: points.len(),
+        });
+        journal::record(
+            "ingest",
+            journal::JournalKind::Decision,
+            "Reused the job's cached parse".to_string(),
+            serde_json::json!({ "points": points.len() }),
+        );
+        return Ok((points, splat_count));
     }
 
     emit_progress("parse", Some(0.0));
-    let mut splats = splat::parse_ply(data).map_err(|e| JsValue::from_str(&e))?;
+    let mut splats = parse_error(data, stage::run("parse", || splat::parse_ply(data))?)?;
+    if splats.is_empty() {
+        return Err(
+            SplatwalkError::EmptyCloud("The splat file contains no splats.".to_string()).into(),
+        );
+    }
+    let splat_count = splats.len() as u32;
 
     // Prune stray floater splats at the single ingest chokepoint so every
     // downstream op (bounds, region suggestion, seed, floor field, mesh) operates
     // on the cleaned set. Defaults on; integrators can disable or tune it.
     if prune {
-        let result = splat::prune_floaters(splats, k, std_ratio, 0.4);
+        let result = stage::run("prune", || splat::prune_floaters(splats, k, std_ratio, 0.4))?;
         match result.skipped_reason {
-            Some(reason) => log(&format!(
-                "Floater prune skipped ({}); kept all {} splats",
-                reason, result.input_count
-            )),
-            None => log(&format!(
-                "Pruned {} floater splats (k={}, std_ratio={:.2}): {} -> {}",
-                result.removed_count,
-                k,
-                std_ratio,
-                result.input_count,
-                result.input_count - result.removed_count
-            )),
+            Some(reason) => {
+                events::emit(Event::FloaterPruneSkipped {
+                    reason: reason.clone(),
+                    count: result.input_count,
+                });
+                journal::record(
+                    "ingest",
+                    journal::JournalKind::Degraded,
+                    format!("Floater prune skipped: {}", reason),
+                    serde_json::json!({ "reason": reason, "points": result.input_count }),
+                );
+            }
+            None => {
+                events::emit(Event::FloatersPruned {
+                    removed: result.removed_count,
+                    k,
+                    std_ratio,
+                    input: result.input_count,
+                    kept: result.input_count - result.removed_count,
+                });
+                journal::record(
+                    "ingest",
+                    journal::JournalKind::Dropped,
+                    format!("Pruned {} floater splats", result.removed_count),
+                    serde_json::json!({
+                        "reason": "statistical_outlier",
+                        "count": result.removed_count,
+                        "input": result.input_count,
+                        "k": k,
+                        "std_ratio": std_ratio,
+                    }),
+                );
+            }
         }
         splats = result.points;
     }
 
+    if let Some(t) = &to_y_up {
+        for p in &mut splats {
+            p.point = t.unapply([p.point.x, p.point.y, p.point.z]).into();
+            p.normal = t.unapply([p.normal.x, p.normal.y, p.normal.z]).into();
+        }
+    }
     if flip_y {
         for p in &mut splats {
             p.point.y = -p.point.y;
             p.normal.y = -p.normal.y;
         }
-        log(&format!(
-            "Parsed {} splats (Y-flipped to render space)",
-            splats.len()
-        ));
-    } else {
-        log(&format!("Parsed {} splats", splats.len()));
     }
-
-    PARSE_CACHE.with(|cache| {
-        *cache.borrow_mut() = Some(ParseCacheEntry {
-            key,
-            points: splats.clone(),
-        });
+    events::emit(Event::SplatsIngested {
+        count: splats.len(),
+        flip_y,
     });
 
-    Ok(splats)
+    // Refill the job's cache in place so repeated bakes reuse its buffer rather
+    // than fragmenting the heap with a fresh allocation each time.
+    memory::with_job(settings.job_id, |job| match job.parse_cache.as_mut() {
+        Some(entry) => {
+            entry.key = key;
+            entry.points.clear();
+            entry.points.extend_from_slice(&splats);
+            entry.splat_count = splat_count;
+        }
+        None => {
+            job.parse_cache = Some(ParseCacheEntry {
+                key,
+                points: splats.clone(),
+                splat_count,
+            });
+        }
+    })?;
+
+    Ok((splats, splat_count))
 }
 
 #[wasm_bindgen]
 pub fn get_splat_bounds(data: &[u8], settings: JsValue) -> Result<JsValue, JsValue> {
+    get_splat_bounds_from(&|s| parse_splats(data, s), settings)
+}
+
+pub(crate) fn get_splat_bounds_from(
+    source: SplatSource,
+    settings: JsValue,
+) -> Result<JsValue, JsValue> {
     let settings = parse_settings(settings)?;
-    let splats = parse_splats(data, &settings)?;
-    let mut result = mesh::get_splat_bounds(&splats, &settings)?;
+    let splats = source(&settings)?;
+    let mut result = stage::run("bounds", || mesh::get_splat_bounds(&splats, &settings))??;
     output_space::apply_bounds(&settings, &mut result);
     Ok(serde_wasm_bindgen::to_value(&result)?)
 }
 
 #[wasm_bindgen]
 pub fn suggest_region(data: &[u8], settings: JsValue) -> Result<JsValue, JsValue> {
+    suggest_region_from(&|s| parse_splats(data, s), settings)
+}
+
+pub(crate) fn suggest_region_from(
+    source: SplatSource,
+    settings: JsValue,
+) -> Result<JsValue, JsValue> {
     let settings = parse_settings(settings)?;
-    let splats = parse_splats(data, &settings)?;
-    let mut result = mesh::suggest_region(&splats, &settings)?;
+    let splats = source(&settings)?;
+    let mut result = stage::run("suggest_region", || {
+        mesh::suggest_region(&splats, &settings)
+    })??;
     output_space::apply_region(&settings, &mut result);
     Ok(serde_wasm_bindgen::to_value(&result)?)
 }
 
 #[wasm_bindgen]
 pub fn convert_splat_to_mesh(data: &[u8], settings: JsValue) -> Result<JsValue, JsValue> {
+    convert_splat_to_mesh_from(&|s| parse_splats(data, s), settings)
+}
+
+/// `convert_splat_to_mesh` as a `Promise` that yields to the event loop
+/// between pipeline stages, for hosts baking on the main thread without a
+/// worker. `data` is copied in, since the bake outlives the call.
+#[wasm_bindgen]
+pub fn convert_splat_to_mesh_async(data: Vec<u8>, settings: JsValue) -> js_sys::Promise {
+    async_mesh::convert(data, settings)
+}
+
+pub(crate) fn convert_splat_to_mesh_from(
+    source: SplatSource,
+    settings: JsValue,
+) -> Result<JsValue, JsValue> {
+    journal::begin("convert_splat_to_mesh");
     let settings = parse_settings(settings)?;
     let mode = settings.mode;
     if mode == 2 {
         validate_collision_mesh_mode(&settings)?;
     }
 
-    log(&format!("Received {} bytes (Mode: {})", data.len(), mode));
+    let splats = source(&settings)?;
+    events::emit(Event::SplatsReceived {
+        count: splats.len(),
+        mode,
+    });
+    let result = stage::run("reconstruct", || mesh::reconstruct_mesh(&splats, &settings))?;
+    reconstruction_to_value(&settings, result)
+}
 
-    let splats = parse_splats(data, &settings)?;
-    let mut result = mesh::reconstruct_mesh(&splats, &settings);
-    log(&format!(
-        "Reconstructed mesh with {} vertices",
-        result.mesh.vertex_count
-    ));
-    output_space::apply_reconstruction(&settings, &mut result);
+/// Map `result` into the requested output space and serialize it, moving the
+/// mesh buffers to typed arrays when `typed_buffers` is set.
+pub(crate) fn reconstruction_to_value(
+    settings: &MeshSettings,
+    mut result: ReconstructionResult,
+) -> Result<JsValue, JsValue> {
+    events::emit(Event::MeshReconstructed {
+        vertices: result.mesh.vertex_count,
+    });
+    output_space::apply_reconstruction(settings, &mut result);
+    if settings.emit_source_mesh.unwrap_or(false) {
+        result.source_mesh = Some(frame::mesh_to_splat(&result.frame, &result.mesh));
+    }
+    #[cfg(feature = "draco")]
+    if let Some(options) = &settings.emit_draco {
+        let encoded = stage::run("draco", || draco::encode(&result.mesh, options))?
+            .map_err(SplatwalkError::ReconstructionFailed)?;
+        result.draco = Some(encoded);
+    }
+    if settings.emit_manifest.unwrap_or(false) {
+        let host_settings = settings.host_settings.as_ref();
+        let bytes = stage::run("manifest", || {
+            manifest::reconstruction(&result, host_settings.unwrap_or(&serde_json::Value::Null))
+        })?
+        .map_err(SplatwalkError::ReconstructionFailed)?;
+        result.manifest = Some(serde_bytes::ByteBuf::from(bytes));
+    }
 
-    Ok(serde_wasm_bindgen::to_value(&result)?)
+    let typed = TypedMesh::take(settings, &mut result.mesh);
+    let typed_source = result
+        .source_mesh
+        .as_mut()
+        .and_then(|mesh| TypedMesh::take(settings, mesh));
+    let typed_unreachable = result
+        .unreachable
+        .as_mut()
+        .and_then(|set| TypedMesh::take(settings, &mut set.mesh));
+    let typed_planes: Vec<Option<TypedMesh>> = result
+        .planes
+        .iter_mut()
+        .flatten()
+        .map(|plane| TypedMesh::take(settings, &mut plane.mesh))
+        .collect();
+    let typed_tiles: Vec<Option<TypedMesh>> = result
+        .tiles
+        .iter_mut()
+        .flat_map(|index| index.tiles.iter_mut())
+        .map(|tile| TypedMesh::take(settings, &mut tile.mesh))
+        .collect();
+    let value = serde_wasm_bindgen::to_value(&result)?;
+    if let Some(typed) = typed {
+        typed.attach(&value, &["mesh"])?;
+    }
+    if let Some(typed) = typed_source {
+        typed.attach(&value, &["source_mesh"])?;
+    }
+    if let Some(typed) = typed_unreachable {
+        typed.attach(&value, &["unreachable", "mesh"])?;
+    }
+    for (i, typed) in typed_planes.into_iter().enumerate() {
+        if let Some(typed) = typed {
+            typed.attach(&value, &["planes", &i.to_string(), "mesh"])?;
+        }
+    }
+    for (i, typed) in typed_tiles.into_iter().enumerate() {
+        if let Some(typed) = typed {
+            typed.attach(&value, &["tiles", "tiles", &i.to_string(), "mesh"])?;
+        }
+    }
+    Ok(value)
 }
 
 #[wasm_bindgen]
 pub fn convert_splat_to_navmesh_basis(data: &[u8], settings: JsValue) -> Result<JsValue, JsValue> {
+    convert_splat_to_navmesh_basis_from(&|s| parse_splats(data, s), settings)
+}
+
+pub(crate) fn convert_splat_to_navmesh_basis_from(
+    source: SplatSource,
+    settings: JsValue,
+) -> Result<JsValue, JsValue> {
+    journal::begin("convert_splat_to_navmesh_basis");
     let settings = parse_settings(settings)?;
     validate_collision_mesh_mode(&settings)?;
-    let splats = parse_splats(data, &settings)?;
-    let mut result = mesh::convert_splat_to_navmesh_basis(&splats, &settings);
+    let splats = source(&settings)?;
+    let mut result = stage::run("navmesh_basis", || {
+        mesh::convert_splat_to_navmesh_basis(&splats, &settings)
+    })?;
     output_space::apply_navmesh_basis(&settings, &mut result);
-    Ok(serde_wasm_bindgen::to_value(&result)?)
+    let typed = TypedMesh::take(&settings, &mut result.mesh);
+    let value = serde_wasm_bindgen::to_value(&result)?;
+    if let Some(typed) = typed {
+        typed.attach(&value, &["mesh"])?;
+    }
+    Ok(value)
 }
 
 #[wasm_bindgen]
 pub fn build_collision_voxel_boundary(data: &[u8], settings: JsValue) -> Result<JsValue, JsValue> {
+    build_collision_voxel_boundary_from(&|s| parse_splats(data, s), settings)
+}
+
+pub(crate) fn build_collision_voxel_boundary_from(
+    source: SplatSource,
+    settings: JsValue,
+) -> Result<JsValue, JsValue> {
+    journal::begin("build_collision_voxel_boundary");
     let options: CollisionVoxelBoundaryOptions =
         serde_wasm_bindgen::from_value(settings.clone()).unwrap_or_default();
     let settings = parse_settings(settings)?;
     validate_collision_mesh_mode(&settings)?;
-    let splats = parse_splats(data, &settings)?;
+    let splats = source(&settings)?;
     let emit_volume = options.emit_volume.unwrap_or(false);
-    let mut result = mesh::build_collision_voxel_boundary(&splats, &settings, emit_volume);
+    let mut result = stage::run("collision", || {
+        mesh::build_collision_voxel_boundary(&splats, &settings, emit_volume)
+    })?;
     output_space::apply_collision_voxel_boundary(&settings, &mut result);
     if options.emit_glb.unwrap_or(false) {
         result.glb = soft_emit_glb(&result.mesh.vertices, &result.mesh.indices);
     }
-    Ok(serde_wasm_bindgen::to_value(&result)?)
+    let typed = TypedMesh::take(&settings, &mut result.mesh);
+    let value = serde_wasm_bindgen::to_value(&result)?;
+    if let Some(typed) = typed {
+        typed.attach(&value, &["mesh"])?;
+    }
+    Ok(value)
 }
 
 #[wasm_bindgen]
 pub fn build_walkable_ground_field(data: &[u8], settings: JsValue) -> Result<JsValue, JsValue> {
-    let settings = parse_settings(settings)?;
-    let splats = parse_splats(data, &settings)?;
-    let mut result = mesh::build_walkable_ground_field(&splats, &settings)?;
+    build_walkable_ground_field_from(&|s| parse_splats(data, s), settings)
+}
+
+pub(crate) fn build_walkable_ground_field_from(
+    source: SplatSource,
+    settings: JsValue,
+) -> Result<JsValue, JsValue> {
+    journal::begin("build_walkable_ground_field");
+    let mut settings = parse_settings(settings)?;
+    let splats = source(&settings)?;
+    let mut result = stage::run("ground_field", || {
+        mesh::build_walkable_ground_field(&splats, &settings)
+    })??;
+    if settings.emit_sensitivity.unwrap_or(false) {
+        // A failed report leaves the field itself intact.
+        result.sensitivity = stage::run("sensitivity", || {
+            mesh::ground_field_sensitivity(&splats, &mut settings)
+        })
+        .ok()
+        .flatten();
+    }
     output_space::apply_ground_field(&settings, &mut result);
     Ok(serde_wasm_bindgen::to_value(&result)?)
 }
 
+/// Fit a CAD-ready vector floor plan: line segments through the wall normal
+/// cluster, projected onto the floor plane and snapped to the dominant
+/// orientations. See `floor_plan`.
+#[wasm_bindgen]
+pub fn build_floor_plan(data: &[u8], settings: JsValue) -> Result<JsValue, JsValue> {
+    build_floor_plan_from(&|s| parse_splats(data, s), settings)
+}
+
+pub(crate) fn build_floor_plan_from(
+    source: SplatSource,
+    settings: JsValue,
+) -> Result<JsValue, JsValue> {
+    journal::begin("build_floor_plan");
+    let settings = parse_settings(settings)?;
+    let splats = source(&settings)?;
+    let result = stage::run("floor_plan", || mesh::build_floor_plan(&splats, &settings))??;
+    events::emit(Event::WallsFitted {
+        segments: result.segments.len(),
+        dominant_angle_deg: result.dominant_angle_deg,
+    });
+    Ok(serde_wasm_bindgen::to_value(&result)?)
+}
+
+/// Grow an obstacle-free play area around a standing point on the walkable
+/// ground field, with clearance and headroom, and return its boundary
+/// polygon. See `safe_area`.
+#[wasm_bindgen]
+pub fn build_safe_area(data: &[u8], settings: JsValue) -> Result<JsValue, JsValue> {
+    build_safe_area_from(&|s| parse_splats(data, s), settings)
+}
+
+pub(crate) fn build_safe_area_from(
+    source: SplatSource,
+    settings: JsValue,
+) -> Result<JsValue, JsValue> {
+    journal::begin("build_safe_area");
+    let settings = parse_settings(settings)?;
+    let splats = source(&settings)?;
+    let result = stage::run("safe_area", || mesh::build_safe_area(&splats, &settings))??;
+    events::emit(Event::SafeAreaFound {
+        area: result.area,
+        shape: result.shape.clone(),
+        inscribed_radius: result.inscribed_radius,
+    });
+    Ok(serde_wasm_bindgen::to_value(&result)?)
+}
+
+/// Find narrow passages between open areas of the walkable ground field
+/// (doorways, gaps between furniture) with their widths. See `chokepoint`.
+#[wasm_bindgen]
+pub fn find_chokepoints(data: &[u8], settings: JsValue) -> Result<JsValue, JsValue> {
+    find_chokepoints_from(&|s| parse_splats(data, s), settings)
+}
+
+pub(crate) fn find_chokepoints_from(
+    source: SplatSource,
+    settings: JsValue,
+) -> Result<JsValue, JsValue> {
+    journal::begin("find_chokepoints");
+    let settings = parse_settings(settings)?;
+    let splats = source(&settings)?;
+    let result = stage::run("chokepoints", || mesh::find_chokepoints(&splats, &settings))??;
+    events::emit(Event::ChokepointsFound {
+        count: result.chokepoints.len(),
+    });
+    Ok(serde_wasm_bindgen::to_value(&result)?)
+}
+
+/// The walkable ground field as simplified 2D polygons with holes, one per
+/// island of floor: an automatic floor plan. See `floor_polygons`.
+#[wasm_bindgen]
+pub fn extract_floor_polygons(data: &[u8], settings: JsValue) -> Result<JsValue, JsValue> {
+    extract_floor_polygons_from(&|s| parse_splats(data, s), settings)
+}
+
+pub(crate) fn extract_floor_polygons_from(
+    source: SplatSource,
+    settings: JsValue,
+) -> Result<JsValue, JsValue> {
+    journal::begin("extract_floor_polygons");
+    let settings = parse_settings(settings)?;
+    let splats = source(&settings)?;
+    let result = stage::run("floor_polygons", || {
+        mesh::extract_floor_polygons(&splats, &settings)
+    })??;
+    events::emit(Event::FloorPolygonsExtracted {
+        polygons: result.polygons.len(),
+        holes: result.polygons.iter().map(|p| p.holes.len()).sum(),
+    });
+    Ok(serde_wasm_bindgen::to_value(&result)?)
+}
+
+/// Low-poly ellipsoids for the largest (or most opaque) splats, each tagged
+/// with what the splat filters did with it, for debug views. See `ellipsoid`.
+#[wasm_bindgen]
+pub fn build_splat_ellipsoids(data: &[u8], settings: JsValue) -> Result<JsValue, JsValue> {
+    journal::begin("build_splat_ellipsoids");
+    let settings = parse_settings(settings)?;
+    let splats = parse_splats(data, &settings)?;
+    // The nav parse keeps a normal per splat, not its rotation. The full parse
+    // has no `.ksplat` reader (and could take one for a `.splat`), so those
+    // ellipsoids fall back to turning onto the normal.
+    let full = stage::run("parse_full", || {
+        (!splat::ksplat::is_ksplat(data))
+            .then(|| splat::parse_full_cloud(data).ok())
+            .flatten()
+    })?;
+    let rotation_of = |index: u32| {
+        full.as_ref()
+            .and_then(|cloud| cloud.rotations.get(index as usize).copied())
+    };
+    let mut result = stage::run("ellipsoids", || {
+        mesh::build_splat_ellipsoids(&splats, &rotation_of, &settings)
+    })??;
+    events::emit(Event::EllipsoidsBuilt {
+        count: result.count,
+        kept: result.splats_kept,
+        discarded: result.splats_discarded,
+    });
+    let typed = result
+        .mesh
+        .as_mut()
+        .and_then(|mesh| TypedMesh::take(&settings, mesh));
+    let instances = settings.typed_buffers.unwrap_or(false).then(|| {
+        (
+            std::mem::take(&mut result.matrices),
+            std::mem::take(&mut result.colors),
+        )
+    });
+    let value = serde_wasm_bindgen::to_value(&result)?;
+    if let Some(typed) = typed {
+        typed.attach(&value, &["mesh"])?;
+    }
+    if let Some((matrices, colors)) = instances {
+        for (key, buffer) in [("matrices", matrices), ("colors", colors)] {
+            let array = js_sys::Float32Array::from(buffer.as_slice());
+            js_sys::Reflect::set(&value, &key.into(), &array.into())?;
+        }
+    }
+    Ok(value)
+}
+
+/// `SplatSession.bake_dirty_tiles`: rebuild the tiles `regions` reach.
+pub(crate) fn bake_dirty_tiles_from(
+    source: SplatSource,
+    regions: &[streaming::DirtyRegion],
+    settings: JsValue,
+) -> Result<JsValue, JsValue> {
+    journal::begin("bake_dirty_tiles");
+    let settings = parse_settings(settings)?;
+    validate_collision_mesh_mode(&settings)?;
+    let splats = source(&settings)?;
+    let mut result = stage::run("dirty_tiles", || {
+        streaming::bake(&splats, regions, &settings)
+    })?
+    .map_err(SplatwalkError::InvalidSettings)?;
+    output_space::apply_tile_patches(&settings, &mut result);
+    let typed: Vec<Option<TypedMesh>> = result
+        .patches
+        .iter_mut()
+        .map(|patch| TypedMesh::take(&settings, &mut patch.mesh))
+        .collect();
+    let value = serde_wasm_bindgen::to_value(&result)?;
+    for (i, typed) in typed.into_iter().enumerate() {
+        if let Some(typed) = typed {
+            typed.attach(&value, &["patches", &i.to_string(), "mesh"])?;
+        }
+    }
+    Ok(value)
+}
+
 /// Extract a triangulated room-floor mesh entirely in WASM: the binary-side
 /// equivalent of the TypeScript FAST NAV floor path. Builds the 2.5D walkable
 /// ground field, selects the seed-nearest connected floor component (with a
@@ -920,8 +2479,46 @@ pub fn build_walkable_ground_field(data: &[u8], settings: JsValue) -> Result<JsV
 /// receive GLB bytes.
 #[wasm_bindgen]
 pub fn build_room_floor_mesh(data: &[u8], settings: JsValue) -> Result<JsValue, JsValue> {
+    build_room_floor_mesh_from(&|s| parse_splat_records(data, s), settings)
+}
+
+/// Settings for one recovery step. The canonical FAST NAV preset is the base
+/// layer so binary-only callers get upstream-correct floor extraction without
+/// copying the preset. Caller settings override the preset, and the step patch
+/// overrides both (preset < caller settings < step patch). A patch can set
+/// anything the caller can, so the merged settings get the same checks as
+/// `parse_settings`. Returns the merged JSON too, for the manifest.
+fn room_floor_step_settings(
+    base: &serde_json::Map<String, serde_json::Value>,
+    step: &RoomFloorStepCfg,
+) -> Result<(serde_json::Value, MeshSettings), SplatwalkError> {
+    let mut merged = fast_nav_preset_json()
+        .as_object()
+        .cloned()
+        .unwrap_or_default();
+    for (k, v) in base {
+        merged.insert(k.clone(), v.clone());
+    }
+    if let Some(serde_json::Value::Object(patch)) = &step.settings {
+        for (k, v) in patch {
+            merged.insert(k.clone(), v.clone());
+        }
+    }
+    let merged = serde_json::Value::Object(merged);
+    let settings: MeshSettings = serde_json::from_value(merged.clone()).map_err(|e| {
+        SplatwalkError::InvalidSettings(format!("Invalid room-floor settings: {}", e))
+    })?;
+    validate_settings(&settings)?;
+    Ok((merged, settings))
+}
+
+pub(crate) fn build_room_floor_mesh_from(
+    source: CountedSplatSource,
+    settings: JsValue,
+) -> Result<JsValue, JsValue> {
+    journal::begin("build_room_floor_mesh");
     let base_value: serde_json::Value = serde_wasm_bindgen::from_value(settings.clone())
-        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        .map_err(|e| SplatwalkError::InvalidSettings(e.to_string()))?;
     let options: RoomFloorOptions = serde_wasm_bindgen::from_value(settings).unwrap_or_default();
 
     let emit_glb = options.emit_glb.unwrap_or(false);
@@ -931,14 +2528,6 @@ pub fn build_room_floor_mesh(data: &[u8], settings: JsValue) -> Result<JsValue,
         _ => default_room_floor_recovery(),
     };
 
-    // Bake the canonical FAST NAV preset as the base layer so binary-only callers
-    // get upstream-correct floor extraction without copying the preset. Caller
-    // settings override the preset, and the per-step recovery patch overrides both
-    // (preset < caller settings < step patch).
-    let preset_obj = fast_nav_preset_json()
-        .as_object()
-        .cloned()
-        .unwrap_or_default();
     let base_obj = base_value.as_object().cloned().unwrap_or_default();
     let mut last_err: Option<mesh::RoomFloorError> = None;
     let mut attempted: Vec<String> = Vec::new();
@@ -947,40 +2536,66 @@ pub fn build_room_floor_mesh(data: &[u8], settings: JsValue) -> Result<JsValue,
         let label = step.label.clone().unwrap_or_else(|| format!("step{}", i));
         let min_area = step.min_room_floor_area.unwrap_or(base_min_area);
 
-        let mut merged = preset_obj.clone();
-        for (k, v) in &base_obj {
-            merged.insert(k.clone(), v.clone());
-        }
-        if let Some(serde_json::Value::Object(patch)) = &step.settings {
-            for (k, v) in patch {
-                merged.insert(k.clone(), v.clone());
-            }
-        }
-        let settings: MeshSettings = serde_json::from_value(serde_json::Value::Object(merged))
-            .map_err(|e| JsValue::from_str(&format!("Invalid room-floor settings: {}", e)))?;
-
-        let splats = parse_splats(data, &settings)?;
-        match mesh::extract_room_floor(&splats, &settings, min_area, &label) {
+        let (merged, settings) = room_floor_step_settings(&base_obj, step)?;
+
+        let (splats, splat_count) = source(&settings)?;
+        // A panicking step fails like any other and the ladder moves on.
+        let attempt = stage::run("room_floor", || {
+            mesh::extract_room_floor(&splats, splat_count, &settings, min_area, &label)
+        })
+        .unwrap_or_else(|panic| {
+            Err(mesh::RoomFloorError {
+                reason: "panic".to_string(),
+                message: panic.message,
+                area: 0.0,
+                components: 0,
+            })
+        });
+        match attempt {
             Ok(build) => {
                 // Convert geometry to the requested output space (if any) BEFORE
                 // generating the GLB, so both the mesh buffers and the GLB bytes
                 // agree on a single coordinate convention.
                 let mut mesh = MeshBuffers::new(build.positions, build.indices);
+                mesh.vertex_alpha = build.vertex_alpha;
+                mesh.vertex_confidence = build.vertex_confidence;
+                mesh.uvs = build.uvs;
                 let mut basis = build.basis;
                 let mut floor_plane = build.floor_plane;
+                let mut void_mesh = build.void_mesh.map(|v| VoidMesh {
+                    name: v.name,
+                    mesh: MeshBuffers::new(v.positions, v.indices),
+                    glb: None,
+                    depth: v.depth,
+                    open_boundary_edges: v.open_boundary_edges,
+                });
                 let mut space = CoordinateSpace::splatwalk_oriented();
+                let mut ground = frame::GroundFrame::new(&settings, None, None);
                 if let Some(transform) = output_space::transform_for(&settings) {
                     output_space::apply_mesh_buffers(&transform, &mut mesh);
+                    if let Some(v) = void_mesh.as_mut() {
+                        output_space::apply_mesh_buffers(&transform, &mut v.mesh);
+                    }
                     output_space::apply_basis(&transform, &mut basis);
                     output_space::apply_floor_plane(&transform, &mut floor_plane);
+                    output_space::apply_ground_frame(&transform, &mut ground);
                     space = transform.coordinate_space();
                 }
+                let source_mesh = settings
+                    .emit_source_mesh
+                    .unwrap_or(false)
+                    .then(|| frame::mesh_to_splat(&ground, &mesh));
                 let glb = if emit_glb {
                     soft_emit_glb(&mesh.vertices, &mesh.indices)
                 } else {
                     None
                 };
-                let result = RoomFloorMeshResult {
+                if emit_glb {
+                    if let Some(v) = void_mesh.as_mut() {
+                        v.glb = soft_emit_glb(&v.mesh.vertices, &v.mesh.indices);
+                    }
+                }
+                let mut result = RoomFloorMeshResult {
                     api_version: API_VERSION,
                     semver: core_semver(),
                     capabilities: capabilities(),
@@ -998,10 +2613,53 @@ pub fn build_room_floor_mesh(data: &[u8], settings: JsValue) -> Result<JsValue,
                     fallback_used: build.fallback_used,
                     step_label: build.step_label,
                     diagnostics: build.diagnostics,
+                    backprojection: build.backprojection,
+                    void_mesh,
+                    face_cells: build.face_cells,
+                    heightfield: build.heightfield,
+                    path_costs: build.path_costs,
+                    walkability_texture: build.walkability_texture,
+                    source_mesh,
+                    manifest: None,
                 };
-                return Ok(serde_wasm_bindgen::to_value(&result)?);
+                if settings.emit_manifest.unwrap_or(false) {
+                    let bytes = stage::run("manifest", || manifest::room_floor(&result, &merged))?
+                        .map_err(SplatwalkError::ReconstructionFailed)?;
+                    result.manifest = Some(serde_bytes::ByteBuf::from(bytes));
+                }
+                let typed = TypedMesh::take(&settings, &mut result.mesh);
+                let typed_void = result
+                    .void_mesh
+                    .as_mut()
+                    .and_then(|v| TypedMesh::take(&settings, &mut v.mesh));
+                let typed_source = result
+                    .source_mesh
+                    .as_mut()
+                    .and_then(|mesh| TypedMesh::take(&settings, mesh));
+                let value = serde_wasm_bindgen::to_value(&result)?;
+                if let Some(typed) = typed {
+                    typed.attach(&value, &["mesh"])?;
+                }
+                if let Some(typed) = typed_void {
+                    typed.attach(&value, &["void_mesh", "mesh"])?;
+                }
+                if let Some(typed) = typed_source {
+                    typed.attach(&value, &["source_mesh"])?;
+                }
+                return Ok(value);
             }
             Err(e) => {
+                journal::record(
+                    "room_floor",
+                    journal::JournalKind::Degraded,
+                    format!("Recovery step {} failed: {}", label, e.message),
+                    serde_json::json!({
+                        "step": label,
+                        "reason": e.reason,
+                        "selected_area": e.area,
+                        "component_count": e.components,
+                    }),
+                );
                 attempted.push(format!("{}({})", label, e.reason));
                 last_err = Some(e);
             }
@@ -1014,6 +2672,7 @@ pub fn build_room_floor_mesh(data: &[u8], settings: JsValue) -> Result<JsValue,
     let failure = match last_err {
         Some(e) => RoomFloorFailure {
             api_version: API_VERSION,
+            code: "reconstruction_failed",
             reason: e.reason,
             message: format!(
                 "FAST NAV floor extraction failed after {} step(s): {}. {}",
@@ -1028,6 +2687,7 @@ pub fn build_room_floor_mesh(data: &[u8], settings: JsValue) -> Result<JsValue,
         },
         None => RoomFloorFailure {
             api_version: API_VERSION,
+            code: "reconstruction_failed",
             reason: "no_steps".to_string(),
             message: "FAST NAV recovery had no configured steps.".to_string(),
             attempted,
@@ -1040,13 +2700,15 @@ pub fn build_room_floor_mesh(data: &[u8], settings: JsValue) -> Result<JsValue,
 }
 
 /// Structured failure returned (as a rejected/thrown value) by
-/// `build_room_floor_mesh`. `reason` is a stable machine code
-/// (`no_component` / `too_small` / `empty_mesh` / `no_steps`); `message` is the
-/// human-readable summary; `attempted` lists each recovery step and the reason
-/// it failed.
+/// `build_room_floor_mesh`. `code` is always `reconstruction_failed` (the
+/// [`SplatwalkError`] code) and `reason` is the finer stable machine code
+/// (`no_component` / `too_small` / `empty_mesh` / `no_steps` / `panic`);
+/// `message` is the human-readable summary; `attempted` lists each recovery
+/// step and the reason it failed.
 #[derive(Serialize)]
 struct RoomFloorFailure {
     api_version: u8,
+    code: &'static str,
     reason: String,
     message: String,
     attempted: Vec<String>,
@@ -1088,7 +2750,8 @@ fn parse_slice_settings(settings: JsValue) -> Result<SliceSettings, JsValue> {
     if settings.is_undefined() || settings.is_null() {
         return Ok(SliceSettings::default());
     }
-    serde_wasm_bindgen::from_value(settings).map_err(|e| JsValue::from_str(&e.to_string()))
+    serde_wasm_bindgen::from_value(settings)
+        .map_err(|e| SplatwalkError::InvalidSettings(e.to_string()).into())
 }
 
 /// Slice a `.ply`/`.spz` splat into a streamed-SOG bundle: a `lod-meta.json`
@@ -1096,18 +2759,21 @@ fn parse_slice_settings(settings: JsValue) -> Result<SliceSettings, JsValue> {
 /// to encode to lossless WebP. Follows Babylon PR #18563's streaming layout.
 #[wasm_bindgen]
 pub fn slice_splat(data: &[u8], settings: JsValue) -> Result<JsValue, JsValue> {
+    journal::begin("slice_splat");
     let settings = parse_slice_settings(settings)?;
     let params = settings.to_params();
-    let cloud = splat::parse_full_cloud(data).map_err(|e| JsValue::from_str(&e))?;
-    log(&format!(
-        "Slicing {} splats (SH degree {}, {} LOD level(s), ~{} splats/chunk)",
-        cloud.len(),
-        params.sh_degree,
-        params.lod_levels,
-        params.chunk_count
-    ));
-    let manifest = slice::slice(&cloud, &params).map_err(|e| JsValue::from_str(&e))?;
-    log(&format!("Sliced into {} chunk(s)", manifest.chunk_count));
+    let cloud = parse_error(data, splat::parse_full_cloud(data))?;
+    events::emit(Event::SliceStarted {
+        splats: cloud.len(),
+        sh_degree: params.sh_degree,
+        lod_levels: params.lod_levels,
+        splats_per_chunk: params.chunk_count,
+    });
+    let manifest = stage::run("slice", || slice::slice(&cloud, &params))?
+        .map_err(SplatwalkError::ReconstructionFailed)?;
+    events::emit(Event::SliceFinished {
+        chunks: manifest.chunk_count,
+    });
     Ok(serde_wasm_bindgen::to_value(&manifest)?)
 }
 
@@ -1117,14 +2783,16 @@ pub fn slice_splat(data: &[u8], settings: JsValue) -> Result<JsValue, JsValue> {
 pub fn convert_to_sog(data: &[u8], settings: JsValue) -> Result<JsValue, JsValue> {
     let settings = parse_slice_settings(settings)?;
     let params = settings.to_params();
-    let cloud = splat::parse_full_cloud(data).map_err(|e| JsValue::from_str(&e))?;
-    let manifest = slice::encode_single(
-        &cloud,
-        params.sh_degree,
-        params.sh_cluster_count,
-        params.sh_iterations,
-    )
-    .map_err(|e| JsValue::from_str(&e))?;
+    let cloud = parse_error(data, splat::parse_full_cloud(data))?;
+    let manifest = stage::run("sog", || {
+        slice::encode_single(
+            &cloud,
+            params.sh_degree,
+            params.sh_cluster_count,
+            params.sh_iterations,
+        )
+    })?
+    .map_err(SplatwalkError::ReconstructionFailed)?;
     Ok(serde_wasm_bindgen::to_value(&manifest)?)
 }
 
@@ -1133,7 +2801,7 @@ pub fn convert_to_sog(data: &[u8], settings: JsValue) -> Result<JsValue, JsValue
 /// by normalizing everything to PLY for the viewer and nav pipeline.
 #[wasm_bindgen]
 pub fn spz_to_ply(data: &[u8]) -> Result<Vec<u8>, JsValue> {
-    let cloud = splat::parse_full_cloud(data).map_err(|e| JsValue::from_str(&e))?;
+    let cloud = parse_error(data, splat::parse_full_cloud(data))?;
     Ok(splat::write_ply(&cloud))
 }
 
@@ -1142,7 +2810,7 @@ pub fn spz_to_ply(data: &[u8]) -> Result<Vec<u8>, JsValue> {
 /// 0. Normalizes `.splat` input to PLY for the viewer and nav pipeline.
 #[wasm_bindgen]
 pub fn splat_to_ply(data: &[u8]) -> Result<Vec<u8>, JsValue> {
-    let cloud = splat::parse_splat_buffer(data).map_err(|e| JsValue::from_str(&e))?;
+    let cloud = splat::parse_splat_buffer(data).map_err(SplatwalkError::ParseError)?;
     Ok(splat::write_ply(&cloud))
 }
 
@@ -1152,20 +2820,302 @@ pub fn splat_to_ply(data: &[u8]) -> Result<Vec<u8>, JsValue> {
 /// triplets; `indices` are `u32` triangle indices.
 #[wasm_bindgen]
 pub fn mesh_to_glb(positions: &[f32], indices: &[u32]) -> Result<Vec<u8>, JsValue> {
-    glb::mesh_to_glb(positions, indices).map_err(|e| JsValue::from_str(&e))
+    glb::mesh_to_glb(positions, indices).map_err(|e| SplatwalkError::ReconstructionFailed(e).into())
+}
+
+/// [`mesh_to_glb`] with options: `{ normals?: boolean, name?: string }`.
+/// `normals` adds angle-weighted vertex normals as a `NORMAL` attribute; `name`
+/// labels the glTF mesh and node. Omitted options match `mesh_to_glb` exactly.
+#[wasm_bindgen]
+pub fn mesh_to_glb_with_options(
+    positions: &[f32],
+    indices: &[u32],
+    options: JsValue,
+) -> Result<Vec<u8>, JsValue> {
+    let options: glb::GlbOptions = if options.is_undefined() || options.is_null() {
+        glb::GlbOptions::default()
+    } else {
+        serde_wasm_bindgen::from_value(options)
+            .map_err(|e| SplatwalkError::InvalidSettings(e.to_string()))?
+    };
+    glb::mesh_to_glb_with(positions, indices, &options)
+        .map_err(|e| SplatwalkError::ReconstructionFailed(e).into())
+}
+
+/// Serialize a result's `mesh` (`{ vertices, indices, normals?, colors?, uvs? }`)
+/// as Wavefront OBJ text, so the navmesh can be downloaded as `.obj` without a
+/// JS exporter. `options`: `{ normals?, colors?, uvs?, name? }`. `normals` adds
+/// `vn` lines (the mesh's normals, else computed), `uvs` adds `vt` lines, and
+/// `colors` appends RGB to each `v` line. Omitted options write positions and
+/// faces only.
+#[wasm_bindgen]
+pub fn export_obj(mesh: JsValue, options: JsValue) -> Result<String, JsValue> {
+    let mesh: obj::ObjMesh = serde_wasm_bindgen::from_value(mesh)
+        .map_err(|e| SplatwalkError::InvalidSettings(e.to_string()))?;
+    let options: obj::ObjOptions = if options.is_undefined() || options.is_null() {
+        obj::ObjOptions::default()
+    } else {
+        serde_wasm_bindgen::from_value(options)
+            .map_err(|e| SplatwalkError::InvalidSettings(e.to_string()))?
+    };
+    obj::mesh_to_obj(&mesh, &options).map_err(|e| SplatwalkError::ReconstructionFailed(e).into())
+}
+
+/// Serialize a result's `mesh` (`{ vertices, indices, normals?, colors? }`) as
+/// Stanford PLY bytes for MeshLab / CloudCompare. `options`: `{ format?,
+/// normals?, colors? }`. `format` is `"binary_little_endian"` (default) or
+/// `"ascii"`; `normals` adds `nx ny nz` (the mesh's normals, else computed)
+/// and `colors` adds `red green blue` bytes.
+#[wasm_bindgen]
+pub fn export_ply(mesh: JsValue, options: JsValue) -> Result<Vec<u8>, JsValue> {
+    let mesh: obj::ObjMesh = serde_wasm_bindgen::from_value(mesh)
+        .map_err(|e| SplatwalkError::InvalidSettings(e.to_string()))?;
+    let options: ply_export::PlyOptions = if options.is_undefined() || options.is_null() {
+        ply_export::PlyOptions::default()
+    } else {
+        serde_wasm_bindgen::from_value(options)
+            .map_err(|e| SplatwalkError::InvalidSettings(e.to_string()))?
+    };
+    ply_export::mesh_to_ply(&mesh, &options)
+        .map_err(|e| SplatwalkError::ReconstructionFailed(e).into())
+}
+
+/// Serialize a result's `mesh` (`{ vertices, indices, normals?, colors?, uvs? }`)
+/// as a `.babylon` JSON scene for the Babylon sandbox or `SceneLoader`.
+/// `options`: `{ space?, name?, colors?, uvs?, check_collisions?, hidden?,
+/// metadata? }`. `space` is the result's `space`; the scene is left-handed
+/// with the mesh in `babylon-lh`. Normals are always written (the mesh's,
+/// else computed).
+#[wasm_bindgen]
+pub fn export_babylon(mesh: JsValue, options: JsValue) -> Result<String, JsValue> {
+    let mesh: obj::ObjMesh = serde_wasm_bindgen::from_value(mesh)
+        .map_err(|e| SplatwalkError::InvalidSettings(e.to_string()))?;
+    let options: babylon::BabylonOptions = if options.is_undefined() || options.is_null() {
+        babylon::BabylonOptions::default()
+    } else {
+        serde_wasm_bindgen::from_value(options)
+            .map_err(|e| SplatwalkError::InvalidSettings(e.to_string()))?
+    };
+    babylon::mesh_to_babylon(&mesh, &options)
+        .map_err(|e| SplatwalkError::ReconstructionFailed(e).into())
+}
+
+/// Decode a project blob from `SplatSession.save_project` without restoring
+/// it: `{ version, sources, settings, heightfields, host_data }`. Lets a host
+/// see which source files (`name`, `byte_length`, `hash`) to fetch before
+/// calling `load_project`.
+#[wasm_bindgen]
+pub fn read_project(blob: &[u8]) -> Result<JsValue, JsValue> {
+    let project = project::decode(blob).map_err(SplatwalkError::InvalidSettings)?;
+    session::project_to_value(&project)
+}
+
+/// `read_bake_manifest` payload.
+#[derive(Serialize)]
+struct BakeManifestContents {
+    manifest: manifest::ManifestIndex,
+    files: std::collections::BTreeMap<String, serde_bytes::ByteBuf>,
+}
+
+/// Unpack the `manifest` archive of an `emit_manifest` bake:
+/// `{ manifest, files }`, with `manifest` the parsed `manifest.json` index and
+/// `files` every entry by name as a `Uint8Array`.
+#[wasm_bindgen]
+pub fn read_bake_manifest(bytes: &[u8]) -> Result<JsValue, JsValue> {
+    let (index, files) = manifest::read(bytes).map_err(SplatwalkError::InvalidSettings)?;
+    let contents = BakeManifestContents {
+        manifest: index,
+        files: files
+            .into_iter()
+            .map(|(name, bytes)| (name, serde_bytes::ByteBuf::from(bytes)))
+            .collect(),
+    };
+    let serializer = serde_wasm_bindgen::Serializer::new().serialize_maps_as_objects(true);
+    Ok(contents.serialize(&serializer)?)
+}
+
+/// Identify a saved artifact (project blob, bake manifest or
+/// `NavMeshQuery.save` bytes) without loading it:
+/// `{ kind, container_version, version, current_version, legacy, readable,
+/// chunks }`, with `chunks` as `{ tag, required, byte_length }`. `readable`
+/// is false for artifacts from a newer build.
+#[wasm_bindgen]
+pub fn inspect_artifact(bytes: &[u8]) -> Result<JsValue, JsValue> {
+    let info = artifact::inspect(bytes).map_err(SplatwalkError::InvalidSettings)?;
+    let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+    Ok(info.serialize(&serializer)?)
+}
+
+/// Rewrite a saved artifact in the current container and schema version,
+/// migrating older versions and rewrapping the pre-container project blob and
+/// manifest zip. Loaders read old versions anyway; this lets a host upgrade
+/// stored files once. Errors on artifacts from a newer build.
+#[wasm_bindgen]
+pub fn migrate_artifact(bytes: &[u8]) -> Result<Vec<u8>, JsValue> {
+    let info = artifact::inspect(bytes).map_err(SplatwalkError::InvalidSettings)?;
+    let migrated = match info.kind.as_str() {
+        "project" => project::decode(bytes).and_then(|p| project::encode(&p)),
+        "bake_manifest" => manifest::migrate(bytes),
+        "navmesh_query" => return nav_query::NavMeshQuery::load(bytes)?.save(),
+        other => Err(format!("Unknown artifact kind {}", other)),
+    };
+    Ok(migrated.map_err(SplatwalkError::InvalidSettings)?)
+}
+
+/// `bake_journal()` payload: the journal plus the usual version fields.
+#[derive(Serialize)]
+struct BakeJournalResult {
+    api_version: u8,
+    semver: String,
+    #[serde(flatten)]
+    journal: journal::Journal,
+}
+
+/// The decision journal of the most recent bake under the current context:
+/// `{ api_version, semver, entry_point, entries }`. Each entry is
+/// `{ seq, stage, kind, message, data }` with `kind` one of `decision`,
+/// `clamped`, `degraded` or `dropped`. Plain objects throughout, so
+/// `JSON.stringify(bake_journal())` is a complete trace for a bug report.
+#[wasm_bindgen]
+pub fn bake_journal() -> Result<JsValue, JsValue> {
+    let result = BakeJournalResult {
+        api_version: API_VERSION,
+        semver: core_semver(),
+        journal: journal::snapshot(),
+    };
+    let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+    Ok(result.serialize(&serializer)?)
+}
+
+/// `reset_module()` report.
+#[derive(Serialize)]
+struct ResetReport {
+    api_version: u8,
+    semver: String,
+    /// Sessions whose sources were dropped.
+    sessions_released: usize,
+    /// Allocated bytes handed back to the allocator by the reset; `None`
+    /// without the `alloc_stats` feature.
+    freed_bytes: Option<usize>,
+    /// Heap in use after the reset, as in `memory_stats`.
+    allocated_bytes: usize,
+    /// False when some state is still held by a call that trapped
+    /// mid-update; reload the WASM module in that case.
+    complete: bool,
+    /// The state that could not be reset: `sessions`, `jobs`, `journal`,
+    /// `progress_callback`.
+    locked: Vec<&'static str>,
+}
+
+/// Return the module to its just-loaded state without reloading the binary:
+/// empty every `SplatSession` (handles stay valid but have no sources), drop
+/// all jobs and their caches, clear the bake journal and the progress
+/// callback, and restart peak tracking. Lets a host recover from an
+/// out-of-memory or fatal error in milliseconds. Linear memory keeps its size,
+/// but the freed space is reused by the next bake. `FloorEditor`s are plain
+/// values owned by JS and are left alone. Only the current context's state is
+/// reset; other `SplatwalkContext` handles keep theirs.
+#[wasm_bindgen]
+pub fn reset_module() -> Result<JsValue, JsValue> {
+    let before = memory::allocated_bytes();
+    let counted = cfg!(feature = "alloc_stats");
+    let mut locked = Vec::new();
+    let (sessions_released, sessions_locked) = session::release_all();
+    if sessions_locked > 0 {
+        locked.push("sessions");
+    }
+    if !memory::try_reset() {
+        locked.push("jobs");
+    }
+    if !journal::try_clear() {
+        locked.push("journal");
+    }
+    if !face_filter::try_clear() {
+        locked.push("face_filter");
+    }
+    let callback_cleared = match context::current().progress.try_borrow_mut() {
+        Ok(mut cb) => {
+            *cb = None;
+            true
+        }
+        Err(_) => false,
+    };
+    if !callback_cleared {
+        locked.push("progress_callback");
+    }
+    let after = memory::allocated_bytes();
+    let freed_bytes = counted.then(|| before.saturating_sub(after));
+    log(&format!(
+        "reset_module: released {} session(s){}",
+        sessions_released,
+        freed_bytes.map_or_else(String::new, |freed| format!(", freed {} bytes", freed))
+    ));
+    let report = ResetReport {
+        api_version: API_VERSION,
+        semver: core_semver(),
+        sessions_released,
+        freed_bytes,
+        allocated_bytes: after,
+        complete: locked.is_empty(),
+        locked,
+    };
+    Ok(serde_wasm_bindgen::to_value(&report)?)
 }
 
 /// Optional GLB for `emit_glb`: skip empty meshes instead of failing the whole build.
 fn soft_emit_glb(vertices: &[f32], indices: &[u32]) -> Option<serde_bytes::ByteBuf> {
     if vertices.is_empty() || indices.is_empty() {
         log("emit_glb skipped: empty mesh (caller can synthesize GLB from volume)");
+        journal::record(
+            "glb",
+            journal::JournalKind::Degraded,
+            "emit_glb skipped: empty mesh".to_string(),
+            serde_json::json!({ "reason": "empty_mesh" }),
+        );
         return None;
     }
-    match glb::mesh_to_glb(vertices, indices) {
-        Ok(bytes) => Some(serde_bytes::ByteBuf::from(bytes)),
-        Err(err) => {
+    match stage::run("glb", || glb::mesh_to_glb(vertices, indices)) {
+        Ok(Ok(bytes)) => Some(serde_bytes::ByteBuf::from(bytes)),
+        // Already journaled by `stage::run`.
+        Err(panic) => {
+            log(&format!("emit_glb skipped: {}", panic.message));
+            None
+        }
+        Ok(Err(err)) => {
             log(&format!("emit_glb skipped: {}", err));
+            journal::record(
+                "glb",
+                journal::JournalKind::Degraded,
+                format!("emit_glb skipped: {}", err),
+                serde_json::json!({ "reason": err }),
+            );
             None
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn room_floor_steps_are_validated_after_merging() {
+        let base = serde_json::Map::new();
+        for step in default_room_floor_recovery() {
+            assert!(room_floor_step_settings(&base, &step).is_ok());
+        }
+        let step = RoomFloorStepCfg {
+            label: None,
+            settings: Some(serde_json::json!({
+                "regions": [{ "mode": "exlude", "shape": "sphere", "center": [0, 0, 0], "radius": 1 }]
+            })),
+            min_room_floor_area: None,
+        };
+        match room_floor_step_settings(&base, &step) {
+            Err(SplatwalkError::InvalidSettings(message)) => {
+                assert!(message.starts_with("regions[0].mode"), "{}", message)
+            }
+            _ => panic!("a malformed step rule was accepted"),
+        }
+    }
+}
@@ -0,0 +1,578 @@
+//! Bake manifests (`emit_manifest`): every artifact of one bake in one file.
+//!
+//! A pipeline that keeps a bake used to save the GLB, the sidecar JSON
+//! (navmesh polygons, heightfield, path costs, ...), the diagnostics, the
+//! settings and the journal one by one, and lost track of which files went
+//! together. With `emit_manifest` the result also carries `manifest`: a zip
+//! archive holding each artifact under a fixed name plus `manifest.json`, an
+//! index of the files with the versions and coordinate space they were baked
+//! with. The zip is the one chunk of a `BAKE` artifact (see `artifact`), with
+//! offsets counted from the start of the file, so any unzip tool still opens
+//! it; [`read`] unpacks it back into named buffers, and also reads the bare
+//! zips written before the container.
+//!
+//! Entries are deflated, named in UTF-8 and stamped 1980-01-01, so the same
+//! bake always produces the same bytes. ZIP64 is not written: each entry and
+//! the whole archive stay under 4 GiB.
+
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::{Compression, Crc};
+use serde::{Deserialize, Serialize};
+
+use crate::artifact::{self, Artifact};
+use crate::glb::{self, GlbOptions};
+use crate::occupancy;
+use crate::{CoordinateSpace, MeshBuffers, ReconstructionResult, RoomFloorMeshResult};
+
+/// Current manifest format version. Readers accept this version and older.
+pub const MANIFEST_VERSION: u32 = 1;
+
+const ZIP_CHUNK: [u8; 4] = *b"ZIP ";
+
+/// Name of the index entry, always the first in the archive.
+pub const INDEX_NAME: &str = "manifest.json";
+
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const END_OF_DIRECTORY: u32 = 0x0605_4b50;
+/// General purpose flag bit 11: names are UTF-8.
+const UTF8_NAMES: u16 = 0x0800;
+/// MS-DOS date of 1980-01-01, the earliest a zip can hold.
+const EPOCH_DATE: u16 = (1 << 5) | 1;
+
+/// `manifest.json`: what the archive holds and how it was baked.
+#[derive(Serialize, Deserialize)]
+pub struct ManifestIndex {
+    pub format: String,
+    pub version: u32,
+    pub api_version: u8,
+    pub semver: String,
+    /// The entry point that baked the artifacts, e.g. `convert_splat_to_mesh`.
+    pub entry_point: String,
+    /// Space of every mesh and point in the archive.
+    pub space: serde_json::Value,
+    pub files: Vec<ManifestFile>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ManifestFile {
+    pub name: String,
+    pub media_type: String,
+    pub byte_length: usize,
+}
+
+/// Artifacts collected for one archive.
+pub struct Manifest {
+    entry_point: &'static str,
+    space: serde_json::Value,
+    files: Vec<(String, &'static str, Vec<u8>)>,
+}
+
+impl Manifest {
+    pub fn new(entry_point: &'static str, space: &CoordinateSpace) -> Self {
+        Manifest {
+            entry_point,
+            space: serde_json::to_value(space).unwrap_or_default(),
+            files: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, name: &str, media_type: &'static str, bytes: Vec<u8>) {
+        self.files.push((name.to_string(), media_type, bytes));
+    }
+
+    /// Add `value` as pretty-printed JSON. Non-finite floats become `null`.
+    pub fn add_json<T: Serialize + ?Sized>(&mut self, name: &str, value: &T) -> Result<(), String> {
+        let bytes = serde_json::to_vec_pretty(value).map_err(|e| format!("{}: {}", name, e))?;
+        self.add(name, "application/json", bytes);
+        Ok(())
+    }
+
+    /// Add `<stem>.glb` with vertex normals and, when the mesh carries any,
+    /// its per-vertex attributes (`colors`, `vertex_alpha`,
+    /// `vertex_confidence`, `uvs`, deviation) as `<stem>_attributes.json`. Empty meshes
+    /// are left out.
+    pub fn add_mesh(&mut self, stem: &str, mesh: &MeshBuffers) -> Result<(), String> {
+        if mesh.vertices.is_empty() || mesh.indices.is_empty() {
+            return Ok(());
+        }
+        let options = GlbOptions {
+            normals: true,
+            name: Some(stem.to_string()),
+        };
+        let bytes = glb::mesh_to_glb_with(&mesh.vertices, &mesh.indices, &options)?;
+        self.add(&format!("{}.glb", stem), "model/gltf-binary", bytes);
+
+        let mut attributes = serde_json::Map::new();
+        for (key, values) in [
+            ("colors", &mesh.colors),
+            ("vertex_alpha", &mesh.vertex_alpha),
+            ("vertex_confidence", &mesh.vertex_confidence),
+            ("uvs", &mesh.uvs),
+            ("vertex_deviation", &mesh.vertex_deviation),
+            ("deviation_colors", &mesh.deviation_colors),
+        ] {
+            if let Some(values) = values {
+                attributes.insert(key.to_string(), serde_json::json!(values));
+            }
+        }
+        if !attributes.is_empty() {
+            self.add_json(&format!("{}_attributes.json", stem), &attributes)?;
+        }
+        Ok(())
+    }
+
+    /// The archive: `manifest.json`, then the artifacts in the order added.
+    pub fn finish(self) -> Result<Vec<u8>, String> {
+        let index = ManifestIndex {
+            format: "splatwalk_bake".to_string(),
+            version: MANIFEST_VERSION,
+            api_version: crate::API_VERSION,
+            semver: crate::core_semver(),
+            entry_point: self.entry_point.to_string(),
+            space: self.space,
+            files: self
+                .files
+                .iter()
+                .map(|(name, media_type, bytes)| ManifestFile {
+                    name: name.clone(),
+                    media_type: media_type.to_string(),
+                    byte_length: bytes.len(),
+                })
+                .collect(),
+        };
+        let index = serde_json::to_vec_pretty(&index).map_err(|e| e.to_string())?;
+        let mut entries = vec![(INDEX_NAME.to_string(), index)];
+        entries.extend(self.files.into_iter().map(|(name, _, bytes)| (name, bytes)));
+        wrap(&entries)
+    }
+}
+
+/// `entries` as a `BAKE` artifact whose one chunk is their zip. The zip's
+/// offsets count from the start of the artifact, the way a self-extracting
+/// archive's do, so unzip tools open the artifact as it is.
+fn wrap(entries: &[(String, Vec<u8>)]) -> Result<Vec<u8>, String> {
+    let mut artifact = Artifact::new(&artifact::MANIFEST);
+    artifact.push(
+        ZIP_CHUNK,
+        true,
+        write_zip(entries, artifact::FIRST_PAYLOAD)?,
+    );
+    artifact.encode()
+}
+
+/// The archive of a `convert_splat_to_mesh` result (already in its output
+/// space). `settings` is the settings object as the host passed it.
+pub fn reconstruction(
+    result: &ReconstructionResult,
+    settings: &serde_json::Value,
+) -> Result<Vec<u8>, String> {
+    let mut manifest = Manifest::new("convert_splat_to_mesh", &result.space);
+    manifest.add_mesh("mesh", &result.mesh)?;
+    for (i, plane) in result.planes.iter().flatten().enumerate() {
+        manifest.add_mesh(&format!("plane_{}", i), &plane.mesh)?;
+    }
+    if let Some(unreachable) = &result.unreachable {
+        manifest.add_mesh("unreachable", &unreachable.mesh)?;
+    }
+    if let Some(source_mesh) = &result.source_mesh {
+        manifest.add_mesh("source_mesh", source_mesh)?;
+    }
+    #[cfg(feature = "draco")]
+    if let Some(draco) = &result.draco {
+        manifest.add("mesh.drc", "application/octet-stream", draco.bytes.to_vec());
+    }
+    manifest.add_json("frame.json", &result.frame)?;
+    if let Some(navmesh) = &result.navmesh {
+        manifest.add_json("navmesh.json", navmesh)?;
+    }
+    if let Some(bytes) = &result.detour_navmesh {
+        manifest.add("navmesh.bin", "application/octet-stream", bytes.to_vec());
+    }
+    if let Some(links) = &result.off_mesh_links {
+        manifest.add_json("off_mesh_links.json", links)?;
+    }
+    if let Some(layers) = &result.floor_layers {
+        manifest.add_json("floor_layers.json", layers)?;
+    }
+    if let Some(map) = &result.occupancy_grid {
+        manifest.add(
+            occupancy::IMAGE_NAME,
+            "image/x-portable-graymap",
+            map.pgm.to_vec(),
+        );
+        manifest.add(
+            "occupancy.yaml",
+            "application/yaml",
+            map.yaml.clone().into_bytes(),
+        );
+    }
+    if let Some(field) = &result.distance_field {
+        manifest.add_json("distance_field.json", field)?;
+    }
+    if let Some(field) = &result.flow_field {
+        manifest.add_json("flow_field.json", field)?;
+    }
+    if let Some(hazards) = &result.hazards {
+        manifest.add_json("hazards.json", hazards)?;
+    }
+    if let Some(costs) = &result.path_costs {
+        manifest.add_json("path_costs.json", costs)?;
+    }
+    if let Some(index) = &result.tiles {
+        // The index points at each tile's GLB instead of repeating its mesh.
+        let mut tiles = Vec::with_capacity(index.tiles.len());
+        for tile in &index.tiles {
+            let stem = format!("tile_{}_{}", tile.column, tile.row);
+            manifest.add_mesh(&stem, &tile.mesh)?;
+            tiles.push(serde_json::json!({
+                "column": tile.column,
+                "row": tile.row,
+                "min": tile.min,
+                "max": tile.max,
+                "mesh": format!("{}.glb", stem),
+                "faces": tile.faces,
+            }));
+        }
+        manifest.add_json(
+            "tiles.json",
+            &serde_json::json!({
+                "tile_size": index.tile_size,
+                "tile_border": index.tile_border,
+                "origin": index.origin,
+                "columns": index.columns,
+                "rows": index.rows,
+                "tiles": tiles,
+            }),
+        )?;
+    }
+    if let Some(thumbnail) = &result.thumbnail {
+        manifest.add("thumbnail.png", "image/png", thumbnail.png.to_vec());
+    }
+    if let Some(map) = &result.heightmap {
+        let (name, media_type) = match map.format.as_str() {
+            "raw" => ("heightmap.r16", "application/octet-stream"),
+            _ => ("heightmap.png", "image/png"),
+        };
+        manifest.add(name, media_type, map.data.to_vec());
+        manifest.add_json(
+            "heightmap.json",
+            &serde_json::json!({
+                "file": name,
+                "width": map.width,
+                "height": map.height,
+                "cell_size": map.cell_size,
+                "min": map.min,
+                "max": map.max,
+            }),
+        )?;
+    }
+    if let Some(partial) = &result.partial {
+        manifest.add_json("partial.json", partial)?;
+    }
+    add_provenance(&mut manifest, &result.diagnostics, settings)?;
+    manifest.finish()
+}
+
+/// The archive of a `build_room_floor_mesh` result (already in its output
+/// space). `settings` are the merged settings of the recovery step that won.
+pub fn room_floor(
+    result: &RoomFloorMeshResult,
+    settings: &serde_json::Value,
+) -> Result<Vec<u8>, String> {
+    let mut manifest = Manifest::new("build_room_floor_mesh", &result.space);
+    manifest.add_mesh("mesh", &result.mesh)?;
+    if let Some(void_mesh) = &result.void_mesh {
+        manifest.add_mesh(&void_mesh.name, &void_mesh.mesh)?;
+    }
+    if let Some(source_mesh) = &result.source_mesh {
+        manifest.add_mesh("source_mesh", source_mesh)?;
+    }
+    manifest.add_json(
+        "floor.json",
+        &serde_json::json!({
+            "basis": result.basis,
+            "floor_plane": result.floor_plane,
+            "selected_area": result.selected_area,
+            "component_count": result.component_count,
+            "selected_cell_count": result.selected_cell_count,
+            "accepted_cell_count": result.accepted_cell_count,
+            "obstacle_cell_count": result.obstacle_cell_count,
+            "rejected_cell_count": result.rejected_cell_count,
+            "fallback_used": result.fallback_used,
+            "step_label": result.step_label,
+        }),
+    )?;
+    if let Some(heightfield) = &result.heightfield {
+        manifest.add_json("heightfield.json", heightfield)?;
+    }
+    if let Some(texture) = &result.walkability_texture {
+        // Binary PGM: the grayscale texels behind a one-line header.
+        let mut pgm = format!("P5\n{} {}\n255\n", texture.width, texture.height).into_bytes();
+        pgm.extend_from_slice(&texture.pixels);
+        manifest.add("walkability.pgm", "image/x-portable-graymap", pgm);
+    }
+    if let Some(cells) = &result.face_cells {
+        manifest.add_json("face_cells.json", cells)?;
+    }
+    if let Some(costs) = &result.path_costs {
+        manifest.add_json("path_costs.json", costs)?;
+    }
+    if let Some(backprojection) = &result.backprojection {
+        manifest.add_json("backprojection.json", backprojection)?;
+    }
+    add_provenance(&mut manifest, &result.diagnostics, settings)?;
+    manifest.finish()
+}
+
+/// Diagnostics, settings and the bake journal, which every archive carries.
+fn add_provenance<T: Serialize>(
+    manifest: &mut Manifest,
+    diagnostics: &T,
+    settings: &serde_json::Value,
+) -> Result<(), String> {
+    manifest.add_json("diagnostics.json", diagnostics)?;
+    manifest.add_json("settings.json", settings)?;
+    manifest.add_json("journal.json", &crate::journal::snapshot())
+}
+
+fn u32_of(value: usize, what: &str) -> Result<u32, String> {
+    u32::try_from(value).map_err(|_| format!("{} exceeds the 4 GiB zip limit", what))
+}
+
+/// A zip of `entries`, each deflated, with offsets counted from `base` bytes
+/// before the zip (0 for a standalone file).
+pub fn write_zip(entries: &[(String, Vec<u8>)], base: usize) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    let mut directory = Vec::new();
+    for (name, bytes) in entries {
+        let mut crc = Crc::new();
+        crc.update(bytes);
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(bytes).map_err(|e| e.to_string())?;
+        let packed = encoder.finish().map_err(|e| e.to_string())?;
+
+        let offset = u32_of(base + out.len(), "archive")?;
+        let sizes = [
+            crc.sum(),
+            u32_of(packed.len(), name)?,
+            u32_of(bytes.len(), name)?,
+        ];
+        let name_length =
+            u16::try_from(name.len()).map_err(|_| format!("{}: name too long", name))?;
+        // Fields shared by the local and central headers, from "version
+        // needed" to the name length.
+        let mut common = Vec::new();
+        for half in [20, UTF8_NAMES, 8, 0, EPOCH_DATE] {
+            common.extend_from_slice(&u16::to_le_bytes(half));
+        }
+        for word in sizes {
+            common.extend_from_slice(&word.to_le_bytes());
+        }
+        common.extend_from_slice(&name_length.to_le_bytes());
+
+        out.extend_from_slice(&LOCAL_HEADER.to_le_bytes());
+        out.extend_from_slice(&common);
+        out.extend_from_slice(&0u16.to_le_bytes());
+        out.extend_from_slice(name.as_bytes());
+        out.extend_from_slice(&packed);
+
+        directory.extend_from_slice(&CENTRAL_HEADER.to_le_bytes());
+        directory.extend_from_slice(&20u16.to_le_bytes());
+        directory.extend_from_slice(&common);
+        // Extra and comment lengths, disk, internal and external attributes.
+        directory.extend_from_slice(&[0; 12]);
+        directory.extend_from_slice(&offset.to_le_bytes());
+        directory.extend_from_slice(name.as_bytes());
+    }
+    let count = u16::try_from(entries.len()).map_err(|_| "too many zip entries".to_string())?;
+    let directory_offset = u32_of(base + out.len(), "archive")?;
+    let directory_size = u32_of(directory.len(), "archive")?;
+    out.extend_from_slice(&directory);
+    out.extend_from_slice(&END_OF_DIRECTORY.to_le_bytes());
+    out.extend_from_slice(&[0; 4]);
+    out.extend_from_slice(&count.to_le_bytes());
+    out.extend_from_slice(&count.to_le_bytes());
+    out.extend_from_slice(&directory_size.to_le_bytes());
+    out.extend_from_slice(&directory_offset.to_le_bytes());
+    out.extend_from_slice(&0u16.to_le_bytes());
+    Ok(out)
+}
+
+/// The entries of a zip, by name. Reads stored and deflated entries and
+/// checks their CRCs, so it also opens archives repacked by other tools
+/// (without ZIP64 or encryption). Offsets are taken relative to where the
+/// directory actually sits, so zips cut out of a larger file read too.
+pub fn read_zip(bytes: &[u8]) -> Result<BTreeMap<String, Vec<u8>>, String> {
+    let u16_at = |at: usize| -> Result<usize, String> {
+        bytes
+            .get(at..at + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]) as usize)
+            .ok_or_else(|| "truncated zip".to_string())
+    };
+    let u32_at = |at: usize| -> Result<u32, String> {
+        bytes
+            .get(at..at + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .ok_or_else(|| "truncated zip".to_string())
+    };
+    // The end record sits behind a comment of at most 64 KiB.
+    let end = (0..bytes.len().saturating_sub(21))
+        .rev()
+        .take(0x10000)
+        .find(|&at| u32_at(at) == Ok(END_OF_DIRECTORY))
+        .ok_or("not a zip archive")?;
+    let count = u16_at(end + 10)?;
+    let mut at = end
+        .checked_sub(u32_at(end + 12)? as usize)
+        .ok_or("corrupt zip directory")?;
+    // How far every recorded offset is from where its data sits.
+    let shift = at as i64 - u32_at(end + 16)? as i64;
+
+    let mut entries = BTreeMap::new();
+    for _ in 0..count {
+        if u32_at(at)? != CENTRAL_HEADER {
+            return Err("corrupt zip directory".to_string());
+        }
+        let method = u16_at(at + 10)?;
+        let crc = u32_at(at + 16)?;
+        let packed_length = u32_at(at + 20)? as usize;
+        let length = u32_at(at + 24)? as usize;
+        let name_length = u16_at(at + 28)?;
+        let skip = name_length + u16_at(at + 30)? + u16_at(at + 32)?;
+        let local = usize::try_from(u32_at(at + 42)? as i64 + shift)
+            .map_err(|_| "corrupt zip directory".to_string())?;
+        let name = bytes
+            .get(at + 46..at + 46 + name_length)
+            .ok_or("truncated zip")?;
+        let name = String::from_utf8_lossy(name).into_owned();
+        at += 46 + skip;
+
+        if u32_at(local)? != LOCAL_HEADER {
+            return Err(format!("{}: corrupt zip entry", name));
+        }
+        let data = local + 30 + u16_at(local + 26)? + u16_at(local + 28)?;
+        let packed = bytes
+            .get(data..data + packed_length)
+            .ok_or_else(|| format!("{}: truncated zip entry", name))?;
+        let unpacked = match method {
+            0 => packed.to_vec(),
+            8 => {
+                let mut out = Vec::with_capacity(length);
+                DeflateDecoder::new(packed)
+                    .read_to_end(&mut out)
+                    .map_err(|e| format!("{}: {}", name, e))?;
+                out
+            }
+            other => return Err(format!("{}: unsupported zip method {}", name, other)),
+        };
+        let mut check = Crc::new();
+        check.update(&unpacked);
+        if unpacked.len() != length || check.sum() != crc {
+            return Err(format!("{}: zip entry is corrupt", name));
+        }
+        entries.insert(name, unpacked);
+    }
+    Ok(entries)
+}
+
+/// Unpack a manifest archive, or a bare zip from before the artifact
+/// container: its index and every file by name (including `manifest.json`
+/// itself).
+pub fn read(bytes: &[u8]) -> Result<(ManifestIndex, BTreeMap<String, Vec<u8>>), String> {
+    let files = if artifact::is_artifact(bytes) {
+        read_zip(artifact::decode(bytes, &artifact::MANIFEST)?.require(&ZIP_CHUNK)?)?
+    } else {
+        read_zip(bytes)?
+    };
+    let index = files
+        .get(INDEX_NAME)
+        .ok_or("not a bake manifest: manifest.json is missing")?;
+    let index: ManifestIndex =
+        serde_json::from_slice(index).map_err(|e| format!("{}: {}", INDEX_NAME, e))?;
+    if index.version > MANIFEST_VERSION {
+        return Err(format!(
+            "Bake manifest version {} is newer than this build reads ({})",
+            index.version, MANIFEST_VERSION
+        ));
+    }
+    Ok((index, files))
+}
+
+/// Rewrite an archive in the current format, e.g. a bare zip as an artifact.
+/// Entries keep the index's order.
+pub fn migrate(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let (index, mut files) = read(bytes)?;
+    let mut entries = Vec::with_capacity(files.len());
+    for name in std::iter::once(INDEX_NAME).chain(index.files.iter().map(|f| f.name.as_str())) {
+        if let Some(bytes) = files.remove(name) {
+            entries.push((name.to_string(), bytes));
+        }
+    }
+    entries.extend(files);
+    wrap(&entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn archives_round_trip_their_files() {
+        let mut manifest = Manifest::new(
+            "convert_splat_to_mesh",
+            &CoordinateSpace::splatwalk_oriented(),
+        );
+        let mut mesh = MeshBuffers::new(
+            vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0],
+            vec![0, 2, 1],
+        );
+        mesh.colors = Some(vec![1.0; 9]);
+        manifest.add_mesh("mesh", &mesh).unwrap();
+        manifest
+            .add_mesh("empty", &MeshBuffers::new(Vec::new(), Vec::new()))
+            .unwrap();
+        manifest
+            .add_json(
+                "settings.json",
+                &serde_json::json!({ "mode": 2, "cell": f64::NAN }),
+            )
+            .unwrap();
+        let bytes = manifest.finish().unwrap();
+        assert_eq!(&bytes[..4], artifact::MAGIC);
+
+        let (index, files) = read(&bytes).unwrap();
+        assert_eq!(index.entry_point, "convert_splat_to_mesh");
+        let names: Vec<&str> = index.files.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["mesh.glb", "mesh_attributes.json", "settings.json"]);
+        assert_eq!(files.len(), 4);
+        assert_eq!(&files["mesh.glb"][..4], b"glTF");
+        assert_eq!(files["mesh.glb"].len(), index.files[0].byte_length);
+        let settings: serde_json::Value = serde_json::from_slice(&files["settings.json"]).unwrap();
+        assert_eq!(settings["mode"], 2);
+        assert!(settings["cell"].is_null());
+
+        // Unzip tools see a zip with some leading bytes, and its offsets
+        // already account for them.
+        assert_eq!(read_zip(&bytes).unwrap(), files);
+
+        // A bare zip from before the container migrates into one.
+        let entries: Vec<(String, Vec<u8>)> = files.clone().into_iter().collect();
+        let bare = write_zip(&entries, 0).unwrap();
+        assert_eq!(read(&bare).unwrap().1, files);
+        let migrated = migrate(&bare).unwrap();
+        assert_eq!(&migrated[..4], artifact::MAGIC);
+        assert_eq!(read(&migrated).unwrap().1, files);
+
+        // A flipped byte in the packed data fails the CRCs.
+        let mut corrupt = bytes.clone();
+        corrupt[100] ^= 0xff;
+        assert!(read_zip(&corrupt).is_err());
+        assert!(read(&corrupt).is_err());
+        assert!(read(b"not a zip").is_err());
+    }
+}
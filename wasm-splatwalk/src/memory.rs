@@ -0,0 +1,444 @@
+//! Job-scoped buffers and heap accounting.
+//!
+//! WASM linear memory only ever grows, so a long editor session that bakes many
+//! large scans keeps whatever peak it reached. What the core keeps alive
+//! between calls is held per job rather than in a single global slot, so a
+//! host can drop one scan's buffers without losing another's. A job holds two
+//! kinds of buffer:
+//!
+//! - its parsed-splat cache, which a repeated bake refills in place;
+//! - a scratch pool for a bake's large working grids (the collision density
+//!   grid and the ground-field height profiles). A bake borrows a zeroed
+//!   grid with [`scratch_f64`], and the grid goes back to the job's pool when
+//!   the bake drops it, so the job's next bake reuses that capacity instead
+//!   of allocating a fresh grid.
+//!
+//! `free_job` / `reset` drop a job's cache and pool together, so the allocator
+//! can hand the space to whatever runs next.
+//!
+//! Job 0 is the implicit default used when a call carries no `job_id`; it
+//! always exists and behaves like the previous single-entry parse cache. The
+//! job table belongs to the current context handle (see [`crate::context`]),
+//! so job ids from one handle mean nothing to another.
+//!
+//! Live and peak allocated bytes come from a counting global allocator, which
+//! costs two atomic updates per allocation. It is only installed in builds with
+//! the `alloc_stats` cargo feature. Other builds report the linear-memory size
+//! in their place, an upper bound on what is allocated.
+
+use serde::Serialize;
+#[cfg(feature = "alloc_stats")]
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::ops::{Deref, DerefMut};
+#[cfg(feature = "alloc_stats")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+use wasm_bindgen::prelude::*;
+
+use crate::{context, splat, ParseCacheEntry, SplatwalkError};
+
+/// System allocator wrapper that tracks live and peak allocated bytes.
+#[cfg(feature = "alloc_stats")]
+struct CountingAllocator;
+
+#[cfg(feature = "alloc_stats")]
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+#[cfg(feature = "alloc_stats")]
+static PEAK_ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(feature = "alloc_stats")]
+fn record_alloc(size: usize) {
+    let live = ALLOCATED.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK_ALLOCATED.fetch_max(live, Ordering::Relaxed);
+}
+
+#[cfg(feature = "alloc_stats")]
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+            record_alloc(new_size);
+        }
+        new_ptr
+    }
+}
+
+#[cfg(feature = "alloc_stats")]
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Most scratch grids a job keeps between bakes.
+const MAX_POOLED: usize = 4;
+
+/// Buffers retained between calls on behalf of one job.
+#[derive(Default)]
+pub(crate) struct JobCache {
+    pub(crate) parse_cache: Option<ParseCacheEntry>,
+    /// Scratch grids returned by the job's finished bakes.
+    scratch: Vec<Vec<f64>>,
+}
+
+impl JobCache {
+    fn retained_bytes(&self) -> usize {
+        let cache = self
+            .parse_cache
+            .as_ref()
+            .map(|entry| entry.points.capacity() * std::mem::size_of::<splat::PointNormal>())
+            .unwrap_or(0);
+        cache + self.scratch_bytes()
+    }
+
+    fn scratch_bytes(&self) -> usize {
+        self.scratch
+            .iter()
+            .map(|buffer| buffer.capacity() * std::mem::size_of::<f64>())
+            .sum()
+    }
+
+    fn cached_splats(&self) -> usize {
+        self.parse_cache
+            .as_ref()
+            .map(|entry| entry.points.len())
+            .unwrap_or(0)
+    }
+}
+
+/// A context's job table.
+pub(crate) struct Jobs {
+    caches: RefCell<BTreeMap<u32, JobCache>>,
+    next_id: Cell<u32>,
+}
+
+impl Default for Jobs {
+    fn default() -> Self {
+        Self {
+            caches: RefCell::new(BTreeMap::new()),
+            next_id: Cell::new(1),
+        }
+    }
+}
+
+/// Run `f` against the cache for `job_id` (0 when `None`). Job 0 is created on
+/// demand; any other id must come from `create_job` and not have been freed.
+pub(crate) fn with_job<R>(
+    job_id: Option<u32>,
+    f: impl FnOnce(&mut JobCache) -> R,
+) -> Result<R, JsValue> {
+    let id = job_id.unwrap_or(0);
+    let context = context::current();
+    let mut jobs = context.jobs.caches.borrow_mut();
+    if id == 0 {
+        return Ok(f(jobs.entry(0).or_default()));
+    }
+    match jobs.get_mut(&id) {
+        Some(cache) => Ok(f(cache)),
+        None => Err(SplatwalkError::InvalidSettings(format!(
+            "Unknown job_id {}: create it with create_job() (it may already have been freed).",
+            id
+        ))
+        .into()),
+    }
+}
+
+/// A zeroed grid of `len` values lent by a job's scratch pool; derefs to
+/// `[f64]`. Dropping it hands the buffer back to the job, or frees it when the
+/// job has been freed in the meantime.
+pub(crate) struct Scratch {
+    job_id: u32,
+    buffer: Vec<f64>,
+}
+
+impl Deref for Scratch {
+    type Target = [f64];
+
+    fn deref(&self) -> &[f64] {
+        &self.buffer
+    }
+}
+
+impl DerefMut for Scratch {
+    fn deref_mut(&mut self) -> &mut [f64] {
+        &mut self.buffer
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let buffer = std::mem::take(&mut self.buffer);
+        let context = context::current();
+        // Busy when dropped while unwinding out of a job-table borrow.
+        let Ok(mut jobs) = context.jobs.caches.try_borrow_mut() else {
+            return;
+        };
+        if let Some(cache) = jobs.get_mut(&self.job_id) {
+            if cache.scratch.len() < MAX_POOLED {
+                cache.scratch.push(buffer);
+            }
+        }
+    }
+}
+
+/// Borrow a zeroed grid of `len` values from the scratch pool of `job_id` (0
+/// when `None`), reusing the largest pooled buffer. An unknown job gets a
+/// fresh buffer that is freed on drop.
+pub(crate) fn scratch_f64(job_id: Option<u32>, len: usize) -> Scratch {
+    let job_id = job_id.unwrap_or(0);
+    let context = context::current();
+    let pooled = context
+        .jobs
+        .caches
+        .try_borrow_mut()
+        .ok()
+        .and_then(|mut jobs| {
+            let pool = &mut jobs.get_mut(&job_id)?.scratch;
+            let largest = (0..pool.len()).max_by_key(|&i| pool[i].capacity())?;
+            Some(pool.swap_remove(largest))
+        });
+    let mut buffer = pooled.unwrap_or_default();
+    buffer.clear();
+    buffer.resize(len, 0.0);
+    Scratch { job_id, buffer }
+}
+
+/// Allocate a new job id. Pass it as `job_id` in settings so the call's
+/// retained buffers are charged to (and freed with) that job.
+#[wasm_bindgen]
+pub fn create_job() -> u32 {
+    let context = context::current();
+    let id = context.jobs.next_id.get();
+    context.jobs.next_id.set(id.wrapping_add(1).max(1));
+    context
+        .jobs
+        .caches
+        .borrow_mut()
+        .insert(id, JobCache::default());
+    id
+}
+
+/// Drop every buffer retained for `job_id`. Returns false when the job was
+/// unknown. Freeing job 0 clears the default cache; the job itself stays usable.
+#[wasm_bindgen]
+pub fn free_job(job_id: u32) -> bool {
+    let context = context::current();
+    let removed = context.jobs.caches.borrow_mut().remove(&job_id);
+    removed.is_some()
+}
+
+/// Drop every job's retained buffers and restart peak tracking. Job ids handed
+/// out earlier become invalid.
+#[wasm_bindgen]
+pub fn reset() {
+    *context::current().jobs.caches.borrow_mut() = BTreeMap::new();
+    restart_peak();
+}
+
+/// [`reset`] for `reset_module`: returns false, changing nothing, when a call
+/// that trapped mid-update still holds the job table.
+pub(crate) fn try_reset() -> bool {
+    let cleared = match context::current().jobs.caches.try_borrow_mut() {
+        Ok(mut jobs) => {
+            *jobs = BTreeMap::new();
+            true
+        }
+        Err(_) => false,
+    };
+    if cleared {
+        restart_peak();
+    }
+    cleared
+}
+
+fn restart_peak() {
+    #[cfg(feature = "alloc_stats")]
+    PEAK_ALLOCATED.store(ALLOCATED.load(Ordering::Relaxed), Ordering::Relaxed);
+}
+
+/// Bytes currently allocated through the Rust allocator, in `alloc_stats`
+/// builds.
+fn counted_bytes() -> Option<usize> {
+    #[cfg(feature = "alloc_stats")]
+    {
+        Some(ALLOCATED.load(Ordering::Relaxed))
+    }
+    #[cfg(not(feature = "alloc_stats"))]
+    {
+        None
+    }
+}
+
+fn counted_peak_bytes() -> Option<usize> {
+    #[cfg(feature = "alloc_stats")]
+    {
+        Some(PEAK_ALLOCATED.load(Ordering::Relaxed))
+    }
+    #[cfg(not(feature = "alloc_stats"))]
+    {
+        None
+    }
+}
+
+/// Bytes in use on the heap: the counting allocator's live bytes in
+/// `alloc_stats` builds, else the linear-memory size.
+pub(crate) fn allocated_bytes() -> usize {
+    counted_bytes().unwrap_or_else(linear_memory_bytes)
+}
+
+/// Where `allocated_bytes` and `peak_allocated_bytes` come from.
+fn allocated_bytes_source() -> &'static str {
+    if cfg!(feature = "alloc_stats") {
+        "allocator"
+    } else {
+        "linear_memory"
+    }
+}
+
+#[derive(Serialize)]
+pub struct JobMemory {
+    pub job_id: u32,
+    /// Bytes held by the job's retained buffers (capacity, not length),
+    /// scratch pool included.
+    pub retained_bytes: usize,
+    /// Bytes held by the job's pooled scratch grids.
+    pub scratch_bytes: usize,
+    pub cached_splats: usize,
+}
+
+#[derive(Serialize)]
+pub struct MemoryStats {
+    pub api_version: u8,
+    /// Size of WASM linear memory in bytes. It never shrinks; watch
+    /// `allocated_bytes` for what is actually in use.
+    pub heap_bytes: usize,
+    /// Bytes currently allocated through the Rust allocator. Without the
+    /// `alloc_stats` feature this is `heap_bytes`, an upper bound.
+    pub allocated_bytes: usize,
+    /// High-water mark of `allocated_bytes` since load or the last `reset`
+    /// (since load when it is the linear-memory size).
+    pub peak_allocated_bytes: usize,
+    /// `"allocator"` when the counting allocator supplies the two counters
+    /// above, `"linear_memory"` when they fall back to the memory size.
+    pub allocated_bytes_source: &'static str,
+    pub jobs: Vec<JobMemory>,
+}
+
+fn linear_memory_bytes() -> usize {
+    #[cfg(target_arch = "wasm32")]
+    {
+        core::arch::wasm32::memory_size(0) * 65536
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        0
+    }
+}
+
+pub(crate) fn stats() -> MemoryStats {
+    let context = context::current();
+    let jobs = context
+        .jobs
+        .caches
+        .borrow()
+        .iter()
+        .map(|(&job_id, cache)| JobMemory {
+            job_id,
+            retained_bytes: cache.retained_bytes(),
+            scratch_bytes: cache.scratch_bytes(),
+            cached_splats: cache.cached_splats(),
+        })
+        .collect();
+    MemoryStats {
+        api_version: crate::API_VERSION,
+        heap_bytes: linear_memory_bytes(),
+        allocated_bytes: allocated_bytes(),
+        peak_allocated_bytes: counted_peak_bytes().unwrap_or_else(linear_memory_bytes),
+        allocated_bytes_source: allocated_bytes_source(),
+        jobs,
+    }
+}
+
+/// Heap usage report: linear memory size, live / peak allocated bytes, and the
+/// bytes each live job is retaining.
+#[wasm_bindgen]
+pub fn memory_stats() -> Result<JsValue, JsValue> {
+    Ok(serde_wasm_bindgen::to_value(&stats())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn freed_jobs_are_forgotten() {
+        let id = create_job();
+        assert!(with_job(Some(id), |_| ()).is_ok());
+        assert!(free_job(id));
+        assert!(!free_job(id));
+        assert!(stats().jobs.iter().all(|job| job.job_id != id));
+        assert!(with_job(None, |_| ()).is_ok());
+    }
+
+    #[test]
+    fn byte_counters_fall_back_to_the_memory_size() {
+        let stats = stats();
+        if cfg!(feature = "alloc_stats") {
+            assert_eq!(stats.allocated_bytes_source, "allocator");
+            assert!(stats.peak_allocated_bytes >= stats.allocated_bytes);
+        } else {
+            assert_eq!(stats.allocated_bytes_source, "linear_memory");
+            assert_eq!(stats.allocated_bytes, stats.heap_bytes);
+        }
+    }
+
+    #[test]
+    fn scratch_grids_return_to_their_job_until_it_is_freed() {
+        let id = create_job();
+        let scratch_bytes = || {
+            let stats = stats();
+            stats
+                .jobs
+                .iter()
+                .find(|job| job.job_id == id)
+                .map(|job| job.scratch_bytes)
+        };
+        let mut grid = scratch_f64(Some(id), 1000);
+        grid[7] = 1.0;
+        assert_eq!(scratch_bytes(), Some(0));
+        drop(grid);
+        assert_eq!(scratch_bytes(), Some(8000));
+
+        // The next bake gets the same buffer back, zeroed.
+        let grid = scratch_f64(Some(id), 500);
+        assert_eq!((grid.len(), grid[7]), (500, 0.0));
+        assert_eq!(scratch_bytes(), Some(0));
+        drop(grid);
+        assert_eq!(scratch_bytes(), Some(8000));
+
+        let grid = scratch_f64(Some(id), 10);
+        assert!(free_job(id));
+        drop(grid);
+        assert_eq!(scratch_bytes(), None);
+    }
+}
@@ -1,13 +1,62 @@
+use crate::bpa::{self, BpaOptions};
+use crate::cad_export::{self, IfcOptions};
+use crate::chokepoint::{self, ChokepointOptions};
+use crate::cluster::{self, NormalClusterOptions, NormalGroup};
+use crate::detour::{self, DetourAgent};
+use crate::deviation;
+use crate::dual_contour::{self, DualContourOptions};
+use crate::ellipsoid::{self, EllipsoidOptions, SplatEllipsoids, SplatShape, SplatVerdict};
+use crate::epsilon::{self, Tolerance};
+use crate::events::{self, Event};
+use crate::face_filter;
+use crate::floor_edit::Heightfield;
+use crate::floor_layers::{self, FloorLayers};
+use crate::floor_plan::{self, FloorPlanOptions};
+use crate::floor_polygons::{self, FloorPolygonOptions};
+use crate::flow_field;
+use crate::frame::{self, GroundFrame};
+use crate::geodesic;
+use crate::hazard::{self, Hazards};
+use crate::heightmap::{self, Heightmap};
+use crate::hull;
+use crate::inpaint;
+use crate::journal::{self, JournalKind};
+use crate::kernel::SplatKernel;
+use crate::memory;
+use crate::navmesh::{self, NavmeshOptions, PolyNavmesh, Span, SpanField};
+use crate::occupancy::{self, OccupancyGrid};
+use crate::offmesh::{self, OffMeshLinks};
+use crate::parallel;
+use crate::path_cost::{self, CostSource, PathCosts};
+use crate::postprocess;
+use crate::preprocess;
+use crate::ransac::{self, RansacOptions, RansacOutcome, Score};
+use crate::reachability::{self, AgentLimits};
+use crate::region::RegionFilter;
+use crate::resolution::Resolution;
+use crate::safe_area::{self, SafeAreaOptions};
+use crate::sensitivity::{self, SensitivityReport};
 use crate::splat::PointNormal;
+use crate::stage::{self, PartialResult};
+use crate::surface_nets::{self, SurfaceNetsOptions};
+use crate::terrain::{self, TerrainOptions};
+use crate::thumbnail;
+use crate::tiling::{self, TileLayout};
+use crate::tsdf::{self, TsdfOptions};
+use crate::uv::UvMapper;
+use crate::vertex_color;
+use crate::void_plane;
+use crate::walkability::{self, WalkabilityTexture};
+use crate::wall_extrude;
 use crate::{
-    CollisionVoxelBoundaryResult, CollisionVoxelVolume, CoordinateSpace, FieldBasis, FloorPlane,
-    GroundFieldCell,
-    GroundFieldCellState, MeshBuffers, MeshSettings, NavmeshBasisResult, ReconstructionDiagnostics,
-    ReconstructionResult, SplatBounds, SuggestedRegion, WalkableGroundFieldResult,
+    Chokepoint, ChokepointResult, CollisionVoxelBoundaryResult, CollisionVoxelVolume,
+    CoordinateSpace, DetectedPlane, FaceCells, FieldBasis, FloorPlanResult, FloorPlane,
+    FloorPolygon, FloorPolygonsResult, GroundCandidate, GroundFieldCell, GroundFieldCellState,
+    MeshBuffers, MeshSettings, NavmeshBasisResult, ReconstructionDiagnostics, ReconstructionResult,
+    SafeAreaResult, SplatBackprojection, SplatBounds, SuggestedRegion, WalkableGroundFieldResult,
 };
-use nalgebra::{Point3, UnitQuaternion, Vector3};
+use nalgebra::{Point3, Rotation3, UnitQuaternion, Vector3};
 use poisson_reconstruction::{PoissonReconstruction, Real};
-use rand::Rng;
 
 #[derive(Debug)]
 pub struct ReconstructedMesh {
@@ -22,12 +71,19 @@ struct Plane {
 }
 
 impl Plane {
-    fn from_points(p1: &Point3<Real>, p2: &Point3<Real>, p3: &Point3<Real>) -> Option<Self> {
+    /// Plane through three points, or `None` when their cross product is
+    /// shorter than `degenerate_area` (see [`Tolerance::degenerate_area`]).
+    fn from_points(
+        p1: &Point3<Real>,
+        p2: &Point3<Real>,
+        p3: &Point3<Real>,
+        degenerate_area: Real,
+    ) -> Option<Self> {
         let v1 = p2 - p1;
         let v2 = p3 - p1;
         let cross = v1.cross(&v2);
 
-        if cross.magnitude() < 1e-6 {
+        if cross.magnitude() < degenerate_area {
             return None;
         }
 
@@ -46,6 +102,9 @@ struct ReconstructionContext {
     oriented_points: Vec<PointNormal>,
     filtered_points: Vec<PointNormal>,
     diagnostics: ReconstructionDiagnostics,
+    tolerance: Tolerance,
+    /// `tile_size`: the box one tile's collision grid is pinned to.
+    tile_bounds: Option<(Vector3<f64>, Vector3<f64>)>,
 }
 
 struct FieldBuild {
@@ -64,6 +123,14 @@ struct CollisionBuild {
     plane: FloorPlane,
     diagnostics: ReconstructionDiagnostics,
     volume: Option<CollisionVoxelVolume>,
+    /// `emit_poly_navmesh`: the polygon navmesh of the carved floors.
+    navmesh: Option<PolyNavmesh>,
+    /// `emit_floor_layers`: the carved floors split into storeys.
+    layers: Option<FloorLayers>,
+    /// `emit_occupancy_grid`: the carved grid flattened into a ROS map.
+    occupancy: Option<OccupancyGrid>,
+    /// Set when meshing panicked after the grid was carved.
+    partial: Option<PartialResult>,
 }
 
 /// Pack bool occupancy LSB-first (bit i → byte[i/8] bit i%8).
@@ -151,14 +218,10 @@ pub fn get_splat_bounds(
     settings: &MeshSettings,
 ) -> Result<SplatBounds, wasm_bindgen::JsValue> {
     let context = build_context(points, settings);
-    let min = context
-        .diagnostics
-        .oriented_min
-        .ok_or_else(|| wasm_bindgen::JsValue::from_str("No valid oriented points for bounds"))?;
-    let max = context
-        .diagnostics
-        .oriented_max
-        .ok_or_else(|| wasm_bindgen::JsValue::from_str("No valid oriented points for bounds"))?;
+    let no_points =
+        || crate::SplatwalkError::EmptyCloud("No valid oriented points for bounds".to_string());
+    let min = context.diagnostics.oriented_min.ok_or_else(no_points)?;
+    let max = context.diagnostics.oriented_max.ok_or_else(no_points)?;
     let floor_y = context.diagnostics.floor_y_percentile_02.unwrap_or(min[1]);
 
     Ok(SplatBounds {
@@ -202,32 +265,695 @@ pub fn suggest_region(
 }
 
 pub fn reconstruct_mesh(points: &[PointNormal], settings: &MeshSettings) -> ReconstructionResult {
-    let mode = settings.mode;
-    web_sys::console::log_1(&format!("Reconstructing mesh (Mode: {})...", mode).into());
+    let mut job = MeshJob::prepare(points, settings);
+    job.mesh(settings);
+    job.finish(settings)
+}
 
-    let context = build_context(points, settings);
-    let mut diagnostics = context.diagnostics.clone();
+/// [`reconstruct_mesh`] split at its stage boundaries, so
+/// `convert_splat_to_mesh_async` can yield to the event loop between them.
+/// Call [`MeshJob::prepare`], [`MeshJob::mesh`] (or [`MeshJob::mesh_slice`]
+/// until it returns `true`) and [`MeshJob::finish`] in order with the same
+/// settings.
+pub struct MeshJob {
+    context: ReconstructionContext,
+    diagnostics: ReconstructionDiagnostics,
+    planes: Option<Vec<DetectedPlane>>,
+    partial: Option<PartialResult>,
+    mesh: ReconstructedMesh,
+    navmesh: Option<PolyNavmesh>,
+    layers: Option<FloorLayers>,
+    occupancy: Option<OccupancyGrid>,
+    tiles: Option<TileLayout>,
+    /// Mode 2's voxel grid basis and cell edge, for the result's frame.
+    grid: Option<(FieldBasis, f64)>,
+    /// `emit_heightmap`: the floor's height grid, read before welding and
+    /// decimation move vertices off the grid.
+    heightmap: Option<Heightmap>,
+    /// Where [`MeshJob::mesh_slice`] resumes.
+    meshing: Meshing,
+}
 
-    let mesh = if context.filtered_points.is_empty() {
-        ReconstructedMesh {
+/// A mesher paused between slices.
+enum Meshing {
+    Start,
+    /// Modes 1 and 5: the ground-plane RANSAC.
+    Ground(GroundSearch),
+    /// Mode 4: plane peeling.
+    Planes(PlanePeeling),
+    /// Untiled mode 2: splatting the cloud into the collision grid.
+    Voxelize(Box<CollisionVoxelize>),
+    /// Mode 0: a solved Poisson reconstruction awaiting surface extraction.
+    Poisson(Box<PoissonReconstruction>),
+}
+
+impl MeshJob {
+    /// Orient, filter and (with `downsample_voxel_size`) downsample `points`.
+    pub fn prepare(points: &[PointNormal], settings: &MeshSettings) -> Self {
+        let mode = settings.mode;
+        events::emit(Event::ReconstructionStarted { mode });
+
+        let mut context = build_context(points, settings);
+        if let Some(size) = settings.downsample_voxel_size {
+            context.filtered_points = preprocess::voxel_downsample(
+                &context.filtered_points,
+                size,
+                environment_scale(settings),
+            );
+            context.diagnostics.downsample_voxel_size = size;
+            journal::record(
+                "downsample",
+                JournalKind::Dropped,
+                format!(
+                    "Voxel downsample kept {} points",
+                    context.filtered_points.len()
+                ),
+                serde_json::json!({
+                    "reason": "voxel_downsample",
+                    "voxel_size": size,
+                    "input": context.diagnostics.points_after_filter,
+                    "kept": context.filtered_points.len(),
+                }),
+            );
+            events::emit(Event::VoxelDownsampled {
+                voxel_size: size,
+                input: context.diagnostics.points_after_filter,
+                kept: context.filtered_points.len(),
+            });
+        }
+        context.diagnostics.points_after_downsample = context.filtered_points.len();
+        let diagnostics = context.diagnostics.clone();
+        MeshJob {
+            context,
+            diagnostics,
+            planes: (mode == 4).then(Vec::new),
+            partial: None,
+            mesh: ReconstructedMesh {
+                vertices: vec![],
+                indices: vec![],
+            },
+            navmesh: None,
+            layers: None,
+            occupancy: None,
+            tiles: None,
+            grid: None,
+            heightmap: None,
+            meshing: Meshing::Start,
+        }
+    }
+
+    /// Run the mode's mesher (Poisson, RANSAC plane(s), voxel navmesh, TSDF,
+    /// terrain, ball pivoting or convex hull).
+    pub fn mesh(&mut self, settings: &MeshSettings) {
+        self.mesh_slice(settings, &mut || false);
+    }
+
+    /// [`MeshJob::mesh`] a slice at a time: runs until meshing is done
+    /// (`true`) or `out_of_time` returns true (`false`), and picks up where it
+    /// left off on the next call. RANSAC (modes 1, 4 and 5) pauses between
+    /// hypotheses, untiled mode 2 between batches of splatted points, and
+    /// Poisson between its solve and its surface extraction. The other modes,
+    /// and each of those steps, run to completion within one slice.
+    pub fn mesh_slice(
+        &mut self,
+        settings: &MeshSettings,
+        out_of_time: &mut dyn FnMut() -> bool,
+    ) -> bool {
+        loop {
+            // The filtered cloud and its diagnostics survive a meshing panic.
+            match stage::run("meshing", || self.advance(settings, out_of_time)) {
+                Ok(None) if out_of_time() => return false,
+                Ok(None) => {}
+                Ok(Some(mesh)) => {
+                    self.mesh = mesh;
+                    break;
+                }
+                Err(panic) => {
+                    self.partial = Some(panic.into());
+                    break;
+                }
+            }
+        }
+        if let Some(options) = &settings.emit_heightmap {
+            self.heightmap(options);
+        }
+        true
+    }
+
+    /// Move the mesher on; `Some` with the mesh once it is done.
+    fn advance(
+        &mut self,
+        settings: &MeshSettings,
+        out_of_time: &mut dyn FnMut() -> bool,
+    ) -> Option<ReconstructedMesh> {
+        let mode = settings.mode;
+        let context = &self.context;
+        let diagnostics = &mut self.diagnostics;
+        let points = &context.filtered_points;
+        let empty = || ReconstructedMesh {
             vertices: vec![],
             indices: vec![],
+        };
+        match std::mem::replace(&mut self.meshing, Meshing::Start) {
+            Meshing::Start if points.is_empty() => Some(empty()),
+            Meshing::Start => {
+                self.meshing = match mode {
+                    1 | 5 => {
+                        Meshing::Ground(GroundSearch::new(points, settings, &context.tolerance))
+                    }
+                    4 => Meshing::Planes(PlanePeeling::new(points, settings, &context.tolerance)),
+                    2 if settings.tile_size.is_none() => {
+                        match CollisionVoxelize::new(context, settings, diagnostics) {
+                            Some(voxelize) => Meshing::Voxelize(Box::new(voxelize)),
+                            None => return Some(self.take_collision(None)),
+                        }
+                    }
+                    2 | 3 | 6..=10 => return Some(self.mesh_at_once(settings)),
+                    _ => match solve_poisson(points) {
+                        Some(poisson) => Meshing::Poisson(Box::new(poisson)),
+                        None => return Some(empty()),
+                    },
+                };
+                None
+            }
+            Meshing::Ground(mut search) => {
+                if !search.advance(out_of_time) {
+                    self.meshing = Meshing::Ground(search);
+                    return None;
+                }
+                Some(if mode == 1 {
+                    search.plane_mesh(settings, diagnostics)
+                } else {
+                    search.terrain_mesh(points, settings, diagnostics)
+                })
+            }
+            Meshing::Planes(mut peeling) => {
+                if !peeling.advance(diagnostics, out_of_time) {
+                    self.meshing = Meshing::Planes(peeling);
+                    return None;
+                }
+                let mesh = merge_plane_meshes(&peeling.planes);
+                self.planes = Some(peeling.planes);
+                Some(mesh)
+            }
+            Meshing::Voxelize(mut voxelize) => {
+                if !voxelize.advance(out_of_time) {
+                    self.meshing = Meshing::Voxelize(voxelize);
+                    return None;
+                }
+                let collision = voxelize.finish(settings, diagnostics, false);
+                Some(self.take_collision(collision))
+            }
+            Meshing::Poisson(poisson) => Some(poisson_mesh(&poisson)),
         }
-    } else if mode == 1 {
-        reconstruct_plane_ransac(&context.filtered_points, &mut diagnostics)
-    } else if mode == 2 {
-        reconstruct_voxel_navmesh(&context, settings, &mut diagnostics)
-    } else {
-        reconstruct_poisson(&context.filtered_points)
-    };
+    }
 
-    ReconstructionResult {
-        api_version: crate::API_VERSION,
-        semver: crate::core_semver(),
-        capabilities: crate::capabilities(),
-        mesh: MeshBuffers::new(mesh.vertices, mesh.indices),
-        space: CoordinateSpace::splatwalk_oriented(),
-        diagnostics,
+    /// The meshers with no pause point: tiled mode 2, TSDF, ball pivoting,
+    /// convex hull, dual contouring, surface nets and walls.
+    fn mesh_at_once(&mut self, settings: &MeshSettings) -> ReconstructedMesh {
+        let context = &self.context;
+        let points = &context.filtered_points;
+        let diagnostics = &mut self.diagnostics;
+        match settings.mode {
+            2 => reconstruct_tiled_navmesh(
+                context,
+                settings,
+                diagnostics,
+                &mut self.partial,
+                &mut self.tiles,
+            ),
+            3 => reconstruct_tsdf(points, settings, diagnostics),
+            6 => reconstruct_ball_pivoting(points, settings, diagnostics),
+            7 => reconstruct_convex_hull(points, settings, diagnostics),
+            8 => reconstruct_dual_contour(points, settings, diagnostics),
+            9 => reconstruct_surface_nets(points, settings, diagnostics),
+            _ => reconstruct_walls(points, settings, diagnostics),
+        }
+    }
+
+    /// Keep an untiled mode 2 collision build's mesh and extras, or journal
+    /// why there is none.
+    fn take_collision(&mut self, collision: Option<CollisionBuild>) -> ReconstructedMesh {
+        let Some(collision) = collision else {
+            journal_collision_failure(&self.diagnostics);
+            return ReconstructedMesh {
+                vertices: vec![],
+                indices: vec![],
+            };
+        };
+        self.diagnostics = collision.diagnostics;
+        self.partial = collision.partial;
+        self.navmesh = collision.navmesh;
+        self.layers = collision.layers;
+        self.occupancy = collision.occupancy;
+        self.grid = Some((collision.basis, self.diagnostics.collision_voxel_size));
+        collision.mesh
+    }
+
+    /// Read the floor's height grid off the untiled mode 2 mesh, or journal
+    /// why there is none.
+    fn heightmap(&mut self, options: &heightmap::HeightmapSettings) {
+        let floors = self.diagnostics.collision_mesh_mode == "walkable_floors";
+        let Some((basis, cell_size)) = self.grid.as_ref().filter(|_| floors) else {
+            journal::record(
+                "heightmap",
+                JournalKind::Degraded,
+                "Heightmap skipped: it needs untiled mode 2 walkable_floors meshing".to_string(),
+                serde_json::json!({ "reason": "no_floor_grid" }),
+            );
+            return;
+        };
+        let vertices = &self.mesh.vertices;
+        match stage::run("heightmap", || {
+            heightmap::from_floor_mesh(vertices, basis.origin, *cell_size, options)
+        }) {
+            Ok(Ok(map)) => self.heightmap = map,
+            Ok(Err(message)) => journal::record(
+                "heightmap",
+                JournalKind::Degraded,
+                format!("Heightmap not written: {}", message),
+                serde_json::json!({ "reason": "encode_failed", "message": message }),
+            ),
+            Err(panic) => self.partial = Some(panic.into()),
+        }
+    }
+
+    /// Weld, decimate and color the mesh, and assemble the result.
+    pub fn finish(self, settings: &MeshSettings) -> ReconstructionResult {
+        let mode = settings.mode;
+        let MeshJob {
+            context,
+            mut diagnostics,
+            mut planes,
+            mut partial,
+            mut mesh,
+            navmesh,
+            layers,
+            occupancy,
+            tiles,
+            grid,
+            heightmap,
+            ..
+        } = self;
+
+        // Mode 4 planes are single quads already, and `mesh` must stay their union.
+        if settings.weld_vertices.unwrap_or(false) && mode != 4 {
+            let before = mesh.vertices.len() / 3;
+            let tolerance = context.tolerance;
+            let welded = stage::run("weld", || {
+                postprocess::weld(&mesh, tolerance.weld(), tolerance.degenerate_area())
+            });
+            match welded {
+                Ok(welded) => mesh = welded,
+                Err(panic) => partial = Some(panic.into()),
+            }
+            diagnostics.welded_vertices = before.saturating_sub(mesh.vertices.len() / 3);
+        }
+        let hazards = Hazards::from_settings(settings).filter(|_| mode != 4);
+        // Before the reachability split, so the flood cannot cross hazards.
+        let mut cut_faces = 0;
+        let cut = settings.hazard_cut.unwrap_or(false);
+        if let Some(hazards) = hazards.as_ref().filter(|_| cut) {
+            match stage::run("hazard_cut", || hazard::cut(&mesh, hazards)) {
+                Ok((kept, removed)) => {
+                    mesh = kept;
+                    cut_faces = removed;
+                    journal::record(
+                        "hazard_cut",
+                        JournalKind::Dropped,
+                        format!("Cut {} faces under water or in hazard volumes", removed),
+                        serde_json::json!({ "reason": "hazard", "faces": removed }),
+                    );
+                }
+                Err(panic) => partial = Some(panic.into()),
+            }
+        }
+        // Before decimation, so unreachable islands don't spend the face budget.
+        let mut unreachable = None;
+        if let Some(start) = settings.reachable_from.as_deref().filter(|_| mode != 4) {
+            let limits = AgentLimits::from_settings(settings);
+            let link = context.tolerance.weld();
+            let split = stage::run("reachability", || {
+                reachability::split(&mesh, [start[0], start[1], start[2]], &limits, link)
+            });
+            match split {
+                Ok(Some(split)) => {
+                    mesh = split.reachable;
+                    diagnostics.reachable_faces = mesh.indices.len() / 3;
+                    diagnostics.unreachable_faces = split.unreachable.mesh.face_count;
+                    diagnostics.unreachable_islands = split.unreachable.islands;
+                    journal::record(
+                        "reachability",
+                        JournalKind::Dropped,
+                        format!(
+                            "Split off {} unreachable faces in {} islands",
+                            diagnostics.unreachable_faces, diagnostics.unreachable_islands
+                        ),
+                        serde_json::json!({
+                            "reason": "unreachable",
+                            "reachable_area": split.reachable_area,
+                            "unreachable_area": split.unreachable.area,
+                            "islands": split.unreachable.islands,
+                        }),
+                    );
+                    unreachable = Some(split.unreachable);
+                }
+                Ok(None) => journal::record(
+                    "reachability",
+                    JournalKind::Degraded,
+                    "No walkable face to start the reachability flood from".to_string(),
+                    serde_json::json!({ "reason": "no_walkable_face" }),
+                ),
+                Err(panic) => partial = Some(panic.into()),
+            }
+        }
+        if let Some(target) = settings.decimate_target_faces.filter(|_| mode != 4) {
+            let input_faces = mesh.indices.len() / 3;
+            diagnostics.decimate_input_faces = input_faces;
+            if input_faces > target {
+                let max_error = settings.decimate_max_error.unwrap_or(f64::INFINITY);
+                let decimated = stage::run("decimate", || {
+                    postprocess::decimate(&mesh, target, max_error)
+                });
+                match decimated {
+                    Ok(decimated) => mesh = decimated,
+                    Err(panic) => partial = Some(panic.into()),
+                }
+            }
+            diagnostics.decimate_output_faces = mesh.indices.len() / 3;
+            events::emit(Event::MeshDecimated {
+                input_faces,
+                output_faces: diagnostics.decimate_output_faces,
+                target,
+            });
+        }
+
+        // Last of the geometry stages, so every per-face and per-vertex output
+        // below lines up with the chain's mesh.
+        let mut chain_colors = None;
+        if let Some(steps) = settings.post_process.as_deref().filter(|_| mode != 4) {
+            let tolerance = context.tolerance;
+            let chained = stage::run("post_process", || {
+                let radius = vertex_color::sample_radius(settings, &context.filtered_points);
+                postprocess::run_chain(&mesh, steps, tolerance, radius)
+            });
+            match chained {
+                Ok(chained) => {
+                    let names: Vec<&str> = steps.iter().map(|s| s.step.as_str()).collect();
+                    journal::record(
+                        "post_process",
+                        JournalKind::Decision,
+                        format!("Ran post-process chain: {}", names.join(", ")),
+                        serde_json::json!({ "steps": names, "faces": chained.faces }),
+                    );
+                    diagnostics.post_process_steps = steps.len();
+                    mesh = chained.mesh;
+                    chain_colors = chained.color_radius;
+                }
+                Err(panic) => partial = Some(panic.into()),
+            }
+        }
+        // After decimation, so the costs line up with the output faces, and
+        // before the per-face outputs below, so they skip rejected faces.
+        let mut path_costs = None;
+        if face_filter::is_set() && mode != 4 {
+            let attributes = stage::run("face_filter", || {
+                let radius = vertex_color::sample_radius(settings, &context.filtered_points);
+                face_filter::attributes(&mesh, &context.filtered_points, radius)
+            });
+            match attributes.map(|attributes| face_filter::score(&attributes)) {
+                Ok(Ok(Some(verdicts))) => {
+                    let (kept, costs, rejected) = face_filter::apply(&mesh, &verdicts);
+                    mesh = kept;
+                    diagnostics.face_filter_rejected = rejected;
+                    journal::record(
+                        "face_filter",
+                        JournalKind::Dropped,
+                        format!(
+                            "Face filter rejected {} of {} faces",
+                            rejected,
+                            verdicts.len()
+                        ),
+                        serde_json::json!({ "reason": "face_filter", "faces": rejected }),
+                    );
+                    path_costs = Some(PathCosts::from_face_costs(costs));
+                }
+                Ok(Ok(None)) => {}
+                Ok(Err(message)) => journal::record(
+                    "face_filter",
+                    JournalKind::Degraded,
+                    format!("Face filter failed, kept every face: {}", message),
+                    serde_json::json!({ "reason": "callback_error", "message": message }),
+                ),
+                Err(panic) => partial = Some(panic.into()),
+            }
+        }
+        // After decimation, so the flags line up with the output faces.
+        let mut hazard_tags = None;
+        if let Some(hazards) = hazards.as_ref() {
+            match stage::run("hazard_tags", || hazard::tag(&mesh, hazards)) {
+                Ok(flags) => {
+                    let hazard_faces = flags.iter().filter(|&&f| f != 0).count();
+                    journal::record(
+                        "hazard_tags",
+                        JournalKind::Decision,
+                        format!("Tagged {} hazard faces", hazard_faces),
+                        serde_json::json!({
+                            "hazard_faces": hazard_faces,
+                            "cut_faces": cut_faces,
+                        }),
+                    );
+                    hazard_tags = Some(hazard::HazardTags {
+                        flags,
+                        hazard_faces,
+                        cut_faces,
+                    });
+                }
+                Err(panic) => partial = Some(panic.into()),
+            }
+        }
+        // After decimation, so there is one distance per output vertex.
+        let mut distance_field = None;
+        if let Some(sources) = settings.distance_sources.as_deref().filter(|_| mode != 4) {
+            let sources: Vec<[f64; 3]> = sources.iter().map(|s| [s[0], s[1], s[2]]).collect();
+            let limits = AgentLimits::from_settings(settings);
+            let link = context.tolerance.weld();
+            let field = stage::run("distance_field", || {
+                geodesic::distances(&mesh, &sources, &limits, link)
+            });
+            match field {
+                Ok(field) => {
+                    journal::record(
+                        "distance_field",
+                        JournalKind::Decision,
+                        format!(
+                            "Walking distances from {} source(s) reach {} of {} vertices",
+                            sources.len(),
+                            field.reached,
+                            field.distances.len()
+                        ),
+                        serde_json::json!({
+                            "sources": sources.len(),
+                            "reached": field.reached,
+                            "max_distance": field.max_distance,
+                        }),
+                    );
+                    distance_field = Some(field);
+                }
+                Err(panic) => partial = Some(panic.into()),
+            }
+        }
+        let mut flow = None;
+        if let Some(goal) = settings.flow_goal.as_deref().filter(|_| mode != 4) {
+            let goal = [goal[0], goal[1], goal[2]];
+            let limits = AgentLimits::from_settings(settings);
+            let link = context.tolerance.weld();
+            let field = stage::run("flow_field", || {
+                flow_field::build(&mesh, goal, &limits, link)
+            });
+            match field {
+                Ok(field) => {
+                    journal::record(
+                        "flow_field",
+                        JournalKind::Decision,
+                        format!(
+                            "Flow field reaches the goal from {} of {} faces",
+                            field.reached_faces,
+                            field.distances.len()
+                        ),
+                        serde_json::json!({ "reached_faces": field.reached_faces }),
+                    );
+                    flow = Some(field);
+                }
+                Err(panic) => partial = Some(panic.into()),
+            }
+        }
+        // On the final faces, so `start_face` / `end_face` index `mesh`.
+        let mut off_mesh_links: Option<OffMeshLinks> = None;
+        if let Some(requested) = settings.off_mesh_links.as_deref().filter(|_| mode != 4) {
+            let limits = AgentLimits::from_settings(settings);
+            match stage::run("off_mesh_links", || {
+                offmesh::place(&mesh, navmesh.as_ref(), requested, &limits)
+            }) {
+                Ok(placed) => {
+                    if !placed.dropped.is_empty() {
+                        journal::record(
+                            "off_mesh_links",
+                            JournalKind::Dropped,
+                            format!(
+                                "Dropped {} of {} off-mesh links with an end off the walkable mesh",
+                                placed.dropped.len(),
+                                requested.len()
+                            ),
+                            serde_json::json!({
+                                "reason": "end_off_mesh",
+                                "dropped": placed.dropped,
+                            }),
+                        );
+                    }
+                    off_mesh_links = Some(placed);
+                }
+                Err(panic) => partial = Some(panic.into()),
+            }
+        }
+
+        let mut mesh = MeshBuffers::new(mesh.vertices, mesh.indices);
+        if settings.emit_colors.unwrap_or(false) || chain_colors.is_some() {
+            let sampled = stage::run("vertex_colors", || {
+                let radius = chain_colors.unwrap_or_else(|| {
+                    vertex_color::sample_radius(settings, &context.filtered_points)
+                });
+                let sample = |vertices: &[f32]| {
+                    vertex_color::sample_vertex_colors(vertices, &context.filtered_points, radius)
+                };
+                let plane_colors: Vec<Vec<f32>> = planes
+                    .iter()
+                    .flatten()
+                    .map(|plane| sample(&plane.mesh.vertices))
+                    .collect();
+                (sample(&mesh.vertices), plane_colors)
+            });
+            match sampled {
+                Ok((colors, plane_colors)) => {
+                    mesh.colors = Some(colors);
+                    for (plane, colors) in planes.iter_mut().flatten().zip(plane_colors) {
+                        plane.mesh.colors = Some(colors);
+                    }
+                }
+                Err(panic) => partial = Some(panic.into()),
+            }
+        }
+        let frame = GroundFrame::new(settings, diagnostics.floor_plane.clone(), grid);
+        let grid_extent = [
+            diagnostics.collision_grid_width as f64 * diagnostics.collision_voxel_size,
+            diagnostics.collision_grid_depth as f64 * diagnostics.collision_voxel_size,
+        ];
+        if let Some(mapper) = UvMapper::from_settings(settings, &frame, grid_extent) {
+            let mapped = stage::run("uvs", || {
+                let plane_uvs: Vec<Vec<f32>> = planes
+                    .iter()
+                    .flatten()
+                    .map(|plane| mapper.map(&plane.mesh.vertices, &plane.mesh.normals))
+                    .collect();
+                (mapper.map(&mesh.vertices, &mesh.normals), plane_uvs)
+            });
+            match mapped {
+                Ok((uvs, plane_uvs)) => {
+                    mesh.uvs = Some(uvs);
+                    for (plane, uvs) in planes.iter_mut().flatten().zip(plane_uvs) {
+                        plane.mesh.uvs = Some(uvs);
+                    }
+                }
+                Err(panic) => partial = Some(panic.into()),
+            }
+        }
+        if settings.emit_deviation.unwrap_or(false) {
+            let range = settings.deviation_range.unwrap_or(deviation::DEFAULT_RANGE);
+            match stage::run("deviation", || {
+                deviation::measure(&mesh.vertices, &context.filtered_points, range)
+            }) {
+                Ok(measured) => {
+                    diagnostics.deviation_mean = measured.mean;
+                    diagnostics.deviation_max = measured.max;
+                    mesh.vertex_deviation = Some(measured.distances);
+                    mesh.deviation_colors = Some(measured.colors);
+                }
+                Err(panic) => partial = Some(panic.into()),
+            }
+        }
+        // After the colors, which it draws with.
+        let mut rendered = None;
+        if let Some(camera) = &settings.emit_thumbnail {
+            match stage::run("thumbnail", || thumbnail::render(&mesh, camera)) {
+                Ok(Ok(image)) => rendered = Some(image),
+                Ok(Err(message)) => journal::record(
+                    "thumbnail",
+                    JournalKind::Degraded,
+                    format!("Thumbnail not written: {}", message),
+                    serde_json::json!({ "reason": "encode_failed", "message": message }),
+                ),
+                Err(panic) => partial = Some(panic.into()),
+            }
+        }
+        let mut detour_navmesh = None;
+        let export = settings.emit_detour_navmesh.unwrap_or(false);
+        if let Some(navmesh) = navmesh.as_ref().filter(|_| export) {
+            let agent = DetourAgent::from_settings(settings);
+            let cell_size = diagnostics.collision_voxel_size;
+            let links = off_mesh_links.as_ref().map_or(&[][..], |l| &l.links[..]);
+            match stage::run("detour_export", || {
+                detour::navmesh_set(navmesh, links, &agent, cell_size)
+            }) {
+                Ok(Ok(bytes)) => detour_navmesh = Some(serde_bytes::ByteBuf::from(bytes)),
+                Ok(Err(message)) => journal::record(
+                    "detour_export",
+                    JournalKind::Degraded,
+                    format!("Detour navmesh not written: {}", message),
+                    serde_json::json!({ "reason": "unsupported_navmesh", "message": message }),
+                ),
+                Err(panic) => partial = Some(panic.into()),
+            }
+        }
+        // Last, so the tiles carry the colors and index the final faces.
+        let mut tile_index = None;
+        if let Some(layout) = tiles {
+            match stage::run("tiles", || tiling::split(&mesh, &layout)) {
+                Ok(index) => {
+                    diagnostics.tile_count = index.tiles.len();
+                    tile_index = Some(index);
+                }
+                Err(panic) => partial = Some(panic.into()),
+            }
+        }
+
+        ReconstructionResult {
+            api_version: crate::API_VERSION,
+            semver: crate::core_semver(),
+            capabilities: crate::capabilities(),
+            mesh,
+            planes,
+            space: CoordinateSpace::splatwalk_oriented(),
+            diagnostics,
+            unreachable,
+            navmesh,
+            detour_navmesh,
+            off_mesh_links,
+            floor_layers: layers,
+            occupancy_grid: occupancy,
+            distance_field,
+            flow_field: flow,
+            hazards: hazard_tags,
+            path_costs,
+            tiles: tile_index,
+            frame,
+            source_mesh: None,
+            #[cfg(feature = "draco")]
+            draco: None,
+            partial,
+            thumbnail: rendered,
+            heightmap,
+            manifest: None,
+        }
     }
 }
 
@@ -238,6 +964,7 @@ pub fn convert_splat_to_navmesh_basis(
     let context = build_context(points, settings);
     let mut diagnostics = context.diagnostics.clone();
     let collision = build_collision_mesh(&context, settings, &mut diagnostics, false);
+    let partial = collision.as_ref().and_then(|c| c.partial.clone());
     let (mesh, basis, plane, diagnostics) = if let Some(collision) = collision {
         (
             collision.mesh,
@@ -246,6 +973,7 @@ pub fn convert_splat_to_navmesh_basis(
             collision.diagnostics,
         )
     } else {
+        journal_collision_failure(&diagnostics);
         (
             ReconstructedMesh {
                 vertices: vec![],
@@ -269,6 +997,7 @@ pub fn convert_splat_to_navmesh_basis(
         basis,
         floor_plane: plane,
         diagnostics,
+        partial,
     }
 }
 
@@ -280,6 +1009,7 @@ pub fn build_collision_voxel_boundary(
     let context = build_context(points, settings);
     let mut diagnostics = context.diagnostics.clone();
     let collision = build_collision_mesh(&context, settings, &mut diagnostics, emit_volume);
+    let partial = collision.as_ref().and_then(|c| c.partial.clone());
     let (mesh, basis, plane, diagnostics, volume) = if let Some(collision) = collision {
         (
             collision.mesh,
@@ -289,6 +1019,7 @@ pub fn build_collision_voxel_boundary(
             collision.volume,
         )
     } else {
+        journal_collision_failure(&diagnostics);
         (
             ReconstructedMesh {
                 vertices: vec![],
@@ -315,6 +1046,7 @@ pub fn build_collision_voxel_boundary(
         basis,
         floor_plane: plane,
         diagnostics,
+        partial,
     }
 }
 
@@ -324,8 +1056,15 @@ pub fn build_walkable_ground_field(
 ) -> Result<WalkableGroundFieldResult, wasm_bindgen::JsValue> {
     let context = build_context(points, settings);
     let mut diagnostics = context.diagnostics.clone();
-    let field = build_field(&context, settings, &mut diagnostics)
-        .ok_or_else(|| wasm_bindgen::JsValue::from_str("Unable to build walkable ground field"))?;
+    let field = build_field(&context, settings, &mut diagnostics).ok_or_else(|| {
+        crate::SplatwalkError::ReconstructionFailed(
+            "Unable to build walkable ground field".to_string(),
+        )
+    })?;
+    let preview = settings
+        .emit_preview
+        .as_deref()
+        .map(|channel| crate::preview::render(&field.cells, field.width, field.height, channel));
 
     Ok(WalkableGroundFieldResult {
         api_version: crate::API_VERSION,
@@ -339,9 +1078,514 @@ pub fn build_walkable_ground_field(
         floor_plane: field.plane,
         space: CoordinateSpace::splatwalk_oriented(),
         diagnostics: field.diagnostics,
+        preview,
+        sensitivity: None,
+    })
+}
+
+/// `emit_sensitivity`: rebuild the ground field at surrogate resolution with
+/// each numeric setting nudged. `settings` is restored before returning.
+pub fn ground_field_sensitivity(
+    points: &[PointNormal],
+    settings: &mut MeshSettings,
+) -> Option<SensitivityReport> {
+    // The bake already journaled filtering this cloud.
+    let mark = journal::position();
+    let context = build_context(points, settings);
+    journal::rewind(mark);
+    let tolerance = context.tolerance;
+    sensitivity::analyze(settings, &tolerance, |settings| {
+        let mut diagnostics = context.diagnostics.clone();
+        build_field(&context, settings, &mut diagnostics).map(|field| sensitivity::Outcome {
+            area: field.diagnostics.selected_component_area,
+            components: field.diagnostics.connected_components,
+            cell_size: field.cell_size,
+        })
     })
 }
 
+/// Fit a vector floor plan to the wall cluster. Clusters with the
+/// `normal_clusters` options whether or not that flag is set.
+pub fn build_floor_plan(
+    points: &[PointNormal],
+    settings: &MeshSettings,
+) -> Result<FloorPlanResult, wasm_bindgen::JsValue> {
+    let context = build_context(points, settings);
+    let points = &context.filtered_points;
+    if points.is_empty() {
+        return Err(crate::SplatwalkError::EmptyCloud(
+            "No splats left for the floor plan after filtering".to_string(),
+        )
+        .into());
+    }
+    let mut diagnostics = context.diagnostics.clone();
+    let mut y_values: Vec<f64> = points.iter().map(|p| p.point.y).collect();
+    let floor_y = percentile(&mut y_values, 0.02);
+    let labels = cluster::classify(points, floor_y, &NormalClusterOptions::resolve(settings));
+    diagnostics.normal_clusters = cluster::summarize(points, &labels);
+
+    let options = FloorPlanOptions::from_settings(settings);
+    let plan = floor_plan::build(
+        points,
+        &labels,
+        floor_y,
+        &options,
+        ransac::bake_seed(settings),
+    );
+    if plan.segments.is_empty() {
+        return Err(crate::SplatwalkError::ReconstructionFailed(format!(
+            "No wall segments found ({} wall splats in {} plan cells)",
+            plan.wall_splat_count, plan.wall_cell_count
+        ))
+        .into());
+    }
+
+    let mut field_diagnostics = context.diagnostics.clone();
+    let (floor_outline, floor_area) = build_field(&context, settings, &mut field_diagnostics)
+        .map(|field| floor_outline(&field))
+        .unwrap_or_default();
+
+    let mut bounds_min = [f64::MAX; 2];
+    let mut bounds_max = [f64::MIN; 2];
+    for p in plan
+        .segments
+        .iter()
+        .flat_map(|s| [s.start, s.end])
+        .chain(floor_outline.iter().copied())
+    {
+        for axis in 0..2 {
+            bounds_min[axis] = bounds_min[axis].min(p[axis]);
+            bounds_max[axis] = bounds_max[axis].max(p[axis]);
+        }
+    }
+    let ifc = settings.emit_ifc.unwrap_or(false).then(|| {
+        // Default wall height: top of the wall cluster above the floor.
+        let measured = diagnostics
+            .normal_clusters
+            .iter()
+            .find(|c| c.label == NormalGroup::Wall.name())
+            .map(|c| c.max[1] - floor_y)
+            .filter(|h| *h > 0.5);
+        cad_export::to_ifc(
+            &plan.segments,
+            &floor_outline,
+            &IfcOptions {
+                floor_y,
+                wall_height: settings
+                    .wall_height
+                    .filter(|v| v.is_finite() && *v > 0.0)
+                    .or(measured)
+                    .unwrap_or(2.5),
+                wall_thickness: settings
+                    .wall_thickness
+                    .filter(|v| v.is_finite() && *v > 0.0)
+                    .unwrap_or(0.1),
+                slab_thickness: 0.2,
+            },
+        )
+    });
+
+    Ok(FloorPlanResult {
+        api_version: crate::API_VERSION,
+        semver: crate::core_semver(),
+        capabilities: crate::capabilities(),
+        floor_y: plan.floor_y,
+        dominant_angle_deg: plan.dominant_angle_deg,
+        wall_splat_count: plan.wall_splat_count,
+        wall_cell_count: plan.wall_cell_count,
+        svg: cad_export::to_svg(&plan.segments, &floor_outline, bounds_min, bounds_max),
+        dxf: cad_export::to_dxf(&plan.segments, &floor_outline),
+        ifc,
+        segments: plan.segments,
+        floor_outline,
+        floor_area,
+        bounds_min,
+        bounds_max,
+        space: CoordinateSpace::splatwalk_oriented(),
+        diagnostics,
+    })
+}
+
+/// Grow a VR safe area around `safe_area_center` (default: the most open
+/// floor cell) on the walkable ground field. See `safe_area`.
+pub fn build_safe_area(
+    points: &[PointNormal],
+    settings: &MeshSettings,
+) -> Result<SafeAreaResult, wasm_bindgen::JsValue> {
+    let context = build_context(points, settings);
+    let mut diagnostics = context.diagnostics.clone();
+    let field = build_field(&context, settings, &mut diagnostics).ok_or_else(|| {
+        crate::SplatwalkError::ReconstructionFailed(
+            "Unable to build walkable ground field".to_string(),
+        )
+    })?;
+    let options = SafeAreaOptions::from_settings(settings);
+    let (width, height, cs) = (field.width, field.height, field.cell_size);
+    let o = field.basis.origin;
+    let axes = [field.basis.tangent, field.basis.bitangent, field.basis.up];
+    let local = |p: [f64; 3]| {
+        let d = [p[0] - o[0], p[1] - o[1], p[2] - o[2]];
+        axes.map(|a| d[0] * a[0] + d[1] * a[1] + d[2] * a[2])
+    };
+
+    // Opacity hanging over each cell between the floor band and the ceiling.
+    let band = cs.max(field.diagnostics.sdf_vertical_cell_size * 2.0);
+    let mut overhead = vec![0.0; field.cells.len()];
+    for p in &context.filtered_points {
+        let [u, v, h] = local([p.point.x, p.point.y, p.point.z]);
+        if u < 0.0 || v < 0.0 {
+            continue;
+        }
+        let (col, row) = ((u / cs) as usize, (v / cs) as usize);
+        if col >= width || row >= height {
+            continue;
+        }
+        let idx = row * width + col;
+        let above = h - field.cells[idx].height as f64;
+        if above > band && above < options.ceiling_height {
+            overhead[idx] += p.opacity;
+        }
+    }
+    let free: Vec<bool> = field
+        .cells
+        .iter()
+        .zip(&overhead)
+        .map(|(cell, &w)| is_accepted_state(&cell.state) && w < safe_area::MIN_OVERHEAD_WEIGHT)
+        .collect();
+    let distance = safe_area::clearance_field(&free, width, height, cs);
+    let clear: Vec<bool> = distance.iter().map(|&d| d >= options.clearance).collect();
+
+    let center = match settings.safe_area_center.as_deref() {
+        Some(&[x, y, z, ..]) => {
+            let [u, v, _] = local([x, y, z]);
+            [u, v]
+        }
+        _ => {
+            let (best, clearance) = distance
+                .iter()
+                .enumerate()
+                .max_by(|a, b| a.1.total_cmp(b.1))
+                .map(|(idx, &d)| (idx, d))
+                .unwrap_or((0, 0.0));
+            journal::record(
+                "safe_area",
+                JournalKind::Decision,
+                format!(
+                    "Standing point defaulted to the most open cell ({:.2} m clear)",
+                    clearance
+                ),
+                serde_json::json!({ "cell": best, "clearance": clearance }),
+            );
+            [
+                ((best % width) as f64 + 0.5) * cs,
+                ((best / width) as f64 + 0.5) * cs,
+            ]
+        }
+    };
+    let area = safe_area::grow(&clear, width, height, cs, center, &options).ok_or_else(|| {
+        crate::SplatwalkError::ReconstructionFailed(format!(
+            "Standing point is not {:.2} m clear of obstacles under a {:.2} m ceiling",
+            options.clearance, options.ceiling_height
+        ))
+    })?;
+
+    let center_cell = (center[1] / cs) as usize * width + (center[0] / cs) as usize;
+    let floor_h = field.cells[center_cell].height as f64;
+    let floor_h = if floor_h.is_finite() { floor_h } else { 0.0 };
+    let world = |[u, v]: [f64; 2], h: f64| {
+        [0, 1, 2].map(|i| o[i] + axes[0][i] * u + axes[1][i] * v + axes[2][i] * h)
+    };
+    let mut boundary: Vec<[f64; 2]> = area
+        .boundary
+        .iter()
+        .map(|&p| {
+            let [x, _, z] = world(p, floor_h);
+            [x, z]
+        })
+        .collect();
+    if floor_plan::signed_area(&boundary) < 0.0 {
+        boundary.reverse();
+    }
+    let center = world(center, floor_h);
+
+    Ok(SafeAreaResult {
+        api_version: crate::API_VERSION,
+        semver: crate::core_semver(),
+        capabilities: crate::capabilities(),
+        center,
+        floor_y: center[1],
+        shape: options.shape.name().to_string(),
+        boundary,
+        area: area.area,
+        inscribed_radius: area.inscribed_radius,
+        clearance: options.clearance,
+        ceiling_height: options.ceiling_height,
+        space: CoordinateSpace::splatwalk_oriented(),
+        diagnostics: field.diagnostics,
+    })
+}
+
+/// The filter verdict of every splat, as ellipsoids for
+/// `build_splat_ellipsoids`. `rotation_of` looks up a splat's source quaternion
+/// `(w, x, y, z)` by its index; without one the ellipsoid turns its third
+/// axis onto the splat normal.
+pub fn build_splat_ellipsoids(
+    points: &[PointNormal],
+    rotation_of: &dyn Fn(u32) -> Option<[f32; 4]>,
+    settings: &MeshSettings,
+) -> Result<SplatEllipsoids, wasm_bindgen::JsValue> {
+    let context = build_context(points, settings);
+    if context.oriented_points.is_empty() {
+        return Err(crate::SplatwalkError::EmptyCloud(
+            "No valid oriented points for ellipsoids".to_string(),
+        )
+        .into());
+    }
+    let kept: std::collections::HashSet<u32> =
+        context.filtered_points.iter().map(|p| p.index).collect();
+    let region = RegionFilter::from_settings(settings);
+    let env_scale = environment_scale(settings);
+    let frame = rotation(settings).map_or_else(nalgebra::Matrix3::identity, |m| {
+        m.into_inner().cast::<f64>()
+    }) * ellipsoid::ingest_frame(settings);
+    let shapes = context
+        .oriented_points
+        .iter()
+        .map(|p| {
+            // Oriented scales are log-space scales times `environment_scale`.
+            let radii = (p.scale / env_scale).map(f64::exp) * env_scale;
+            let turn = match rotation_of(p.index) {
+                Some([w, x, y, z]) => {
+                    let q = UnitQuaternion::new_normalize(nalgebra::Quaternion::new(
+                        w as f64, x as f64, y as f64, z as f64,
+                    ));
+                    frame * q.to_rotation_matrix().into_inner()
+                }
+                None => UnitQuaternion::rotation_between(&Vector3::z(), &p.normal)
+                    .unwrap_or_else(|| {
+                        UnitQuaternion::from_axis_angle(&Vector3::x_axis(), std::f64::consts::PI)
+                    })
+                    .to_rotation_matrix()
+                    .into_inner(),
+            };
+            let verdict = if kept.contains(&p.index) {
+                SplatVerdict::Kept
+            } else if !region.contains(&p.point) {
+                SplatVerdict::OutsideRegion
+            } else {
+                SplatVerdict::FaintOrLarge
+            };
+            SplatShape {
+                center: p.point,
+                axes: turn * nalgebra::Matrix3::from_diagonal(&radii),
+                opacity: p.opacity,
+                color: p.color,
+                index: p.index,
+                verdict,
+            }
+        })
+        .collect();
+    Ok(ellipsoid::build(
+        shapes,
+        &EllipsoidOptions::from_settings(settings),
+        CoordinateSpace::splatwalk_oriented(),
+    ))
+}
+
+pub fn find_chokepoints(
+    points: &[PointNormal],
+    settings: &MeshSettings,
+) -> Result<ChokepointResult, wasm_bindgen::JsValue> {
+    let context = build_context(points, settings);
+    let mut diagnostics = context.diagnostics.clone();
+    let field = build_field(&context, settings, &mut diagnostics).ok_or_else(|| {
+        crate::SplatwalkError::ReconstructionFailed(
+            "Unable to build walkable ground field".to_string(),
+        )
+    })?;
+    let options = ChokepointOptions::from_settings(settings);
+    let (width, height, cs) = (field.width, field.height, field.cell_size);
+    let free: Vec<bool> = field
+        .cells
+        .iter()
+        .map(|cell| is_accepted_state(&cell.state))
+        .collect();
+    let clearance = safe_area::clearance_field(&free, width, height, cs);
+    let passages = chokepoint::find(&clearance, width, height, cs, &options);
+
+    let (o, axes) = (
+        field.basis.origin,
+        [field.basis.tangent, field.basis.bitangent, field.basis.up],
+    );
+    let world = |u: f64, v: f64, h: f64| {
+        [0, 1, 2].map(|i| o[i] + axes[0][i] * u + axes[1][i] * v + axes[2][i] * h)
+    };
+    let chokepoints = passages
+        .iter()
+        .map(|p| {
+            let floor_h = field.cells[p.cell].height as f64;
+            let h = if floor_h.is_finite() { floor_h } else { 0.0 };
+            let u = ((p.cell % width) as f64 + 0.5) * cs;
+            let v = ((p.cell / width) as f64 + 0.5) * cs;
+            let [du, dv] = p.across.map(|a| a * p.width * 0.5);
+            Chokepoint {
+                position: world(u, v, h),
+                width: p.width,
+                ends: [world(u + du, v + dv, h), world(u - du, v - dv, h)],
+                sides: p.sides,
+            }
+        })
+        .collect::<Vec<_>>();
+    journal::record(
+        "chokepoints",
+        JournalKind::Decision,
+        format!(
+            "Found {} chokepoint(s) between {:.2} and {:.2} m wide",
+            chokepoints.len(),
+            options.min_width,
+            options.max_width
+        ),
+        serde_json::json!({
+            "count": chokepoints.len(),
+            "min_width": options.min_width,
+            "max_width": options.max_width,
+        }),
+    );
+
+    Ok(ChokepointResult {
+        api_version: crate::API_VERSION,
+        semver: crate::core_semver(),
+        capabilities: crate::capabilities(),
+        chokepoints,
+        min_width: options.min_width,
+        max_width: options.max_width,
+        space: CoordinateSpace::splatwalk_oriented(),
+        diagnostics: field.diagnostics,
+    })
+}
+
+pub fn extract_floor_polygons(
+    points: &[PointNormal],
+    settings: &MeshSettings,
+) -> Result<FloorPolygonsResult, wasm_bindgen::JsValue> {
+    let context = build_context(points, settings);
+    let mut diagnostics = context.diagnostics.clone();
+    let field = build_field(&context, settings, &mut diagnostics).ok_or_else(|| {
+        crate::SplatwalkError::ReconstructionFailed(
+            "Unable to build walkable ground field".to_string(),
+        )
+    })?;
+    let options = FloorPolygonOptions::from_settings(settings);
+    let (width, cs) = (field.width, field.cell_size);
+    let mask: Vec<bool> = field
+        .cells
+        .iter()
+        .map(|cell| is_accepted_state(&cell.state))
+        .collect();
+    let found = floor_polygons::extract(&mask, width, field.height, cs, &options);
+
+    let (o, axes) = (
+        field.basis.origin,
+        [field.basis.tangent, field.basis.bitangent, field.basis.up],
+    );
+    let plan = |ring: &[[f64; 2]]| -> Vec<[f64; 2]> {
+        ring.iter()
+            .map(|&[col, row]| {
+                [
+                    o[0] + (axes[0][0] * col + axes[1][0] * row) * cs,
+                    o[2] + (axes[0][2] * col + axes[1][2] * row) * cs,
+                ]
+            })
+            .collect()
+    };
+    let polygons: Vec<FloorPolygon> = found
+        .islands
+        .iter()
+        .map(|island| {
+            let (mut min_y, mut max_y) = (f64::INFINITY, f64::NEG_INFINITY);
+            for &c in &island.cells {
+                let h = field.cells[c].height as f64;
+                let h = if h.is_finite() { h } else { 0.0 };
+                let u = ((c % width) as f64 + 0.5) * cs;
+                let v = ((c / width) as f64 + 0.5) * cs;
+                let y = o[1] + axes[0][1] * u + axes[1][1] * v + axes[2][1] * h;
+                min_y = min_y.min(y);
+                max_y = max_y.max(y);
+            }
+            FloorPolygon {
+                outer: plan(&island.outer),
+                holes: island.holes.iter().map(|hole| plan(hole)).collect(),
+                area: island.cells.len() as f64 * cs * cs,
+                min_y,
+                max_y,
+            }
+        })
+        .collect();
+    let holes: usize = polygons.iter().map(|p| p.holes.len()).sum();
+    journal::record(
+        "floor_polygons",
+        JournalKind::Decision,
+        format!(
+            "Traced {} floor polygon(s) with {} hole(s); {} island(s) and {} hole(s) under {} m² dropped",
+            polygons.len(),
+            holes,
+            found.dropped_islands,
+            found.dropped_holes,
+            options.min_area
+        ),
+        serde_json::json!({
+            "polygons": polygons.len(),
+            "holes": holes,
+            "dropped_islands": found.dropped_islands,
+            "dropped_holes": found.dropped_holes,
+            "min_area": options.min_area,
+        }),
+    );
+
+    Ok(FloorPolygonsResult {
+        api_version: crate::API_VERSION,
+        semver: crate::core_semver(),
+        capabilities: crate::capabilities(),
+        polygons,
+        dropped_islands: found.dropped_islands,
+        dropped_holes: found.dropped_holes,
+        tolerance: options.tolerance.unwrap_or(cs),
+        min_area: options.min_area,
+        space: CoordinateSpace::splatwalk_oriented(),
+        diagnostics: field.diagnostics,
+    })
+}
+
+/// Outer boundary of the walkable component of the ground field in plan
+/// `[x, z]`, mapped from grid corners through the field basis, and its area.
+fn floor_outline(field: &FieldBuild) -> (Vec<[f64; 2]>, f64) {
+    let mask: Vec<bool> = field
+        .cells
+        .iter()
+        .map(|cell| is_accepted_state(&cell.state))
+        .collect();
+    let ring = floor_plan::simplify_ring(
+        &floor_plan::trace_outline(&mask, field.width, field.height),
+        1.0,
+    );
+    let (o, t, b, cs) = (
+        field.basis.origin,
+        field.basis.tangent,
+        field.basis.bitangent,
+        field.cell_size,
+    );
+    let outline = ring
+        .iter()
+        .map(|&[col, row]| {
+            [
+                o[0] + (t[0] * col + b[0] * row) * cs,
+                o[2] + (t[2] * col + b[2] * row) * cs,
+            ]
+        })
+        .collect();
+    (outline, floor_plan::signed_area(&ring) * cs * cs)
+}
+
 fn default_field_basis() -> FieldBasis {
     FieldBasis {
         origin: [0.0, 0.0, 0.0],
@@ -351,6 +1595,16 @@ fn default_field_basis() -> FieldBasis {
     }
 }
 
+/// Vertical relief factor for meshed heightfields (default 1, clamped to
+/// `[0, 10]`): 0 flattens every cell onto the floor plane, values above 1
+/// exaggerate relief.
+fn relief_scale(settings: &MeshSettings) -> f64 {
+    match settings.relief_scale {
+        Some(s) if s.is_finite() && s >= 0.0 => s.min(10.0),
+        _ => 1.0,
+    }
+}
+
 fn environment_scale(settings: &MeshSettings) -> f64 {
     match settings.environment_scale {
         Some(s) if s.is_finite() && s > 0.0 => s,
@@ -358,14 +1612,9 @@ fn environment_scale(settings: &MeshSettings) -> f64 {
     }
 }
 
-fn build_context(points: &[PointNormal], settings: &MeshSettings) -> ReconstructionContext {
-    let min_alpha = settings.min_alpha.unwrap_or(0.05);
-    let max_scale = settings.max_scale.unwrap_or(5.0);
-    let env_scale = environment_scale(settings);
-    // Filter against authoring-space gaussian scales; positions/scales are then
-    // multiplied by env_scale so world-space bake matches the renderer.
-    let max_scale_world = max_scale * env_scale;
-    let rot_matrix = settings.rotation.as_ref().and_then(|rot| {
+/// The `rotation` setting (Euler angles in radians), when it has three values.
+fn rotation(settings: &MeshSettings) -> Option<Rotation3<Real>> {
+    settings.rotation.as_ref().and_then(|rot| {
         if rot.len() == 3 {
             let q =
                 UnitQuaternion::from_euler_angles(rot[0] as Real, rot[1] as Real, rot[2] as Real);
@@ -373,7 +1622,32 @@ fn build_context(points: &[PointNormal], settings: &MeshSettings) -> Reconstruct
         } else {
             None
         }
-    });
+    })
+}
+
+/// `point` mapped into the bake frame as [`build_context`] maps splat
+/// centres: `rotation`, then `environment_scale`.
+pub fn orient_point(point: &Point3<f64>, settings: &MeshSettings) -> Point3<f64> {
+    let mut pt = Point3::new(point.x as Real, point.y as Real, point.z as Real);
+    if let Some(m) = rotation(settings) {
+        pt = m.transform_point(&pt);
+    }
+    let env_scale = environment_scale(settings);
+    Point3::new(
+        pt.x as f64 * env_scale,
+        pt.y as f64 * env_scale,
+        pt.z as f64 * env_scale,
+    )
+}
+
+fn build_context(points: &[PointNormal], settings: &MeshSettings) -> ReconstructionContext {
+    let min_alpha = settings.min_alpha.unwrap_or(0.05);
+    let max_scale = settings.max_scale.unwrap_or(5.0);
+    let env_scale = environment_scale(settings);
+    // Filter against authoring-space gaussian scales; positions/scales are then
+    // multiplied by env_scale so world-space bake matches the renderer.
+    let max_scale_world = max_scale * env_scale;
+    let rot_matrix = rotation(settings);
 
     let mut diagnostics = ReconstructionDiagnostics::empty(points.len());
     diagnostics.region_min = settings.region_min.clone();
@@ -411,6 +1685,8 @@ fn build_context(points: &[PointNormal], settings: &MeshSettings) -> Reconstruct
                 p.scale.z * env_scale,
             ),
             opacity: p.opacity,
+            color: p.color,
+            index: p.index,
         };
 
         let coords = [oriented.point.x, oriented.point.y, oriented.point.z];
@@ -428,22 +1704,14 @@ fn build_context(points: &[PointNormal], settings: &MeshSettings) -> Reconstruct
         diagnostics.floor_y_percentile_02 = Some(percentile(&mut y_values, 0.02));
     }
 
+    let region = RegionFilter::from_settings(settings);
     let mut filtered_points = Vec::with_capacity(oriented_points.len());
+    let mut points_faint_or_large = 0usize;
 
     for p in &oriented_points {
-        if let (Some(region_min), Some(region_max)) = (&settings.region_min, &settings.region_max) {
-            if region_min.len() == 3 && region_max.len() == 3 {
-                if p.point.x < region_min[0]
-                    || p.point.x > region_max[0]
-                    || p.point.y < region_min[1]
-                    || p.point.y > region_max[1]
-                    || p.point.z < region_min[2]
-                    || p.point.z > region_max[2]
-                {
-                    diagnostics.points_region_discarded += 1;
-                    continue;
-                }
-            }
+        if !region.contains(&p.point) {
+            diagnostics.points_region_discarded += 1;
+            continue;
         }
 
         if p.opacity <= min_alpha
@@ -451,6 +1719,7 @@ fn build_context(points: &[PointNormal], settings: &MeshSettings) -> Reconstruct
             || p.scale.y >= max_scale_world
             || p.scale.z >= max_scale_world
         {
+            points_faint_or_large += 1;
             continue;
         }
 
@@ -458,11 +1727,32 @@ fn build_context(points: &[PointNormal], settings: &MeshSettings) -> Reconstruct
     }
 
     diagnostics.points_after_filter = filtered_points.len();
+    for (reason, count) in [
+        ("non_finite_position", diagnostics.points_invalid),
+        ("outside_region", diagnostics.points_region_discarded),
+        ("below_min_alpha_or_above_max_scale", points_faint_or_large),
+    ] {
+        if count > 0 {
+            journal::record(
+                "filter",
+                JournalKind::Dropped,
+                format!("Dropped {} splats: {}", count, reason),
+                serde_json::json!({ "reason": reason, "count": count }),
+            );
+        }
+    }
+
+    diagnostics.seed = ransac::bake_seed(settings);
+    let tolerance = epsilon::resolve(settings, &filtered_points);
+    diagnostics.epsilon = tolerance.epsilon;
+    diagnostics.epsilon_source = tolerance.source.to_string();
 
     ReconstructionContext {
         oriented_points,
         filtered_points,
         diagnostics,
+        tolerance,
+        tile_bounds: None,
     }
 }
 
@@ -476,20 +1766,236 @@ fn percentile(values: &mut [f64], p: f64) -> f64 {
     values[idx]
 }
 
-fn reconstruct_voxel_navmesh(
+/// `tile_size`: build the collision mesh tile by tile, each on a grid pinned
+/// to the tile and its border, and weld the clipped pieces into one mesh.
+fn reconstruct_tiled_navmesh(
     context: &ReconstructionContext,
     settings: &MeshSettings,
     diagnostics: &mut ReconstructionDiagnostics,
+    partial: &mut Option<PartialResult>,
+    tiles: &mut Option<TileLayout>,
 ) -> ReconstructedMesh {
-    let Some(collision) = build_collision_mesh(context, settings, diagnostics, false) else {
-        return ReconstructedMesh {
-            vertices: vec![],
-            indices: vec![],
+    let empty = || ReconstructedMesh {
+        vertices: vec![],
+        indices: vec![],
+    };
+    let Some((points, bounds_min, bounds_max)) = tiled_points(context, settings, diagnostics)
+    else {
+        return empty();
+    };
+
+    let layout = TileLayout::new(bounds_min, bounds_max, settings);
+    let total = layout.columns * layout.rows;
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    let mut built = 0;
+    for tile in 0..total {
+        let (column, row) = (tile % layout.columns, tile / layout.columns);
+        crate::emit_progress("collision_tiles", Some(tile as f64 / total as f64));
+        let Some(piece) = build_tile(
+            context,
+            &points,
+            settings,
+            diagnostics,
+            partial,
+            &layout,
+            (column, row),
+        ) else {
+            continue;
+        };
+        let offset = (vertices.len() / 3) as u32;
+        vertices.extend(piece.vertices);
+        indices.extend(piece.indices.iter().map(|i| i + offset));
+        built += 1;
+    }
+    journal::record(
+        "tiles",
+        JournalKind::Decision,
+        format!("Built {} of {} tiles of {} m", built, total, layout.size),
+        serde_json::json!({
+            "built": built,
+            "tiles": total,
+            "columns": layout.columns,
+            "rows": layout.rows,
+            "tile_size": layout.size,
+            "tile_border": layout.border,
+        }),
+    );
+    *tiles = Some(layout);
+    if built == 0 {
+        diagnostics.collision_failure_reason = Some("no_tile_built".to_string());
+        journal_collision_failure(diagnostics);
+        return empty();
+    }
+
+    // Neighbouring tiles share voxel-aligned seams; welding joins them.
+    let pieces = ReconstructedMesh { vertices, indices };
+    let tolerance = context.tolerance;
+    postprocess::weld(&pieces, tolerance.weld(), tolerance.degenerate_area())
+}
+
+/// The collision grid bounds and the cluster-filtered cloud every tile is cut
+/// from, or `None` (journaled) when there is nothing to tile.
+fn tiled_points(
+    context: &ReconstructionContext,
+    settings: &MeshSettings,
+    diagnostics: &mut ReconstructionDiagnostics,
+) -> Option<(Vec<PointNormal>, Vector3<f64>, Vector3<f64>)> {
+    let mut points = context.filtered_points.clone();
+    let Some((bounds_min, bounds_max)) = collision_grid_bounds(diagnostics, settings) else {
+        diagnostics.collision_failure_reason = Some("no_bounds".to_string());
+        journal_collision_failure(diagnostics);
+        return None;
+    };
+    if !filter_collision_cluster(&mut points, settings, diagnostics) {
+        journal_collision_failure(diagnostics);
+        return None;
+    }
+    Some((points, bounds_min, bounds_max))
+}
+
+/// The collision mesh of one tile of `layout`, clipped to its square, or
+/// `None` when the tile holds no points or its grid failed (journaled). Grid
+/// statistics are added to `diagnostics`.
+fn build_tile(
+    context: &ReconstructionContext,
+    points: &[PointNormal],
+    settings: &MeshSettings,
+    diagnostics: &mut ReconstructionDiagnostics,
+    partial: &mut Option<PartialResult>,
+    layout: &TileLayout,
+    (column, row): (usize, usize),
+) -> Option<ReconstructedMesh> {
+    let (tile_min, tile_max) = layout.bounds(column, row);
+    let tile_points: Vec<PointNormal> = points
+        .iter()
+        .filter(|p| {
+            (tile_min.x..=tile_max.x).contains(&p.point.x)
+                && (tile_min.z..=tile_max.z).contains(&p.point.z)
+        })
+        .cloned()
+        .collect();
+    if tile_points.is_empty() {
+        return None;
+    }
+    let tile_context = ReconstructionContext {
+        oriented_points: Vec::new(),
+        filtered_points: tile_points,
+        diagnostics: context.diagnostics.clone(),
+        tolerance: context.tolerance,
+        tile_bounds: Some((tile_min, tile_max)),
+    };
+    let mut tile_diagnostics = diagnostics.clone();
+    let Some(collision) =
+        build_collision_mesh(&tile_context, settings, &mut tile_diagnostics, false)
+    else {
+        let reason = tile_diagnostics
+            .collision_failure_reason
+            .unwrap_or_else(|| "unknown".to_string());
+        journal::record(
+            "tiles",
+            JournalKind::Degraded,
+            format!("Tile ({}, {}) not built: {}", column, row, reason),
+            serde_json::json!({ "column": column, "row": row, "reason": reason }),
+        );
+        return None;
+    };
+    if collision.partial.is_some() {
+        *partial = collision.partial;
+    }
+    // Grid statistics add up over the tiles; the voxel size is the
+    // coarsest any tile had to fall back to.
+    let tile_diagnostics = collision.diagnostics;
+    diagnostics.collision_voxel_size = diagnostics
+        .collision_voxel_size
+        .max(tile_diagnostics.collision_voxel_size);
+    diagnostics.collision_occupied_voxels += tile_diagnostics.collision_occupied_voxels;
+    diagnostics.collision_filled_voxels += tile_diagnostics.collision_filled_voxels;
+    diagnostics.collision_carved_voxels += tile_diagnostics.collision_carved_voxels;
+    diagnostics.collision_scene_type = tile_diagnostics.collision_scene_type;
+
+    Some(layout.clip(&collision.mesh, column, row))
+}
+
+/// Tiles rebuilt by [`reconstruct_tiles`].
+pub struct TileRebuild {
+    /// The layout the tiles were cut from, its height range set to the
+    /// collision grid bounds.
+    pub layout: TileLayout,
+    /// One welded piece per requested tile, in request order; empty where the
+    /// tile holds no walkable faces.
+    pub pieces: Vec<ReconstructedMesh>,
+    pub diagnostics: ReconstructionDiagnostics,
+    pub partial: Option<PartialResult>,
+}
+
+/// Rebuild only `tiles` of `layout` from `points`, each as one tile of a
+/// `tile_size` bake is built, for `SplatSession.bake_dirty_tiles`. The
+/// layout's `y_range` is replaced by the height of the collision grid bounds.
+pub fn reconstruct_tiles(
+    points: &[PointNormal],
+    settings: &MeshSettings,
+    mut layout: TileLayout,
+    tiles: &[(usize, usize)],
+) -> TileRebuild {
+    let job = MeshJob::prepare(points, settings);
+    let context = job.context;
+    let mut diagnostics = job.diagnostics;
+    let mut partial = None;
+    let empty = || ReconstructedMesh {
+        vertices: vec![],
+        indices: vec![],
+    };
+    let Some((points, bounds_min, bounds_max)) = tiled_points(&context, settings, &mut diagnostics)
+    else {
+        return TileRebuild {
+            layout,
+            pieces: tiles.iter().map(|_| empty()).collect(),
+            diagnostics,
+            partial,
         };
     };
+    layout.y_range = [bounds_min.y, bounds_max.y];
+    let tolerance = context.tolerance;
+    let pieces = tiles
+        .iter()
+        .enumerate()
+        .map(|(i, &tile)| {
+            crate::emit_progress("collision_tiles", Some(i as f64 / tiles.len() as f64));
+            build_tile(
+                &context,
+                &points,
+                settings,
+                &mut diagnostics,
+                &mut partial,
+                &layout,
+                tile,
+            )
+            .map_or_else(empty, |piece| {
+                postprocess::weld(&piece, tolerance.weld(), tolerance.degenerate_area())
+            })
+        })
+        .collect();
+    TileRebuild {
+        layout,
+        pieces,
+        diagnostics,
+        partial,
+    }
+}
 
-    *diagnostics = collision.diagnostics;
-    collision.mesh
+/// Journal why the collision stage produced no mesh.
+fn journal_collision_failure(diagnostics: &ReconstructionDiagnostics) {
+    let reason = diagnostics
+        .collision_failure_reason
+        .as_deref()
+        .unwrap_or("unknown");
+    journal::record(
+        "collision",
+        JournalKind::Degraded,
+        format!("Collision mesh not built: {}", reason),
+        serde_json::json!({ "reason": reason }),
+    );
 }
 
 fn collision_grid_bounds(
@@ -499,13 +2005,8 @@ fn collision_grid_bounds(
     // When region is pinned, size the voxel grid to that box (PlayCanvas writeVoxel pads
     // around the working volume). Using full splat AABB for city-scale materialized
     // streams forces coarse voxel_size under the dense-grid cap and destroys stairs.
-    if let (Some(rmin), Some(rmax)) = (&settings.region_min, &settings.region_max) {
-        if rmin.len() == 3 && rmax.len() == 3 {
-            return Some((
-                Vector3::new(rmin[0], rmin[1], rmin[2]),
-                Vector3::new(rmax[0], rmax[1], rmax[2]),
-            ));
-        }
+    if let Some(bounds) = RegionFilter::from_settings(settings).bounds() {
+        return Some(bounds);
     }
 
     let min = diagnostics.oriented_min?;
@@ -546,96 +2047,246 @@ fn build_collision_mesh(
     diagnostics: &mut ReconstructionDiagnostics,
     emit_volume: bool,
 ) -> Option<CollisionBuild> {
-    let mut points = context.filtered_points.clone();
-    if points.is_empty() {
-        diagnostics.collision_failure_reason = Some("no_filtered_points".to_string());
-        return None;
-    }
+    let mut voxelize = CollisionVoxelize::new(context, settings, diagnostics)?;
+    voxelize.advance(&mut || false);
+    voxelize.finish(settings, diagnostics, emit_volume)
+}
 
-    let cluster_seed = resolve_cluster_seed(settings, diagnostics);
-    if settings.collision_filter_cluster.unwrap_or(true) {
-        crate::emit_progress("collision_cluster", None);
-        let opacity_threshold = settings
-            .collision_opacity_threshold
-            .unwrap_or(0.1)
-            .max(0.05);
-        let discarded =
-            filter_splats_coarse_cluster(&mut points, cluster_seed, opacity_threshold);
-        if discarded > 0 {
-            web_sys::console::log_1(
-                &format!(
-                    "Coarse filter-cluster (PC --filter-cluster): kept {} splats, removed {} disconnected",
-                    points.len(),
-                    discarded
-                )
-                .into(),
-            );
-        }
+/// [`build_collision_mesh`] split around splatting the cloud into the density
+/// grid, its longest loop, so `convert_splat_to_mesh_async` can yield between
+/// batches of points.
+struct CollisionVoxelize {
+    points: Vec<PointNormal>,
+    grid: VoxelGrid,
+    scene_type: String,
+    fill_size: f64,
+    region_pinned: bool,
+    threshold: f64,
+    kernel: SplatKernel,
+    density: memory::Scratch,
+    /// Points already splatted.
+    next: usize,
+}
+
+impl CollisionVoxelize {
+    /// Cluster-filter the cloud and size the grid. `None` (with
+    /// `collision_failure_reason` set) when there is nothing to voxelize or
+    /// the region is too large.
+    fn new(
+        context: &ReconstructionContext,
+        settings: &MeshSettings,
+        diagnostics: &mut ReconstructionDiagnostics,
+    ) -> Option<Self> {
+        let mut points = context.filtered_points.clone();
         if points.is_empty() {
-            diagnostics.collision_failure_reason = Some("filter_cluster_removed_all".to_string());
+            diagnostics.collision_failure_reason = Some("no_filtered_points".to_string());
             return None;
         }
-    }
 
-    let (bounds_min, bounds_max) = collision_grid_bounds(diagnostics, settings)?;
-    let scene_type = settings
-        .collision_scene_type
-        .as_deref()
-        .unwrap_or("indoor")
-        .to_string();
-    let mut voxel_size = settings
-        .collision_voxel_size
-        .filter(|v| v.is_finite() && *v > 0.0)
-        .unwrap_or(0.05)
-        .clamp(0.025, 0.5);
-    let fill_size = settings.collision_fill_size.unwrap_or(1.6);
-    // PlayCanvas writeVoxel: pad grid by exterior/floor fill radius + 1 voxel before voxelize.
-    let pad = if scene_type == "indoor" {
-        (fill_size / voxel_size).ceil().max(1.0) * voxel_size + voxel_size
-    } else if scene_type == "outdoor" {
-        (fill_size / voxel_size).ceil().max(1.0) * voxel_size + voxel_size
-    } else {
-        fill_size.max(0.3)
-    };
-    let max_voxels = settings
-        .collision_max_voxels
-        .filter(|v| *v > 0)
-        .unwrap_or(1_500_000usize)
-        .min(2_500_000);
-
-    let grid = loop {
-        let padded_min = bounds_min - Vector3::new(pad, pad, pad);
-        let padded_max = bounds_max + Vector3::new(pad, pad, pad);
-        let extent = padded_max - padded_min;
-        let dims = [
-            (extent.x / voxel_size).ceil().max(1.0) as usize + 1,
-            (extent.y / voxel_size).ceil().max(1.0) as usize + 1,
-            (extent.z / voxel_size).ceil().max(1.0) as usize + 1,
-        ];
-        let grid = VoxelGrid {
-            min: padded_min,
-            dims,
-            voxel_size,
+        // Tiles come from a cloud the whole-scene cluster filter already ran on.
+        if context.tile_bounds.is_none()
+            && !filter_collision_cluster(&mut points, settings, diagnostics)
+        {
+            return None;
+        }
+
+        let (bounds_min, bounds_max) = match context.tile_bounds {
+            Some(bounds) => bounds,
+            None => collision_grid_bounds(diagnostics, settings)?,
+        };
+        let scene_type = settings
+            .collision_scene_type
+            .as_deref()
+            .unwrap_or("indoor")
+            .to_string();
+        let requested_voxel_size = settings
+            .collision_voxel_size
+            .filter(|v| v.is_finite() && *v > 0.0)
+            .unwrap_or(0.05);
+        let mut voxel_size = requested_voxel_size.clamp(0.025, 0.5);
+        let fill_size = settings.collision_fill_size.unwrap_or(1.6);
+        // PlayCanvas writeVoxel: pad grid by exterior/floor fill radius + 1 voxel before voxelize.
+        let pad = if scene_type == "indoor" {
+            (fill_size / voxel_size).ceil().max(1.0) * voxel_size + voxel_size
+        } else if scene_type == "outdoor" {
+            (fill_size / voxel_size).ceil().max(1.0) * voxel_size + voxel_size
+        } else {
+            fill_size.max(0.3)
         };
-        if grid.len() <= max_voxels {
-            break grid;
-        }
-        web_sys::console::log_1(
-            &format!(
-                "Collision grid {} voxels exceeds cap {} — coarsening voxel {:.3}m → {:.3}m",
-                grid.len(),
-                max_voxels,
+        let max_voxels = settings
+            .collision_max_voxels
+            .filter(|v| *v > 0)
+            .unwrap_or(1_500_000usize)
+            .min(2_500_000);
+
+        let grid = loop {
+            let padded_min = bounds_min - Vector3::new(pad, pad, pad);
+            let padded_max = bounds_max + Vector3::new(pad, pad, pad);
+            let extent = padded_max - padded_min;
+            let dims = [
+                (extent.x / voxel_size).ceil().max(1.0) as usize + 1,
+                (extent.y / voxel_size).ceil().max(1.0) as usize + 1,
+                (extent.z / voxel_size).ceil().max(1.0) as usize + 1,
+            ];
+            let grid = VoxelGrid {
+                min: padded_min,
+                dims,
                 voxel_size,
-                voxel_size * 1.25
-            )
-            .into(),
+            };
+            if grid.len() <= max_voxels {
+                break grid;
+            }
+            events::emit(Event::GridCoarsened {
+                voxels: grid.len(),
+                cap: max_voxels,
+                voxel_size,
+                next_voxel_size: voxel_size * 1.25,
+            });
+            if voxel_size >= 0.5 {
+                diagnostics.collision_failure_reason = Some("region_too_large".to_string());
+                write_collision_grid_diagnostics(
+                    diagnostics,
+                    &grid,
+                    0,
+                    0,
+                    0,
+                    0,
+                    0,
+                    &scene_type,
+                    false,
+                );
+                return None;
+            }
+            voxel_size *= 1.25;
+        };
+        journal::clamped(
+            "collision",
+            "collision_voxel_size",
+            requested_voxel_size,
+            grid.voxel_size,
         );
-        if voxel_size >= 0.5 {
-            diagnostics.collision_failure_reason = Some("region_too_large".to_string());
+
+        let region_pinned = settings
+            .region_min
+            .as_ref()
+            .zip(settings.region_max.as_ref())
+            .map(|(min, max)| min.len() == 3 && max.len() == 3)
+            .unwrap_or(false);
+
+        crate::emit_progress("collision_grid", Some(1.0));
+        events::emit(Event::GridConfigured {
+            cols: grid.dims[0],
+            layers: grid.dims[1],
+            rows: grid.dims[2],
+            voxels: grid.len(),
+            cell_size: grid.voxel_size,
+            splats: points.len(),
+            region_pinned,
+        });
+
+        let threshold = settings
+            .collision_opacity_threshold
+            .unwrap_or(0.1)
+            .max(0.001);
+        let kernel = SplatKernel::from_settings(settings, 6.0);
+        Some(CollisionVoxelize {
+            density: memory::scratch_f64(settings.job_id, grid.len()),
+            points,
+            grid,
+            scene_type,
+            fill_size,
+            region_pinned,
+            threshold,
+            kernel,
+            next: 0,
+        })
+    }
+
+    /// Splat points into the density grid until all are in (`true`) or
+    /// `out_of_time` says to yield (`false`).
+    fn advance(&mut self, out_of_time: &mut dyn FnMut() -> bool) -> bool {
+        const BATCH: usize = 1024;
+        let CollisionVoxelize {
+            points,
+            grid,
+            kernel,
+            density,
+            next,
+            ..
+        } = self;
+        let voxel_size = grid.voxel_size;
+        let point_count = points.len();
+        let report_every = (point_count / 50).max(1);
+        while *next < point_count {
+            let end = (*next + BATCH).min(point_count);
+            for (pi, p) in points.iter().enumerate().take(end).skip(*next) {
+                if pi % report_every == 0 {
+                    crate::emit_progress(
+                        "collision_voxelize",
+                        Some(pi as f64 / point_count as f64),
+                    );
+                }
+                let center = Vector3::new(p.point.x, p.point.y, p.point.z);
+                let scale_avg = ((p.scale.x + p.scale.y + p.scale.z) / 3.0).max(voxel_size * 0.5);
+                let radius = kernel.radius(scale_avg * 2.5, voxel_size, 1.0);
+                let Some((cx, cy, cz)) = grid.point_to_voxel(&center) else {
+                    continue;
+                };
+                let vr = (radius / voxel_size).ceil() as isize;
+                let span = |c: usize, axis: usize| {
+                    (c as isize - vr).max(0)..=(c as isize + vr).min(grid.dims[axis] as isize - 1)
+                };
+
+                for y in span(cy, 1) {
+                    for z in span(cz, 2) {
+                        for x in span(cx, 0) {
+                            let voxel_center = grid.center(x as usize, y as usize, z as usize);
+                            let dist_sq = (voxel_center - center).norm_squared();
+                            let falloff = kernel.weight(dist_sq, radius);
+                            if falloff <= 0.0 {
+                                continue;
+                            }
+                            let idx = grid.idx(x as usize, y as usize, z as usize);
+                            density[idx] += p.opacity.max(0.0) * falloff;
+                        }
+                    }
+                }
+            }
+            *next = end;
+            if out_of_time() {
+                break;
+            }
+        }
+        *next == point_count
+    }
+
+    /// Threshold, fill and carve the splatted grid, then mesh it.
+    fn finish(
+        self,
+        settings: &MeshSettings,
+        diagnostics: &mut ReconstructionDiagnostics,
+        emit_volume: bool,
+    ) -> Option<CollisionBuild> {
+        let CollisionVoxelize {
+            grid,
+            scene_type,
+            fill_size,
+            region_pinned,
+            threshold,
+            density,
+            ..
+        } = self;
+        let mut solid = density
+            .iter()
+            .map(|v| *v >= threshold)
+            .collect::<Vec<bool>>();
+        let occupied_before = solid.iter().filter(|&&v| v).count();
+        if occupied_before == 0 {
+            diagnostics.collision_failure_reason = Some("no_occupied_voxels".to_string());
             write_collision_grid_diagnostics(
                 diagnostics,
                 &grid,
-                0,
+                occupied_before,
                 0,
                 0,
                 0,
@@ -645,123 +2296,297 @@ fn build_collision_mesh(
             );
             return None;
         }
-        voxel_size *= 1.25;
-    };
 
-    let region_pinned = settings
-        .region_min
-        .as_ref()
-        .zip(settings.region_max.as_ref())
-        .map(|(min, max)| min.len() == 3 && max.len() == 3)
-        .unwrap_or(false);
-
-    crate::emit_progress("collision_grid", Some(1.0));
-    web_sys::console::log_1(
-        &format!(
-            "Collision grid: {}x{}x{} ({} voxels), voxel={:.3}m, splats={}, region_pinned={}",
-            grid.dims[0],
-            grid.dims[1],
-            grid.dims[2],
-            grid.len(),
-            grid.voxel_size,
-            points.len(),
-            region_pinned
-        )
-        .into(),
-    );
+        let seed = collision_seed(settings, diagnostics, &grid);
+        diagnostics.collision_seed_used = Some([seed.x, seed.y, seed.z]);
+        diagnostics.collision_seed_state = seed_state(
+            &grid,
+            &solid,
+            seed,
+            settings.collision_carve_height.unwrap_or(1.6),
+            settings.collision_carve_radius.unwrap_or(0.2),
+        );
+        // PlayCanvas writeVoxel uses optional pre-voxel `--filter-cluster` on splats, not a
+        // post-voxel solid trim. Post-voxel cluster filtering removed for carve parity.
+        let cluster_kept = occupied_before;
+        let cluster_discarded = 0usize;
+        crate::emit_progress("collision_fill", None);
+        let (filled, external_fill_leaked) = apply_collision_fill(
+            &grid,
+            &mut solid,
+            &scene_type,
+            fill_size,
+            seed,
+            region_pinned,
+        );
+        crate::emit_progress("collision_carve", None);
+        let nav_region = carve_pc_style(
+            &grid,
+            &solid,
+            seed,
+            settings.collision_carve_height.unwrap_or(1.6),
+            settings.collision_carve_radius.unwrap_or(0.2),
+        );
+        let carved = nav_region.iter().filter(|&&v| v).count();
+        if carved == 0 {
+            diagnostics.collision_failure_reason =
+                Some("seed_not_reachable_or_capsule_blocked".to_string());
+            diagnostics.collision_seed_state = seed_state(
+                &grid,
+                &solid,
+                seed,
+                settings.collision_carve_height.unwrap_or(1.6),
+                settings.collision_carve_radius.unwrap_or(0.2),
+            );
+            write_collision_grid_diagnostics(
+                diagnostics,
+                &grid,
+                occupied_before,
+                cluster_kept,
+                cluster_discarded,
+                filled,
+                carved,
+                &scene_type,
+                external_fill_leaked,
+            );
+            return None;
+        }
 
-    let threshold = settings
-        .collision_opacity_threshold
-        .unwrap_or(0.1)
-        .max(0.001);
-    let mut density = vec![0.0_f64; grid.len()];
-    let point_count = points.len();
-    let report_every = (point_count / 50).max(1);
-    for (pi, p) in points.iter().enumerate() {
-        if pi % report_every == 0 {
-            crate::emit_progress("collision_voxelize", Some(pi as f64 / point_count as f64));
-        }
-        let center = Vector3::new(p.point.x, p.point.y, p.point.z);
-        let scale_avg = ((p.scale.x + p.scale.y + p.scale.z) / 3.0).max(voxel_size * 0.5);
-        let radius = (scale_avg * 2.5).max(voxel_size).min(voxel_size * 6.0);
-        let Some((cx, cy, cz)) = grid.point_to_voxel(&center) else {
-            continue;
+        let mesh_mode = settings
+            .collision_mesh_mode
+            .as_deref()
+            .unwrap_or("walkable_floors")
+            .to_string();
+        crate::emit_progress("collision_mesh", None);
+        let shell = matches!(mesh_mode.as_str(), "obstacle_shell" | "faces");
+        // `agent_height`: floors with splat mass less than that above them (table
+        // tops, low ceilings, short archways) are not walkable.
+        let headroom_region = settings.agent_height.filter(|_| !shell).map(|height| {
+            let headroom = (height / grid.voxel_size).ceil().max(1.0) as usize;
+            let (region, rejected) =
+                clear_low_headroom(&grid, &solid, &nav_region, headroom, |idx| {
+                    density[idx] >= threshold
+                });
+            diagnostics.collision_low_headroom_floors = rejected;
+            journal::record(
+                "collision_mesh",
+                JournalKind::Dropped,
+                format!(
+                    "Rejected {} floor voxels with under {:.2} m of headroom",
+                    rejected, height
+                ),
+                serde_json::json!({
+                    "reason": "low_headroom",
+                    "agent_height": height,
+                    "headroom_voxels": headroom,
+                    "rejected": rejected,
+                }),
+            );
+            region
+        });
+        let floor_region = headroom_region.as_deref().unwrap_or(&nav_region);
+        // Keep the carved grid (diagnostics, `emit_volume`) if meshing panics.
+        let smooth_iterations = settings.smooth_iterations.unwrap_or(0);
+        let max_step = settings
+            .max_step_height
+            .map_or(0, |h| (h / grid.voxel_size).floor() as usize);
+        let meshed = stage::run("collision_mesh", || match mesh_mode.as_str() {
+            "obstacle_shell" | "faces" => (mesh_from_obstacle_shell(&grid, &solid, &nav_region), 0),
+            _ => {
+                let (mut mesh, ramps) =
+                    mesh_from_walkable_floors(&grid, &solid, floor_region, max_step);
+                // Relax voxel stair steps; a riser is at most one voxel tall.
+                postprocess::smooth_heights(
+                    &mut mesh,
+                    smooth_iterations,
+                    settings.smooth_lambda.unwrap_or(0.5),
+                    grid.voxel_size * 1.5,
+                );
+                (mesh, ramps)
+            }
+        });
+        let (mesh, mut partial) = match meshed {
+            Ok((mesh, ramps)) => {
+                if max_step > 0 {
+                    diagnostics.collision_stair_ramps = ramps;
+                    journal::record(
+                        "collision_mesh",
+                        JournalKind::Decision,
+                        format!("Ramped {} stair treads up to their neighbours", ramps),
+                        serde_json::json!({
+                            "max_step_height": settings.max_step_height,
+                            "max_step_voxels": max_step,
+                            "ramps": ramps,
+                        }),
+                    );
+                }
+                (mesh, None)
+            }
+            Err(panic) => (
+                ReconstructedMesh {
+                    vertices: vec![],
+                    indices: vec![],
+                },
+                Some(PartialResult::from(panic)),
+            ),
         };
-        let vr = (radius / voxel_size).ceil() as isize;
+        let surface_faces = mesh.indices.len() / 3;
+
+        let mut navmesh = None;
+        if settings.emit_poly_navmesh.unwrap_or(false) && partial.is_none() {
+            if shell {
+                journal::record(
+                    "poly_navmesh",
+                    JournalKind::Degraded,
+                    format!("Polygon navmesh skipped for {} meshing", mesh_mode),
+                    serde_json::json!({ "reason": "not_walkable_floors", "mesh_mode": mesh_mode }),
+                );
+            } else {
+                crate::emit_progress("poly_navmesh", None);
+                let options = NavmeshOptions::from_settings(settings, grid.voxel_size);
+                let built = stage::run("poly_navmesh", || {
+                    navmesh::build(&span_field(&grid, &solid, floor_region), &options)
+                });
+                match built {
+                    Ok(built) => {
+                        diagnostics.navmesh_regions = built.region_count;
+                        diagnostics.navmesh_polys = built.poly_sizes.len();
+                        journal::record(
+                            "poly_navmesh",
+                            JournalKind::Decision,
+                            format!(
+                                "Polygon navmesh: {} polygons in {} regions",
+                                diagnostics.navmesh_polys, diagnostics.navmesh_regions
+                            ),
+                            serde_json::json!({
+                                "regions": built.region_count,
+                                "polys": built.poly_sizes.len(),
+                                "max_climb_voxels": options.max_climb,
+                                "max_verts_per_poly": options.max_verts_per_poly,
+                            }),
+                        );
+                        navmesh = Some(built);
+                    }
+                    Err(panic) => partial = Some(panic.into()),
+                }
+            }
+        }
 
-        for y in (cy as isize - vr).max(0)..=(cy as isize + vr).min(grid.dims[1] as isize - 1) {
-            for z in (cz as isize - vr).max(0)..=(cz as isize + vr).min(grid.dims[2] as isize - 1) {
-                for x in
-                    (cx as isize - vr).max(0)..=(cx as isize + vr).min(grid.dims[0] as isize - 1)
-                {
-                    let voxel_center = grid.center(x as usize, y as usize, z as usize);
-                    let dist_sq = (voxel_center - center).norm_squared();
-                    if dist_sq > radius * radius {
-                        continue;
+        let mut layers = None;
+        if settings.emit_floor_layers.unwrap_or(false) && partial.is_none() {
+            if shell {
+                journal::record(
+                    "floor_layers",
+                    JournalKind::Degraded,
+                    format!("Floor layers skipped for {} meshing", mesh_mode),
+                    serde_json::json!({ "reason": "not_walkable_floors", "mesh_mode": mesh_mode }),
+                );
+            } else {
+                crate::emit_progress("floor_layers", None);
+                let max_climb =
+                    (settings.agent_max_climb.unwrap_or(0.5) / grid.voxel_size).floor() as i32;
+                let min_area = settings.floor_layer_min_area.unwrap_or(1.0);
+                let built = stage::run("floor_layers", || {
+                    floor_layers::build(
+                        &span_field(&grid, &solid, floor_region),
+                        max_climb,
+                        min_area,
+                    )
+                });
+                match built {
+                    Ok(built) => {
+                        diagnostics.floor_layers = built.layers.len();
+                        journal::record(
+                            "floor_layers",
+                            JournalKind::Decision,
+                            format!(
+                                "{} floor layers, {} under {} m² dropped",
+                                built.layers.len(),
+                                built.dropped,
+                                min_area
+                            ),
+                            serde_json::json!({
+                                "layers": built.layers.len(),
+                                "dropped": built.dropped,
+                                "connections": built.connections.len(),
+                                "max_climb_voxels": max_climb,
+                            }),
+                        );
+                        layers = Some(built);
                     }
-                    let falloff = (-dist_sq / (2.0 * radius * radius)).exp();
-                    let idx = grid.idx(x as usize, y as usize, z as usize);
-                    density[idx] += p.opacity.max(0.0) * falloff;
+                    Err(panic) => partial = Some(panic.into()),
                 }
             }
         }
-    }
 
-    let mut solid = density
-        .iter()
-        .map(|v| *v >= threshold)
-        .collect::<Vec<bool>>();
-    let occupied_before = solid.iter().filter(|&&v| v).count();
-    if occupied_before == 0 {
-        diagnostics.collision_failure_reason = Some("no_occupied_voxels".to_string());
-        write_collision_grid_diagnostics(
-            diagnostics,
-            &grid,
-            occupied_before,
-            0,
-            0,
-            0,
-            0,
-            &scene_type,
-            false,
-        );
-        return None;
-    }
+        let mut occupancy = None;
+        if settings.emit_occupancy_grid.unwrap_or(false) && partial.is_none() {
+            crate::emit_progress("occupancy_grid", None);
+            let climb =
+                (settings.agent_max_climb.unwrap_or(0.5) / grid.voxel_size).floor() as usize;
+            let clearance =
+                (settings.collision_carve_height.unwrap_or(1.6) / grid.voxel_size).ceil() as usize;
+            let built = stage::run("occupancy_grid", || {
+                occupancy::build(
+                    &span_field(&grid, &solid, floor_region),
+                    climb,
+                    clearance,
+                    |x, y, z| y < grid.dims[1] && density[grid.idx(x, y, z)] >= threshold,
+                )
+            });
+            match built {
+                Ok(Some(built)) => {
+                    journal::record(
+                        "occupancy_grid",
+                        JournalKind::Decision,
+                        format!(
+                            "Occupancy grid {} x {}: {} free, {} occupied, {} unknown cells",
+                            built.width, built.height, built.free, built.occupied, built.unknown
+                        ),
+                        serde_json::json!({
+                            "width": built.width,
+                            "height": built.height,
+                            "free": built.free,
+                            "occupied": built.occupied,
+                            "unknown": built.unknown,
+                            "climb_voxels": climb,
+                            "clearance_voxels": clearance,
+                        }),
+                    );
+                    occupancy = Some(built);
+                }
+                Ok(None) => journal::record(
+                    "occupancy_grid",
+                    JournalKind::Degraded,
+                    "Occupancy grid skipped: no walkable floor".to_string(),
+                    serde_json::json!({ "reason": "no_walkable_floor" }),
+                ),
+                Err(panic) => partial = Some(panic.into()),
+            }
+        }
 
-    let seed = collision_seed(settings, diagnostics, &grid);
-    diagnostics.collision_seed_used = Some([seed.x, seed.y, seed.z]);
-    diagnostics.collision_seed_state = seed_state(
-        &grid,
-        &solid,
-        seed,
-        settings.collision_carve_height.unwrap_or(1.6),
-        settings.collision_carve_radius.unwrap_or(0.2),
-    );
-    // PlayCanvas writeVoxel uses optional pre-voxel `--filter-cluster` on splats, not a
-    // post-voxel solid trim. Post-voxel cluster filtering removed for carve parity.
-    let cluster_kept = occupied_before;
-    let cluster_discarded = 0usize;
-    crate::emit_progress("collision_fill", None);
-    let (filled, external_fill_leaked) = apply_collision_fill(
-        &grid,
-        &mut solid,
-        &scene_type,
-        fill_size,
-        seed,
-        region_pinned,
-    );
-    crate::emit_progress("collision_carve", None);
-    let nav_region = carve_pc_style(
-        &grid,
-        &solid,
-        seed,
-        settings.collision_carve_height.unwrap_or(1.6),
-        settings.collision_carve_radius.unwrap_or(0.2),
-    );
-    let carved = nav_region.iter().filter(|&&v| v).count();
-    if carved == 0 {
-        diagnostics.collision_failure_reason =
-            Some("seed_not_reachable_or_capsule_blocked".to_string());
+        diagnostics.floor_plane = Some(FloorPlane {
+            normal: [0.0, 1.0, 0.0],
+            d: -seed.y,
+        });
+        diagnostics.floor_plane_source = "voxel_collision".to_string();
+        diagnostics.floor_plane_normal_y = 1.0;
+        diagnostics.floor_plane_height = seed.y;
+        diagnostics.grid_width = grid.dims[0];
+        diagnostics.grid_height = grid.dims[2];
+        diagnostics.cell_size = grid.voxel_size;
+        diagnostics.faces_generated = surface_faces;
+        diagnostics.valid_vertices = mesh.vertices.len() / 3;
+        diagnostics.collision_voxel_size = grid.voxel_size;
+        diagnostics.collision_grid_width = grid.dims[0];
+        diagnostics.collision_grid_height = grid.dims[1];
+        diagnostics.collision_grid_depth = grid.dims[2];
+        diagnostics.collision_occupied_voxels = occupied_before;
+        diagnostics.collision_cluster_kept_voxels = cluster_kept;
+        diagnostics.collision_cluster_discarded_voxels = cluster_discarded;
+        diagnostics.collision_filled_voxels = filled;
+        diagnostics.collision_carved_voxels = carved;
+        diagnostics.collision_surface_faces = surface_faces;
         diagnostics.collision_seed_state = seed_state(
             &grid,
             &solid,
@@ -769,96 +2594,56 @@ fn build_collision_mesh(
             settings.collision_carve_height.unwrap_or(1.6),
             settings.collision_carve_radius.unwrap_or(0.2),
         );
-        write_collision_grid_diagnostics(
-            diagnostics,
-            &grid,
-            occupied_before,
-            cluster_kept,
-            cluster_discarded,
+        diagnostics.collision_scene_type = scene_type;
+        if !matches!(mesh_mode.as_str(), "obstacle_shell" | "faces") {
+            diagnostics.smooth_iterations = smooth_iterations;
+        }
+        diagnostics.collision_mesh_mode = mesh_mode;
+        diagnostics.collision_external_fill_leaked = external_fill_leaked;
+        diagnostics.collision_failure_reason = None;
+
+        events::emit(Event::CollisionCarved {
+            cols: grid.dims[0],
+            layers: grid.dims[1],
+            rows: grid.dims[2],
+            cell_size: grid.voxel_size,
+            occupied: occupied_before,
+            kept: cluster_kept,
+            discarded: cluster_discarded,
             filled,
             carved,
-            &scene_type,
-            external_fill_leaked,
-        );
-        return None;
-    }
-
-    let mesh_mode = settings
-        .collision_mesh_mode
-        .as_deref()
-        .unwrap_or("walkable_floors")
-        .to_string();
-    crate::emit_progress("collision_mesh", None);
-    let mesh = match mesh_mode.as_str() {
-        "obstacle_shell" | "faces" => mesh_from_obstacle_shell(&grid, &solid, &nav_region),
-        "walkable_floors" => mesh_from_walkable_floors(&grid, &solid, &nav_region),
-        _ => mesh_from_walkable_floors(&grid, &solid, &nav_region),
-    };
-    let surface_faces = mesh.indices.len() / 3;
-
-    diagnostics.floor_plane = Some(FloorPlane {
-        normal: [0.0, 1.0, 0.0],
-        d: -seed.y,
-    });
-    diagnostics.floor_plane_source = "voxel_collision".to_string();
-    diagnostics.floor_plane_normal_y = 1.0;
-    diagnostics.floor_plane_height = seed.y;
-    diagnostics.grid_width = grid.dims[0];
-    diagnostics.grid_height = grid.dims[2];
-    diagnostics.cell_size = grid.voxel_size;
-    diagnostics.faces_generated = surface_faces;
-    diagnostics.valid_vertices = mesh.vertices.len() / 3;
-    diagnostics.collision_voxel_size = grid.voxel_size;
-    diagnostics.collision_grid_width = grid.dims[0];
-    diagnostics.collision_grid_height = grid.dims[1];
-    diagnostics.collision_grid_depth = grid.dims[2];
-    diagnostics.collision_occupied_voxels = occupied_before;
-    diagnostics.collision_cluster_kept_voxels = cluster_kept;
-    diagnostics.collision_cluster_discarded_voxels = cluster_discarded;
-    diagnostics.collision_filled_voxels = filled;
-    diagnostics.collision_carved_voxels = carved;
-    diagnostics.collision_surface_faces = surface_faces;
-    diagnostics.collision_seed_state = seed_state(
-        &grid,
-        &solid,
-        seed,
-        settings.collision_carve_height.unwrap_or(1.6),
-        settings.collision_carve_radius.unwrap_or(0.2),
-    );
-    diagnostics.collision_scene_type = scene_type;
-    diagnostics.collision_mesh_mode = mesh_mode;
-    diagnostics.collision_external_fill_leaked = external_fill_leaked;
-    diagnostics.collision_failure_reason = None;
-
-    web_sys::console::log_1(&format!(
-        "Collision carve: grid={}x{}x{}, voxel={:.3}, occupied={}, kept={}, discarded={}, filled={}, carved={}, faces={}",
-        grid.dims[0], grid.dims[1], grid.dims[2], grid.voxel_size, occupied_before, cluster_kept, cluster_discarded, filled, carved, surface_faces
-    ).into());
+            faces: surface_faces,
+        });
 
-    let basis = FieldBasis {
-        origin: [grid.min.x, grid.min.y, grid.min.z],
-        tangent: [1.0, 0.0, 0.0],
-        bitangent: [0.0, 0.0, 1.0],
-        up: [0.0, 1.0, 0.0],
-    };
-    let plane = diagnostics.floor_plane.clone().unwrap_or(FloorPlane {
-        normal: [0.0, 1.0, 0.0],
-        d: -seed.y,
-    });
+        let basis = FieldBasis {
+            origin: [grid.min.x, grid.min.y, grid.min.z],
+            tangent: [1.0, 0.0, 0.0],
+            bitangent: [0.0, 0.0, 1.0],
+            up: [0.0, 1.0, 0.0],
+        };
+        let plane = diagnostics.floor_plane.clone().unwrap_or(FloorPlane {
+            normal: [0.0, 1.0, 0.0],
+            d: -seed.y,
+        });
 
-    let volume = if emit_volume {
-        Some(pack_collision_volume(&grid, &solid, &nav_region))
-    } else {
-        None
-    };
+        let volume = if emit_volume {
+            Some(pack_collision_volume(&grid, &solid, &nav_region))
+        } else {
+            None
+        };
 
-    Some(CollisionBuild {
-        mesh,
-        basis,
-        plane,
-        diagnostics: diagnostics.clone(),
-        volume,
-    })
+        Some(CollisionBuild {
+            mesh,
+            basis,
+            plane,
+            diagnostics: diagnostics.clone(),
+            volume,
+            navmesh,
+            layers,
+            occupancy,
+            partial,
+        })
+    }
 }
 
 fn collision_seed(
@@ -1260,6 +3045,45 @@ fn bfs_free_voxels(
     visited
 }
 
+/// `collision_filter_cluster`: drop splats disconnected from the seed's
+/// coarse cluster. False when nothing is left.
+fn filter_collision_cluster(
+    points: &mut Vec<PointNormal>,
+    settings: &MeshSettings,
+    diagnostics: &mut ReconstructionDiagnostics,
+) -> bool {
+    let cluster_seed = resolve_cluster_seed(settings, diagnostics);
+    if settings.collision_filter_cluster.unwrap_or(true) {
+        crate::emit_progress("collision_cluster", None);
+        let opacity_threshold = settings
+            .collision_opacity_threshold
+            .unwrap_or(0.1)
+            .max(0.05);
+        let discarded = filter_splats_coarse_cluster(points, cluster_seed, opacity_threshold);
+        if discarded > 0 {
+            events::emit(Event::ClusterFiltered {
+                kept: points.len(),
+                removed: discarded,
+            });
+            journal::record(
+                "collision",
+                JournalKind::Dropped,
+                format!("Filter-cluster removed {} disconnected splats", discarded),
+                serde_json::json!({
+                    "reason": "disconnected_from_seed_cluster",
+                    "count": discarded,
+                    "kept": points.len(),
+                }),
+            );
+        }
+        if points.is_empty() {
+            diagnostics.collision_failure_reason = Some("filter_cluster_removed_all".to_string());
+            return false;
+        }
+    }
+    true
+}
+
 fn resolve_cluster_seed(
     settings: &MeshSettings,
     diagnostics: &ReconstructionDiagnostics,
@@ -1518,28 +3342,98 @@ fn mesh_from_obstacle_shell(
             }
         }
     }
-
-    ReconstructedMesh { vertices, indices }
+
+    ReconstructedMesh { vertices, indices }
+}
+
+/// Copy of `nav_region` with the carved voxel on top of each floor cleared
+/// where `occupied` voxels (splat mass) sit less than `headroom` voxels
+/// above it, so no walkable floor is emitted there; plus how many were.
+fn clear_low_headroom(
+    grid: &VoxelGrid,
+    solid: &[bool],
+    nav_region: &[bool],
+    headroom: usize,
+    occupied: impl Fn(usize) -> bool,
+) -> (Vec<bool>, usize) {
+    let mut region = nav_region.to_vec();
+    let mut rejected = 0;
+    for y in 0..grid.dims[1].saturating_sub(1) {
+        for z in 0..grid.dims[2] {
+            for x in 0..grid.dims[0] {
+                let above = grid.idx(x, y + 1, z);
+                if !solid[grid.idx(x, y, z)] || !nav_region[above] {
+                    continue;
+                }
+                let top = (y + headroom).min(grid.dims[1] - 1);
+                if (y + 1..=top).any(|c| occupied(grid.idx(x, c, z))) {
+                    region[above] = false;
+                    rejected += 1;
+                }
+            }
+        }
+    }
+    (region, rejected)
+}
+
+/// Walkable spans of the carved grid: a floor on top of every solid voxel
+/// with carved space above, reaching up to the first voxel that is not.
+fn span_field(grid: &VoxelGrid, solid: &[bool], nav_region: &[bool]) -> SpanField {
+    let [width, height, depth] = grid.dims;
+    let mut columns = vec![Vec::new(); width * depth];
+    for z in 0..depth {
+        for x in 0..width {
+            let column = &mut columns[z * width + x];
+            for y in 0..height.saturating_sub(1) {
+                if !solid[grid.idx(x, y, z)] || !nav_region[grid.idx(x, y + 1, z)] {
+                    continue;
+                }
+                let ceiling = (y + 1..height)
+                    .find(|&c| !nav_region[grid.idx(x, c, z)])
+                    .unwrap_or(height);
+                column.push(Span {
+                    floor: (y + 1) as i32,
+                    ceiling: ceiling as i32,
+                });
+            }
+        }
+    }
+    SpanField {
+        width,
+        depth,
+        origin: [grid.min.x, grid.min.y, grid.min.z],
+        cell_size: grid.voxel_size,
+        columns,
+    }
 }
 
 /// Walkable floor + stair tread tops for Recast (PC-style): upward-facing quads on
 /// solid voxels that border carved nav volume above. Skips wall/ceiling shells that
 /// fragment Recast into green shards.
+///
+/// With `max_step` (voxels, 0 = off) a tread whose neighbour's floor is at most
+/// that much higher has the corners of their shared edge raised to meet it, so a
+/// staircase comes out as one connected ramp instead of separate treads. Also
+/// returns how many treads were ramped.
 fn mesh_from_walkable_floors(
     grid: &VoxelGrid,
     solid: &[bool],
     nav_region: &[bool],
-) -> ReconstructedMesh {
+    max_step: usize,
+) -> (ReconstructedMesh, usize) {
     let combined: Vec<bool> = solid
         .iter()
         .zip(nav_region.iter())
         .map(|(&s, &n)| s || n)
         .collect();
     let Some((occ_min, occ_max)) = occupied_voxel_bounds(grid, &combined) else {
-        return ReconstructedMesh {
-            vertices: Vec::new(),
-            indices: Vec::new(),
-        };
+        return (
+            ReconstructedMesh {
+                vertices: Vec::new(),
+                indices: Vec::new(),
+            },
+            0,
+        );
     };
     let grid_span = [
         occ_max[0].saturating_sub(occ_min[0]),
@@ -1560,6 +3454,12 @@ fn mesh_from_walkable_floors(
     let mut vertices = Vec::<f32>::new();
     let mut indices = Vec::<u32>::new();
     let mut vertex_map = std::collections::HashMap::<(usize, usize, usize), u32>::new();
+    let mut ramps = 0;
+    // Floor top of column (x, z) between `low` and `high` voxels, lowest first.
+    let tread_top = |x: usize, z: usize, low: usize, high: usize| {
+        (low..=high.min(grid.dims[1] - 1))
+            .find(|&t| solid[grid.idx(x, t - 1, z)] && nav_region[grid.idx(x, t, z)])
+    };
 
     let emit_corner = |vertices: &mut Vec<f32>,
                        vertex_map: &mut std::collections::HashMap<(usize, usize, usize), u32>,
@@ -1598,11 +3498,33 @@ fn mesh_from_walkable_floors(
                 }
 
                 let top_y = y + 1;
+                // Edges as (neighbour across it, the two corners it joins).
+                let mut lift = [top_y; 4];
+                if max_step > 0 {
+                    let edges = [
+                        (z.checked_sub(1).map(|nz| (x, nz)), 0, 1),
+                        ((x + 1 < grid.dims[0]).then_some((x + 1, z)), 1, 2),
+                        ((z + 1 < grid.dims[2]).then_some((x, z + 1)), 2, 3),
+                        (x.checked_sub(1).map(|nx| (nx, z)), 3, 0),
+                    ];
+                    for (neighbour, a, b) in edges {
+                        let Some((nx, nz)) = neighbour else {
+                            continue;
+                        };
+                        if let Some(top) = tread_top(nx, nz, top_y + 1, top_y + max_step) {
+                            lift[a] = lift[a].max(top);
+                            lift[b] = lift[b].max(top);
+                        }
+                    }
+                    if lift.iter().any(|&h| h > top_y) {
+                        ramps += 1;
+                    }
+                }
                 let corners = [
-                    (x, top_y, z),
-                    (x + 1, top_y, z),
-                    (x + 1, top_y, z + 1),
-                    (x, top_y, z + 1),
+                    (x, lift[0], z),
+                    (x + 1, lift[1], z),
+                    (x + 1, lift[2], z + 1),
+                    (x, lift[3], z + 1),
                 ];
                 let mut face_indices = [0_u32; 4];
                 for (slot, corner) in corners.iter().enumerate() {
@@ -1620,7 +3542,7 @@ fn mesh_from_walkable_floors(
         }
     }
 
-    ReconstructedMesh { vertices, indices }
+    (ReconstructedMesh { vertices, indices }, ramps)
 }
 
 fn occupied_voxel_bounds(
@@ -1702,8 +3624,10 @@ fn build_field(
         return None;
     }
 
-    let voxel_target = settings.voxel_target.unwrap_or(4000.0);
-    let ransac_thresh = settings.ransac_thresh.unwrap_or(0.1);
+    let resolution = Resolution::from_settings(settings);
+    let ransac_thresh = settings
+        .ransac_thresh
+        .unwrap_or_else(|| context.tolerance.scaled(0.1));
     let floor_projection_epsilon = settings
         .floor_projection_epsilon
         .or(settings.height_projection_epsilon)
@@ -1744,6 +3668,7 @@ fn build_field(
         .sdf_influence_radius_scale
         .unwrap_or(2.5)
         .clamp(0.5, 6.0);
+    let kernel = SplatKernel::from_settings(settings, 4.0);
 
     let p_coords: Vec<Point3<Real>> = points
         .iter()
@@ -1757,15 +3682,24 @@ fn build_field(
     };
     let lower_band_height = (floor_projection_epsilon * 4.0).max(0.45);
     let min_floor_normal_y = 0.82;
-    let (_diagnostic_plane, max_inliers) = find_floor_plane(
+    let outcome = find_floor_plane(
         &p_coords,
         ransac_thresh,
-        1200,
+        context.tolerance.degenerate_area(),
+        ransac_options(settings, 1200),
         floor_y,
         lower_band_height,
         min_floor_normal_y,
     );
-    diagnostics.ransac_inliers = max_inliers;
+    let chosen = record_ground_candidates(&outcome, &p_coords, ransac_thresh, settings, diagnostics);
+    // The field stays axis-aligned; a chosen candidate only moves the floor to
+    // its inlier height.
+    let (floor_y, floor_plane_source) = match chosen {
+        Some((_, height)) if diagnostics.chosen_ground_index.is_some() => {
+            (height, "chosen_candidate")
+        }
+        _ => (floor_y, "lower_envelope"),
+    };
 
     let floor_d = -floor_y;
     let floor_height = floor_y;
@@ -1773,10 +3707,31 @@ fn build_field(
         normal: [0.0, 1.0, 0.0],
         d: floor_d,
     });
-    diagnostics.floor_plane_source = "lower_envelope".to_string();
+    diagnostics.floor_plane_source = floor_plane_source.to_string();
     diagnostics.floor_plane_normal_y = 1.0;
     diagnostics.floor_plane_height = floor_height;
     diagnostics.floor_plane_used_fallback = false;
+    journal::record(
+        "ground_plane",
+        JournalKind::Decision,
+        format!(
+            "Floor plane at y={:.3} ({})",
+            floor_height, floor_plane_source
+        ),
+        serde_json::json!({
+            "source": floor_plane_source,
+            "height": floor_height,
+            "chosen_ground_index": diagnostics.chosen_ground_index,
+            "candidates": diagnostics.ground_candidates.len(),
+            "ransac_inliers": diagnostics.ransac_inliers,
+        }),
+    );
+
+    let groups = NormalClusterOptions::from_settings(settings).map(|options| {
+        let labels = cluster::classify(points, floor_y, &options);
+        diagnostics.normal_clusters = cluster::summarize(points, &labels);
+        labels
+    });
 
     let tangent_64 = Vector3::new(1.0, 0.0, 0.0);
     let bitangent_64 = Vector3::new(0.0, 0.0, 1.0);
@@ -1804,11 +3759,17 @@ fn build_field(
         return None;
     }
 
-    let mut cell_size = settings
+    let requested_cell_size = settings
         .sdf_cell_size
         .filter(|v| v.is_finite() && *v > 0.0)
-        .unwrap_or_else(|| (width_m * depth_m / voxel_target).sqrt());
-    cell_size = cell_size.clamp(0.03, 2.0);
+        .unwrap_or_else(|| resolution.cell_size(width_m, depth_m));
+    let cell_size = requested_cell_size.clamp(0.03, 2.0);
+    journal::clamped(
+        "ground_field",
+        "sdf_cell_size",
+        requested_cell_size,
+        cell_size,
+    );
 
     let cols = (width_m / cell_size).ceil() as usize;
     let rows = (depth_m / cell_size).ceil() as usize;
@@ -1837,16 +3798,20 @@ fn build_field(
             return None;
         }
     };
-    let mut profiles = vec![0.0_f64; profile_len];
+    let mut profiles = memory::scratch_f64(settings.job_id, profile_len);
     let mut normal_weight = vec![0.0_f64; num_cells];
     let mut sample_weight = vec![0.0_f64; num_cells];
+    let mut wall_weight = vec![0.0_f64; num_cells];
+    let wall_band = (floor_y + obstacle_clearance_min)..=(floor_y + obstacle_clearance_max);
 
-    for p in points {
+    for (i, p) in points.iter().enumerate() {
+        let group = groups.as_ref().map(|labels| labels[i]);
+        if group == Some(NormalGroup::Wall) && !wall_band.contains(&p.point.y) {
+            continue;
+        }
         let normal_y = p.normal.y.abs().min(1.0);
         let scale_avg = ((p.scale.x + p.scale.y + p.scale.z) / 3.0).max(0.001);
-        let influence_radius = (scale_avg * influence_radius_scale)
-            .max(cell_size * 0.5)
-            .min(cell_size * 4.0);
+        let influence_radius = kernel.radius(scale_avg * influence_radius_scale, cell_size, 0.5);
         let col_min =
             (((p.point.x - influence_radius - min_u) / cell_size).floor() as isize).max(0);
         let col_max = (((p.point.x + influence_radius - min_u) / cell_size).floor() as isize)
@@ -1860,7 +3825,11 @@ fn build_field(
         let bin_radius = ((y_sigma * influence_radius_scale / sdf_vertical_cell_size).ceil()
             as isize)
             .clamp(1, 8);
-        let base_density = p.opacity.max(0.0) * (0.35 + 0.65 * normal_y);
+        let base_density = p.opacity.max(0.0)
+            * match group {
+                Some(NormalGroup::Floor) => 1.0,
+                _ => 0.35 + 0.65 * normal_y,
+            };
 
         for row in row_min..=row_max {
             for col in col_min..=col_max {
@@ -1869,12 +3838,16 @@ fn build_field(
                 let dx = cell_center_x - p.point.x;
                 let dz = cell_center_z - p.point.z;
                 let xz_dist_sq = dx * dx + dz * dz;
-                if xz_dist_sq > influence_radius * influence_radius {
+                let xz_falloff = kernel.weight(xz_dist_sq, influence_radius);
+                if xz_falloff <= 0.0 {
                     continue;
                 }
 
-                let xz_falloff = (-xz_dist_sq / (2.0 * influence_radius * influence_radius)).exp();
                 let cell_idx = row as usize * width + col as usize;
+                if group == Some(NormalGroup::Wall) {
+                    wall_weight[cell_idx] += base_density * xz_falloff;
+                    continue;
+                }
                 normal_weight[cell_idx] += normal_y * base_density * xz_falloff;
                 sample_weight[cell_idx] += base_density * xz_falloff;
 
@@ -2013,6 +3986,10 @@ fn build_field(
         let state = if surface.primary_height.is_none() {
             cells_void += 1;
             GroundFieldCellState::Void
+        } else if wall_weight[idx] >= floor_weight.max(min_evidence_weight) {
+            cells_rejected_obstacle += 1;
+            diagnostics.cells_rejected_wall += 1;
+            GroundFieldCellState::Obstacle
         } else if obstacle_weight >= min_evidence_weight && obstacle_score >= obstacle_threshold {
             cells_rejected_obstacle += 1;
             GroundFieldCellState::Obstacle
@@ -2043,6 +4020,7 @@ fn build_field(
             obstacle_score: obstacle_score as f32,
             primary_layer_height: primary_height as f32,
             layer_count: surface.layer_count,
+            primary_layer: surface.primary_layer,
             peak_density: surface.peak_density as f32,
             surface_confidence: surface.surface_confidence as f32,
             signed_distance: surface.signed_distance_proxy as f32,
@@ -2060,6 +4038,10 @@ fn build_field(
         height,
         settings.hole_fill_radius.unwrap_or(1),
     );
+    let inpainted = settings
+        .fill_holes_max_area
+        .map(|max_area| inpaint::inpaint_holes(&mut cells, width, height, cell_size, max_area))
+        .unwrap_or_default();
     let cells_eroded = erode_agent_radius(
         &mut cells,
         width,
@@ -2096,14 +4078,16 @@ fn build_field(
     diagnostics.grid_width = width;
     diagnostics.grid_height = height;
     diagnostics.cell_size = cell_size;
-    diagnostics.valid_vertices = valid_cell_count + holes_filled;
+    diagnostics.valid_vertices = valid_cell_count + holes_filled + inpainted.cells;
     diagnostics.holes_filled = holes_filled;
+    diagnostics.holes_inpainted = inpainted.regions;
+    diagnostics.cells_inpainted = inpainted.cells;
     diagnostics.rejected_cells = rejected_cells;
     diagnostics.cells_rejected_low_confidence = cells_rejected_low_confidence;
     diagnostics.cells_rejected_height_variance = cells_rejected_height_variance;
     diagnostics.cells_rejected_obstacle = cells_rejected_obstacle;
     diagnostics.cells_void = cells_void;
-    diagnostics.cells_filled = holes_filled;
+    diagnostics.cells_filled = holes_filled + inpainted.cells;
     diagnostics.cells_eroded = cells_eroded;
     diagnostics.cells_discarded_component = discarded_cells;
     diagnostics.connected_components = component_count;
@@ -2119,26 +4103,25 @@ fn build_field(
     diagnostics.sdf_cells_multi_layer = multi_layer_cells;
     diagnostics.sdf_cells_smoothed = smoothed_cells;
 
-    web_sys::console::log_1(&format!(
-        "2.5D SDF column field: {}x{}, cell_size={:.3}, y_bins={}, clearance=[{:.2},{:.2}], surfaces={}, multi_layer={}, floor_bins={}, obstacleBand_bins={}, holes_filled={}, eroded={}, discarded={}, rejected(conf={}, discontinuity={}, obs={}, void={})",
-        width,
-        height,
+    events::emit(Event::ColumnFieldBuilt {
+        cols: width,
+        rows: height,
         cell_size,
-        profile_bins,
-        obstacle_clearance_min,
-        obstacle_clearance_max,
-        cells_with_surface,
-        multi_layer_cells,
-        points_contributed,
-        obstacle_points,
+        y_bins: profile_bins,
+        clearance_min: obstacle_clearance_min,
+        clearance_max: obstacle_clearance_max,
+        surfaces: cells_with_surface,
+        multi_layer: multi_layer_cells,
+        floor_bins: points_contributed,
+        obstacle_bins: obstacle_points,
         holes_filled,
-        cells_eroded,
-        discarded_cells,
-        cells_rejected_low_confidence,
-        cells_rejected_discontinuity,
-        cells_rejected_obstacle,
-        cells_void
-    ).into());
+        eroded: cells_eroded,
+        discarded: discarded_cells,
+        rejected_confidence: cells_rejected_low_confidence,
+        rejected_discontinuity: cells_rejected_discontinuity,
+        rejected_obstacle: cells_rejected_obstacle,
+        void: cells_void,
+    });
 
     let origin_vec = tangent_64 * min_u + bitangent_64 * min_v;
     let plane = diagnostics.floor_plane.clone().unwrap_or(FloorPlane {
@@ -2242,6 +4225,8 @@ fn fill_low_confidence_holes(
 struct DensitySurface {
     primary_height: Option<f64>,
     layer_count: usize,
+    /// Index of the floor layer among the column's layers, bottom first.
+    primary_layer: usize,
     peak_density: f64,
     surface_confidence: f64,
     obstacle_density: f64,
@@ -2255,6 +4240,7 @@ fn empty_density_surface() -> DensitySurface {
     DensitySurface {
         primary_height: None,
         layer_count: 0,
+        primary_layer: 0,
         peak_density: 0.0,
         surface_confidence: 0.0,
         obstacle_density: 0.0,
@@ -2455,6 +4441,7 @@ fn extract_density_surfaces(
         surfaces[cell_idx] = DensitySurface {
             primary_height: Some(primary_height),
             layer_count: layers.len(),
+            primary_layer: primary_idx,
             peak_density,
             surface_confidence: primary.3,
             obstacle_density,
@@ -2791,6 +4778,25 @@ fn select_connected_component(
             }
         }
     }
+    if discarded > 0 {
+        journal::record(
+            "ground_field",
+            JournalKind::Dropped,
+            format!(
+                "Discarded {} of {} floor components ({} cells)",
+                component_sizes.len() - 1,
+                component_sizes.len(),
+                discarded
+            ),
+            serde_json::json!({
+                "reason": "not_selected",
+                "component_mode": mode.unwrap_or("largest"),
+                "selected_component": selected_component,
+                "components": component_sizes.len(),
+                "cells": discarded,
+            }),
+        );
+    }
 
     (
         component_sizes.len(),
@@ -2822,12 +4828,12 @@ fn neighbors4(row: usize, col: usize, width: usize, height: usize) -> Vec<(usize
 fn find_floor_plane(
     points: &[Point3<Real>],
     threshold: f64,
-    iterations: usize,
+    degenerate_area: f64,
+    options: RansacOptions,
     floor_y: f64,
     lower_band_height: f64,
     min_normal_y: f64,
-) -> (Option<Plane>, usize) {
-    let mut rng = rand::thread_rng();
+) -> RansacOutcome<Plane> {
     let lower_limit = floor_y + lower_band_height;
     let mut sample_indices = points
         .iter()
@@ -2846,132 +4852,500 @@ fn find_floor_plane(
     }
 
     if sample_indices.len() < 3 {
-        return (None, 0);
+        return RansacOutcome::empty();
     }
 
-    let mut best_plane = None;
-    let mut best_score = 0.0_f64;
-    let mut best_inliers = 0usize;
+    ransac::run(
+        points,
+        options,
+        |rng| {
+            let [idx1, idx2, idx3] = ransac::draw_triple(rng, &sample_indices)?;
+            let mut plane =
+                Plane::from_points(&points[idx1], &points[idx2], &points[idx3], degenerate_area)?;
+            if plane.normal.y < 0.0 {
+                plane.normal = -plane.normal;
+                plane.d = -plane.d;
+            }
+            if (plane.normal.y as f64) < min_normal_y {
+                return None;
+            }
+            Some(plane)
+        },
+        |plane, pts, weight| {
+            let runs = parallel::chunks(pts, |run| {
+                let mut lower_inliers = 0usize;
+                let mut all_inliers = 0usize;
+                let mut low_height_error = 0.0_f64;
+                for p in run {
+                    if plane.distance(p) < threshold {
+                        all_inliers += 1;
+                        if (p.y as f64) <= lower_limit {
+                            lower_inliers += 1;
+                            low_height_error += ((p.y as f64) - floor_y).abs();
+                        }
+                    }
+                }
+                (lower_inliers, all_inliers, low_height_error)
+            });
+            let (lower_inliers, all_inliers, low_height_error) = runs
+                .into_iter()
+                .fold((0, 0, 0.0), |(l, a, e), (rl, ra, re)| {
+                    (l + rl, a + ra, e + re)
+                });
+
+            if lower_inliers == 0 {
+                return Score {
+                    score: 0.0,
+                    inliers: 0,
+                };
+            }
 
-    for _ in 0..iterations {
-        let idx1 = sample_indices[rng.gen_range(0..sample_indices.len())];
-        let idx2 = sample_indices[rng.gen_range(0..sample_indices.len())];
-        let idx3 = sample_indices[rng.gen_range(0..sample_indices.len())];
-        if idx1 == idx2 || idx2 == idx3 || idx1 == idx3 {
-            continue;
+            let mean_low_height_error = low_height_error / lower_inliers as f64;
+            let low_band_bonus = lower_inliers as f64 * 3.0;
+            let height_penalty = mean_low_height_error / lower_band_height.max(0.001);
+            Score {
+                score: (all_inliers as f64 + low_band_bonus) * weight - height_penalty,
+                inliers: all_inliers,
+            }
+        },
+        |a, b| same_plane(a, b, threshold),
+    )
+}
+
+/// A RANSAC plane fit, resumable between hypotheses. Pass the same points to
+/// every call.
+struct PlaneSearch {
+    pool: Vec<usize>,
+    threshold: f64,
+    degenerate_area: f64,
+    /// `None` for three points or fewer, which never fit.
+    search: Option<ransac::Search<Plane>>,
+}
+
+impl PlaneSearch {
+    fn new(
+        points: &[Point3<Real>],
+        threshold: f64,
+        degenerate_area: f64,
+        options: RansacOptions,
+    ) -> Self {
+        let n = points.len();
+        PlaneSearch {
+            pool: if n > 3 { (0..n).collect() } else { Vec::new() },
+            threshold,
+            degenerate_area,
+            search: (n > 3).then(|| ransac::Search::new(points, options)),
         }
+    }
 
-        let Some(mut plane) = Plane::from_points(&points[idx1], &points[idx2], &points[idx3])
-        else {
-            continue;
+    /// Draw hypotheses until the search is done (`true`) or `out_of_time`.
+    fn advance(&mut self, points: &[Point3<Real>], out_of_time: &mut dyn FnMut() -> bool) -> bool {
+        let PlaneSearch {
+            pool,
+            threshold,
+            degenerate_area,
+            search,
+        } = self;
+        let Some(search) = search else {
+            return true;
         };
+        let threshold = *threshold;
+        search.advance(
+            points,
+            &mut |rng| {
+                let [idx1, idx2, idx3] = ransac::draw_triple(rng, pool)?;
+                Plane::from_points(
+                    &points[idx1],
+                    &points[idx2],
+                    &points[idx3],
+                    *degenerate_area,
+                )
+            },
+            &|plane, pts, weight| plane_score(plane, pts, weight, threshold),
+            &|a, b| same_plane(a, b, threshold),
+            out_of_time,
+        )
+    }
 
-        if plane.normal.y < 0.0 {
-            plane.normal = -plane.normal;
-            plane.d = -plane.d;
+    fn finish(self, points: &[Point3<Real>]) -> RansacOutcome<Plane> {
+        let threshold = self.threshold;
+        match self.search {
+            Some(search) => search.finish(points, &|plane, pts, weight| {
+                plane_score(plane, pts, weight, threshold)
+            }),
+            None => RansacOutcome::empty(),
         }
+    }
+}
 
-        if (plane.normal.y as f64) < min_normal_y {
-            continue;
-        }
+/// Inliers within `threshold` of `plane`.
+fn plane_score(plane: &Plane, points: &[Point3<Real>], weight: f64, threshold: f64) -> Score {
+    let inliers = parallel::count(points, |p| plane.distance(p) < threshold);
+    Score {
+        score: inliers as f64 * weight,
+        inliers,
+    }
+}
 
-        let mut lower_inliers = 0usize;
-        let mut all_inliers = 0usize;
-        let mut low_height_error = 0.0_f64;
+/// Planes within ~5 degrees and two inlier thresholds of each other count as
+/// one ground candidate.
+fn same_plane(a: &Plane, b: &Plane, threshold: f64) -> bool {
+    let dot = a.normal.dot(&b.normal);
+    let d_delta = if dot < 0.0 { a.d + b.d } else { a.d - b.d };
+    dot.abs() > 0.996 && d_delta.abs() < threshold * 2.0
+}
+
+fn ransac_options(settings: &MeshSettings, max_iterations: usize) -> RansacOptions {
+    let mut options = RansacOptions::new(max_iterations, settings.ransac_sample_size);
+    options.verify_top = settings.ground_candidate_count.unwrap_or(4).clamp(1, 8);
+    options.seed = ransac::bake_seed(settings);
+    options
+}
 
-        for p in points {
-            if plane.distance(p) < threshold {
-                all_inliers += 1;
-                if (p.y as f64) <= lower_limit {
-                    lower_inliers += 1;
-                    low_height_error += ((p.y as f64) - floor_y).abs();
+/// Describe each verified candidate and resolve `chosen_ground_index`. Returns
+/// the plane to bake against (the chosen one, else the best).
+fn record_ground_candidates(
+    outcome: &RansacOutcome<Plane>,
+    points: &[Point3<Real>],
+    threshold: f64,
+    settings: &MeshSettings,
+    diagnostics: &mut ReconstructionDiagnostics,
+) -> Option<(Plane, f64)> {
+    diagnostics.ransac_inliers = outcome.inliers;
+    diagnostics.ransac_iterations = outcome.iterations;
+    diagnostics.ground_candidates = outcome
+        .candidates
+        .iter()
+        .enumerate()
+        .map(|(index, (plane, score))| {
+            let mut min = [f64::MAX; 3];
+            let mut max = [f64::MIN; 3];
+            let mut height_sum = 0.0;
+            let mut count = 0usize;
+            for p in points {
+                if plane.distance(p) < threshold {
+                    for axis in 0..3 {
+                        min[axis] = min[axis].min(p[axis]);
+                        max[axis] = max[axis].max(p[axis]);
+                    }
+                    height_sum += p.y;
+                    count += 1;
                 }
             }
-        }
+            if count == 0 {
+                min = [0.0; 3];
+                max = [0.0; 3];
+            }
+            GroundCandidate {
+                index,
+                plane: FloorPlane {
+                    normal: [
+                        plane.normal.x,
+                        plane.normal.y,
+                        plane.normal.z,
+                    ],
+                    d: plane.d,
+                },
+                height: height_sum / count.max(1) as f64,
+                inliers: score.inliers,
+                extent_min: min,
+                extent_max: max,
+            }
+        })
+        .collect();
 
-        if lower_inliers == 0 {
-            continue;
+    let chosen = settings
+        .chosen_ground_index
+        .filter(|&index| index < outcome.candidates.len());
+    diagnostics.chosen_ground_index = chosen;
+    if let (Some(requested), None) = (settings.chosen_ground_index, chosen) {
+        journal::record(
+            "ground_plane",
+            JournalKind::Degraded,
+            format!(
+                "chosen_ground_index {} out of range ({} candidates); using the best plane",
+                requested,
+                outcome.candidates.len()
+            ),
+            serde_json::json!({
+                "requested": requested,
+                "candidates": outcome.candidates.len(),
+            }),
+        );
+    }
+    let index = chosen.unwrap_or(0);
+    outcome
+        .candidates
+        .get(index)
+        .map(|(plane, _)| (plane.clone(), diagnostics.ground_candidates[index].height))
+}
+
+/// Modes 1 and 5: the chosen RANSAC plane, facing up, as the floor plane.
+fn record_ransac_floor(plane: &Plane, height: f64, diagnostics: &mut ReconstructionDiagnostics) {
+    let floor = frame::facing_up(FloorPlane {
+        normal: [plane.normal.x, plane.normal.y, plane.normal.z],
+        d: plane.d,
+    });
+    diagnostics.floor_plane_source = if diagnostics.chosen_ground_index.is_some() {
+        "chosen_candidate"
+    } else {
+        "ransac"
+    }
+    .to_string();
+    diagnostics.floor_plane_normal_y = floor.normal[1];
+    diagnostics.floor_plane_height = height;
+    diagnostics.floor_plane = Some(floor);
+}
+
+/// The ground-plane RANSAC of modes 1 and 5, resumable between hypotheses.
+struct GroundSearch {
+    coords: Vec<Point3<Real>>,
+    threshold: f64,
+    search: PlaneSearch,
+}
+
+impl GroundSearch {
+    fn new(points: &[PointNormal], settings: &MeshSettings, tolerance: &Tolerance) -> Self {
+        let coords: Vec<Point3<Real>> = points
+            .iter()
+            .map(|p| Point3::new(p.point.x as Real, p.point.y as Real, p.point.z as Real))
+            .collect();
+        let threshold = tolerance.scaled(0.2);
+        let search = PlaneSearch::new(
+            &coords,
+            threshold,
+            tolerance.degenerate_area(),
+            ransac_options(settings, 2000),
+        );
+        GroundSearch {
+            coords,
+            threshold,
+            search,
         }
+    }
+
+    fn advance(&mut self, out_of_time: &mut dyn FnMut() -> bool) -> bool {
+        self.search.advance(&self.coords, out_of_time)
+    }
 
-        let mean_low_height_error = low_height_error / lower_inliers as f64;
-        let low_band_bonus = lower_inliers as f64 * 3.0;
-        let height_penalty = mean_low_height_error / lower_band_height.max(0.001);
-        let score = all_inliers as f64 + low_band_bonus - height_penalty;
+    /// Record the candidates and return the plane to bake against.
+    fn ground(
+        self,
+        settings: &MeshSettings,
+        diagnostics: &mut ReconstructionDiagnostics,
+    ) -> (Vec<Point3<Real>>, Option<(Plane, f64)>) {
+        let outcome = self.search.finish(&self.coords);
+        let chosen = record_ground_candidates(
+            &outcome,
+            &self.coords,
+            self.threshold,
+            settings,
+            diagnostics,
+        );
+        (self.coords, chosen)
+    }
 
-        if score > best_score {
-            best_score = score;
-            best_inliers = all_inliers;
-            best_plane = Some(plane);
+    /// Mode 1: a quad over the chosen plane's inliers.
+    fn plane_mesh(
+        self,
+        settings: &MeshSettings,
+        diagnostics: &mut ReconstructionDiagnostics,
+    ) -> ReconstructedMesh {
+        if self.coords.len() < 3 {
+            return ReconstructedMesh {
+                vertices: vec![],
+                indices: vec![],
+            };
+        }
+        let threshold = self.threshold;
+        let (coords, chosen) = self.ground(settings, diagnostics);
+        if let Some((plane, height)) = chosen {
+            record_ransac_floor(&plane, height, diagnostics);
+            generate_plane_mesh(&plane, &coords, threshold)
+        } else {
+            ReconstructedMesh {
+                vertices: vec![],
+                indices: vec![],
+            }
         }
     }
 
-    (best_plane, best_inliers)
+    /// Mode 5: a greedy Delaunay terrain over the chosen plane.
+    fn terrain_mesh(
+        self,
+        points: &[PointNormal],
+        settings: &MeshSettings,
+        diagnostics: &mut ReconstructionDiagnostics,
+    ) -> ReconstructedMesh {
+        let Some((plane, height)) = self.ground(settings, diagnostics).1 else {
+            return ReconstructedMesh {
+                vertices: vec![],
+                indices: vec![],
+            };
+        };
+        record_ransac_floor(&plane, height, diagnostics);
+
+        let options = TerrainOptions::from_settings(settings);
+        let built = terrain::build(points, plane.normal, plane.d, &options);
+        diagnostics.terrain_cell_size = built.cell_size;
+        diagnostics.terrain_candidates = built.candidates;
+        diagnostics.terrain_max_error = built.max_error;
+        ReconstructedMesh {
+            vertices: built.vertices,
+            indices: built.indices,
+        }
+    }
 }
 
-fn find_ransac_plane(
-    points: &[Point3<Real>],
+/// Mode 4: peel off up to `plane_count` planes, largest first. Each round runs
+/// RANSAC on the points no earlier plane claimed and then removes its inliers,
+/// so a floor, the walls and a tabletop come back as separate quads. Resumable
+/// between RANSAC hypotheses.
+struct PlanePeeling {
+    remaining: Vec<Point3<Real>>,
+    max_planes: usize,
     threshold: f64,
-    iterations: usize,
-) -> (Option<Plane>, usize) {
-    let mut rng = rand::thread_rng();
-    let mut best_plane = None;
-    let mut max_inliers = 0;
-    let n = points.len();
-
-    if n <= 3 {
-        return (best_plane, max_inliers);
-    }
-
-    for _ in 0..iterations {
-        let idx1 = rng.gen_range(0..n);
-        let idx2 = rng.gen_range(0..n);
-        let idx3 = rng.gen_range(0..n);
-        if idx1 == idx2 || idx2 == idx3 || idx1 == idx3 {
-            continue;
+    degenerate_area: f64,
+    min_inliers: usize,
+    centroid: Vector3<Real>,
+    options: RansacOptions,
+    planes: Vec<DetectedPlane>,
+    /// The round in progress.
+    search: Option<PlaneSearch>,
+    done: bool,
+}
+
+impl PlanePeeling {
+    fn new(points: &[PointNormal], settings: &MeshSettings, tolerance: &Tolerance) -> Self {
+        let remaining: Vec<Point3<Real>> = points
+            .iter()
+            .map(|p| Point3::new(p.point.x as Real, p.point.y as Real, p.point.z as Real))
+            .collect();
+        let max_planes = settings.plane_count.unwrap_or(8).clamp(1, 32);
+        let threshold = settings
+            .plane_threshold
+            .filter(|v| v.is_finite() && *v > 0.0)
+            .unwrap_or_else(|| tolerance.scaled(0.2));
+        let min_inliers = settings
+            .plane_min_inliers
+            .unwrap_or((remaining.len() / 200).max(50))
+            .max(3);
+        let centroid = remaining
+            .iter()
+            .fold(Vector3::zeros(), |sum, p| sum + p.coords)
+            / remaining.len().max(1) as Real;
+        let mut options = ransac_options(settings, 2000);
+        options.verify_top = 1;
+        PlanePeeling {
+            remaining,
+            max_planes,
+            threshold,
+            degenerate_area: tolerance.degenerate_area(),
+            min_inliers,
+            centroid,
+            options,
+            planes: Vec::new(),
+            search: None,
+            done: false,
         }
+    }
 
-        if let Some(plane) = Plane::from_points(&points[idx1], &points[idx2], &points[idx3]) {
-            let inliers = points
-                .iter()
-                .filter(|p| plane.distance(p) < threshold)
-                .count();
-            if inliers > max_inliers {
-                max_inliers = inliers;
-                best_plane = Some(plane);
+    /// Peel planes until done (`true`) or `out_of_time`.
+    fn advance(
+        &mut self,
+        diagnostics: &mut ReconstructionDiagnostics,
+        out_of_time: &mut dyn FnMut() -> bool,
+    ) -> bool {
+        while !self.done {
+            let search = match self.search.as_mut() {
+                Some(search) => search,
+                None if self.planes.len() < self.max_planes
+                    && self.remaining.len() >= self.min_inliers =>
+                {
+                    self.search.insert(PlaneSearch::new(
+                        &self.remaining,
+                        self.threshold,
+                        self.degenerate_area,
+                        self.options,
+                    ))
+                }
+                None => break,
+            };
+            if !search.advance(&self.remaining, out_of_time) {
+                return false;
+            }
+            if let Some(search) = self.search.take() {
+                let outcome = search.finish(&self.remaining);
+                diagnostics.ransac_iterations += outcome.iterations;
+                self.done = !self.peel(outcome, diagnostics);
             }
         }
+        self.done = true;
+        true
     }
 
-    (best_plane, max_inliers)
-}
-
-fn reconstruct_plane_ransac(
-    points: &[PointNormal],
-    diagnostics: &mut ReconstructionDiagnostics,
-) -> ReconstructedMesh {
-    let p_coords: Vec<Point3<Real>> = points
-        .iter()
-        .map(|p| Point3::new(p.point.x as Real, p.point.y as Real, p.point.z as Real))
-        .collect();
+    /// Keep a round's plane and drop its inliers; `false` when the round found
+    /// none worth keeping, which ends the peeling.
+    fn peel(
+        &mut self,
+        outcome: RansacOutcome<Plane>,
+        diagnostics: &mut ReconstructionDiagnostics,
+    ) -> bool {
+        let Some((mut plane, _)) = outcome.candidates.into_iter().next() else {
+            return false;
+        };
+        if outcome.inliers < self.min_inliers {
+            return false;
+        }
 
-    if p_coords.len() < 3 {
-        return ReconstructedMesh {
-            vertices: vec![],
-            indices: vec![],
+        let up = plane.normal.y;
+        let orientation = if up.abs() >= 15.0_f64.to_radians().cos() {
+            "horizontal"
+        } else if up.abs() <= 15.0_f64.to_radians().sin() {
+            "vertical"
+        } else {
+            "oblique"
         };
-    }
+        let flip = if orientation == "horizontal" {
+            up < 0.0
+        } else {
+            plane.normal.dot(&self.centroid) + plane.d < 0.0
+        };
+        if flip {
+            plane.normal = -plane.normal;
+            plane.d = -plane.d;
+        }
 
-    let (best_plane, max_inliers) = find_ransac_plane(&p_coords, 0.2, 2000);
-    diagnostics.ransac_inliers = max_inliers;
+        let threshold = self.threshold;
+        let mesh = generate_plane_mesh(&plane, &self.remaining, threshold);
+        let before = self.remaining.len();
+        self.remaining.retain(|p| plane.distance(p) >= threshold);
+        let inliers = before - self.remaining.len();
+        diagnostics.ransac_inliers += inliers;
+        self.planes.push(DetectedPlane {
+            index: self.planes.len(),
+            plane: FloorPlane {
+                normal: [plane.normal.x, plane.normal.y, plane.normal.z],
+                d: plane.d,
+            },
+            orientation: orientation.to_string(),
+            inliers,
+            mesh: MeshBuffers::new(mesh.vertices, mesh.indices),
+        });
+        true
+    }
+}
 
-    if let Some(plane) = best_plane {
-        generate_plane_mesh(&plane, &p_coords, 0.2)
-    } else {
-        ReconstructedMesh {
-            vertices: vec![],
-            indices: vec![],
-        }
+/// Concatenate the per-plane quads into one mesh.
+fn merge_plane_meshes(planes: &[DetectedPlane]) -> ReconstructedMesh {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    for plane in planes {
+        let offset = (vertices.len() / 3) as u32;
+        vertices.extend_from_slice(&plane.mesh.vertices);
+        indices.extend(plane.mesh.indices.iter().map(|i| i + offset));
     }
+    ReconstructedMesh { vertices, indices }
 }
 
 fn generate_plane_mesh(
@@ -3005,35 +5379,283 @@ fn generate_plane_mesh(
         }
     }
 
-    if count == 0 {
+    if count == 0 {
+        return ReconstructedMesh {
+            vertices: vec![],
+            indices: vec![],
+        };
+    }
+
+    let corners_uv = [
+        (min_u, min_v),
+        (max_u, min_v),
+        (max_u, max_v),
+        (min_u, max_v),
+    ];
+    let mut vertices = Vec::new();
+
+    for (u, v) in corners_uv {
+        let p_rec = u * tangent + v * bitangent - plane.d * normal;
+        vertices.push(p_rec.x as f32);
+        vertices.push(p_rec.y as f32);
+        vertices.push(p_rec.z as f32);
+    }
+
+    ReconstructedMesh {
+        vertices,
+        indices: vec![0, 1, 2, 0, 2, 3],
+    }
+}
+
+fn reconstruct_tsdf(
+    points: &[PointNormal],
+    settings: &MeshSettings,
+    diagnostics: &mut ReconstructionDiagnostics,
+) -> ReconstructedMesh {
+    let mut min = [f64::MAX; 3];
+    let mut max = [f64::MIN; 3];
+    for p in points {
+        let c = [p.point.x, p.point.y, p.point.z];
+        for axis in 0..3 {
+            min[axis] = min[axis].min(c[axis]);
+            max[axis] = max[axis].max(c[axis]);
+        }
+    }
+    let options = TsdfOptions::from_settings(settings, min, max);
+    let build = tsdf::reconstruct(points, &options, &|stage, fraction| {
+        crate::emit_progress(stage, Some(fraction))
+    });
+    diagnostics.tsdf_voxel_size = build.voxel_size;
+    diagnostics.tsdf_grid_width = build.dims[0];
+    diagnostics.tsdf_grid_height = build.dims[1];
+    diagnostics.tsdf_grid_depth = build.dims[2];
+    diagnostics.tsdf_observed_voxels = build.observed_voxels;
+
+    ReconstructedMesh {
+        vertices: build.vertices,
+        indices: build.indices,
+    }
+}
+
+/// Mode 8: dual contouring over the same fused field as mode 3.
+fn reconstruct_dual_contour(
+    points: &[PointNormal],
+    settings: &MeshSettings,
+    diagnostics: &mut ReconstructionDiagnostics,
+) -> ReconstructedMesh {
+    let mut min = [f64::MAX; 3];
+    let mut max = [f64::MIN; 3];
+    for p in points {
+        let c = [p.point.x, p.point.y, p.point.z];
+        for axis in 0..3 {
+            min[axis] = min[axis].min(c[axis]);
+            max[axis] = max[axis].max(c[axis]);
+        }
+    }
+    let options = DualContourOptions::from_settings(settings, min, max);
+    let build = dual_contour::reconstruct(points, &options, &|stage, fraction| {
+        crate::emit_progress(stage, Some(fraction))
+    });
+    diagnostics.tsdf_voxel_size = build.voxel_size;
+    diagnostics.tsdf_grid_width = build.dims[0];
+    diagnostics.tsdf_grid_height = build.dims[1];
+    diagnostics.tsdf_grid_depth = build.dims[2];
+    diagnostics.tsdf_observed_voxels = build.observed_voxels;
+    diagnostics.dc_sharp_vertices = build.sharp_vertices;
+    diagnostics.dc_clamped_vertices = build.clamped_vertices;
+
+    ReconstructedMesh {
+        vertices: build.vertices,
+        indices: build.indices,
+    }
+}
+
+/// Mode 9: naive surface nets over splat occupancy.
+fn reconstruct_surface_nets(
+    points: &[PointNormal],
+    settings: &MeshSettings,
+    diagnostics: &mut ReconstructionDiagnostics,
+) -> ReconstructedMesh {
+    let mut min = [f64::MAX; 3];
+    let mut max = [f64::MIN; 3];
+    for p in points {
+        let c = [p.point.x, p.point.y, p.point.z];
+        for axis in 0..3 {
+            min[axis] = min[axis].min(c[axis]);
+            max[axis] = max[axis].max(c[axis]);
+        }
+    }
+    let options = SurfaceNetsOptions::from_settings(settings, min, max);
+    let build = surface_nets::reconstruct(points, &options, &|stage, fraction| {
+        crate::emit_progress(stage, Some(fraction))
+    });
+    diagnostics.surface_nets_voxel_size = build.voxel_size;
+    diagnostics.surface_nets_occupied_voxels = build.occupied_voxels;
+
+    ReconstructedMesh {
+        vertices: build.vertices,
+        indices: build.indices,
+    }
+}
+
+/// Mode 10: floor plan wall segments extruded from the floor to the ceiling.
+fn reconstruct_walls(
+    points: &[PointNormal],
+    settings: &MeshSettings,
+    diagnostics: &mut ReconstructionDiagnostics,
+) -> ReconstructedMesh {
+    let mut y_values: Vec<f64> = points.iter().map(|p| p.point.y).collect();
+    let floor_y = percentile(&mut y_values, 0.02);
+    let cluster_options = NormalClusterOptions::resolve(settings);
+    let labels = cluster::classify(points, floor_y, &cluster_options);
+    diagnostics.normal_clusters = cluster::summarize(points, &labels);
+
+    crate::emit_progress("wall_fit", None);
+    let options = FloorPlanOptions::from_settings(settings);
+    let plan = floor_plan::build(
+        points,
+        &labels,
+        floor_y,
+        &options,
+        ransac::bake_seed(settings),
+    );
+    diagnostics.wall_segments = plan.segments.len();
+    diagnostics.wall_floor_y = floor_y;
+    if plan.segments.is_empty() {
+        journal::record(
+            "wall_extrude",
+            JournalKind::Degraded,
+            format!(
+                "No wall segments found ({} wall splats in {} plan cells)",
+                plan.wall_splat_count, plan.wall_cell_count
+            ),
+            serde_json::json!({
+                "reason": "no_wall_segments",
+                "wall_splats": plan.wall_splat_count,
+                "wall_cells": plan.wall_cell_count,
+            }),
+        );
         return ReconstructedMesh {
             vertices: vec![],
             indices: vec![],
         };
     }
 
-    let corners_uv = [
-        (min_u, min_v),
-        (max_u, min_v),
-        (max_u, max_v),
-        (min_u, max_v),
-    ];
-    let mut vertices = Vec::new();
+    // Top of the walls: `wall_height` over the floor, else the ceiling above
+    // the floor plan band, else the top of the wall cluster, else 2.5 m.
+    let wall_top = diagnostics
+        .normal_clusters
+        .iter()
+        .find(|c| c.label == NormalGroup::Wall.name())
+        .map(|c| c.max[1])
+        .filter(|y| *y - floor_y > 0.5);
+    let height = settings.wall_height.filter(|v| v.is_finite() && *v > 0.0);
+    let ceiling =
+        wall_extrude::ceiling_height(points, floor_y, options.max_height, &cluster_options);
+    let (ceiling_y, source) = match (height, ceiling, wall_top) {
+        (Some(height), _, _) => (floor_y + height, "wall_height"),
+        (None, Some(y), _) => (y, "ceiling"),
+        (None, None, Some(y)) => (y, "wall_cluster"),
+        (None, None, None) => (floor_y + 2.5, "default"),
+    };
+    diagnostics.wall_ceiling_y = ceiling_y;
+    diagnostics.wall_ceiling_source = source.to_string();
+    journal::record(
+        "wall_extrude",
+        JournalKind::Decision,
+        format!(
+            "Extruded {} wall segment(s) {:.2} m high (from {})",
+            plan.segments.len(),
+            ceiling_y - floor_y,
+            source
+        ),
+        serde_json::json!({
+            "segments": plan.segments.len(),
+            "floor_y": floor_y,
+            "ceiling_y": ceiling_y,
+            "source": source,
+        }),
+    );
 
-    for (u, v) in corners_uv {
-        let p_rec = u * tangent + v * bitangent - plane.d * normal;
-        vertices.push(p_rec.x as f32);
-        vertices.push(p_rec.y as f32);
-        vertices.push(p_rec.z as f32);
+    crate::emit_progress("wall_extrude", None);
+    let thickness = settings
+        .wall_thickness
+        .filter(|v| v.is_finite() && *v > 0.0)
+        .unwrap_or(0.1);
+    let (vertices, indices) = wall_extrude::extrude(&plan.segments, floor_y, ceiling_y, thickness);
+    ReconstructedMesh { vertices, indices }
+}
+
+/// Mode 6: ball pivoting over the splat centres.
+fn reconstruct_ball_pivoting(
+    points: &[PointNormal],
+    settings: &MeshSettings,
+    diagnostics: &mut ReconstructionDiagnostics,
+) -> ReconstructedMesh {
+    let options = BpaOptions::from_settings(settings, points);
+    let build = bpa::reconstruct(points, &options, &|stage, fraction| {
+        crate::emit_progress(stage, Some(fraction))
+    });
+    diagnostics.bpa_radii = build.radii;
+    diagnostics.bpa_boundary_edges = build.boundary_edges;
+    diagnostics.bpa_unused_points = build.unused_points;
+
+    ReconstructedMesh {
+        vertices: build.vertices,
+        indices: build.indices,
+    }
+}
+
+/// Mode 7: the convex hull of the splat centres.
+fn reconstruct_convex_hull(
+    points: &[PointNormal],
+    settings: &MeshSettings,
+    diagnostics: &mut ReconstructionDiagnostics,
+) -> ReconstructedMesh {
+    let positions: Vec<Vector3<f64>> = points.iter().map(|p| p.point.coords).collect();
+    let max_vertices = settings.hull_max_vertices.unwrap_or(usize::MAX);
+    let Some(build) = hull::build(&positions, max_vertices, &|stage, fraction| {
+        crate::emit_progress(stage, Some(fraction))
+    }) else {
+        journal::record(
+            "hull",
+            JournalKind::Degraded,
+            "Filtered cloud is flat or has fewer than 4 points; no hull".to_string(),
+            serde_json::json!({ "reason": "flat", "points": points.len() }),
+        );
+        return ReconstructedMesh {
+            vertices: vec![],
+            indices: vec![],
+        };
+    };
+    diagnostics.hull_vertices = build.vertices.len() / 3;
+    diagnostics.hull_volume = build.volume;
+    diagnostics.hull_points_outside = build.points_outside;
+    if build.points_outside > 0 {
+        journal::record(
+            "hull",
+            JournalKind::Decision,
+            format!(
+                "Stopped the hull at {} vertices with {} points outside",
+                diagnostics.hull_vertices, build.points_outside
+            ),
+            serde_json::json!({
+                "reason": "hull_max_vertices",
+                "vertices": diagnostics.hull_vertices,
+                "points_outside": build.points_outside,
+            }),
+        );
     }
 
     ReconstructedMesh {
-        vertices,
-        indices: vec![0, 1, 2, 0, 2, 3],
+        vertices: build.vertices,
+        indices: build.indices,
     }
 }
 
-fn reconstruct_poisson(points: &[PointNormal]) -> ReconstructedMesh {
+/// Fit the Poisson indicator function; `None` for an empty cloud. The solver
+/// runs to convergence in one call.
+fn solve_poisson(points: &[PointNormal]) -> Option<PoissonReconstruction> {
     let p_coords: Vec<Point3<Real>> = points
         .iter()
         .map(|p| Point3::new(p.point.x as Real, p.point.y as Real, p.point.z as Real))
@@ -3044,14 +5666,16 @@ fn reconstruct_poisson(points: &[PointNormal]) -> ReconstructedMesh {
         .collect();
 
     if p_coords.is_empty() {
-        return ReconstructedMesh {
-            vertices: vec![],
-            indices: vec![],
-        };
+        return None;
     }
 
-    let poisson =
-        PoissonReconstruction::from_points_and_normals(&p_coords, &p_normals, 0.0, 4, 4, 10);
+    Some(PoissonReconstruction::from_points_and_normals(
+        &p_coords, &p_normals, 0.0, 4, 4, 10,
+    ))
+}
+
+/// Extract the surface of a solved reconstruction.
+fn poisson_mesh(poisson: &PoissonReconstruction) -> ReconstructedMesh {
     let mesh_buffers = poisson.reconstruct_mesh_buffers();
     let mut vertices = Vec::new();
     let mut indices = Vec::new();
@@ -3088,6 +5712,30 @@ pub struct RoomFloorBuild {
     pub rejected_cell_count: usize,
     pub fallback_used: bool,
     pub step_label: String,
+    pub backprojection: Option<SplatBackprojection>,
+    pub vertex_alpha: Option<Vec<f32>>,
+    pub vertex_confidence: Option<Vec<f32>>,
+    pub uvs: Option<Vec<f32>>,
+    pub walkability_texture: Option<WalkabilityTexture>,
+    pub face_cells: Option<FaceCells>,
+    pub heightfield: Option<Heightfield>,
+    pub path_costs: Option<PathCosts>,
+    pub void_mesh: Option<VoidMeshBuild>,
+}
+
+/// Catch geometry below a floor whose edge drops off into nothing (balconies,
+/// docks): either one flat `kill_plane` under the whole floor, or an `apron` of
+/// flat cells extending outward from each open edge. Kept separate from the
+/// floor so it never reaches the navmesh.
+pub struct VoidMeshBuild {
+    /// `"kill_plane"` or `"apron"` (the requested `void_plane` mode).
+    pub name: String,
+    pub positions: Vec<f32>,
+    pub indices: Vec<u32>,
+    /// Distance below the floor plane, in metres.
+    pub depth: f64,
+    /// Floor cell edges that face a void cell or the edge of the field.
+    pub open_boundary_edges: usize,
 }
 
 /// Typed failure from [`extract_room_floor`]; `reason` mirrors the TypeScript
@@ -3209,11 +5857,17 @@ fn trim_stray_floor_cells(field: &FieldBuild, cells: &[usize]) -> Vec<usize> {
     best
 }
 
+/// Smallest floor component (cells, then square metres) a room floor may be.
+const MIN_FLOOR_COMPONENT_CELLS: usize = 20;
+const MIN_FLOOR_COMPONENT_AREA: f64 = 1.2;
+
 /// Extract a triangulated room-floor mesh from the 2.5D ground field. Port of the
 /// TypeScript `buildFastFloorMesh` selection + trim + triangulation, with the
-/// seed snapped to the detected floor plane.
+/// seed snapped to the detected floor plane. `splat_count` is the number of
+/// records the point indices address; the back-projection is sized from it.
 pub fn extract_room_floor(
     points: &[PointNormal],
+    splat_count: u32,
     settings: &MeshSettings,
     min_room_floor_area: f64,
     step_label: &str,
@@ -3378,8 +6032,8 @@ pub fn extract_room_floor(
     };
 
     let select = |components: &Vec<FloorComponent>| -> Option<(usize, bool)> {
-        let min_cells = 20usize;
-        let min_area = 1.2_f64;
+        let min_cells = MIN_FLOOR_COMPONENT_CELLS;
+        let min_area = MIN_FLOOR_COMPONENT_AREA;
         let max_seed_distance = 3.25_f64;
         let viable: Vec<usize> = (0..components.len())
             .filter(|&i| {
@@ -3445,6 +6099,20 @@ pub fn extract_room_floor(
         if relaxed_selection.is_some()
             && (selection.is_none() || relaxed_area > area_of_sel(&components, &selection))
         {
+            journal::record(
+                "room_floor",
+                JournalKind::Degraded,
+                format!(
+                    "Strict floor mask too small; using the relaxed mask ({:.2} m^2)",
+                    relaxed_area
+                ),
+                serde_json::json!({
+                    "step": step_label,
+                    "strict_area": area_of_sel(&components, &selection),
+                    "relaxed_area": relaxed_area,
+                    "min_room_floor_area": min_room_floor_area,
+                }),
+            );
             components = relaxed_components;
             selection = relaxed_selection;
             fallback_used = true;
@@ -3470,9 +6138,66 @@ pub fn extract_room_floor(
     };
     if used_largest_fallback {
         fallback_used = true;
+        journal::record(
+            "room_floor",
+            JournalKind::Degraded,
+            "No floor component near the seed; using the largest island".to_string(),
+            serde_json::json!({ "step": step_label, "seed": seed }),
+        );
+    }
+    let (small, far) = components
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != sel_idx)
+        .fold((0usize, 0usize), |(small, far), (_, c)| {
+            if c.cells.len() < MIN_FLOOR_COMPONENT_CELLS
+                || (c.cells.len() as f64 * cs * cs) < MIN_FLOOR_COMPONENT_AREA
+            {
+                (small + 1, far)
+            } else {
+                (small, far + 1)
+            }
+        });
+    journal::record(
+        "room_floor",
+        JournalKind::Decision,
+        format!(
+            "Selected floor component {} of {} ({} cells)",
+            sel_idx,
+            component_count,
+            components[sel_idx].cells.len()
+        ),
+        serde_json::json!({
+            "step": step_label,
+            "component": sel_idx,
+            "cells": components[sel_idx].cells.len(),
+            "distance_to_seed": components[sel_idx].distance_to_seed,
+        }),
+    );
+    for (reason, count) in [("below_min_area", small), ("not_selected", far)] {
+        if count > 0 {
+            journal::record(
+                "room_floor",
+                JournalKind::Dropped,
+                format!("Dropped {} floor components: {}", count, reason),
+                serde_json::json!({ "step": step_label, "reason": reason, "count": count }),
+            );
+        }
     }
 
     let floor_cells = trim_stray_floor_cells(&field, &components[sel_idx].cells);
+    let backprojection = if settings.emit_backprojection.unwrap_or(false) {
+        Some(backproject_splats(
+            splat_count as usize,
+            &context.filtered_points,
+            &field,
+            &components,
+            sel_idx,
+            &floor_cells,
+        ))
+    } else {
+        None
+    };
 
     let selected_area = floor_cells.len() as f64 * cs * cs;
     if selected_area < min_room_floor_area {
@@ -3487,27 +6212,104 @@ pub fn extract_room_floor(
         });
     }
 
+    // Relief scaling is applied to the emitted geometry only: cell
+    // classification, seed matching and backprojection above all ran on the
+    // measured heights.
+    let relief = relief_scale(settings);
+    let relief_reference = field.diagnostics.floor_plane_height;
     let mut positions: Vec<f32> = Vec::new();
     let mut indices: Vec<u32> = Vec::new();
+    let mut face_cells = settings.emit_face_cells.unwrap_or(false).then(Vec::new);
+    let mut heightfield = settings
+        .emit_heightfield
+        .unwrap_or(false)
+        .then(|| Heightfield {
+            width,
+            height: field.height,
+            cell_size: cs,
+            basis: field.basis.clone(),
+            heights: vec![f32::NAN; field.cells.len()],
+            layers: field.cells.iter().map(|c| c.primary_layer as u32).collect(),
+            costs: vec![1.0; field.cells.len()],
+        });
+    let cost_source = CostSource::from_settings(settings);
+    let cost_scale = path_cost::cost_scale(settings);
+    let mut face_costs = settings.emit_path_costs.unwrap_or(false).then(Vec::new);
+    let mut uvs = settings
+        .emit_walkability_texture
+        .unwrap_or(false)
+        .then(Vec::new);
     for &idx in &floor_cells {
+        let cost = cost_source.cell_cost(&field.cells[idx], cost_scale);
+        if let Some(face_costs) = face_costs.as_mut() {
+            face_costs.extend_from_slice(&[cost, cost]);
+        }
+        if let Some(face_cells) = face_cells.as_mut() {
+            let cell = [
+                (idx / width) as u32,
+                (idx % width) as u32,
+                field.cells[idx].primary_layer as u32,
+            ];
+            // Two triangles per cell quad.
+            face_cells.extend_from_slice(&cell);
+            face_cells.extend_from_slice(&cell);
+        }
         let row = (idx / width) as f64;
         let col = (idx % width) as f64;
         let h = field.cells[idx].height;
         let h = if h.is_finite() { h as f64 } else { 0.0 };
+        let h = relief_reference + (h - relief_reference) * relief;
+        if let Some(heightfield) = heightfield.as_mut() {
+            heightfield.heights[idx] = h as f32;
+            heightfield.costs[idx] = cost;
+        }
         let base = (positions.len() / 3) as u32;
-        for p in [
-            point_at(col, row, h),
-            point_at(col, row + 1.0, h),
-            point_at(col + 1.0, row + 1.0, h),
-            point_at(col + 1.0, row, h),
+        for (c, r) in [
+            (col, row),
+            (col, row + 1.0),
+            (col + 1.0, row + 1.0),
+            (col + 1.0, row),
         ] {
+            let p = point_at(c, r, h);
             positions.push(p[0] as f32);
             positions.push(p[1] as f32);
             positions.push(p[2] as f32);
+            if let Some(uvs) = uvs.as_mut() {
+                uvs.extend_from_slice(&walkability::uv(c, r, width, field.height));
+            }
         }
         indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
     }
 
+    let vertex_alpha = if settings.feather_boundary.unwrap_or(false) {
+        let feather_width = settings
+            .feather_width_cells
+            .filter(|v| v.is_finite() && *v > 0.0)
+            .unwrap_or(3.0);
+        Some(feather_floor_alpha(&field, &floor_cells, feather_width))
+    } else {
+        None
+    };
+
+    let vertex_confidence = settings
+        .emit_confidence
+        .unwrap_or(false)
+        .then(|| floor_vertex_confidence(&field, &floor_cells));
+
+    let walkability_texture = uvs.is_some().then(|| {
+        let mut coverage = vec![None; field.cells.len()];
+        for &idx in &floor_cells {
+            coverage[idx] = Some(field.cells[idx].surface_confidence as f64);
+        }
+        walkability::bake(width, field.height, &coverage)
+    });
+
+    let void_mesh = settings.void_plane.as_deref().map(|mode| {
+        let depth = void_plane::depth(settings, mode);
+        let extent = void_plane::extent_cells(settings);
+        build_void_mesh(&field, &floor_cells, mode, depth, extent, &point_at)
+    });
+
     if positions.is_empty() || indices.is_empty() {
         return Err(RoomFloorError {
             reason: "empty_mesh".to_string(),
@@ -3531,9 +6333,380 @@ pub fn extract_room_floor(
         rejected_cell_count,
         fallback_used,
         step_label: step_label.to_string(),
+        backprojection,
+        vertex_alpha,
+        vertex_confidence,
+        uvs,
+        walkability_texture,
+        face_cells: face_cells.map(|cells| FaceCells {
+            grid_width: field.width,
+            grid_height: field.height,
+            cell_size: field.cell_size,
+            cells,
+        }),
+        heightfield,
+        path_costs: face_costs.map(PathCosts::from_face_costs),
+        void_mesh,
     })
 }
 
+/// Flat catch geometry `depth` metres below the floor plane. Open edges are
+/// floor cell edges whose neighbour is a `Void` cell or lies outside the field;
+/// walls and clutter (`Obstacle`, `HeightVariance`) never count. A `kill_plane`
+/// is one quad spanning the floor's cell bounds grown by `extent` cells; an
+/// `apron` covers every void or out-of-field cell within `extent` 4-connected
+/// steps of an open edge, so it only appears where the floor actually drops off.
+fn build_void_mesh(
+    field: &FieldBuild,
+    floor_cells: &[usize],
+    mode: &str,
+    depth: f64,
+    extent: i64,
+    point_at: &dyn Fn(f64, f64, f64) -> [f64; 3],
+) -> VoidMeshBuild {
+    let (w, h) = (field.width as i64, field.height as i64);
+    let mut on_floor = vec![false; field.cells.len()];
+    for &idx in floor_cells {
+        on_floor[idx] = true;
+    }
+    let in_field = |row: i64, col: i64| row >= 0 && col >= 0 && row < h && col < w;
+    let is_open = |row: i64, col: i64| {
+        if !in_field(row, col) {
+            return true;
+        }
+        let idx = (row * w + col) as usize;
+        !on_floor[idx] && matches!(field.cells[idx].state, GroundFieldCellState::Void)
+    };
+
+    let mut seeds: Vec<(i64, i64)> = Vec::new();
+    let (mut min_row, mut min_col, mut max_row, mut max_col) = (h, w, -1_i64, -1_i64);
+    let mut heights: Vec<f64> = Vec::with_capacity(floor_cells.len());
+    for &idx in floor_cells {
+        let row = (idx / field.width) as i64;
+        let col = (idx % field.width) as i64;
+        min_row = min_row.min(row);
+        min_col = min_col.min(col);
+        max_row = max_row.max(row);
+        max_col = max_col.max(col);
+        let height = field.cells[idx].height;
+        if height.is_finite() {
+            heights.push(height as f64);
+        }
+        for (dr, dc) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+            if is_open(row + dr, col + dc) {
+                seeds.push((row + dr, col + dc));
+            }
+        }
+    }
+    let open_boundary_edges = seeds.len();
+
+    let reference = if field.diagnostics.floor_plane_height.is_finite() {
+        field.diagnostics.floor_plane_height
+    } else if heights.is_empty() {
+        0.0
+    } else {
+        percentile(&mut heights, 0.5)
+    };
+    let plane_h = reference - depth;
+
+    let mut positions: Vec<f32> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+    let mut push_quad = |c0: f64, r0: f64, c1: f64, r1: f64| {
+        let base = (positions.len() / 3) as u32;
+        for p in [
+            point_at(c0, r0, plane_h),
+            point_at(c0, r1, plane_h),
+            point_at(c1, r1, plane_h),
+            point_at(c1, r0, plane_h),
+        ] {
+            positions.push(p[0] as f32);
+            positions.push(p[1] as f32);
+            positions.push(p[2] as f32);
+        }
+        indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    };
+
+    if mode == "apron" {
+        // BFS over the field padded by `extent` cells on every side.
+        let pw = w + 2 * extent;
+        let ph = h + 2 * extent;
+        let mut visited = vec![false; (pw * ph) as usize];
+        let mut queue: std::collections::VecDeque<(i64, i64, i64)> =
+            std::collections::VecDeque::new();
+        let mut push =
+            |row: i64,
+             col: i64,
+             steps: i64,
+             queue: &mut std::collections::VecDeque<(i64, i64, i64)>| {
+                let (pr, pc) = (row + extent, col + extent);
+                if pr < 0 || pc < 0 || pr >= ph || pc >= pw {
+                    return;
+                }
+                let slot = (pr * pw + pc) as usize;
+                if !visited[slot] && is_open(row, col) {
+                    visited[slot] = true;
+                    queue.push_back((row, col, steps));
+                }
+            };
+        for &(row, col) in &seeds {
+            push(row, col, 1, &mut queue);
+        }
+        while let Some((row, col, steps)) = queue.pop_front() {
+            push_quad(col as f64, row as f64, col as f64 + 1.0, row as f64 + 1.0);
+            if steps < extent {
+                for (dr, dc) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                    push(row + dr, col + dc, steps + 1, &mut queue);
+                }
+            }
+        }
+    } else if max_row >= min_row {
+        push_quad(
+            (min_col - extent) as f64,
+            (min_row - extent) as f64,
+            (max_col + 1 + extent) as f64,
+            (max_row + 1 + extent) as f64,
+        );
+    }
+
+    VoidMeshBuild {
+        name: mode.to_string(),
+        positions,
+        indices,
+        depth,
+        open_boundary_edges,
+    }
+}
+
+/// Per-vertex alpha for the room-floor quads, in emission order (four corners
+/// per cell). A corner's distance to the floor edge is the smallest chamfer
+/// distance of the floor cells around it (zero when any neighbour is off the
+/// floor); alpha ramps from 0 at the edge to 1 `width` cells in. Inside the band
+/// the ramp is weighted by coverage, the corner's mean cell confidence relative
+/// to the floor median: well-covered edges fade linearly, thinly covered ones
+/// quadratically.
+fn feather_floor_alpha(field: &FieldBuild, floor_cells: &[usize], width: f64) -> Vec<f32> {
+    let (w, h) = (field.width, field.height);
+    let mut on_floor = vec![false; w * h];
+    for &idx in floor_cells {
+        on_floor[idx] = true;
+    }
+
+    // Two-pass chamfer distance to the nearest off-floor cell (grid edges count
+    // as off-floor).
+    let mut dist = vec![f64::INFINITY; w * h];
+    let diagonal = std::f64::consts::SQRT_2;
+    let at = |dist: &[f64], row: isize, col: isize| -> f64 {
+        if row < 0 || col < 0 || row >= h as isize || col >= w as isize {
+            0.0
+        } else {
+            dist[row as usize * w + col as usize]
+        }
+    };
+    for (idx, d) in dist.iter_mut().enumerate() {
+        if !on_floor[idx] {
+            *d = 0.0;
+        }
+    }
+    for row in 0..h as isize {
+        for col in 0..w as isize {
+            let idx = row as usize * w + col as usize;
+            let best = dist[idx]
+                .min(at(&dist, row - 1, col) + 1.0)
+                .min(at(&dist, row, col - 1) + 1.0)
+                .min(at(&dist, row - 1, col - 1) + diagonal)
+                .min(at(&dist, row - 1, col + 1) + diagonal);
+            dist[idx] = best;
+        }
+    }
+    for row in (0..h as isize).rev() {
+        for col in (0..w as isize).rev() {
+            let idx = row as usize * w + col as usize;
+            let best = dist[idx]
+                .min(at(&dist, row + 1, col) + 1.0)
+                .min(at(&dist, row, col + 1) + 1.0)
+                .min(at(&dist, row + 1, col + 1) + diagonal)
+                .min(at(&dist, row + 1, col - 1) + diagonal);
+            dist[idx] = best;
+        }
+    }
+
+    let mut confidences: Vec<f64> = floor_cells
+        .iter()
+        .map(|&idx| field.cells[idx].confidence as f64)
+        .filter(|c| c.is_finite() && *c > 0.0)
+        .collect();
+    let reference = if confidences.is_empty() {
+        1.0
+    } else {
+        percentile(&mut confidences, 0.5).max(1e-6)
+    };
+
+    let mut alpha = Vec::with_capacity(floor_cells.len() * 4);
+    for &idx in floor_cells {
+        let row = (idx / w) as isize;
+        let col = (idx % w) as isize;
+        for (cr, cc) in [
+            (row, col),
+            (row + 1, col),
+            (row + 1, col + 1),
+            (row, col + 1),
+        ] {
+            // Cells sharing grid corner (cr, cc).
+            let mut edge = f64::INFINITY;
+            let mut coverage = 0.0;
+            let mut covered = 0;
+            for (r, c) in [(cr - 1, cc - 1), (cr - 1, cc), (cr, cc - 1), (cr, cc)] {
+                let d = at(&dist, r, c);
+                edge = edge.min(d);
+                if d > 0.0 {
+                    let cell = &field.cells[r as usize * w + c as usize];
+                    if cell.confidence.is_finite() {
+                        coverage += cell.confidence as f64;
+                        covered += 1;
+                    }
+                }
+            }
+            let ramp = (edge / width).clamp(0.0, 1.0);
+            let coverage = if covered > 0 {
+                (coverage / covered as f64 / reference).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            alpha.push((ramp * (coverage + (1.0 - coverage) * ramp)) as f32);
+        }
+    }
+    alpha
+}
+
+/// Per-vertex confidence for the room-floor quads (same vertex order as the
+/// positions). Each corner averages the accumulated surface weight of the
+/// floor cells sharing it, then the values are histogram-equalized so a heat
+/// map spreads over the whole colour ramp instead of saturating on a few dense
+/// cells.
+fn floor_vertex_confidence(field: &FieldBuild, floor_cells: &[usize]) -> Vec<f32> {
+    let (w, h) = (field.width as isize, field.height as isize);
+    let mut on_floor = vec![false; field.cells.len()];
+    for &idx in floor_cells {
+        on_floor[idx] = true;
+    }
+    let weight = |row: isize, col: isize| -> Option<f64> {
+        if row < 0 || col < 0 || row >= h || col >= w {
+            return None;
+        }
+        let idx = (row * w + col) as usize;
+        let c = field.cells[idx].surface_confidence as f64;
+        (on_floor[idx] && c.is_finite()).then_some(c.max(0.0))
+    };
+
+    let mut raw = Vec::with_capacity(floor_cells.len() * 4);
+    for &idx in floor_cells {
+        let row = idx as isize / w;
+        let col = idx as isize % w;
+        for (cr, cc) in [
+            (row, col),
+            (row + 1, col),
+            (row + 1, col + 1),
+            (row, col + 1),
+        ] {
+            let (sum, n) = [(cr - 1, cc - 1), (cr - 1, cc), (cr, cc - 1), (cr, cc)]
+                .into_iter()
+                .filter_map(|(r, c)| weight(r, c))
+                .fold((0.0, 0usize), |(s, n), c| (s + c, n + 1));
+            raw.push(if n > 0 { sum / n as f64 } else { 0.0 });
+        }
+    }
+    equalize_histogram(&raw)
+}
+
+/// Map `values` through their own cumulative distribution (256 bins over the
+/// value range) to `[0, 1]`. A constant input maps to all ones.
+fn equalize_histogram(values: &[f64]) -> Vec<f32> {
+    const BINS: usize = 256;
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    if values.is_empty() || max - min <= f64::EPSILON {
+        return vec![1.0; values.len()];
+    }
+    let bin = |v: f64| (((v - min) / (max - min)) * (BINS - 1) as f64).round() as usize;
+    let mut cdf = [0usize; BINS];
+    for &v in values {
+        cdf[bin(v)] += 1;
+    }
+    for i in 1..BINS {
+        cdf[i] += cdf[i - 1];
+    }
+    let first = cdf.iter().copied().find(|&c| c > 0).unwrap_or(0);
+    let span = (values.len() - first).max(1) as f64;
+    values
+        .iter()
+        .map(|&v| ((cdf[bin(v)] - first) as f64 / span) as f32)
+        .collect()
+}
+
+/// Map each input splat onto the floor component its centre landed in. A splat
+/// that survived `build_context` filtering counts for the field cell containing
+/// its centre when it sits within a height band of that cell's floor; it
+/// `contributed` when that cell is part of the emitted (selected, trimmed) floor.
+/// The maps cover all `splat_count` records, pruned ones included.
+fn backproject_splats(
+    splat_count: usize,
+    filtered: &[PointNormal],
+    field: &FieldBuild,
+    components: &[FloorComponent],
+    selected: usize,
+    floor_cells: &[usize],
+) -> SplatBackprojection {
+    let mut cell_component = vec![-1_i32; field.cells.len()];
+    for (id, component) in components.iter().enumerate() {
+        for &idx in &component.cells {
+            cell_component[idx] = id as i32;
+        }
+    }
+    let mut on_floor = vec![false; field.cells.len()];
+    for &idx in floor_cells {
+        on_floor[idx] = true;
+    }
+
+    let cs = field.cell_size;
+    let band = cs.max(field.diagnostics.sdf_vertical_cell_size * 2.0);
+    let o = field.basis.origin;
+    let axes = [field.basis.tangent, field.basis.bitangent, field.basis.up];
+    let mut component = vec![-1_i32; splat_count];
+    let mut contributed = vec![false; splat_count];
+    let mut contributed_count = 0;
+    for p in filtered {
+        let local = [p.point.x - o[0], p.point.y - o[1], p.point.z - o[2]];
+        let [u, v, h] = axes.map(|a| local[0] * a[0] + local[1] * a[1] + local[2] * a[2]);
+        if u < 0.0 || v < 0.0 {
+            continue;
+        }
+        let col = (u / cs).floor() as usize;
+        let row = (v / cs).floor() as usize;
+        if col >= field.width || row >= field.height {
+            continue;
+        }
+        let idx = row * field.width + col;
+        let floor_h = field.cells[idx].height as f64;
+        if !floor_h.is_finite() || (h - floor_h).abs() > band {
+            continue;
+        }
+        let i = p.index as usize;
+        component[i] = cell_component[idx];
+        if on_floor[idx] && !contributed[i] {
+            contributed[i] = true;
+            contributed_count += 1;
+        }
+    }
+
+    SplatBackprojection {
+        splat_count,
+        contributed: pack_bool_mask(&contributed),
+        contributed_count,
+        component,
+        selected_component: selected as i32,
+    }
+}
+
 /// Stable string name for a [`GroundFieldCellState`] (matches the serde
 /// `snake_case` rename used on the wire).
 fn state_name(state: &GroundFieldCellState) -> &'static str {
@@ -3548,3 +6721,249 @@ fn state_name(state: &GroundFieldCellState) -> &'static str {
         GroundFieldCellState::DiscardedComponent => "discarded_component",
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A field from one string per row: `F` walkable floor at `height`, `W`
+    /// an obstacle, `.` void. Returns the field and its floor cells.
+    fn grid(rows: &[&str], height: f32) -> (FieldBuild, Vec<usize>) {
+        let width = rows[0].len();
+        let mut cells = Vec::new();
+        let mut floor_cells = Vec::new();
+        for (i, c) in rows.concat().chars().enumerate() {
+            let state = match c {
+                'F' => {
+                    floor_cells.push(i);
+                    GroundFieldCellState::Walkable
+                }
+                'W' => GroundFieldCellState::Obstacle,
+                _ => GroundFieldCellState::Void,
+            };
+            cells.push(GroundFieldCell {
+                height,
+                confidence: 1.0,
+                variance: 0.0,
+                normal_alignment: 1.0,
+                obstacle_score: 0.0,
+                primary_layer_height: height,
+                layer_count: 1,
+                primary_layer: 0,
+                peak_density: 1.0,
+                surface_confidence: 1.0,
+                signed_distance: 0.0,
+                gradient: [0.0, 0.0],
+                component_id: -1,
+                state,
+            });
+        }
+        let field = FieldBuild {
+            cells,
+            width,
+            height: rows.len(),
+            cell_size: 1.0,
+            basis: FieldBasis {
+                origin: [0.0; 3],
+                tangent: [1.0, 0.0, 0.0],
+                bitangent: [0.0, 0.0, 1.0],
+                up: [0.0, 1.0, 0.0],
+            },
+            plane: FloorPlane {
+                normal: [0.0, 1.0, 0.0],
+                d: 0.0,
+            },
+            diagnostics: ReconstructionDiagnostics::empty(0),
+        };
+        (field, floor_cells)
+    }
+
+    /// Field column, row and height straight to x, y, z.
+    fn point_at(col: f64, row: f64, height: f64) -> [f64; 3] {
+        [col, height, row]
+    }
+
+    fn corners(positions: &[f32]) -> Vec<[f32; 3]> {
+        positions
+            .chunks_exact(3)
+            .map(|p| [p[0], p[1], p[2]])
+            .collect()
+    }
+
+    #[test]
+    fn void_mesh_apron_grows_only_from_open_edges() {
+        // Walled room with one gap on the east side of the middle row.
+        let (mut field, floor) = grid(&["WWWWW", "WFFFW", "WFFF.", "WFFFW", "WWWWW"], 1.0);
+        field.diagnostics.floor_plane_height = 1.0;
+        let apron = build_void_mesh(&field, &floor, "apron", 0.25, 2, &point_at);
+        assert_eq!(apron.open_boundary_edges, 1);
+        // The void cell in the gap, then the out-of-field cell past it; the
+        // walls stop the flood from spreading along the room.
+        let quads = corners(&apron.positions);
+        assert_eq!((quads.len(), apron.indices.len()), (8, 12));
+        assert_eq!(quads[0], [4.0, 0.75, 2.0]);
+        assert_eq!(quads[4], [5.0, 0.75, 2.0]);
+        assert!(quads.iter().all(|p| p[1] == 0.75));
+
+        // A floor touching the field edge is open there too.
+        let (field, floor) = grid(&["FF", "WW"], 1.0);
+        let apron = build_void_mesh(&field, &floor, "apron", 0.0, 1, &point_at);
+        assert_eq!(apron.open_boundary_edges, 4);
+        assert_eq!(apron.indices.len(), 4 * 6);
+    }
+
+    #[test]
+    fn void_mesh_kill_plane_spans_the_grown_floor_bounds() {
+        let (mut field, floor) = grid(&["WWWWW", "WFFFW", "WFFF.", "WFFFW", "WWWWW"], 1.0);
+        field.diagnostics.floor_plane_height = 1.5;
+        let plane = build_void_mesh(&field, &floor, "kill_plane", 2.0, 8, &point_at);
+        assert_eq!(plane.name, "kill_plane");
+        assert_eq!(plane.indices, vec![0, 1, 2, 0, 2, 3]);
+        let quad = corners(&plane.positions);
+        assert_eq!(quad[0], [-7.0, -0.5, -7.0]);
+        assert_eq!(quad[2], [12.0, -0.5, 12.0]);
+
+        // Without a fitted floor plane the depth is measured from the median
+        // floor cell height.
+        field.diagnostics.floor_plane_height = f64::NAN;
+        for (i, &idx) in floor.iter().enumerate() {
+            field.cells[idx].height = i as f32;
+        }
+        let plane = build_void_mesh(&field, &floor, "kill_plane", 2.0, 8, &point_at);
+        assert_eq!(corners(&plane.positions)[0][1], 2.0);
+
+        // No floor, no plane.
+        let plane = build_void_mesh(&field, &[], "kill_plane", 2.0, 8, &point_at);
+        assert!(plane.positions.is_empty() && plane.indices.is_empty());
+    }
+
+    #[test]
+    fn feathered_alpha_falls_off_toward_open_edges() {
+        let (mut field, floor) = grid(&["FFFFFFFFF"; 9], 0.0);
+        // Alpha of the grid corner at `row`, `col`: the first corner of the
+        // cell below and right of it.
+        let corner = |alpha: &[f32], row: usize, col: usize| alpha[(row * 9 + col) * 4];
+
+        let alpha = feather_floor_alpha(&field, &floor, 2.0);
+        assert_eq!(alpha.len(), floor.len() * 4);
+        let diagonal: Vec<f32> = (0..5).map(|i| corner(&alpha, i, i)).collect();
+        assert_eq!(diagonal, [0.0, 0.5, 1.0, 1.0, 1.0]);
+        for row in 2..=7 {
+            for col in 2..=7 {
+                assert_eq!(corner(&alpha, row, col), 1.0);
+            }
+        }
+
+        // A thinly covered border fades faster, and the interior stays opaque.
+        for (i, cell) in field.cells.iter_mut().enumerate() {
+            let (row, col) = (i / 9, i % 9);
+            if row == 0 || col == 0 || row == 8 || col == 8 {
+                cell.confidence = 0.5;
+            }
+        }
+        let thin = feather_floor_alpha(&field, &floor, 2.0);
+        assert_eq!(corner(&thin, 0, 0), 0.0);
+        assert!(corner(&thin, 1, 1) < 0.5);
+        assert_eq!(corner(&thin, 4, 4), 1.0);
+    }
+
+    /// A 4 m square of upward-facing splats on a gentle ramp along x.
+    fn ramp_floor() -> Vec<PointNormal> {
+        let mut points = Vec::new();
+        for i in 0..80 {
+            for j in 0..80 {
+                let (x, z) = (i as f64 * 0.05, j as f64 * 0.05);
+                points.push(PointNormal {
+                    point: Point3::new(x, 0.04 * x, z),
+                    normal: Vector3::new(0.0, 1.0, 0.0),
+                    scale: Vector3::new(0.03, 0.005, 0.03),
+                    opacity: 4.0,
+                    color: [0.5; 3],
+                    index: points.len() as u32,
+                });
+            }
+        }
+        points
+    }
+
+    fn room_floor_settings(patch: serde_json::Value) -> MeshSettings {
+        let mut json = crate::fast_nav_preset_json();
+        for (k, v) in patch.as_object().unwrap() {
+            json[k] = v.clone();
+        }
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn relief_scale_moves_vertices_but_not_the_floor_selection() {
+        let points = ramp_floor();
+        let bake = |relief: f64| {
+            let settings = room_floor_settings(serde_json::json!({ "relief_scale": relief }));
+            extract_room_floor(&points, points.len() as u32, &settings, 1.0, "test")
+                .unwrap_or_else(|e| panic!("{}", e.message))
+        };
+        let measured = bake(1.0);
+        let reference = measured.diagnostics.floor_plane_height;
+        for relief in [0.0, 2.5] {
+            let scaled = bake(relief);
+            // Same cells, same classification: only the heights change.
+            assert_eq!(scaled.selected_cell_count, measured.selected_cell_count);
+            assert_eq!(scaled.accepted_cell_count, measured.accepted_cell_count);
+            assert_eq!(scaled.indices, measured.indices);
+            for (a, b) in measured
+                .positions
+                .chunks_exact(3)
+                .zip(scaled.positions.chunks_exact(3))
+            {
+                assert_eq!((a[0], a[2]), (b[0], b[2]));
+                let expected = reference + (a[1] as f64 - reference) * relief;
+                assert!((b[1] as f64 - expected).abs() < 1e-4);
+            }
+        }
+        // The ramp really has relief to scale.
+        let ys = measured.positions.iter().skip(1).step_by(3);
+        let (lo, hi) = ys.fold((f32::MAX, f32::MIN), |(lo, hi), &y| (lo.min(y), hi.max(y)));
+        assert!(hi - lo > 0.1);
+    }
+
+    #[test]
+    fn sliced_meshing_matches_one_pass() {
+        // A ramp plus a wall, so mode 4 peels more than one plane.
+        let mut points = ramp_floor();
+        for i in 0..80 {
+            for j in 0..40 {
+                points.push(PointNormal {
+                    index: points.len() as u32,
+                    ..crate::splat::test_splat(
+                        [0.0, j as f64 * 0.05, i as f64 * 0.05],
+                        [1.0, 0.0, 0.0],
+                    )
+                });
+            }
+        }
+        for mode in [1, 2, 4, 5] {
+            let settings: MeshSettings = serde_json::from_value(serde_json::json!({
+                "mode": mode,
+                "collision_voxel_size": 0.2,
+                "collision_fill_size": 0.4,
+            }))
+            .unwrap();
+            let whole = reconstruct_mesh(&points, &settings);
+            assert!(!whole.mesh.indices.is_empty(), "mode {}", mode);
+            // Out of time after every step: each call does the least it can.
+            let mut job = MeshJob::prepare(&points, &settings);
+            let mut slices = 1;
+            while !job.mesh_slice(&settings, &mut || true) {
+                slices += 1;
+            }
+            let sliced = job.finish(&settings);
+            assert!(slices > 2, "mode {} ran in {} slices", mode, slices);
+            assert_eq!(sliced.mesh.vertices, whole.mesh.vertices, "mode {}", mode);
+            assert_eq!(sliced.mesh.indices, whole.mesh.indices, "mode {}", mode);
+            assert_eq!(
+                sliced.diagnostics.ransac_iterations,
+                whole.diagnostics.ransac_iterations
+            );
+        }
+    }
+}
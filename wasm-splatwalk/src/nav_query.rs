@@ -0,0 +1,788 @@
+//! Path queries over a baked walkable mesh.
+//!
+//! Hosts used to ship the output mesh back into a separate navigation library
+//! just to ask for a path. A [`NavMeshQuery`] keeps the mesh in the core
+//! instead: its walkable faces (the `agent_max_slope` / `max_step_height`
+//! test of `reachable_from`) form a graph whose edges are the shared face
+//! edges, matched in plan view so that the voxel floors' stacked corners link
+//! across a riser when it is within `agent_max_climb`. [`NavGraph::find_path`]
+//! runs A* over that graph, from face to face through the midpoints of the
+//! shared edges. Those midpoints zig-zag across every triangle, so with
+//! `smooth` the edges crossed become portals for the "simple stupid funnel
+//! algorithm" (string pulling), which keeps only the corners the walk has to
+//! turn around. [`NavGraph::raycast`] walks a straight line across the same
+//! graph, face to face, and stops at the first edge without a walkable face
+//! on the other side, for line-of-movement checks and steering.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::artifact::{self, Artifact};
+use crate::epsilon;
+use crate::output_space::{self, OutputTransform};
+use crate::reachability::{self, AgentLimits};
+use crate::walk_sim::{self, WalkOptions};
+use crate::SplatwalkError;
+
+/// Schema version of `NavMeshQuery.save` blobs (`NAVQ` artifacts).
+pub const NAVMESH_QUERY_VERSION: u32 = 1;
+
+const MESH_CHUNK: [u8; 4] = *b"MESH";
+const SETTINGS_CHUNK: [u8; 4] = *b"SETT";
+
+/// The walkable faces of a mesh and the edges that join them.
+pub struct NavGraph {
+    positions: Vec<Vector3<f64>>,
+    faces: Vec<[usize; 3]>,
+    walkable: Vec<bool>,
+    /// Per face: the neighbouring faces and the corners of the shared edge,
+    /// as this face's own vertex indices, left then right when walking out of
+    /// this face in plan view.
+    neighbours: Vec<Vec<(usize, [usize; 2])>>,
+}
+
+/// Where a [`NavGraph::raycast`] stopped.
+pub struct RayHit {
+    pub point: Vector3<f64>,
+    /// Plan-view distance from the ray's origin.
+    pub distance: f64,
+    /// Outward plan-view normal of the boundary edge hit; `None` when the ray
+    /// ran its full length on the mesh.
+    pub normal: Option<Vector3<f64>>,
+}
+
+struct Frontier {
+    cost: f64,
+    face: usize,
+}
+
+impl PartialEq for Frontier {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Frontier {}
+
+impl PartialOrd for Frontier {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Frontier {
+    // Reversed so `BinaryHeap` pops the cheapest face first.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .total_cmp(&self.cost)
+            .then_with(|| other.face.cmp(&self.face))
+    }
+}
+
+impl NavGraph {
+    /// The graph of `vertices` / `indices` (packed xyz and triangles). `link`
+    /// is the plan-view distance under which two corners count as the same.
+    /// Triangles with a corner out of range are left out.
+    pub fn new(vertices: &[f32], indices: &[u32], limits: &AgentLimits, link: f64) -> Self {
+        let positions: Vec<Vector3<f64>> = vertices
+            .chunks_exact(3)
+            .map(|v| Vector3::new(v[0] as f64, v[1] as f64, v[2] as f64))
+            .collect();
+        let faces: Vec<[usize; 3]> = indices
+            .chunks_exact(3)
+            .map(|f| [f[0] as usize, f[1] as usize, f[2] as usize])
+            .collect();
+        let walkable = reachability::walkable_faces(&positions, &faces, limits);
+
+        let link = link.max(1e-6);
+        let quantize =
+            |p: &Vector3<f64>| ((p.x / link).round() as i64, (p.z / link).round() as i64);
+        let mut edges: HashMap<_, Vec<(usize, [usize; 2])>> = HashMap::new();
+        for (fi, f) in faces.iter().enumerate().filter(|&(fi, _)| walkable[fi]) {
+            for k in 0..3 {
+                let (a, b) = (f[k], f[(k + 1) % 3]);
+                let (qa, qb) = (quantize(&positions[a]), quantize(&positions[b]));
+                if qa != qb {
+                    edges
+                        .entry((qa.min(qb), qa.max(qb)))
+                        .or_default()
+                        .push((fi, [a, b]));
+                }
+            }
+        }
+        let mut neighbours = vec![Vec::new(); faces.len()];
+        for group in edges.values() {
+            for &(fi, [a, b]) in group {
+                let height = |e: [usize; 2]| (positions[e[0]].y + positions[e[1]].y) / 2.0;
+                // The face's third corner lies behind the edge, whatever the
+                // winding.
+                let behind = positions[faces[fi].iter().sum::<usize>() - a - b];
+                let midpoint = (positions[a] + positions[b]) / 2.0;
+                let edge = if side(behind, midpoint, positions[a]) > 0.0 {
+                    [a, b]
+                } else {
+                    [b, a]
+                };
+                for &(fj, other) in group {
+                    if fj != fi && (height(edge) - height(other)).abs() <= limits.max_climb {
+                        neighbours[fi].push((fj, edge));
+                    }
+                }
+            }
+        }
+        NavGraph {
+            positions,
+            faces,
+            walkable,
+            neighbours,
+        }
+    }
+
+    /// The walkable face under `p` (the one whose plan-view triangle holds it,
+    /// nearest in height), else the walkable face whose centroid is nearest.
+    pub fn locate(&self, p: Vector3<f64>) -> Option<usize> {
+        self.face_under(p).map(|(fi, _)| fi).or_else(|| {
+            let walkable = (0..self.faces.len()).filter(|&fi| self.walkable[fi]);
+            walkable.min_by(|&a, &b| {
+                (self.centroid(a) - p)
+                    .norm()
+                    .total_cmp(&(self.centroid(b) - p).norm())
+            })
+        })
+    }
+
+    /// The walkable face whose plan-view triangle holds `p`, nearest `p` in
+    /// height, and its height there.
+    pub fn face_under(&self, p: Vector3<f64>) -> Option<(usize, f64)> {
+        (0..self.faces.len())
+            .filter(|&fi| self.walkable[fi])
+            .filter_map(|fi| self.height_at(fi, p).map(|y| (fi, y)))
+            .min_by(|a, b| (a.1 - p.y).abs().total_cmp(&(b.1 - p.y).abs()))
+    }
+
+    /// The corners of every walkable face.
+    pub fn walkable_triangles(&self) -> Vec<[Vector3<f64>; 3]> {
+        (0..self.faces.len())
+            .filter(|&fi| self.walkable[fi])
+            .map(|fi| self.faces[fi].map(|v| self.positions[v]))
+            .collect()
+    }
+
+    /// Height of face `fi` at `p`'s plan-view position, when it lies inside.
+    fn height_at(&self, fi: usize, p: Vector3<f64>) -> Option<f64> {
+        let [a, b, c] = self.faces[fi].map(|v| self.positions[v]);
+        let cross = |u: Vector3<f64>, v: Vector3<f64>, w: Vector3<f64>| {
+            (v.x - u.x) * (w.z - u.z) - (w.x - u.x) * (v.z - u.z)
+        };
+        let area = cross(a, b, c);
+        if area.abs() < 1e-12 {
+            return None;
+        }
+        let (wa, wb) = (cross(p, b, c) / area, cross(a, p, c) / area);
+        let wc = 1.0 - wa - wb;
+        (wa >= -1e-9 && wb >= -1e-9 && wc >= -1e-9).then(|| wa * a.y + wb * b.y + wc * c.y)
+    }
+
+    fn centroid(&self, fi: usize) -> Vector3<f64> {
+        let [a, b, c] = self.faces[fi].map(|v| self.positions[v]);
+        (a + b + c) / 3.0
+    }
+
+    /// The shared edges crossed on the cheapest walk from the face under
+    /// `start` to the face under `end`, in order; `None` when no walk joins
+    /// them. Each step costs the straight line from where the walk entered
+    /// a face to the midpoint of the edge it leaves by.
+    pub fn portals(&self, start: Vector3<f64>, end: Vector3<f64>) -> Option<Vec<[usize; 2]>> {
+        let (from, to) = (self.locate(start)?, self.locate(end)?);
+        let midpoint = |e: [usize; 2]| (self.positions[e[0]] + self.positions[e[1]]) / 2.0;
+
+        let n = self.faces.len();
+        let mut cost = vec![f64::INFINITY; n];
+        let mut entry = vec![start; n];
+        let mut came_from: Vec<Option<(usize, [usize; 2])>> = vec![None; n];
+        let mut closed = vec![false; n];
+        let mut heap = BinaryHeap::new();
+        cost[from] = 0.0;
+        heap.push(Frontier {
+            cost: (end - start).norm(),
+            face: from,
+        });
+        while let Some(Frontier { face, .. }) = heap.pop() {
+            if closed[face] {
+                continue;
+            }
+            if face == to {
+                let mut portals = Vec::new();
+                let mut at = to;
+                while let Some((previous, edge)) = came_from[at] {
+                    portals.push(edge);
+                    at = previous;
+                }
+                portals.reverse();
+                return Some(portals);
+            }
+            closed[face] = true;
+            for &(next, edge) in &self.neighbours[face] {
+                let m = midpoint(edge);
+                let c = cost[face] + (m - entry[face]).norm();
+                if !closed[next] && c < cost[next] {
+                    cost[next] = c;
+                    entry[next] = m;
+                    came_from[next] = Some((face, edge));
+                    heap.push(Frontier {
+                        cost: c + (end - m).norm(),
+                        face: next,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    /// Waypoints from `start` to `end`, or `None` when no walk joins them:
+    /// the two points with the midpoint of every shared edge crossed between
+    /// them, or with `smooth` only the edge corners the walk turns around.
+    pub fn find_path(
+        &self,
+        start: Vector3<f64>,
+        end: Vector3<f64>,
+        smooth: bool,
+    ) -> Option<Vec<Vector3<f64>>> {
+        let portals = self.portals(start, end)?;
+        if smooth {
+            return Some(self.funnel(start, end, &portals));
+        }
+        let mut path = vec![start];
+        path.extend(
+            portals
+                .iter()
+                .map(|e| (self.positions[e[0]] + self.positions[e[1]]) / 2.0),
+        );
+        path.push(end);
+        Some(path)
+    }
+
+    /// Walk from the face under `origin` along the plan-view `direction` for
+    /// up to `max_distance`, crossing shared edges like [`Self::find_path`]
+    /// does, and stop at the first edge with no walkable face beyond: a wall,
+    /// a drop, a step over `max_climb` or the mesh border. `None` when there
+    /// is no walkable face or `direction` is vertical.
+    pub fn raycast(
+        &self,
+        origin: Vector3<f64>,
+        direction: Vector3<f64>,
+        max_distance: f64,
+    ) -> Option<RayHit> {
+        let plan = Vector3::new(direction.x, 0.0, direction.z);
+        if plan.norm() < 1e-12 {
+            return None;
+        }
+        let d = plan / plan.norm();
+        let mut face = self.locate(origin)?;
+        let mut t = 0.0;
+        let on_face = |face: usize, t: f64| {
+            let p = origin + d * t;
+            let y = self
+                .height_at(face, p)
+                .unwrap_or_else(|| self.centroid(face).y);
+            Vector3::new(p.x, y, p.z)
+        };
+        // Each face is entered at most once along a straight line.
+        for _ in 0..self.faces.len() {
+            let [a, b, c] = self.faces[face];
+            let [pa, pb, pc] = [a, b, c].map(|v| self.positions[v]);
+            // Corners in the order that keeps the face left of every edge.
+            let corners = if side(pa, pb, pc) > 0.0 {
+                [a, b, c]
+            } else {
+                [a, c, b]
+            };
+            // The ray leaves across the first edge it moves to the right of.
+            let exit = (0..3)
+                .filter_map(|k| {
+                    let (u, v) = (corners[k], corners[(k + 1) % 3]);
+                    let (pu, pv) = (self.positions[u], self.positions[v]);
+                    let rate = (pv.x - pu.x) * d.z - (pv.z - pu.z) * d.x;
+                    (rate < 0.0).then(|| (-side(pu, pv, origin) / rate, [u, v]))
+                })
+                .min_by(|x, y| x.0.total_cmp(&y.0));
+            let Some((leave, [u, v])) = exit else {
+                break;
+            };
+            let leave = leave.max(t);
+            if leave >= max_distance {
+                return Some(RayHit {
+                    point: on_face(face, max_distance),
+                    distance: max_distance,
+                    normal: None,
+                });
+            }
+            let beyond = self.neighbours[face]
+                .iter()
+                .find(|(_, e)| *e == [u, v] || *e == [v, u]);
+            if let Some(&(next, _)) = beyond {
+                face = next;
+                t = leave;
+                continue;
+            }
+            let (pu, pv) = (self.positions[u], self.positions[v]);
+            let p = origin + d * leave;
+            let edge = pv - pu;
+            let length = edge.x.hypot(edge.z);
+            let along = if length > 0.0 {
+                (((p.x - pu.x) * edge.x + (p.z - pu.z) * edge.z) / (length * length))
+                    .clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            return Some(RayHit {
+                point: Vector3::new(p.x, pu.y + edge.y * along, p.z),
+                distance: leave,
+                normal: (length > 0.0).then(|| Vector3::new(edge.z, 0.0, -edge.x) / length),
+            });
+        }
+        Some(RayHit {
+            point: on_face(face, t),
+            distance: t,
+            normal: None,
+        })
+    }
+
+    /// String-pull `start` to `end` through `portals` (left, right) in plan
+    /// view. The apex fans a funnel out over the portals; when a side would
+    /// cross the other, the other side's corner becomes a waypoint and the
+    /// new apex, and the scan restarts from the portal after it.
+    fn funnel(
+        &self,
+        start: Vector3<f64>,
+        end: Vector3<f64>,
+        portals: &[[usize; 2]],
+    ) -> Vec<Vector3<f64>> {
+        let mut sides: Vec<[Vector3<f64>; 2]> = portals
+            .iter()
+            .map(|e| [self.positions[e[0]], self.positions[e[1]]])
+            .collect();
+        sides.push([end, end]);
+        let same =
+            |a: Vector3<f64>, b: Vector3<f64>| (a.x - b.x).powi(2) + (a.z - b.z).powi(2) < 1e-12;
+
+        let mut path = vec![start];
+        let (mut apex, mut left, mut right) = (start, start, start);
+        let (mut left_at, mut right_at) = (0, 0);
+        let mut i = 0;
+        while i < sides.len() {
+            let [l, r] = sides[i];
+            let portal = i + 1;
+            // Tighten the right side, unless it would cross the left.
+            if side(apex, right, r) >= 0.0 {
+                if same(apex, right) || side(apex, left, r) < 0.0 {
+                    right = r;
+                    right_at = portal;
+                } else {
+                    apex = left;
+                    (right, right_at) = (left, left_at);
+                    i = left_at;
+                    // Corners stacked across a riser are one waypoint.
+                    if !same(path[path.len() - 1], apex) {
+                        path.push(apex);
+                    }
+                    continue;
+                }
+            }
+            // Tighten the left side, unless it would cross the right.
+            if side(apex, left, l) <= 0.0 {
+                if same(apex, left) || side(apex, right, l) > 0.0 {
+                    left = l;
+                    left_at = portal;
+                } else {
+                    apex = right;
+                    (left, left_at) = (right, right_at);
+                    i = right_at;
+                    if !same(path[path.len() - 1], apex) {
+                        path.push(apex);
+                    }
+                    continue;
+                }
+            }
+            i += 1;
+        }
+        // The last apex may be `end` itself, from the closing portal.
+        if path.len() > 1 && same(path[path.len() - 1], end) {
+            path.pop();
+        }
+        path.push(end);
+        path
+    }
+}
+
+/// Which side of the plan-view line `o -> d` the point `p` lies on: positive
+/// to the left, negative to the right.
+fn side(o: Vector3<f64>, d: Vector3<f64>, p: Vector3<f64>) -> f64 {
+    (d.x - o.x) * (p.z - o.z) - (d.z - o.z) * (p.x - o.x)
+}
+
+/// The `MESH` chunk of a saved query: vertex and index counts (`u32`), then
+/// the `f32` positions and `u32` indices.
+fn read_mesh(bytes: &[u8]) -> Result<QueryMesh, String> {
+    let word = |i: usize| {
+        bytes
+            .get(i * 4..i * 4 + 4)
+            .map(|w| [w[0], w[1], w[2], w[3]])
+    };
+    let (Some(v), Some(i)) = (word(0), word(1)) else {
+        return Err("Truncated MESH chunk".to_string());
+    };
+    let (vertices, indices) = (
+        u32::from_le_bytes(v) as usize,
+        u32::from_le_bytes(i) as usize,
+    );
+    if bytes.len() != 8 + vertices * 12 + indices * 4 || indices % 3 != 0 {
+        return Err("Malformed MESH chunk".to_string());
+    }
+    let words = bytes[8..].chunks_exact(4).map(|w| [w[0], w[1], w[2], w[3]]);
+    Ok(QueryMesh {
+        vertices: words
+            .clone()
+            .take(vertices * 3)
+            .map(f32::from_le_bytes)
+            .collect(),
+        indices: words.skip(vertices * 3).map(u32::from_le_bytes).collect(),
+    })
+}
+
+/// A mesh from a bake result: `{ vertices, indices }`, plain arrays or typed
+/// buffers.
+#[derive(Deserialize)]
+struct QueryMesh {
+    vertices: Vec<f32>,
+    indices: Vec<u32>,
+}
+
+/// What `NavMeshQuery.raycast` returns.
+#[derive(Serialize)]
+struct RaycastResult {
+    hit: bool,
+    point: [f32; 3],
+    distance: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    normal: Option<[f32; 3]>,
+}
+
+/// Path queries over a baked mesh.
+#[wasm_bindgen]
+pub struct NavMeshQuery {
+    graph: NavGraph,
+    transform: Option<OutputTransform>,
+    max_climb: f64,
+    /// The settings as passed, for `save`.
+    settings: serde_json::Value,
+}
+
+#[wasm_bindgen]
+impl NavMeshQuery {
+    /// Index `mesh` (the `mesh` of a bake result, in the settings'
+    /// `output_space`) for path queries. `settings` supplies the agent limits
+    /// and `output_space`; pass the bake settings.
+    #[wasm_bindgen(constructor)]
+    pub fn new(mesh: JsValue, settings: JsValue) -> Result<NavMeshQuery, JsValue> {
+        let mesh: QueryMesh = serde_wasm_bindgen::from_value(mesh)
+            .map_err(|e| SplatwalkError::InvalidSettings(format!("Invalid mesh: {}", e)))?;
+        let stored = if settings.is_undefined() || settings.is_null() {
+            serde_json::Value::Null
+        } else {
+            serde_wasm_bindgen::from_value(settings.clone())
+                .map_err(|e| SplatwalkError::InvalidSettings(e.to_string()))?
+        };
+        Self::build(mesh, settings, stored)
+    }
+
+    /// Save the query as a `NAVQ` artifact: its mesh, in `output_space`, and
+    /// the settings it was built with. `NavMeshQuery.load` rebuilds it.
+    pub fn save(&self) -> Result<Vec<u8>, JsValue> {
+        let graph = &self.graph;
+        let mut mesh = Vec::with_capacity(8 + (graph.positions.len() + graph.faces.len()) * 12);
+        mesh.extend_from_slice(&(graph.positions.len() as u32).to_le_bytes());
+        mesh.extend_from_slice(&(graph.faces.len() as u32 * 3).to_le_bytes());
+        for &p in &graph.positions {
+            for c in self.output(p) {
+                mesh.extend_from_slice(&c.to_le_bytes());
+            }
+        }
+        for &i in graph.faces.iter().flatten() {
+            mesh.extend_from_slice(&(i as u32).to_le_bytes());
+        }
+        let settings = serde_json::to_vec(&self.settings)
+            .map_err(|e| SplatwalkError::InvalidSettings(e.to_string()))?;
+        let mut artifact = Artifact::new(&artifact::NAVMESH_QUERY);
+        artifact.push(MESH_CHUNK, true, mesh);
+        artifact.push(SETTINGS_CHUNK, true, settings);
+        Ok(artifact
+            .encode()
+            .map_err(SplatwalkError::ReconstructionFailed)?)
+    }
+
+    /// Rebuild a query from `save` bytes written by this or an older build.
+    pub fn load(bytes: &[u8]) -> Result<NavMeshQuery, JsValue> {
+        let artifact = artifact::decode(bytes, &artifact::NAVMESH_QUERY)
+            .map_err(SplatwalkError::InvalidSettings)?;
+        let mesh = artifact
+            .require(&MESH_CHUNK)
+            .and_then(read_mesh)
+            .map_err(SplatwalkError::InvalidSettings)?;
+        let stored: serde_json::Value = artifact
+            .require(&SETTINGS_CHUNK)
+            .and_then(|json| serde_json::from_slice(json).map_err(|e| e.to_string()))
+            .map_err(SplatwalkError::InvalidSettings)?;
+        let settings = stored
+            .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+            .map_err(|e| SplatwalkError::InvalidSettings(e.to_string()))?;
+        Self::build(mesh, settings, stored)
+    }
+
+    /// Waypoints from `start` to `end` (`[x, y, z]`, in `output_space`) as
+    /// packed xyz: `start`, the midpoint of every face edge crossed, `end`.
+    /// With `smooth`, the path is string-pulled through those edges instead
+    /// and keeps only the corners it turns around. Empty when no walk joins
+    /// them or the mesh has no walkable face.
+    pub fn find_path(
+        &self,
+        start: Vec<f64>,
+        end: Vec<f64>,
+        smooth: Option<bool>,
+    ) -> Result<Vec<f32>, JsValue> {
+        let (start, end) = (self.point(&start)?, self.point(&end)?);
+        let path = self
+            .graph
+            .find_path(start, end, smooth.unwrap_or(false))
+            .unwrap_or_default();
+        Ok(path.iter().flat_map(|&p| self.output(p)).collect())
+    }
+
+    /// Walk from `origin` along `direction` (`[x, y, z]`, in `output_space`;
+    /// only its horizontal part counts) and report the first boundary of the
+    /// walkable surface: `{ hit, point, distance, normal? }`. `hit` is false
+    /// when the ray ran `max_distance` (default unlimited) without reaching
+    /// one; `normal` is the boundary edge's outward horizontal normal.
+    /// `undefined` when the mesh has no walkable face.
+    pub fn raycast(
+        &self,
+        origin: Vec<f64>,
+        direction: Vec<f64>,
+        max_distance: Option<f64>,
+    ) -> Result<JsValue, JsValue> {
+        let origin = self.point(&origin)?;
+        let direction = self.point(&direction)?;
+        let max_distance = max_distance.unwrap_or(f64::INFINITY);
+        if max_distance.is_nan() || max_distance < 0.0 {
+            return Err(SplatwalkError::InvalidSettings(
+                "Raycast max_distance must be non-negative".to_string(),
+            )
+            .into());
+        }
+        if direction.x.hypot(direction.z) < 1e-12 {
+            return Err(SplatwalkError::InvalidSettings(
+                "Raycast direction must not be vertical".to_string(),
+            )
+            .into());
+        }
+        let result = self
+            .graph
+            .raycast(origin, direction, max_distance)
+            .map(|ray| RaycastResult {
+                hit: ray.normal.is_some(),
+                point: self.output(ray.point),
+                distance: ray.distance,
+                normal: ray.normal.map(|n| self.output(n)),
+            });
+        Ok(serde_wasm_bindgen::to_value(&result)?)
+    }
+
+    /// Random-walk virtual agents over the mesh as a playability smoke test:
+    /// `{ agents?, steps?, step_length?, seed? }` in, `{ agents, steps,
+    /// playable, falls, stuck, unreachable_targets, mean_distance, incidents }`
+    /// out, with incident positions in `output_space`. `undefined` when the
+    /// mesh has no walkable face.
+    pub fn simulate_agents(&self, options: JsValue) -> Result<JsValue, JsValue> {
+        let options: WalkOptions = if options.is_undefined() || options.is_null() {
+            WalkOptions::default()
+        } else {
+            serde_wasm_bindgen::from_value(options)
+                .map_err(|e| SplatwalkError::InvalidSettings(e.to_string()))?
+        };
+        options
+            .validate()
+            .map_err(SplatwalkError::InvalidSettings)?;
+        let mut report = walk_sim::simulate(&self.graph, self.max_climb, &options);
+        if let (Some(report), Some(t)) = (report.as_mut(), self.transform.as_ref()) {
+            for incident in &mut report.incidents {
+                incident.position = t.apply(incident.position);
+                incident.target = incident.target.map(|p| t.apply(p));
+            }
+        }
+        Ok(serde_wasm_bindgen::to_value(&report)?)
+    }
+}
+
+impl NavMeshQuery {
+    fn build(
+        mesh: QueryMesh,
+        settings: JsValue,
+        stored: serde_json::Value,
+    ) -> Result<NavMeshQuery, JsValue> {
+        let vertex_count = (mesh.vertices.len() / 3) as u32;
+        if mesh.indices.iter().any(|&i| i >= vertex_count) {
+            return Err(SplatwalkError::InvalidSettings(
+                "Invalid mesh: index out of range".to_string(),
+            )
+            .into());
+        }
+        let settings = crate::parse_settings(settings)?;
+        let transform = output_space::transform_for(&settings);
+        let mut vertices = mesh.vertices;
+        if let Some(t) = transform.as_ref() {
+            for v in vertices.chunks_exact_mut(3) {
+                let p = t.unapply([v[0] as f64, v[1] as f64, v[2] as f64]);
+                v.copy_from_slice(&[p[0] as f32, p[1] as f32, p[2] as f32]);
+            }
+        }
+        let limits = AgentLimits::from_settings(&settings);
+        let link = epsilon::resolve(&settings, &[]).weld();
+        Ok(NavMeshQuery {
+            graph: NavGraph::new(&vertices, &mesh.indices, &limits, link),
+            transform,
+            max_climb: limits.max_climb,
+            settings: stored,
+        })
+    }
+
+    fn point(&self, p: &[f64]) -> Result<Vector3<f64>, SplatwalkError> {
+        if p.len() < 3 || p.iter().any(|c| !c.is_finite()) {
+            return Err(SplatwalkError::InvalidSettings(
+                "Query points must be finite [x, y, z] points".to_string(),
+            ));
+        }
+        let p = [p[0], p[1], p[2]];
+        let [x, y, z] = self.transform.as_ref().map_or(p, |t| t.unapply(p));
+        Ok(Vector3::new(x, y, z))
+    }
+
+    fn output(&self, p: Vector3<f64>) -> [f32; 3] {
+        let p = [p.x, p.y, p.z];
+        self.transform
+            .as_ref()
+            .map_or(p, |t| t.apply(p))
+            .map(|c| c as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits() -> AgentLimits {
+        AgentLimits {
+            max_climb: 0.5,
+            max_slope_deg: 40.0,
+            max_step: 0.0,
+        }
+    }
+
+    /// Unit squares at `(x, z, y)`, each with its own four corners, the way
+    /// the voxel floors emit them.
+    fn squares(cells: &[(u32, u32, f32)]) -> (Vec<f32>, Vec<u32>) {
+        let (mut vertices, mut indices) = (Vec::new(), Vec::new());
+        for &(x, z, y) in cells {
+            let base = (vertices.len() / 3) as u32;
+            for (dx, dz) in [(0, 0), (1, 0), (1, 1), (0, 1)] {
+                vertices.extend_from_slice(&[(x + dx) as f32, y, (z + dz) as f32]);
+            }
+            indices.extend([0, 2, 1, 0, 3, 2].iter().map(|i| base + i));
+        }
+        (vertices, indices)
+    }
+
+    #[test]
+    fn paths_go_around_walls_and_up_low_steps() {
+        // A U: two 1x3 arms joined along the bottom row, the right arm one
+        // low step up, and a ledge too high to climb beside the left arm.
+        let (vertices, indices) = squares(&[
+            (0, 0, 0.0),
+            (1, 0, 0.0),
+            (2, 0, 0.2),
+            (0, 1, 0.0),
+            (0, 2, 0.0),
+            (2, 1, 0.2),
+            (2, 2, 0.2),
+            (1, 2, 2.0),
+        ]);
+        let graph = NavGraph::new(&vertices, &indices, &limits(), 1e-3);
+        let (start, end) = (Vector3::new(0.5, 0.0, 2.5), Vector3::new(2.5, 0.2, 2.5));
+        let path = graph.find_path(start, end, false).unwrap();
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&end));
+        // Down the left arm, along the bottom and up the right arm: never
+        // through the gap in the middle of the U.
+        assert!(path.iter().any(|p| p.z < 1.0));
+        assert!(path.iter().all(|p| !(p.x > 1.0 && p.x < 2.0 && p.z > 1.0)));
+
+        // Smoothed, the path cuts straight to the two inner corners of the U.
+        let smooth = graph.find_path(start, end, true).unwrap();
+        let plan: Vec<(f64, f64)> = smooth.iter().map(|p| (p.x, p.z)).collect();
+        assert_eq!(plan, [(0.5, 2.5), (1.0, 1.0), (2.0, 1.0), (2.5, 2.5)]);
+        assert_eq!(smooth.last(), Some(&end));
+
+        // Down a straight arm there is nothing to turn around.
+        let (top, bottom) = (Vector3::new(0.5, 0.0, 2.9), Vector3::new(0.2, 0.0, 0.1));
+        assert_eq!(graph.find_path(top, bottom, true).unwrap(), [top, bottom]);
+
+        // The ledge is out of reach.
+        let ledge = Vector3::new(1.5, 2.0, 2.5);
+        assert!(graph.find_path(start, ledge, true).is_none());
+    }
+
+    #[test]
+    fn rays_stop_at_the_first_boundary() {
+        // An L: a row of three squares, the last one low step up, and a
+        // square above the first with a high ledge beside it.
+        let (vertices, indices) = squares(&[
+            (0, 0, 0.0),
+            (1, 0, 0.0),
+            (2, 0, 0.2),
+            (0, 1, 0.0),
+            (1, 1, 2.0),
+        ]);
+        let graph = NavGraph::new(&vertices, &indices, &limits(), 1e-3);
+        let origin = Vector3::new(0.5, 0.0, 0.5);
+
+        // Along the row and up the step to the far border.
+        let ray = graph.raycast(origin, Vector3::x(), f64::INFINITY).unwrap();
+        assert!((ray.distance - 2.5).abs() < 1e-9);
+        assert!((ray.point - Vector3::new(3.0, 0.2, 0.5)).norm() < 1e-6);
+        assert_eq!(ray.normal, Some(Vector3::x()));
+
+        // The ledge is a wall.
+        let ray = graph
+            .raycast(Vector3::new(0.5, 0.0, 1.5), Vector3::x(), f64::INFINITY)
+            .unwrap();
+        assert!((ray.distance - 0.5).abs() < 1e-9);
+        assert_eq!(ray.normal, Some(Vector3::x()));
+
+        // Diagonally out past the ledge's corner, leaving through the top.
+        let ray = graph
+            .raycast(origin, Vector3::new(0.5, 0.0, 1.0), f64::INFINITY)
+            .unwrap();
+        assert!((ray.point - Vector3::new(1.0, 0.0, 1.5)).norm() < 1e-9);
+        assert_eq!(ray.normal, Some(Vector3::x()));
+
+        // A short ray stays on the mesh; a vertical one goes nowhere.
+        let ray = graph.raycast(origin, -Vector3::z() * 3.0, 0.25).unwrap();
+        assert_eq!(ray.normal, None);
+        assert!((ray.point - Vector3::new(0.5, 0.0, 0.25)).norm() < 1e-9);
+        assert!(graph.raycast(origin, Vector3::y(), 1.0).is_none());
+    }
+}
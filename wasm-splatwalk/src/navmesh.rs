@@ -0,0 +1,844 @@
+//! Recast-style polygon navmesh (`emit_poly_navmesh`).
+//!
+//! The voxel mode's `walkable_floors` mesh is two triangles per floor voxel:
+//! fine for collision, but a path planner wants a few large convex polygons
+//! with known neighbours. This module runs the Recast pipeline on the carved
+//! voxel grid:
+//!
+//! 1. Compact heightfield: one span per walkable voxel top, linked to the
+//!    spans of the four neighbouring columns within `agent_max_climb`.
+//! 2. Watershed regions: a breadth-first distance to the walkable border,
+//!    flooded from its ridges down, level by level. Regions smaller than
+//!    `navmesh_merge_region_area` merge into the neighbour they share most
+//!    border with; isolated ones below `navmesh_min_region_area` are dropped.
+//!    A region whose outline encloses a hole is cut in two through the hole,
+//!    so every region is a simple polygon.
+//! 3. Contours: each region's outline is walked along span edges, then
+//!    simplified. Vertices where the neighbouring region changes are kept, so
+//!    portals line up on both sides; wall runs are Douglas-Peucker simplified
+//!    to `navmesh_max_edge_error` voxels.
+//! 4. Polygons: each contour is ear-clipped and the triangles are merged
+//!    greedily, longest shared edge first, into convex polygons of at most
+//!    `navmesh_max_verts_per_poly` corners.
+//!
+//! Grid coordinates are voxel corners: `x` / `z` columns and `y` floor levels,
+//! all in voxels of `cell_size`.
+
+use std::collections::{HashMap, VecDeque};
+
+use serde::Serialize;
+
+use crate::MeshSettings;
+
+/// Column offsets of the four span directions: -x, +z, +x, -z (Recast order).
+const DX: [i32; 4] = [-1, 0, 1, 0];
+const DZ: [i32; 4] = [0, 1, 0, -1];
+
+/// Walkable span: the floor of a carved column and the first solid voxel
+/// above it, in voxels.
+#[derive(Clone, Copy, Debug)]
+pub struct Span {
+    pub floor: i32,
+    pub ceiling: i32,
+}
+
+/// Walkable spans per column of a `width x depth` voxel grid.
+pub struct SpanField {
+    pub width: usize,
+    pub depth: usize,
+    /// World position of voxel corner (0, 0, 0).
+    pub origin: [f64; 3],
+    pub cell_size: f64,
+    /// Column `z * width + x`, spans bottom first.
+    pub columns: Vec<Vec<Span>>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct NavmeshOptions {
+    /// Largest floor step between linked spans, in voxels.
+    pub max_climb: i32,
+    pub min_region_cells: usize,
+    pub merge_region_cells: usize,
+    /// Wall simplification tolerance, in voxels.
+    pub max_edge_error: f64,
+    pub max_verts_per_poly: usize,
+}
+
+impl NavmeshOptions {
+    pub fn from_settings(settings: &MeshSettings, cell_size: f64) -> Self {
+        let cells = |area: f64| (area / (cell_size * cell_size)).round() as usize;
+        Self {
+            max_climb: (settings.agent_max_climb.unwrap_or(0.5) / cell_size).floor() as i32,
+            min_region_cells: cells(settings.navmesh_min_region_area.unwrap_or(0.5)),
+            merge_region_cells: cells(settings.navmesh_merge_region_area.unwrap_or(2.0)),
+            max_edge_error: settings.navmesh_max_edge_error.unwrap_or(1.3),
+            max_verts_per_poly: settings
+                .navmesh_max_verts_per_poly
+                .unwrap_or(6)
+                .clamp(3, 12),
+        }
+    }
+}
+
+/// Region areas and the edge error must be finite and non-negative.
+pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+    for (name, value) in [
+        ("navmesh_min_region_area", settings.navmesh_min_region_area),
+        (
+            "navmesh_merge_region_area",
+            settings.navmesh_merge_region_area,
+        ),
+        ("navmesh_max_edge_error", settings.navmesh_max_edge_error),
+    ] {
+        if let Some(v) = value {
+            if !(v.is_finite() && v >= 0.0) {
+                return Err(format!("Invalid {}: {}", name, v));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Convex polygons over the walkable floor, with their neighbours.
+#[derive(Serialize)]
+pub struct PolyNavmesh {
+    /// Polygon corners, `x, y, z` per vertex.
+    pub vertices: Vec<f32>,
+    /// Corner indices of every polygon, concatenated. Polygons are wound
+    /// like `mesh` (normal up) and convex.
+    pub polys: Vec<u32>,
+    /// Corners per polygon, `3..=max_verts_per_poly`.
+    pub poly_sizes: Vec<u32>,
+    /// Parallel to `polys`: the polygon across the edge from each corner to
+    /// the next, or -1 on a wall.
+    pub neighbors: Vec<i32>,
+    /// Watershed region of each polygon, from 0.
+    pub poly_regions: Vec<u32>,
+    pub region_count: usize,
+    pub max_verts_per_poly: usize,
+}
+
+/// Build the polygon navmesh of `field`.
+pub fn build(field: &SpanField, options: &NavmeshOptions) -> PolyNavmesh {
+    let spans = Compact::new(field, options.max_climb);
+    let dist = spans.border_distance();
+    let mut reg = spans.watershed(&dist);
+    spans.merge_regions(&mut reg, options);
+    let mut region_count = spans.relabel(&mut reg);
+
+    // Cut regions with holes until every region has a single outline.
+    let mut contours = spans.trace(&reg, region_count);
+    while let Some(hole) = contours.iter().find(|c| area2(&c.points) > 0) {
+        let zmin = hole.points.iter().map(|p| p[2]).min().unwrap_or(0);
+        let zmax = hole.points.iter().map(|p| p[2]).max().unwrap_or(0);
+        let cut = zmin + (zmax - zmin + 1) / 2;
+        let region = hole.region;
+        for (r, z) in reg.iter_mut().zip(&spans.z) {
+            if *r == region && *z >= cut {
+                *r = region_count + 1;
+            }
+        }
+        region_count = spans.relabel(&mut reg);
+        contours = spans.trace(&reg, region_count);
+    }
+
+    let mut out = PolyBuilder::new(field, options.max_climb);
+    for contour in &contours {
+        let simplified = simplify(&contour.points, options.max_edge_error);
+        let ids: Vec<usize> = simplified.iter().map(|p| out.vertex(*p)).collect();
+        let mut ring: Vec<usize> = Vec::with_capacity(ids.len());
+        for id in ids {
+            if ring.last() != Some(&id) && !(ring.first() == Some(&id) && ring.len() > 1) {
+                ring.push(id);
+            }
+        }
+        if ring.len() < 3 {
+            continue;
+        }
+        let triangles = out.triangulate(&ring);
+        let polys = out.merge(triangles, options.max_verts_per_poly);
+        for poly in polys {
+            out.polys.push((poly, contour.region - 1));
+        }
+    }
+    out.finish(region_count, options.max_verts_per_poly)
+}
+
+/// Flattened spans with their neighbour links.
+struct Compact {
+    x: Vec<i32>,
+    z: Vec<i32>,
+    floor: Vec<i32>,
+    conn: Vec<[Option<usize>; 4]>,
+}
+
+struct RawContour {
+    /// Region, from 1.
+    region: u32,
+    /// `[x, y, z, region across the edge ending here]` per corner.
+    points: Vec<[i32; 4]>,
+}
+
+impl Compact {
+    fn new(field: &SpanField, max_climb: i32) -> Self {
+        let mut start = vec![0usize; field.columns.len() + 1];
+        for (c, column) in field.columns.iter().enumerate() {
+            start[c + 1] = start[c] + column.len();
+        }
+        let mut spans = Compact {
+            x: Vec::with_capacity(start[field.columns.len()]),
+            z: Vec::new(),
+            floor: Vec::new(),
+            conn: Vec::new(),
+        };
+        for (c, column) in field.columns.iter().enumerate() {
+            let (x, z) = ((c % field.width) as i32, (c / field.width) as i32);
+            for span in column {
+                let mut links = [None; 4];
+                for (dir, link) in links.iter_mut().enumerate() {
+                    let (nx, nz) = (x + DX[dir], z + DZ[dir]);
+                    if nx < 0 || nz < 0 || nx as usize >= field.width || nz as usize >= field.depth
+                    {
+                        continue;
+                    }
+                    let n = nz as usize * field.width + nx as usize;
+                    *link = field.columns[n]
+                        .iter()
+                        .position(|other| {
+                            (other.floor - span.floor).abs() <= max_climb
+                                && other.ceiling.min(span.ceiling) > other.floor.max(span.floor)
+                        })
+                        .map(|k| start[n] + k);
+                }
+                spans.x.push(x);
+                spans.z.push(z);
+                spans.floor.push(span.floor);
+                spans.conn.push(links);
+            }
+        }
+        spans
+    }
+
+    fn len(&self) -> usize {
+        self.floor.len()
+    }
+
+    /// Steps from each span to the nearest span with a missing link.
+    fn border_distance(&self) -> Vec<u32> {
+        let mut dist = vec![u32::MAX; self.len()];
+        let mut queue = VecDeque::new();
+        for (s, d) in dist.iter_mut().enumerate() {
+            if self.conn[s].iter().any(Option::is_none) {
+                *d = 0;
+                queue.push_back(s);
+            }
+        }
+        while let Some(s) = queue.pop_front() {
+            for n in self.conn[s].iter().flatten() {
+                if dist[*n] == u32::MAX {
+                    dist[*n] = dist[s] + 1;
+                    queue.push_back(*n);
+                }
+            }
+        }
+        dist
+    }
+
+    /// Watershed: process distance levels from the highest down, growing the
+    /// existing regions into each level before seeding new ones in it.
+    fn watershed(&self, dist: &[u32]) -> Vec<u32> {
+        let max = dist.iter().copied().max().unwrap_or(0);
+        let mut levels = vec![Vec::new(); max as usize + 1];
+        for (s, &d) in dist.iter().enumerate() {
+            levels[d as usize].push(s);
+        }
+        let mut reg = vec![0u32; self.len()];
+        let mut next = 1;
+        for level in (0..=max).rev() {
+            let at_level = &levels[level as usize];
+            let mut queue = VecDeque::new();
+            for &s in at_level {
+                if let Some(r) = self.conn[s]
+                    .iter()
+                    .flatten()
+                    .map(|&n| reg[n])
+                    .find(|&r| r != 0)
+                {
+                    reg[s] = r;
+                    queue.push_back(s);
+                }
+            }
+            self.flood(&mut reg, &mut queue, |n| dist[n] >= level);
+            for &s in at_level {
+                if reg[s] == 0 {
+                    reg[s] = next;
+                    next += 1;
+                    self.flood(&mut reg, &mut VecDeque::from([s]), |n| dist[n] >= level);
+                }
+            }
+        }
+        reg
+    }
+
+    /// Spread each queued span's region to unlabelled neighbours passing `open`.
+    fn flood(&self, reg: &mut [u32], queue: &mut VecDeque<usize>, open: impl Fn(usize) -> bool) {
+        while let Some(s) = queue.pop_front() {
+            for &n in self.conn[s].iter().flatten() {
+                if reg[n] == 0 && open(n) {
+                    reg[n] = reg[s];
+                    queue.push_back(n);
+                }
+            }
+        }
+    }
+
+    /// Merge small regions into the neighbour they share most border with,
+    /// smallest first, then drop isolated regions below the minimum size.
+    fn merge_regions(&self, reg: &mut [u32], options: &NavmeshOptions) {
+        let count = reg.iter().copied().max().unwrap_or(0) as usize + 1;
+        let mut size = vec![0usize; count];
+        let mut border: HashMap<(u32, u32), usize> = HashMap::new();
+        for s in 0..self.len() {
+            size[reg[s] as usize] += 1;
+            for &n in self.conn[s].iter().flatten() {
+                if reg[n] != reg[s] {
+                    *border.entry((reg[s], reg[n])).or_default() += 1;
+                }
+            }
+        }
+        let mut parent: Vec<u32> = (0..count as u32).collect();
+        let root = |parent: &[u32], mut r: u32| {
+            while parent[r as usize] != r {
+                r = parent[r as usize];
+            }
+            r
+        };
+        let mut order: Vec<u32> = (1..count as u32).collect();
+        order.sort_by_key(|&r| (size[r as usize], r));
+        for r in order {
+            let r = root(&parent, r);
+            if size[r as usize] >= options.merge_region_cells {
+                continue;
+            }
+            let mut shared: HashMap<u32, usize> = HashMap::new();
+            for (&(a, b), &n) in &border {
+                let (a, b) = (root(&parent, a), root(&parent, b));
+                if a == r && b != r && b != 0 {
+                    *shared.entry(b).or_default() += n;
+                }
+            }
+            if let Some((&target, _)) = shared.iter().max_by_key(|(&t, &n)| (n, u32::MAX - t)) {
+                parent[r as usize] = target;
+                size[target as usize] += size[r as usize];
+            }
+        }
+        let mut linked = vec![false; count];
+        for &(a, b) in border.keys() {
+            let (a, b) = (root(&parent, a), root(&parent, b));
+            if a != b && b != 0 {
+                linked[a as usize] = true;
+            }
+        }
+        for r in reg.iter_mut() {
+            *r = root(&parent, *r);
+            if *r != 0 && size[*r as usize] < options.min_region_cells && !linked[*r as usize] {
+                *r = 0;
+            }
+        }
+    }
+
+    /// Renumber regions 1.. by connected component; returns the count.
+    fn relabel(&self, reg: &mut [u32]) -> u32 {
+        let mut out = vec![0u32; self.len()];
+        let mut next = 0;
+        for s in 0..self.len() {
+            if reg[s] == 0 || out[s] != 0 {
+                continue;
+            }
+            next += 1;
+            out[s] = next;
+            let mut queue = VecDeque::from([s]);
+            while let Some(c) = queue.pop_front() {
+                for &n in self.conn[c].iter().flatten() {
+                    if out[n] == 0 && reg[n] == reg[c] {
+                        out[n] = next;
+                        queue.push_back(n);
+                    }
+                }
+            }
+        }
+        reg.copy_from_slice(&out);
+        next
+    }
+
+    fn trace(&self, reg: &[u32], region_count: u32) -> Vec<RawContour> {
+        let across = |s: usize, dir: usize| self.conn[s][dir].map_or(0, |n| reg[n]);
+        let mut flags: Vec<u8> = (0..self.len())
+            .map(|s| {
+                if reg[s] == 0 {
+                    return 0;
+                }
+                (0..4)
+                    .filter(|&d| across(s, d) != reg[s])
+                    .fold(0, |f, d| f | (1 << d))
+            })
+            .collect();
+        let mut contours = Vec::new();
+        for s0 in 0..self.len() {
+            while flags[s0] != 0 {
+                let d0 = flags[s0].trailing_zeros() as usize;
+                let (mut s, mut d) = (s0, d0);
+                let mut points = Vec::new();
+                for _ in 0..self.len() * 8 + 8 {
+                    if flags[s] & (1 << d) != 0 {
+                        let (cx, cz) = match d {
+                            0 => (0, 1),
+                            1 => (1, 1),
+                            2 => (1, 0),
+                            _ => (0, 0),
+                        };
+                        points.push([
+                            self.x[s] + cx,
+                            self.corner_height(s, d),
+                            self.z[s] + cz,
+                            across(s, d) as i32,
+                        ]);
+                        flags[s] &= !(1 << d);
+                        d = (d + 1) % 4;
+                    } else if let Some(n) = self.conn[s][d] {
+                        s = n;
+                        d = (d + 3) % 4;
+                    } else {
+                        break;
+                    }
+                    if s == s0 && d == d0 {
+                        break;
+                    }
+                }
+                if points.len() >= 3 && reg[s0] <= region_count {
+                    contours.push(RawContour {
+                        region: reg[s0],
+                        points,
+                    });
+                }
+            }
+        }
+        contours
+    }
+
+    /// Highest floor around the corner between `dir` and the next direction.
+    fn corner_height(&self, s: usize, dir: usize) -> i32 {
+        let next = (dir + 1) % 4;
+        let mut h = self.floor[s];
+        for (first, second) in [(dir, next), (next, dir)] {
+            if let Some(a) = self.conn[s][first] {
+                h = h.max(self.floor[a]);
+                if let Some(b) = self.conn[a][second] {
+                    h = h.max(self.floor[b]);
+                }
+            }
+        }
+        h
+    }
+}
+
+/// Twice the signed `[x, z]` area: negative for outlines, positive for holes.
+fn area2(points: &[[i32; 4]]) -> i64 {
+    (0..points.len())
+        .map(|i| {
+            let (a, b) = (points[i], points[(i + 1) % points.len()]);
+            a[0] as i64 * b[2] as i64 - b[0] as i64 * a[2] as i64
+        })
+        .sum()
+}
+
+/// Keep the corners where the neighbouring region changes, then add back
+/// wall corners farther than `max_error` from the simplified outline.
+fn simplify(raw: &[[i32; 4]], max_error: f64) -> Vec<[i32; 4]> {
+    let n = raw.len();
+    // Region across the edge from corner i to corner i + 1.
+    let edge_region = |i: usize| raw[(i + 1) % n][3];
+    let mut keep: Vec<usize> = (0..n)
+        .filter(|&i| edge_region((i + n - 1) % n) != edge_region(i))
+        .collect();
+    if keep.is_empty() {
+        let lowest = (0..n).min_by_key(|&i| (raw[i][0], raw[i][2])).unwrap_or(0);
+        let highest = (0..n).max_by_key(|&i| (raw[i][0], raw[i][2])).unwrap_or(0);
+        keep = vec![lowest.min(highest), lowest.max(highest)];
+        keep.dedup();
+    }
+
+    let mut out = Vec::new();
+    for k in 0..keep.len() {
+        let (a, b) = (keep[k], keep[(k + 1) % keep.len()]);
+        out.push(a);
+        if edge_region(a) == 0 {
+            douglas_peucker(raw, a, if b > a { b } else { b + n }, max_error, &mut out);
+        }
+    }
+    out.iter().map(|&i| raw[i % n]).collect()
+}
+
+/// Append (in order) the corners strictly between `a` and `b` (cyclic
+/// indices, `b` may exceed the length) farther than `max_error` from chord.
+fn douglas_peucker(raw: &[[i32; 4]], a: usize, b: usize, max_error: f64, out: &mut Vec<usize>) {
+    let n = raw.len();
+    let (pa, pb) = (raw[a % n], raw[b % n]);
+    let (dx, dz) = ((pb[0] - pa[0]) as f64, (pb[2] - pa[2]) as f64);
+    let len = dx.hypot(dz);
+    let farthest = (a + 1..b)
+        .map(|i| {
+            let p = raw[i % n];
+            let (px, pz) = ((p[0] - pa[0]) as f64, (p[2] - pa[2]) as f64);
+            let d = if len > 0.0 {
+                (px * dz - pz * dx).abs() / len
+            } else {
+                px.hypot(pz)
+            };
+            (i, d)
+        })
+        .max_by(|x, y| x.1.total_cmp(&y.1).then(y.0.cmp(&x.0)));
+    if let Some((i, d)) = farthest {
+        if d > max_error {
+            douglas_peucker(raw, a, i, max_error, out);
+            out.push(i);
+            douglas_peucker(raw, i, b, max_error, out);
+        }
+    }
+}
+
+struct PolyBuilder {
+    origin: [f64; 3],
+    cell_size: f64,
+    max_climb: i32,
+    /// Grid corners `[x, y, z]` of the shared vertices.
+    vertices: Vec<[i32; 3]>,
+    /// Vertices at each `(x, z)`; corners within the climb share one.
+    by_column: HashMap<(i32, i32), Vec<usize>>,
+    /// Vertex rings and their region (from 0).
+    polys: Vec<(Vec<usize>, u32)>,
+}
+
+impl PolyBuilder {
+    fn new(field: &SpanField, max_climb: i32) -> Self {
+        PolyBuilder {
+            origin: field.origin,
+            cell_size: field.cell_size,
+            max_climb,
+            vertices: Vec::new(),
+            by_column: HashMap::new(),
+            polys: Vec::new(),
+        }
+    }
+
+    fn vertex(&mut self, p: [i32; 4]) -> usize {
+        let column = self.by_column.entry((p[0], p[2])).or_default();
+        if let Some(&v) = column
+            .iter()
+            .find(|&&v| (self.vertices[v][1] - p[1]).abs() <= self.max_climb)
+        {
+            return v;
+        }
+        column.push(self.vertices.len());
+        self.vertices.push([p[0], p[1], p[2]]);
+        self.vertices.len() - 1
+    }
+
+    fn xz(&self, v: usize) -> [i64; 2] {
+        [self.vertices[v][0] as i64, self.vertices[v][2] as i64]
+    }
+
+    /// Twice the signed area of `a b c`; negative turns right in `[x, z]`,
+    /// the convex turn of a normal-up outline.
+    fn cross(&self, a: usize, b: usize, c: usize) -> i64 {
+        let ([ax, az], [bx, bz], [cx, cz]) = (self.xz(a), self.xz(b), self.xz(c));
+        (bx - ax) * (cz - az) - (bz - az) * (cx - ax)
+    }
+
+    /// Ear-clip a normal-up ring, shortest diagonal first.
+    fn triangulate(&self, ring: &[usize]) -> Vec<Vec<usize>> {
+        let mut ring = ring.to_vec();
+        let mut triangles = Vec::new();
+        while ring.len() > 3 {
+            let n = ring.len();
+            let is_ear = |i: usize| {
+                let (a, b, c) = (ring[(i + n - 1) % n], ring[i], ring[(i + 1) % n]);
+                if self.cross(a, b, c) >= 0 {
+                    return false;
+                }
+                ring.iter().all(|&p| {
+                    p == a
+                        || p == b
+                        || p == c
+                        || self.xz(p) == self.xz(a)
+                        || self.xz(p) == self.xz(b)
+                        || self.xz(p) == self.xz(c)
+                        || !(self.cross(a, b, p) <= 0
+                            && self.cross(b, c, p) <= 0
+                            && self.cross(c, a, p) <= 0)
+                })
+            };
+            let diagonal = |i: usize| {
+                let ([ax, az], [cx, cz]) =
+                    (self.xz(ring[(i + n - 1) % n]), self.xz(ring[(i + 1) % n]));
+                (cx - ax).pow(2) + (cz - az).pow(2)
+            };
+            // A self-touching ring can run out of ears; clip the flattest
+            // corner anyway rather than lose the rest of the region.
+            let ear = (0..n)
+                .filter(|&i| is_ear(i))
+                .min_by_key(|&i| (diagonal(i), i))
+                .unwrap_or_else(|| {
+                    (0..n)
+                        .min_by_key(|&i| {
+                            self.cross(ring[(i + n - 1) % n], ring[i], ring[(i + 1) % n])
+                        })
+                        .unwrap_or(0)
+                });
+            let (a, b, c) = (ring[(ear + n - 1) % n], ring[ear], ring[(ear + 1) % n]);
+            if self.cross(a, b, c) < 0 {
+                triangles.push(vec![a, b, c]);
+            }
+            ring.remove(ear);
+        }
+        if ring.len() == 3 && self.cross(ring[0], ring[1], ring[2]) < 0 {
+            triangles.push(ring);
+        }
+        triangles
+    }
+
+    /// Merge polygons pairwise across their longest shared edge while the
+    /// result stays convex and within `max_verts` corners.
+    fn merge(&self, mut polys: Vec<Vec<usize>>, max_verts: usize) -> Vec<Vec<usize>> {
+        loop {
+            let mut best: Option<(i64, usize, usize, usize, usize)> = None;
+            for pa in 0..polys.len() {
+                for pb in pa + 1..polys.len() {
+                    if let Some((value, ea, eb)) =
+                        self.merge_value(&polys[pa], &polys[pb], max_verts)
+                    {
+                        if best.is_none_or(|b| value > b.0) {
+                            best = Some((value, pa, pb, ea, eb));
+                        }
+                    }
+                }
+            }
+            let Some((_, pa, pb, ea, eb)) = best else {
+                return polys;
+            };
+            let (a, b) = (&polys[pa], &polys[pb]);
+            let (na, nb) = (a.len(), b.len());
+            let mut merged: Vec<usize> = (1..=na).map(|k| a[(ea + k) % na]).collect();
+            merged.extend((2..nb).map(|k| b[(eb + k) % nb]));
+            polys[pa] = merged;
+            polys.swap_remove(pb);
+        }
+    }
+
+    /// Squared length of the edge `a` and `b` share, when merging across it
+    /// keeps a convex polygon of at most `max_verts` corners, with the edge's
+    /// index in each.
+    fn merge_value(
+        &self,
+        a: &[usize],
+        b: &[usize],
+        max_verts: usize,
+    ) -> Option<(i64, usize, usize)> {
+        let (na, nb) = (a.len(), b.len());
+        if na + nb - 2 > max_verts {
+            return None;
+        }
+        for ea in 0..na {
+            let (a0, a1) = (a[ea], a[(ea + 1) % na]);
+            let Some(eb) = (0..nb).find(|&j| b[j] == a1 && b[(j + 1) % nb] == a0) else {
+                continue;
+            };
+            // The two corners where the outlines join must still turn right.
+            if self.cross(a[(ea + na - 1) % na], a0, b[(eb + 2) % nb]) > 0
+                || self.cross(b[(eb + nb - 1) % nb], a1, a[(ea + 2) % na]) > 0
+            {
+                return None;
+            }
+            let ([x0, z0], [x1, z1]) = (self.xz(a0), self.xz(a1));
+            return Some(((x1 - x0).pow(2) + (z1 - z0).pow(2), ea, eb));
+        }
+        None
+    }
+
+    fn finish(self, region_count: u32, max_verts_per_poly: usize) -> PolyNavmesh {
+        let mut edges: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+        for (p, (ring, _)) in self.polys.iter().enumerate() {
+            for i in 0..ring.len() {
+                let (a, b) = (ring[i], ring[(i + 1) % ring.len()]);
+                edges.entry((a.min(b), a.max(b))).or_default().push(p);
+            }
+        }
+        let mut navmesh = PolyNavmesh {
+            vertices: self
+                .vertices
+                .iter()
+                .flat_map(|v| {
+                    [
+                        (self.origin[0] + v[0] as f64 * self.cell_size) as f32,
+                        (self.origin[1] + v[1] as f64 * self.cell_size) as f32,
+                        (self.origin[2] + v[2] as f64 * self.cell_size) as f32,
+                    ]
+                })
+                .collect(),
+            polys: Vec::new(),
+            poly_sizes: Vec::new(),
+            neighbors: Vec::new(),
+            poly_regions: Vec::new(),
+            region_count: region_count as usize,
+            max_verts_per_poly,
+        };
+        for (p, (ring, region)) in self.polys.iter().enumerate() {
+            for i in 0..ring.len() {
+                let (a, b) = (ring[i], ring[(i + 1) % ring.len()]);
+                let across = edges[&(a.min(b), a.max(b))]
+                    .iter()
+                    .find(|&&q| q != p)
+                    .map_or(-1, |&q| q as i32);
+                navmesh.polys.push(a as u32);
+                navmesh.neighbors.push(across);
+            }
+            navmesh.poly_sizes.push(ring.len() as u32);
+            navmesh.poly_regions.push(*region);
+        }
+        navmesh
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(width: usize, depth: usize, floor: impl Fn(usize, usize) -> Option<i32>) -> SpanField {
+        SpanField {
+            width,
+            depth,
+            origin: [0.0; 3],
+            cell_size: 1.0,
+            columns: (0..width * depth)
+                .map(|c| {
+                    floor(c % width, c / width)
+                        .map(|floor| Span {
+                            floor,
+                            ceiling: floor + 20,
+                        })
+                        .into_iter()
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+
+    fn options() -> NavmeshOptions {
+        NavmeshOptions {
+            max_climb: 1,
+            min_region_cells: 2,
+            merge_region_cells: 4,
+            max_edge_error: 1.3,
+            max_verts_per_poly: 6,
+        }
+    }
+
+    /// Sum of polygon areas in `[x, z]`, checking each is convex and wound
+    /// normal-up on the way.
+    fn covered_area(navmesh: &PolyNavmesh) -> f64 {
+        let v = |i: u32| {
+            let i = i as usize * 3;
+            (navmesh.vertices[i] as f64, navmesh.vertices[i + 2] as f64)
+        };
+        let mut offset = 0;
+        let mut total = 0.0;
+        for &size in &navmesh.poly_sizes {
+            let ring = &navmesh.polys[offset..offset + size as usize];
+            offset += size as usize;
+            let n = ring.len();
+            for i in 0..n {
+                let ((ax, az), (bx, bz), (cx, cz)) =
+                    (v(ring[i]), v(ring[(i + 1) % n]), v(ring[(i + 2) % n]));
+                assert!((bx - ax) * (cz - az) - (bz - az) * (cx - ax) <= 1e-9);
+            }
+            total -= (0..n)
+                .map(|i| {
+                    let ((ax, az), (bx, bz)) = (v(ring[i]), v(ring[(i + 1) % n]));
+                    ax * bz - bx * az
+                })
+                .sum::<f64>()
+                * 0.5;
+        }
+        total
+    }
+
+    #[test]
+    fn room_with_a_pillar_becomes_convex_polygons_around_it() {
+        let room = field(12, 12, |x, z| {
+            (!((5..7).contains(&x) && (5..7).contains(&z))).then_some(0)
+        });
+        let navmesh = build(&room, &options());
+        assert!((covered_area(&navmesh) - 140.0).abs() < 1e-6);
+        assert!(navmesh.neighbors.iter().any(|&q| q >= 0));
+        assert!(navmesh
+            .poly_sizes
+            .iter()
+            .all(|&n| (3..=6).contains(&(n as usize))));
+
+        // Neighbour links are symmetric.
+        let starts: Vec<usize> = navmesh
+            .poly_sizes
+            .iter()
+            .scan(0, |o, &n| {
+                let s = *o;
+                *o += n as usize;
+                Some(s)
+            })
+            .collect();
+        for (p, &start) in starts.iter().enumerate() {
+            for e in 0..navmesh.poly_sizes[p] as usize {
+                let q = navmesh.neighbors[start + e];
+                if q >= 0 {
+                    let q = q as usize;
+                    let back =
+                        &navmesh.neighbors[starts[q]..starts[q] + navmesh.poly_sizes[q] as usize];
+                    assert!(back.contains(&(p as i32)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn steps_within_the_climb_join_and_cliffs_split() {
+        // Floor 0 for x < 4, floor 1 for 4..8, floor 5 beyond: a step and a cliff.
+        let terraces = field(12, 6, |x, _| {
+            Some(if x < 4 {
+                0
+            } else if x < 8 {
+                1
+            } else {
+                5
+            })
+        });
+        let navmesh = build(&terraces, &options());
+        assert!((covered_area(&navmesh) - 72.0).abs() < 1e-6);
+        let polys_on = |y: f32| {
+            let mut offset = 0;
+            navmesh
+                .poly_sizes
+                .iter()
+                .filter(|&&n| {
+                    let ring = &navmesh.polys[offset..offset + n as usize];
+                    offset += n as usize;
+                    ring.iter()
+                        .all(|&v| navmesh.vertices[v as usize * 3 + 1] == y)
+                })
+                .count()
+        };
+        // The step joins the lower floors into one region; the cliff top is
+        // another, with no link between them.
+        assert_eq!(polys_on(5.0), 1);
+        assert_eq!(navmesh.region_count, 2);
+        assert!(navmesh.neighbors.iter().all(|&q| q < 0));
+    }
+}
@@ -0,0 +1,106 @@
+//! Smooth per-vertex normals for [`MeshBuffers`](crate::MeshBuffers).
+//!
+//! Every mesh the core returns carries `normals`, so a viewer can shade it
+//! smoothly without recomputing them on the JS thread. Each face contributes
+//! its unit normal weighted by the corner angle it subtends at the vertex
+//! (Thürmer and Wüthrich). Unlike area weighting, that does not depend on
+//! how a flat region was triangulated: a long sliver and a fan of small
+//! triangles on the same wall pull equally, so the marching-cubes and
+//! voxel meshes' uneven faces do not tilt their normals.
+//!
+//! Normals follow the winding, like the faces Recast sees. A vertex on no
+//! triangle, or only on degenerate ones, gets +Y.
+
+/// Angle-weighted unit vertex normals, three values per vertex.
+pub fn angle_weighted(positions: &[f32], indices: &[u32]) -> Vec<f32> {
+    let vertex_count = positions.len() / 3;
+    let mut sums = vec![[0.0_f64; 3]; vertex_count];
+    let at = |i: u32| {
+        let i = i as usize * 3;
+        [0, 1, 2].map(|a| positions[i + a] as f64)
+    };
+    let sub = |a: [f64; 3], b: [f64; 3]| [a[0] - b[0], a[1] - b[1], a[2] - b[2]];
+    let dot = |a: [f64; 3], b: [f64; 3]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+    for tri in indices.chunks_exact(3) {
+        if tri.iter().any(|&i| i as usize >= vertex_count) {
+            continue;
+        }
+        let corners = [at(tri[0]), at(tri[1]), at(tri[2])];
+        let (u, v) = (sub(corners[1], corners[0]), sub(corners[2], corners[0]));
+        let n = [
+            u[1] * v[2] - u[2] * v[1],
+            u[2] * v[0] - u[0] * v[2],
+            u[0] * v[1] - u[1] * v[0],
+        ];
+        let len = dot(n, n).sqrt();
+        if !(len > 1e-12 && len.is_finite()) {
+            continue;
+        }
+        for k in 0..3 {
+            let p = corners[k];
+            let (a, b) = (sub(corners[(k + 1) % 3], p), sub(corners[(k + 2) % 3], p));
+            let cos = dot(a, b) / (dot(a, a) * dot(b, b)).sqrt();
+            let angle = cos.clamp(-1.0, 1.0).acos();
+            for axis in 0..3 {
+                sums[tri[k] as usize][axis] += n[axis] / len * angle;
+            }
+        }
+    }
+    let mut out = Vec::with_capacity(vertex_count * 3);
+    for n in sums {
+        let len = dot(n, n).sqrt();
+        if len > 1e-12 && len.is_finite() {
+            out.extend(n.iter().map(|c| (c / len) as f32));
+        } else {
+            out.extend_from_slice(&[0.0, 1.0, 0.0]);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cube_corner_ignores_the_triangulation() {
+        // The corner at the origin of an outward-wound cube is on three
+        // faces. One of them is split into a fan of four triangles around
+        // its centre, which halves the area the corner touches there, so
+        // area weighting would tilt the normal away from -Y. The corner
+        // still sees 90 degrees of each face, so the normal stays diagonal.
+        let positions = [
+            0.0, 0.0, 0.0, // 0: the corner
+            1.0, 0.0, 0.0, // 1
+            0.0, 1.0, 0.0, // 2
+            0.0, 0.0, 1.0, // 3
+            1.0, 0.0, 1.0, // 4
+            0.0, 1.0, 1.0, // 5
+            1.0, 1.0, 0.0, // 6
+            0.5, 0.0, 0.5, // 7: centre of the y = 0 face
+        ];
+        let x_face = [0, 3, 5, 0, 5, 2];
+        let z_face = [0, 2, 6, 0, 6, 1];
+        let y_fan = [0, 1, 7, 1, 4, 7, 4, 3, 7, 3, 0, 7];
+        let indices = [&x_face[..], &z_face, &y_fan].concat();
+        let normals = angle_weighted(&positions, &indices);
+        let third = -(1.0_f32 / 3.0).sqrt();
+        for (got, want) in normals[..3].iter().zip([third; 3]) {
+            assert!((got - want).abs() < 1e-6, "{:?}", &normals[..3]);
+        }
+    }
+
+    #[test]
+    fn loose_and_degenerate_vertices_point_up() {
+        let positions = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 2.0, 0.0, 0.0, 5.0, 5.0, 5.0];
+        let normals = angle_weighted(&positions, &[0, 1, 2]);
+        assert_eq!(normals, [0.0, 1.0, 0.0].repeat(4));
+
+        // A flat quad wound to face +Y.
+        let positions = [0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 0.0, 1.0, 1.0, 0.0, 0.0];
+        let normals = angle_weighted(&positions, &[0, 1, 2, 0, 2, 3]);
+        for n in normals.chunks_exact(3) {
+            assert_eq!(n, [0.0, 1.0, 0.0]);
+        }
+    }
+}
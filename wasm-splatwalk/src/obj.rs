@@ -0,0 +1,239 @@
+//! Wavefront OBJ writer.
+//!
+//! The text counterpart of `glb`: one object of positions and triangles, so
+//! the CLI and web UI can offer a reconstructed navmesh as `.obj` without a
+//! JS-side exporter. Extras are opt-in:
+//!
+//! - `normals`: `vn` lines, referenced from each face corner;
+//! - `uvs`: `vt` lines, likewise;
+//! - `colors`: three floats in `[0, 1]` after each `v` position, the
+//!   per-vertex colour extension MeshLab, Blender and CloudCompare read.
+//!   Readers without it ignore the extra values.
+//!
+//! Every extra is per vertex, so a face corner uses the same index for `v`,
+//! `vt` and `vn`.
+
+use std::fmt::Write;
+
+use serde::Deserialize;
+
+use crate::normals;
+
+/// The mesh fields [`mesh_to_obj`] and `ply_export` read: any result's
+/// `mesh` as returned.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ObjMesh {
+    pub vertices: Vec<f32>,
+    pub indices: Vec<u32>,
+    #[serde(default)]
+    pub normals: Option<Vec<f32>>,
+    #[serde(default)]
+    pub colors: Option<Vec<f32>>,
+    #[serde(default)]
+    pub uvs: Option<Vec<f32>>,
+}
+
+/// Optional OBJ content beyond positions and faces.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ObjOptions {
+    /// Write `vn` normals: the mesh's own, else angle-weighted ones computed
+    /// from the winding.
+    #[serde(default)]
+    pub normals: bool,
+    /// Append the mesh's `colors` to each `v` line.
+    #[serde(default)]
+    pub colors: bool,
+    /// Write the mesh's `uvs` as `vt` lines.
+    #[serde(default)]
+    pub uvs: bool,
+    /// Object name (`o` line).
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+/// Serialize `mesh` as OBJ text with `options`' extras.
+///
+/// Errors on the same malformed input as `mesh_to_glb`, and when an extra is
+/// asked for but the mesh has no matching per-vertex buffer.
+pub fn mesh_to_obj(mesh: &ObjMesh, options: &ObjOptions) -> Result<String, String> {
+    const CALLER: &str = "export_obj";
+    let positions = &mesh.vertices;
+    let indices = &mesh.indices;
+    let vertex_count = check_mesh(CALLER, mesh)?;
+
+    let colors = if options.colors {
+        Some(per_vertex(
+            CALLER,
+            "colors",
+            &mesh.colors,
+            vertex_count * 3,
+        )?)
+    } else {
+        None
+    };
+    let uvs = if options.uvs {
+        Some(per_vertex(CALLER, "uvs", &mesh.uvs, vertex_count * 2)?)
+    } else {
+        None
+    };
+    let computed;
+    let normals = if !options.normals {
+        None
+    } else if mesh.normals.as_ref().is_some_and(|n| !n.is_empty()) {
+        Some(per_vertex(
+            CALLER,
+            "normals",
+            &mesh.normals,
+            vertex_count * 3,
+        )?)
+    } else {
+        computed = normals::angle_weighted(positions, indices);
+        Some(computed.as_slice())
+    };
+
+    let mut obj = String::with_capacity(positions.len() * 12 + indices.len() * 8);
+    obj.push_str("# SplatWalk\n");
+    if let Some(name) = &options.name {
+        let _ = writeln!(obj, "o {}", name);
+    }
+    for (i, p) in positions.chunks_exact(3).enumerate() {
+        let _ = write!(obj, "v {} {} {}", p[0], p[1], p[2]);
+        if let Some(colors) = colors {
+            let c = &colors[i * 3..i * 3 + 3];
+            let _ = write!(obj, " {} {} {}", c[0], c[1], c[2]);
+        }
+        obj.push('\n');
+    }
+    if let Some(uvs) = uvs {
+        for t in uvs.chunks_exact(2) {
+            let _ = writeln!(obj, "vt {} {}", t[0], t[1]);
+        }
+    }
+    if let Some(normals) = normals {
+        for n in normals.chunks_exact(3) {
+            let _ = writeln!(obj, "vn {} {} {}", n[0], n[1], n[2]);
+        }
+    }
+    for face in indices.chunks_exact(3) {
+        obj.push('f');
+        for &i in face {
+            // OBJ indices are 1-based.
+            let i = i + 1;
+            let _ = match (uvs.is_some(), normals.is_some()) {
+                (false, false) => write!(obj, " {}", i),
+                (true, false) => write!(obj, " {}/{}", i, i),
+                (false, true) => write!(obj, " {}//{}", i, i),
+                (true, true) => write!(obj, " {}/{}/{}", i, i, i),
+            };
+        }
+        obj.push('\n');
+    }
+    Ok(obj)
+}
+
+/// The vertex count of `mesh`, or the error `caller` reports for empty,
+/// ragged or out-of-range buffers (the same input `mesh_to_glb` rejects).
+pub(crate) fn check_mesh(caller: &str, mesh: &ObjMesh) -> Result<usize, String> {
+    let positions = &mesh.vertices;
+    let indices = &mesh.indices;
+    if positions.is_empty() || indices.is_empty() {
+        return Err(format!("{}: empty vertices or indices", caller));
+    }
+    if !positions.len().is_multiple_of(3) || !indices.len().is_multiple_of(3) {
+        return Err(format!(
+            "{}: vertices ({}) and indices ({}) must be multiples of 3",
+            caller,
+            positions.len(),
+            indices.len()
+        ));
+    }
+    let vertex_count = positions.len() / 3;
+    if let Some(&i) = indices.iter().find(|&&i| i as usize >= vertex_count) {
+        return Err(format!(
+            "{}: index {} out of range (vertex_count {})",
+            caller, i, vertex_count
+        ));
+    }
+    Ok(vertex_count)
+}
+
+/// `buffer` when it holds `expected` values.
+pub(crate) fn per_vertex<'a>(
+    caller: &str,
+    name: &str,
+    buffer: &'a Option<Vec<f32>>,
+    expected: usize,
+) -> Result<&'a [f32], String> {
+    match buffer {
+        Some(values) if values.len() == expected => Ok(values),
+        Some(values) => Err(format!(
+            "{}: mesh.{} has {} values, expected {}",
+            caller,
+            name,
+            values.len(),
+            expected
+        )),
+        None => Err(format!(
+            "{}: {} requested but mesh has no {}",
+            caller, name, name
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triangle() -> ObjMesh {
+        ObjMesh {
+            vertices: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, -1.0],
+            indices: vec![0, 1, 2],
+            normals: None,
+            colors: Some(vec![1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0]),
+            uvs: Some(vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0]),
+        }
+    }
+
+    #[test]
+    fn extras_are_opt_in() {
+        let plain = mesh_to_obj(&triangle(), &ObjOptions::default()).unwrap();
+        assert_eq!(plain, "# SplatWalk\nv 0 0 0\nv 1 0 0\nv 0 0 -1\nf 1 2 3\n");
+
+        let full = mesh_to_obj(
+            &triangle(),
+            &ObjOptions {
+                normals: true,
+                colors: true,
+                uvs: true,
+                name: Some("navmesh".to_string()),
+            },
+        )
+        .unwrap();
+        let lines: Vec<&str> = full.lines().collect();
+        assert_eq!(lines[1], "o navmesh");
+        assert_eq!(lines[3], "v 1 0 0 0 1 0");
+        assert_eq!(lines[6], "vt 1 0");
+        // Counter-clockwise from above: the computed normal faces +Y.
+        assert_eq!(lines[8], "vn 0 1 0");
+        assert_eq!(lines.last(), Some(&"f 1/1/1 2/2/2 3/3/3"));
+    }
+
+    #[test]
+    fn malformed_meshes_and_missing_extras_are_rejected() {
+        let colors = ObjOptions {
+            colors: true,
+            ..ObjOptions::default()
+        };
+        let mut bare = triangle();
+        bare.colors = None;
+        assert!(mesh_to_obj(&bare, &colors)
+            .unwrap_err()
+            .contains("no colors"));
+        bare.indices = vec![0, 1, 3];
+        assert!(mesh_to_obj(&bare, &ObjOptions::default())
+            .unwrap_err()
+            .contains("out of range"));
+        bare.indices.clear();
+        assert!(mesh_to_obj(&bare, &ObjOptions::default()).is_err());
+    }
+}
@@ -0,0 +1,176 @@
+//! ROS occupancy grid of the mode 2 voxel grid (`emit_occupancy_grid`).
+//!
+//! Robot navigation stacks (ROS `map_server`, Nav2) load a 2D map as a
+//! grayscale PGM and a YAML file giving its resolution and origin. [`build`]
+//! flattens the carved collision grid into such a map, one cell per voxel
+//! column:
+//!
+//! - free where the column holds a walkable floor;
+//! - occupied where it holds none but has splat mass in the band a robot
+//!   would run into: from `agent_max_climb` above the lowest floor (steps and
+//!   door sills stay passable) up to `collision_carve_height` above the
+//!   highest one (ceilings are left out);
+//! - unknown where the scan saw nothing in that band.
+//!
+//! The map is cropped to the free and occupied cells. It is seen from above
+//! in `splatwalk_oriented`: map `x` runs along `+X` and map `y` along `+Z`,
+//! which with `+Y` as the map's up axis is the right-handed frame ROS uses.
+//! Image row 0 is the `max z` edge, as `map_server` reads images top down.
+
+use serde::Serialize;
+
+use crate::navmesh::SpanField;
+
+/// `map_saver`'s trinary pixel values.
+pub const FREE: u8 = 254;
+pub const OCCUPIED: u8 = 0;
+pub const UNKNOWN: u8 = 205;
+
+/// The file name `yaml` points its `image` key at.
+pub const IMAGE_NAME: &str = "occupancy.pgm";
+
+/// A `map_server` map: the PGM and its YAML.
+#[derive(Serialize)]
+pub struct OccupancyGrid {
+    /// Cells per row, along `+X`, and rows, from `max z` down to `min z`.
+    pub width: usize,
+    pub height: usize,
+    /// Metres per cell: the collision voxel size.
+    pub resolution: f64,
+    /// The YAML's `origin`: world `x` and `z` of the lower-left corner of the
+    /// image (last row, first cell), and a yaw of 0.
+    pub origin: [f64; 3],
+    /// Cells of each class.
+    pub free: usize,
+    pub occupied: usize,
+    pub unknown: usize,
+    /// Binary PGM (`P5`): [`FREE`], [`OCCUPIED`] or [`UNKNOWN`] per cell.
+    pub pgm: serde_bytes::ByteBuf,
+    /// `map_server` YAML for `pgm` saved as [`IMAGE_NAME`].
+    pub yaml: String,
+}
+
+/// Flatten `field` into a map, or `None` when it has no walkable floor.
+/// `climb` and `clearance` are in voxels; `observed(x, y, z)` says whether a
+/// voxel holds splat mass, and is false above the grid.
+pub fn build(
+    field: &SpanField,
+    climb: usize,
+    clearance: usize,
+    observed: impl Fn(usize, usize, usize) -> bool,
+) -> Option<OccupancyGrid> {
+    let floors = field
+        .columns
+        .iter()
+        .flatten()
+        .map(|span| span.floor as usize);
+    let lowest = floors.clone().min()?;
+    let highest = floors.max()?;
+    let band = lowest + climb..highest + clearance;
+
+    let cells: Vec<u8> = (0..field.columns.len())
+        .map(|c| {
+            let (x, z) = (c % field.width, c / field.width);
+            if !field.columns[c].is_empty() {
+                FREE
+            } else if band.clone().any(|y| observed(x, y, z)) {
+                OCCUPIED
+            } else {
+                UNKNOWN
+            }
+        })
+        .collect();
+
+    // Crop to the cells the scan says something about.
+    let (mut lo, mut hi) = ([usize::MAX; 2], [0; 2]);
+    for (c, &cell) in cells.iter().enumerate() {
+        if cell != UNKNOWN {
+            let (x, z) = (c % field.width, c / field.width);
+            lo = [lo[0].min(x), lo[1].min(z)];
+            hi = [hi[0].max(x), hi[1].max(z)];
+        }
+    }
+    let width = hi[0] - lo[0] + 1;
+    let height = hi[1] - lo[1] + 1;
+    let mut pixels = Vec::with_capacity(width * height);
+    for z in (lo[1]..=hi[1]).rev() {
+        pixels.extend_from_slice(&cells[z * field.width + lo[0]..=z * field.width + hi[0]]);
+    }
+    let count = |class: u8| pixels.iter().filter(|&&p| p == class).count();
+    let (free, occupied, unknown) = (count(FREE), count(OCCUPIED), count(UNKNOWN));
+
+    let mut pgm = format!("P5\n{} {}\n255\n", width, height).into_bytes();
+    pgm.extend_from_slice(&pixels);
+    let origin = [
+        field.origin[0] + lo[0] as f64 * field.cell_size,
+        field.origin[2] + lo[1] as f64 * field.cell_size,
+        0.0,
+    ];
+    // `free_thresh` just under 205's occupancy (50 / 255), so unknown cells
+    // load as unknown rather than free.
+    let yaml = format!(
+        "image: {}\nmode: trinary\nresolution: {:?}\norigin: [{:?}, {:?}, {:?}]\nnegate: 0\noccupied_thresh: 0.65\nfree_thresh: 0.196\n",
+        IMAGE_NAME, field.cell_size, origin[0], origin[1], origin[2]
+    );
+    Some(OccupancyGrid {
+        width,
+        height,
+        resolution: field.cell_size,
+        origin,
+        free,
+        occupied,
+        unknown,
+        pgm: serde_bytes::ByteBuf::from(pgm),
+        yaml,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::navmesh::Span;
+
+    #[test]
+    fn floors_are_free_walls_occupied_and_the_rest_cropped_or_unknown() {
+        // A 4 x 3 column grid with floor at voxel 2 in columns (1, 0) and
+        // (1, 1). Splat mass: a wall at (2, 1) from voxel 3 up, a sill at
+        // (2, 0) just above the floor, a ceiling over (1, 2) at voxel 9.
+        let mut columns = vec![Vec::new(); 12];
+        columns[1] = vec![Span {
+            floor: 2,
+            ceiling: 9,
+        }];
+        columns[5] = columns[1].clone();
+        let field = SpanField {
+            width: 4,
+            depth: 3,
+            origin: [10.0, 0.0, 20.0],
+            cell_size: 0.5,
+            columns,
+        };
+        let observed = |x, y, z| match (x, z) {
+            (2, 1) => y >= 3,
+            (2, 0) => y == 2,
+            (1, 2) => y == 9,
+            _ => false,
+        };
+        let map = build(&field, 1, 4, observed).unwrap();
+        // Columns 1..=2, rows 0..=1; row 1 (far) comes first.
+        assert_eq!((map.width, map.height), (2, 2));
+        assert_eq!(
+            &map.pgm[map.pgm.len() - 4..],
+            &[FREE, OCCUPIED, FREE, UNKNOWN]
+        );
+        assert_eq!((map.free, map.occupied, map.unknown), (2, 1, 1));
+        assert_eq!(map.origin, [10.5, 20.0, 0.0]);
+        assert!(map.pgm.starts_with(b"P5\n2 2\n255\n"));
+        assert!(map.yaml.contains("origin: [10.5, 20.0, 0.0]"));
+        assert!(map.yaml.starts_with("image: occupancy.pgm\n"));
+
+        let empty = SpanField {
+            columns: vec![Vec::new(); 12],
+            ..field
+        };
+        assert!(build(&empty, 1, 4, observed).is_none());
+    }
+}
@@ -0,0 +1,286 @@
+//! Off-mesh links (`off_mesh_links`).
+//!
+//! A scan shows the ladder, the gap a player jumps and the ledge they drop
+//! from, but the navmesh cannot: none of them is a walkable surface. Authors
+//! list those traversals as links between two points, and [`place`] checks
+//! each end against the output mesh the way Detour does when it loads a
+//! connection: the end must have a walkable face within `radius` across and
+//! `agent_max_climb` vertically. Ends are snapped onto that face, links with
+//! an end off the mesh are dropped, and the kept links are returned with the
+//! faces and `navmesh` polygons they join and written into `detour_navmesh`.
+
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+
+use crate::mesh::ReconstructedMesh;
+use crate::navmesh::PolyNavmesh;
+use crate::reachability::{self, AgentLimits};
+use crate::MeshSettings;
+
+/// Default search radius around each end, in metres.
+pub const DEFAULT_RADIUS: f64 = 0.5;
+
+/// One `off_mesh_links` entry.
+#[derive(Clone, Debug, Deserialize)]
+pub struct OffMeshLinkSettings {
+    /// `[x, y, z]` where the traversal starts.
+    pub start: Vec<f64>,
+    /// `[x, y, z]` where it lands.
+    pub end: Vec<f64>,
+    /// How far across an end may sit from the mesh, in metres (default 0.5).
+    pub radius: Option<f64>,
+    /// Whether agents may also traverse it from `end` to `start` (default
+    /// true).
+    pub bidirectional: Option<bool>,
+}
+
+/// A link whose ends both landed on the mesh.
+#[derive(Clone, Debug, Serialize)]
+pub struct OffMeshLink {
+    /// Position in `off_mesh_links`.
+    pub index: usize,
+    /// The ends, snapped onto `start_face` and `end_face`.
+    pub start: [f64; 3],
+    pub end: [f64; 3],
+    pub radius: f64,
+    pub bidirectional: bool,
+    /// `mesh` faces the ends lie on.
+    pub start_face: u32,
+    pub end_face: u32,
+    /// `navmesh` polygons under the ends, with `emit_poly_navmesh`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_poly: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_poly: Option<u32>,
+}
+
+/// A link left out because one of its ends had no walkable face in reach.
+#[derive(Clone, Debug, Serialize)]
+pub struct DroppedLink {
+    pub index: usize,
+    /// `"start"` or `"end"`.
+    pub end: &'static str,
+}
+
+/// Result `off_mesh_links`.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct OffMeshLinks {
+    pub links: Vec<OffMeshLink>,
+    pub dropped: Vec<DroppedLink>,
+}
+
+/// Every link needs finite `[x, y, z]` ends and a positive `radius`.
+pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+    for (i, link) in settings.off_mesh_links.iter().flatten().enumerate() {
+        for (name, point) in [("start", &link.start), ("end", &link.end)] {
+            if point.len() != 3 || point.iter().any(|c| !c.is_finite()) {
+                return Err(format!(
+                    "off_mesh_links[{}].{} must be a finite [x, y, z] point",
+                    i, name
+                ));
+            }
+        }
+        if let Some(radius) = link.radius {
+            if !(radius.is_finite() && radius > 0.0) {
+                return Err(format!("Invalid off_mesh_links[{}].radius: {}", i, radius));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Snap the ends of `requested` onto the walkable faces of `mesh`, and find
+/// the `navmesh` polygons under them.
+pub fn place(
+    mesh: &ReconstructedMesh,
+    navmesh: Option<&PolyNavmesh>,
+    requested: &[OffMeshLinkSettings],
+    limits: &AgentLimits,
+) -> OffMeshLinks {
+    let positions: Vec<Vector3<f64>> = mesh
+        .vertices
+        .chunks_exact(3)
+        .map(|v| Vector3::new(v[0] as f64, v[1] as f64, v[2] as f64))
+        .collect();
+    let faces: Vec<[usize; 3]> = mesh
+        .indices
+        .chunks_exact(3)
+        .map(|f| [f[0] as usize, f[1] as usize, f[2] as usize])
+        .collect();
+    let walkable = reachability::walkable_faces(&positions, &faces, limits);
+
+    // Detour's search box around an end: `radius` across, the climb up and
+    // down.
+    let snap = |p: Vector3<f64>, radius: f64| {
+        faces
+            .iter()
+            .enumerate()
+            .filter(|&(fi, _)| walkable[fi])
+            .map(|(fi, f)| {
+                let [a, b, c] = f.map(|i| positions[i]);
+                (fi, closest_on_triangle(p, a, b, c))
+            })
+            .filter(|(_, q)| {
+                (q.x - p.x).abs() <= radius
+                    && (q.z - p.z).abs() <= radius
+                    && (q.y - p.y).abs() <= limits.max_climb
+            })
+            .min_by(|a, b| (a.1 - p).norm().total_cmp(&(b.1 - p).norm()))
+    };
+
+    let mut placed = OffMeshLinks::default();
+    for (index, link) in requested.iter().enumerate() {
+        let radius = link.radius.unwrap_or(DEFAULT_RADIUS);
+        let point = |c: &[f64]| Vector3::new(c[0], c[1], c[2]);
+        let Some((start_face, start)) = snap(point(&link.start), radius) else {
+            placed.dropped.push(DroppedLink {
+                index,
+                end: "start",
+            });
+            continue;
+        };
+        let Some((end_face, end)) = snap(point(&link.end), radius) else {
+            placed.dropped.push(DroppedLink { index, end: "end" });
+            continue;
+        };
+        placed.links.push(OffMeshLink {
+            index,
+            start: [start.x, start.y, start.z],
+            end: [end.x, end.y, end.z],
+            radius,
+            bidirectional: link.bidirectional.unwrap_or(true),
+            start_face: start_face as u32,
+            end_face: end_face as u32,
+            start_poly: navmesh.and_then(|n| poly_under(n, start, limits.max_climb)),
+            end_poly: navmesh.and_then(|n| poly_under(n, end, limits.max_climb)),
+        });
+    }
+    placed
+}
+
+/// The point of triangle `abc` nearest `p` (Ericson, Real-Time Collision
+/// Detection 5.1.5).
+fn closest_on_triangle(
+    p: Vector3<f64>,
+    a: Vector3<f64>,
+    b: Vector3<f64>,
+    c: Vector3<f64>,
+) -> Vector3<f64> {
+    let (ab, ac, ap) = (b - a, c - a, p - a);
+    let (d1, d2) = (ab.dot(&ap), ac.dot(&ap));
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return a;
+    }
+    let bp = p - b;
+    let (d3, d4) = (ab.dot(&bp), ac.dot(&bp));
+    if d3 >= 0.0 && d4 <= d3 {
+        return b;
+    }
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+    let cp = p - c;
+    let (d5, d6) = (ab.dot(&cp), ac.dot(&cp));
+    if d6 >= 0.0 && d5 <= d6 {
+        return c;
+    }
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && d4 - d3 >= 0.0 && d5 - d6 >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+    let denom = 1.0 / (va + vb + vc);
+    a + ab * (vb * denom) + ac * (vc * denom)
+}
+
+/// The polygon whose plan view holds `p`, nearest in height and within
+/// `max_climb` of it.
+fn poly_under(navmesh: &PolyNavmesh, p: Vector3<f64>, max_climb: f64) -> Option<u32> {
+    let vertex = |i: u32| {
+        let i = i as usize * 3;
+        let v = &navmesh.vertices[i..i + 3];
+        Vector3::new(v[0] as f64, v[1] as f64, v[2] as f64)
+    };
+    let mut best = None;
+    let mut start = 0;
+    for (pi, &size) in navmesh.poly_sizes.iter().enumerate() {
+        let corners: Vec<Vector3<f64>> = navmesh.polys[start..start + size as usize]
+            .iter()
+            .map(|&i| vertex(i))
+            .collect();
+        start += size as usize;
+        // Convex and wound one way, so p is inside when it is on the same
+        // side of every edge.
+        let sides: Vec<f64> = (0..corners.len())
+            .map(|k| {
+                let (u, v) = (corners[k], corners[(k + 1) % corners.len()]);
+                (v.x - u.x) * (p.z - u.z) - (p.x - u.x) * (v.z - u.z)
+            })
+            .collect();
+        let inside = sides.iter().all(|&s| s >= -1e-6) || sides.iter().all(|&s| s <= 1e-6);
+        if !inside {
+            continue;
+        }
+        let height = corners.iter().map(|c| c.y).sum::<f64>() / corners.len() as f64;
+        let gap = (height - p.y).abs();
+        if gap <= max_climb && best.is_none_or(|(_, g)| gap < g) {
+            best = Some((pi as u32, gap));
+        }
+    }
+    best.map(|(pi, _)| pi)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 2 x 2 m floor at `y` from `x0`, as two triangles wound up.
+    fn floor(mesh: &mut ReconstructedMesh, x0: f32, y: f32) {
+        let base = (mesh.vertices.len() / 3) as u32;
+        for (x, z) in [(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)] {
+            mesh.vertices.extend_from_slice(&[x0 + x, y, z]);
+        }
+        mesh.indices
+            .extend([0, 2, 1, 0, 3, 2].iter().map(|i| base + i));
+    }
+
+    #[test]
+    fn ends_snap_onto_the_floors_and_off_mesh_ends_drop() {
+        // A floor and a ledge 3 m up across a 1 m gap.
+        let mut mesh = ReconstructedMesh {
+            vertices: vec![],
+            indices: vec![],
+        };
+        floor(&mut mesh, 0.0, 0.0);
+        floor(&mut mesh, 3.0, 3.0);
+        let limits = AgentLimits {
+            max_climb: 0.5,
+            max_slope_deg: 40.0,
+            max_step: 0.0,
+        };
+        let link = |start: [f64; 3], end: [f64; 3]| OffMeshLinkSettings {
+            start: start.to_vec(),
+            end: end.to_vec(),
+            radius: None,
+            bidirectional: Some(false),
+        };
+        let requested = [
+            link([2.3, 0.2, 1.0], [3.2, 3.0, 1.0]),
+            link([1.0, 0.0, 1.0], [5.0, 1.5, 1.0]),
+        ];
+        let placed = place(&mesh, None, &requested, &limits);
+        assert_eq!(placed.links.len(), 1);
+        let kept = &placed.links[0];
+        assert_eq!(kept.index, 0);
+        assert!((kept.start[0] - 2.0).abs() < 1e-9 && kept.start[1].abs() < 1e-9);
+        assert_eq!(kept.end, [3.2, 3.0, 1.0]);
+        assert!(kept.start_face < 2 && kept.end_face >= 2);
+        assert!(!kept.bidirectional);
+        assert_eq!(placed.dropped.len(), 1);
+        assert_eq!((placed.dropped[0].index, placed.dropped[0].end), (1, "end"));
+    }
+}
@@ -9,12 +9,24 @@
 //! identical to the legacy output.
 //!
 //! Only geometric outputs are converted: mesh vertices (with a winding flip when
-//! the basis is mirrored), `FieldBasis` vectors, `FloorPlane` normals, and the
-//! top-level oriented bounds / region corners. Per-cell ground-field scalars and
-//! the `diagnostics` bag stay in `splatwalk_oriented` space.
+//! the basis is mirrored), `FieldBasis` vectors, `FloorPlane` normals, the
+//! ground frame's `to_splat` matrix, and the top-level oriented bounds / region
+//! corners. Per-cell ground-field scalars and the `diagnostics` bag stay in
+//! `splatwalk_oriented` space.
+//!
+//! `MeshSettings.coordinate_system` names a host convention instead
+//! (`"babylon-lh"`, `"threejs-rh"`, `"gltf"`, `"z-up"`). A preset also sets the
+//! `flip_y` default, brings Z-up input into `+Y` up on ingest, and makes the
+//! region filter compare in the host's space, so one word replaces the
+//! `flip_y` / `output_space` / region bookkeeping a host would otherwise get
+//! right by hand.
 
 use serde::Deserialize;
 
+use crate::flow_field::FlowField;
+use crate::frame::{self, GroundFrame};
+use crate::navmesh::PolyNavmesh;
+use crate::streaming::TilePatches;
 use crate::{
     CollisionVoxelBoundaryResult, CoordinateSpace, FieldBasis, FloorPlane, MeshBuffers,
     MeshSettings, NavmeshBasisResult, ReconstructionResult, SplatBounds, SuggestedRegion,
@@ -37,12 +49,22 @@ pub struct OutputSpaceSettings {
 }
 
 const IDENTITY: [[f64; 3]; 3] = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+// +Y-up -> +Z-up while preserving right-handedness (rotation of -90 deg about X):
+// (x, y, z) -> (x, -z, y).
+const Y_UP_TO_Z_UP: [[f64; 3]; 3] = [[1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]];
+// Right -> left handedness mirrors a single axis (Z), giving determinant -1.
+const MIRROR_Z: [[f64; 3]; 3] = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, -1.0]];
+
+/// Host conventions accepted by `coordinate_system`.
+pub const COORDINATE_SYSTEMS: &[&str] = &["babylon-lh", "threejs-rh", "gltf", "z-up"];
 
 /// Resolved linear map from `splatwalk_oriented` to the requested convention plus
 /// the winding decision and the reported space metadata.
+#[derive(Clone, Debug)]
 pub struct OutputTransform {
     matrix: [[f64; 3]; 3],
     flip_winding: bool,
+    space: &'static str,
     up_axis: String,
     handedness: String,
 }
@@ -71,19 +93,8 @@ fn resolve(s: &OutputSpaceSettings) -> OutputTransform {
         .unwrap_or("right")
         .to_ascii_lowercase();
 
-    // +Y-up -> +Z-up while preserving right-handedness (rotation of -90 deg about X):
-    // (x, y, z) -> (x, -z, y).
-    let m_up = if up == "z" {
-        [[1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]]
-    } else {
-        IDENTITY
-    };
-    // Right -> left handedness mirrors a single axis (Z), giving determinant -1.
-    let m_hand = if hand == "left" {
-        [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, -1.0]]
-    } else {
-        IDENTITY
-    };
+    let m_up = if up == "z" { Y_UP_TO_Z_UP } else { IDENTITY };
+    let m_hand = if hand == "left" { MIRROR_Z } else { IDENTITY };
 
     let matrix = mat_mul(&m_hand, &m_up);
     let mirrored = det3(&matrix) < 0.0;
@@ -101,6 +112,7 @@ fn resolve(s: &OutputSpaceSettings) -> OutputTransform {
     OutputTransform {
         matrix,
         flip_winding,
+        space: "engine_output",
         up_axis: if up == "z" {
             "z".to_string()
         } else {
@@ -117,7 +129,7 @@ fn resolve(s: &OutputSpaceSettings) -> OutputTransform {
 impl OutputTransform {
     /// Apply the linear map to a point or direction (the map fixes the origin, so
     /// the same routine is correct for both).
-    fn apply(&self, v: [f64; 3]) -> [f64; 3] {
+    pub fn apply(&self, v: [f64; 3]) -> [f64; 3] {
         let m = &self.matrix;
         [
             m[0][0] * v[0] + m[0][1] * v[1] + m[0][2] * v[2],
@@ -126,24 +138,115 @@ impl OutputTransform {
         ]
     }
 
+    /// Map a point from the output convention back to `splatwalk_oriented`. The
+    /// map is orthogonal, so its inverse is the transpose.
+    pub fn unapply(&self, v: [f64; 3]) -> [f64; 3] {
+        let m = &self.matrix;
+        [
+            m[0][0] * v[0] + m[1][0] * v[1] + m[2][0] * v[2],
+            m[0][1] * v[0] + m[1][1] * v[1] + m[2][1] * v[2],
+            m[0][2] * v[0] + m[1][2] * v[1] + m[2][2] * v[2],
+        ]
+    }
+
+    /// Whether the map reverses triangle winding.
+    pub fn flips_winding(&self) -> bool {
+        self.flip_winding
+    }
+
     /// Space metadata describing the requested output convention.
     pub fn coordinate_space(&self) -> CoordinateSpace {
         CoordinateSpace {
-            space: "engine_output".to_string(),
+            space: self.space.to_string(),
             up_axis: self.up_axis.clone(),
             handedness: self.handedness.clone(),
         }
     }
 }
 
+/// The map from `splatwalk_oriented` into a `coordinate_system` preset.
+///
+/// - `babylon-lh`: the Y-flipped splat in a left-handed scene already renders
+///   `splatwalk_oriented` unmirrored, with counter-clockwise faces showing as
+///   Babylon's clockwise front faces, so the numbers pass through unchanged.
+/// - `threejs-rh`: a splat stood upright by a 180-degree turn about X has its
+///   Z negated relative to the Y-flipped parse, so Z is mirrored (and winding
+///   flipped) back into three.js world space.
+/// - `gltf`: `splatwalk_oriented` is glTF's own convention.
+/// - `z-up`: right-handed `+Z` up, as `output_space.up_axis: "z"`.
+pub fn preset(name: &str) -> OutputTransform {
+    let (space, matrix, up_axis, handedness) = match name {
+        "babylon-lh" => ("babylon-lh", IDENTITY, "y", "left"),
+        "threejs-rh" => ("threejs-rh", MIRROR_Z, "y", "right"),
+        "z-up" => ("z-up", Y_UP_TO_Z_UP, "z", "right"),
+        _ => ("gltf", IDENTITY, "y", "right"),
+    };
+    OutputTransform {
+        matrix,
+        flip_winding: det3(&matrix) < 0.0,
+        space,
+        up_axis: up_axis.to_string(),
+        handedness: handedness.to_string(),
+    }
+}
+
+/// `coordinate_system` must name a known preset and replaces `output_space`
+/// rather than combining with it.
+pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+    let Some(name) = settings.coordinate_system.as_deref() else {
+        return Ok(());
+    };
+    if !COORDINATE_SYSTEMS.contains(&name) {
+        return Err(format!(
+            "Unknown coordinate_system \"{}\" (expected one of {})",
+            name,
+            COORDINATE_SYSTEMS.join(", ")
+        ));
+    }
+    if settings.output_space.is_some() {
+        return Err("coordinate_system and output_space cannot both be set".to_string());
+    }
+    Ok(())
+}
+
 /// Build the output transform for a settings object, or `None` when no conversion
 /// was requested (the default, byte-identical path).
 pub fn transform_for(settings: &MeshSettings) -> Option<OutputTransform> {
-    settings.output_space.as_ref().map(resolve)
+    match settings.coordinate_system.as_deref() {
+        Some(name) => Some(preset(name)),
+        None => settings.output_space.as_ref().map(resolve),
+    }
 }
 
-/// Transform mesh vertices in place and flip triangle winding when the basis is
-/// mirrored.
+/// Whether ingest negates Y: `flip_y` when given, otherwise the preset's
+/// default (true for the Babylon and three.js presets, whose splat loaders
+/// import Y-down).
+pub fn flip_y(settings: &MeshSettings) -> bool {
+    settings.flip_y.unwrap_or(matches!(
+        settings.coordinate_system.as_deref(),
+        Some("babylon-lh" | "threejs-rh")
+    ))
+}
+
+/// The preset whose splats arrive in a non-`+Y`-up basis, which ingest maps
+/// back into `splatwalk_oriented` with [`OutputTransform::unapply`].
+pub fn ingest_transform(settings: &MeshSettings) -> Option<OutputTransform> {
+    settings
+        .coordinate_system
+        .as_deref()
+        .filter(|&name| name == "z-up")
+        .map(preset)
+}
+
+/// The space `region_*`, `crop_transform` and `regions` are written in: the
+/// preset's, or `splatwalk_oriented` (`None`) without one.
+pub fn region_frame(settings: &MeshSettings) -> Option<OutputTransform> {
+    settings.coordinate_system.as_deref().map(preset)
+}
+
+/// Transform mesh vertices and normals in place and flip triangle winding when
+/// the basis is mirrored. The map is orthogonal, so normals rotate like
+/// positions, and the flipped winding keeps them on the front side.
 pub fn apply_mesh_buffers(t: &OutputTransform, mesh: &mut MeshBuffers) {
     let vertex_count = mesh.vertices.len() / 3;
     for i in 0..vertex_count {
@@ -157,6 +260,10 @@ pub fn apply_mesh_buffers(t: &OutputTransform, mesh: &mut MeshBuffers) {
         mesh.vertices[base + 1] = o[1] as f32;
         mesh.vertices[base + 2] = o[2] as f32;
     }
+    for n in mesh.normals.chunks_exact_mut(3) {
+        let o = t.apply([n[0] as f64, n[1] as f64, n[2] as f64]);
+        n.copy_from_slice(&[o[0] as f32, o[1] as f32, o[2] as f32]);
+    }
 
     if t.flip_winding {
         let mut i = 0;
@@ -167,6 +274,36 @@ pub fn apply_mesh_buffers(t: &OutputTransform, mesh: &mut MeshBuffers) {
     }
 }
 
+/// Transform polygon navmesh vertices in place and, when the basis is
+/// mirrored, reverse each polygon along with its edge neighbours.
+pub fn apply_poly_navmesh(t: &OutputTransform, navmesh: &mut PolyNavmesh) {
+    for v in navmesh.vertices.chunks_exact_mut(3) {
+        let o = t.apply([v[0] as f64, v[1] as f64, v[2] as f64]);
+        v.copy_from_slice(&[o[0] as f32, o[1] as f32, o[2] as f32]);
+    }
+
+    if t.flip_winding {
+        let mut start = 0;
+        for &size in &navmesh.poly_sizes {
+            let end = start + size as usize;
+            navmesh.polys[start..end].reverse();
+            // Edge i ran from corner i to i + 1; reversed, it ends at corner i.
+            navmesh.neighbors[start..end].reverse();
+            navmesh.neighbors[start..end].rotate_left(1);
+            start = end;
+        }
+    }
+}
+
+/// Rotate flow-field directions in place. Face order is unchanged by a
+/// winding flip, so nothing else moves.
+pub fn apply_flow_field(t: &OutputTransform, field: &mut FlowField) {
+    for d in field.directions.chunks_exact_mut(3) {
+        let o = t.apply([d[0] as f64, d[1] as f64, d[2] as f64]);
+        d.copy_from_slice(&[o[0] as f32, o[1] as f32, o[2] as f32]);
+    }
+}
+
 /// Transform a field basis (origin + the three direction vectors) in place.
 pub fn apply_basis(t: &OutputTransform, basis: &mut FieldBasis) {
     basis.origin = t.apply(basis.origin);
@@ -181,6 +318,20 @@ pub fn apply_floor_plane(t: &OutputTransform, plane: &mut FloorPlane) {
     plane.normal = t.apply(plane.normal);
 }
 
+/// Transform a ground frame in place. `to_splat` now starts from output
+/// space, so it undoes `t` first.
+pub fn apply_ground_frame(t: &OutputTransform, ground: &mut GroundFrame) {
+    if let Some(plane) = ground.floor_plane.as_mut() {
+        apply_floor_plane(t, plane);
+    }
+    if let Some(basis) = ground.basis.as_mut() {
+        apply_basis(t, basis);
+    }
+    let to_splat = ground.to_splat;
+    ground.to_splat = frame::linear_matrix(|v| frame::transform(&to_splat, t.unapply(v)));
+    ground.from_splat = frame::invert(&ground.to_splat);
+}
+
 fn elementwise_minmax(a: [f64; 3], b: [f64; 3]) -> ([f64; 3], [f64; 3]) {
     let mut lo = [0.0; 3];
     let mut hi = [0.0; 3];
@@ -194,6 +345,32 @@ fn elementwise_minmax(a: [f64; 3], b: [f64; 3]) -> ([f64; 3], [f64; 3]) {
 pub fn apply_reconstruction(settings: &MeshSettings, result: &mut ReconstructionResult) {
     if let Some(t) = transform_for(settings) {
         apply_mesh_buffers(&t, &mut result.mesh);
+        for plane in result.planes.iter_mut().flatten() {
+            apply_mesh_buffers(&t, &mut plane.mesh);
+            apply_floor_plane(&t, &mut plane.plane);
+        }
+        if let Some(set) = result.unreachable.as_mut() {
+            apply_mesh_buffers(&t, &mut set.mesh);
+        }
+        if let Some(navmesh) = result.navmesh.as_mut() {
+            apply_poly_navmesh(&t, navmesh);
+        }
+        for layer in result.floor_layers.iter_mut().flat_map(|l| &mut l.layers) {
+            apply_mesh_buffers(&t, &mut layer.mesh);
+        }
+        if let Some(field) = result.flow_field.as_mut() {
+            apply_flow_field(&t, field);
+        }
+        for link in result.off_mesh_links.iter_mut().flat_map(|l| &mut l.links) {
+            link.start = t.apply(link.start);
+            link.end = t.apply(link.end);
+        }
+        // The tile layout (`origin`, `tile_size`) stays in splatwalk_oriented.
+        for tile in result.tiles.iter_mut().flat_map(|index| &mut index.tiles) {
+            apply_mesh_buffers(&t, &mut tile.mesh);
+            (tile.min, tile.max) = elementwise_minmax(t.apply(tile.min), t.apply(tile.max));
+        }
+        apply_ground_frame(&t, &mut result.frame);
         result.space = t.coordinate_space();
     }
 }
@@ -248,3 +425,85 @@ pub fn apply_region(settings: &MeshSettings, result: &mut SuggestedRegion) {
         result.space = t.coordinate_space();
     }
 }
+
+/// Patches and their regions are converted; `tile_size` and the anchored
+/// column / row stay in `splatwalk_oriented`, as for `tiles`.
+pub fn apply_tile_patches(settings: &MeshSettings, result: &mut TilePatches) {
+    if let Some(t) = transform_for(settings) {
+        for region in &mut result.regions {
+            (region.min, region.max) = elementwise_minmax(t.apply(region.min), t.apply(region.max));
+        }
+        for patch in &mut result.patches {
+            apply_mesh_buffers(&t, &mut patch.mesh);
+            (patch.min, patch.max) = elementwise_minmax(t.apply(patch.min), t.apply(patch.max));
+        }
+        result.space = t.coordinate_space();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(json: serde_json::Value) -> MeshSettings {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn coordinate_systems_resolve_to_presets() {
+        let three = settings(serde_json::json!({ "mode": 0, "coordinate_system": "threejs-rh" }));
+        let t = transform_for(&three).unwrap();
+        assert_eq!(t.apply([1.0, 2.0, 3.0]), [1.0, 2.0, -3.0]);
+        assert!(t.flip_winding);
+        assert_eq!(t.coordinate_space().space, "threejs-rh");
+        assert!(flip_y(&three) && ingest_transform(&three).is_none());
+
+        let babylon = settings(serde_json::json!({
+            "mode": 0,
+            "coordinate_system": "babylon-lh",
+            "flip_y": false,
+        }));
+        let t = transform_for(&babylon).unwrap();
+        assert_eq!(t.apply([1.0, 2.0, 3.0]), [1.0, 2.0, 3.0]);
+        assert!(!t.flip_winding && !flip_y(&babylon));
+        assert_eq!(t.coordinate_space().handedness, "left");
+
+        let z_up = settings(serde_json::json!({ "mode": 0, "coordinate_system": "z-up" }));
+        let t = ingest_transform(&z_up).unwrap();
+        // Z-up input stands up along +Y, and output goes back to Z-up.
+        assert_eq!(t.unapply([0.0, 0.0, 1.0]), [0.0, 1.0, 0.0]);
+        assert_eq!(t.apply([0.0, 1.0, 0.0]), [0.0, 0.0, 1.0]);
+        assert!(!flip_y(&z_up) && !t.flip_winding);
+
+        assert!(validate(&settings(serde_json::json!({ "mode": 0 }))).is_ok());
+        assert!(validate(&settings(
+            serde_json::json!({ "mode": 0, "coordinate_system": "unity" })
+        ))
+        .is_err());
+        assert!(validate(&settings(serde_json::json!({
+            "mode": 0,
+            "coordinate_system": "gltf",
+            "output_space": { "up_axis": "z" },
+        })))
+        .is_err());
+    }
+
+    #[test]
+    fn ground_frame_maps_output_back_to_the_file() {
+        let three = settings(serde_json::json!({ "mode": 0, "coordinate_system": "threejs-rh" }));
+        let t = transform_for(&three).unwrap();
+        let floor = FloorPlane {
+            normal: [0.0, 1.0, 0.0],
+            d: -1.0,
+        };
+        let mut ground = GroundFrame::new(&three, Some(floor), None);
+        apply_ground_frame(&t, &mut ground);
+        // threejs-rh flips Y on ingest and Z on output.
+        let file_point = [1.0, 2.0, 3.0];
+        let output = t.apply([1.0, -2.0, 3.0]);
+        assert_eq!(output, [1.0, -2.0, -3.0]);
+        assert_eq!(frame::transform(&ground.to_splat, output), file_point);
+        assert_eq!(frame::transform(&ground.from_splat, file_point), output);
+        assert_eq!(ground.basis.unwrap().bitangent, [0.0, 0.0, -1.0]);
+    }
+}
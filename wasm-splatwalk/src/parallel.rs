@@ -0,0 +1,51 @@
+//! Data-parallel loops over point sets.
+//!
+//! With the `threads` feature these fan out with rayon: natively on its
+//! default pool, in the browser once [`thread_support::start`] has spawned
+//! the worker pool. Everywhere else, and in the browser before the pool is
+//! up, they run in order on the calling thread. The split is fixed by
+//! [`CHUNK`], not by the thread count, so a bake gives the same result
+//! either way, down to the rounding of float sums.
+
+#[cfg(feature = "threads")]
+use rayon::prelude::*;
+
+/// Items per unit of work.
+pub const CHUNK: usize = 4096;
+
+/// Whether the loops here run on a pool: natively always, in the browser
+/// once it is up.
+#[cfg(feature = "threads")]
+fn pooled() -> bool {
+    cfg!(not(target_arch = "wasm32")) || crate::thread_support::pool_threads() > 1
+}
+
+/// `f` of each [`CHUNK`]-item run of `items`, in order. Callers combine the
+/// results in that order.
+pub fn chunks<T: Sync, A: Send>(items: &[T], f: impl Fn(&[T]) -> A + Sync + Send) -> Vec<A> {
+    #[cfg(feature = "threads")]
+    if pooled() {
+        return items.par_chunks(CHUNK).map(f).collect();
+    }
+    items.chunks(CHUNK).map(f).collect()
+}
+
+/// Items of `items` that pass `keep`.
+pub fn count<T: Sync>(items: &[T], keep: impl Fn(&T) -> bool + Sync + Send) -> usize {
+    chunks(items, |run| run.iter().filter(|item| keep(item)).count())
+        .into_iter()
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunked_results_stay_in_order() {
+        let items: Vec<usize> = (0..CHUNK * 3 + 7).collect();
+        let firsts = chunks(&items, |run| run[0]);
+        assert_eq!(firsts, vec![0, CHUNK, CHUNK * 2, CHUNK * 3]);
+        assert_eq!(count(&items, |&i| i % 2 == 0), (CHUNK * 3 + 8) / 2);
+    }
+}
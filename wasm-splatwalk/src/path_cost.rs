@@ -0,0 +1,166 @@
+//! Per-cell path cost multipliers for the room floor.
+//!
+//! Every floor cell carries a traversal cost multiplier (1 = normal floor).
+//! Costs are derived from ground-field attributes at bake time
+//! (`path_cost_from`) or painted later in a `FloorEditor`, and are exported
+//! per triangle next to the mesh so A* over the floor (or the Detour navmesh
+//! baked from it) steers around expensive areas.
+//!
+//! Detour has no per-triangle costs, only up to 64 area ids, each with one
+//! cost in the query filter. [`PathCosts`] therefore also quantizes the costs
+//! into area ids: area 0 is the default cost 1 (the area the floor-sheet bake
+//! already gives walkable polygons) and areas 1..=62 hold the painted levels,
+//! exact when there are at most 62 distinct costs and log-spaced otherwise.
+//! Area 63 is left alone because it is Recast's `RC_WALKABLE_AREA`.
+
+use serde::Serialize;
+
+use crate::{GroundFieldCell, MeshSettings};
+
+/// Detour area ids available for non-default costs (1..=62).
+const MAX_COST_AREAS: usize = 62;
+
+/// Per-triangle path costs in index-buffer order, plus their Detour areas.
+#[derive(Clone, Serialize)]
+pub struct PathCosts {
+    /// Cost multiplier per triangle.
+    pub face_costs: Vec<f32>,
+    /// Detour area id per triangle; index into `area_costs`.
+    pub face_areas: Vec<u8>,
+    /// Query-filter cost per area id, starting with area 0 at cost 1.
+    pub area_costs: Vec<f32>,
+}
+
+impl PathCosts {
+    /// Quantize `face_costs` (finite, > 0) into Detour areas.
+    pub fn from_face_costs(face_costs: Vec<f32>) -> Self {
+        let mut levels: Vec<f32> = face_costs.iter().copied().filter(|&c| c != 1.0).collect();
+        levels.sort_by(f32::total_cmp);
+        levels.dedup();
+        if levels.len() > MAX_COST_AREAS {
+            let lo = levels[0].ln();
+            let hi = levels[levels.len() - 1].ln();
+            levels = (0..MAX_COST_AREAS)
+                .map(|k| (lo + (hi - lo) * k as f32 / (MAX_COST_AREAS - 1) as f32).exp())
+                .collect();
+        }
+        let face_areas = face_costs
+            .iter()
+            .map(|&cost| {
+                if cost == 1.0 {
+                    return 0;
+                }
+                // Nearest level in log space; exact matches land on their own level.
+                let above = levels.partition_point(|&l| l < cost).min(levels.len() - 1);
+                let below = above.saturating_sub(1);
+                let nearest = if (cost.ln() - levels[below].ln()).abs()
+                    <= (levels[above].ln() - cost.ln()).abs()
+                {
+                    below
+                } else {
+                    above
+                };
+                nearest as u8 + 1
+            })
+            .collect();
+        let mut area_costs = vec![1.0];
+        area_costs.extend(levels);
+        Self {
+            face_costs,
+            face_areas,
+            area_costs,
+        }
+    }
+}
+
+/// Cell attribute a bake derives path costs from.
+#[derive(Clone, Copy, PartialEq)]
+pub enum CostSource {
+    Uniform,
+    /// Low floor confidence costs more.
+    Confidence,
+    /// Poor normal alignment (slopes, rough ground) costs more.
+    Slope,
+}
+
+impl CostSource {
+    pub fn from_settings(settings: &MeshSettings) -> Self {
+        match settings.path_cost_from.as_deref() {
+            Some("confidence") => CostSource::Confidence,
+            Some("slope") => CostSource::Slope,
+            _ => CostSource::Uniform,
+        }
+    }
+
+    pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+        let known = ["uniform", "confidence", "slope"];
+        if let Some(other) = settings
+            .path_cost_from
+            .as_deref()
+            .filter(|s| !known.contains(s))
+        {
+            return Err(format!(
+                "Invalid path_cost_from: {}. Expected \"uniform\", \"confidence\", or \"slope\".",
+                other
+            ));
+        }
+        match settings.path_cost_scale {
+            Some(scale) if !(scale.is_finite() && scale >= 0.0) => {
+                Err(format!("Invalid path_cost_scale: {}", scale))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Cost of `cell`: `1 + scale * (1 - attribute)`, with the attribute
+    /// clamped to `[0, 1]`.
+    pub fn cell_cost(self, cell: &GroundFieldCell, scale: f64) -> f32 {
+        let attribute = match self {
+            CostSource::Uniform => return 1.0,
+            CostSource::Confidence => cell.confidence,
+            CostSource::Slope => cell.normal_alignment,
+        };
+        let attribute = if attribute.is_finite() {
+            attribute.clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        (1.0 + scale * (1.0 - attribute as f64)) as f32
+    }
+}
+
+/// `path_cost_scale` (default 4): the cost of a cell whose attribute is 0.
+pub fn cost_scale(settings: &MeshSettings) -> f64 {
+    settings
+        .path_cost_scale
+        .filter(|s| s.is_finite() && *s >= 0.0)
+        .unwrap_or(4.0)
+}
+
+/// Whether `cost` is a usable multiplier.
+pub fn valid_cost(cost: f64) -> bool {
+    cost.is_finite() && cost > 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantizes_costs_into_detour_areas() {
+        let costs = PathCosts::from_face_costs(vec![1.0, 3.0, 1.0, 0.5, 3.0]);
+        assert_eq!(costs.face_areas, vec![0, 2, 0, 1, 2]);
+        assert_eq!(costs.area_costs, vec![1.0, 0.5, 3.0]);
+
+        // More distinct costs than areas: log-spaced levels spanning the range.
+        let many: Vec<f32> = (0..200).map(|i| 2.0 + i as f32 * 0.5).collect();
+        let costs = PathCosts::from_face_costs(many.clone());
+        assert_eq!(costs.area_costs.len(), MAX_COST_AREAS + 1);
+        assert!((costs.area_costs[1] - 2.0).abs() < 1e-4);
+        assert!((costs.area_costs[MAX_COST_AREAS] - many[199]).abs() < 1e-3);
+        for (&cost, &area) in many.iter().zip(&costs.face_areas) {
+            let level = costs.area_costs[area as usize];
+            assert!((level / cost).ln().abs() < 0.05, "{} -> {}", cost, level);
+        }
+    }
+}
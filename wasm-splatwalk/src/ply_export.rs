@@ -0,0 +1,212 @@
+//! Stanford PLY mesh writer.
+//!
+//! The core reads splat PLYs but had no way to write a mesh back out, so a
+//! bake could only be inspected in MeshLab or CloudCompare through a GLB or
+//! OBJ detour. [`mesh_to_ply`] writes one `vertex` and one `face` element
+//! in either encoding the format defines for this:
+//!
+//! - `binary_little_endian` (default): compact, and what splat tooling
+//!   writes;
+//! - `ascii`: readable in a text editor and diff-friendly.
+//!
+//! Normals (`nx ny nz` floats) and colours (`red green blue` bytes, the
+//! names MeshLab and CloudCompare map to vertex colour) are opt-in, as for
+//! `export_obj`. Faces are `vertex_indices` lists with a `uchar` count and
+//! `uint` indices.
+
+use std::fmt::Write;
+
+use serde::Deserialize;
+
+use crate::normals;
+use crate::obj::{self, ObjMesh};
+
+/// Encodings `format` accepts.
+pub const FORMATS: &[&str] = &["binary_little_endian", "ascii"];
+
+/// Optional PLY content beyond positions and faces.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct PlyOptions {
+    /// `"binary_little_endian"` (default) or `"ascii"`.
+    #[serde(default)]
+    pub format: Option<String>,
+    /// Write `nx ny nz`: the mesh's own normals, else angle-weighted ones
+    /// computed from the winding.
+    #[serde(default)]
+    pub normals: bool,
+    /// Write the mesh's `colors` as `red green blue` bytes.
+    #[serde(default)]
+    pub colors: bool,
+}
+
+/// Serialize `mesh` as PLY bytes with `options`' encoding and extras.
+///
+/// Errors on the same malformed input as `export_obj`, on an unknown
+/// `format`, and when an extra is asked for but the mesh has no matching
+/// per-vertex buffer.
+pub fn mesh_to_ply(mesh: &ObjMesh, options: &PlyOptions) -> Result<Vec<u8>, String> {
+    const CALLER: &str = "export_ply";
+    let format = options.format.as_deref().unwrap_or(FORMATS[0]);
+    if !FORMATS.contains(&format) {
+        return Err(format!(
+            "{}: unknown format \"{}\" (expected one of {})",
+            CALLER,
+            format,
+            FORMATS.join(", ")
+        ));
+    }
+    let binary = format != "ascii";
+    let positions = &mesh.vertices;
+    let indices = &mesh.indices;
+    let vertex_count = obj::check_mesh(CALLER, mesh)?;
+
+    let colors = if options.colors {
+        let colors = obj::per_vertex(CALLER, "colors", &mesh.colors, vertex_count * 3)?;
+        Some(
+            colors
+                .iter()
+                .map(|&c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+                .collect::<Vec<u8>>(),
+        )
+    } else {
+        None
+    };
+    let computed;
+    let normals = if !options.normals {
+        None
+    } else if mesh.normals.as_ref().is_some_and(|n| !n.is_empty()) {
+        Some(obj::per_vertex(
+            CALLER,
+            "normals",
+            &mesh.normals,
+            vertex_count * 3,
+        )?)
+    } else {
+        computed = normals::angle_weighted(positions, indices);
+        Some(computed.as_slice())
+    };
+
+    let mut header = String::new();
+    let _ = writeln!(header, "ply\nformat {} 1.0\ncomment SplatWalk", format);
+    let _ = writeln!(header, "element vertex {}", vertex_count);
+    header.push_str("property float x\nproperty float y\nproperty float z\n");
+    if normals.is_some() {
+        header.push_str("property float nx\nproperty float ny\nproperty float nz\n");
+    }
+    if colors.is_some() {
+        header.push_str("property uchar red\nproperty uchar green\nproperty uchar blue\n");
+    }
+    let _ = writeln!(header, "element face {}", indices.len() / 3);
+    header.push_str("property list uchar uint vertex_indices\nend_header\n");
+
+    let mut out = header.into_bytes();
+    if binary {
+        let per_vertex = 12 + normals.map_or(0, |_| 12) + colors.as_ref().map_or(0, |_| 3);
+        out.reserve(vertex_count * per_vertex + indices.len() / 3 * 13);
+        for i in 0..vertex_count {
+            for &v in &positions[i * 3..i * 3 + 3] {
+                out.extend_from_slice(&v.to_le_bytes());
+            }
+            if let Some(normals) = normals {
+                for &n in &normals[i * 3..i * 3 + 3] {
+                    out.extend_from_slice(&n.to_le_bytes());
+                }
+            }
+            if let Some(colors) = &colors {
+                out.extend_from_slice(&colors[i * 3..i * 3 + 3]);
+            }
+        }
+        for face in indices.chunks_exact(3) {
+            out.push(3);
+            for &i in face {
+                out.extend_from_slice(&i.to_le_bytes());
+            }
+        }
+    } else {
+        let mut body = String::with_capacity(vertex_count * 24 + indices.len() * 8);
+        for i in 0..vertex_count {
+            let p = &positions[i * 3..i * 3 + 3];
+            let _ = write!(body, "{} {} {}", p[0], p[1], p[2]);
+            if let Some(normals) = normals {
+                let n = &normals[i * 3..i * 3 + 3];
+                let _ = write!(body, " {} {} {}", n[0], n[1], n[2]);
+            }
+            if let Some(colors) = &colors {
+                let c = &colors[i * 3..i * 3 + 3];
+                let _ = write!(body, " {} {} {}", c[0], c[1], c[2]);
+            }
+            body.push('\n');
+        }
+        for face in indices.chunks_exact(3) {
+            let _ = writeln!(body, "3 {} {} {}", face[0], face[1], face[2]);
+        }
+        out.extend_from_slice(body.as_bytes());
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triangle() -> ObjMesh {
+        ObjMesh {
+            vertices: vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, -1.0],
+            indices: vec![0, 1, 2],
+            normals: None,
+            colors: Some(vec![1.0, 0.0, 0.0, 0.0, 0.5, 0.0, 0.0, 0.0, 1.0]),
+            uvs: None,
+        }
+    }
+
+    #[test]
+    fn ascii_lists_vertices_then_faces() {
+        let options = PlyOptions {
+            format: Some("ascii".to_string()),
+            normals: true,
+            colors: true,
+        };
+        let ply = String::from_utf8(mesh_to_ply(&triangle(), &options).unwrap()).unwrap();
+        let (header, body) = ply.split_once("end_header\n").unwrap();
+        assert!(header.starts_with("ply\nformat ascii 1.0\n"));
+        assert!(header.contains("element vertex 3\n") && header.contains("element face 1\n"));
+        assert!(header.contains("property float nx\n") && header.contains("property uchar red\n"));
+        let lines: Vec<&str> = body.lines().collect();
+        // Counter-clockwise from above: the computed normal faces +Y.
+        assert_eq!(lines[1], "1 0 0 0 1 0 0 128 0");
+        assert_eq!(lines[3], "3 0 1 2");
+    }
+
+    #[test]
+    fn binary_is_little_endian_and_sized_by_the_header() {
+        let ply = mesh_to_ply(&triangle(), &PlyOptions::default()).unwrap();
+        let header_end = ply.windows(11).position(|w| w == b"end_header\n").unwrap() + 11;
+        let header = std::str::from_utf8(&ply[..header_end]).unwrap();
+        assert!(header.contains("format binary_little_endian 1.0\n"));
+        assert!(!header.contains("nx") && !header.contains("red"));
+        // Three vertices of three floats, then one face: count byte and
+        // three u32 indices.
+        let body = &ply[header_end..];
+        assert_eq!(body.len(), 3 * 12 + 13);
+        assert_eq!(&body[12..16], &1.0f32.to_le_bytes());
+        assert_eq!(body[36], 3);
+        assert_eq!(&body[45..49], &2u32.to_le_bytes());
+
+        let unknown = PlyOptions {
+            format: Some("binary_big_endian".to_string()),
+            ..PlyOptions::default()
+        };
+        assert!(mesh_to_ply(&triangle(), &unknown)
+            .unwrap_err()
+            .contains("unknown format"));
+        let mut bare = triangle();
+        bare.colors = None;
+        let colors = PlyOptions {
+            colors: true,
+            ..PlyOptions::default()
+        };
+        assert!(mesh_to_ply(&bare, &colors)
+            .unwrap_err()
+            .contains("no colors"));
+    }
+}
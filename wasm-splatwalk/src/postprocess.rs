@@ -0,0 +1,912 @@
+//! Mesh post-processing after reconstruction.
+//!
+//! Poisson and the voxel modes can emit hundreds of thousands of triangles,
+//! far more than a runtime navmesh or collider needs. [`decimate`] reduces a
+//! mesh to a target face count by quadric edge collapse (Garland & Heckbert):
+//! every vertex accumulates the planes of its original faces, and the edge
+//! whose merged vertex strays least from those planes collapses first, so flat
+//! floors shrink to a few triangles while steps and walls keep their shape.
+//!
+//! The voxel navmesh has the opposite problem: every floor cell sits on a
+//! voxel top, so gentle ramps come out as one-voxel stair steps that make a
+//! walking character bob. [`smooth_heights`] relaxes those steps with an
+//! iterative Laplacian pass before anything else reads the mesh.
+//!
+//! [`weld`] merges vertices that sit within the bake's tolerance of each
+//! other (see [`crate::epsilon`]), so per-cell meshers that emit shared
+//! corners twice come out as one connected surface.
+//!
+//! `post_process` runs these and a few more ([`skirt`], [`clip`],
+//! `transfer_colors`) as an ordered chain of named steps on the output mesh
+//! ([`run_chain`]), so a host that needs a different order or a second pass
+//! doesn't have to redo them in JS on the returned buffers.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use nalgebra::{Matrix3, Point3, Vector3};
+use serde::Deserialize;
+
+use crate::epsilon::Tolerance;
+use crate::mesh::ReconstructedMesh;
+use crate::reachability;
+use crate::region::{RegionRuleSettings, RegionVolume};
+use crate::MeshSettings;
+
+/// Step names `post_process` accepts.
+const CHAIN_STEPS: [&str; 6] = [
+    "weld",
+    "smooth",
+    "decimate",
+    "skirt",
+    "clip",
+    "transfer_colors",
+];
+
+/// Default `skirt` depth in metres.
+const DEFAULT_SKIRT_DEPTH: f64 = 0.1;
+
+/// One `post_process` step. `step` names it; the other fields are its
+/// parameters, and a step ignores the ones it doesn't take.
+#[derive(Deserialize, Clone, Default)]
+pub struct PostProcessStep {
+    pub step: String,
+    /// `weld`: merge distance in metres (default: the bake tolerance).
+    pub distance: Option<f64>,
+    /// `smooth`: passes (default 1), `lambda` in `(0, 1]` (default 0.5) and
+    /// the stacked-corner link in metres (default 0, edges only).
+    pub iterations: Option<usize>,
+    pub lambda: Option<f64>,
+    pub step_link: Option<f64>,
+    /// `decimate`: required face budget, and the optional error bound.
+    pub target_faces: Option<usize>,
+    pub max_error: Option<f64>,
+    /// `skirt`: how far below each open edge the skirt hangs, in metres
+    /// (default 0.1).
+    pub depth: Option<f64>,
+    /// `clip`: a `regions`-style rule; `include` keeps the faces inside it,
+    /// `exclude` drops them.
+    pub region: Option<RegionRuleSettings>,
+    /// `transfer_colors`: splat color sample radius in metres (default:
+    /// `color_sample_radius` or its default).
+    pub radius: Option<f64>,
+}
+
+/// Weight of the perpendicular planes that hold open boundary edges in place,
+/// relative to the unit-weight face planes.
+const BOUNDARY_WEIGHT: f64 = 10.0;
+
+/// `decimate_target_faces` must be at least 1, `decimate_max_error` a
+/// non-negative finite length and `smooth_lambda` in `(0, 1]` when set.
+pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+    if settings.decimate_target_faces == Some(0) {
+        return Err("Invalid decimate_target_faces: 0. Expected at least 1.".to_string());
+    }
+    if let Some(error) = settings.decimate_max_error {
+        if !(error.is_finite() && error >= 0.0) {
+            return Err(format!("Invalid decimate_max_error: {}", error));
+        }
+    }
+    if let Some(lambda) = settings.smooth_lambda.filter(|l| !(*l > 0.0 && *l <= 1.0)) {
+        return Err(format!(
+            "Invalid smooth_lambda: {}. Expected a value in (0, 1].",
+            lambda
+        ));
+    }
+    for (i, step) in settings
+        .post_process
+        .as_deref()
+        .unwrap_or_default()
+        .iter()
+        .enumerate()
+    {
+        validate_step(step).map_err(|e| format!("post_process[{}]: {}", i, e))?;
+    }
+    Ok(())
+}
+
+fn validate_step(step: &PostProcessStep) -> Result<(), String> {
+    if !CHAIN_STEPS.contains(&step.step.as_str()) {
+        return Err(format!(
+            "unknown step {:?}. Expected one of {}.",
+            step.step,
+            CHAIN_STEPS.join(", ")
+        ));
+    }
+    let lengths = [
+        ("distance", step.distance),
+        ("step_link", step.step_link.filter(|l| *l != 0.0)),
+        ("max_error", step.max_error.filter(|e| *e != 0.0)),
+        ("depth", step.depth),
+        ("radius", step.radius),
+    ];
+    for (name, value) in lengths {
+        if let Some(v) = value.filter(|v| !(v.is_finite() && *v > 0.0)) {
+            return Err(format!("invalid {}: {}", name, v));
+        }
+    }
+    if let Some(lambda) = step.lambda.filter(|l| !(*l > 0.0 && *l <= 1.0)) {
+        return Err(format!(
+            "invalid lambda: {}. Expected a value in (0, 1].",
+            lambda
+        ));
+    }
+    match step.step.as_str() {
+        "decimate" if step.target_faces.unwrap_or(0) == 0 => {
+            Err("decimate needs target_faces of at least 1".to_string())
+        }
+        "clip" if step.region.as_ref().and_then(|r| r.resolve()).is_none() => {
+            Err("clip needs a valid region rule".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The output of a `post_process` chain.
+pub struct Chained {
+    pub mesh: ReconstructedMesh,
+    /// Sample radius of the last `transfer_colors` step, if any ran. Colors
+    /// depend only on vertex positions, so they are sampled once on the final
+    /// mesh rather than carried through the later steps.
+    pub color_radius: Option<f64>,
+    /// Faces left after each step.
+    pub faces: Vec<usize>,
+}
+
+/// Run `steps` in order on `mesh`. `tolerance` supplies the default weld
+/// distance and the degenerate-triangle bound, `color_radius` the default
+/// `transfer_colors` radius. Steps are validated by [`validate`].
+pub fn run_chain(
+    mesh: &ReconstructedMesh,
+    steps: &[PostProcessStep],
+    tolerance: Tolerance,
+    color_radius: f64,
+) -> Chained {
+    let mut mesh = ReconstructedMesh {
+        vertices: mesh.vertices.clone(),
+        indices: mesh.indices.clone(),
+    };
+    let mut colors = None;
+    let mut faces = Vec::with_capacity(steps.len());
+    for step in steps {
+        match step.step.as_str() {
+            "weld" => {
+                let distance = step.distance.unwrap_or_else(|| tolerance.weld());
+                mesh = weld(&mesh, distance, tolerance.degenerate_area());
+            }
+            "smooth" => smooth_heights(
+                &mut mesh,
+                step.iterations.unwrap_or(1),
+                step.lambda.unwrap_or(0.5),
+                step.step_link.unwrap_or(0.0),
+            ),
+            "decimate" => {
+                let target = step.target_faces.unwrap_or(usize::MAX);
+                if mesh.indices.len() / 3 > target {
+                    let max_error = step.max_error.unwrap_or(f64::INFINITY);
+                    mesh = decimate(&mesh, target, max_error);
+                }
+            }
+            "skirt" => mesh = skirt(&mesh, step.depth.unwrap_or(DEFAULT_SKIRT_DEPTH)),
+            "clip" => {
+                if let Some((include, volume)) = step.region.as_ref().and_then(|r| r.resolve()) {
+                    mesh = clip(&mesh, include, &volume);
+                }
+            }
+            _ => colors = Some(step.radius.unwrap_or(color_radius)),
+        }
+        faces.push(mesh.indices.len() / 3);
+    }
+    Chained {
+        mesh,
+        color_radius: colors,
+        faces,
+    }
+}
+
+/// `mesh` with a vertical skirt hanging `depth` below every open edge, wound
+/// to match the face it borders, so the seam between the navmesh and a render
+/// or collision mesh under it shows no cracks.
+pub fn skirt(mesh: &ReconstructedMesh, depth: f64) -> ReconstructedMesh {
+    let count = mesh.vertices.len() / 3;
+    let faces: Vec<[u32; 3]> = mesh
+        .indices
+        .chunks_exact(3)
+        .map(|f| [f[0], f[1], f[2]])
+        .filter(|f| f.iter().all(|&i| (i as usize) < count))
+        .collect();
+    let mut uses: HashMap<(u32, u32), usize> = HashMap::new();
+    for f in &faces {
+        for k in 0..3 {
+            *uses.entry(edge_key(f[k], f[(k + 1) % 3])).or_default() += 1;
+        }
+    }
+    let mut out = ReconstructedMesh {
+        vertices: mesh.vertices.clone(),
+        indices: faces.iter().flatten().copied().collect(),
+    };
+    let mut low: HashMap<u32, u32> = HashMap::new();
+    let mut lowered = |v: u32, out: &mut ReconstructedMesh| {
+        *low.entry(v).or_insert_with(|| {
+            let base = v as usize * 3;
+            let [x, y, z] = [base, base + 1, base + 2].map(|i| out.vertices[i]);
+            out.vertices
+                .extend_from_slice(&[x, (y as f64 - depth) as f32, z]);
+            (out.vertices.len() / 3 - 1) as u32
+        })
+    };
+    for f in &faces {
+        for k in 0..3 {
+            let (a, b) = (f[k], f[(k + 1) % 3]);
+            if uses[&edge_key(a, b)] == 1 {
+                // The skirt walks the edge the other way, as a neighbour would.
+                let (a_low, b_low) = (lowered(a, &mut out), lowered(b, &mut out));
+                out.indices
+                    .extend_from_slice(&[b, a, a_low, b, a_low, b_low]);
+            }
+        }
+    }
+    out
+}
+
+/// The faces of `mesh` whose centre is inside `volume` (`include`) or
+/// outside it.
+pub fn clip(mesh: &ReconstructedMesh, include: bool, volume: &RegionVolume) -> ReconstructedMesh {
+    let positions: Vec<Vector3<f64>> = mesh
+        .vertices
+        .chunks_exact(3)
+        .map(|v| Vector3::new(v[0] as f64, v[1] as f64, v[2] as f64))
+        .collect();
+    let faces: Vec<[usize; 3]> = mesh
+        .indices
+        .chunks_exact(3)
+        .map(|f| [f[0] as usize, f[1] as usize, f[2] as usize])
+        .filter(|f| f.iter().all(|&i| i < positions.len()))
+        .collect();
+    let keep: Vec<usize> = (0..faces.len())
+        .filter(|&fi| {
+            let [a, b, c] = faces[fi].map(|v| positions[v]);
+            volume.contains(&Point3::from((a + b + c) / 3.0)) == include
+        })
+        .collect();
+    reachability::submesh(&positions, &faces, &keep)
+}
+
+/// Symmetric 4x4 error quadric, upper triangle row by row.
+#[derive(Clone, Copy, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    /// Squared distance to the plane `n . p + d = 0` (`n` unit), times `weight`.
+    fn plane(n: Vector3<f64>, d: f64, weight: f64) -> Self {
+        let (a, b, c) = (n.x, n.y, n.z);
+        Quadric(
+            [
+                a * a,
+                a * b,
+                a * c,
+                a * d,
+                b * b,
+                b * c,
+                b * d,
+                c * c,
+                c * d,
+                d * d,
+            ]
+            .map(|v| v * weight),
+        )
+    }
+
+    fn sum(&self, other: &Quadric) -> Quadric {
+        let mut out = *self;
+        for (a, b) in out.0.iter_mut().zip(other.0) {
+            *a += b;
+        }
+        out
+    }
+
+    fn error(&self, p: &Vector3<f64>) -> f64 {
+        let q = &self.0;
+        let (x, y, z) = (p.x, p.y, p.z);
+        let e = q[0] * x * x
+            + 2.0 * q[1] * x * y
+            + 2.0 * q[2] * x * z
+            + 2.0 * q[3] * x
+            + q[4] * y * y
+            + 2.0 * q[5] * y * z
+            + 2.0 * q[6] * y
+            + q[7] * z * z
+            + 2.0 * q[8] * z
+            + q[9];
+        e.max(0.0)
+    }
+
+    /// The point of least error, when the planes pin one down.
+    fn minimizer(&self) -> Option<Vector3<f64>> {
+        let q = &self.0;
+        let a = Matrix3::new(q[0], q[1], q[2], q[1], q[4], q[5], q[2], q[5], q[7]);
+        if a.determinant().abs() < 1e-9 {
+            return None;
+        }
+        a.try_inverse()
+            .map(|inv| inv * Vector3::new(-q[3], -q[6], -q[8]))
+    }
+}
+
+/// An edge collapse waiting in the queue. `stamps` are the endpoint versions
+/// when it was scored; a newer version means the entry is stale.
+struct Candidate {
+    cost: f64,
+    v0: u32,
+    v1: u32,
+    stamps: [u32; 2],
+    position: Vector3<f64>,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    // Reversed so `BinaryHeap` pops the cheapest collapse; ties break on the
+    // vertex ids so the result doesn't depend on hash order.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .total_cmp(&self.cost)
+            .then_with(|| (other.v0, other.v1).cmp(&(self.v0, self.v1)))
+    }
+}
+
+struct Decimator {
+    positions: Vec<Vector3<f64>>,
+    quadrics: Vec<Quadric>,
+    stamps: Vec<u32>,
+    faces: Vec<[u32; 3]>,
+    face_alive: Vec<bool>,
+    vertex_faces: Vec<Vec<u32>>,
+}
+
+impl Decimator {
+    fn candidate(&self, v0: u32, v1: u32) -> Candidate {
+        let (a, b) = (v0 as usize, v1 as usize);
+        let q = self.quadrics[a].sum(&self.quadrics[b]);
+        let (pa, pb) = (self.positions[a], self.positions[b]);
+        let mid = (pa + pb) * 0.5;
+        let reach = (pb - pa).norm() * 2.0;
+        // An ill-conditioned solve can land far off the surface; keep it near
+        // the edge or fall back to the endpoints and midpoint.
+        let optimal = q.minimizer().filter(|p| (p - mid).norm() <= reach);
+        let (position, cost) = optimal
+            .into_iter()
+            .chain([pa, pb, mid])
+            .map(|p| (p, q.error(&p)))
+            .min_by(|x, y| x.1.total_cmp(&y.1))
+            .unwrap_or((mid, 0.0));
+        Candidate {
+            cost,
+            v0,
+            v1,
+            stamps: [self.stamps[a], self.stamps[b]],
+            position,
+        }
+    }
+
+    /// Distinct vertices sharing a live face with `v`.
+    fn neighbors(&self, v: usize) -> Vec<u32> {
+        let mut out: Vec<u32> = self.vertex_faces[v]
+            .iter()
+            .filter(|&&fi| self.face_alive[fi as usize])
+            .flat_map(|&fi| self.faces[fi as usize])
+            .filter(|&u| u as usize != v)
+            .collect();
+        out.sort_unstable();
+        out.dedup();
+        out
+    }
+
+    /// Whether merging `b` into `a` at `position` keeps the surface manifold
+    /// and no surviving triangle flips or collapses to a sliver.
+    fn can_collapse(&self, a: usize, b: usize, position: &Vector3<f64>) -> bool {
+        let (a32, b32) = (a as u32, b as u32);
+        let shared_faces = self.vertex_faces[a]
+            .iter()
+            .filter(|&&fi| self.face_alive[fi as usize] && self.faces[fi as usize].contains(&b32))
+            .count();
+        let na = self.neighbors(a);
+        let common = self
+            .neighbors(b)
+            .iter()
+            .filter(|u| na.binary_search(u).is_ok())
+            .count();
+        // Link condition: the edge's opposite vertices must be the only shared
+        // neighbours, or the collapse pinches the surface.
+        if shared_faces == 0 || common != shared_faces {
+            return false;
+        }
+        for &v in &[a, b] {
+            for &fi in &self.vertex_faces[v] {
+                if !self.face_alive[fi as usize] {
+                    continue;
+                }
+                let f = self.faces[fi as usize];
+                if f.contains(&a32) && f.contains(&b32) {
+                    continue;
+                }
+                let old = f.map(|i| self.positions[i as usize]);
+                let new = f.map(|i| {
+                    if i == a32 || i == b32 {
+                        *position
+                    } else {
+                        self.positions[i as usize]
+                    }
+                });
+                let n_old = (old[1] - old[0]).cross(&(old[2] - old[0]));
+                let n_new = (new[1] - new[0]).cross(&(new[2] - new[0]));
+                if n_new.dot(&n_old) < 0.0 || n_new.norm() < 1e-6 * n_old.norm() {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Merge `b` into `a`, which moves to `position`. Returns the number of
+    /// faces removed.
+    fn collapse(&mut self, a: usize, b: usize, position: Vector3<f64>) -> usize {
+        let (a32, b32) = (a as u32, b as u32);
+        self.positions[a] = position;
+        self.quadrics[a] = self.quadrics[a].sum(&self.quadrics[b]);
+        let mut removed = 0;
+        for fi in std::mem::take(&mut self.vertex_faces[b]) {
+            let face = &mut self.faces[fi as usize];
+            if !self.face_alive[fi as usize] {
+                continue;
+            }
+            if face.contains(&a32) {
+                self.face_alive[fi as usize] = false;
+                removed += 1;
+            } else {
+                for v in face.iter_mut().filter(|v| **v == b32) {
+                    *v = a32;
+                }
+                self.vertex_faces[a].push(fi);
+            }
+        }
+        let face_alive = &self.face_alive;
+        self.vertex_faces[a].retain(|&fi| face_alive[fi as usize]);
+        self.stamps[a] += 1;
+        self.stamps[b] += 1;
+        removed
+    }
+}
+
+/// Unit normal and offset of triangle `f`, or `None` when it is degenerate.
+fn face_plane(positions: &[Vector3<f64>], f: &[u32; 3]) -> Option<(Vector3<f64>, f64)> {
+    let [p0, p1, p2] = f.map(|i| positions[i as usize]);
+    let n = (p1 - p0).cross(&(p2 - p0)).try_normalize(1e-20)?;
+    Some((n, -n.dot(&p0)))
+}
+
+fn edge_key(a: u32, b: u32) -> (u32, u32) {
+    (a.min(b), a.max(b))
+}
+
+/// Reduce `mesh` to at most `target_faces` triangles by quadric edge
+/// collapse. Collapsing stops early once the cheapest remaining edge would
+/// move the surface more than roughly `max_error` metres (its quadric error
+/// exceeds `max_error^2`), or when no edge can collapse without flipping a
+/// triangle or making the mesh non-manifold. Open boundary edges are pinned
+/// by heavily weighted perpendicular planes, so holes and navmesh borders keep
+/// their outline. Degenerate input triangles and unreferenced vertices are
+/// dropped.
+pub fn decimate(
+    mesh: &ReconstructedMesh,
+    target_faces: usize,
+    max_error: f64,
+) -> ReconstructedMesh {
+    let positions: Vec<Vector3<f64>> = mesh
+        .vertices
+        .chunks_exact(3)
+        .map(|v| Vector3::new(v[0] as f64, v[1] as f64, v[2] as f64))
+        .collect();
+    let faces: Vec<[u32; 3]> = mesh
+        .indices
+        .chunks_exact(3)
+        .map(|f| [f[0], f[1], f[2]])
+        .filter(|f| {
+            f.iter().all(|&i| (i as usize) < positions.len())
+                && f[0] != f[1]
+                && f[1] != f[2]
+                && f[0] != f[2]
+        })
+        .collect();
+
+    let mut vertex_faces = vec![Vec::new(); positions.len()];
+    let mut quadrics = vec![Quadric::default(); positions.len()];
+    let mut edge_faces: HashMap<(u32, u32), (usize, usize)> = HashMap::new();
+    for (fi, f) in faces.iter().enumerate() {
+        let plane = face_plane(&positions, f).map(|(n, d)| Quadric::plane(n, d, 1.0));
+        for &v in f {
+            vertex_faces[v as usize].push(fi as u32);
+            if let Some(q) = &plane {
+                quadrics[v as usize] = quadrics[v as usize].sum(q);
+            }
+        }
+        for (a, b) in [(f[0], f[1]), (f[1], f[2]), (f[2], f[0])] {
+            edge_faces.entry(edge_key(a, b)).or_insert((0, fi)).0 += 1;
+        }
+    }
+    for (&(a, b), &(count, fi)) in &edge_faces {
+        if count != 1 {
+            continue;
+        }
+        let Some((n, _)) = face_plane(&positions, &faces[fi]) else {
+            continue;
+        };
+        let pa = positions[a as usize];
+        let Some(perp) = (positions[b as usize] - pa).cross(&n).try_normalize(1e-20) else {
+            continue;
+        };
+        let q = Quadric::plane(perp, -perp.dot(&pa), BOUNDARY_WEIGHT);
+        quadrics[a as usize] = quadrics[a as usize].sum(&q);
+        quadrics[b as usize] = quadrics[b as usize].sum(&q);
+    }
+
+    let mut live_faces = faces.len();
+    let mut state = Decimator {
+        stamps: vec![0; positions.len()],
+        positions,
+        quadrics,
+        face_alive: vec![true; faces.len()],
+        faces,
+        vertex_faces,
+    };
+    let mut heap: BinaryHeap<Candidate> = edge_faces
+        .keys()
+        .map(|&(a, b)| state.candidate(a, b))
+        .collect();
+
+    let max_cost = max_error * max_error;
+    while live_faces > target_faces {
+        let Some(c) = heap.pop() else {
+            break;
+        };
+        let (a, b) = (c.v0 as usize, c.v1 as usize);
+        if c.stamps != [state.stamps[a], state.stamps[b]] {
+            continue;
+        }
+        if c.cost > max_cost {
+            break;
+        }
+        if !state.can_collapse(a, b, &c.position) {
+            continue;
+        }
+        live_faces -= state.collapse(a, b, c.position);
+        for n in state.neighbors(a) {
+            heap.push(state.candidate(c.v0, n));
+        }
+    }
+
+    let mut remap = vec![u32::MAX; state.positions.len()];
+    let mut vertices = Vec::new();
+    let mut indices = Vec::with_capacity(live_faces * 3);
+    for (f, _) in state
+        .faces
+        .iter()
+        .zip(&state.face_alive)
+        .filter(|(_, alive)| **alive)
+    {
+        for &v in f {
+            if remap[v as usize] == u32::MAX {
+                remap[v as usize] = (vertices.len() / 3) as u32;
+                let p = state.positions[v as usize];
+                vertices.extend_from_slice(&[p.x as f32, p.y as f32, p.z as f32]);
+            }
+            indices.push(remap[v as usize]);
+        }
+    }
+    ReconstructedMesh { vertices, indices }
+}
+
+/// Merge vertices closer than `distance` and drop the triangles that
+/// collapse: repeated corners, or an edge cross product shorter than
+/// `degenerate_area`. Each vertex joins the first kept vertex found within
+/// `distance` in its 3 x 3 x 3 block of hash cells, in input order, so the
+/// result is deterministic. Unreferenced vertices are dropped.
+pub fn weld(mesh: &ReconstructedMesh, distance: f64, degenerate_area: f64) -> ReconstructedMesh {
+    let positions: Vec<Vector3<f64>> = mesh
+        .vertices
+        .chunks_exact(3)
+        .map(|v| Vector3::new(v[0] as f64, v[1] as f64, v[2] as f64))
+        .collect();
+    let cell_of = |p: &Vector3<f64>| {
+        (
+            (p.x / distance).floor() as i64,
+            (p.y / distance).floor() as i64,
+            (p.z / distance).floor() as i64,
+        )
+    };
+    let mut grid: HashMap<(i64, i64, i64), Vec<u32>> = HashMap::new();
+    let mut representative = Vec::with_capacity(positions.len());
+    for (i, p) in positions.iter().enumerate() {
+        let (cx, cy, cz) = cell_of(p);
+        let mut found = None;
+        'search: for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let Some(kept) = grid.get(&(cx + dx, cy + dy, cz + dz)) else {
+                        continue;
+                    };
+                    if let Some(&j) = kept
+                        .iter()
+                        .find(|&&j| (positions[j as usize] - p).norm() <= distance)
+                    {
+                        found = Some(j);
+                        break 'search;
+                    }
+                }
+            }
+        }
+        representative.push(found.unwrap_or_else(|| {
+            grid.entry((cx, cy, cz)).or_default().push(i as u32);
+            i as u32
+        }));
+    }
+
+    let mut remap = vec![u32::MAX; positions.len()];
+    let mut vertices = Vec::new();
+    let mut indices = Vec::with_capacity(mesh.indices.len());
+    for f in mesh.indices.chunks_exact(3) {
+        if f.iter().any(|&i| i as usize >= positions.len()) {
+            continue;
+        }
+        let [a, b, c] = [f[0], f[1], f[2]].map(|i| representative[i as usize]);
+        if a == b || b == c || a == c {
+            continue;
+        }
+        let [pa, pb, pc] = [a, b, c].map(|i| positions[i as usize]);
+        if (pb - pa).cross(&(pc - pa)).norm() < degenerate_area {
+            continue;
+        }
+        for v in [a, b, c] {
+            if remap[v as usize] == u32::MAX {
+                remap[v as usize] = (vertices.len() / 3) as u32;
+                let p = positions[v as usize];
+                vertices.extend_from_slice(&[p.x as f32, p.y as f32, p.z as f32]);
+            }
+            indices.push(remap[v as usize]);
+        }
+    }
+    ReconstructedMesh { vertices, indices }
+}
+
+/// Iterative Laplacian smoothing of the heights of a 2.5D mesh. Each pass
+/// moves every vertex's `y` a fraction `lambda` of the way to the mean height
+/// of its neighbours; `x` and `z` never move, so the navmesh outline and its
+/// cell layout stay where the carve put them.
+///
+/// Neighbours are the vertices sharing an edge, plus vertices at exactly the
+/// same `x`/`z` no more than `step_link` above or below: the voxel mesher
+/// emits the two sides of a stair riser as separate vertices, and linking them
+/// is what lets a step relax into a ramp. A `step_link` of 0 keeps the plain
+/// edge graph.
+pub fn smooth_heights(
+    mesh: &mut ReconstructedMesh,
+    iterations: usize,
+    lambda: f64,
+    step_link: f64,
+) {
+    if iterations == 0 {
+        return;
+    }
+    let count = mesh.vertices.len() / 3;
+    let mut neighbours: Vec<Vec<u32>> = vec![Vec::new(); count];
+    for f in mesh.indices.chunks_exact(3) {
+        for (a, b) in [(f[0], f[1]), (f[1], f[2]), (f[2], f[0])] {
+            if a == b || a as usize >= count || b as usize >= count {
+                continue;
+            }
+            neighbours[a as usize].push(b);
+            neighbours[b as usize].push(a);
+        }
+    }
+    if step_link > 0.0 {
+        let mut columns: HashMap<(u32, u32), Vec<u32>> = HashMap::new();
+        for (i, v) in mesh.vertices.chunks_exact(3).enumerate() {
+            columns
+                .entry((v[0].to_bits(), v[2].to_bits()))
+                .or_default()
+                .push(i as u32);
+        }
+        for column in columns.values() {
+            for (k, &a) in column.iter().enumerate() {
+                for &b in &column[k + 1..] {
+                    let dy = mesh.vertices[a as usize * 3 + 1] - mesh.vertices[b as usize * 3 + 1];
+                    if (dy.abs() as f64) <= step_link {
+                        neighbours[a as usize].push(b);
+                        neighbours[b as usize].push(a);
+                    }
+                }
+            }
+        }
+    }
+    for list in &mut neighbours {
+        list.sort_unstable();
+        list.dedup();
+    }
+
+    let mut heights: Vec<f64> = mesh.vertices.chunks_exact(3).map(|v| v[1] as f64).collect();
+    let mut next = heights.clone();
+    for _ in 0..iterations {
+        for (i, list) in neighbours.iter().enumerate() {
+            if list.is_empty() {
+                continue;
+            }
+            let mean = list.iter().map(|&n| heights[n as usize]).sum::<f64>() / list.len() as f64;
+            next[i] = heights[i] + lambda * (mean - heights[i]);
+        }
+        std::mem::swap(&mut heights, &mut next);
+    }
+    for (v, h) in mesh.vertices.chunks_exact_mut(3).zip(heights) {
+        v[1] = h as f32;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `n x n` quads of a unit-spaced grid at height `y(x, z)`, two triangles each.
+    fn grid(n: usize, y: impl Fn(f32, f32) -> f32) -> ReconstructedMesh {
+        let mut vertices = Vec::new();
+        for z in 0..=n {
+            for x in 0..=n {
+                vertices.extend_from_slice(&[x as f32, y(x as f32, z as f32), z as f32]);
+            }
+        }
+        let row = (n + 1) as u32;
+        let mut indices = Vec::new();
+        for z in 0..n as u32 {
+            for x in 0..n as u32 {
+                let i = z * row + x;
+                indices.extend_from_slice(&[i, i + row, i + 1, i + 1, i + row, i + row + 1]);
+            }
+        }
+        ReconstructedMesh { vertices, indices }
+    }
+
+    #[test]
+    fn flat_grid_collapses_to_target_and_keeps_its_outline() {
+        let mesh = grid(20, |_, _| 0.0);
+        let out = decimate(&mesh, 50, f64::INFINITY);
+        assert!(out.indices.len() / 3 <= 50);
+        assert!(!out.indices.is_empty());
+        // Flat input: every vertex stays on the plane and inside the square.
+        for v in out.vertices.chunks_exact(3) {
+            assert!(v[1].abs() < 1e-4);
+            assert!((-1e-4..=20.0001).contains(&v[0]) && (-1e-4..=20.0001).contains(&v[2]));
+        }
+        // Corners are pinned by two boundary planes each.
+        let has = |x: f32, z: f32| {
+            out.vertices
+                .chunks_exact(3)
+                .any(|v| (v[0] - x).abs() < 1e-3 && (v[2] - z).abs() < 1e-3)
+        };
+        assert!(has(0.0, 0.0) && has(20.0, 0.0) && has(0.0, 20.0) && has(20.0, 20.0));
+    }
+
+    #[test]
+    fn max_error_stops_before_flattening_a_step() {
+        let mesh = grid(10, |x, _| if x > 5.0 { 1.0 } else { 0.0 });
+        let out = decimate(&mesh, 1, 0.01);
+        assert!(out.indices.len() / 3 > 1);
+        assert!(out.vertices.chunks_exact(3).any(|v| v[1] > 0.99));
+        assert!(out.vertices.chunks_exact(3).any(|v| v[1] < 0.01));
+    }
+
+    /// Two floor strips one step apart whose riser vertices are not shared,
+    /// as the voxel mesher emits them.
+    fn split_step() -> ReconstructedMesh {
+        let mut mesh = grid(4, |_, _| 0.0);
+        let high = grid(4, |_, _| 0.1);
+        let offset = (mesh.vertices.len() / 3) as u32;
+        mesh.vertices.extend(
+            high.vertices
+                .chunks_exact(3)
+                .flat_map(|v| [v[0] + 4.0, v[1], v[2]]),
+        );
+        mesh.indices.extend(high.indices.iter().map(|i| i + offset));
+        mesh
+    }
+
+    #[test]
+    fn smoothing_relaxes_a_split_riser_into_a_ramp() {
+        let mut unlinked = split_step();
+        smooth_heights(&mut unlinked, 10, 0.5, 0.0);
+        assert_eq!(unlinked.vertices, split_step().vertices);
+
+        let mut mesh = split_step();
+        smooth_heights(&mut mesh, 10, 0.5, 0.15);
+        let riser: Vec<f32> = mesh
+            .vertices
+            .chunks_exact(3)
+            .filter(|v| v[0] == 4.0)
+            .map(|v| v[1])
+            .collect();
+        assert!(riser.iter().all(|&y| y > 0.01 && y < 0.09));
+        // Far ends barely move, and nothing moves sideways.
+        assert!(mesh
+            .vertices
+            .chunks_exact(3)
+            .any(|v| v[0] == 0.0 && v[1] < 0.01));
+        let outline = |m: &ReconstructedMesh| -> Vec<(f32, f32)> {
+            m.vertices.chunks_exact(3).map(|v| (v[0], v[2])).collect()
+        };
+        assert_eq!(outline(&mesh), outline(&split_step()));
+    }
+
+    #[test]
+    fn weld_joins_split_corners_and_drops_slivers() {
+        // Two quads sharing an edge, each with its own copy of the corners,
+        // one copy off by a hair; plus a sliver triangle.
+        let mesh = ReconstructedMesh {
+            vertices: vec![
+                0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, //
+                1.0, 0.0005, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 1.0, //
+                0.0, 0.0, 0.0, 0.5, 0.0, 0.0, 1.0, 0.0, 0.0002,
+            ],
+            indices: vec![0, 2, 1, 3, 5, 4, 6, 7, 8],
+        };
+        let out = weld(&mesh, 0.001, 1e-6);
+        assert_eq!(out.indices.len(), 6);
+        assert_eq!(out.vertices.len() / 3, 4);
+        // The second triangle now reuses the first one's corners.
+        assert_eq!(&out.indices[..3], &[0, 1, 2]);
+        assert_eq!(out.indices[3], 2);
+        assert_eq!(out.indices[4], 1);
+    }
+
+    #[test]
+    fn chain_runs_steps_in_order() {
+        // A 4 x 4 flat grid: clip to the left half, then hang a skirt on it.
+        let steps: Vec<PostProcessStep> = serde_json::from_value(serde_json::json!([
+            { "step": "clip", "region": { "min": [-1, -1, -1], "max": [2, 1, 5] } },
+            { "step": "skirt", "depth": 0.5 },
+            { "step": "transfer_colors" },
+        ]))
+        .unwrap();
+        let chained = run_chain(&grid(4, |_, _| 0.0), &steps, Tolerance::fixed(), 0.2);
+        // Two columns of quads, then one skirt quad per open edge: 2 + 4 + 2 + 4.
+        assert_eq!(chained.faces, [16, 16 + 12 * 2, 16 + 12 * 2]);
+        assert_eq!(chained.color_radius, Some(0.2));
+        let mesh = &chained.mesh;
+        assert!(mesh.vertices.chunks_exact(3).all(|v| v[0] <= 2.0));
+        let hanging = mesh
+            .vertices
+            .chunks_exact(3)
+            .filter(|v| v[1] == -0.5)
+            .count();
+        assert_eq!(hanging, 12);
+        // Every edge is now shared: the skirt closes the boundary it follows,
+        // leaving only its own bottom rim open.
+        let mut uses: HashMap<(u32, u32), usize> = HashMap::new();
+        for f in mesh.indices.chunks_exact(3) {
+            for k in 0..3 {
+                *uses.entry(edge_key(f[k], f[(k + 1) % 3])).or_default() += 1;
+            }
+        }
+        let open = uses.values().filter(|&&n| n == 1).count();
+        assert_eq!(open, 12);
+    }
+}
@@ -0,0 +1,206 @@
+//! Input-cloud preprocessing ahead of reconstruction.
+//!
+//! Multi-million splat captures make RANSAC and Poisson slow while adding
+//! little shape information at navmesh scale. [`voxel_downsample`] collapses
+//! the cloud onto a uniform grid, one composited splat per occupied cell, so
+//! `downsample_voxel_size` trades surface detail for speed with a single knob
+//! without thinning the density the navmesh is built from.
+
+use std::collections::HashMap;
+
+use nalgebra::{Matrix3, SymmetricEigen, Vector3};
+
+use crate::splat::PointNormal;
+use crate::MeshSettings;
+
+/// `downsample_voxel_size` must be a positive finite length when set.
+pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+    match settings.downsample_voxel_size {
+        Some(size) if !(size.is_finite() && size > 0.0) => {
+            Err(format!("Invalid downsample_voxel_size: {}", size))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Replace the points in each `voxel_size` cube with one splat composited
+/// from them, so the density the navmesh accumulates survives the merge.
+///
+/// Members are weighted by alpha, the sigmoid of their opacity logit, so a
+/// faint splat pulls the result less than a solid one. The position, color
+/// and normal are alpha-weighted means; splat normals are unoriented, so each
+/// is flipped into the hemisphere of the cell's first normal first. The
+/// covariance is moment-matched: each member's ellipsoid plus the spread of
+/// the member centres, split into the variance along the merged normal and
+/// the principal axes across it. `env_scale` is `environment_scale`, which the
+/// oriented log-space scales carry. Every splat deposits its clamped opacity
+/// into the density grids, so the merged opacity is the sum of the members'
+/// positive opacities (their mean when none is positive) and density
+/// thresholds behave as they did on the full cloud.
+///
+/// A cell holding one splat keeps it unchanged. The result keeps the `index`
+/// of the first point in its cell, and cells come out in first-seen order.
+/// Non-finite points are dropped.
+pub fn voxel_downsample(
+    points: &[PointNormal],
+    voxel_size: f64,
+    env_scale: f64,
+) -> Vec<PointNormal> {
+    let mut slots: HashMap<(i64, i64, i64), usize> = HashMap::new();
+    let mut cells: Vec<Vec<&PointNormal>> = Vec::new();
+    for p in points {
+        if !(p.point.x.is_finite() && p.point.y.is_finite() && p.point.z.is_finite()) {
+            continue;
+        }
+        let key = (
+            (p.point.x / voxel_size).floor() as i64,
+            (p.point.y / voxel_size).floor() as i64,
+            (p.point.z / voxel_size).floor() as i64,
+        );
+        let slot = *slots.entry(key).or_insert_with(|| {
+            cells.push(Vec::new());
+            cells.len() - 1
+        });
+        cells[slot].push(p);
+    }
+    cells
+        .iter()
+        .map(|members| composite(members, env_scale))
+        .collect()
+}
+
+fn composite(members: &[&PointNormal], env_scale: f64) -> PointNormal {
+    if let [single] = members {
+        return (*single).clone();
+    }
+    let alpha: Vec<f64> = members
+        .iter()
+        .map(|p| 1.0 / (1.0 + (-p.opacity).exp()))
+        .collect();
+    let total: f64 = alpha.iter().sum();
+    // Logits far below zero underflow: fall back to equal weights.
+    let weight = |k: usize| {
+        if total > 0.0 {
+            alpha[k] / total
+        } else {
+            1.0 / members.len() as f64
+        }
+    };
+
+    let first = members[0].normal;
+    let mut centre = Vector3::zeros();
+    let mut normal = Vector3::zeros();
+    let mut color = [0.0; 3];
+    for (k, p) in members.iter().enumerate() {
+        let w = weight(k);
+        centre += p.point.coords * w;
+        let side = if first.dot(&p.normal) < 0.0 { -w } else { w };
+        normal += p.normal * side;
+        for (sum, c) in color.iter_mut().zip(p.color) {
+            *sum += c as f64 * w;
+        }
+    }
+    let normal = normal.try_normalize(1e-12).unwrap_or_else(Vector3::y);
+
+    // Members only carry their normal, so their tangent radii are taken as
+    // one circular radius of the same mean square.
+    let mut covariance = Matrix3::zeros();
+    for (k, p) in members.iter().enumerate() {
+        let r = p.scale.map(|s| (s / env_scale).exp() * env_scale);
+        let n = p.normal.try_normalize(1e-12).unwrap_or_else(Vector3::y);
+        let tangent_sq = (r.x * r.x + r.y * r.y) / 2.0;
+        let offset = p.point.coords - centre;
+        covariance += (Matrix3::identity() * tangent_sq
+            + n * n.transpose() * (r.z * r.z - tangent_sq)
+            + offset * offset.transpose())
+            * weight(k);
+    }
+    let across = Matrix3::identity() - normal * normal.transpose();
+    let mut tangent = SymmetricEigen::new(across * covariance * across).eigenvalues;
+    tangent.as_mut_slice().sort_by(|a, b| b.total_cmp(a));
+    let variances = Vector3::new(tangent[0], tangent[1], normal.dot(&(covariance * normal)));
+    let scale = variances.map(|v| (v.max(1e-24).sqrt() / env_scale).ln() * env_scale);
+
+    let mass: f64 = members.iter().map(|p| p.opacity.max(0.0)).sum();
+    let opacity = if mass > 0.0 {
+        mass
+    } else {
+        members.iter().map(|p| p.opacity).sum::<f64>() / members.len() as f64
+    };
+
+    PointNormal {
+        point: centre.into(),
+        normal,
+        scale,
+        opacity,
+        color: color.map(|c| c as f32),
+        index: members[0].index,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Point3;
+
+    fn point(x: f64, y: f64, z: f64, normal: [f64; 3], index: u32) -> PointNormal {
+        PointNormal {
+            point: Point3::new(x, y, z),
+            normal: Vector3::new(normal[0], normal[1], normal[2]),
+            scale: Vector3::new(0.1, 0.1, 0.1),
+            opacity: 0.5 + index as f64 * 0.1,
+            color: [0.5; 3],
+            index,
+        }
+    }
+
+    #[test]
+    fn one_point_per_occupied_voxel() {
+        let points = vec![
+            point(0.1, 0.1, 0.1, [0.0, 1.0, 0.0], 0),
+            point(5.2, 0.0, 0.0, [1.0, 0.0, 0.0], 1),
+            // Same voxel as the first, normal flipped: must not cancel out.
+            point(0.3, 0.3, 0.3, [0.0, -1.0, 0.0], 2),
+            point(f64::NAN, 0.0, 0.0, [0.0, 1.0, 0.0], 3),
+        ];
+        let out = voxel_downsample(&points, 0.5, 1.0);
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0].index, 0);
+        // Alpha-weighted: the more opaque second splat pulls the centre.
+        let [a, b] = [0.5_f64, 0.7].map(|logit| 1.0 / (1.0 + (-logit).exp()));
+        assert!((out[0].point.x - (0.1 * a + 0.3 * b) / (a + b)).abs() < 1e-12);
+        assert!((out[0].normal.y - 1.0).abs() < 1e-12);
+        assert!((out[0].opacity - 1.2).abs() < 1e-12);
+        assert_eq!(out[1].index, 1);
+        assert_eq!(out[1].point.x, 5.2);
+        assert_eq!(out[1].scale, points[1].scale);
+    }
+
+    #[test]
+    fn merged_floor_keeps_its_density_and_spread() {
+        // Four thin floor splats 0.2 m apart in one 0.5 m voxel, radius
+        // 1 cm across and 1 mm through, stored as log-space scales.
+        let scale = Vector3::new(0.01_f64.ln(), 0.01_f64.ln(), 0.001_f64.ln());
+        let points: Vec<PointNormal> = [(0.1, 0.1), (0.3, 0.1), (0.1, 0.3), (0.3, 0.3)]
+            .into_iter()
+            .enumerate()
+            .map(|(k, (x, z))| PointNormal {
+                scale,
+                opacity: 2.0,
+                ..point(x, 0.2, z, [0.0, 1.0, 0.0], k as u32)
+            })
+            .collect();
+        let out = voxel_downsample(&points, 0.5, 1.0);
+        assert_eq!(out.len(), 1);
+        let merged = &out[0];
+        assert!((merged.opacity - 8.0).abs() < 1e-12);
+        assert!((merged.point.coords - Vector3::new(0.2, 0.2, 0.2)).norm() < 1e-12);
+        assert!((merged.normal.y - 1.0).abs() < 1e-12);
+        // Across the floor the radius covers the spread of the centres
+        // (0.1 m standard deviation); through it the splats stay thin.
+        let radii = merged.scale.map(f64::exp);
+        let across = (0.1_f64 * 0.1 + 0.01 * 0.01).sqrt();
+        assert!((radii.x - across).abs() < 1e-9 && (radii.y - across).abs() < 1e-9);
+        assert!((radii.z - 0.001).abs() < 1e-9);
+    }
+}
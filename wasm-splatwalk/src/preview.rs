@@ -0,0 +1,178 @@
+//! Canvas previews of the ground field (`emit_preview`).
+//!
+//! Tuning `cell_size`, confidence or slope thresholds is a loop of "bake,
+//! look, adjust", and drawing the field as a mesh means a WebGL viewer just to
+//! see which cells survived. [`render`] paints one ground-field attribute into
+//! an RGBA image, one pixel per cell, that a web UI can hand straight to
+//! `new ImageData(...)` and `putImageData` on a 2D canvas.
+
+use serde::Serialize;
+
+use crate::{GroundFieldCell, GroundFieldCellState, MeshSettings};
+
+/// Attributes a preview can show.
+const CHANNELS: [&str; 3] = ["height", "coverage", "slope"];
+
+/// Alpha of cells that have data but were rejected as floor: dimmed, so the
+/// walkable area stands out but the reason for a gap is still visible.
+const REJECTED_ALPHA: u8 = 96;
+
+/// Gradient of the `height` channel, low to high.
+pub(crate) const HEIGHT_RAMP: [[f64; 3]; 5] = [
+    [0.27, 0.00, 0.33],
+    [0.23, 0.32, 0.55],
+    [0.13, 0.57, 0.55],
+    [0.37, 0.79, 0.38],
+    [0.99, 0.91, 0.14],
+];
+
+/// Gradient of the `slope` channel, flat to vertical.
+const SLOPE_RAMP: [[f64; 3]; 3] = [[0.10, 0.70, 0.25], [0.95, 0.80, 0.15], [0.85, 0.15, 0.10]];
+
+/// A ground-field attribute as an RGBA image.
+#[derive(Serialize)]
+pub struct FieldPreview {
+    pub channel: String,
+    pub width: usize,
+    pub height: usize,
+    /// `width * height * 4` RGBA bytes, row-major from cell row 0 at the top.
+    /// Cells without data are transparent.
+    pub pixels: serde_bytes::ByteBuf,
+    /// Attribute values at the two ends of the color ramp: metres for
+    /// `height`, surface confidence for `coverage`, degrees for `slope`.
+    pub min: f64,
+    pub max: f64,
+}
+
+/// `emit_preview` must name one of [`CHANNELS`].
+pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+    match settings.emit_preview.as_deref() {
+        Some(channel) if !CHANNELS.contains(&channel) => Err(format!(
+            "Invalid emit_preview: {}. Expected height, coverage or slope.",
+            channel
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Paint `channel` of a `width x height` field. `height` is stretched over
+/// the floor's range, `coverage` runs black to white from 0 to the highest
+/// surface confidence, and `slope` green to red from flat to vertical.
+pub fn render(
+    cells: &[GroundFieldCell],
+    width: usize,
+    height: usize,
+    channel: &str,
+) -> FieldPreview {
+    let value = |cell: &GroundFieldCell| match channel {
+        "coverage" => cell.surface_confidence as f64,
+        "slope" => (cell.normal_alignment.clamp(-1.0, 1.0) as f64)
+            .abs()
+            .acos()
+            .to_degrees(),
+        _ => cell.height as f64,
+    };
+    let has_data = |cell: &GroundFieldCell| {
+        !matches!(cell.state, GroundFieldCellState::Void) && value(cell).is_finite()
+    };
+    let (min, max) = match channel {
+        "slope" => (0.0, 90.0),
+        _ => {
+            let values = cells.iter().filter(|c| has_data(c)).map(value);
+            let (lo, hi) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| {
+                (lo.min(v), hi.max(v))
+            });
+            match channel {
+                "coverage" => (0.0, hi.max(0.0)),
+                _ if lo <= hi => (lo, hi),
+                _ => (0.0, 0.0),
+            }
+        }
+    };
+
+    let mut pixels = vec![0u8; width * height * 4];
+    for (cell, pixel) in cells.iter().zip(pixels.chunks_exact_mut(4)) {
+        if !has_data(cell) {
+            continue;
+        }
+        let t = if max > min {
+            ((value(cell) - min) / (max - min)).clamp(0.0, 1.0)
+        } else {
+            0.5
+        };
+        let rgb = match channel {
+            "coverage" => [t; 3],
+            "slope" => ramp(&SLOPE_RAMP, t),
+            _ => ramp(&HEIGHT_RAMP, t),
+        };
+        for (byte, c) in pixel.iter_mut().zip(rgb) {
+            *byte = (c * 255.0).round() as u8;
+        }
+        pixel[3] = match cell.state {
+            GroundFieldCellState::Walkable | GroundFieldCellState::Filled => 255,
+            _ => REJECTED_ALPHA,
+        };
+    }
+    FieldPreview {
+        channel: channel.to_string(),
+        width,
+        height,
+        pixels: serde_bytes::ByteBuf::from(pixels),
+        min,
+        max,
+    }
+}
+
+/// Linear interpolation along evenly spaced color stops, `t` in `[0, 1]`.
+pub(crate) fn ramp(stops: &[[f64; 3]], t: f64) -> [f64; 3] {
+    let x = t * (stops.len() - 1) as f64;
+    let i = (x.floor() as usize).min(stops.len() - 2);
+    let f = x - i as f64;
+    [0, 1, 2].map(|k| stops[i][k] + (stops[i + 1][k] - stops[i][k]) * f)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(height: f32, state: GroundFieldCellState) -> GroundFieldCell {
+        GroundFieldCell {
+            height,
+            confidence: 1.0,
+            variance: 0.0,
+            normal_alignment: 1.0,
+            obstacle_score: 0.0,
+            primary_layer_height: height,
+            layer_count: 1,
+            primary_layer: 0,
+            peak_density: 1.0,
+            surface_confidence: height,
+            signed_distance: 0.0,
+            gradient: [0.0, 0.0],
+            component_id: 0,
+            state,
+        }
+    }
+
+    #[test]
+    fn pixels_follow_cells_and_states() {
+        let cells = vec![
+            cell(0.0, GroundFieldCellState::Walkable),
+            cell(2.0, GroundFieldCellState::Walkable),
+            cell(1.0, GroundFieldCellState::Obstacle),
+            cell(9.0, GroundFieldCellState::Void),
+        ];
+        let preview = render(&cells, 2, 2, "height");
+        assert_eq!(preview.pixels.len(), 16);
+        assert_eq!((preview.min, preview.max), (0.0, 2.0));
+        assert_eq!(preview.pixels[..4], [69, 0, 84, 255]);
+        assert_eq!(preview.pixels[4..8], [252, 232, 36, 255]);
+        assert_eq!(preview.pixels[11], REJECTED_ALPHA);
+        assert_eq!(preview.pixels[12..], [0, 0, 0, 0]);
+
+        let coverage = render(&cells, 2, 2, "coverage");
+        assert_eq!(coverage.pixels[4..8], [255, 255, 255, 255]);
+        let slope = render(&cells, 2, 2, "slope");
+        assert_eq!(slope.pixels[..3], [26, 179, 64]);
+    }
+}
@@ -0,0 +1,308 @@
+//! Project files: the complete editing state in one versioned binary blob.
+//!
+//! A project records everything a host needs to reopen an editing session
+//! except the splat bytes themselves: each session source by name with its
+//! transform, enable flag, ingest settings and a content fingerprint (so the
+//! host can find and verify the file), the bake settings (which carry the
+//! scene selections such as `collision_seed` and `chosen_ground_index`), the
+//! edited floors as heightfields, and an opaque host payload for UI state.
+//!
+//! The blob is a `PROJ` artifact (see `artifact`) holding one `JSON` chunk of
+//! gzip-compressed JSON. Blobs from before the container (the magic `SWPJ`, a
+//! little-endian `u32` format version, then the same gzip payload) still
+//! load. JSON has no `NaN`, so heightfield cells that are not floor are
+//! stored as `null` and restored as `NaN`.
+
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+
+use crate::artifact::{self, Artifact};
+use crate::floor_edit::Heightfield;
+use crate::{FieldBasis, MeshSettings};
+
+/// Magic of the pre-container project blob.
+const LEGACY_MAGIC: &[u8; 4] = b"SWPJ";
+const JSON_CHUNK: [u8; 4] = *b"JSON";
+
+/// Current project format version. Readers accept this version and older.
+pub const PROJECT_VERSION: u32 = 1;
+
+/// Ingest fields a source was parsed with, replayed when it is restored.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct IngestSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prune_floaters: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prune_floaters_k: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prune_floaters_std_ratio: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flip_y: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coordinate_system: Option<String>,
+}
+
+impl IngestSettings {
+    pub fn from_settings(settings: &MeshSettings) -> Self {
+        Self {
+            prune_floaters: settings.prune_floaters,
+            prune_floaters_k: settings.prune_floaters_k,
+            prune_floaters_std_ratio: settings.prune_floaters_std_ratio,
+            flip_y: settings.flip_y,
+            coordinate_system: settings.coordinate_system.clone(),
+        }
+    }
+
+    pub fn to_settings(&self) -> Result<MeshSettings, String> {
+        let mut value = serde_json::to_value(self).map_err(|e| e.to_string())?;
+        value["mode"] = serde_json::json!(0);
+        serde_json::from_value(value).map_err(|e| e.to_string())
+    }
+}
+
+/// Where a session source came from: enough to find the file again and check
+/// it is the same one.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct SourceOrigin {
+    pub byte_length: usize,
+    /// Sampled content fingerprint, as 16 hex digits.
+    pub hash: String,
+    pub ingest: IngestSettings,
+    /// Chunks appended with `append_chunk`, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chunks: Vec<ChunkOrigin>,
+}
+
+/// A chunk appended to a streamed source: its size and fingerprint.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ChunkOrigin {
+    pub byte_length: usize,
+    pub hash: String,
+}
+
+/// One session source as saved.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ProjectSource {
+    pub name: String,
+    pub enabled: bool,
+    /// World-from-node transform, 16 column-major values.
+    pub transform: Vec<f64>,
+    #[serde(flatten)]
+    pub origin: SourceOrigin,
+}
+
+/// Everything in a project file.
+#[derive(Serialize)]
+pub struct Project {
+    pub version: u32,
+    pub sources: Vec<ProjectSource>,
+    /// Bake settings as the host passed them, or `null`.
+    #[serde(default)]
+    pub settings: serde_json::Value,
+    /// Edited floors by host-chosen name.
+    #[serde(default)]
+    pub heightfields: BTreeMap<String, Heightfield>,
+    /// Opaque host state (selection, camera, UI), stored verbatim.
+    #[serde(default)]
+    pub host_data: serde_json::Value,
+}
+
+/// [`Heightfield`] as stored: `null` instead of `NaN` for cells that are not
+/// floor.
+#[derive(Serialize, Deserialize)]
+struct StoredHeightfield {
+    width: usize,
+    height: usize,
+    cell_size: f64,
+    basis: FieldBasis,
+    heights: Vec<Option<f32>>,
+    layers: Vec<u32>,
+    #[serde(default)]
+    costs: Vec<f32>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredProject {
+    sources: Vec<ProjectSource>,
+    #[serde(default)]
+    settings: serde_json::Value,
+    #[serde(default)]
+    heightfields: BTreeMap<String, StoredHeightfield>,
+    #[serde(default)]
+    host_data: serde_json::Value,
+}
+
+/// Encode `project` as a blob (its `version` is ignored; the current version
+/// is written).
+pub fn encode(project: &Project) -> Result<Vec<u8>, String> {
+    let stored = StoredProject {
+        sources: project.sources.clone(),
+        settings: project.settings.clone(),
+        heightfields: project
+            .heightfields
+            .iter()
+            .map(|(name, field)| {
+                let stored = StoredHeightfield {
+                    width: field.width,
+                    height: field.height,
+                    cell_size: field.cell_size,
+                    basis: field.basis.clone(),
+                    heights: field
+                        .heights
+                        .iter()
+                        .map(|h| h.is_finite().then_some(*h))
+                        .collect(),
+                    layers: field.layers.clone(),
+                    costs: field.costs.clone(),
+                };
+                (name.clone(), stored)
+            })
+            .collect(),
+        host_data: project.host_data.clone(),
+    };
+    let json = serde_json::to_vec(&stored).map_err(|e| e.to_string())?;
+    let mut encoder = GzEncoder::new(
+        Vec::with_capacity(json.len() / 4),
+        flate2::Compression::default(),
+    );
+    encoder.write_all(&json).map_err(|e| e.to_string())?;
+    let mut artifact = Artifact::new(&artifact::PROJECT);
+    artifact.push(
+        JSON_CHUNK,
+        true,
+        encoder.finish().map_err(|e| e.to_string())?,
+    );
+    artifact.encode()
+}
+
+/// Decode a blob written by [`encode`] (this or an older format version, or
+/// the pre-container `SWPJ` framing).
+pub fn decode(blob: &[u8]) -> Result<Project, String> {
+    let (version, payload) = if blob.starts_with(LEGACY_MAGIC) && blob.len() >= 8 {
+        let version = u32::from_le_bytes([blob[4], blob[5], blob[6], blob[7]]);
+        if version != 1 {
+            return Err(format!(
+                "Unsupported project version {} (this build reads up to {})",
+                version, PROJECT_VERSION
+            ));
+        }
+        (version, blob[8..].to_vec())
+    } else if artifact::is_artifact(blob) {
+        let artifact = artifact::decode(blob, &artifact::PROJECT)?;
+        (artifact.version, artifact.require(&JSON_CHUNK)?.to_vec())
+    } else {
+        return Err("Not a splatwalk project (missing PROJ artifact header)".to_string());
+    };
+    let mut json = Vec::new();
+    GzDecoder::new(payload.as_slice())
+        .read_to_end(&mut json)
+        .map_err(|e| format!("Corrupt project payload: {}", e))?;
+    let stored: StoredProject =
+        serde_json::from_slice(&json).map_err(|e| format!("Corrupt project payload: {}", e))?;
+    Ok(Project {
+        version,
+        sources: stored.sources,
+        settings: stored.settings,
+        heightfields: stored
+            .heightfields
+            .into_iter()
+            .map(|(name, s)| {
+                let field = Heightfield {
+                    width: s.width,
+                    height: s.height,
+                    cell_size: s.cell_size,
+                    basis: s.basis,
+                    heights: s.heights.iter().map(|h| h.unwrap_or(f32::NAN)).collect(),
+                    layers: s.layers,
+                    costs: s.costs,
+                };
+                (name, field)
+            })
+            .collect(),
+        host_data: stored.host_data,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_sources_settings_and_floors() {
+        let field = Heightfield {
+            width: 2,
+            height: 1,
+            cell_size: 0.25,
+            basis: FieldBasis {
+                origin: [1.0, 0.0, 2.0],
+                tangent: [1.0, 0.0, 0.0],
+                bitangent: [0.0, 0.0, 1.0],
+                up: [0.0, 1.0, 0.0],
+            },
+            heights: vec![0.5, f32::NAN],
+            layers: vec![1, 0],
+            costs: vec![3.0, 1.0],
+        };
+        let project = Project {
+            version: 0,
+            sources: vec![ProjectSource {
+                name: "lobby".to_string(),
+                enabled: false,
+                transform: (0..16).map(f64::from).collect(),
+                origin: SourceOrigin {
+                    byte_length: 1234,
+                    hash: "00000000deadbeef".to_string(),
+                    ingest: IngestSettings {
+                        flip_y: Some(true),
+                        ..Default::default()
+                    },
+                    chunks: vec![ChunkOrigin {
+                        byte_length: 56,
+                        hash: "000000000000cafe".to_string(),
+                    }],
+                },
+            }],
+            settings: serde_json::json!({ "mode": 2, "chosen_ground_index": 1 }),
+            heightfields: BTreeMap::from([("ground".to_string(), field)]),
+            host_data: serde_json::json!({ "selected": [3, 4] }),
+        };
+
+        let blob = encode(&project).unwrap();
+        assert_eq!(&blob[..4], artifact::MAGIC);
+        let loaded = decode(&blob).unwrap();
+        assert_eq!(loaded.version, PROJECT_VERSION);
+        let source = &loaded.sources[0];
+        assert_eq!(source.name, "lobby");
+        assert!(!source.enabled);
+        assert_eq!(source.transform[15], 15.0);
+        assert_eq!(source.origin.byte_length, 1234);
+        assert_eq!(source.origin.ingest.flip_y, Some(true));
+        assert!(source.origin.ingest.to_settings().unwrap().flip_y.unwrap());
+        assert_eq!(source.origin.chunks[0].byte_length, 56);
+        assert_eq!(loaded.settings["chosen_ground_index"], 1);
+        assert_eq!(loaded.host_data["selected"][1], 4);
+        let ground = &loaded.heightfields["ground"];
+        assert_eq!(ground.heights[0], 0.5);
+        assert!(ground.heights[1].is_nan());
+        assert_eq!(ground.costs, vec![3.0, 1.0]);
+        assert_eq!(ground.basis.origin, [1.0, 0.0, 2.0]);
+
+        // The pre-container framing of the same payload still loads.
+        let payload = artifact::decode(&blob, &artifact::PROJECT)
+            .unwrap()
+            .require(&JSON_CHUNK)
+            .unwrap()
+            .to_vec();
+        let legacy = [&LEGACY_MAGIC[..], &1u32.to_le_bytes(), &payload].concat();
+        assert_eq!(decode(&legacy).unwrap().sources[0].name, "lobby");
+
+        let mut future = blob.clone();
+        future[12] = 99;
+        assert!(decode(&future).err().unwrap().contains("version 99"));
+        assert!(decode(b"PK\x03\x04....").is_err());
+    }
+}
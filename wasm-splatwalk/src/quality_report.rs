@@ -0,0 +1,549 @@
+//! Scan-quality report for a parsed splat cloud.
+//!
+//! Capture apps want to tell the user to re-scan a corner before baking, not
+//! after a broken navmesh comes back. This module bins the cloud into a
+//! top-down (XZ) density map, flags cells below a density floor as uncovered,
+//! and reports every enclosed run of uncovered cells as a hole (runs touching
+//! the map border are outside the capture, not holes in it). Capture noise is
+//! estimated from local plane residuals: splats are bucketed into voxels, a
+//! plane is fitted to each well-populated voxel, and the median RMS distance to
+//! those planes is reported as `noise_sigma`.
+//!
+//! Merged captures sometimes hold the same floor twice, a few centimetres
+//! apart, which fattens it in every bake. Each cell's floor-facing splats are
+//! split in two by height (Otsu's threshold), and the cell is doubled when the
+//! two sheets are further apart than their spread allows for one noisy
+//! surface (Ashman's D) but close enough to be one floor rather than a table
+//! over it. Runs of doubled cells are reported as `doubled_surfaces`.
+
+use nalgebra::{Matrix3, Vector3};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::splat::PointNormal;
+
+/// Longest map side when `cell_size` is derived from the cloud extent.
+const DEFAULT_MAP_CELLS: f64 = 128.0;
+/// Hard cap on either map side, whatever `cell_size` asks for.
+const MAX_MAP_CELLS: usize = 1024;
+/// Splats a voxel needs before its plane residual counts toward the noise.
+const MIN_NOISE_PATCH_SPLATS: usize = 10;
+/// Floor-facing splats a cell needs before its heights are tested for a
+/// second sheet.
+const MIN_DOUBLED_CELL_SPLATS: usize = 12;
+/// Smallest share of a cell's floor splats the thinner sheet must hold.
+const MIN_SHEET_FRACTION: f64 = 0.2;
+/// Ashman's D above which the two sheets are separate surfaces.
+const MIN_SHEET_SEPARATION: f64 = 2.5;
+/// `|normal.y|` of a floor-facing splat: within 30 degrees of vertical.
+const FLOOR_MIN_NORMAL_Y: f64 = 0.866;
+
+#[derive(Deserialize, Default)]
+pub struct QualityReportOptions {
+    /// Map cell edge in world units (default: longest XZ extent / 128).
+    pub cell_size: Option<f64>,
+    /// Splats a cell needs to count as covered (default: 10% of the median
+    /// occupied-cell density, at least 1).
+    pub min_density: Option<u32>,
+    /// Holes smaller than this many cells are dropped (default 2).
+    pub min_hole_cells: Option<usize>,
+    /// Splats below this opacity (0..1) are ignored (default 0.05).
+    pub min_alpha: Option<f64>,
+    /// Voxel edge for the plane-residual noise fit (default 2 * `cell_size`).
+    pub noise_voxel_size: Option<f64>,
+    /// Closest two floor sheets in one cell can be and still count as a
+    /// doubled capture, in world units (default 0.03).
+    pub doubled_min_offset: Option<f64>,
+    /// Furthest apart they can be; further apart is a step or a tabletop
+    /// (default 0.15).
+    pub doubled_max_offset: Option<f64>,
+    /// Doubled regions smaller than this many cells are dropped (default 4).
+    pub doubled_min_cells: Option<usize>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ScanHole {
+    pub cells: usize,
+    /// `cells * cell_size^2`, in square world units.
+    pub area: f64,
+    /// XZ centroid of the hole.
+    pub center: [f64; 2],
+    /// XZ bounds of the hole.
+    pub min: [f64; 2],
+    pub max: [f64; 2],
+}
+
+/// A region where the floor was captured twice.
+#[derive(Serialize, Clone)]
+pub struct DoubledSurface {
+    pub cells: usize,
+    pub area: f64,
+    pub center: [f64; 2],
+    pub min: [f64; 2],
+    pub max: [f64; 2],
+    /// Median height gap between the two sheets over the region's cells.
+    pub offset: f64,
+    /// Mean height of the lower and the upper sheet.
+    pub heights: [f64; 2],
+}
+
+#[derive(Serialize)]
+pub struct QualityReport {
+    pub api_version: u8,
+    pub splat_count: usize,
+    /// Splats that passed `min_alpha` and were binned.
+    pub splats_used: usize,
+    /// XZ corner of map cell (0, 0).
+    pub origin: [f64; 2],
+    pub cell_size: f64,
+    pub width: usize,
+    pub height: usize,
+    /// Splats per cell, row-major (`row * width + col`, rows along +Z).
+    pub density: Vec<u32>,
+    pub min_density: u32,
+    /// Covered cells over all cells inside the scan footprint (map cells minus
+    /// the uncovered regions connected to the border).
+    pub coverage_ratio: f64,
+    /// Median RMS distance of splats to their voxel's best-fit plane, in world
+    /// units. `None` when no voxel had enough splats.
+    pub noise_sigma: Option<f64>,
+    pub noise_patches: usize,
+    /// Enclosed uncovered regions, largest first.
+    pub holes: Vec<ScanHole>,
+    /// Regions with two floor sheets, largest first.
+    pub doubled_surfaces: Vec<DoubledSurface>,
+}
+
+fn median(values: &mut [f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(|a, b| a.total_cmp(b));
+    Some(values[values.len() / 2])
+}
+
+/// Running sums for a least-squares plane fit.
+#[derive(Default)]
+struct Moments {
+    n: usize,
+    sum: Vector3<f64>,
+    outer: Matrix3<f64>,
+}
+
+impl Moments {
+    fn add(&mut self, p: Vector3<f64>) {
+        self.n += 1;
+        self.sum += p;
+        self.outer += p * p.transpose();
+    }
+
+    /// RMS distance to the best-fit plane: the square root of the smallest
+    /// covariance eigenvalue.
+    fn plane_rms(&self) -> f64 {
+        let n = self.n as f64;
+        let mean = self.sum / n;
+        let covariance = self.outer / n - mean * mean.transpose();
+        let smallest = covariance.symmetric_eigenvalues().min();
+        smallest.max(0.0).sqrt()
+    }
+}
+
+fn noise_sigma(points: &[&PointNormal], voxel: f64) -> (Option<f64>, usize) {
+    let mut voxels: HashMap<(i64, i64, i64), Moments> = HashMap::new();
+    for p in points {
+        let key = (
+            (p.point.x / voxel).floor() as i64,
+            (p.point.y / voxel).floor() as i64,
+            (p.point.z / voxel).floor() as i64,
+        );
+        voxels.entry(key).or_default().add(p.point.coords);
+    }
+    let mut residuals = voxels
+        .values()
+        .filter(|m| m.n >= MIN_NOISE_PATCH_SPLATS)
+        .map(Moments::plane_rms)
+        .collect::<Vec<f64>>();
+    let patches = residuals.len();
+    (median(&mut residuals), patches)
+}
+
+/// 4-connected runs of `member` cells, each with its cell indices and whether
+/// it touches the map border.
+fn regions(member: &[bool], width: usize, height: usize) -> Vec<(Vec<usize>, bool)> {
+    let mut labels = vec![usize::MAX; member.len()];
+    let mut regions = Vec::new();
+    for start in 0..member.len() {
+        if !member[start] || labels[start] != usize::MAX {
+            continue;
+        }
+        let label = regions.len();
+        let mut cells = Vec::new();
+        let mut touches_border = false;
+        let mut stack = vec![start];
+        labels[start] = label;
+        while let Some(idx) = stack.pop() {
+            cells.push(idx);
+            let (row, col) = (idx / width, idx % width);
+            if row == 0 || col == 0 || row + 1 == height || col + 1 == width {
+                touches_border = true;
+            }
+            let mut visit = |r: usize, c: usize| {
+                let n = r * width + c;
+                if member[n] && labels[n] == usize::MAX {
+                    labels[n] = label;
+                    stack.push(n);
+                }
+            };
+            if row > 0 {
+                visit(row - 1, col);
+            }
+            if row + 1 < height {
+                visit(row + 1, col);
+            }
+            if col > 0 {
+                visit(row, col - 1);
+            }
+            if col + 1 < width {
+                visit(row, col + 1);
+            }
+        }
+        regions.push((cells, touches_border));
+    }
+    regions
+}
+
+/// XZ centroid, min and max of the map `cells`.
+fn cell_bounds(
+    cells: &[usize],
+    origin: [f64; 2],
+    cell_size: f64,
+    width: usize,
+) -> ([f64; 2], [f64; 2], [f64; 2]) {
+    let mut min = [f64::MAX; 2];
+    let mut max = [f64::MIN; 2];
+    let mut sum = [0.0; 2];
+    for &idx in cells {
+        let x = origin[0] + (idx % width) as f64 * cell_size;
+        let z = origin[1] + (idx / width) as f64 * cell_size;
+        min = [min[0].min(x), min[1].min(z)];
+        max = [max[0].max(x + cell_size), max[1].max(z + cell_size)];
+        sum = [sum[0] + x, sum[1] + z];
+    }
+    let n = cells.len() as f64;
+    let center = [sum[0] / n + cell_size * 0.5, sum[1] / n + cell_size * 0.5];
+    (center, min, max)
+}
+
+/// Mean and variance of `values`.
+fn moments(values: &[f64]) -> (f64, f64) {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+    (mean, variance)
+}
+
+/// The mean heights of the two sheets in one cell's sorted floor `heights`,
+/// split at Otsu's threshold, when they are separate surfaces between
+/// `min_offset` and `max_offset` apart.
+fn split_sheets(heights: &[f64], min_offset: f64, max_offset: f64) -> Option<(f64, f64)> {
+    let n = heights.len();
+    if n < MIN_DOUBLED_CELL_SPLATS {
+        return None;
+    }
+    let min_count = (n as f64 * MIN_SHEET_FRACTION).ceil() as usize;
+    let total: f64 = heights.iter().sum();
+    let mut below = 0.0;
+    let mut best = None;
+    let mut best_score = 0.0;
+    for k in 1..n {
+        below += heights[k - 1];
+        if k < min_count || n - k < min_count {
+            continue;
+        }
+        let (w0, w1) = (k as f64, (n - k) as f64);
+        let gap = (total - below) / w1 - below / w0;
+        let score = w0 * w1 * gap * gap;
+        if score > best_score {
+            best_score = score;
+            best = Some(k);
+        }
+    }
+    let (lower, upper) = heights.split_at(best?);
+    let ((m0, v0), (m1, v1)) = (moments(lower), moments(upper));
+    let offset = m1 - m0;
+    let separation = std::f64::consts::SQRT_2 * offset / (v0 + v1).sqrt();
+    (offset >= min_offset && offset <= max_offset && separation >= MIN_SHEET_SEPARATION)
+        .then_some((m0, m1))
+}
+
+/// Map cell of `p`, row-major from `origin`.
+fn cell_of(
+    p: &PointNormal,
+    origin: [f64; 2],
+    cell_size: f64,
+    width: usize,
+    height: usize,
+) -> usize {
+    let col = (((p.point.x - origin[0]) / cell_size) as usize).min(width - 1);
+    let row = (((p.point.z - origin[1]) / cell_size) as usize).min(height - 1);
+    row * width + col
+}
+
+fn doubled_surfaces(
+    points: &[&PointNormal],
+    origin: [f64; 2],
+    cell_size: f64,
+    width: usize,
+    height: usize,
+    options: &QualityReportOptions,
+) -> Vec<DoubledSurface> {
+    let min_offset = options
+        .doubled_min_offset
+        .filter(|v| v.is_finite() && *v >= 0.0)
+        .unwrap_or(0.03);
+    let max_offset = options
+        .doubled_max_offset
+        .filter(|v| v.is_finite())
+        .unwrap_or(0.15)
+        .max(min_offset);
+    let mut floor: Vec<(usize, f64)> = points
+        .iter()
+        .filter(|p| p.normal.y.abs() >= FLOOR_MIN_NORMAL_Y)
+        .map(|p| (cell_of(p, origin, cell_size, width, height), p.point.y))
+        .collect();
+    floor.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)));
+    let mut sheets = vec![None; width * height];
+    for run in floor.chunk_by(|a, b| a.0 == b.0) {
+        let heights: Vec<f64> = run.iter().map(|&(_, y)| y).collect();
+        sheets[run[0].0] = split_sheets(&heights, min_offset, max_offset);
+    }
+
+    let doubled: Vec<bool> = sheets.iter().map(Option::is_some).collect();
+    let min_cells = options.doubled_min_cells.unwrap_or(4).max(1);
+    let mut surfaces: Vec<DoubledSurface> = regions(&doubled, width, height)
+        .into_iter()
+        .filter(|(cells, _)| cells.len() >= min_cells)
+        .map(|(cells, _)| {
+            let (center, min, max) = cell_bounds(&cells, origin, cell_size, width);
+            let pairs: Vec<(f64, f64)> = cells.iter().filter_map(|&idx| sheets[idx]).collect();
+            let n = pairs.len() as f64;
+            let mut offsets: Vec<f64> = pairs.iter().map(|(lower, upper)| upper - lower).collect();
+            DoubledSurface {
+                cells: cells.len(),
+                area: n * cell_size * cell_size,
+                center,
+                min,
+                max,
+                offset: median(&mut offsets).unwrap_or(0.0),
+                heights: [
+                    pairs.iter().map(|p| p.0).sum::<f64>() / n,
+                    pairs.iter().map(|p| p.1).sum::<f64>() / n,
+                ],
+            }
+        })
+        .collect();
+    surfaces.sort_by_key(|surface| std::cmp::Reverse(surface.cells));
+    surfaces
+}
+
+pub fn build_quality_report(
+    points: &[PointNormal],
+    options: &QualityReportOptions,
+) -> Result<QualityReport, String> {
+    let min_alpha = options.min_alpha.unwrap_or(0.05).clamp(0.0, 1.0);
+    let used: Vec<&PointNormal> = points
+        .iter()
+        .filter(|p| 1.0 / (1.0 + (-p.opacity).exp()) >= min_alpha)
+        .collect();
+    if used.is_empty() {
+        return Err("Quality report needs at least one splat above min_alpha".to_string());
+    }
+
+    let (mut min_x, mut max_x) = (f64::MAX, f64::MIN);
+    let (mut min_z, mut max_z) = (f64::MAX, f64::MIN);
+    for p in &used {
+        min_x = min_x.min(p.point.x);
+        max_x = max_x.max(p.point.x);
+        min_z = min_z.min(p.point.z);
+        max_z = max_z.max(p.point.z);
+    }
+    let extent = (max_x - min_x).max(max_z - min_z);
+    let requested = options
+        .cell_size
+        .filter(|c| c.is_finite() && *c > 0.0)
+        .unwrap_or(extent / DEFAULT_MAP_CELLS);
+    let cell_size = requested.max(extent / MAX_MAP_CELLS as f64).max(1.0e-3);
+    let width = (((max_x - min_x) / cell_size).floor() as usize + 1).min(MAX_MAP_CELLS);
+    let height = (((max_z - min_z) / cell_size).floor() as usize + 1).min(MAX_MAP_CELLS);
+
+    let origin = [min_x, min_z];
+    let mut density = vec![0u32; width * height];
+    for p in &used {
+        density[cell_of(p, origin, cell_size, width, height)] += 1;
+    }
+
+    let min_density = options.min_density.unwrap_or_else(|| {
+        let mut occupied = density
+            .iter()
+            .filter(|&&d| d > 0)
+            .map(|&d| d as f64)
+            .collect::<Vec<f64>>();
+        (median(&mut occupied).unwrap_or(0.0) * 0.1)
+            .round()
+            .max(1.0) as u32
+    });
+    let covered: Vec<bool> = density.iter().map(|&d| d >= min_density).collect();
+
+    let uncovered: Vec<bool> = covered.iter().map(|&c| !c).collect();
+    let regions = regions(&uncovered, width, height);
+    let outside: usize = regions
+        .iter()
+        .filter(|(_, border)| *border)
+        .map(|(cells, _)| cells.len())
+        .sum();
+    let covered_cells = covered.iter().filter(|&&c| c).count();
+    let footprint = width * height - outside;
+    let coverage_ratio = if footprint == 0 {
+        0.0
+    } else {
+        covered_cells as f64 / footprint as f64
+    };
+
+    let min_hole_cells = options.min_hole_cells.unwrap_or(2).max(1);
+    let mut holes: Vec<ScanHole> = regions
+        .iter()
+        .filter(|(cells, border)| !*border && cells.len() >= min_hole_cells)
+        .map(|(cells, _)| {
+            let (center, min, max) = cell_bounds(cells, origin, cell_size, width);
+            ScanHole {
+                cells: cells.len(),
+                area: cells.len() as f64 * cell_size * cell_size,
+                center,
+                min,
+                max,
+            }
+        })
+        .collect();
+    holes.sort_by_key(|hole| std::cmp::Reverse(hole.cells));
+
+    let voxel = options
+        .noise_voxel_size
+        .filter(|v| v.is_finite() && *v > 0.0)
+        .unwrap_or(cell_size * 2.0);
+    let (noise_sigma, noise_patches) = noise_sigma(&used, voxel);
+    let doubled_surfaces = doubled_surfaces(&used, origin, cell_size, width, height, options);
+
+    Ok(QualityReport {
+        api_version: crate::API_VERSION,
+        splat_count: points.len(),
+        splats_used: used.len(),
+        origin,
+        cell_size,
+        width,
+        height,
+        density,
+        min_density,
+        coverage_ratio,
+        noise_sigma,
+        noise_patches,
+        holes,
+        doubled_surfaces,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::splat::test_splat;
+
+    const UP: [f64; 3] = [0.0, 1.0, 0.0];
+
+    #[test]
+    fn enclosed_gap_is_reported_as_hole() {
+        // A 4 m x 4 m floor on a 5 cm lattice with a 1 m square missing in
+        // the middle and alternating +-1 cm height noise.
+        let mut points = Vec::new();
+        for i in 0..80 {
+            for j in 0..80 {
+                let (x, z) = (i as f64 * 0.05, j as f64 * 0.05);
+                if (1.5..2.5).contains(&x) && (1.5..2.5).contains(&z) {
+                    continue;
+                }
+                let y = if (i + j) % 2 == 0 { 0.26 } else { 0.24 };
+                points.push(test_splat([x, y, z], UP));
+            }
+        }
+        let options = QualityReportOptions {
+            cell_size: Some(0.25),
+            ..Default::default()
+        };
+        let report = build_quality_report(&points, &options).unwrap();
+        assert_eq!((report.width, report.height), (16, 16));
+        assert_eq!(report.holes.len(), 1);
+        let hole = &report.holes[0];
+        assert_eq!(hole.cells, 16);
+        assert!((hole.center[0] - 2.0).abs() < 1e-9 && (hole.center[1] - 2.0).abs() < 1e-9);
+        assert!((report.coverage_ratio - 240.0 / 256.0).abs() < 1e-9);
+        let sigma = report.noise_sigma.unwrap();
+        assert!((sigma - 0.01).abs() < 0.002, "sigma {}", sigma);
+        // Two centimetres of noise is one floor, not two.
+        assert!(report.doubled_surfaces.is_empty());
+    }
+
+    #[test]
+    fn border_gaps_are_not_holes() {
+        // An L-shaped scan: the missing quadrant touches the border.
+        let mut points = Vec::new();
+        for i in 0..40 {
+            for j in 0..40 {
+                if i >= 20 && j >= 20 {
+                    continue;
+                }
+                points.push(test_splat([i as f64 * 0.1, 0.0, j as f64 * 0.1], UP));
+            }
+        }
+        let options = QualityReportOptions {
+            cell_size: Some(0.5),
+            ..Default::default()
+        };
+        let report = build_quality_report(&points, &options).unwrap();
+        assert!(report.holes.is_empty());
+        assert!((report.coverage_ratio - 1.0).abs() < 1e-9);
+        assert!(report.noise_sigma.unwrap() < 1e-6);
+    }
+
+    #[test]
+    fn misaligned_second_capture_is_a_doubled_surface() {
+        // A 4 m floor on a 5 cm lattice with 1 cm noise. The west half was
+        // captured again 6 cm higher; a tabletop 0.7 m up stands in the east.
+        let mut points = Vec::new();
+        for i in 0..80 {
+            for j in 0..80 {
+                let (x, z) = (i as f64 * 0.05, j as f64 * 0.05);
+                let noise = if (i + j) % 2 == 0 { 0.01 } else { -0.01 };
+                points.push(test_splat([x, noise, z], UP));
+                if x < 2.0 {
+                    points.push(test_splat([x + 0.02, 0.06 - noise, z + 0.02], UP));
+                }
+                if (3.0..3.5).contains(&x) && (1.0..1.5).contains(&z) {
+                    points.push(test_splat([x, 0.7 + noise, z], UP));
+                }
+            }
+        }
+        let options = QualityReportOptions {
+            cell_size: Some(0.5),
+            ..Default::default()
+        };
+        let report = build_quality_report(&points, &options).unwrap();
+        assert_eq!(report.doubled_surfaces.len(), 1);
+        let surface = &report.doubled_surfaces[0];
+        assert_eq!(surface.cells, 4 * 8);
+        assert_eq!((surface.min, surface.max), ([0.0, 0.0], [2.0, 4.0]));
+        assert!(
+            (surface.offset - 0.06).abs() < 0.005,
+            "offset {}",
+            surface.offset
+        );
+        assert!(surface.heights[0].abs() < 0.005 && (surface.heights[1] - 0.06).abs() < 0.005);
+    }
+}
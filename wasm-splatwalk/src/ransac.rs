@@ -0,0 +1,371 @@
+//! Subsampled RANSAC driver shared by the plane fits in `mesh.rs`.
+//!
+//! Scoring every hypothesis against the full cloud makes ground detection
+//! O(iterations x points), which dominates on multi-million splat scans.
+//! Instead each hypothesis is scored on a fixed random subset (`sample_size`
+//! points), the best few are kept, and only those are re-scored on the full
+//! cloud to pick the winner. Iteration stops early once the best subset
+//! inlier ratio says another hypothesis is unlikely to beat it at the
+//! requested confidence, so the cost no longer grows with the point count
+//! beyond the final verification passes.
+//!
+//! Draws come from a seeded generator, so the same cloud and settings always
+//! yield the same shortlist; callers can refer back to a candidate by index.
+//! [`bake_seed`] resolves the one seed every stochastic step of a bake uses.
+
+use nalgebra::Point3;
+use poisson_reconstruction::Real;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::MeshSettings;
+
+/// Default subset size for hypothesis scoring.
+pub const DEFAULT_SAMPLE_SIZE: usize = 10_000;
+
+/// Default generator seed.
+pub const DEFAULT_SEED: u64 = 0x5eed_5a1a;
+
+/// Largest seed a JS number carries exactly (`Number.MAX_SAFE_INTEGER`).
+const MAX_SEED: u64 = (1 << 53) - 1;
+
+/// `seed` must round-trip through a JS number.
+pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+    match settings.seed {
+        Some(seed) if seed > MAX_SEED => Err(format!(
+            "Invalid seed: {}. Expected at most {} (Number.MAX_SAFE_INTEGER).",
+            seed, MAX_SEED
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// The seed of a bake: `seed`, else the older `ransac_seed`, else
+/// [`DEFAULT_SEED`]. Every stochastic step seeds its generator from it.
+pub fn bake_seed(settings: &MeshSettings) -> u64 {
+    settings
+        .seed
+        .or(settings.ransac_seed.map(u64::from))
+        .unwrap_or(DEFAULT_SEED)
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RansacOptions {
+    /// Upper bound on hypotheses drawn.
+    pub max_iterations: usize,
+    /// Points each hypothesis is scored on; 0 scores on the full cloud.
+    pub sample_size: usize,
+    /// Best subset candidates re-scored on the full cloud.
+    pub verify_top: usize,
+    /// Probability of having drawn an all-inlier triple before stopping early.
+    pub confidence: f64,
+    /// Hypotheses always drawn before early termination may kick in.
+    pub min_iterations: usize,
+    /// Generator seed for hypothesis draws and the scoring subset.
+    pub seed: u64,
+}
+
+impl RansacOptions {
+    pub fn new(max_iterations: usize, sample_size: Option<usize>) -> Self {
+        Self {
+            max_iterations,
+            sample_size: sample_size.unwrap_or(DEFAULT_SAMPLE_SIZE),
+            verify_top: 4,
+            confidence: 0.999,
+            min_iterations: 64.min(max_iterations),
+            seed: DEFAULT_SEED,
+        }
+    }
+}
+
+/// Score of one model on a point set. `score` ranks models (higher is better);
+/// `inliers` is the raw inlier count on that set.
+#[derive(Clone, Copy, Debug)]
+pub struct Score {
+    pub score: f64,
+    pub inliers: usize,
+}
+
+pub struct RansacOutcome<M> {
+    /// Inliers of the best candidate on the full cloud.
+    pub inliers: usize,
+    /// Hypotheses actually drawn (below `max_iterations` on early exit).
+    pub iterations: usize,
+    /// Up to `verify_top` mutually distinct candidates with their full-cloud
+    /// scores, best first.
+    pub candidates: Vec<(M, Score)>,
+}
+
+/// Run RANSAC over `points`. `hypothesize` proposes a model (or `None` for a
+/// degenerate draw); `score` evaluates a model on a point set, with `weight`
+/// the full-cloud / subset size ratio so count-based scores stay comparable
+/// between the subset and the full cloud. `same` reports two models as
+/// duplicates, so the shortlist holds distinct alternatives rather than several
+/// copies of the winner.
+pub fn run<M>(
+    points: &[Point3<Real>],
+    options: RansacOptions,
+    mut hypothesize: impl FnMut(&mut StdRng) -> Option<M>,
+    score: impl Fn(&M, &[Point3<Real>], f64) -> Score,
+    same: impl Fn(&M, &M) -> bool,
+) -> RansacOutcome<M> {
+    let mut search = Search::new(points, options);
+    search.advance(points, &mut hypothesize, &score, &same, &mut || false);
+    search.finish(points, &score)
+}
+
+/// [`run`] as a resumable loop: [`Search::advance`] draws hypotheses until the
+/// search is done or its caller runs out of time, and [`Search::finish`]
+/// verifies the shortlist. Pass the same points and closures to every call.
+pub struct Search<M> {
+    options: RansacOptions,
+    rng: StdRng,
+    /// The scoring subset; empty when hypotheses are scored on every point.
+    subset: Vec<Point3<Real>>,
+    weight: f64,
+    best: Vec<(Score, M)>,
+    required: usize,
+    iterations: usize,
+}
+
+impl<M> Search<M> {
+    pub fn new(points: &[Point3<Real>], options: RansacOptions) -> Self {
+        let mut rng = StdRng::seed_from_u64(options.seed);
+        let n = points.len();
+        let subsampled = options.sample_size > 0 && n > options.sample_size;
+        let subset: Vec<Point3<Real>> = if subsampled {
+            rand::seq::index::sample(&mut rng, n, options.sample_size)
+                .into_iter()
+                .map(|i| points[i])
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let weight = if subsampled {
+            n as f64 / subset.len() as f64
+        } else {
+            1.0
+        };
+        Self {
+            options,
+            rng,
+            subset,
+            weight,
+            best: Vec::with_capacity(options.verify_top.max(1) + 1),
+            required: options.max_iterations,
+            iterations: 0,
+        }
+    }
+
+    fn done(&self) -> bool {
+        self.iterations
+            >= self
+                .options
+                .max_iterations
+                .min(self.required.max(self.options.min_iterations))
+    }
+
+    /// Draw hypotheses until the search is done (`true`) or `out_of_time`
+    /// says to yield (`false`). At least one hypothesis is drawn per call.
+    pub fn advance(
+        &mut self,
+        points: &[Point3<Real>],
+        hypothesize: &mut impl FnMut(&mut StdRng) -> Option<M>,
+        score: &impl Fn(&M, &[Point3<Real>], f64) -> Score,
+        same: &impl Fn(&M, &M) -> bool,
+        out_of_time: &mut dyn FnMut() -> bool,
+    ) -> bool {
+        let keep = self.options.verify_top.max(1);
+        while !self.done() {
+            self.iterations += 1;
+            let scored = if self.subset.is_empty() {
+                points
+            } else {
+                self.subset.as_slice()
+            };
+            if let Some(model) = hypothesize(&mut self.rng) {
+                let s = score(&model, scored, self.weight);
+                if s.inliers > 0 && shortlist(&mut self.best, model, s, keep, same) {
+                    self.required =
+                        required_iterations(s.inliers as f64 / scored.len() as f64, self.options);
+                }
+            }
+            if out_of_time() {
+                return self.done();
+            }
+        }
+        true
+    }
+
+    /// Re-score the shortlisted candidates on every point; the subset only
+    /// ranked them.
+    pub fn finish(
+        self,
+        points: &[Point3<Real>],
+        score: &impl Fn(&M, &[Point3<Real>], f64) -> Score,
+    ) -> RansacOutcome<M> {
+        let subsampled = !self.subset.is_empty();
+        let mut candidates: Vec<(M, Score)> = self
+            .best
+            .into_iter()
+            .map(|(s, model)| {
+                let s = if subsampled {
+                    score(&model, points, 1.0)
+                } else {
+                    s
+                };
+                (model, s)
+            })
+            .collect();
+        candidates.sort_by(|a, b| b.1.score.total_cmp(&a.1.score));
+        RansacOutcome {
+            inliers: candidates.first().map(|(_, s)| s.inliers).unwrap_or(0),
+            iterations: self.iterations,
+            candidates,
+        }
+    }
+}
+
+/// Shortlist `model` if it beats one of the `keep` best, replacing a kept
+/// duplicate of it. Returns whether it is the new best.
+fn shortlist<M>(
+    best: &mut Vec<(Score, M)>,
+    model: M,
+    s: Score,
+    keep: usize,
+    same: &impl Fn(&M, &M) -> bool,
+) -> bool {
+    if let Some(dup) = best.iter().position(|(_, m)| same(m, &model)) {
+        if s.score <= best[dup].0.score {
+            return false;
+        }
+        best.remove(dup);
+    }
+    let slot = best
+        .iter()
+        .position(|(b, _)| s.score > b.score)
+        .unwrap_or(best.len());
+    if slot >= keep {
+        return false;
+    }
+    best.insert(slot, (s, model));
+    best.truncate(keep);
+    slot == 0
+}
+
+impl<M> RansacOutcome<M> {
+    /// Outcome of a fit that never ran (too few points).
+    pub fn empty() -> Self {
+        Self {
+            inliers: 0,
+            iterations: 0,
+            candidates: Vec::new(),
+        }
+    }
+}
+
+/// Hypotheses needed to draw at least one all-inlier triple with
+/// `options.confidence` when a fraction `inlier_ratio` of points are inliers.
+fn required_iterations(inlier_ratio: f64, options: RansacOptions) -> usize {
+    let p_good = inlier_ratio.clamp(0.0, 1.0).powi(3);
+    if p_good >= 1.0 {
+        return 0;
+    }
+    if p_good <= 0.0 {
+        return options.max_iterations;
+    }
+    let needed = (1.0 - options.confidence).ln() / (1.0 - p_good).ln();
+    if needed.is_finite() {
+        needed.ceil().min(options.max_iterations as f64) as usize
+    } else {
+        options.max_iterations
+    }
+}
+
+/// Draw three distinct entries of `pool` (indices into the cloud).
+pub fn draw_triple(rng: &mut impl Rng, pool: &[usize]) -> Option<[usize; 3]> {
+    let a = pool[rng.gen_range(0..pool.len())];
+    let b = pool[rng.gen_range(0..pool.len())];
+    let c = pool[rng.gen_range(0..pool.len())];
+    if a == b || b == c || a == c {
+        None
+    } else {
+        Some([a, b, c])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn required_iterations_shrinks_with_inlier_ratio() {
+        let options = RansacOptions::new(2000, None);
+        let sparse = required_iterations(0.2, options);
+        let dense = required_iterations(0.8, options);
+        assert!(dense < sparse);
+        assert!(sparse <= 2000);
+        assert_eq!(required_iterations(0.0, options), 2000);
+    }
+
+    #[test]
+    fn subsampled_fit_is_verified_on_full_cloud() {
+        // A dense y = 0 plane plus a sparse band of clutter above it.
+        let mut points = Vec::new();
+        for i in 0..200 {
+            for j in 0..200 {
+                points.push(Point3::new(i as Real * 0.05, 0.0, j as Real * 0.05));
+            }
+        }
+        for i in 0..5000 {
+            let t = i as Real;
+            points.push(Point3::new(
+                (t * 0.37) % 10.0,
+                1.0 + (t * 0.13) % 2.0,
+                (t * 0.71) % 10.0,
+            ));
+        }
+        let pool: Vec<usize> = (0..points.len()).collect();
+        let threshold = 0.02;
+        let outcome = run(
+            &points,
+            RansacOptions::new(500, Some(2000)),
+            |rng| draw_triple(rng, &pool).map(|[a, b, c]| (points[a].y, points[b].y, points[c].y)),
+            |&(a, b, c), pts, weight| {
+                let flat = (a - b).abs() < threshold && (b - c).abs() < threshold;
+                let inliers = if flat {
+                    pts.iter().filter(|p| (p.y - a).abs() < threshold).count()
+                } else {
+                    0
+                };
+                Score {
+                    score: inliers as f64 * weight,
+                    inliers,
+                }
+            },
+            |a, b| (a.0 - b.0).abs() < threshold,
+        );
+        assert!(!outcome.candidates.is_empty());
+        assert_eq!(outcome.inliers, 200 * 200);
+        assert!(outcome.iterations < 500);
+        // Clutter planes may fill the rest of the shortlist, but never a copy of
+        // the floor.
+        assert!(outcome.candidates[1..]
+            .iter()
+            .all(|(m, _)| m.0.abs() >= threshold));
+    }
+
+    #[test]
+    fn seed_takes_precedence_over_ransac_seed() {
+        let mut settings: MeshSettings =
+            serde_json::from_value(serde_json::json!({ "mode": 0 })).unwrap();
+        assert_eq!(bake_seed(&settings), DEFAULT_SEED);
+        settings.ransac_seed = Some(7);
+        assert_eq!(bake_seed(&settings), 7);
+        settings.seed = Some(42);
+        assert_eq!(bake_seed(&settings), 42);
+        assert!(validate(&settings).is_ok());
+        settings.seed = Some(u64::MAX);
+        assert!(validate(&settings).is_err());
+    }
+}
@@ -0,0 +1,345 @@
+//! Reachability from a start position (`reachable_from`).
+//!
+//! Scans routinely leave floor patches the carve or field keeps but no player
+//! can walk to: a balcony across open air, a table top, a sliver of floor
+//! behind glass. [`split`] floods the output mesh from the walkable face
+//! nearest the start position and keeps only what it reaches; everything
+//! else is returned as a separate, labelled unreachable set.
+//!
+//! A face is walkable when it tilts no more than `agent_max_slope` from
+//! horizontal. Walkable faces are connected through shared corners (vertices
+//! within the weld tolerance count as shared) and through step links:
+//! corners at the same `x`/`z` no more than `agent_max_climb` apart
+//! vertically, which is how the voxel mesher's floors meet across a stair
+//! riser. The limits mean the same as `recast_config`'s `walkableSlopeAngle`
+//! and `walkableClimb`, so one set of agent values drives both.
+
+use std::collections::HashMap;
+
+use nalgebra::Vector3;
+use serde::Serialize;
+
+use crate::mesh::ReconstructedMesh;
+use crate::{MeshBuffers, MeshSettings};
+
+/// Agent limits the flood walks under.
+#[derive(Clone, Copy, Debug)]
+pub struct AgentLimits {
+    /// Tallest step between floors, in metres (default 0.5).
+    pub max_climb: f64,
+    /// Steepest walkable face, in degrees from horizontal (default 40).
+    pub max_slope_deg: f64,
+    /// Faces rising no more than this, in metres, are walkable however steep
+    /// (`max_step_height`, default 0 = off): the ramps stair treads become.
+    pub max_step: f64,
+}
+
+impl AgentLimits {
+    pub fn from_settings(settings: &MeshSettings) -> Self {
+        Self {
+            max_climb: settings.agent_max_climb.unwrap_or(0.5),
+            max_slope_deg: settings.agent_max_slope.unwrap_or(40.0),
+            max_step: settings.max_step_height.unwrap_or(0.0),
+        }
+    }
+}
+
+/// Faces the flood did not reach.
+#[derive(Serialize)]
+pub struct UnreachableSet {
+    pub mesh: MeshBuffers,
+    /// Per face of `mesh`: the unreachable island it belongs to, numbered
+    /// from 0 by decreasing area, or -1 for faces too steep to walk on.
+    pub island: Vec<i32>,
+    pub islands: usize,
+    /// Walkable area of the islands, in square metres.
+    pub area: f64,
+}
+
+/// `reachable_from` must be a finite `[x, y, z]` point, `agent_max_climb` and
+/// `max_step_height` non-negative lengths, `agent_max_slope` in `(0, 90]`
+/// degrees and `agent_height` a positive length.
+pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+    if let Some(start) = settings.reachable_from.as_ref() {
+        if start.len() < 3 || start.iter().any(|c| !c.is_finite()) {
+            return Err("reachable_from must be a finite [x, y, z] point".to_string());
+        }
+    }
+    if let Some(climb) = settings.agent_max_climb {
+        if !(climb.is_finite() && climb >= 0.0) {
+            return Err(format!("Invalid agent_max_climb: {}", climb));
+        }
+    }
+    if let Some(slope) = settings.agent_max_slope {
+        if !(slope > 0.0 && slope <= 90.0) {
+            return Err(format!("Invalid agent_max_slope: {}", slope));
+        }
+    }
+    if let Some(step) = settings.max_step_height {
+        if !(step.is_finite() && step >= 0.0) {
+            return Err(format!("Invalid max_step_height: {}", step));
+        }
+    }
+    if let Some(height) = settings.agent_height {
+        if !(height.is_finite() && height > 0.0) {
+            return Err(format!("Invalid agent_height: {}", height));
+        }
+    }
+    Ok(())
+}
+
+/// `mesh` split into the part reachable from the start and the rest.
+pub struct Reachability {
+    pub reachable: ReconstructedMesh,
+    pub reachable_area: f64,
+    pub unreachable: UnreachableSet,
+}
+
+/// Flood `mesh` from the walkable face whose centroid is nearest `start`.
+/// `link` is the distance under which corners count as shared. `None` when
+/// the mesh has no walkable face.
+pub fn split(
+    mesh: &ReconstructedMesh,
+    start: [f64; 3],
+    limits: &AgentLimits,
+    link: f64,
+) -> Option<Reachability> {
+    let positions: Vec<Vector3<f64>> = mesh
+        .vertices
+        .chunks_exact(3)
+        .map(|v| Vector3::new(v[0] as f64, v[1] as f64, v[2] as f64))
+        .collect();
+    let faces: Vec<[usize; 3]> = mesh
+        .indices
+        .chunks_exact(3)
+        .map(|f| [f[0] as usize, f[1] as usize, f[2] as usize])
+        .filter(|f| f.iter().all(|&i| i < positions.len()))
+        .collect();
+    let area_of = |f: &[usize; 3]| {
+        let [a, b, c] = f.map(|i| positions[i]);
+        (b - a).cross(&(c - a)).norm() * 0.5
+    };
+    let walkable = walkable_faces(&positions, &faces, limits);
+
+    // One node per distinct corner position; walkable faces join their
+    // corners, and step links join corners stacked within the climb.
+    let link = link.max(1e-6);
+    let quantize = |v: f64| (v / link).round() as i64;
+    let mut node_of: HashMap<(i64, i64, i64), usize> = HashMap::new();
+    let vertex_node: Vec<usize> = positions
+        .iter()
+        .map(|p| {
+            let key = (quantize(p.x), quantize(p.y), quantize(p.z));
+            let next = node_of.len();
+            *node_of.entry(key).or_insert(next)
+        })
+        .collect();
+    let mut parent: Vec<usize> = (0..node_of.len()).collect();
+    for (f, _) in faces.iter().zip(&walkable).filter(|(_, &w)| w) {
+        union(&mut parent, vertex_node[f[0]], vertex_node[f[1]]);
+        union(&mut parent, vertex_node[f[0]], vertex_node[f[2]]);
+    }
+    let mut columns: HashMap<(i64, i64), Vec<(i64, usize)>> = HashMap::new();
+    for (&(x, y, z), &node) in &node_of {
+        columns.entry((x, z)).or_default().push((y, node));
+    }
+    for column in columns.values_mut() {
+        column.sort_unstable();
+        for pair in column.windows(2) {
+            if (pair[1].0 - pair[0].0) as f64 * link <= limits.max_climb {
+                union(&mut parent, pair[0].1, pair[1].1);
+            }
+        }
+    }
+
+    let start = Vector3::new(start[0], start[1], start[2]);
+    let start_face = (0..faces.len()).filter(|&i| walkable[i]).min_by(|&a, &b| {
+        let d = |i: usize| {
+            let [p, q, r] = faces[i].map(|v| positions[v]);
+            ((p + q + r) / 3.0 - start).norm()
+        };
+        d(a).total_cmp(&d(b))
+    })?;
+    let component = |parent: &mut [usize], f: &[usize; 3]| find(parent, vertex_node[f[0]]);
+    let reached = component(&mut parent, &faces[start_face]);
+
+    let mut reachable = Vec::new();
+    let mut reachable_area = 0.0;
+    let mut rest: Vec<(usize, Option<usize>)> = Vec::new();
+    let mut island_area: HashMap<usize, f64> = HashMap::new();
+    for (i, f) in faces.iter().enumerate() {
+        if !walkable[i] {
+            rest.push((i, None));
+            continue;
+        }
+        let root = component(&mut parent, f);
+        if root == reached {
+            reachable.push(i);
+            reachable_area += area_of(f);
+        } else {
+            *island_area.entry(root).or_default() += area_of(f);
+            rest.push((i, Some(root)));
+        }
+    }
+
+    // Islands numbered by decreasing area (root order breaks ties).
+    let mut ranked: Vec<(usize, f64)> = island_area.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    let rank: HashMap<usize, i32> = ranked
+        .iter()
+        .enumerate()
+        .map(|(n, &(root, _))| (root, n as i32))
+        .collect();
+    let island = rest
+        .iter()
+        .map(|(_, root)| root.map_or(-1, |r| rank[&r]))
+        .collect();
+    let rest_faces: Vec<usize> = rest.iter().map(|&(i, _)| i).collect();
+    let unreachable = submesh(&positions, &faces, &rest_faces);
+
+    Some(Reachability {
+        reachable: submesh(&positions, &faces, &reachable),
+        reachable_area,
+        unreachable: UnreachableSet {
+            mesh: MeshBuffers::new(unreachable.vertices, unreachable.indices),
+            island,
+            islands: ranked.len(),
+            area: ranked.iter().map(|&(_, a)| a).sum(),
+        },
+    })
+}
+
+/// Per face: whether it tilts no more than `max_slope_deg` from horizontal,
+/// or rises no more than `max_step` and is not vertical. Faces with a corner
+/// index out of range are not.
+pub fn walkable_faces(
+    positions: &[Vector3<f64>],
+    faces: &[[usize; 3]],
+    limits: &AgentLimits,
+) -> Vec<bool> {
+    let min_up = limits.max_slope_deg.to_radians().cos();
+    faces
+        .iter()
+        .map(|f| {
+            if f.iter().any(|&i| i >= positions.len()) {
+                return false;
+            }
+            let [a, b, c] = f.map(|i| positions[i]);
+            let n = (b - a).cross(&(c - a));
+            let rise = a.y.max(b.y).max(c.y) - a.y.min(b.y).min(c.y);
+            let step = limits.max_step > 0.0 && rise <= limits.max_step + 1e-9 && n.y != 0.0;
+            n.norm() > 0.0 && (n.y.abs() / n.norm() >= min_up - 1e-9 || step)
+        })
+        .collect()
+}
+
+/// The faces `keep` of a mesh, with only the vertices they use.
+pub fn submesh(
+    positions: &[Vector3<f64>],
+    faces: &[[usize; 3]],
+    keep: &[usize],
+) -> ReconstructedMesh {
+    let mut remap = vec![u32::MAX; positions.len()];
+    let mut vertices = Vec::new();
+    let mut indices = Vec::with_capacity(keep.len() * 3);
+    for &f in keep {
+        for v in faces[f] {
+            if remap[v] == u32::MAX {
+                remap[v] = (vertices.len() / 3) as u32;
+                let p = positions[v];
+                vertices.extend_from_slice(&[p.x as f32, p.y as f32, p.z as f32]);
+            }
+            indices.push(remap[v]);
+        }
+    }
+    ReconstructedMesh { vertices, indices }
+}
+
+fn find(parent: &mut [usize], mut x: usize) -> usize {
+    while parent[x] != x {
+        parent[x] = parent[parent[x]];
+        x = parent[x];
+    }
+    x
+}
+
+fn union(parent: &mut [usize], a: usize, b: usize) {
+    let (ra, rb) = (find(parent, a), find(parent, b));
+    if ra != rb {
+        parent[ra.max(rb)] = ra.min(rb);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A unit square at height `y` with its lower corner at (`x`, `z`).
+    fn square(mesh: &mut ReconstructedMesh, x: f32, y: f32, z: f32) {
+        let base = (mesh.vertices.len() / 3) as u32;
+        for (dx, dz) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)] {
+            mesh.vertices.extend_from_slice(&[x + dx, y, z + dz]);
+        }
+        mesh.indices
+            .extend([0, 2, 1, 0, 3, 2].iter().map(|i| base + i));
+    }
+
+    #[test]
+    fn islands_and_walls_are_split_off() {
+        let mut mesh = ReconstructedMesh {
+            vertices: vec![],
+            indices: vec![],
+        };
+        square(&mut mesh, 0.0, 0.0, 0.0);
+        // A 0.3 m step up, sharing the x = 1 column: reachable.
+        square(&mut mesh, 1.0, 0.3, 0.0);
+        // A floating 1 m high ledge two squares away: an island.
+        square(&mut mesh, 3.0, 1.0, 0.0);
+        // A vertical wall on the first square's edge: too steep.
+        let base = (mesh.vertices.len() / 3) as u32;
+        mesh.vertices
+            .extend_from_slice(&[0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0]);
+        mesh.indices.extend([base, base + 1, base + 2]);
+
+        let limits = AgentLimits {
+            max_climb: 0.5,
+            max_slope_deg: 40.0,
+            max_step: 0.0,
+        };
+        let result = split(&mesh, [0.2, 0.0, 0.2], &limits, 1e-3).unwrap();
+        assert_eq!(result.reachable.indices.len() / 3, 4);
+        assert!((result.reachable_area - 2.0).abs() < 1e-9);
+        assert_eq!(result.unreachable.island, vec![0, 0, -1]);
+        assert_eq!(result.unreachable.islands, 1);
+        assert!((result.unreachable.area - 1.0).abs() < 1e-9);
+
+        // Below the step height the raised square becomes a second island.
+        let strict = AgentLimits {
+            max_climb: 0.2,
+            ..limits
+        };
+        let result = split(&mesh, [0.2, 0.0, 0.2], &strict, 1e-3).unwrap();
+        assert_eq!(result.reachable.indices.len() / 3, 2);
+        assert_eq!(result.unreachable.islands, 2);
+    }
+
+    #[test]
+    fn max_step_makes_short_steep_faces_walkable() {
+        // A tread ramped 0.2 m over 0.1 m (63 degrees) and a 1 m wall.
+        let positions = [
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.1, 0.2, 0.0),
+            Vector3::new(0.0, 0.0, 0.1),
+            Vector3::new(0.0, 1.0, 0.0),
+        ];
+        let faces = [[0, 2, 1], [0, 3, 2]];
+        let mut limits = AgentLimits {
+            max_climb: 0.5,
+            max_slope_deg: 40.0,
+            max_step: 0.0,
+        };
+        assert_eq!(walkable_faces(&positions, &faces, &limits), [false, false]);
+        limits.max_step = 0.25;
+        assert_eq!(walkable_faces(&positions, &faces, &limits), [true, false]);
+    }
+}
@@ -0,0 +1,729 @@
+//! Region-of-interest filtering for `build_context`.
+//!
+//! Two shapes are accepted, both in `splatwalk_oriented` space (or, with a
+//! `coordinate_system` preset, in that preset's space):
+//!
+//! - the legacy axis-aligned `region_min` / `region_max` pair, and
+//! - an oriented crop box: `crop_transform` (a 4x4 column-major world-from-box
+//!   matrix, exactly what a rotatable crop gizmo carries) plus `half_extents`
+//!   along the box's local axes.
+//!
+//! On top of that single base volume, `regions` carries an ordered list of
+//! include/exclude rules (boxes, spheres, XZ polygons) for venues like "this
+//! hall plus that corridor minus the fountain". A `"frustum"` rule is the
+//! volume a 2D screen rectangle sweeps through the viewer's camera, so "crop
+//! what I see" is one rule instead of unprojection math in every host.
+//!
+//! When both base shapes are supplied the oriented box wins, so a host can keep sending its
+//! old AABB as a fallback for older cores. The matrix is applied verbatim; no
+//! renderer convention (e.g. Babylon's Y negation) is assumed — hosts that
+//! display in a flipped space already fold that into `flip_y` or the matrix.
+
+use nalgebra::{Matrix4, Point3, Vector3, Vector4};
+use serde::Deserialize;
+
+use crate::output_space::{self, OutputTransform};
+use crate::MeshSettings;
+
+/// Oriented crop box resolved from `crop_transform` + `half_extents`.
+#[derive(Clone, Debug)]
+pub struct CropBox {
+    world_from_box: Matrix4<f64>,
+    box_from_world: Matrix4<f64>,
+    half_extents: [f64; 3],
+}
+
+impl CropBox {
+    /// Builds the box from a 16-value column-major matrix and three half extents.
+    /// Returns `None` for malformed input or a singular transform.
+    pub fn new(transform: &[f64], half_extents: &[f64]) -> Option<Self> {
+        if transform.len() != 16 || half_extents.len() != 3 {
+            return None;
+        }
+        if transform.iter().chain(half_extents).any(|v| !v.is_finite()) {
+            return None;
+        }
+        let world_from_box = Matrix4::from_column_slice(transform);
+        let box_from_world = world_from_box.try_inverse()?;
+        Some(Self {
+            world_from_box,
+            box_from_world,
+            half_extents: [
+                half_extents[0].abs(),
+                half_extents[1].abs(),
+                half_extents[2].abs(),
+            ],
+        })
+    }
+
+    pub fn contains(&self, p: &Point3<f64>) -> bool {
+        let local = self.box_from_world.transform_point(p);
+        local.x.abs() <= self.half_extents[0]
+            && local.y.abs() <= self.half_extents[1]
+            && local.z.abs() <= self.half_extents[2]
+    }
+
+    /// World-space AABB enclosing the eight transformed box corners.
+    pub fn aabb(&self) -> ([f64; 3], [f64; 3]) {
+        let mut min = [f64::MAX; 3];
+        let mut max = [f64::MIN; 3];
+        let h = self.half_extents;
+        for corner in 0..8 {
+            let local = Point3::new(
+                if corner & 1 == 0 { -h[0] } else { h[0] },
+                if corner & 2 == 0 { -h[1] } else { h[1] },
+                if corner & 4 == 0 { -h[2] } else { h[2] },
+            );
+            let world = self.world_from_box.transform_point(&local);
+            for axis in 0..3 {
+                min[axis] = min[axis].min(world[axis]);
+                max[axis] = max[axis].max(world[axis]);
+            }
+        }
+        (min, max)
+    }
+}
+
+/// The part of a camera's view behind a screen rectangle, resolved from a
+/// `"frustum"` rule.
+#[derive(Clone, Debug)]
+pub struct ViewFrustum {
+    /// Half-spaces `plane . (p, 1) >= 0`: the four rectangle edges and the
+    /// camera's front.
+    planes: [Vector4<f64>; 5],
+    eye: Point3<f64>,
+    near: f64,
+    far: f64,
+}
+
+impl ViewFrustum {
+    /// Builds the frustum from 16-value column-major view (camera-from-world)
+    /// and projection matrices, a `[x_min, y_min, x_max, y_max]` rectangle in
+    /// `viewport` units with Y down, and distances from the camera. Returns
+    /// `None` for malformed input, an empty rectangle or a singular view.
+    pub fn new(
+        view: &[f64],
+        projection: &[f64],
+        rect: &[f64],
+        viewport: &[f64],
+        near: f64,
+        far: f64,
+    ) -> Option<Self> {
+        if view.len() != 16 || projection.len() != 16 || rect.len() != 4 || viewport.len() != 2 {
+            return None;
+        }
+        if view
+            .iter()
+            .chain(projection)
+            .chain(rect)
+            .chain(viewport)
+            .any(|v| !v.is_finite())
+        {
+            return None;
+        }
+        if viewport.iter().any(|&v| v <= 0.0) || !(near >= 0.0 && far > near) {
+            return None;
+        }
+        let view = Matrix4::from_column_slice(view);
+        let eye = view.try_inverse()?.transform_point(&Point3::origin());
+        let clip = Matrix4::from_column_slice(projection) * view;
+        let (x, y, w) = (
+            clip.row(0).transpose(),
+            clip.row(1).transpose(),
+            clip.row(3).transpose(),
+        );
+        // Pixels to NDC: X right, Y up.
+        let ndc_x = |v: f64| 2.0 * v / viewport[0] - 1.0;
+        let ndc_y = |v: f64| 1.0 - 2.0 * v / viewport[1];
+        let (left, right) = (ndc_x(rect[0].min(rect[2])), ndc_x(rect[0].max(rect[2])));
+        let (bottom, top) = (ndc_y(rect[1].max(rect[3])), ndc_y(rect[1].min(rect[3])));
+        if left >= right || bottom >= top {
+            return None;
+        }
+        Some(Self {
+            planes: [x - w * left, w * right - x, y - w * bottom, w * top - y, w],
+            eye,
+            near,
+            far,
+        })
+    }
+
+    pub fn contains(&self, p: &Point3<f64>) -> bool {
+        let h = p.to_homogeneous();
+        let distance = (p - self.eye).norm();
+        distance >= self.near
+            && distance <= self.far
+            && self.planes.iter().all(|plane| plane.dot(&h) >= 0.0)
+    }
+
+    /// The cube around the camera that `far` bounds; unbounded without one.
+    pub fn aabb(&self) -> ([f64; 3], [f64; 3]) {
+        let e = self.eye;
+        let r = self.far;
+        ([e.x - r, e.y - r, e.z - r], [e.x + r, e.y + r, e.z + r])
+    }
+}
+
+/// One closed volume in `splatwalk_oriented` space.
+#[derive(Clone, Debug)]
+pub enum RegionVolume {
+    Aabb {
+        min: [f64; 3],
+        max: [f64; 3],
+    },
+    Oriented(Box<CropBox>),
+    Sphere {
+        center: [f64; 3],
+        radius: f64,
+    },
+    /// Vertical prism: an XZ footprint polygon extruded between `y_min` and `y_max`.
+    Polygon {
+        footprint: Vec<[f64; 2]>,
+        y_min: f64,
+        y_max: f64,
+    },
+    Frustum(Box<ViewFrustum>),
+}
+
+impl RegionVolume {
+    pub fn contains(&self, p: &Point3<f64>) -> bool {
+        match self {
+            RegionVolume::Aabb { min, max } => {
+                p.x >= min[0]
+                    && p.x <= max[0]
+                    && p.y >= min[1]
+                    && p.y <= max[1]
+                    && p.z >= min[2]
+                    && p.z <= max[2]
+            }
+            RegionVolume::Oriented(crop) => crop.contains(p),
+            RegionVolume::Sphere { center, radius } => {
+                let d = Vector3::new(p.x - center[0], p.y - center[1], p.z - center[2]);
+                d.norm_squared() <= radius * radius
+            }
+            RegionVolume::Polygon {
+                footprint,
+                y_min,
+                y_max,
+            } => p.y >= *y_min && p.y <= *y_max && point_in_polygon(footprint, p.x, p.z),
+            RegionVolume::Frustum(frustum) => frustum.contains(p),
+        }
+    }
+
+    pub fn aabb(&self) -> ([f64; 3], [f64; 3]) {
+        match self {
+            RegionVolume::Aabb { min, max } => (*min, *max),
+            RegionVolume::Oriented(crop) => crop.aabb(),
+            RegionVolume::Sphere { center, radius } => (
+                [center[0] - radius, center[1] - radius, center[2] - radius],
+                [center[0] + radius, center[1] + radius, center[2] + radius],
+            ),
+            RegionVolume::Polygon {
+                footprint,
+                y_min,
+                y_max,
+            } => {
+                let mut min = [f64::MAX, *y_min, f64::MAX];
+                let mut max = [f64::MIN, *y_max, f64::MIN];
+                for v in footprint {
+                    min[0] = min[0].min(v[0]);
+                    max[0] = max[0].max(v[0]);
+                    min[2] = min[2].min(v[1]);
+                    max[2] = max[2].max(v[1]);
+                }
+                (min, max)
+            }
+            RegionVolume::Frustum(frustum) => frustum.aabb(),
+        }
+    }
+}
+
+/// Even-odd crossing test on the XZ plane.
+fn point_in_polygon(footprint: &[[f64; 2]], x: f64, z: f64) -> bool {
+    let mut inside = false;
+    let mut j = footprint.len() - 1;
+    for i in 0..footprint.len() {
+        let (xi, zi) = (footprint[i][0], footprint[i][1]);
+        let (xj, zj) = (footprint[j][0], footprint[j][1]);
+        if (zi > z) != (zj > z) && x < (xj - xi) * (z - zi) / (zj - zi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+/// One entry of `MeshSettings.regions`. `shape` selects which fields are read:
+///
+/// - `"box"`: `min` + `max`, or `transform` (16 values, column-major) + `half_extents`
+/// - `"sphere"`: `center` + `radius`
+/// - `"polygon"`: `points` as a flat `[x0, z0, x1, z1, ...]` XZ footprint (at least
+///   three vertices) plus optional `y_min` / `y_max` (default unbounded)
+/// - `"frustum"`: `view` + `projection` (16 values each, column-major) and a
+///   screen `rect` in `viewport` units (default `[1, 1]`), plus optional `near` /
+///   `far` distances from the camera (default unbounded)
+///
+/// `mode` is `"include"` (default) or `"exclude"`.
+#[derive(Deserialize, Clone, Default)]
+pub struct RegionRuleSettings {
+    pub mode: Option<String>,
+    pub shape: Option<String>,
+    pub min: Option<Vec<f64>>,
+    pub max: Option<Vec<f64>>,
+    pub transform: Option<Vec<f64>>,
+    pub half_extents: Option<Vec<f64>>,
+    pub center: Option<Vec<f64>>,
+    pub radius: Option<f64>,
+    pub points: Option<Vec<f64>>,
+    pub y_min: Option<f64>,
+    pub y_max: Option<f64>,
+    pub view: Option<Vec<f64>>,
+    pub projection: Option<Vec<f64>>,
+    pub rect: Option<Vec<f64>>,
+    pub viewport: Option<Vec<f64>>,
+    pub near: Option<f64>,
+    pub far: Option<f64>,
+}
+
+impl RegionRuleSettings {
+    /// Whether the rule includes, and its shape; `None` when malformed.
+    pub fn resolve(&self) -> Option<(bool, RegionVolume)> {
+        let include = match self.mode.as_deref() {
+            None | Some("include") => true,
+            Some("exclude") => false,
+            Some(_) => return None,
+        };
+        Some((include, self.volume()?))
+    }
+
+    /// The rule's shape alone, ignoring `mode`; `None` when malformed.
+    pub fn volume(&self) -> Option<RegionVolume> {
+        let volume = match self.shape.as_deref().unwrap_or("box") {
+            "box" => {
+                if let (Some(t), Some(h)) = (&self.transform, &self.half_extents) {
+                    RegionVolume::Oriented(Box::new(CropBox::new(t, h)?))
+                } else {
+                    aabb_volume(self.min.as_deref()?, self.max.as_deref()?)?
+                }
+            }
+            "sphere" => {
+                let c = self.center.as_deref()?;
+                let radius = self.radius?;
+                if c.len() != 3 || !radius.is_finite() || radius <= 0.0 {
+                    return None;
+                }
+                RegionVolume::Sphere {
+                    center: [c[0], c[1], c[2]],
+                    radius,
+                }
+            }
+            "polygon" => {
+                let pts = self.points.as_deref()?;
+                if pts.len() < 6 || pts.len() % 2 != 0 || pts.iter().any(|v| !v.is_finite()) {
+                    return None;
+                }
+                RegionVolume::Polygon {
+                    footprint: pts.chunks_exact(2).map(|c| [c[0], c[1]]).collect(),
+                    y_min: self.y_min.unwrap_or(f64::NEG_INFINITY),
+                    y_max: self.y_max.unwrap_or(f64::INFINITY),
+                }
+            }
+            "frustum" => RegionVolume::Frustum(Box::new(ViewFrustum::new(
+                self.view.as_deref()?,
+                self.projection.as_deref()?,
+                self.rect.as_deref()?,
+                self.viewport.as_deref().unwrap_or(&[1.0, 1.0]),
+                self.near.unwrap_or(0.0),
+                self.far.unwrap_or(f64::INFINITY),
+            )?)),
+            _ => return None,
+        };
+        Some(volume)
+    }
+}
+
+/// Shapes a rule may name.
+pub const SHAPES: &[&str] = &["box", "sphere", "polygon", "frustum"];
+
+/// The crop box must be complete and well formed, and every `regions` rule
+/// must have a known `mode` and `shape` and resolve to a volume: a dropped
+/// crop or rule would silently widen the filter (a lost crop or sole include
+/// keeps the whole scene, a lost exclude keeps what it cut).
+pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+    validate_crop(settings)?;
+    for (i, rule) in settings
+        .regions
+        .as_deref()
+        .unwrap_or_default()
+        .iter()
+        .enumerate()
+    {
+        if let Some(mode) = rule
+            .mode
+            .as_deref()
+            .filter(|m| !matches!(*m, "include" | "exclude"))
+        {
+            return Err(format!(
+                "regions[{}].mode must be \"include\" or \"exclude\", got \"{}\"",
+                i, mode
+            ));
+        }
+        let shape = rule.shape.as_deref().unwrap_or("box");
+        if !SHAPES.contains(&shape) {
+            return Err(format!(
+                "regions[{}].shape must be one of {}, got \"{}\"",
+                i,
+                SHAPES.join(", "),
+                shape
+            ));
+        }
+        if rule.volume().is_none() {
+            return Err(format!("regions[{}] is not a valid {}", i, shape));
+        }
+    }
+    Ok(())
+}
+
+/// `crop_transform` and `half_extents` come together: an invertible matrix of
+/// 16 finite values and three positive half extents.
+fn validate_crop(settings: &MeshSettings) -> Result<(), String> {
+    let (transform, half) = match (&settings.crop_transform, &settings.half_extents) {
+        (None, None) => return Ok(()),
+        (Some(transform), Some(half)) => (transform, half),
+        _ => return Err("crop_transform and half_extents must be set together".to_string()),
+    };
+    if transform.len() != 16 || transform.iter().any(|v| !v.is_finite()) {
+        return Err(format!(
+            "Invalid crop_transform: expected 16 finite numbers, got {:?}",
+            transform
+        ));
+    }
+    if half.len() != 3 || half.iter().any(|v| !(v.is_finite() && *v > 0.0)) {
+        return Err(format!(
+            "Invalid half_extents: expected 3 positive numbers, got {:?}",
+            half
+        ));
+    }
+    if CropBox::new(transform, half).is_none() {
+        return Err("Invalid crop_transform: the matrix is not invertible".to_string());
+    }
+    Ok(())
+}
+
+fn aabb_volume(min: &[f64], max: &[f64]) -> Option<RegionVolume> {
+    if min.len() != 3 || max.len() != 3 {
+        return None;
+    }
+    Some(RegionVolume::Aabb {
+        min: [min[0], min[1], min[2]],
+        max: [max[0], max[1], max[2]],
+    })
+}
+
+/// The active region filter for one bake: an optional base volume (crop box or
+/// legacy AABB) ANDed with the ordered include/exclude rule list.
+#[derive(Clone, Debug, Default)]
+pub struct RegionFilter {
+    base: Option<RegionVolume>,
+    rules: Vec<(bool, RegionVolume)>,
+    /// The `coordinate_system` the volumes are written in; points are mapped
+    /// into it before testing.
+    frame: Option<OutputTransform>,
+}
+
+impl RegionFilter {
+    pub fn from_settings(settings: &MeshSettings) -> Self {
+        let crop = match (&settings.crop_transform, &settings.half_extents) {
+            (Some(transform), Some(half)) => {
+                CropBox::new(transform, half).map(|crop| RegionVolume::Oriented(Box::new(crop)))
+            }
+            _ => None,
+        };
+        let base = crop.or_else(|| match (&settings.region_min, &settings.region_max) {
+            (Some(rmin), Some(rmax)) => aabb_volume(rmin, rmax),
+            _ => None,
+        });
+        let rules = settings
+            .regions
+            .as_deref()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(RegionRuleSettings::resolve)
+            .collect();
+        Self {
+            base,
+            rules,
+            frame: output_space::region_frame(settings),
+        }
+    }
+
+    /// Rules are evaluated in order and the last matching rule decides. A point
+    /// matched by no rule is kept only when the list opens with an exclude (so
+    /// `[exclude fountain]` alone means "everything minus the fountain").
+    pub fn contains(&self, p: &Point3<f64>) -> bool {
+        let p = &match &self.frame {
+            Some(frame) => Point3::from(frame.apply([p.x, p.y, p.z])),
+            None => *p,
+        };
+        if let Some(base) = &self.base {
+            if !base.contains(p) {
+                return false;
+            }
+        }
+        let Some((first_include, _)) = self.rules.first() else {
+            return true;
+        };
+        let mut keep = !first_include;
+        for (include, volume) in &self.rules {
+            if volume.contains(p) {
+                keep = *include;
+            }
+        }
+        keep
+    }
+
+    /// Axis-aligned bounds of the region, used to size dense grids: the base
+    /// volume when pinned, otherwise the union of include volumes when the rule
+    /// list opens with an include (nothing outside them can survive).
+    pub fn bounds(&self) -> Option<(Vector3<f64>, Vector3<f64>)> {
+        let (min, max) = if let Some(base) = &self.base {
+            base.aabb()
+        } else {
+            match self.rules.first() {
+                Some((true, _)) => {}
+                _ => return None,
+            }
+            let mut min = [f64::MAX; 3];
+            let mut max = [f64::MIN; 3];
+            for (_, volume) in self.rules.iter().filter(|(include, _)| *include) {
+                let (lo, hi) = volume.aabb();
+                for axis in 0..3 {
+                    min[axis] = min[axis].min(lo[axis]);
+                    max[axis] = max[axis].max(hi[axis]);
+                }
+            }
+            if min.iter().chain(&max).any(|v| !v.is_finite()) {
+                return None;
+            }
+            (min, max)
+        };
+        // Presets are signed axis permutations, so the box maps to a box.
+        let (min, max) = match &self.frame {
+            Some(frame) => (frame.unapply(min), frame.unapply(max)),
+            None => (min, max),
+        };
+        let (a, b) = (Vector3::from(min), Vector3::from(max));
+        Some((a.inf(&b), a.sup(&b)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotated_crop_box_rejects_aabb_corners() {
+        // 45 degrees about Y, unit half extents, translated to (10, 0, 0).
+        let (s, c) = std::f64::consts::FRAC_PI_4.sin_cos();
+        let transform = [
+            c, 0.0, -s, 0.0, //
+            0.0, 1.0, 0.0, 0.0, //
+            s, 0.0, c, 0.0, //
+            10.0, 0.0, 0.0, 1.0,
+        ];
+        let crop = CropBox::new(&transform, &[1.0, 1.0, 1.0]).unwrap();
+        assert!(crop.contains(&Point3::new(10.0, 0.0, 0.0)));
+        assert!(crop.contains(&Point3::new(11.3, 0.0, 0.0)));
+        // Inside the enclosing AABB but outside the rotated box.
+        assert!(!crop.contains(&Point3::new(11.2, 0.0, 1.2)));
+        let (min, max) = crop.aabb();
+        assert!((max[0] - (10.0 + 2f64.sqrt())).abs() < 1e-9);
+        assert!((min[1] + 1.0).abs() < 1e-9);
+    }
+
+    fn rule(mode: &str, shape: &str) -> RegionRuleSettings {
+        RegionRuleSettings {
+            mode: Some(mode.to_string()),
+            shape: Some(shape.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn include_exclude_rules_evaluate_in_order() {
+        let hall = RegionRuleSettings {
+            min: Some(vec![0.0, 0.0, 0.0]),
+            max: Some(vec![10.0, 5.0, 10.0]),
+            ..rule("include", "box")
+        };
+        let corridor = RegionRuleSettings {
+            points: Some(vec![10.0, 4.0, 20.0, 4.0, 20.0, 6.0, 10.0, 6.0]),
+            y_min: Some(0.0),
+            y_max: Some(3.0),
+            ..rule("include", "polygon")
+        };
+        let fountain = RegionRuleSettings {
+            center: Some(vec![5.0, 0.0, 5.0]),
+            radius: Some(1.0),
+            ..rule("exclude", "sphere")
+        };
+        let filter = RegionFilter {
+            base: None,
+            rules: [hall, corridor, fountain]
+                .iter()
+                .filter_map(RegionRuleSettings::resolve)
+                .collect(),
+            frame: None,
+        };
+        assert!(filter.contains(&Point3::new(2.0, 1.0, 2.0)));
+        assert!(filter.contains(&Point3::new(15.0, 1.0, 5.0)));
+        assert!(!filter.contains(&Point3::new(15.0, 4.0, 5.0)));
+        assert!(!filter.contains(&Point3::new(5.0, 0.5, 5.0)));
+        assert!(!filter.contains(&Point3::new(-1.0, 1.0, 2.0)));
+        let (min, max) = filter.bounds().unwrap();
+        assert_eq!((min.x, max.x, max.y), (0.0, 20.0, 5.0));
+    }
+
+    #[test]
+    fn leading_exclude_keeps_everything_else() {
+        let filter = RegionFilter {
+            base: None,
+            rules: vec![RegionRuleSettings {
+                center: Some(vec![0.0, 0.0, 0.0]),
+                radius: Some(1.0),
+                ..rule("exclude", "sphere")
+            }
+            .resolve()
+            .unwrap()],
+            frame: None,
+        };
+        assert!(filter.contains(&Point3::new(3.0, 0.0, 0.0)));
+        assert!(!filter.contains(&Point3::new(0.5, 0.0, 0.0)));
+        assert!(filter.bounds().is_none());
+    }
+
+    #[test]
+    fn coordinate_system_regions_are_compared_in_the_host_space() {
+        // A Z-up box 2 m tall over the 4 x 4 m square around the origin.
+        let settings: MeshSettings = serde_json::from_value(serde_json::json!({
+            "mode": 0,
+            "coordinate_system": "z-up",
+            "region_min": [-2.0, -2.0, 0.0],
+            "region_max": [2.0, 2.0, 2.0],
+        }))
+        .unwrap();
+        let filter = RegionFilter::from_settings(&settings);
+        // Oriented (x, y, z) is Z-up (x, -z, y).
+        assert!(filter.contains(&Point3::new(1.0, 1.5, 1.0)));
+        assert!(!filter.contains(&Point3::new(1.0, -0.5, 1.0)));
+        let (min, max) = filter.bounds().unwrap();
+        assert_eq!(
+            (min, max),
+            (Vector3::new(-2.0, 0.0, -2.0), Vector3::new(2.0, 2.0, 2.0))
+        );
+    }
+
+    #[test]
+    fn singular_transform_is_rejected() {
+        assert!(CropBox::new(&[0.0; 16], &[1.0, 1.0, 1.0]).is_none());
+    }
+
+    #[test]
+    fn screen_rect_frustum_keeps_what_the_camera_sees_behind_it() {
+        // A camera at (10, 0, 0) looking down -Z with a square 90 degree
+        // OpenGL-style projection (near 0.1, far 100).
+        let mut view = [0.0; 16];
+        for i in 0..4 {
+            view[i * 5] = 1.0;
+        }
+        view[12] = -10.0;
+        let (n, f) = (0.1, 100.0);
+        let mut projection = [0.0; 16];
+        projection[0] = 1.0;
+        projection[5] = 1.0;
+        projection[10] = -(f + n) / (f - n);
+        projection[11] = -1.0;
+        projection[14] = -2.0 * f * n / (f - n);
+        // The top-right quarter of an 800 x 600 canvas, dragged bottom-up.
+        let rule = RegionRuleSettings {
+            view: Some(view.to_vec()),
+            projection: Some(projection.to_vec()),
+            rect: Some(vec![400.0, 300.0, 800.0, 0.0]),
+            viewport: Some(vec![800.0, 600.0]),
+            far: Some(20.0),
+            ..rule("include", "frustum")
+        };
+        let (include, volume) = rule.resolve().unwrap();
+        assert!(include);
+        assert!(volume.contains(&Point3::new(11.0, 1.0, -5.0)));
+        assert!(!volume.contains(&Point3::new(9.0, 1.0, -5.0)));
+        assert!(!volume.contains(&Point3::new(11.0, -1.0, -5.0)));
+        // Behind the camera, and past `far`.
+        assert!(!volume.contains(&Point3::new(11.0, 1.0, 5.0)));
+        assert!(!volume.contains(&Point3::new(14.0, 4.0, -25.0)));
+        let (min, max) = volume.aabb();
+        assert_eq!((min[0], max[2]), (-10.0, 20.0));
+
+        let empty = RegionRuleSettings {
+            rect: Some(vec![400.0, 0.0, 400.0, 300.0]),
+            ..rule
+        };
+        assert!(empty.resolve().is_none());
+    }
+
+    #[test]
+    fn malformed_rules_are_rejected_by_index() {
+        let check = |regions: serde_json::Value| {
+            let settings: MeshSettings =
+                serde_json::from_value(serde_json::json!({ "mode": 2, "regions": regions }))
+                    .unwrap();
+            validate(&settings)
+        };
+        let hall = serde_json::json!({ "min": [0, 0, 0], "max": [10, 3, 10] });
+        assert_eq!(check(serde_json::json!([hall])), Ok(()));
+        let typo = serde_json::json!({ "mode": "exlude", "shape": "sphere", "center": [5, 0, 5], "radius": 1 });
+        let err = check(serde_json::json!([hall, typo])).unwrap_err();
+        assert!(err.starts_with("regions[1].mode"), "{}", err);
+        let cube = serde_json::json!({ "shape": "cube", "min": [0, 0, 0], "max": [1, 1, 1] });
+        let err = check(serde_json::json!([cube])).unwrap_err();
+        assert!(err.starts_with("regions[0].shape"), "{}", err);
+        let short = serde_json::json!({ "min": [0, 0], "max": [1, 1, 1] });
+        assert_eq!(
+            check(serde_json::json!([short])),
+            Err("regions[0] is not a valid box".to_string())
+        );
+        let point = serde_json::json!({ "shape": "sphere", "center": [0, 0, 0], "radius": 0 });
+        assert_eq!(
+            check(serde_json::json!([hall, point])),
+            Err("regions[1] is not a valid sphere".to_string())
+        );
+    }
+
+    #[test]
+    fn malformed_crop_boxes_are_rejected() {
+        let check = |crop: serde_json::Value| {
+            let mut json = serde_json::json!({ "mode": 2 });
+            for (k, v) in crop.as_object().unwrap() {
+                json[k] = v.clone();
+            }
+            validate(&serde_json::from_value(json).unwrap())
+        };
+        let identity = Matrix4::<f64>::identity().as_slice().to_vec();
+        let ok =
+            check(serde_json::json!({ "crop_transform": identity, "half_extents": [1, 2, 3] }));
+        assert_eq!(ok, Ok(()));
+        let half_only = check(serde_json::json!({ "half_extents": [1, 2, 3] })).unwrap_err();
+        assert!(half_only.contains("together"), "{}", half_only);
+        let singular = check(
+            serde_json::json!({ "crop_transform": vec![0.0; 16], "half_extents": [1, 1, 1] }),
+        );
+        assert!(singular.unwrap_err().contains("not invertible"));
+        let short = check(
+            serde_json::json!({ "crop_transform": vec![1.0; 12], "half_extents": [1, 1, 1] }),
+        );
+        assert!(short.unwrap_err().starts_with("Invalid crop_transform"));
+        let flat =
+            check(serde_json::json!({ "crop_transform": identity, "half_extents": [1, 0, 1] }));
+        assert!(flat.unwrap_err().starts_with("Invalid half_extents"));
+    }
+}
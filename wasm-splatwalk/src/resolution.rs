@@ -0,0 +1,144 @@
+//! Grid resolution for footprint-sized grids.
+//!
+//! The ground field, the TSDF and surface-nets voxel grids and the terrain
+//! candidate grid all size their cells from `voxel_target`, a total cell
+//! count spread over the footprint: `sqrt(area / voxel_target)`. That keeps
+//! memory flat but ties the resolution to the footprint's shape. A 100 m by
+//! 2 m corridor at the default target gets 0.22 m cells, nine across the
+//! corridor, which is too coarse to hold a doorway.
+//!
+//! [`Resolution`] lets a bake pin the resolution instead. `cells_per_meter`
+//! fixes the cell edge outright. `voxel_target_u` and `voxel_target_v` ask
+//! for at least that many cells along the footprint's first and second axis
+//! (X and Z, or the floor plane's tangent and bitangent). Cells stay square:
+//! Recast spans, face cells and heightfield exports all assume one cell edge,
+//! so the finer of the per-axis edges wins. Explicit per-grid sizes such as
+//! `sdf_cell_size` or `tsdf_voxel_size` still take precedence, and every grid
+//! keeps its own clamp and memory cap.
+
+use crate::MeshSettings;
+
+/// Cell count the footprint heuristic aims for when `voxel_target` is unset.
+pub const DEFAULT_VOXEL_TARGET: f64 = 4000.0;
+
+/// Settings that size a footprint grid's cells.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Resolution {
+    pub voxel_target: f64,
+    pub voxel_target_u: Option<f64>,
+    pub voxel_target_v: Option<f64>,
+    pub cells_per_meter: Option<f64>,
+}
+
+impl Default for Resolution {
+    fn default() -> Self {
+        Self {
+            voxel_target: DEFAULT_VOXEL_TARGET,
+            voxel_target_u: None,
+            voxel_target_v: None,
+            cells_per_meter: None,
+        }
+    }
+}
+
+/// `cells_per_meter`, `voxel_target_u` and `voxel_target_v` must be finite
+/// and positive when set.
+pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+    for (name, value) in [
+        ("cells_per_meter", settings.cells_per_meter),
+        ("voxel_target_u", settings.voxel_target_u),
+        ("voxel_target_v", settings.voxel_target_v),
+    ] {
+        if let Some(value) = value {
+            if !(value.is_finite() && value > 0.0) {
+                return Err(format!(
+                    "Invalid {}: {}. Expected a positive number.",
+                    name, value
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+impl Resolution {
+    pub fn from_settings(settings: &MeshSettings) -> Self {
+        Self {
+            voxel_target: settings
+                .voxel_target
+                .unwrap_or(DEFAULT_VOXEL_TARGET)
+                .max(1.0),
+            voxel_target_u: settings.voxel_target_u,
+            voxel_target_v: settings.voxel_target_v,
+            cells_per_meter: settings.cells_per_meter,
+        }
+    }
+
+    /// Cell edge in metres for a footprint `extent_u` by `extent_v` metres,
+    /// before the caller's clamp.
+    pub fn cell_size(&self, extent_u: f64, extent_v: f64) -> f64 {
+        let (extent_u, extent_v) = (extent_u.max(0.0), extent_v.max(0.0));
+        if let Some(cells_per_meter) = self.cells_per_meter {
+            return 1.0 / cells_per_meter;
+        }
+        let per_axis = [
+            self.voxel_target_u.map(|target| extent_u / target),
+            self.voxel_target_v.map(|target| extent_v / target),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|edge| *edge > 0.0)
+        .reduce(f64::min);
+        per_axis.unwrap_or_else(|| (extent_u * extent_v / self.voxel_target).sqrt())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corridor_resolution_follows_the_override() {
+        let resolution = Resolution::default();
+        // 100 m x 2 m corridor: the area heuristic leaves 9 cells across.
+        let edge = resolution.cell_size(100.0, 2.0);
+        assert!((edge - (200.0_f64 / 4000.0).sqrt()).abs() < 1e-12);
+        assert_eq!((2.0 / edge).ceil(), 9.0);
+
+        let across = Resolution {
+            voxel_target_v: Some(40.0),
+            ..resolution
+        };
+        assert!((across.cell_size(100.0, 2.0) - 0.05).abs() < 1e-12);
+
+        // Both axes: the finer edge wins so each gets at least its count.
+        let both = Resolution {
+            voxel_target_u: Some(500.0),
+            ..across
+        };
+        assert!((both.cell_size(100.0, 2.0) - 0.05).abs() < 1e-12);
+        let both = Resolution {
+            voxel_target_u: Some(4000.0),
+            ..across
+        };
+        assert!((both.cell_size(100.0, 2.0) - 0.025).abs() < 1e-12);
+
+        // cells_per_meter beats every target.
+        let fixed = Resolution {
+            cells_per_meter: Some(10.0),
+            ..both
+        };
+        assert!((fixed.cell_size(100.0, 2.0) - 0.1).abs() < 1e-12);
+    }
+
+    #[test]
+    fn flat_footprint_falls_back_to_the_other_axis() {
+        // A zero-depth footprint gives no V edge; U still sizes the cell.
+        let resolution = Resolution {
+            voxel_target_u: Some(100.0),
+            voxel_target_v: Some(100.0),
+            ..Resolution::default()
+        };
+        assert!((resolution.cell_size(10.0, 0.0) - 0.1).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,393 @@
+//! VR safe area around a standing point (`build_safe_area`).
+//!
+//! Guardian-style play boundaries need a region the player can swing their
+//! arms in, not just floor they can walk on. A ground-field cell is free when
+//! it is accepted floor and no more than [`MIN_OVERHEAD_WEIGHT`] of splat
+//! opacity hangs above it below `safe_area_ceiling_height` (table tops,
+//! shelves, sloped ceilings). Free cells closer than `safe_area_clearance` to
+//! a blocked cell or the field edge are then eroded away ([`clearance_field`]).
+//!
+//! The region is grown from the standing point by casting `safe_area_rays`
+//! rays out to `safe_area_max_radius` through the eroded mask:
+//!
+//! - `star` joins the ray ends into a polygon star-shaped about the standing
+//!   point, shortening ray pairs until no blocked cell lies between them.
+//! - `convex` (default) clips a disc by the half-plane perpendicular to every
+//!   ray that hit something, then by one more half-plane per blocked cell
+//!   still inside, nearest first. The result is convex and obstacle-free but
+//!   greedy: it is a maximal region, not necessarily the largest one.
+//!
+//! All geometry here is in field-local metres (`u` along the basis tangent,
+//! `v` along the bitangent); `mesh::build_safe_area` maps it to `[x, z]`.
+
+use std::f64::consts::{FRAC_1_SQRT_2, SQRT_2, TAU};
+
+use crate::MeshSettings;
+
+/// Opacity above a cell's floor, below the ceiling height, at which the cell
+/// counts as obstructed (one fully opaque splat's worth).
+pub const MIN_OVERHEAD_WEIGHT: f64 = 1.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SafeAreaShape {
+    Convex,
+    Star,
+}
+
+impl SafeAreaShape {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SafeAreaShape::Convex => "convex",
+            SafeAreaShape::Star => "star",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SafeAreaOptions {
+    pub clearance: f64,
+    pub ceiling_height: f64,
+    pub max_radius: f64,
+    pub rays: usize,
+    pub shape: SafeAreaShape,
+}
+
+impl SafeAreaOptions {
+    pub fn from_settings(settings: &MeshSettings) -> Self {
+        Self {
+            clearance: settings.safe_area_clearance.unwrap_or(0.3),
+            ceiling_height: settings.safe_area_ceiling_height.unwrap_or(2.2),
+            max_radius: settings.safe_area_max_radius.unwrap_or(4.0),
+            rays: settings.safe_area_rays.unwrap_or(72).clamp(8, 720),
+            shape: match settings.safe_area_shape.as_deref() {
+                Some("star") => SafeAreaShape::Star,
+                _ => SafeAreaShape::Convex,
+            },
+        }
+    }
+}
+
+/// `safe_area_shape` must be `convex` or `star`, the lengths finite (the
+/// clearance non-negative, the others positive), and `safe_area_center` an
+/// `[x, y, z]` point.
+pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+    if let Some(shape) = settings.safe_area_shape.as_deref() {
+        if !matches!(shape, "convex" | "star") {
+            return Err(format!(
+                "Invalid safe_area_shape: {}. Expected convex or star.",
+                shape
+            ));
+        }
+    }
+    if let Some(clearance) = settings.safe_area_clearance {
+        if !(clearance.is_finite() && clearance >= 0.0) {
+            return Err(format!("Invalid safe_area_clearance: {}", clearance));
+        }
+    }
+    for (name, value) in [
+        (
+            "safe_area_ceiling_height",
+            settings.safe_area_ceiling_height,
+        ),
+        ("safe_area_max_radius", settings.safe_area_max_radius),
+    ] {
+        if let Some(v) = value {
+            if !(v.is_finite() && v > 0.0) {
+                return Err(format!("Invalid {}: {}", name, v));
+            }
+        }
+    }
+    if let Some(center) = settings.safe_area_center.as_ref() {
+        if center.len() < 3 || center.iter().any(|c| !c.is_finite()) {
+            return Err("safe_area_center must be a finite [x, y, z] point".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Distance in metres from each cell centre to the nearest blocked cell (or
+/// the field edge), by a two-pass 8-neighbour chamfer. 0 on blocked cells.
+pub fn clearance_field(free: &[bool], width: usize, height: usize, cell_size: f64) -> Vec<f64> {
+    let mut d: Vec<f64> = (0..width * height)
+        .map(|idx| {
+            if !free[idx] {
+                return 0.0;
+            }
+            let (col, row) = (idx % width, idx / width);
+            (col + 1).min(row + 1).min(width - col).min(height - row) as f64
+        })
+        .collect();
+    let steps = [
+        (-1, 0, 1.0),
+        (0, -1, 1.0),
+        (-1, -1, SQRT_2),
+        (1, -1, SQRT_2),
+    ];
+    let relax = |d: &mut Vec<f64>, col: usize, row: usize, sign: isize| {
+        let idx = row * width + col;
+        for &(dc, dr, cost) in &steps {
+            let (c, r) = (col as isize + dc * sign, row as isize + dr * sign);
+            if c >= 0 && r >= 0 && (c as usize) < width && (r as usize) < height {
+                let n = r as usize * width + c as usize;
+                d[idx] = d[idx].min(d[n] + cost);
+            }
+        }
+    };
+    for row in 0..height {
+        for col in 0..width {
+            relax(&mut d, col, row, 1);
+        }
+    }
+    for row in (0..height).rev() {
+        for col in (0..width).rev() {
+            relax(&mut d, col, row, -1);
+        }
+    }
+    // Centre-to-centre cells to centre-to-edge metres.
+    d.iter()
+        .map(|&cells| ((cells - 0.5) * cell_size).max(0.0))
+        .collect()
+}
+
+/// A grown safe area in field-local metres.
+pub struct SafeArea {
+    /// Counter-clockwise in `[u, v]`.
+    pub boundary: Vec<[f64; 2]>,
+    pub area: f64,
+    /// Radius of the largest circle about the standing point inside
+    /// `boundary`.
+    pub inscribed_radius: f64,
+}
+
+/// Grow the safe area around `center` through `clear` (cells that passed the
+/// clearance erosion). `None` when `center` itself is not clear.
+pub fn grow(
+    clear: &[bool],
+    width: usize,
+    height: usize,
+    cell_size: f64,
+    center: [f64; 2],
+    options: &SafeAreaOptions,
+) -> Option<SafeArea> {
+    let cell_of = |p: [f64; 2]| {
+        let (col, row) = ((p[0] / cell_size).floor(), (p[1] / cell_size).floor());
+        if col >= 0.0 && row >= 0.0 && (col as usize) < width && (row as usize) < height {
+            Some(row as usize * width + col as usize)
+        } else {
+            None
+        }
+    };
+    let is_clear = |p: [f64; 2]| cell_of(p).is_some_and(|idx| clear[idx]);
+    if !is_clear(center) {
+        return None;
+    }
+
+    let n = options.rays;
+    let dirs: Vec<[f64; 2]> = (0..n)
+        .map(|k| {
+            let angle = TAU * k as f64 / n as f64;
+            [angle.cos(), angle.sin()]
+        })
+        .collect();
+    let at = |dir: [f64; 2], r: f64| [center[0] + dir[0] * r, center[1] + dir[1] * r];
+
+    let step = cell_size * 0.25;
+    let mut radii: Vec<f64> = dirs
+        .iter()
+        .map(|&dir| {
+            let mut r = 0.0;
+            while r < options.max_radius {
+                let next = (r + step).min(options.max_radius);
+                if !is_clear(at(dir, next)) {
+                    break;
+                }
+                r = next;
+            }
+            r
+        })
+        .collect();
+
+    // Blocked cell centres inside `inside`, nearest to `center` first.
+    let blocked_inside = |ring: &[[f64; 2]], inside: &dyn Fn([f64; 2]) -> bool| {
+        let (mut lo, mut hi) = ([f64::MAX; 2], [f64::MIN; 2]);
+        for p in ring {
+            for axis in 0..2 {
+                lo[axis] = lo[axis].min(p[axis]);
+                hi[axis] = hi[axis].max(p[axis]);
+            }
+        }
+        let span = |axis: usize, len: usize| {
+            let first = ((lo[axis] / cell_size).floor().max(0.0) as usize).min(len);
+            let last = ((hi[axis] / cell_size).ceil().max(0.0) as usize).min(len);
+            first..last
+        };
+        let mut hits: Vec<[f64; 2]> = span(1, height)
+            .flat_map(|row| span(0, width).map(move |col| (col, row)))
+            .filter(|&(col, row)| !clear[row * width + col])
+            .map(|(col, row)| {
+                [
+                    (col as f64 + 0.5) * cell_size,
+                    (row as f64 + 0.5) * cell_size,
+                ]
+            })
+            .filter(|&q| inside(q))
+            .collect();
+        hits.sort_by(|a, b| dist(*a, center).total_cmp(&dist(*b, center)));
+        hits
+    };
+    // How far a half-plane or ray may reach toward blocked centre `q` while
+    // keeping its whole cell out, but never past `center`.
+    let limit_for = |q: [f64; 2]| {
+        let d = dist(q, center);
+        (d - cell_size * FRAC_1_SQRT_2).max(d * 0.5)
+    };
+
+    let boundary = match options.shape {
+        SafeAreaShape::Star => {
+            let mut changed = true;
+            while changed {
+                changed = false;
+                for k in 0..n {
+                    let next = (k + 1) % n;
+                    let tri = [center, at(dirs[k], radii[k]), at(dirs[next], radii[next])];
+                    let hits = blocked_inside(&tri, &|q| in_triangle(q, &tri));
+                    if let Some(&q) = hits.first() {
+                        let limit = limit_for(q);
+                        radii[k] = radii[k].min(limit);
+                        radii[next] = radii[next].min(limit);
+                        changed = true;
+                    }
+                }
+            }
+            (0..n).map(|k| at(dirs[k], radii[k])).collect()
+        }
+        SafeAreaShape::Convex => {
+            let mut ring: Vec<[f64; 2]> = dirs.iter().map(|&d| at(d, options.max_radius)).collect();
+            for (dir, &r) in dirs.iter().zip(&radii) {
+                if r < options.max_radius {
+                    ring = clip(&ring, center, *dir, r);
+                }
+            }
+            loop {
+                let hits = blocked_inside(&ring, &|q| in_polygon(q, &ring));
+                let Some(&q) = hits.first() else {
+                    break;
+                };
+                let d = dist(q, center);
+                let dir = [(q[0] - center[0]) / d, (q[1] - center[1]) / d];
+                ring = clip(&ring, center, dir, limit_for(q));
+            }
+            ring
+        }
+    };
+
+    let inscribed_radius = (0..boundary.len())
+        .map(|i| segment_distance(center, boundary[i], boundary[(i + 1) % boundary.len()]))
+        .fold(f64::INFINITY, f64::min);
+    let area = crate::floor_plan::signed_area(&boundary).abs();
+    Some(SafeArea {
+        boundary,
+        area,
+        inscribed_radius,
+    })
+}
+
+fn dist(a: [f64; 2], b: [f64; 2]) -> f64 {
+    (a[0] - b[0]).hypot(a[1] - b[1])
+}
+
+fn cross(o: [f64; 2], a: [f64; 2], b: [f64; 2]) -> f64 {
+    (a[0] - o[0]) * (b[1] - o[1]) - (a[1] - o[1]) * (b[0] - o[0])
+}
+
+fn in_triangle(p: [f64; 2], tri: &[[f64; 2]; 3]) -> bool {
+    let s = [
+        cross(tri[0], tri[1], p),
+        cross(tri[1], tri[2], p),
+        cross(tri[2], tri[0], p),
+    ];
+    s.iter().all(|&v| v >= 0.0) || s.iter().all(|&v| v <= 0.0)
+}
+
+/// Even-odd point-in-polygon test.
+fn in_polygon(p: [f64; 2], ring: &[[f64; 2]]) -> bool {
+    let mut inside = false;
+    for i in 0..ring.len() {
+        let (a, b) = (ring[i], ring[(i + 1) % ring.len()]);
+        if (a[1] > p[1]) != (b[1] > p[1])
+            && p[0] < a[0] + (p[1] - a[1]) / (b[1] - a[1]) * (b[0] - a[0])
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Sutherland-Hodgman clip of `ring` to `(x - origin) . dir <= limit`.
+fn clip(ring: &[[f64; 2]], origin: [f64; 2], dir: [f64; 2], limit: f64) -> Vec<[f64; 2]> {
+    let f = |p: [f64; 2]| (p[0] - origin[0]) * dir[0] + (p[1] - origin[1]) * dir[1] - limit;
+    let mut out = Vec::with_capacity(ring.len() + 1);
+    for i in 0..ring.len() {
+        let (a, b) = (ring[i], ring[(i + 1) % ring.len()]);
+        let (fa, fb) = (f(a), f(b));
+        if fa <= 0.0 {
+            out.push(a);
+        }
+        if (fa <= 0.0) != (fb <= 0.0) {
+            let t = fa / (fa - fb);
+            out.push([a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t]);
+        }
+    }
+    out
+}
+
+fn segment_distance(p: [f64; 2], a: [f64; 2], b: [f64; 2]) -> f64 {
+    let ab = [b[0] - a[0], b[1] - a[1]];
+    let len2 = ab[0] * ab[0] + ab[1] * ab[1];
+    let t = if len2 > 0.0 {
+        (((p[0] - a[0]) * ab[0] + (p[1] - a[1]) * ab[1]) / len2).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    dist(p, [a[0] + ab[0] * t, a[1] + ab[1] * t])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn areas_avoid_a_pillar_and_stay_inside_the_room() {
+        // 40 x 40 cells of 0.1 m with a 2 x 2 pillar east of the centre.
+        let (width, height, cs) = (40, 40, 0.1);
+        let mut free = vec![true; width * height];
+        for row in 19..21 {
+            for col in 28..30 {
+                free[row * width + col] = false;
+            }
+        }
+        let distance = clearance_field(&free, width, height, cs);
+        let clear: Vec<bool> = distance.iter().map(|&d| d >= 0.3).collect();
+        let mut options = SafeAreaOptions {
+            clearance: 0.3,
+            ceiling_height: 2.2,
+            max_radius: 4.0,
+            rays: 72,
+            shape: SafeAreaShape::Convex,
+        };
+
+        let convex = grow(&clear, width, height, cs, [2.0, 2.0], &options).unwrap();
+        options.shape = SafeAreaShape::Star;
+        let star = grow(&clear, width, height, cs, [2.0, 2.0], &options).unwrap();
+        for area in [&convex, &star] {
+            assert!(!in_polygon([2.85, 2.0], &area.boundary));
+            assert!(area.boundary.iter().all(|p| (0.0..=4.0).contains(&p[0])));
+            assert!(area.inscribed_radius > 0.3 && area.inscribed_radius < 0.6);
+        }
+        assert!(star.area > convex.area);
+        assert!(crate::floor_plan::signed_area(&convex.boundary) > 0.0);
+
+        // Inside the pillar's clearance there is nothing to grow from.
+        assert!(grow(&clear, width, height, cs, [2.6, 2.0], &options).is_none());
+    }
+}
@@ -0,0 +1,347 @@
+//! Parameter sensitivity report (`emit_sensitivity`).
+//!
+//! Users spend hours nudging `sdf_density_threshold` or `hole_fill_radius` on
+//! a scene where neither moves the floor, while the one setting that does goes
+//! untouched. [`analyze`] re-runs the ground field at a coarse surrogate
+//! resolution with each numeric setting below and above its value, and ranks
+//! the settings by how far the walkable area and the number of connected
+//! components move. The surrogate shares the oriented, filtered cloud of the
+//! bake, so a report costs a couple of dozen cheap field builds.
+
+use serde::Serialize;
+
+use crate::epsilon::Tolerance;
+use crate::journal::{self, JournalKind};
+use crate::MeshSettings;
+
+/// Default relative nudge applied below and above each setting.
+pub const DEFAULT_STEP: f64 = 0.25;
+
+/// Most field cells a surrogate run targets (`voxel_target`).
+const SURROGATE_CELLS: f64 = 2000.0;
+
+/// What one surrogate field build produced.
+#[derive(Clone, Copy)]
+pub struct Outcome {
+    /// Walkable area of the selected component, in square metres.
+    pub area: f64,
+    pub components: usize,
+    pub cell_size: f64,
+}
+
+/// Where a setting lives in [`MeshSettings`].
+#[derive(Clone, Copy)]
+enum Slot {
+    Length(fn(&mut MeshSettings) -> &mut Option<f64>),
+    Count(fn(&mut MeshSettings) -> &mut Option<usize>),
+}
+
+struct Parameter {
+    name: &'static str,
+    slot: Slot,
+    /// The value the field build uses when the setting is unset.
+    value: fn(&MeshSettings, &Tolerance) -> f64,
+    /// Smallest nudge, for settings whose value may be 0.
+    min_delta: f64,
+}
+
+fn ransac_thresh(settings: &MeshSettings, tolerance: &Tolerance) -> f64 {
+    settings
+        .ransac_thresh
+        .unwrap_or_else(|| tolerance.scaled(0.1))
+}
+
+fn floor_projection_epsilon(settings: &MeshSettings, tolerance: &Tolerance) -> f64 {
+    settings
+        .floor_projection_epsilon
+        .or(settings.height_projection_epsilon)
+        .unwrap_or_else(|| ransac_thresh(settings, tolerance).max(0.16))
+}
+
+/// The settings the ground field reads, with the defaults it falls back to.
+const PARAMETERS: [Parameter; 10] = [
+    Parameter {
+        name: "voxel_target",
+        slot: Slot::Length(|s| &mut s.voxel_target),
+        value: |s, _| s.voxel_target.unwrap_or(4000.0),
+        min_delta: 1.0,
+    },
+    Parameter {
+        name: "ransac_thresh",
+        slot: Slot::Length(|s| &mut s.ransac_thresh),
+        value: ransac_thresh,
+        min_delta: 0.0,
+    },
+    Parameter {
+        name: "floor_projection_epsilon",
+        slot: Slot::Length(|s| &mut s.floor_projection_epsilon),
+        value: floor_projection_epsilon,
+        min_delta: 0.0,
+    },
+    Parameter {
+        name: "obstacle_height_epsilon",
+        slot: Slot::Length(|s| &mut s.obstacle_height_epsilon),
+        value: |s, t| {
+            s.obstacle_height_epsilon
+                .unwrap_or_else(|| (floor_projection_epsilon(s, t) * 1.5).max(0.24))
+        },
+        min_delta: 0.0,
+    },
+    Parameter {
+        name: "min_floor_confidence",
+        slot: Slot::Length(|s| &mut s.min_floor_confidence),
+        value: |s, _| s.min_floor_confidence.unwrap_or(0.01),
+        min_delta: 0.0,
+    },
+    Parameter {
+        name: "sdf_density_threshold",
+        slot: Slot::Length(|s| &mut s.sdf_density_threshold),
+        value: |s, _| s.sdf_density_threshold.unwrap_or(0.08),
+        min_delta: 0.0,
+    },
+    Parameter {
+        name: "sdf_influence_radius_scale",
+        slot: Slot::Length(|s| &mut s.sdf_influence_radius_scale),
+        value: |s, _| s.sdf_influence_radius_scale.unwrap_or(2.5),
+        min_delta: 0.0,
+    },
+    Parameter {
+        name: "collision_carve_height",
+        slot: Slot::Length(|s| &mut s.collision_carve_height),
+        value: |s, _| s.collision_carve_height.unwrap_or(1.7),
+        min_delta: 0.0,
+    },
+    Parameter {
+        name: "agent_radius_erode",
+        slot: Slot::Length(|s| &mut s.agent_radius_erode),
+        value: |s, _| s.agent_radius_erode.unwrap_or(0.0),
+        min_delta: 0.1,
+    },
+    Parameter {
+        name: "hole_fill_radius",
+        slot: Slot::Count(|s| &mut s.hole_fill_radius),
+        value: |s, _| s.hole_fill_radius.unwrap_or(1) as f64,
+        min_delta: 1.0,
+    },
+];
+
+/// Result `sensitivity`.
+#[derive(Serialize)]
+pub struct SensitivityReport {
+    /// Relative nudge applied below and above each setting.
+    pub step: f64,
+    /// Cell size of the surrogate field, in metres.
+    pub surrogate_cell_size: f64,
+    /// Walkable area and component count of the surrogate at the settings as
+    /// passed.
+    pub baseline_area: f64,
+    pub baseline_components: usize,
+    /// Most sensitive first.
+    pub parameters: Vec<ParameterSensitivity>,
+}
+
+#[derive(Serialize)]
+pub struct ParameterSensitivity {
+    pub setting: &'static str,
+    /// The value the surrogate used: the setting, or its default when unset.
+    /// `voxel_target` is capped at the surrogate resolution, and
+    /// `cells_per_meter` and `voxel_target_u`/`voxel_target_v` are ignored.
+    pub value: f64,
+    /// The nudged values, below and above `value`.
+    pub lower: f64,
+    pub upper: f64,
+    /// Walkable area and component count at `lower` and `upper`; `null` when
+    /// no field could be built there.
+    pub area: [Option<f64>; 2],
+    pub components: [Option<usize>; 2],
+    /// Largest relative change of the area or the component count over the
+    /// two nudges, 1 when a nudge lost the field.
+    pub score: f64,
+}
+
+/// `sensitivity_step` must be a fraction in `(0, 1)`.
+pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+    match settings.sensitivity_step {
+        Some(step) if !(step.is_finite() && step > 0.0 && step < 1.0) => Err(format!(
+            "Invalid sensitivity_step: {}. Expected a fraction between 0 and 1.",
+            step
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Rank the settings in [`PARAMETERS`] by how far `evaluate` moves when each
+/// is nudged by `sensitivity_step`. `settings` is restored before returning;
+/// `None` when the surrogate cannot build a field at the settings as passed.
+pub fn analyze(
+    settings: &mut MeshSettings,
+    tolerance: &Tolerance,
+    mut evaluate: impl FnMut(&MeshSettings) -> Option<Outcome>,
+) -> Option<SensitivityReport> {
+    let step = settings.sensitivity_step.unwrap_or(DEFAULT_STEP);
+    let voxel_target = settings.voxel_target;
+    settings.voxel_target = Some(voxel_target.unwrap_or(4000.0).min(SURROGATE_CELLS));
+    // Explicit resolution overrides would bypass the surrogate cap.
+    let overrides = (
+        settings.voxel_target_u.take(),
+        settings.voxel_target_v.take(),
+        settings.cells_per_meter.take(),
+    );
+    // Surrogate runs make the same decisions as the bake; keep them out of its
+    // journal.
+    let mark = journal::position();
+    let report = evaluate(settings).map(|baseline| {
+        let mut parameters: Vec<ParameterSensitivity> = PARAMETERS
+            .iter()
+            .map(|parameter| {
+                nudge(
+                    parameter,
+                    settings,
+                    tolerance,
+                    step,
+                    &baseline,
+                    &mut evaluate,
+                )
+            })
+            .collect();
+        parameters.sort_by(|a, b| b.score.total_cmp(&a.score));
+        SensitivityReport {
+            step,
+            surrogate_cell_size: baseline.cell_size,
+            baseline_area: baseline.area,
+            baseline_components: baseline.components,
+            parameters,
+        }
+    });
+    journal::rewind(mark);
+    settings.voxel_target = voxel_target;
+    (
+        settings.voxel_target_u,
+        settings.voxel_target_v,
+        settings.cells_per_meter,
+    ) = overrides;
+
+    if let Some(report) = &report {
+        let top = &report.parameters[0];
+        journal::record(
+            "sensitivity",
+            JournalKind::Decision,
+            format!(
+                "Walkable area is most sensitive to {} (score {:.2})",
+                top.setting, top.score
+            ),
+            serde_json::json!({
+                "step": step,
+                "ranking": report
+                    .parameters
+                    .iter()
+                    .map(|p| serde_json::json!({ "setting": p.setting, "score": p.score }))
+                    .collect::<Vec<_>>(),
+            }),
+        );
+    }
+    report
+}
+
+/// Evaluate `parameter` below and above its value, then restore it.
+fn nudge(
+    parameter: &Parameter,
+    settings: &mut MeshSettings,
+    tolerance: &Tolerance,
+    step: f64,
+    baseline: &Outcome,
+    evaluate: &mut impl FnMut(&MeshSettings) -> Option<Outcome>,
+) -> ParameterSensitivity {
+    let value = (parameter.value)(settings, tolerance);
+    let mut delta = (value.abs() * step).max(parameter.min_delta);
+    if let Slot::Count(_) = parameter.slot {
+        delta = delta.round().max(1.0);
+    }
+    let (lower, upper) = ((value - delta).max(0.0), value + delta);
+
+    let outcomes = [lower, upper].map(|nudged| {
+        if nudged == value {
+            return Some(*baseline);
+        }
+        match parameter.slot {
+            Slot::Length(slot) => {
+                let saved = slot(settings).replace(nudged);
+                let outcome = evaluate(settings);
+                *slot(settings) = saved;
+                outcome
+            }
+            Slot::Count(slot) => {
+                let saved = slot(settings).replace(nudged as usize);
+                let outcome = evaluate(settings);
+                *slot(settings) = saved;
+                outcome
+            }
+        }
+    });
+    let cell_area = baseline.cell_size * baseline.cell_size;
+    let score = outcomes
+        .iter()
+        .map(|outcome| match outcome {
+            Some(outcome) => {
+                let area = (outcome.area - baseline.area).abs() / baseline.area.max(cell_area);
+                let components = outcome.components.abs_diff(baseline.components) as f64
+                    / baseline.components.max(1) as f64;
+                area.max(components)
+            }
+            None => 1.0,
+        })
+        .fold(0.0, f64::max);
+    ParameterSensitivity {
+        setting: parameter.name,
+        value,
+        lower,
+        upper,
+        area: outcomes.map(|o| o.map(|o| o.area)),
+        components: outcomes.map(|o| o.map(|o| o.components)),
+        score,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_are_ranked_and_restored() {
+        let mut settings: MeshSettings = serde_json::from_value(
+            serde_json::json!({ "mode": 0, "hole_fill_radius": 2, "cells_per_meter": 20 }),
+        )
+        .unwrap();
+        // Area follows min_floor_confidence; one extra hole-fill ring splits
+        // the floor in two.
+        let evaluate = |s: &MeshSettings| {
+            assert_eq!(s.cells_per_meter, None);
+            Some(Outcome {
+                area: 100.0 - 1000.0 * s.min_floor_confidence.unwrap_or(0.01),
+                components: if s.hole_fill_radius == Some(3) { 2 } else { 1 },
+                cell_size: 0.5,
+            })
+        };
+        let report = analyze(&mut settings, &Tolerance::fixed(), evaluate).unwrap();
+        assert_eq!(report.baseline_area, 90.0);
+        assert_eq!(report.parameters[0].setting, "hole_fill_radius");
+        assert_eq!(report.parameters[0].score, 1.0);
+        assert_eq!(report.parameters[0].components, [Some(1), Some(2)]);
+        let confidence = &report.parameters[1];
+        assert_eq!(confidence.setting, "min_floor_confidence");
+        assert!((confidence.score - 2.5 / 90.0).abs() < 1e-9);
+        assert!(report.parameters[2..].iter().all(|p| p.score == 0.0));
+        // agent_radius_erode is 0: only nudged upwards.
+        let erode = report
+            .parameters
+            .iter()
+            .find(|p| p.setting == "agent_radius_erode")
+            .unwrap();
+        assert_eq!((erode.lower, erode.upper), (0.0, 0.1));
+
+        assert_eq!(settings.voxel_target, None);
+        assert_eq!(settings.cells_per_meter, Some(20.0));
+        assert_eq!(settings.hole_fill_radius, Some(2));
+        assert_eq!(settings.min_floor_confidence, None);
+    }
+}
@@ -0,0 +1,902 @@
+//! Splat sessions: a lightweight scene graph of named splat sources.
+//!
+//! An editor that composes several captures of adjacent rooms keeps each capture
+//! as its own node with a world-from-node transform and an enable flag. Bakes run
+//! on the composed cloud (every enabled node, transformed into the shared
+//! `splatwalk_oriented` frame) through the same code paths as the byte-based
+//! entry points, so a session bake and a single-file bake of the same points are
+//! identical.
+//!
+//! Each source is parsed once, at `add_source`, with that call's ingest settings
+//! (`prune_floaters*`, `flip_y`, `coordinate_system`). Bake settings then only drive reconstruction;
+//! their ingest fields are ignored because the points are already parsed.
+//!
+//! `save_project` / `load_project` write and restore the session together with
+//! the host's settings and floor edits; see `project`.
+//!
+//! Session sources are shared with the registry of the context handle they
+//! were created under, so `reset_module` can free them while JS still holds
+//! the session handles; see [`release_all`].
+//!
+//! Sources can grow chunk by chunk (`append_chunk`) while a space is being
+//! scanned; every edit that can move the walkable surface is reported as a
+//! dirty region, and `bake_dirty_tiles` rebuilds only the navmesh tiles those
+//! reach. See `streaming`.
+
+use std::cell::{Ref, RefCell, RefMut};
+use std::rc::{Rc, Weak};
+
+use nalgebra::{Matrix3, Matrix4};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::context;
+use crate::floor_edit::Heightfield;
+use crate::format_report::{self, FormatReportOptions};
+use crate::project::{self, ChunkOrigin, IngestSettings, Project, ProjectSource, SourceOrigin};
+use crate::quality_report::{self, QualityReportOptions};
+use crate::splat::{self, PlyPassthrough, PointNormal};
+use crate::streaming::DirtyRegion;
+use crate::{MeshSettings, SplatwalkError};
+
+pub(crate) struct SceneNode {
+    name: String,
+    points: Vec<PointNormal>,
+    /// Splats in the source file (pruned splats included), used to offset
+    /// `PointNormal::index` into composed-cloud order.
+    splat_count: u32,
+    transform: Matrix4<f64>,
+    enabled: bool,
+    /// Header comments and non-vertex PLY elements, kept verbatim for the host.
+    passthrough: PlyPassthrough,
+    /// SH degree declared by the source file.
+    sh_degree: usize,
+    /// File size, fingerprint and ingest settings, for project files.
+    origin: SourceOrigin,
+}
+
+impl SceneNode {
+    /// The box this node's points cover in the shared frame, if any.
+    fn region(&self) -> Option<DirtyRegion> {
+        DirtyRegion::of(&self.name, &self.points, &self.transform)
+    }
+
+    /// [`SceneNode::region`] when the node takes part in bakes.
+    fn dirty_region(&self) -> Option<DirtyRegion> {
+        self.region().filter(|_| self.enabled)
+    }
+
+    /// Node points mapped through `transform`, with indices shifted by `offset`.
+    /// Normals use the inverse-transpose of the linear part; gaussian scales
+    /// follow the mean axis scale.
+    fn transformed_points(&self, offset: u32, out: &mut Vec<PointNormal>) {
+        if self.transform == Matrix4::identity() {
+            out.extend(self.points.iter().map(|p| PointNormal {
+                index: offset + p.index,
+                ..p.clone()
+            }));
+            return;
+        }
+        let linear: Matrix3<f64> = self.transform.fixed_view::<3, 3>(0, 0).into_owned();
+        let normal_matrix = linear
+            .try_inverse()
+            .map(|m| m.transpose())
+            .unwrap_or(linear);
+        let scale = linear.determinant().abs().cbrt();
+        out.extend(self.points.iter().map(|p| {
+            let normal = normal_matrix * p.normal;
+            PointNormal {
+                point: self.transform.transform_point(&p.point),
+                normal: normal.try_normalize(1e-12).unwrap_or(normal),
+                scale: p.scale * scale,
+                opacity: p.opacity,
+                color: p.color,
+                index: offset + p.index,
+            }
+        }));
+    }
+}
+
+/// `append_chunk` result.
+#[derive(Serialize)]
+struct ChunkUpdate {
+    /// Points parsed from the chunk.
+    point_count: usize,
+    /// Points in the source after the append.
+    total_points: usize,
+    /// Where the chunk landed, or `None` when the source is disabled or the
+    /// chunk kept no points.
+    dirty: Option<DirtyRegion>,
+}
+
+#[derive(Serialize)]
+struct SceneNodeInfo {
+    name: String,
+    enabled: bool,
+    point_count: usize,
+    transform: Vec<f64>,
+}
+
+pub(crate) type SessionNodes = RefCell<Vec<SceneNode>>;
+
+/// Drop the sources of every live session of the current context, for
+/// `reset_module`. The sessions
+/// stay usable and simply come back empty. Returns how many sessions were
+/// emptied and how many are still borrowed by a call that trapped (those keep
+/// their sources).
+pub(crate) fn release_all() -> (usize, usize) {
+    let context = context::current();
+    let Ok(mut live) = context.sessions.try_borrow_mut() else {
+        return (0, 1);
+    };
+    live.retain(|weak| weak.strong_count() > 0);
+    let (mut released, mut locked) = (0, 0);
+    for nodes in live.iter().filter_map(Weak::upgrade) {
+        match nodes.try_borrow_mut() {
+            Ok(mut nodes) => {
+                *nodes = Vec::new();
+                released += 1;
+            }
+            Err(_) => locked += 1,
+        }
+    }
+    (released, locked)
+}
+
+fn unknown_source(name: &str) -> JsValue {
+    SplatwalkError::InvalidSettings(format!("Unknown session source: {}", name)).into()
+}
+
+/// A set of named splat sources baked as one composed cloud.
+#[wasm_bindgen]
+pub struct SplatSession {
+    /// Shared with the creating context's session registry.
+    nodes: Rc<SessionNodes>,
+    /// Regions changed since the last `bake_dirty_tiles`.
+    dirty: Vec<DirtyRegion>,
+    /// `set_change_callback`.
+    on_change: Option<js_sys::Function>,
+}
+
+impl Default for SplatSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SplatSession {
+    fn nodes(&self) -> Ref<'_, Vec<SceneNode>> {
+        self.nodes.borrow()
+    }
+
+    fn nodes_mut(&mut self) -> RefMut<'_, Vec<SceneNode>> {
+        self.nodes.borrow_mut()
+    }
+
+    fn node(&self, name: &str) -> Result<Ref<'_, SceneNode>, JsValue> {
+        Ref::filter_map(self.nodes(), |nodes| nodes.iter().find(|n| n.name == name))
+            .map_err(|_| unknown_source(name))
+    }
+
+    fn node_mut(&mut self, name: &str) -> Result<RefMut<'_, SceneNode>, JsValue> {
+        RefMut::filter_map(self.nodes_mut(), |nodes| {
+            nodes.iter_mut().find(|n| n.name == name)
+        })
+        .map_err(|_| unknown_source(name))
+    }
+
+    /// Queue `region` for `bake_dirty_tiles` and report it to the change
+    /// callback.
+    fn mark(&mut self, region: Option<DirtyRegion>) {
+        let Some(region) = region else {
+            return;
+        };
+        if let Some(callback) = &self.on_change {
+            let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+            if let Ok(value) = region.serialize(&serializer) {
+                let _ = callback.call1(&JsValue::NULL, &value);
+            }
+        }
+        self.dirty.push(region);
+    }
+
+    /// Every enabled node's points in the shared frame, in insertion order. Point
+    /// indices address the concatenation of the enabled sources' files.
+    pub(crate) fn composed_points(&self) -> Vec<PointNormal> {
+        let nodes = self.nodes();
+        let total = nodes
+            .iter()
+            .filter(|n| n.enabled)
+            .map(|n| n.points.len())
+            .sum();
+        let mut out = Vec::with_capacity(total);
+        let mut offset = 0;
+        for node in nodes.iter().filter(|n| n.enabled) {
+            node.transformed_points(offset, &mut out);
+            offset += node.splat_count;
+        }
+        out
+    }
+
+    /// Parse and insert (or replace) source `name`. Returns its point count.
+    fn insert_source(
+        &mut self,
+        name: &str,
+        data: &[u8],
+        settings: &MeshSettings,
+    ) -> Result<usize, JsValue> {
+        let (points, splat_count) = crate::parse_splat_records(data, settings)?;
+        let passthrough = splat::parse_ply_passthrough(data).map_err(SplatwalkError::ParseError)?;
+        let count = points.len();
+        let node = SceneNode {
+            name: name.to_string(),
+            points,
+            splat_count,
+            transform: Matrix4::identity(),
+            enabled: true,
+            passthrough,
+            sh_degree: splat::source_sh_degree(data),
+            origin: SourceOrigin {
+                byte_length: data.len(),
+                hash: source_hash(data),
+                ingest: IngestSettings::from_settings(settings),
+                chunks: Vec::new(),
+            },
+        };
+        let added = node.dirty_region();
+        let replaced = {
+            let mut nodes = self.nodes_mut();
+            match nodes.iter_mut().find(|n| n.name == name) {
+                Some(existing) => std::mem::replace(existing, node).dirty_region(),
+                None => {
+                    nodes.push(node);
+                    None
+                }
+            }
+        };
+        self.mark(replaced);
+        self.mark(added);
+        Ok(count)
+    }
+
+    /// Parse `data` with the ingest settings of source `name` and append its
+    /// points. Returns the chunk's point count and the region it dirtied.
+    fn append(&mut self, name: &str, data: &[u8]) -> Result<(usize, Option<DirtyRegion>), JsValue> {
+        let settings = self
+            .node(name)?
+            .origin
+            .ingest
+            .to_settings()
+            .map_err(SplatwalkError::InvalidSettings)?;
+        let (mut points, splat_count) = crate::parse_splat_records(data, &settings)?;
+        let count = points.len();
+        let dirty = {
+            let mut node = self.node_mut(name)?;
+            let offset = node.splat_count;
+            for p in &mut points {
+                p.index += offset;
+            }
+            node.splat_count += splat_count;
+            node.sh_degree = node.sh_degree.max(splat::source_sh_degree(data));
+            node.origin.chunks.push(ChunkOrigin {
+                byte_length: data.len(),
+                hash: source_hash(data),
+            });
+            let dirty = node
+                .enabled
+                .then(|| DirtyRegion::of(name, &points, &node.transform))
+                .flatten();
+            node.points.extend(points);
+            dirty
+        };
+        self.mark(dirty.clone());
+        Ok((count, dirty))
+    }
+
+    /// Records across every enabled source's files: the index space of
+    /// [`composed_points`](Self::composed_points).
+    pub(crate) fn composed_splat_count(&self) -> u32 {
+        self.nodes()
+            .iter()
+            .filter(|n| n.enabled)
+            .map(|n| n.splat_count)
+            .sum()
+    }
+
+    fn source(&self) -> impl Fn(&MeshSettings) -> Result<Vec<PointNormal>, JsValue> + '_ {
+        let counted = self.counted_source();
+        move |settings| counted(settings).map(|(points, _)| points)
+    }
+
+    fn counted_source(
+        &self,
+    ) -> impl Fn(&MeshSettings) -> Result<(Vec<PointNormal>, u32), JsValue> + '_ {
+        move |_| {
+            let points = self.composed_points();
+            if points.is_empty() {
+                return Err(SplatwalkError::EmptyCloud(
+                    "Session has no enabled splat sources to bake.".to_string(),
+                )
+                .into());
+            }
+            Ok((points, self.composed_splat_count()))
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl SplatSession {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> SplatSession {
+        let nodes = Rc::new(RefCell::new(Vec::new()));
+        let context = context::current();
+        let mut live = context.sessions.borrow_mut();
+        live.retain(|weak| weak.strong_count() > 0);
+        live.push(Rc::downgrade(&nodes));
+        SplatSession {
+            nodes,
+            dirty: Vec::new(),
+            on_change: None,
+        }
+    }
+
+    /// Parse `data` (PLY / SPZ) as source `name`, replacing any source with the
+    /// same name. `settings` is optional and only its ingest fields are read.
+    /// Returns the parsed point count. New sources start enabled with an identity
+    /// transform.
+    pub fn add_source(
+        &mut self,
+        name: &str,
+        data: &[u8],
+        settings: JsValue,
+    ) -> Result<usize, JsValue> {
+        let settings = if settings.is_undefined() || settings.is_null() {
+            serde_json::from_value(serde_json::json!({ "mode": 0 }))
+                .map_err(|e| SplatwalkError::InvalidSettings(e.to_string()))?
+        } else {
+            crate::parse_settings(settings)?
+        };
+        self.insert_source(name, data, &settings)
+    }
+
+    /// Append the splats in `data` (PLY / SPZ) to source `name`, for scans
+    /// that arrive in chunks; a missing source is created as `add_source`
+    /// would, with `settings`' ingest fields. Later chunks are parsed with the
+    /// source's own ingest settings, so floater pruning sees one chunk at a
+    /// time. Returns `{ point_count, total_points, dirty }`, where `dirty` is
+    /// the `{ source, min, max }` box the chunk landed in (shared frame), or
+    /// `null` for a disabled source or an empty chunk.
+    pub fn append_chunk(
+        &mut self,
+        name: &str,
+        data: &[u8],
+        settings: JsValue,
+    ) -> Result<JsValue, JsValue> {
+        let (point_count, dirty) = if self.node(name).is_ok() {
+            self.append(name, data)?
+        } else {
+            let count = self.add_source(name, data, settings)?;
+            (count, self.node(name)?.dirty_region())
+        };
+        let update = ChunkUpdate {
+            point_count,
+            total_points: self.node(name)?.points.len(),
+            dirty,
+        };
+        let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+        Ok(update.serialize(&serializer)?)
+    }
+
+    /// Call `callback({ source, min, max })` with every region whose walkable
+    /// surface may have changed, as the edit happens: appended chunks, and
+    /// sources added, replaced, removed, moved or toggled. `undefined` clears
+    /// it.
+    pub fn set_change_callback(&mut self, callback: Option<js_sys::Function>) {
+        self.on_change = callback;
+    }
+
+    /// Regions changed since the last `bake_dirty_tiles`, in the order they
+    /// were reported.
+    pub fn dirty_regions(&self) -> Result<JsValue, JsValue> {
+        let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+        Ok(self.dirty.serialize(&serializer)?)
+    }
+
+    /// Rebuild the navmesh tiles the pending dirty regions reach and clear
+    /// them. `settings` is a mode 2 bake with `tile_size`; see `streaming`.
+    pub fn bake_dirty_tiles(&mut self, settings: JsValue) -> Result<JsValue, JsValue> {
+        let value = crate::bake_dirty_tiles_from(&self.source(), &self.dirty, settings)?;
+        self.dirty.clear();
+        Ok(value)
+    }
+
+    /// Remove source `name`. Returns whether it existed.
+    pub fn remove_source(&mut self, name: &str) -> bool {
+        let removed = {
+            let mut nodes = self.nodes_mut();
+            let position = nodes.iter().position(|n| n.name == name);
+            position.map(|i| nodes.remove(i))
+        };
+        let existed = removed.is_some();
+        self.mark(removed.and_then(|node| node.dirty_region()));
+        existed
+    }
+
+    /// Set the world-from-node transform of `name` as 16 column-major values
+    /// (a Babylon/three.js world matrix, applied verbatim).
+    pub fn set_transform(&mut self, name: &str, matrix: Vec<f64>) -> Result<(), JsValue> {
+        if matrix.len() != 16 || matrix.iter().any(|v| !v.is_finite()) {
+            return Err(SplatwalkError::InvalidSettings(
+                "set_transform expects 16 finite column-major values".to_string(),
+            )
+            .into());
+        }
+        let before = self.node(name)?.dirty_region();
+        self.node_mut(name)?.transform = Matrix4::from_column_slice(&matrix);
+        let after = self.node(name)?.dirty_region();
+        self.mark(before);
+        self.mark(after);
+        Ok(())
+    }
+
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> Result<(), JsValue> {
+        if self.node(name)?.enabled == enabled {
+            return Ok(());
+        }
+        let region = self.node(name)?.region();
+        self.node_mut(name)?.enabled = enabled;
+        self.mark(region);
+        Ok(())
+    }
+
+    /// Source names in insertion order.
+    pub fn source_names(&self) -> Vec<String> {
+        self.nodes().iter().map(|n| n.name.clone()).collect()
+    }
+
+    /// Per-source `{ name, enabled, point_count, transform }` records.
+    pub fn describe(&self) -> Result<JsValue, JsValue> {
+        let info: Vec<SceneNodeInfo> = self
+            .nodes()
+            .iter()
+            .map(|n| SceneNodeInfo {
+                name: n.name.clone(),
+                enabled: n.enabled,
+                point_count: n.points.len(),
+                transform: n.transform.as_slice().to_vec(),
+            })
+            .collect();
+        Ok(serde_wasm_bindgen::to_value(&info)?)
+    }
+
+    /// PLY passthrough for source `name`: `{ comments, obj_info, elements }`,
+    /// where each element is `{ name, count, properties, records }` and records
+    /// are plain `{ property: value }` objects (lists become arrays). Elements
+    /// other than `vertex` (`camera`, `chunk`, custom metadata) land here; SPZ
+    /// sources report an empty passthrough.
+    pub fn source_elements(&self, name: &str) -> Result<JsValue, JsValue> {
+        let node = self.node(name)?;
+        let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+        Ok(node.passthrough.serialize(&serializer)?)
+    }
+
+    /// Estimated encoded size and parse time of the composed cloud in every
+    /// supported storage format (PLY, SPZ, `.splat`, compressed PLY) plus a
+    /// recommendation for `options` (`priority`, `max_bytes`, `require_sh`,
+    /// `sample_size`). See `format_report`.
+    pub fn format_report(&self, options: JsValue) -> Result<JsValue, JsValue> {
+        let options: FormatReportOptions = if options.is_undefined() || options.is_null() {
+            FormatReportOptions::default()
+        } else {
+            serde_wasm_bindgen::from_value(options)
+                .map_err(|e| SplatwalkError::InvalidSettings(e.to_string()))?
+        };
+        let sh_degree = self
+            .nodes()
+            .iter()
+            .filter(|n| n.enabled)
+            .map(|n| n.sh_degree)
+            .max()
+            .unwrap_or(0);
+        let report =
+            format_report::build_format_report(&self.composed_points(), sh_degree, &options)
+                .map_err(SplatwalkError::EmptyCloud)?;
+        Ok(serde_wasm_bindgen::to_value(&report)?)
+    }
+
+    /// Scan-quality report of the composed cloud: a top-down density map,
+    /// coverage ratio, enclosed holes, a plane-residual noise estimate, and
+    /// floors captured twice. `options`: `cell_size`, `min_density`,
+    /// `min_hole_cells`, `min_alpha`, `noise_voxel_size`, `doubled_min_offset`,
+    /// `doubled_max_offset`, `doubled_min_cells`. See `quality_report`.
+    pub fn quality_report(&self, options: JsValue) -> Result<JsValue, JsValue> {
+        let options: QualityReportOptions = if options.is_undefined() || options.is_null() {
+            QualityReportOptions::default()
+        } else {
+            serde_wasm_bindgen::from_value(options)
+                .map_err(|e| SplatwalkError::InvalidSettings(e.to_string()))?
+        };
+        let report = quality_report::build_quality_report(&self.composed_points(), &options)
+            .map_err(SplatwalkError::EmptyCloud)?;
+        Ok(serde_wasm_bindgen::to_value(&report)?)
+    }
+
+    /// Save the session plus the host's editing state as one project blob.
+    /// `state` is optional: `{ settings?, heightfields?: { [name]: Heightfield },
+    /// host_data? }`. Sources are recorded by name, transform, enable flag,
+    /// ingest settings and content fingerprint; their bytes are not embedded.
+    pub fn save_project(&self, state: JsValue) -> Result<Vec<u8>, JsValue> {
+        let state: ProjectState = if state.is_undefined() || state.is_null() {
+            ProjectState::default()
+        } else {
+            serde_wasm_bindgen::from_value(state)
+                .map_err(|e| SplatwalkError::InvalidSettings(e.to_string()))?
+        };
+        if !state.settings.is_null() {
+            let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+            crate::parse_settings(state.settings.serialize(&serializer)?)?;
+        }
+        for field in state.heightfields.values() {
+            field.validate()?;
+        }
+        let project = Project {
+            version: project::PROJECT_VERSION,
+            sources: self
+                .nodes()
+                .iter()
+                .map(|n| ProjectSource {
+                    name: n.name.clone(),
+                    enabled: n.enabled,
+                    transform: n.transform.as_slice().to_vec(),
+                    origin: n.origin.clone(),
+                })
+                .collect(),
+            settings: state.settings,
+            heightfields: state.heightfields,
+            host_data: state.host_data,
+        };
+        Ok(project::encode(&project).map_err(SplatwalkError::InvalidSettings)?)
+    }
+
+    /// Restore a project saved with `save_project`, replacing every source.
+    /// `sources` maps each saved source name to its file bytes (`{ [name]:
+    /// Uint8Array }`; a source grown with `append_chunk` takes `[file,
+    /// ...chunks]`); each is re-parsed with its saved ingest settings and
+    /// must match the saved size and fingerprint. Returns the project as
+    /// `read_project` does, so the host can reapply settings and reopen
+    /// `FloorEditor`s from `heightfields`.
+    pub fn load_project(&mut self, blob: &[u8], sources: JsValue) -> Result<JsValue, JsValue> {
+        let project = project::decode(blob).map_err(SplatwalkError::InvalidSettings)?;
+        let mut restored = SplatSession::new();
+        for source in &project.sources {
+            let supplied = js_sys::Reflect::get(&sources, &JsValue::from_str(&source.name))
+                .ok()
+                .filter(|v| !v.is_undefined() && !v.is_null())
+                .ok_or_else(|| {
+                    SplatwalkError::InvalidSettings(format!(
+                        "Project source {:?} was not supplied",
+                        source.name
+                    ))
+                })?;
+            // A streamed source comes as `[file, ...chunks]`.
+            let files: Vec<Vec<u8>> = if js_sys::Array::is_array(&supplied) {
+                js_sys::Array::from(&supplied)
+                    .iter()
+                    .map(|v| js_sys::Uint8Array::new(&v).to_vec())
+                    .collect()
+            } else {
+                vec![js_sys::Uint8Array::new(&supplied).to_vec()]
+            };
+            if files.len() != 1 + source.origin.chunks.len() {
+                return Err(SplatwalkError::InvalidSettings(format!(
+                    "Project source {:?} was saved with {} appended chunk(s); got {}",
+                    source.name,
+                    source.origin.chunks.len(),
+                    files.len().saturating_sub(1)
+                ))
+                .into());
+            }
+            let saved = std::iter::once((source.origin.byte_length, &source.origin.hash)).chain(
+                source
+                    .origin
+                    .chunks
+                    .iter()
+                    .map(|chunk| (chunk.byte_length, &chunk.hash)),
+            );
+            for (bytes, (byte_length, saved_hash)) in files.iter().zip(saved) {
+                let hash = source_hash(bytes);
+                if bytes.len() != byte_length || hash != *saved_hash {
+                    return Err(SplatwalkError::InvalidSettings(format!(
+                        "Project source {:?} does not match the saved file ({} bytes, hash {}; got {} bytes, hash {})",
+                        source.name, byte_length, saved_hash, bytes.len(), hash
+                    ))
+                    .into());
+                }
+            }
+            let settings = source
+                .origin
+                .ingest
+                .to_settings()
+                .map_err(SplatwalkError::InvalidSettings)?;
+            restored.insert_source(&source.name, &files[0], &settings)?;
+            for chunk in &files[1..] {
+                restored.append(&source.name, chunk)?;
+            }
+            restored.set_transform(&source.name, source.transform.clone())?;
+            restored.set_enabled(&source.name, source.enabled)?;
+        }
+        // Everything the old and the restored sources cover may have changed.
+        let previous: Vec<DirtyRegion> = self
+            .nodes()
+            .iter()
+            .filter_map(SceneNode::dirty_region)
+            .collect();
+        restored.dirty = std::mem::take(&mut self.dirty);
+        restored.on_change = self.on_change.take();
+        *self = restored;
+        let current: Vec<DirtyRegion> = self
+            .nodes()
+            .iter()
+            .filter_map(SceneNode::dirty_region)
+            .collect();
+        for region in previous.into_iter().chain(current) {
+            self.mark(Some(region));
+        }
+        project_to_value(&project)
+    }
+
+    /// Point count of the composed cloud (enabled sources only).
+    pub fn point_count(&self) -> usize {
+        self.nodes()
+            .iter()
+            .filter(|n| n.enabled)
+            .map(|n| n.points.len())
+            .sum()
+    }
+
+    pub fn get_splat_bounds(&self, settings: JsValue) -> Result<JsValue, JsValue> {
+        crate::get_splat_bounds_from(&self.source(), settings)
+    }
+
+    pub fn suggest_region(&self, settings: JsValue) -> Result<JsValue, JsValue> {
+        crate::suggest_region_from(&self.source(), settings)
+    }
+
+    pub fn convert_splat_to_mesh(&self, settings: JsValue) -> Result<JsValue, JsValue> {
+        crate::convert_splat_to_mesh_from(&self.source(), settings)
+    }
+
+    pub fn convert_splat_to_navmesh_basis(&self, settings: JsValue) -> Result<JsValue, JsValue> {
+        crate::convert_splat_to_navmesh_basis_from(&self.source(), settings)
+    }
+
+    pub fn build_collision_voxel_boundary(&self, settings: JsValue) -> Result<JsValue, JsValue> {
+        crate::build_collision_voxel_boundary_from(&self.source(), settings)
+    }
+
+    pub fn build_walkable_ground_field(&self, settings: JsValue) -> Result<JsValue, JsValue> {
+        crate::build_walkable_ground_field_from(&self.source(), settings)
+    }
+
+    pub fn build_room_floor_mesh(&self, settings: JsValue) -> Result<JsValue, JsValue> {
+        crate::build_room_floor_mesh_from(&self.counted_source(), settings)
+    }
+
+    pub fn build_floor_plan(&self, settings: JsValue) -> Result<JsValue, JsValue> {
+        crate::build_floor_plan_from(&self.source(), settings)
+    }
+
+    pub fn build_safe_area(&self, settings: JsValue) -> Result<JsValue, JsValue> {
+        crate::build_safe_area_from(&self.source(), settings)
+    }
+
+    pub fn find_chokepoints(&self, settings: JsValue) -> Result<JsValue, JsValue> {
+        crate::find_chokepoints_from(&self.source(), settings)
+    }
+
+    pub fn extract_floor_polygons(&self, settings: JsValue) -> Result<JsValue, JsValue> {
+        crate::extract_floor_polygons_from(&self.source(), settings)
+    }
+}
+
+/// Host editing state passed to `SplatSession::save_project`.
+#[derive(Default, Deserialize)]
+struct ProjectState {
+    #[serde(default)]
+    settings: serde_json::Value,
+    #[serde(default)]
+    heightfields: std::collections::BTreeMap<String, Heightfield>,
+    #[serde(default)]
+    host_data: serde_json::Value,
+}
+
+/// Content fingerprint of a source file as 16 hex digits.
+fn source_hash(data: &[u8]) -> String {
+    format!("{:016x}", crate::fingerprint(data))
+}
+
+/// A decoded project as a plain JS object (settings and host data as objects,
+/// not `Map`s).
+pub(crate) fn project_to_value(project: &Project) -> Result<JsValue, JsValue> {
+    let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+    Ok(project.serialize(&serializer)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{Point3, Vector3};
+
+    fn node(transform: Matrix4<f64>) -> SceneNode {
+        SceneNode {
+            name: "room".to_string(),
+            points: vec![PointNormal {
+                point: Point3::new(1.0, 0.0, 0.0),
+                normal: Vector3::new(0.0, 1.0, 0.0),
+                scale: Vector3::new(0.1, 0.1, 0.1),
+                opacity: 1.0,
+                color: [0.5; 3],
+                index: 0,
+            }],
+            splat_count: 1,
+            transform,
+            enabled: true,
+            passthrough: PlyPassthrough::default(),
+            sh_degree: 0,
+            origin: SourceOrigin::default(),
+        }
+    }
+
+    #[test]
+    fn composed_cloud_applies_node_transforms_and_skips_disabled() {
+        let moved =
+            Matrix4::new_translation(&Vector3::new(5.0, 0.0, 0.0)) * Matrix4::new_scaling(2.0);
+        let mut session = SplatSession::new();
+        session.nodes_mut().push(node(moved));
+        let mut hidden = node(Matrix4::identity());
+        hidden.enabled = false;
+        session.nodes_mut().push(hidden);
+        session.nodes_mut().push(node(Matrix4::identity()));
+
+        let points = session.composed_points();
+        assert_eq!(points.len(), 2);
+        assert_eq!(session.point_count(), 2);
+        assert_eq!(points[1].index, 1);
+        assert!((points[0].point.x - 7.0).abs() < 1e-12);
+        assert!((points[0].scale.x - 0.2).abs() < 1e-12);
+        assert!((points[0].normal.y - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn release_all_empties_live_sessions() {
+        let mut session = SplatSession::new();
+        session.nodes_mut().push(node(Matrix4::identity()));
+        drop(SplatSession::new());
+        assert_eq!(release_all(), (1, 0));
+        assert_eq!(session.point_count(), 0);
+        session.nodes_mut().push(node(Matrix4::identity()));
+        assert_eq!(session.point_count(), 1);
+    }
+
+    /// Upward-facing floor splats on a 5 cm grid, `x` from `x0` over 2 m and
+    /// `z` over 4 m, with file indices `stride` apart.
+    fn floor(x0: f64, stride: u32) -> Vec<PointNormal> {
+        (0..40 * 80)
+            .map(|k| PointNormal {
+                index: k * stride,
+                ..splat::test_splat(
+                    [x0 + (k / 80) as f64 * 0.05, 0.0, (k % 80) as f64 * 0.05],
+                    [0.0, 1.0, 0.0],
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn backprojection_marks_floor_splats_in_composed_order() {
+        // The first source kept every other splat of its file, the second
+        // adds a ceiling above its floor.
+        let mut ceiling: Vec<PointNormal> = (0..50)
+            .map(|k| PointNormal {
+                index: 3200 + k,
+                ..splat::test_splat([3.0, 2.5, k as f64 * 0.05], [0.0, -1.0, 0.0])
+            })
+            .collect();
+        let mut second = floor(2.0, 1);
+        second.append(&mut ceiling);
+        let mut session = SplatSession::new();
+        session.nodes_mut().push(SceneNode {
+            points: floor(0.0, 2),
+            splat_count: 6400,
+            ..node(Matrix4::identity())
+        });
+        session.nodes_mut().push(SceneNode {
+            points: second,
+            splat_count: 3250,
+            ..node(Matrix4::identity())
+        });
+
+        let mut settings = crate::fast_nav_preset_json();
+        settings["emit_backprojection"] = true.into();
+        let settings: MeshSettings = serde_json::from_value(settings).unwrap();
+        let points = session.composed_points();
+        let count = session.composed_splat_count();
+        let build = crate::mesh::extract_room_floor(&points, count, &settings, 1.0, "test")
+            .unwrap_or_else(|e| panic!("{}", e.message));
+        let map = build.backprojection.unwrap();
+        assert_eq!(map.splat_count, 9650);
+        assert_eq!(map.component.len(), 9650);
+        let bit = |i: usize| map.contributed[i / 8] >> (i % 8) & 1 == 1;
+        let marked = (0..9650).filter(|&i| bit(i)).count();
+        assert_eq!(marked, map.contributed_count);
+
+        // Interior floor splats of both sources land on the emitted floor.
+        let interior = |k: usize| (10..70).contains(&(k % 80)) && (10..30).contains(&(k / 80));
+        for k in (0..3200).filter(|&k| interior(k)) {
+            let (first, second) = (2 * k, 6400 + k);
+            assert!(bit(first) && bit(second), "splat {} / {}", first, second);
+            assert_eq!(map.component[first], map.selected_component);
+            assert_eq!(map.component[second], map.selected_component);
+        }
+        // Pruned splats of the first file and the ceiling never do.
+        assert!((0..3200).all(|k| !bit(2 * k + 1) && map.component[2 * k + 1] == -1));
+        assert!((9600..9650).all(|i| !bit(i) && map.component[i] == -1));
+    }
+
+    /// An ASCII PLY of `points`, upward-facing (`rot` turns +z onto +y).
+    fn ascii_ply(points: &[[f64; 3]]) -> Vec<u8> {
+        let mut ply = format!(
+            "ply\nformat ascii 1.0\nelement vertex {}\n\
+property float x\nproperty float y\nproperty float z\nproperty float opacity\n\
+property float scale_0\nproperty float scale_1\nproperty float scale_2\n\
+property float rot_0\nproperty float rot_1\nproperty float rot_2\nproperty float rot_3\n\
+end_header\n",
+            points.len()
+        );
+        for [x, y, z] in points {
+            ply += &format!("{} {} {} 4 0.01 0.01 0.01 0.7071 -0.7071 0 0\n", x, y, z);
+        }
+        ply.into_bytes()
+    }
+
+    #[test]
+    fn pruned_trailing_splats_still_count_toward_offsets_and_the_mask() {
+        // A 2 m x 4 m floor whose last record is a floater far above it.
+        let mut records: Vec<[f64; 3]> = (0..40 * 80)
+            .map(|k| [(k / 80) as f64 * 0.05, 0.0, (k % 80) as f64 * 0.05])
+            .collect();
+        records.push([1.0, 40.0, 2.0]);
+        let data = ascii_ply(&records);
+
+        let mut settings = crate::fast_nav_preset_json();
+        settings["emit_backprojection"] = true.into();
+        let settings: MeshSettings = serde_json::from_value(settings).unwrap();
+        let mut session = SplatSession::new();
+        session.insert_source("a", &data, &settings).unwrap();
+        session.insert_source("b", &data, &settings).unwrap();
+
+        // Both files prune the same records, floater included.
+        let points = session.composed_points();
+        let first_b = session.nodes()[0].points.len();
+        assert_eq!(points.len(), 2 * first_b);
+        assert!(points[..first_b].iter().all(|p| p.index < 3200));
+        assert_eq!(points[first_b].index, 3201 + points[0].index);
+        assert_eq!(session.composed_splat_count(), 6402);
+
+        let build = crate::mesh::extract_room_floor(
+            &points,
+            session.composed_splat_count(),
+            &settings,
+            1.0,
+            "test",
+        )
+        .unwrap_or_else(|e| panic!("{}", e.message));
+        let map = build.backprojection.unwrap();
+        assert_eq!(map.splat_count, 6402);
+        assert_eq!(map.component.len(), 6402);
+        // The floaters never land; an interior floor record of `b` does.
+        let interior = 10 * 80 + 40;
+        assert_eq!((map.component[3200], map.component[6401]), (-1, -1));
+        assert_eq!(map.component[3201 + interior], map.selected_component);
+    }
+}
@@ -1,10 +1,45 @@
 use nalgebra::{Point3, Quaternion, UnitQuaternion, Vector3};
 use ply_rs::parser::Parser;
-use ply_rs::ply::{Property, PropertyAccess};
+use ply_rs::ply::{DefaultElement, Property, PropertyAccess, PropertyType, ScalarType};
+use serde::Serialize;
+use flate2::read::GzDecoder;
+use std::borrow::Cow;
 use std::collections::HashMap;
-use std::io::Cursor;
+use std::io::{Cursor, Read};
 use web_sys::console;
 
+use crate::events::{self, Event};
+
+pub mod compressed_ply;
+pub mod ksplat;
+pub mod spz;
+
+/// One PLY property declaration as written in the header.
+#[derive(Serialize, Clone)]
+pub struct PlyPropertyInfo {
+    pub name: String,
+    /// Scalar type (`"float"`, `"uchar"`, ...) or `"list<count,item>"`.
+    pub data_type: String,
+}
+
+/// A non-vertex PLY element (`camera`, `chunk`, exporter metadata, ...) kept as
+/// generic key/value records so host apps don't need a second PLY parser.
+#[derive(Serialize, Clone)]
+pub struct PlyElementRecords {
+    pub name: String,
+    pub count: usize,
+    pub properties: Vec<PlyPropertyInfo>,
+    pub records: Vec<serde_json::Map<String, serde_json::Value>>,
+}
+
+/// Header comments plus every non-vertex element of a PLY file.
+#[derive(Serialize, Clone, Default)]
+pub struct PlyPassthrough {
+    pub comments: Vec<String>,
+    pub obj_info: Vec<String>,
+    pub elements: Vec<PlyElementRecords>,
+}
+
 /// First spherical-harmonic basis constant (`Y_0^0`). Shared with the SOG
 /// encoder so the DC term round-trips through Babylon's decoder.
 pub const SH_C0: f32 = 0.282_094_79;
@@ -14,6 +49,10 @@ pub const SH_C0: f32 = 0.282_094_79;
 /// back in. We undo it on read to recover the true SH0 coefficient.
 const SPZ_COLOR_SCALE: f32 = 0.15;
 
+/// The newest `.spz` container revision handed to `spz_rs`; later ones go
+/// straight to the pure-Rust [`spz`] reader.
+const SPZ_RS_MAX_VERSION: u32 = 2;
+
 #[derive(Debug, Clone)]
 pub struct Splat {
     pub x: f32,
@@ -27,6 +66,7 @@ pub struct Splat {
     pub scale_1: f32,
     pub scale_2: f32,
     pub opacity: f32,
+    pub f_dc: [f32; 3],
 }
 
 impl PropertyAccess for Splat {
@@ -43,35 +83,177 @@ impl PropertyAccess for Splat {
             scale_1: 0.1,
             scale_2: 0.1,
             opacity: 1.0,
+            f_dc: [0.0; 3],
         }
     }
 
     fn set_property(&mut self, key: String, property: Property) {
-        match (key.as_ref(), property) {
-            ("x", Property::Float(v)) => self.x = v,
-            ("y", Property::Float(v)) => self.y = v,
-            ("z", Property::Float(v)) => self.z = v,
-            ("rot_0", Property::Float(v)) => self.rot_0 = v,
-            ("rot_1", Property::Float(v)) => self.rot_1 = v,
-            ("rot_2", Property::Float(v)) => self.rot_2 = v,
-            ("rot_3", Property::Float(v)) => self.rot_3 = v,
-            ("scale_0", Property::Float(v)) => self.scale_0 = v,
-            ("scale_1", Property::Float(v)) => self.scale_1 = v,
-            ("scale_2", Property::Float(v)) => self.scale_2 = v,
-            ("opacity", Property::Float(v))
-            | ("alpha", Property::Float(v))
-            | ("scalar_opacity", Property::Float(v)) => self.opacity = v,
+        let Some(v) = scalar_as_f32(&property) else {
+            return;
+        };
+        match key.as_ref() {
+            "x" => self.x = v,
+            "y" => self.y = v,
+            "z" => self.z = v,
+            "rot_0" => self.rot_0 = v,
+            "rot_1" => self.rot_1 = v,
+            "rot_2" => self.rot_2 = v,
+            "rot_3" => self.rot_3 = v,
+            "scale_0" => self.scale_0 = v,
+            "scale_1" => self.scale_1 = v,
+            "scale_2" => self.scale_2 = v,
+            "opacity" | "alpha" | "scalar_opacity" => self.opacity = v,
+            "f_dc_0" => self.f_dc[0] = v,
+            "f_dc_1" => self.f_dc[1] = v,
+            "f_dc_2" => self.f_dc[2] = v,
             _ => {}
         }
     }
 }
 
+/// Any scalar PLY property widened/narrowed to `f32`. Exporters disagree on
+/// types (`double` positions, `int`/`uint` quantized fields), so the readers
+/// accept every scalar width instead of silently dropping non-`float` values.
+/// List properties yield `None`.
+fn scalar_as_f32(property: &Property) -> Option<f32> {
+    Some(match *property {
+        Property::Char(v) => v as f32,
+        Property::UChar(v) => v as f32,
+        Property::Short(v) => v as f32,
+        Property::UShort(v) => v as f32,
+        Property::Int(v) => v as f32,
+        Property::UInt(v) => v as f32,
+        Property::Float(v) => v,
+        Property::Double(v) => v as f32,
+        _ => return None,
+    })
+}
+
+/// Structural problems in a PLY vertex layout, reported instead of parsing
+/// garbage (ply-rs otherwise defaults missing/unsupported fields silently).
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlyLayoutError {
+    MissingVertexElement,
+    /// A required position property (`x`/`y`/`z`) is absent.
+    MissingProperty(String),
+    /// A Gaussian attribute was declared as a list instead of a scalar.
+    ListProperty(String),
+    /// The payload could not be decoded (truncated data, bad tokens, ...).
+    Payload(String),
+}
+
+impl std::fmt::Display for PlyLayoutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlyLayoutError::MissingVertexElement => write!(f, "PLY file missing 'vertex' element"),
+            PlyLayoutError::MissingProperty(name) => {
+                write!(f, "PLY vertex element missing required property '{}'", name)
+            }
+            PlyLayoutError::ListProperty(name) => write!(
+                f,
+                "PLY vertex property '{}' is a list; Gaussian attributes must be scalars",
+                name
+            ),
+            PlyLayoutError::Payload(msg) => write!(f, "Failed to read PLY payload: {}", msg),
+        }
+    }
+}
+
+impl From<PlyLayoutError> for String {
+    fn from(e: PlyLayoutError) -> String {
+        e.to_string()
+    }
+}
+
+/// Gaussian attributes the readers consume; these must be scalar when present.
+fn is_gaussian_attribute(name: &str) -> bool {
+    matches!(
+        name,
+        "x" | "y"
+            | "z"
+            | "rot_0"
+            | "rot_1"
+            | "rot_2"
+            | "rot_3"
+            | "scale_0"
+            | "scale_1"
+            | "scale_2"
+            | "opacity"
+            | "alpha"
+            | "scalar_opacity"
+    ) || name.starts_with("f_dc_")
+        || name.starts_with("f_rest_")
+}
+
+/// Check the vertex element before reading it: positions must exist and every
+/// consumed attribute must be a scalar (of any width). Unrelated list properties
+/// (e.g. per-vertex face refs some exporters add) are tolerated and skipped.
+fn validate_vertex_layout(element: &ply_rs::ply::ElementDef) -> Result<(), PlyLayoutError> {
+    for axis in ["x", "y", "z"] {
+        if !element.properties.contains_key(axis) {
+            return Err(PlyLayoutError::MissingProperty(axis.to_string()));
+        }
+    }
+    for (name, def) in &element.properties {
+        if matches!(def.data_type, PropertyType::List(_, _)) && is_gaussian_attribute(name) {
+            return Err(PlyLayoutError::ListProperty(name.clone()));
+        }
+    }
+    Ok(())
+}
+
+/// Read the `vertex` payload. Payloads are laid out in header order, so
+/// elements ahead of `vertex` (e.g. a `chunk` table) are consumed first.
+fn read_vertex_payload<E: PropertyAccess>(
+    parser: &Parser<E>,
+    cursor: &mut Cursor<&[u8]>,
+    header: &ply_rs::ply::Header,
+) -> Result<Vec<E>, PlyLayoutError> {
+    let skip_parser = Parser::<DefaultElement>::new();
+    for (key, element) in &header.elements {
+        if key == "vertex" {
+            return parser
+                .read_payload_for_element(cursor, element, header)
+                .map_err(|e| PlyLayoutError::Payload(e.to_string()));
+        }
+        skip_parser
+            .read_payload_for_element(cursor, element, header)
+            .map_err(|e| PlyLayoutError::Payload(e.to_string()))?;
+    }
+    Err(PlyLayoutError::MissingVertexElement)
+}
+
 #[derive(Clone)]
 pub struct PointNormal {
     pub point: Point3<f64>,
     pub normal: Vector3<f64>,
     pub scale: Vector3<f64>,
     pub opacity: f64,
+    /// Base RGB in `[0, 1]` from the SH DC term (see [`dc_to_rgb`]); mid-grey
+    /// when the source carries no color.
+    pub color: [f32; 3],
+    /// Position of this splat in its source file, assigned at parse time and kept
+    /// through pruning so per-splat outputs map back onto the renderer's order.
+    pub index: u32,
+}
+
+/// A small, opaque, mid-grey splat at `point` for unit tests.
+#[cfg(test)]
+pub(crate) fn test_splat(point: [f64; 3], normal: [f64; 3]) -> PointNormal {
+    PointNormal {
+        point: Point3::from(point),
+        normal: Vector3::from(normal),
+        scale: Vector3::repeat(0.01),
+        opacity: 4.0,
+        color: [0.5; 3],
+        index: 0,
+    }
+}
+
+/// Base RGB of a splat from its SH DC coefficients (`0.5 + SH_C0 * f_dc`),
+/// clamped to `[0, 1]`. View-dependent bands are ignored.
+pub fn dc_to_rgb(dc: [f32; 3]) -> [f32; 3] {
+    dc.map(|c| (0.5 + SH_C0 * c).clamp(0.0, 1.0))
 }
 
 /// Outcome of a {@link prune_floaters} pass.
@@ -276,58 +458,74 @@ pub fn prune_floaters(
     }
 }
 
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Inflate gzip-wrapped input. `.spz` files as written by Scaniverse and the
+/// reference exporter are a gzip stream around the `NGSP` payload; anything
+/// without the gzip magic is returned untouched.
+fn inflate_gzip(data: &[u8]) -> Result<Cow<'_, [u8]>, String> {
+    if !data.starts_with(&GZIP_MAGIC) {
+        return Ok(Cow::Borrowed(data));
+    }
+    let mut inflated = Vec::with_capacity(data.len() * 4);
+    GzDecoder::new(data)
+        .read_to_end(&mut inflated)
+        .map_err(|e| format!("Failed to inflate gzip input: {}", e))?;
+    events::emit(Event::GzipInflated {
+        compressed_bytes: data.len(),
+        inflated_bytes: inflated.len(),
+    });
+    Ok(Cow::Owned(inflated))
+}
+
+/// True when `data` starts like one of the formats [`parse_ply`] reads: PLY,
+/// SPZ (raw or gzip-wrapped), `.ksplat` or antimatter15 `.splat`.
+pub fn is_known_format(data: &[u8]) -> bool {
+    data.starts_with(b"ply")
+        || data.starts_with(b"NGSP")
+        || data.starts_with(&GZIP_MAGIC)
+        || ksplat::is_ksplat(data)
+        || is_splat_buffer(data)
+}
+
 pub fn parse_ply(data: &[u8]) -> Result<Vec<PointNormal>, String> {
+    let inflated = inflate_gzip(data)?;
+    let data: &[u8] = &inflated;
+
     // Check for "NGSP" magic number (Niantic SPZ format)
     if data.len() >= 4 && &data[0..4] == b"NGSP" {
-        console::log_1(&"Detected NGSP/SPZ format. Parsing with spz_rs...".into());
-        let cursor = std::io::Cursor::new(data);
-        match spz_rs::load_packed_gaussians_from_decompressed_buffer(cursor) {
-            Ok(packed) => {
-                let num_points = packed.num_points;
-                console::log_1(&format!("Parsed {} points from SPZ", num_points).into());
-
-                let mut points = Vec::with_capacity(num_points);
-
-                for i in 0..num_points {
-                    let g = packed.unpack(i);
-                    let pos = Point3::new(
-                        g.position[0] as f64,
-                        g.position[1] as f64,
-                        g.position[2] as f64,
-                    );
-                    let scale =
-                        Vector3::new(g.scale[0] as f64, g.scale[1] as f64, g.scale[2] as f64);
-                    let opacity = g.alpha as f64;
-
-                    // rotation is [w, x, y, z]
-                    let r0 = g.rotation[0] as f64; // w
-                    let r1 = g.rotation[1] as f64; // x
-                    let r2 = g.rotation[2] as f64; // y
-                    let r3 = g.rotation[3] as f64; // z
-
-                    // Rotate Z-axis (0, 0, 1) by this quaternion
-                    let nx = 2.0 * (r1 * r3 + r2 * r0);
-                    let ny = 2.0 * (r2 * r3 - r1 * r0);
-                    let nz = 1.0 - 2.0 * (r1 * r1 + r2 * r2);
-
-                    let normal = Vector3::new(nx, ny, nz);
-
-                    points.push(PointNormal {
-                        point: pos,
-                        normal,
-                        scale,
-                        opacity,
-                    });
-                }
-
-                return Ok(points);
+        events::emit(Event::FormatDetected { format: "spz" });
+        return match decode_spz(data, false) {
+            Ok(cloud) => {
+                events::emit(Event::SplatsDecoded {
+                    count: cloud.len(),
+                    format: "spz",
+                    sh_degree: None,
+                });
+                Ok(points_from_cloud(&cloud))
             }
-            Err(e) => {
-                let err_msg = format!("Failed to parse SPZ: {:?}", e);
+            Err(err_msg) => {
                 console::log_1(&err_msg.clone().into());
-                return Err(err_msg);
+                Err(err_msg)
             }
-        }
+        };
+    }
+
+    // `.ksplat` before `.splat`: neither has magic, and a ksplat header can pass
+    // the `.splat` record check.
+    if ksplat::is_ksplat(data) {
+        let points = ksplat::parse_ksplat(data)?;
+        events::emit(Event::SplatsDecoded {
+            count: points.len(),
+            format: "ksplat",
+            sh_degree: None,
+        });
+        return Ok(points);
+    }
+
+    if is_splat_buffer(data) {
+        events::emit(Event::FormatDetected { format: "splat" });
+        return parse_splat_buffer(data).map(|cloud| points_from_cloud(&cloud));
     }
 
     // Default to PLY parser
@@ -335,20 +533,21 @@ pub fn parse_ply(data: &[u8]) -> Result<Vec<PointNormal>, String> {
     let parser = Parser::<Splat>::new();
 
     let header = parser.read_header(&mut cursor).map_err(|e| e.to_string())?;
-
-    // Check if vertex element exists
-    if !header.elements.contains_key("vertex") {
-        return Err("PLY file missing 'vertex' element".to_string());
+    if compressed_ply::is_compressed(&header) {
+        events::emit(Event::FormatDetected {
+            format: "compressed_ply",
+        });
+        return compressed_ply::parse_compressed(&mut cursor, &header)
+            .map(|cloud| points_from_cloud(&cloud));
     }
 
-    let mut splats = Vec::new();
-    for (_key, element) in &header.elements {
-        if _key == "vertex" {
-            splats = parser
-                .read_payload_for_element(&mut cursor, element, &header)
-                .map_err(|e| e.to_string())?;
-        }
-    }
+    let vertex = header
+        .elements
+        .get("vertex")
+        .ok_or(PlyLayoutError::MissingVertexElement)?;
+    validate_vertex_layout(vertex)?;
+
+    let splats: Vec<Splat> = read_vertex_payload(&parser, &mut cursor, &header)?;
 
     let mut points = Vec::with_capacity(splats.len());
 
@@ -374,12 +573,168 @@ pub fn parse_ply(data: &[u8]) -> Result<Vec<PointNormal>, String> {
             normal: Vector3::new(normal.x as f64, normal.y as f64, normal.z as f64),
             scale,
             opacity,
+            color: dc_to_rgb(splat.f_dc),
+            index: points.len() as u32,
         });
     }
 
     Ok(points)
 }
 
+/// Headerless antimatter15 `.splat` detection: not PLY or SPZ, a whole number
+/// of 32-byte records, and the leading records decode to finite positions and
+/// positive finite scales. The format has no magic, so the record check keeps a
+/// truncated or unknown file from being misread as splats.
+fn is_splat_buffer(data: &[u8]) -> bool {
+    const RECORD: usize = 32;
+    if data.is_empty()
+        || !data.len().is_multiple_of(RECORD)
+        || data.starts_with(b"ply")
+        || data.starts_with(b"NGSP")
+    {
+        return false;
+    }
+    let read_f32 = |b: &[u8]| f32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+    data.chunks_exact(RECORD).take(64).all(|r| {
+        let position_ok = (0..3).all(|k| {
+            let v = read_f32(&r[k * 4..k * 4 + 4]);
+            v.is_finite() && v.abs() < 1.0e7
+        });
+        let scale_ok = (3..6).all(|k| {
+            let v = read_f32(&r[k * 4..k * 4 + 4]);
+            (0.0..1.0e4).contains(&v)
+        });
+        position_ok && scale_ok
+    })
+}
+
+/// Nav/mesh points from a full cloud, in the same conventions `parse_ply` reads
+/// from PLY: log-space scale, raw opacity logit, normal = local +Z rotated by
+/// the splat's quaternion.
+fn points_from_cloud(cloud: &FullSplatCloud) -> Vec<PointNormal> {
+    (0..cloud.len())
+        .map(|i| {
+            let [x, y, z] = cloud.positions[i];
+            let [w, qx, qy, qz] = cloud.rotations[i];
+            let q = UnitQuaternion::new_normalize(Quaternion::new(w, qx, qy, qz));
+            let normal = q.transform_vector(&Vector3::z_axis());
+            let [s0, s1, s2] = cloud.scales[i];
+            PointNormal {
+                point: Point3::new(x as f64, y as f64, z as f64),
+                normal: Vector3::new(normal.x as f64, normal.y as f64, normal.z as f64),
+                scale: Vector3::new(s0 as f64, s1 as f64, s2 as f64),
+                opacity: cloud.opacity_logit[i] as f64,
+                color: dc_to_rgb(cloud.sh0[i]),
+                index: i as u32,
+            }
+        })
+        .collect()
+}
+
+/// Read the header comments and all non-vertex elements of a PLY file. SPZ and
+/// other non-PLY input yields an empty passthrough rather than an error.
+pub fn parse_ply_passthrough(data: &[u8]) -> Result<PlyPassthrough, String> {
+    if !data.starts_with(b"ply") {
+        return Ok(PlyPassthrough::default());
+    }
+    let mut cursor = Cursor::new(data);
+    let parser = Parser::<DefaultElement>::new();
+    let header = parser.read_header(&mut cursor).map_err(|e| e.to_string())?;
+    let mut out = PlyPassthrough {
+        comments: header.comments.clone(),
+        obj_info: header.obj_infos.clone(),
+        elements: Vec::new(),
+    };
+    let extra_count = header.elements.keys().filter(|k| *k != "vertex").count();
+    let vertex_parser = Parser::<Splat>::new();
+    for (key, element) in &header.elements {
+        if out.elements.len() == extra_count {
+            break;
+        }
+        if key == "vertex" {
+            vertex_parser
+                .read_payload_for_element(&mut cursor, element, &header)
+                .map_err(|e| e.to_string())?;
+            continue;
+        }
+        let rows = parser
+            .read_payload_for_element(&mut cursor, element, &header)
+            .map_err(|e| e.to_string())?;
+        out.elements.push(PlyElementRecords {
+            name: key.clone(),
+            count: rows.len(),
+            properties: element
+                .properties
+                .values()
+                .map(|p| PlyPropertyInfo {
+                    name: p.name.clone(),
+                    data_type: property_type_name(&p.data_type),
+                })
+                .collect(),
+            records: rows
+                .into_iter()
+                .map(|row| {
+                    row.into_iter()
+                        .map(|(k, v)| (k, property_to_json(v)))
+                        .collect()
+                })
+                .collect(),
+        });
+    }
+    Ok(out)
+}
+
+fn scalar_type_name(t: &ScalarType) -> &'static str {
+    match t {
+        ScalarType::Char => "char",
+        ScalarType::UChar => "uchar",
+        ScalarType::Short => "short",
+        ScalarType::UShort => "ushort",
+        ScalarType::Int => "int",
+        ScalarType::UInt => "uint",
+        ScalarType::Float => "float",
+        ScalarType::Double => "double",
+    }
+}
+
+fn property_type_name(t: &PropertyType) -> String {
+    match t {
+        PropertyType::Scalar(s) => scalar_type_name(s).to_string(),
+        PropertyType::List(count, item) => {
+            format!(
+                "list<{},{}>",
+                scalar_type_name(count),
+                scalar_type_name(item)
+            )
+        }
+    }
+}
+
+fn property_to_json(p: Property) -> serde_json::Value {
+    use serde_json::{json, Value};
+    match p {
+        Property::Char(v) => json!(v),
+        Property::UChar(v) => json!(v),
+        Property::Short(v) => json!(v),
+        Property::UShort(v) => json!(v),
+        Property::Int(v) => json!(v),
+        Property::UInt(v) => json!(v),
+        // Non-finite floats have no JSON form; surface them as null.
+        Property::Float(v) => {
+            serde_json::Number::from_f64(v as f64).map_or(Value::Null, Value::Number)
+        }
+        Property::Double(v) => serde_json::Number::from_f64(v).map_or(Value::Null, Value::Number),
+        Property::ListChar(v) => json!(v),
+        Property::ListUChar(v) => json!(v),
+        Property::ListShort(v) => json!(v),
+        Property::ListUShort(v) => json!(v),
+        Property::ListInt(v) => json!(v),
+        Property::ListUInt(v) => json!(v),
+        Property::ListFloat(v) => json!(v),
+        Property::ListDouble(v) => json!(v),
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Full-fidelity splat model (used by the SOG / slicing pipeline)
 //
@@ -502,10 +857,8 @@ impl PropertyAccess for FullSplatRecord {
     }
 
     fn set_property(&mut self, key: String, property: Property) {
-        let v = match property {
-            Property::Float(v) => v,
-            Property::Double(v) => v as f32,
-            _ => return,
+        let Some(v) = scalar_as_f32(&property) else {
+            return;
         };
         match key.as_str() {
             "x" => self.x = v,
@@ -536,22 +889,59 @@ impl PropertyAccess for FullSplatRecord {
     }
 }
 
-/// Parse a `.ply` or `.spz` buffer into a full-fidelity [`FullSplatCloud`],
-/// preserving spherical harmonics. Used exclusively by the SOG / slicing path.
+/// Parse a `.ply`, `.spz` or `.splat` buffer into a full-fidelity
+/// [`FullSplatCloud`], preserving spherical harmonics. Used exclusively by the
+/// SOG / slicing path.
 pub fn parse_full_cloud(data: &[u8]) -> Result<FullSplatCloud, String> {
+    let inflated = inflate_gzip(data)?;
+    let data: &[u8] = &inflated;
     if data.len() >= 4 && &data[0..4] == b"NGSP" {
         return parse_full_cloud_spz(data);
     }
+    if is_splat_buffer(data) {
+        return parse_splat_buffer(data);
+    }
     parse_full_cloud_ply(data)
 }
 
 fn parse_full_cloud_spz(data: &[u8]) -> Result<FullSplatCloud, String> {
-    console::log_1(&"Detected NGSP/SPZ format. Parsing full splat cloud with spz_rs...".into());
+    events::emit(Event::FormatDetected { format: "spz" });
+    let cloud = decode_spz(data, true)?;
+    events::emit(Event::SplatsDecoded {
+        count: cloud.len(),
+        format: "spz",
+        sh_degree: Some(cloud.sh_degree),
+    });
+    Ok(cloud)
+}
+
+/// Decode an inflated `.spz` payload. `spz_rs` reads the container revisions
+/// up to [`SPZ_RS_MAX_VERSION`]; newer ones, and payloads it rejects, go to the
+/// pure-Rust [`spz::decode`]. Without `keep_sh` the cloud carries no `sh_rest`.
+fn decode_spz(data: &[u8], keep_sh: bool) -> Result<FullSplatCloud, String> {
+    let header = spz::read_header(data).map_err(|e| format!("Failed to parse SPZ: {}", e))?;
+    let reason = if header.version > SPZ_RS_MAX_VERSION {
+        format!("version {}", header.version)
+    } else {
+        match decode_spz_rs(data, keep_sh) {
+            Ok(cloud) => return Ok(cloud),
+            Err(e) => e,
+        }
+    };
+    let cloud = spz::decode(data, keep_sh).map_err(|e| format!("Failed to parse SPZ: {}", e))?;
+    events::emit(Event::SpzDecodedNatively {
+        version: header.version,
+        reason,
+    });
+    Ok(cloud)
+}
+
+fn decode_spz_rs(data: &[u8], keep_sh: bool) -> Result<FullSplatCloud, String> {
     let cursor = Cursor::new(data);
     let packed = spz_rs::load_packed_gaussians_from_decompressed_buffer(cursor)
-        .map_err(|e| format!("Failed to parse SPZ: {:?}", e))?;
+        .map_err(|e| format!("{:?}", e))?;
 
-    let degree = packed.sh_degree.min(3);
+    let degree = if keep_sh { packed.sh_degree.min(3) } else { 0 };
     let coeffs = sh_rest_coeffs_for_degree(degree);
     let stride = 3 * coeffs;
     let n = packed.num_points;
@@ -589,8 +979,6 @@ fn parse_full_cloud_spz(data: &[u8]) -> Result<FullSplatCloud, String> {
             cloud.sh_rest.push(g.sh_b[k]);
         }
     }
-
-    console::log_1(&format!("Parsed {} splats from SPZ (SH degree {})", n, degree).into());
     Ok(cloud)
 }
 
@@ -598,11 +986,15 @@ fn parse_full_cloud_ply(data: &[u8]) -> Result<FullSplatCloud, String> {
     let mut cursor = Cursor::new(data);
     let parser = Parser::<FullSplatRecord>::new();
     let header = parser.read_header(&mut cursor).map_err(|e| e.to_string())?;
+    if compressed_ply::is_compressed(&header) {
+        return compressed_ply::parse_compressed(&mut cursor, &header);
+    }
 
     let vertex = header
         .elements
         .get("vertex")
-        .ok_or_else(|| "PLY file missing 'vertex' element".to_string())?;
+        .ok_or(PlyLayoutError::MissingVertexElement)?;
+    validate_vertex_layout(vertex)?;
 
     let rest_total = vertex
         .properties
@@ -613,9 +1005,7 @@ fn parse_full_cloud_ply(data: &[u8]) -> Result<FullSplatCloud, String> {
     let coeffs = sh_rest_coeffs_for_degree(degree);
     let stride = 3 * coeffs;
 
-    let records: Vec<FullSplatRecord> = parser
-        .read_payload_for_element(&mut cursor, vertex, &header)
-        .map_err(|e| e.to_string())?;
+    let records: Vec<FullSplatRecord> = read_vertex_payload(&parser, &mut cursor, &header)?;
 
     let n = records.len();
     let mut cloud = FullSplatCloud {
@@ -638,7 +1028,11 @@ fn parse_full_cloud_ply(data: &[u8]) -> Result<FullSplatCloud, String> {
         cloud.sh_rest.extend_from_slice(&r.f_rest[0..stride]);
     }
 
-    console::log_1(&format!("Parsed {} splats from PLY (SH degree {})", n, degree).into());
+    events::emit(Event::SplatsDecoded {
+        count: n,
+        format: "ply",
+        sh_degree: Some(degree),
+    });
     Ok(cloud)
 }
 
@@ -720,10 +1114,79 @@ pub fn parse_splat_buffer(data: &[u8]) -> Result<FullSplatCloud, String> {
         cloud.sh0.push(sh0);
     }
 
-    console::log_1(&format!("Parsed {} splats from .splat (SH degree 0)", n).into());
+    events::emit(Event::SplatsDecoded {
+        count: n,
+        format: "splat",
+        sh_degree: Some(0),
+    });
     Ok(cloud)
 }
 
+/// Serialize a [`FullSplatCloud`] to antimatter15 `.splat` records, the inverse
+/// of [`parse_splat_buffer`]. Spherical harmonics beyond the DC term are dropped
+/// (the format has no room for them) and every field is quantized as documented
+/// there.
+pub fn write_splat_buffer(cloud: &FullSplatCloud) -> Vec<u8> {
+    let mut out = Vec::with_capacity(cloud.len() * 32);
+    for i in 0..cloud.len() {
+        for v in cloud.positions[i] {
+            push_f32(&mut out, v);
+        }
+        for v in cloud.scales[i] {
+            push_f32(&mut out, v.exp());
+        }
+        let to_u8 = |v: f32| (v * 255.0).round().clamp(0.0, 255.0) as u8;
+        for c in cloud.sh0[i] {
+            out.push(to_u8(c * SH_C0 + 0.5));
+        }
+        out.push(to_u8(1.0 / (1.0 + (-cloud.opacity_logit[i]).exp())));
+        for q in cloud.rotations[i] {
+            out.push((q * 128.0 + 128.0).round().clamp(0.0, 255.0) as u8);
+        }
+    }
+    out
+}
+
+/// SH degree declared by a PLY header (`f_rest_*` count) or an SPZ header, read
+/// without decoding the payload. Unknown input reports 0.
+pub fn source_sh_degree(data: &[u8]) -> usize {
+    if data.starts_with(&GZIP_MAGIC) {
+        // Only the 16-byte SPZ header is needed; don't inflate the payload.
+        let mut header = Vec::with_capacity(16);
+        let _ = GzDecoder::new(data).take(16).read_to_end(&mut header);
+        return if header.len() == 16 && header.starts_with(b"NGSP") {
+            (header[12] as usize).min(3)
+        } else {
+            0
+        };
+    }
+    if data.len() >= 16 && &data[0..4] == b"NGSP" {
+        return (data[12] as usize).min(3);
+    }
+    let parser = Parser::<DefaultElement>::new();
+    let Ok(header) = parser.read_header(&mut Cursor::new(data)) else {
+        return 0;
+    };
+    // Compressed PLY keeps `f_rest_*` in a separate `sh` element.
+    let sh_element = if compressed_ply::is_compressed(&header) {
+        "sh"
+    } else {
+        "vertex"
+    };
+    header
+        .elements
+        .get(sh_element)
+        .map(|vertex| {
+            let rest_total = vertex
+                .properties
+                .keys()
+                .filter(|k| k.starts_with("f_rest_"))
+                .count();
+            degree_from_rest_total(rest_total)
+        })
+        .unwrap_or(0)
+}
+
 /// Serialize a [`FullSplatCloud`] to a binary little-endian 3DGS `.ply` buffer.
 /// Powers inline `.spz -> .ply` conversion so the rest of the app (Babylon
 /// viewer + nav pipeline) only ever has to deal with PLY.
@@ -800,6 +1263,77 @@ mod tests {
     use super::*;
     use wasm_bindgen_test::*;
 
+    #[wasm_bindgen_test]
+    fn big_endian_mixed_scalar_types_parse() {
+        let mut ply = b"ply\nformat binary_big_endian 1.0\nelement vertex 2\n\
+property double x\nproperty int y\nproperty uchar z\nproperty ushort opacity\n\
+property list uchar int face_refs\nend_header\n"
+            .to_vec();
+        for (x, y, z, opacity) in [(1.5_f64, -2_i32, 7_u8, 1_u16), (-0.25, 40, 255, 0)] {
+            ply.extend_from_slice(&x.to_be_bytes());
+            ply.extend_from_slice(&y.to_be_bytes());
+            ply.push(z);
+            ply.extend_from_slice(&opacity.to_be_bytes());
+            ply.push(2);
+            ply.extend_from_slice(&3_i32.to_be_bytes());
+            ply.extend_from_slice(&4_i32.to_be_bytes());
+        }
+        let points = parse_ply(&ply).unwrap();
+        assert_eq!(points.len(), 2);
+        assert_eq!(
+            (points[0].point.x, points[0].point.y, points[0].point.z),
+            (1.5, -2.0, 7.0)
+        );
+        assert_eq!((points[1].point.z, points[1].opacity), (255.0, 0.0));
+    }
+
+    #[wasm_bindgen_test]
+    fn list_positions_and_missing_axes_are_typed_errors() {
+        let list_x = b"ply\nformat ascii 1.0\nelement vertex 1\nproperty list uchar float x\n\
+property float y\nproperty float z\nend_header\n1 0.5 0 0\n";
+        assert_eq!(
+            parse_ply(list_x).err().unwrap(),
+            String::from(PlyLayoutError::ListProperty("x".to_string()))
+        );
+        let no_z = b"ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\n\
+property float y\nend_header\n0 0\n";
+        assert_eq!(
+            parse_ply(no_z).err().unwrap(),
+            String::from(PlyLayoutError::MissingProperty("z".to_string()))
+        );
+        let truncated = b"ply\nformat binary_little_endian 1.0\nelement vertex 2\n\
+property float x\nproperty float y\nproperty float z\nend_header\n\0\0\0\0";
+        assert!(parse_ply(truncated)
+            .err()
+            .unwrap()
+            .starts_with("Failed to read PLY payload"));
+    }
+
+    #[wasm_bindgen_test]
+    fn passthrough_reads_elements_around_vertex() {
+        let ply = b"ply\nformat ascii 1.0\ncomment exported by capture-tool\n\
+element camera 1\nproperty float fx\nproperty list uchar int ids\n\
+element vertex 2\nproperty float x\nproperty float y\nproperty float z\n\
+element meta 1\nproperty uint frames\nend_header\n\
+500.5 2 7 9\n0 0 0\n1 2 3\n42\n";
+        let passthrough = parse_ply_passthrough(ply).unwrap();
+        assert_eq!(passthrough.comments, vec!["exported by capture-tool"]);
+        assert_eq!(passthrough.elements.len(), 2);
+        let camera = &passthrough.elements[0];
+        assert_eq!(camera.name, "camera");
+        assert_eq!(camera.properties[1].data_type, "list<uchar,int>");
+        assert_eq!(camera.records[0]["fx"], serde_json::json!(500.5));
+        assert_eq!(camera.records[0]["ids"], serde_json::json!([7, 9]));
+        assert_eq!(
+            passthrough.elements[1].records[0]["frames"],
+            serde_json::json!(42)
+        );
+
+        let points = parse_ply(ply).unwrap();
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[1].point.z, 3.0);
+    }
+
     /// Build a single antimatter15 `.splat` record (32 bytes).
     fn splat_record(
         pos: [f32; 3],
@@ -875,6 +1409,57 @@ mod tests {
         assert!((points[0].point.z - 3.0).abs() < 1e-5);
         assert!((points[1].point.x + 4.0).abs() < 1e-5);
         assert!((points[1].point.z + 6.0).abs() < 1e-5);
+        // Base color survives as the f_dc_* SH DC term.
+        for (k, byte) in [200.0_f32, 100.0, 50.0].into_iter().enumerate() {
+            assert!((points[0].color[k] - byte / 255.0).abs() < 1e-4);
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn parse_ply_detects_splat_buffers() {
+        let mut buf = Vec::new();
+        buf.extend(splat_record(
+            [1.0, 2.0, 3.0],
+            [1.0, 1.0, 1.0],
+            [128, 64, 32, 255],
+            [255, 128, 128, 128],
+        ));
+        buf.extend(splat_record(
+            [-4.0, 5.0, -6.0],
+            [0.5, 0.5, 0.5],
+            [10, 20, 30, 40],
+            [128, 128, 128, 255],
+        ));
+
+        let points = parse_ply(&buf).expect(".splat buffer parses as points");
+        assert_eq!(points.len(), 2);
+        assert_eq!((points[1].point.x, points[1].index), (-4.0, 1));
+        // Identity rotation keeps the +Z normal; scale is log-space like PLY.
+        assert!((points[0].normal.z - 1.0).abs() < 1e-3);
+        assert!(points[0].scale.x.abs() < 1e-6);
+        assert!((points[1].scale.x - 0.5_f64.ln()).abs() < 1e-5);
+        assert!(points[0].opacity > 5.0 && points[1].opacity < 0.0);
+
+        // A NaN position disqualifies the record set; it falls through to PLY.
+        let mut bogus = buf.clone();
+        bogus[0..4].copy_from_slice(&f32::NAN.to_le_bytes());
+        assert!(parse_ply(&bogus).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn parse_ply_inflates_gzip_input() {
+        use flate2::write::GzEncoder;
+        use std::io::Write;
+
+        let raw = splat_record([1.0, 2.0, 3.0], [1.0, 1.0, 1.0], [0; 4], [128; 4]);
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(&raw).unwrap();
+        let gz = encoder.finish().unwrap();
+
+        let points = parse_ply(&gz).expect("gzip-wrapped buffer parses");
+        assert_eq!(points.len(), 1);
+        assert!((points[0].point.y - 2.0).abs() < 1e-5);
+        assert!(parse_ply(&gz[..gz.len() / 2]).is_err());
     }
 
     #[wasm_bindgen_test]
@@ -0,0 +1,325 @@
+//! SuperSplat / PlayCanvas compressed PLY (`.compressed.ply`) decoding.
+//!
+//! The layout replaces per-vertex floats with a `chunk` element of per-256-splat
+//! ranges and a `vertex` element of four packed `uint`s:
+//!
+//! - `packed_position` / `packed_scale`: 11-10-11 bit unorms lerped between the
+//!   chunk's `min_*` / `max_*` (scale ranges are log-space, like plain PLY);
+//! - `packed_rotation`: "smallest three" quaternion, a 2-bit index of the
+//!   dropped largest component plus three 10-bit components in
+//!   `[-1/sqrt(2), 1/sqrt(2)]`;
+//! - `packed_color`: 8-bit RGBA, colour lerped between the chunk's colour range
+//!   (when present) and alpha stored as linear opacity.
+//!
+//! An optional `sh` element carries `f_rest_*` as `uchar`s quantized over
+//! `[-4, 4)`. Everything is decoded into a [`FullSplatCloud`] in the same
+//! conventions the float PLY path produces (log scale, opacity logit, DC colour
+//! as SH coefficients).
+
+use ply_rs::parser::Parser;
+use ply_rs::ply::{DefaultElement, Header, Property, PropertyAccess};
+use std::io::Cursor;
+
+use super::{
+    degree_from_rest_total, sh_rest_coeffs_for_degree, FullSplatCloud, PlyLayoutError, SH_C0,
+};
+
+/// Splats covered by one `chunk` record.
+const CHUNK_SIZE: usize = 256;
+
+/// True when the header uses the chunked compressed layout.
+pub fn is_compressed(header: &Header) -> bool {
+    header.elements.contains_key("chunk")
+        && header
+            .elements
+            .get("vertex")
+            .is_some_and(|v| v.properties.contains_key("packed_position"))
+}
+
+const CHUNK_FIELDS: [&str; 18] = [
+    "min_x",
+    "min_y",
+    "min_z",
+    "max_x",
+    "max_y",
+    "max_z",
+    "min_scale_x",
+    "min_scale_y",
+    "min_scale_z",
+    "max_scale_x",
+    "max_scale_y",
+    "max_scale_z",
+    "min_r",
+    "min_g",
+    "min_b",
+    "max_r",
+    "max_g",
+    "max_b",
+];
+
+/// One `chunk` record, fields in [`CHUNK_FIELDS`] order. Colour ranges default
+/// to `[0, 1]` for files written without them.
+struct ChunkRecord([f32; 18]);
+
+impl ChunkRecord {
+    fn lerp(&self, min: usize, t: [f32; 3]) -> [f32; 3] {
+        let r = &self.0;
+        std::array::from_fn(|k| r[min + k] + (r[min + 3 + k] - r[min + k]) * t[k])
+    }
+}
+
+impl PropertyAccess for ChunkRecord {
+    fn new() -> Self {
+        let mut fields = [0.0; 18];
+        fields[15..18].copy_from_slice(&[1.0; 3]);
+        ChunkRecord(fields)
+    }
+
+    fn set_property(&mut self, key: String, property: Property) {
+        if let (Some(slot), Some(v)) = (
+            CHUNK_FIELDS.iter().position(|f| *f == key),
+            super::scalar_as_f32(&property),
+        ) {
+            self.0[slot] = v;
+        }
+    }
+}
+
+/// One packed `vertex` record.
+#[derive(Default)]
+struct PackedVertex {
+    position: u32,
+    rotation: u32,
+    scale: u32,
+    color: u32,
+}
+
+impl PropertyAccess for PackedVertex {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn set_property(&mut self, key: String, property: Property) {
+        // Packed words need all 32 bits; `scalar_as_f32` would round them.
+        let v = match property {
+            Property::UInt(v) => v,
+            Property::Int(v) => v as u32,
+            _ => return,
+        };
+        match key.as_ref() {
+            "packed_position" => self.position = v,
+            "packed_rotation" => self.rotation = v,
+            "packed_scale" => self.scale = v,
+            "packed_color" => self.color = v,
+            _ => {}
+        }
+    }
+}
+
+/// One `sh` record: quantized `f_rest_*` bytes.
+struct ShRecord([u8; 45]);
+
+impl PropertyAccess for ShRecord {
+    fn new() -> Self {
+        ShRecord([128; 45])
+    }
+
+    fn set_property(&mut self, key: String, property: Property) {
+        let Some(index) = key
+            .strip_prefix("f_rest_")
+            .and_then(|i| i.parse::<usize>().ok())
+        else {
+            return;
+        };
+        if let (Some(slot), Property::UChar(v)) = (self.0.get_mut(index), property) {
+            *slot = v;
+        }
+    }
+}
+
+fn unorm(value: u32, bits: u32) -> f32 {
+    let max = (1u32 << bits) - 1;
+    (value & max) as f32 / max as f32
+}
+
+fn unpack_111011(value: u32) -> [f32; 3] {
+    [
+        unorm(value >> 21, 11),
+        unorm(value >> 11, 10),
+        unorm(value, 11),
+    ]
+}
+
+/// `[w, x, y, z]` from the smallest-three encoding.
+fn unpack_rotation(value: u32) -> [f32; 4] {
+    let norm = std::f32::consts::SQRT_2;
+    let a = (unorm(value >> 20, 10) - 0.5) * norm;
+    let b = (unorm(value >> 10, 10) - 0.5) * norm;
+    let c = (unorm(value, 10) - 0.5) * norm;
+    let m = (1.0 - (a * a + b * b + c * c)).max(0.0).sqrt();
+    match value >> 30 {
+        0 => [m, a, b, c],
+        1 => [a, m, b, c],
+        2 => [a, b, m, c],
+        _ => [a, b, c, m],
+    }
+}
+
+fn read_element<E: PropertyAccess>(
+    cursor: &mut Cursor<&[u8]>,
+    element: &ply_rs::ply::ElementDef,
+    header: &Header,
+) -> Result<Vec<E>, PlyLayoutError> {
+    Parser::<E>::new()
+        .read_payload_for_element(cursor, element, header)
+        .map_err(|e| PlyLayoutError::Payload(e.to_string()))
+}
+
+/// Decode a compressed PLY whose header has already been read into `header`,
+/// with `cursor` positioned at the start of the payload.
+pub fn parse_compressed(
+    cursor: &mut Cursor<&[u8]>,
+    header: &Header,
+) -> Result<FullSplatCloud, String> {
+    let mut chunks = Vec::new();
+    let mut vertices = Vec::new();
+    let mut sh = Vec::new();
+    let mut sh_rest_total = 0;
+    // Payloads follow header order; unknown elements are skipped.
+    for (name, element) in &header.elements {
+        match name.as_str() {
+            "chunk" => chunks = read_element::<ChunkRecord>(cursor, element, header)?,
+            "vertex" => vertices = read_element::<PackedVertex>(cursor, element, header)?,
+            "sh" => {
+                sh_rest_total = element
+                    .properties
+                    .keys()
+                    .filter(|k| k.starts_with("f_rest_"))
+                    .count();
+                sh = read_element::<ShRecord>(cursor, element, header)?;
+            }
+            _ => {
+                read_element::<DefaultElement>(cursor, element, header)?;
+            }
+        }
+    }
+
+    let n = vertices.len();
+    if chunks.len() < n.div_ceil(CHUNK_SIZE) {
+        return Err(format!(
+            "Compressed PLY has {} chunk records for {} splats (need {})",
+            chunks.len(),
+            n,
+            n.div_ceil(CHUNK_SIZE)
+        ));
+    }
+    let degree = if sh.len() == n {
+        degree_from_rest_total(sh_rest_total)
+    } else {
+        0
+    };
+    let coeffs = sh_rest_coeffs_for_degree(degree);
+    let stride = 3 * coeffs;
+
+    let mut cloud = FullSplatCloud {
+        sh_degree: degree,
+        positions: Vec::with_capacity(n),
+        scales: Vec::with_capacity(n),
+        rotations: Vec::with_capacity(n),
+        opacity_logit: Vec::with_capacity(n),
+        sh0: Vec::with_capacity(n),
+        sh_rest: Vec::with_capacity(n * stride),
+    };
+    for (i, v) in vertices.iter().enumerate() {
+        let chunk = &chunks[i / CHUNK_SIZE];
+        cloud
+            .positions
+            .push(chunk.lerp(0, unpack_111011(v.position)));
+        cloud.scales.push(chunk.lerp(6, unpack_111011(v.scale)));
+        cloud.rotations.push(unpack_rotation(v.rotation));
+
+        let rgb = [
+            unorm(v.color >> 24, 8),
+            unorm(v.color >> 16, 8),
+            unorm(v.color >> 8, 8),
+        ];
+        let color = chunk.lerp(12, rgb);
+        cloud.sh0.push(color.map(|c| (c - 0.5) / SH_C0));
+        let alpha = unorm(v.color, 8).clamp(1.0e-6, 1.0 - 1.0e-6);
+        cloud.opacity_logit.push(-(1.0 / alpha - 1.0).ln());
+
+        if stride > 0 {
+            cloud.sh_rest.extend(
+                sh[i].0[..stride]
+                    .iter()
+                    .map(|&q| ((q as f32 + 0.5) / 256.0 - 0.5) * 8.0),
+            );
+        }
+    }
+    Ok(cloud)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pack_111011(t: [f32; 3]) -> u32 {
+        let q = |v: f32, bits: u32| (v * ((1u32 << bits) - 1) as f32).round() as u32;
+        (q(t[0], 11) << 21) | (q(t[1], 10) << 11) | q(t[2], 11)
+    }
+
+    /// Two splats in one chunk; the second sits at the chunk maximum.
+    fn compressed_ply() -> Vec<u8> {
+        let mut out = b"ply\nformat binary_little_endian 1.0\nelement chunk 1\n".to_vec();
+        for field in &CHUNK_FIELDS[..12] {
+            out.extend(format!("property float {}\n", field).as_bytes());
+        }
+        out.extend(b"element vertex 2\n");
+        for field in [
+            "packed_position",
+            "packed_rotation",
+            "packed_scale",
+            "packed_color",
+        ] {
+            out.extend(format!("property uint {}\n", field).as_bytes());
+        }
+        out.extend(b"end_header\n");
+        let chunk: [f32; 12] = [
+            -1.0, 0.0, 2.0, 1.0, 4.0, 6.0, -5.0, -5.0, -5.0, -1.0, -1.0, -1.0,
+        ];
+        for v in chunk {
+            out.extend(v.to_le_bytes());
+        }
+        // Identity rotation: w (index 0) dropped, x = y = z = 0 -> 10-bit 0.5.
+        let identity = (512u32 << 20) | (512 << 10) | 512;
+        for (t, alpha) in [([0.5, 0.25, 0.0], 255u32), ([1.0, 1.0, 1.0], 0)] {
+            let color = (255u32 << 24) | alpha;
+            for word in [pack_111011(t), identity, pack_111011(t), color] {
+                out.extend(word.to_le_bytes());
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn dequantizes_chunk_ranges() {
+        let data = compressed_ply();
+        let mut cursor = Cursor::new(data.as_slice());
+        let header = Parser::<DefaultElement>::new()
+            .read_header(&mut cursor)
+            .unwrap();
+        assert!(is_compressed(&header));
+        let cloud = parse_compressed(&mut cursor, &header).unwrap();
+        assert_eq!(cloud.len(), 2);
+
+        let [x, y, z] = cloud.positions[0];
+        assert!(x.abs() < 1e-3 && (y - 1.0).abs() < 2e-3 && (z - 2.0).abs() < 1e-6);
+        assert_eq!(cloud.positions[1], [1.0, 4.0, 6.0]);
+        assert_eq!(cloud.scales[1], [-1.0, -1.0, -1.0]);
+        let [w, qx, ..] = cloud.rotations[0];
+        assert!((w - 1.0).abs() < 1e-2 && qx.abs() < 1e-2);
+        assert!(cloud.opacity_logit[0] > 10.0 && cloud.opacity_logit[1] < -10.0);
+        // Full red maps to the top of the default colour range.
+        assert!((cloud.sh0[0][0] - 0.5 / SH_C0).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,342 @@
+//! GaussianSplats3D `.ksplat` decoding for the nav/mesh pipeline.
+//!
+//! A `.ksplat` file is a 4096-byte main header, `max_section_count` 1024-byte
+//! section headers, then each section's storage: bucket metadata, bucket
+//! centres, and fixed-size splat records. Compression level 0 stores `f32`
+//! fields; levels 1 and 2 store positions as `u16` offsets from a per-bucket
+//! centre and scale / rotation as half floats (they differ only in how the
+//! spherical harmonics are packed, which the nav path does not read). Scales
+//! are linear and colour alpha is 8-bit linear opacity; both are converted to
+//! the log-space scale and opacity logit [`super::parse_ply`] reads from PLY.
+
+use nalgebra::{Point3, Quaternion, UnitQuaternion, Vector3};
+
+use super::PointNormal;
+
+const HEADER_BYTES: usize = 4096;
+const SECTION_HEADER_BYTES: usize = 1024;
+/// Bytes per bucket centre (3x `f32`).
+const BUCKET_CENTER_BYTES: usize = 12;
+/// Default `u16` position range for compressed levels.
+const DEFAULT_SCALE_RANGE: u32 = 32767;
+
+/// Record layout of one compression level.
+struct Level {
+    scale_offset: usize,
+    rotation_offset: usize,
+    color_offset: usize,
+    /// Bytes per record before any spherical harmonics.
+    base_bytes: usize,
+    /// Bytes per spherical-harmonic component.
+    sh_component_bytes: usize,
+}
+
+const LEVELS: [Level; 3] = [
+    Level {
+        scale_offset: 12,
+        rotation_offset: 24,
+        color_offset: 40,
+        base_bytes: 44,
+        sh_component_bytes: 4,
+    },
+    Level {
+        scale_offset: 6,
+        rotation_offset: 12,
+        color_offset: 20,
+        base_bytes: 24,
+        sh_component_bytes: 2,
+    },
+    Level {
+        scale_offset: 6,
+        rotation_offset: 12,
+        color_offset: 20,
+        base_bytes: 24,
+        sh_component_bytes: 1,
+    },
+];
+
+/// Parsed main header.
+struct Header {
+    compression_level: usize,
+    max_section_count: usize,
+    section_count: usize,
+    splat_count: usize,
+}
+
+fn u16_at(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+fn f32_at(data: &[u8], offset: usize) -> f32 {
+    f32::from_bits(u32_at(data, offset))
+}
+
+/// IEEE 754 binary16 to `f32`.
+pub(super) fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32;
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        31 if mantissa == 0.0 => sign * f32::INFINITY,
+        31 => f32::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+fn read_header(data: &[u8]) -> Option<Header> {
+    if data.len() < HEADER_BYTES {
+        return None;
+    }
+    let (major, minor) = (data[0], data[1]);
+    if major != 0 || minor < 1 {
+        return None;
+    }
+    let header = Header {
+        max_section_count: u32_at(data, 4) as usize,
+        section_count: u32_at(data, 8) as usize,
+        splat_count: u32_at(data, 16) as usize,
+        compression_level: u16_at(data, 20) as usize,
+    };
+    let sane = header.compression_level < LEVELS.len()
+        && header.section_count >= 1
+        && header.section_count <= header.max_section_count
+        && header
+            .max_section_count
+            .checked_mul(SECTION_HEADER_BYTES)
+            .is_some_and(|b| HEADER_BYTES + b <= data.len());
+    sane.then_some(header)
+}
+
+/// `.ksplat` has no magic; accept a buffer whose main header parses as
+/// version 0.1+ with a known compression level and section headers that fit.
+pub fn is_ksplat(data: &[u8]) -> bool {
+    read_header(data).is_some()
+}
+
+/// Bucket (index into the section's centre table) holding splat `i`. Full
+/// buckets come first; the rest have their lengths listed in the metadata.
+fn bucket_index(
+    i: usize,
+    bucket_size: usize,
+    full_buckets: usize,
+    partial_lengths: &[usize],
+) -> usize {
+    if i < full_buckets * bucket_size {
+        return i / bucket_size.max(1);
+    }
+    let mut start = full_buckets * bucket_size;
+    for (k, &len) in partial_lengths.iter().enumerate() {
+        if i < start + len {
+            return full_buckets + k;
+        }
+        start += len;
+    }
+    full_buckets + partial_lengths.len().saturating_sub(1)
+}
+
+/// Spherical-harmonic components per record for a ksplat SH degree.
+fn sh_components(degree: usize) -> usize {
+    match degree {
+        0 => 0,
+        1 => 9,
+        _ => 24,
+    }
+}
+
+/// Decode every section of a `.ksplat` buffer into nav points, in file order.
+pub fn parse_ksplat(data: &[u8]) -> Result<Vec<PointNormal>, String> {
+    let header = read_header(data).ok_or("Invalid .ksplat header")?;
+    let level = &LEVELS[header.compression_level];
+    let truncated = || "Truncated .ksplat buffer".to_string();
+
+    let mut points = Vec::with_capacity(header.splat_count);
+    let mut section_base = HEADER_BYTES + header.max_section_count * SECTION_HEADER_BYTES;
+    for s in 0..header.section_count {
+        let h = HEADER_BYTES + s * SECTION_HEADER_BYTES;
+        let splat_count = u32_at(data, h) as usize;
+        let max_splat_count = u32_at(data, h + 4) as usize;
+        let bucket_size = u32_at(data, h + 8) as usize;
+        let bucket_count = u32_at(data, h + 12) as usize;
+        let bucket_block_size = f32_at(data, h + 16);
+        let bucket_storage_bytes = u16_at(data, h + 20) as usize;
+        let scale_range = match u32_at(data, h + 24) {
+            0 => DEFAULT_SCALE_RANGE,
+            r => r,
+        } as f32;
+        let full_buckets = u32_at(data, h + 32) as usize;
+        let partial_buckets = u32_at(data, h + 36) as usize;
+        let sh_degree = u16_at(data, h + 40) as usize;
+
+        let record_bytes = level.base_bytes + sh_components(sh_degree) * level.sh_component_bytes;
+        let bucket_meta_bytes = partial_buckets.checked_mul(4).ok_or_else(truncated)?;
+        // Sizes come straight from the file; checked so a corrupt header
+        // reports truncation instead of overflowing.
+        let data_base = bucket_storage_bytes
+            .checked_mul(bucket_count)
+            .and_then(|b| b.checked_add(bucket_meta_bytes))
+            .and_then(|b| b.checked_add(section_base))
+            .ok_or_else(truncated)?;
+        let section_end = record_bytes
+            .checked_mul(max_splat_count)
+            .and_then(|b| b.checked_add(data_base))
+            .filter(|&end| end <= data.len())
+            .ok_or_else(truncated)?;
+        if splat_count > max_splat_count {
+            return Err(truncated());
+        }
+
+        let partial_lengths: Vec<usize> = (0..partial_buckets)
+            .map(|k| u32_at(data, section_base + k * 4) as usize)
+            .collect();
+        let centers_base = section_base + bucket_meta_bytes;
+        let compressed = header.compression_level > 0;
+        if compressed && bucket_storage_bytes < BUCKET_CENTER_BYTES {
+            return Err("Invalid .ksplat bucket layout".to_string());
+        }
+        let position_scale = bucket_block_size / 2.0 / scale_range;
+
+        for i in 0..splat_count {
+            let r = data_base + i * record_bytes;
+            let position = if compressed {
+                let b = bucket_index(i, bucket_size, full_buckets, &partial_lengths);
+                if b >= bucket_count {
+                    return Err("Invalid .ksplat bucket layout".to_string());
+                }
+                let c = centers_base + b * bucket_storage_bytes;
+                [0, 1, 2].map(|k| {
+                    (u16_at(data, r + k * 2) as f32 - scale_range) * position_scale
+                        + f32_at(data, c + k * 4)
+                })
+            } else {
+                [0, 1, 2].map(|k| f32_at(data, r + k * 4))
+            };
+            let read = |offset: usize, k: usize| -> f32 {
+                if compressed {
+                    f16_to_f32(u16_at(data, r + offset + k * 2))
+                } else {
+                    f32_at(data, r + offset + k * 4)
+                }
+            };
+            let scale = [0, 1, 2].map(|k| (read(level.scale_offset, k).max(1e-9) as f64).ln());
+            let [w, x, y, z] = [0, 1, 2, 3].map(|k| read(level.rotation_offset, k));
+            let q = UnitQuaternion::new_normalize(Quaternion::new(w, x, y, z));
+            let normal = q.transform_vector(&Vector3::z_axis());
+            let alpha = (data[r + level.color_offset + 3] as f64 / 255.0).clamp(1e-6, 1.0 - 1e-6);
+
+            points.push(PointNormal {
+                point: Point3::new(position[0] as f64, position[1] as f64, position[2] as f64),
+                normal: Vector3::new(normal.x as f64, normal.y as f64, normal.z as f64),
+                scale: Vector3::new(scale[0], scale[1], scale[2]),
+                opacity: (alpha / (1.0 - alpha)).ln(),
+                color: [0, 1, 2].map(|k| data[r + level.color_offset + k] as f32 / 255.0),
+                index: points.len() as u32,
+            });
+        }
+        section_base = section_end;
+    }
+    Ok(points)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn f16_bits(v: f32) -> u16 {
+        // Exact for the small powers of two and zero used below.
+        if v == 0.0 {
+            return 0;
+        }
+        let bits = v.to_bits();
+        let sign = ((bits >> 16) & 0x8000) as u16;
+        let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+        let mantissa = ((bits >> 13) & 0x3ff) as u16;
+        sign | ((exponent as u16) << 10) | mantissa
+    }
+
+    /// One section, one full bucket of `splats` at compression level 1.
+    fn level1_buffer(center: [f32; 3], splats: &[([u16; 3], [f32; 3], u8)]) -> Vec<u8> {
+        let mut buf = vec![0_u8; HEADER_BYTES + SECTION_HEADER_BYTES];
+        buf[1] = 1;
+        buf[4..8].copy_from_slice(&1_u32.to_le_bytes());
+        buf[8..12].copy_from_slice(&1_u32.to_le_bytes());
+        buf[16..20].copy_from_slice(&(splats.len() as u32).to_le_bytes());
+        buf[20..22].copy_from_slice(&1_u16.to_le_bytes());
+        let h = HEADER_BYTES;
+        let n = (splats.len() as u32).to_le_bytes();
+        buf[h..h + 4].copy_from_slice(&n);
+        buf[h + 4..h + 8].copy_from_slice(&n);
+        buf[h + 8..h + 12].copy_from_slice(&n);
+        buf[h + 12..h + 16].copy_from_slice(&1_u32.to_le_bytes());
+        buf[h + 16..h + 20].copy_from_slice(&2.0_f32.to_le_bytes());
+        buf[h + 20..h + 22].copy_from_slice(&12_u16.to_le_bytes());
+        buf[h + 32..h + 36].copy_from_slice(&1_u32.to_le_bytes());
+        for v in center {
+            buf.extend_from_slice(&v.to_le_bytes());
+        }
+        for (pos, scale, alpha) in splats {
+            for v in pos {
+                buf.extend_from_slice(&v.to_le_bytes());
+            }
+            for v in scale {
+                buf.extend_from_slice(&f16_bits(*v).to_le_bytes());
+            }
+            for v in [1.0, 0.0, 0.0, 0.0] {
+                buf.extend_from_slice(&f16_bits(v).to_le_bytes());
+            }
+            buf.extend_from_slice(&[255, 255, 255, *alpha]);
+        }
+        buf
+    }
+
+    #[test]
+    fn half_floats_decode() {
+        assert_eq!(f16_to_f32(0x3c00), 1.0);
+        assert_eq!(f16_to_f32(0xc000), -2.0);
+        assert_eq!(f16_to_f32(0x3800), 0.5);
+        assert_eq!(f16_to_f32(0), 0.0);
+        assert!(f16_to_f32(0x7c00).is_infinite());
+    }
+
+    #[test]
+    fn compressed_positions_are_bucket_relative() {
+        let buf = level1_buffer(
+            [10.0, 0.0, -5.0],
+            &[
+                ([32767, 32767, 32767], [1.0, 1.0, 1.0], 255),
+                ([65534, 0, 32767], [0.5, 0.5, 0.5], 0),
+            ],
+        );
+        assert!(is_ksplat(&buf));
+        let points = parse_ksplat(&buf).unwrap();
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0].point, Point3::new(10.0, 0.0, -5.0));
+        // Half the bucket block (1.0) either side of the centre.
+        assert!((points[1].point.x - 11.0).abs() < 1e-4);
+        assert!((points[1].point.y + 1.0).abs() < 1e-4);
+        assert!(points[0].scale.x.abs() < 1e-6);
+        assert!((points[1].scale.x - 0.5_f64.ln()).abs() < 1e-6);
+        assert!((points[0].normal.z - 1.0).abs() < 1e-6);
+        assert!(points[0].opacity > 5.0 && points[1].opacity < -5.0);
+        assert_eq!(points[1].index, 1);
+    }
+
+    #[test]
+    fn truncated_and_foreign_buffers_are_rejected() {
+        let buf = level1_buffer([0.0; 3], &[([0, 0, 0], [1.0; 3], 255)]);
+        assert!(parse_ksplat(&buf[..buf.len() - 1]).is_err());
+        assert!(!is_ksplat(b"ply\nformat ascii 1.0\n"));
+        let mut bad_level = buf.clone();
+        bad_level[20] = 3;
+        assert!(!is_ksplat(&bad_level));
+    }
+}
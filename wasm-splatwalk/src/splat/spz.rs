@@ -0,0 +1,281 @@
+//! Pure-Rust Niantic `.spz` decoding.
+//!
+//! `spz_rs` reads the inflated `NGSP` payload of the container revisions it
+//! was written for. This module reads the same payload straight from the
+//! published layout, so a file `spz_rs` rejects still loads, and a new
+//! revision only needs a branch here. [`super::parse_ply`] and
+//! [`super::parse_full_cloud`] pick the reader from the header; callers only
+//! ever pass the file's bytes.
+//!
+//! The payload is a 16-byte header (`NGSP`, version, point count, SH degree,
+//! fractional bits, flags, reserved) followed by one array per attribute:
+//! positions, alphas, colours, scales, rotations, then SH. Version 1 stores
+//! positions as half floats, later versions as 24-bit fixed point. Versions 1
+//! and 2 store rotations as three signed bytes of `x, y, z`; version 3 packs
+//! the three smallest components into one 32-bit word. Values are converted to
+//! the conventions of [`FullSplatCloud`].
+
+use super::{ksplat, sh_rest_coeffs_for_degree, FullSplatCloud, SH_C0, SPZ_COLOR_SCALE};
+
+const MAGIC: &[u8; 4] = b"NGSP";
+const HEADER_BYTES: usize = 16;
+/// The newest container revision this module reads.
+pub const MAX_VERSION: u32 = 3;
+
+/// The fixed header of an `NGSP` payload.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Header {
+    pub version: u32,
+    pub num_points: usize,
+    pub sh_degree: usize,
+    pub fractional_bits: u32,
+}
+
+/// Read the header of an inflated payload.
+pub fn read_header(data: &[u8]) -> Result<Header, String> {
+    if data.len() < HEADER_BYTES || !data.starts_with(MAGIC) {
+        return Err("Not an SPZ payload: missing NGSP header".to_string());
+    }
+    let u32_at = |offset: usize| {
+        u32::from_le_bytes([
+            data[offset],
+            data[offset + 1],
+            data[offset + 2],
+            data[offset + 3],
+        ])
+    };
+    Ok(Header {
+        version: u32_at(4),
+        num_points: u32_at(8) as usize,
+        sh_degree: data[12] as usize,
+        fractional_bits: data[13] as u32,
+    })
+}
+
+/// Decode an inflated payload. Without `keep_sh` the cloud has SH degree 0
+/// and no `sh_rest`, which is all the nav / mesh path reads.
+pub fn decode(data: &[u8], keep_sh: bool) -> Result<FullSplatCloud, String> {
+    let header = read_header(data)?;
+    if header.version == 0 || header.version > MAX_VERSION {
+        return Err(format!(
+            "SPZ version {} is not supported (this build reads 1 to {})",
+            header.version, MAX_VERSION
+        ));
+    }
+    if header.sh_degree > 3 {
+        return Err(format!(
+            "SPZ SH degree {} is out of range",
+            header.sh_degree
+        ));
+    }
+    let n = header.num_points;
+    let coeffs = sh_rest_coeffs_for_degree(header.sh_degree);
+    let position_bytes = if header.version == 1 { 6 } else { 9 };
+    let rotation_bytes = if header.version >= 3 { 4 } else { 3 };
+    let record_bytes = position_bytes + 1 + 3 + 3 + rotation_bytes + coeffs * 3;
+    let expected = n
+        .checked_mul(record_bytes)
+        .and_then(|bytes| bytes.checked_add(HEADER_BYTES))
+        .ok_or_else(|| format!("SPZ point count {} is too large", n))?;
+    if data.len() < expected {
+        return Err(format!(
+            "SPZ payload truncated: {} points need {} bytes, got {}",
+            n,
+            expected,
+            data.len()
+        ));
+    }
+
+    // Attribute arrays, in file order.
+    let (positions, rest) = data[HEADER_BYTES..].split_at(n * position_bytes);
+    let (alphas, rest) = rest.split_at(n);
+    let (colors, rest) = rest.split_at(n * 3);
+    let (scales, rest) = rest.split_at(n * 3);
+    let (rotations, sh) = rest.split_at(n * rotation_bytes);
+
+    let degree = if keep_sh { header.sh_degree } else { 0 };
+    let kept_coeffs = sh_rest_coeffs_for_degree(degree);
+    let mut cloud = FullSplatCloud {
+        sh_degree: degree,
+        positions: Vec::with_capacity(n),
+        scales: Vec::with_capacity(n),
+        rotations: Vec::with_capacity(n),
+        opacity_logit: Vec::with_capacity(n),
+        sh0: Vec::with_capacity(n),
+        sh_rest: Vec::with_capacity(n * 3 * kept_coeffs),
+    };
+    let fixed_scale = 1.0 / (1u32 << header.fractional_bits.min(24)) as f32;
+    // Colours unpack as in the reference decoder, then convert to SH0 the
+    // way the `spz_rs` path does.
+    let color_to_sh0 = SPZ_COLOR_SCALE / SH_C0;
+
+    for i in 0..n {
+        let p = &positions[i * position_bytes..(i + 1) * position_bytes];
+        cloud.positions.push(if header.version == 1 {
+            [0, 1, 2].map(|k| half(&p[k * 2..]))
+        } else {
+            [0, 1, 2].map(|k| fixed24(&p[k * 3..]) * fixed_scale)
+        });
+
+        // Keep the logit finite for fully transparent or opaque splats.
+        let alpha = (alphas[i] as f32).clamp(0.5, 254.5) / 255.0;
+        cloud.opacity_logit.push((alpha / (1.0 - alpha)).ln());
+
+        let c = [colors[i * 3], colors[i * 3 + 1], colors[i * 3 + 2]];
+        let color = c.map(|c| (c as f32 / 255.0 - 0.5) / SPZ_COLOR_SCALE);
+        cloud.sh0.push(color.map(|c| c * color_to_sh0));
+
+        let s = &scales[i * 3..i * 3 + 3];
+        cloud
+            .scales
+            .push([0, 1, 2].map(|k| s[k] as f32 / 16.0 - 10.0));
+
+        let r = &rotations[i * rotation_bytes..(i + 1) * rotation_bytes];
+        let [x, y, z, w] = if header.version >= 3 {
+            smallest_three(u32::from_le_bytes([r[0], r[1], r[2], r[3]]))
+        } else {
+            let [x, y, z] = [0, 1, 2].map(|k| r[k] as f32 / 127.5 - 1.0);
+            [x, y, z, (1.0 - (x * x + y * y + z * z)).max(0.0).sqrt()]
+        };
+        let norm = (w * w + x * x + y * y + z * z).sqrt();
+        cloud.rotations.push(if norm > 0.0 {
+            [w / norm, x / norm, y / norm, z / norm]
+        } else {
+            [1.0, 0.0, 0.0, 0.0]
+        });
+
+        if kept_coeffs > 0 {
+            // SPZ interleaves channels per coefficient; the cloud is
+            // channel-major.
+            let record = &sh[i * coeffs * 3..(i + 1) * coeffs * 3];
+            for channel in 0..3 {
+                for k in 0..kept_coeffs {
+                    cloud
+                        .sh_rest
+                        .push((record[k * 3 + channel] as f32 - 128.0) / 128.0);
+                }
+            }
+        }
+    }
+    Ok(cloud)
+}
+
+/// A little-endian half float.
+fn half(bytes: &[u8]) -> f32 {
+    ksplat::f16_to_f32(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+/// A little-endian 24-bit signed integer, sign-extended through the top of
+/// an `i32`.
+fn fixed24(bytes: &[u8]) -> f32 {
+    (i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) >> 8) as f32
+}
+
+/// Unpack a version 3 rotation, `[x, y, z, w]`. The top two bits index the
+/// largest component; the other three are 9-bit magnitudes in units of
+/// `sqrt(1/2) / 511` with a sign bit each, packed from `w` down to `x`.
+fn smallest_three(word: u32) -> [f32; 4] {
+    const MASK: u32 = (1 << 9) - 1;
+    let largest = (word >> 30) as usize;
+    let mut rest = word;
+    let mut q = [0.0f32; 4];
+    let mut sum_squares = 0.0;
+    for i in (0..4).rev() {
+        if i == largest {
+            continue;
+        }
+        let magnitude = std::f32::consts::FRAC_1_SQRT_2 * (rest & MASK) as f32 / MASK as f32;
+        q[i] = if (rest >> 9) & 1 == 1 {
+            -magnitude
+        } else {
+            magnitude
+        };
+        sum_squares += q[i] * q[i];
+        rest >>= 10;
+    }
+    q[largest] = (1.0 - sum_squares).max(0.0).sqrt();
+    q
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A payload of `version` holding one splat at `(1, -2, 0.5)`, identity
+    /// rotation, opacity 0.5, log scale 0 and SH degree 1.
+    fn payload(version: u32) -> Vec<u8> {
+        let mut out = b"NGSP".to_vec();
+        out.extend_from_slice(&version.to_le_bytes());
+        out.extend_from_slice(&1u32.to_le_bytes());
+        out.extend_from_slice(&[1, 12, 0, 0]);
+        if version == 1 {
+            for v in [0x3c00u16, 0xc000, 0x3800] {
+                out.extend_from_slice(&v.to_le_bytes());
+            }
+        } else {
+            for v in [1.0f32, -2.0, 0.5] {
+                let fixed = (v * 4096.0) as i32;
+                out.extend_from_slice(&fixed.to_le_bytes()[..3]);
+            }
+        }
+        out.push(128);
+        out.extend_from_slice(&[255, 128, 0]);
+        out.extend_from_slice(&[160; 3]);
+        if version >= 3 {
+            // w largest, x = y = z = 0.
+            out.extend_from_slice(&(3u32 << 30).to_le_bytes());
+        } else {
+            out.extend_from_slice(&[128; 3]);
+        }
+        // Three coefficients, channels interleaved: R positive, G zero, B
+        // negative.
+        out.extend_from_slice(&[192, 128, 64, 192, 128, 64, 192, 128, 64]);
+        out
+    }
+
+    #[test]
+    fn every_revision_decodes_to_the_same_splat() {
+        for version in 1..=MAX_VERSION {
+            let cloud = decode(&payload(version), true).unwrap();
+            assert_eq!(cloud.len(), 1, "v{}", version);
+            let [x, y, z] = cloud.positions[0];
+            assert!((x - 1.0).abs() < 1e-3 && (y + 2.0).abs() < 1e-3 && (z - 0.5).abs() < 1e-3);
+            assert!(cloud.scales[0].iter().all(|s| s.abs() < 1e-6));
+            assert!(cloud.opacity_logit[0].abs() < 0.01);
+            assert!((cloud.rotations[0][0] - 1.0).abs() < 1e-3, "v{}", version);
+            // Full red, mid green, no blue after the DC colour transform.
+            let rgb = super::super::dc_to_rgb(cloud.sh0[0]);
+            assert!(rgb[0] > rgb[1] && rgb[1] > rgb[2]);
+            assert_eq!(cloud.sh_degree, 1);
+            assert_eq!(&cloud.sh_rest[..3], &[0.5; 3]);
+            assert_eq!(&cloud.sh_rest[6..], &[-0.5; 3]);
+
+            let lean = decode(&payload(version), false).unwrap();
+            assert_eq!(lean.sh_degree, 0);
+            assert!(lean.sh_rest.is_empty());
+        }
+    }
+
+    #[test]
+    fn smallest_three_restores_the_largest_component() {
+        // x largest; y = +mag 511 (sqrt(1/2)), z and w zero.
+        let word = 511 << 20;
+        let [x, y, z, w] = smallest_three(word);
+        assert!((y - std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-6);
+        assert!((x - std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-3);
+        assert_eq!([z, w], [0.0, 0.0]);
+        // A set sign bit negates the component.
+        assert!(smallest_three(3 << 30 | 1 << 9 | 100)[2] < 0.0);
+    }
+
+    #[test]
+    fn future_and_truncated_payloads_are_rejected() {
+        let mut future = payload(3);
+        future[4] = 4;
+        let error = |data: &[u8]| decode(data, true).err().unwrap_or_default();
+        assert!(error(&future).contains("version 4"));
+        let full = payload(2);
+        assert!(error(&full[..full.len() - 1]).contains("truncated"));
+        assert!(decode(b"ply\n", true).is_err());
+    }
+}
@@ -0,0 +1,105 @@
+//! Panic containment for pipeline stages.
+//!
+//! An index out of bounds deep in a mesher used to take the whole instance
+//! down for the rest of the page session. Entry points now run their stages
+//! through [`run`], which turns a panic into a [`StagePanic`]: surfaced as an
+//! `internal_error` [`SplatwalkError`] when nothing usable exists yet, or, when
+//! earlier stages already produced something (filtered cloud, collision grid,
+//! voxel volume), returned alongside that output as a [`PartialResult`].
+//!
+//! Containment relies on unwinding. Native builds (tests, tools) always
+//! unwind; a wasm32 build only does with `-C panic=unwind` on a toolchain with
+//! wasm exception handling. Under the default `panic = "abort"` wasm build a
+//! panic still traps, and the panic hook's console message is all that is left.
+
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+
+use serde::Serialize;
+use wasm_bindgen::JsValue;
+
+use crate::error::SplatwalkError;
+use crate::journal::{self, JournalKind};
+
+/// A stage that panicked, with the panic message.
+#[derive(Clone, Debug)]
+pub struct StagePanic {
+    pub stage: &'static str,
+    pub message: String,
+}
+
+/// Marks a result whose later stages failed; serialized as `partial`.
+/// Everything produced before `failed_stage` is intact, everything after it
+/// is empty.
+#[derive(Clone, Serialize)]
+pub struct PartialResult {
+    pub failed_stage: &'static str,
+    pub message: String,
+}
+
+impl From<StagePanic> for PartialResult {
+    fn from(panic: StagePanic) -> Self {
+        PartialResult {
+            failed_stage: panic.stage,
+            message: panic.message,
+        }
+    }
+}
+
+impl From<StagePanic> for SplatwalkError {
+    fn from(panic: StagePanic) -> Self {
+        SplatwalkError::InternalError(format!("stage {} panicked: {}", panic.stage, panic.message))
+    }
+}
+
+impl From<StagePanic> for JsValue {
+    fn from(panic: StagePanic) -> Self {
+        SplatwalkError::from(panic).into()
+    }
+}
+
+/// Run `f` as pipeline stage `stage`, catching a panic instead of letting it
+/// unwind out of the entry point. Caught panics are also journaled.
+pub fn run<T>(stage: &'static str, f: impl FnOnce() -> T) -> Result<T, StagePanic> {
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|payload| {
+        let message = payload_message(payload.as_ref());
+        journal::record(
+            stage,
+            JournalKind::Degraded,
+            format!("Stage {} panicked: {}", stage, message),
+            serde_json::json!({ "reason": "panic", "message": message }),
+        );
+        StagePanic { stage, message }
+    })
+}
+
+/// The `&str` / `String` a panic was raised with.
+fn payload_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic payload".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn panics_become_stage_errors() {
+        assert_eq!(run("ok", || 7).unwrap(), 7);
+        let panic = run("meshing", || -> usize {
+            let cells: Vec<usize> = Vec::new();
+            cells[3]
+        })
+        .unwrap_err();
+        assert_eq!(panic.stage, "meshing");
+        assert!(panic.message.contains("index out of bounds"));
+        let error = SplatwalkError::from(panic);
+        assert_eq!(error.code(), "internal_error");
+        assert!(error.detail().starts_with("stage meshing panicked"));
+    }
+}
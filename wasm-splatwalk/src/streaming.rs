@@ -0,0 +1,316 @@
+//! Live navmesh updates while a space is being scanned.
+//!
+//! A capture app streaming splats into a `SplatSession` cannot afford a full
+//! bake per chunk. `append_chunk` grows a source in place and records the box
+//! the chunk landed in, and the other session edits that can move the walkable
+//! surface (adding, replacing, removing, moving or toggling a source) record
+//! the boxes they touch. Each record goes to the session's change callback as
+//! it happens and onto a pending list. `bake_dirty_tiles` then rebuilds only
+//! the navmesh tiles the pending boxes reach and returns them as patches the
+//! host swaps into the tiles it already streams.
+//!
+//! Patches use the anchored tile layout (see [`TileLayout::anchored`]): a
+//! tile keeps its square as the scan grows, where a full `tile_size` bake
+//! starts its layout at the scene's corner. Each patch is the tile's collision
+//! mesh built as one tile of a `tile_size` bake is. Post-processing and
+//! per-vertex outputs need a full bake.
+
+use std::collections::BTreeSet;
+
+use nalgebra::{Matrix4, Point3};
+use serde::Serialize;
+
+use crate::events::{self, Event};
+use crate::mesh;
+use crate::splat::PointNormal;
+use crate::stage::PartialResult;
+use crate::tiling::{self, TileLayout};
+use crate::{CoordinateSpace, MeshBuffers, MeshSettings, ReconstructionDiagnostics};
+
+/// A box whose walkable surface may have changed.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DirtyRegion {
+    /// The session source whose edit dirtied the box.
+    pub source: String,
+    pub min: [f64; 3],
+    pub max: [f64; 3],
+}
+
+impl DirtyRegion {
+    /// Bounds of `points` mapped through `transform`, or `None` when no point
+    /// is finite.
+    pub fn of(source: &str, points: &[PointNormal], transform: &Matrix4<f64>) -> Option<Self> {
+        let mut min = [f64::INFINITY; 3];
+        let mut max = [f64::NEG_INFINITY; 3];
+        for p in points {
+            let q = transform.transform_point(&p.point);
+            if !(q.x.is_finite() && q.y.is_finite() && q.z.is_finite()) {
+                continue;
+            }
+            for (axis, v) in [q.x, q.y, q.z].into_iter().enumerate() {
+                min[axis] = min[axis].min(v);
+                max[axis] = max[axis].max(v);
+            }
+        }
+        min[0].is_finite().then(|| DirtyRegion {
+            source: source.to_string(),
+            min,
+            max,
+        })
+    }
+
+    /// The box around this one's corners in the bake frame of `settings`.
+    fn oriented(&self, settings: &MeshSettings) -> DirtyRegion {
+        let mut min = [f64::INFINITY; 3];
+        let mut max = [f64::NEG_INFINITY; 3];
+        for corner in 0..8 {
+            let pick = |axis: usize| {
+                if corner & (1 << axis) == 0 {
+                    self.min[axis]
+                } else {
+                    self.max[axis]
+                }
+            };
+            let q = mesh::orient_point(&Point3::new(pick(0), pick(1), pick(2)), settings);
+            for (axis, v) in [q.x, q.y, q.z].into_iter().enumerate() {
+                min[axis] = min[axis].min(v);
+                max[axis] = max[axis].max(v);
+            }
+        }
+        DirtyRegion {
+            source: self.source.clone(),
+            min,
+            max,
+        }
+    }
+}
+
+/// One rebuilt tile.
+#[derive(Serialize)]
+pub struct TilePatch {
+    /// Anchored column and row; negative west and north of the plan origin.
+    pub column: i64,
+    pub row: i64,
+    /// The tile's square, over the height of its faces (the grid's height
+    /// when it has none).
+    pub min: [f64; 3],
+    pub max: [f64; 3],
+    /// The tile's faces. Empty when the tile no longer holds walkable floor,
+    /// so the host drops it.
+    pub mesh: MeshBuffers,
+}
+
+/// Result of `SplatSession.bake_dirty_tiles`.
+#[derive(Serialize)]
+pub struct TilePatches {
+    pub api_version: u8,
+    pub semver: String,
+    pub capabilities: Vec<String>,
+    pub tile_size: f64,
+    pub tile_border: f64,
+    /// The pending regions the bake consumed, in the bake frame.
+    pub regions: Vec<DirtyRegion>,
+    /// Every tile the regions reach, row by row.
+    pub patches: Vec<TilePatch>,
+    pub space: CoordinateSpace,
+    pub diagnostics: ReconstructionDiagnostics,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partial: Option<PartialResult>,
+}
+
+/// Anchored tiles whose square grown by `border` overlaps one of `regions`,
+/// as `(row, column)` so they iterate row by row. Each region's tile count is
+/// checked against [`tiling::MAX_TILES`] before any tile is listed: one stray
+/// splat far from the scan stretches its chunk's box across millions of
+/// tiles.
+fn dirty_tiles(
+    regions: &[DirtyRegion],
+    size: f64,
+    border: f64,
+) -> Result<BTreeSet<(i64, i64)>, String> {
+    // Past 2^53 tile indices stop being exact, and `as i64` saturates.
+    let tile = |v: f64| {
+        let index = (v / size).floor();
+        (index.abs() < 2f64.powi(53)).then_some(index as i64)
+    };
+    let too_many = |region: &DirtyRegion, count: Option<i64>| {
+        let count = count.map_or("too many".to_string(), |c| c.to_string());
+        format!(
+            "Dirty region of source \"{}\" reaches {} tiles (at most {}); run a full bake instead",
+            region.source,
+            count,
+            tiling::MAX_TILES
+        )
+    };
+    let mut spans = Vec::with_capacity(regions.len());
+    for region in regions {
+        let (Some(first_row), Some(last_row), Some(first_column), Some(last_column)) = (
+            tile(region.min[2] - border),
+            tile(region.max[2] + border),
+            tile(region.min[0] - border),
+            tile(region.max[0] + border),
+        ) else {
+            return Err(too_many(region, None));
+        };
+        let count = (last_row - first_row + 1).checked_mul(last_column - first_column + 1);
+        if count.is_none_or(|c| c > tiling::MAX_TILES as i64) {
+            return Err(too_many(region, count));
+        }
+        spans.push((first_row..=last_row, first_column..=last_column));
+    }
+    let mut tiles = BTreeSet::new();
+    for (rows, columns) in spans {
+        for row in rows {
+            for column in columns.clone() {
+                tiles.insert((row, column));
+            }
+        }
+    }
+    Ok(tiles)
+}
+
+/// Rebuild the tiles `regions` (session frame) reach from the composed
+/// cloud `points`. `settings` must be a mode 2 bake with `tile_size`.
+pub fn bake(
+    points: &[PointNormal],
+    regions: &[DirtyRegion],
+    settings: &MeshSettings,
+) -> Result<TilePatches, String> {
+    if settings.mode != 2 || settings.tile_size.is_none() {
+        return Err("bake_dirty_tiles requires mode 2 with tile_size".to_string());
+    }
+    let (size, border) = tiling::anchored_metrics(settings);
+    let regions: Vec<DirtyRegion> = regions.iter().map(|r| r.oriented(settings)).collect();
+    let tiles = dirty_tiles(&regions, size, border)?;
+    if tiles.len() > tiling::MAX_TILES {
+        return Err(format!(
+            "Dirty regions reach {} tiles (at most {}); run a full bake instead",
+            tiles.len(),
+            tiling::MAX_TILES
+        ));
+    }
+
+    // Padded by a tile on every side: `clip` files faces off the layout under
+    // its edge tiles, which must not be tiles being rebuilt.
+    let first = tiles.iter().fold([i64::MAX; 2], |first, &(row, column)| {
+        [first[0].min(column - 1), first[1].min(row - 1)]
+    });
+    let last = tiles.iter().fold([i64::MIN; 2], |last, &(row, column)| {
+        [last[0].max(column + 1), last[1].max(row + 1)]
+    });
+    let mut diagnostics = ReconstructionDiagnostics::empty(points.len());
+    let mut partial = None;
+    let mut patches = Vec::with_capacity(tiles.len());
+    if !tiles.is_empty() {
+        let local: Vec<(usize, usize)> = tiles
+            .iter()
+            .map(|&(row, column)| ((column - first[0]) as usize, (row - first[1]) as usize))
+            .collect();
+        let layout = TileLayout::anchored(settings, first, last);
+        let rebuild = mesh::reconstruct_tiles(points, settings, layout, &local);
+        for ((&(row, column), &(c, r)), piece) in tiles.iter().zip(&local).zip(rebuild.pieces) {
+            let (square_min, square_max) = rebuild.layout.square(c, r);
+            let (low, high) = if piece.indices.is_empty() {
+                (rebuild.layout.y_range[0], rebuild.layout.y_range[1])
+            } else {
+                let heights = piece.vertices.iter().skip(1).step_by(3).map(|&y| y as f64);
+                heights.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), y| {
+                    (lo.min(y), hi.max(y))
+                })
+            };
+            patches.push(TilePatch {
+                column,
+                row,
+                min: [square_min[0], low, square_min[1]],
+                max: [square_max[0], high, square_max[1]],
+                mesh: MeshBuffers::new(piece.vertices, piece.indices),
+            });
+        }
+        diagnostics = rebuild.diagnostics;
+        partial = rebuild.partial;
+    }
+    events::emit(Event::TilesPatched {
+        regions: regions.len(),
+        tiles: patches.len(),
+        empty: patches.iter().filter(|p| p.mesh.indices.is_empty()).count(),
+    });
+    Ok(TilePatches {
+        api_version: crate::API_VERSION,
+        semver: crate::core_semver(),
+        capabilities: crate::capabilities(),
+        tile_size: size,
+        tile_border: border,
+        regions,
+        patches,
+        space: CoordinateSpace::splatwalk_oriented(),
+        diagnostics,
+        partial,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(min: [f64; 3], max: [f64; 3]) -> DirtyRegion {
+        DirtyRegion {
+            source: "scan".to_string(),
+            min,
+            max,
+        }
+    }
+
+    #[test]
+    fn dirty_tiles_reach_through_the_border() {
+        // 4 m tiles with a 1 m border: a box inside tile (0, 0) but within
+        // the border of its east neighbour dirties both.
+        let tiles = dirty_tiles(&[region([1.0, 0.0, 1.0], [3.5, 2.0, 2.0])], 4.0, 1.0).unwrap();
+        assert_eq!(tiles.into_iter().collect::<Vec<_>>(), [(0, 0), (0, 1)]);
+        // West of the origin the columns go negative; rows come out in order.
+        let tiles = dirty_tiles(
+            &[
+                region([-2.0, 0.0, 5.0], [-1.5, 0.0, 5.5]),
+                region([-2.0, 0.0, 1.5], [-1.5, 0.0, 2.0]),
+            ],
+            4.0,
+            0.0,
+        )
+        .unwrap();
+        assert_eq!(tiles.into_iter().collect::<Vec<_>>(), [(0, -1), (1, -1)]);
+    }
+
+    #[test]
+    fn far_regions_are_refused_before_listing_tiles() {
+        // A small box far from the origin is one tile.
+        let far = region([4.0e9, 0.0, 4.0e9], [4.0e9 + 1.0, 0.0, 4.0e9 + 1.0]);
+        let tiles = dirty_tiles(&[far], 4.0, 0.0).unwrap();
+        assert_eq!(
+            tiles.into_iter().collect::<Vec<_>>(),
+            [(1_000_000_000, 1_000_000_000)]
+        );
+        // A chunk with one floater a few kilometres out spans millions of
+        // tiles; a splat at 1e300 does not even have a tile index.
+        let floater = region([0.0, 0.0, 0.0], [5000.0, 0.0, 5000.0]);
+        let err = dirty_tiles(&[floater], 4.0, 0.0).unwrap_err();
+        assert!(err.contains("1565001 tiles"), "{}", err);
+        let huge = region([0.0, 0.0, 0.0], [1.0e300, 0.0, 1.0]);
+        let err = dirty_tiles(&[huge], 4.0, 0.0).unwrap_err();
+        assert!(err.contains("too many tiles"), "{}", err);
+    }
+
+    #[test]
+    fn regions_follow_the_source_transform() {
+        let points = [[0.0, 0.0, 0.0], [1.0, 2.0, -1.0]].map(|[x, y, z]| PointNormal {
+            point: Point3::new(x, y, z),
+            normal: nalgebra::Vector3::new(0.0, 1.0, 0.0),
+            scale: nalgebra::Vector3::new(0.01, 0.01, 0.01),
+            opacity: 1.0,
+            color: [0.5; 3],
+            index: 0,
+        });
+        let moved = Matrix4::new_translation(&nalgebra::Vector3::new(10.0, 0.0, 0.0));
+        let dirty = DirtyRegion::of("scan", &points, &moved).unwrap();
+        assert_eq!(dirty, region([10.0, 0.0, -1.0], [11.0, 2.0, 0.0]));
+        assert_eq!(DirtyRegion::of("scan", &[], &moved), None);
+    }
+}
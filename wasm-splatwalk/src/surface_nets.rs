@@ -0,0 +1,433 @@
+//! Naive surface nets over splat occupancy (`mode = 9`).
+//!
+//! Web games want collision that is cheap to build and cheap to query, not
+//! accurate: a few thousand triangles that keep the player out of walls.
+//! Mode 9 spreads each splat's opacity over a voxel grid with the collision
+//! kernel, marks the voxels whose density reaches
+//! `collision_opacity_threshold` as occupied, and wraps the occupied set in
+//! naive surface nets (Gibson, "Constrained Elastic Surface Nets"). Every
+//! cube of eight neighbouring voxel centres that mixes occupied and empty
+//! voxels gets one vertex, at the mean of its crossing edge midpoints; every
+//! occupied voxel face that borders an empty voxel becomes one quad. That is
+//! two triangles per exposed face, far fewer than marching cubes emits over
+//! the same grid, and no lookup tables.
+//!
+//! The grid is padded with empty voxels, so the mesh is closed and faces
+//! away from the occupied side. `surface_nets_smoothing` relaxation passes
+//! then move each vertex towards its neighbours while keeping it inside its
+//! cube, which rounds off the voxel stairs without shrinking thin walls.
+
+use std::collections::HashMap;
+
+use nalgebra::Vector3;
+
+use crate::kernel::SplatKernel;
+use crate::resolution::Resolution;
+use crate::splat::PointNormal;
+use crate::MeshSettings;
+
+/// Dense-grid cap; the voxel grows until the grid fits.
+const MAX_VOXELS: usize = 4_000_000;
+
+/// Most relaxation passes accepted.
+const MAX_SMOOTHING: usize = 16;
+
+#[derive(Clone, Debug)]
+pub struct SurfaceNetsOptions {
+    pub voxel_size: f64,
+    /// Density at which a voxel counts as occupied.
+    pub threshold: f64,
+    pub kernel: SplatKernel,
+    /// Relaxation passes after extraction.
+    pub smoothing: usize,
+}
+
+impl SurfaceNetsOptions {
+    /// Options for a cloud spanning `min..max`. The voxel edge is derived
+    /// from the [`Resolution`] over the XZ footprint like the ground field's
+    /// cell size, clamped to `[0.01, 2.0]` m, then coarsened until the grid
+    /// fits [`MAX_VOXELS`].
+    pub fn from_settings(settings: &MeshSettings, min: [f64; 3], max: [f64; 3]) -> Self {
+        let extent = [max[0] - min[0], max[1] - min[1], max[2] - min[2]];
+        let kernel = SplatKernel::from_settings(settings, 6.0);
+        let mut voxel_size = Resolution::from_settings(settings)
+            .cell_size(extent[0], extent[2])
+            .clamp(0.01, 2.0);
+        while grid_dims(extent, voxel_size, &kernel)
+            .iter()
+            .product::<usize>()
+            > MAX_VOXELS
+        {
+            voxel_size *= 1.25;
+        }
+        Self {
+            voxel_size,
+            threshold: settings
+                .collision_opacity_threshold
+                .unwrap_or(0.1)
+                .max(0.001),
+            kernel,
+            smoothing: settings.surface_nets_smoothing.unwrap_or(2),
+        }
+    }
+}
+
+/// `surface_nets_smoothing` is at most [`MAX_SMOOTHING`] passes.
+pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+    match settings.surface_nets_smoothing {
+        Some(n) if n > MAX_SMOOTHING => Err(format!(
+            "Invalid surface_nets_smoothing: {}. Expected at most {}.",
+            n, MAX_SMOOTHING
+        )),
+        _ => Ok(()),
+    }
+}
+
+pub struct SurfaceNetsBuild {
+    pub vertices: Vec<f32>,
+    pub indices: Vec<u32>,
+    pub voxel_size: f64,
+    pub occupied_voxels: usize,
+}
+
+/// Empty voxels around the cloud: the kernel's reach plus one, so occupied
+/// voxels never touch the border.
+fn padding(kernel: &SplatKernel) -> usize {
+    kernel.max_radius_cells.ceil() as usize + 1
+}
+
+fn grid_dims(extent: [f64; 3], voxel_size: f64, kernel: &SplatKernel) -> [usize; 3] {
+    extent.map(|e| (e.max(0.0) / voxel_size).floor() as usize + 1 + 2 * padding(kernel))
+}
+
+struct Grid {
+    origin: Vector3<f64>,
+    voxel_size: f64,
+    dims: [usize; 3],
+}
+
+impl Grid {
+    fn idx(&self, v: [usize; 3]) -> usize {
+        v[0] + self.dims[0] * (v[1] + self.dims[1] * v[2])
+    }
+
+    fn center(&self, v: [usize; 3]) -> Vector3<f64> {
+        self.origin
+            + Vector3::new(v[0] as f64, v[1] as f64, v[2] as f64).add_scalar(0.5) * self.voxel_size
+    }
+}
+
+/// Voxelize `points` and wrap the occupied voxels. `progress` receives
+/// `(stage, fraction)` for `surface_nets_voxelize` and `surface_nets_mesh`.
+pub fn reconstruct(
+    points: &[PointNormal],
+    options: &SurfaceNetsOptions,
+    progress: &dyn Fn(&str, f64),
+) -> SurfaceNetsBuild {
+    let mut build = SurfaceNetsBuild {
+        vertices: vec![],
+        indices: vec![],
+        voxel_size: options.voxel_size,
+        occupied_voxels: 0,
+    };
+    if points.is_empty() {
+        return build;
+    }
+    let mut min = [f64::MAX; 3];
+    let mut max = [f64::MIN; 3];
+    for p in points {
+        let c = [p.point.x, p.point.y, p.point.z];
+        for axis in 0..3 {
+            min[axis] = min[axis].min(c[axis]);
+            max[axis] = max[axis].max(c[axis]);
+        }
+    }
+    let voxel = options.voxel_size;
+    let pad = padding(&options.kernel) as f64 * voxel;
+    let grid = Grid {
+        origin: Vector3::new(min[0] - pad, min[1] - pad, min[2] - pad),
+        voxel_size: voxel,
+        dims: grid_dims([0, 1, 2].map(|a| max[a] - min[a]), voxel, &options.kernel),
+    };
+
+    let occupied = occupancy(&grid, points, options, progress);
+    build.occupied_voxels = occupied.iter().filter(|&&o| o).count();
+    let (positions, cubes, quads) = extract(&grid, &occupied, progress);
+    let positions = relax(&grid, positions, &cubes, &quads, options.smoothing);
+
+    for (quad, outward) in quads {
+        let [p0, p1, p2, p3] = quad.map(|i| positions[i as usize]);
+        // Split on the shorter diagonal, each triangle facing `outward`.
+        let triangles = if (p2 - p0).norm_squared() <= (p3 - p1).norm_squared() {
+            [[quad[0], quad[1], quad[2]], [quad[0], quad[2], quad[3]]]
+        } else {
+            [[quad[0], quad[1], quad[3]], [quad[1], quad[2], quad[3]]]
+        };
+        for mut tri in triangles {
+            let [a, b, c] = tri.map(|i| positions[i as usize]);
+            if (b - a).cross(&(c - a)).dot(&outward) < 0.0 {
+                tri.swap(1, 2);
+            }
+            build.indices.extend_from_slice(&tri);
+        }
+    }
+    build.vertices = positions
+        .iter()
+        .flat_map(|p| [p.x as f32, p.y as f32, p.z as f32])
+        .collect();
+    build
+}
+
+/// Voxels whose kernel-spread opacity reaches the threshold, spread as the
+/// mode 2 collision voxelizer does.
+fn occupancy(
+    grid: &Grid,
+    points: &[PointNormal],
+    options: &SurfaceNetsOptions,
+    progress: &dyn Fn(&str, f64),
+) -> Vec<bool> {
+    let voxel = grid.voxel_size;
+    let mut density = vec![0.0_f32; grid.dims.iter().product()];
+    let report_every = (points.len() / 50).max(1);
+    for (pi, p) in points.iter().enumerate() {
+        if pi % report_every == 0 {
+            progress("surface_nets_voxelize", pi as f64 / points.len() as f64);
+        }
+        let centre = p.point.coords;
+        let scale_avg = ((p.scale.x + p.scale.y + p.scale.z) / 3.0).max(voxel * 0.5);
+        let radius = options.kernel.radius(scale_avg * 2.5, voxel, 1.0);
+        let base = (centre - grid.origin) / voxel;
+        let home = [base.x, base.y, base.z].map(|v| v.floor() as isize);
+        let reach = (radius / voxel).ceil() as isize;
+        let range = |axis: usize| {
+            (home[axis] - reach).max(0)..=(home[axis] + reach).min(grid.dims[axis] as isize - 1)
+        };
+        for z in range(2) {
+            for y in range(1) {
+                for x in range(0) {
+                    let v = [x as usize, y as usize, z as usize];
+                    let falloff = options
+                        .kernel
+                        .weight((grid.center(v) - centre).norm_squared(), radius);
+                    if falloff > 0.0 {
+                        density[grid.idx(v)] += (p.opacity.max(0.0) * falloff) as f32;
+                    }
+                }
+            }
+        }
+    }
+    density
+        .into_iter()
+        .map(|d| f64::from(d) >= options.threshold)
+        .collect()
+}
+
+/// One quad per occupied face: its four vertices in order around the face,
+/// and the direction from the occupied voxel to the empty one.
+type Quad = ([u32; 4], Vector3<f64>);
+
+/// Surface net vertices (at their crossing-midpoint mean), the lower voxel of
+/// the cube each sits in, and the quads joining them.
+fn extract(
+    grid: &Grid,
+    occupied: &[bool],
+    progress: &dyn Fn(&str, f64),
+) -> (Vec<Vector3<f64>>, Vec<[usize; 3]>, Vec<Quad>) {
+    let mut positions = Vec::new();
+    let mut cubes = Vec::new();
+    let mut cube_vertex: HashMap<usize, u32> = HashMap::new();
+    let mut quads = Vec::new();
+    let [nx, ny, nz] = grid.dims;
+    let is_occupied = |v: [usize; 3]| occupied[grid.idx(v)];
+
+    let mut vertex = |cube: [usize; 3]| -> u32 {
+        *cube_vertex.entry(grid.idx(cube)).or_insert_with(|| {
+            let mut sum = Vector3::zeros();
+            let mut crossings = 0;
+            for axis in 0..3 {
+                let (u, w) = ((axis + 1) % 3, (axis + 2) % 3);
+                for (du, dw) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let mut a = cube;
+                    a[u] += du;
+                    a[w] += dw;
+                    let mut b = a;
+                    b[axis] += 1;
+                    if is_occupied(a) != is_occupied(b) {
+                        sum += (grid.center(a) + grid.center(b)) * 0.5;
+                        crossings += 1;
+                    }
+                }
+            }
+            positions.push(sum / f64::from(crossings));
+            cubes.push(cube);
+            (positions.len() - 1) as u32
+        })
+    };
+
+    for z in 0..nz {
+        progress("surface_nets_mesh", z as f64 / nz as f64);
+        for y in 0..ny {
+            for x in 0..nx {
+                let a = [x, y, z];
+                for axis in 0..3 {
+                    let mut b = a;
+                    b[axis] += 1;
+                    if b[axis] >= grid.dims[axis] || is_occupied(a) == is_occupied(b) {
+                        continue;
+                    }
+                    // The padding keeps occupied voxels off the border, so
+                    // all four cubes around this voxel edge exist.
+                    let (u, w) = ((axis + 1) % 3, (axis + 2) % 3);
+                    let quad = [(1, 1), (0, 1), (0, 0), (1, 0)].map(|(du, dw)| {
+                        let mut cube = a;
+                        cube[u] -= du;
+                        cube[w] -= dw;
+                        vertex(cube)
+                    });
+                    let mut outward = Vector3::zeros();
+                    outward[axis] = if is_occupied(a) { 1.0 } else { -1.0 };
+                    quads.push((quad, outward));
+                }
+            }
+        }
+    }
+    progress("surface_nets_mesh", 1.0);
+    (positions, cubes, quads)
+}
+
+/// `passes` rounds of moving every vertex to the mean of its quad
+/// neighbours, clamped to its cube.
+fn relax(
+    grid: &Grid,
+    mut positions: Vec<Vector3<f64>>,
+    cubes: &[[usize; 3]],
+    quads: &[Quad],
+    passes: usize,
+) -> Vec<Vector3<f64>> {
+    if passes == 0 {
+        return positions;
+    }
+    let mut neighbours: Vec<Vec<u32>> = vec![Vec::new(); positions.len()];
+    for (quad, _) in quads {
+        for k in 0..4 {
+            let (a, b) = (quad[k], quad[(k + 1) % 4]);
+            if !neighbours[a as usize].contains(&b) {
+                neighbours[a as usize].push(b);
+                neighbours[b as usize].push(a);
+            }
+        }
+    }
+    for _ in 0..passes {
+        positions = (0..positions.len())
+            .map(|i| {
+                if neighbours[i].is_empty() {
+                    return positions[i];
+                }
+                let mean = neighbours[i]
+                    .iter()
+                    .map(|&n| positions[n as usize])
+                    .sum::<Vector3<f64>>()
+                    / neighbours[i].len() as f64;
+                let lo = grid.center(cubes[i]);
+                let hi = lo.add_scalar(grid.voxel_size);
+                mean.zip_zip_map(&lo, &hi, |p, lo, hi| p.clamp(lo, hi))
+            })
+            .collect();
+    }
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kernel::KernelType;
+    use nalgebra::Point3;
+
+    fn sphere(count: usize) -> Vec<PointNormal> {
+        let golden = std::f64::consts::PI * (3.0 - 5.0_f64.sqrt());
+        (0..count)
+            .map(|i| {
+                let y = 1.0 - 2.0 * (i as f64 + 0.5) / count as f64;
+                let r = (1.0 - y * y).sqrt();
+                let theta = golden * i as f64;
+                let n = Vector3::new(r * theta.cos(), y, r * theta.sin());
+                PointNormal {
+                    point: Point3::from(n),
+                    normal: n,
+                    scale: Vector3::new(0.05, 0.05, 0.01),
+                    opacity: 1.0,
+                    color: [0.5; 3],
+                    index: i as u32,
+                }
+            })
+            .collect()
+    }
+
+    fn options(smoothing: usize) -> SurfaceNetsOptions {
+        SurfaceNetsOptions {
+            voxel_size: 0.1,
+            threshold: 0.5,
+            kernel: SplatKernel {
+                kind: KernelType::Gaussian,
+                sigma: 1.0,
+                max_radius_cells: 2.0,
+            },
+            smoothing,
+        }
+    }
+
+    #[test]
+    fn sphere_shell_is_closed_and_faces_out_of_the_solid() {
+        let build = reconstruct(&sphere(4000), &options(2), &|_, _| {});
+        assert!(build.occupied_voxels > 0);
+        assert!(!build.indices.is_empty());
+
+        let mut edges: HashMap<(u32, u32), usize> = HashMap::new();
+        for tri in build.indices.chunks(3) {
+            for k in 0..3 {
+                let (a, b) = (tri[k], tri[(k + 1) % 3]);
+                *edges.entry((a.min(b), a.max(b))).or_default() += 1;
+            }
+        }
+        assert!(
+            edges.values().all(|&n| n % 2 == 0),
+            "surface has open edges"
+        );
+
+        let vertex = |i: u32| {
+            let v = &build.vertices[i as usize * 3..i as usize * 3 + 3];
+            Vector3::new(f64::from(v[0]), f64::from(v[1]), f64::from(v[2]))
+        };
+        // The shell around the sampled sphere: outer skin faces out, inner
+        // skin faces the hollow centre.
+        for tri in build.indices.chunks(3) {
+            let [a, b, c] = [0, 1, 2].map(|k| vertex(tri[k]));
+            let centroid = (a + b + c) / 3.0;
+            let facing = (b - a).cross(&(c - a)).dot(&centroid);
+            assert!(
+                (centroid.norm() - 1.0).abs() < 0.35,
+                "stray face at {:?}",
+                centroid
+            );
+            if centroid.norm() > 1.15 {
+                assert!(facing >= 0.0);
+            } else if centroid.norm() < 0.85 {
+                assert!(facing <= 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn smoothing_keeps_vertices_in_their_cubes() {
+        let points = sphere(2000);
+        let rough = reconstruct(&points, &options(0), &|_, _| {});
+        let smooth = reconstruct(&points, &options(4), &|_, _| {});
+        assert_eq!(rough.indices.len(), smooth.indices.len());
+        for (r, s) in rough.vertices.chunks(3).zip(smooth.vertices.chunks(3)) {
+            for k in 0..3 {
+                assert!((r[k] - s[k]).abs() <= 0.1 + 1e-5);
+            }
+        }
+        assert_ne!(rough.vertices, smooth.vertices);
+    }
+}
@@ -0,0 +1,497 @@
+//! 2.5D terrain triangulation (`mode = 5`).
+//!
+//! Outdoor terrain is a height field over the ground, and a regular grid
+//! spends as many triangles on a flat meadow as on a ridge, while still
+//! aliasing the ridge wherever it runs across the cells. Mode 5 projects the
+//! ground-facing splats onto the RANSAC ground plane, bins them into candidate
+//! heights, and grows a Delaunay triangulation by greedy insertion (Garland
+//! and Heckbert, "Fast Polygonal Approximation of Terrains and Height
+//! Fields"): the candidate furthest from the current surface goes in next,
+//! until every candidate is within `terrain_max_error` or the mesh holds
+//! `terrain_max_vertices`. Flat ground stays a few large triangles and ridges
+//! collect vertices along their crest.
+//!
+//! The triangulation is bounded by the footprint rectangle, whose edges stay
+//! fixed as hull edges, and trimmed to the cells that hold ground before it is
+//! lifted back to 3D along the plane normal.
+
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use nalgebra::Vector3;
+
+use crate::resolution::Resolution;
+use crate::splat::PointNormal;
+use crate::MeshSettings;
+
+/// Cap on candidate cells; the cell grows until the footprint fits.
+const MAX_CELLS: usize = 1_000_000;
+
+const NONE: usize = usize::MAX;
+
+#[derive(Clone, Debug)]
+pub struct TerrainOptions {
+    /// `terrain_cell_size`, or derived from `resolution` over the footprint.
+    pub cell_size: Option<f64>,
+    pub resolution: Resolution,
+    /// Height error, in metres, at which insertion stops.
+    pub max_error: f64,
+    pub max_vertices: usize,
+    /// Smallest `|cos|` between a splat normal and the plane normal for the
+    /// splat to count as ground.
+    pub min_alignment: f64,
+}
+
+impl TerrainOptions {
+    pub fn from_settings(settings: &MeshSettings) -> Self {
+        Self {
+            cell_size: settings
+                .terrain_cell_size
+                .filter(|v| v.is_finite() && *v > 0.0),
+            resolution: Resolution::from_settings(settings),
+            max_error: settings
+                .terrain_max_error
+                .filter(|v| v.is_finite() && *v >= 0.0)
+                .unwrap_or(0.05),
+            max_vertices: settings.terrain_max_vertices.unwrap_or(20_000).max(4),
+            min_alignment: settings
+                .terrain_max_slope
+                .filter(|v| v.is_finite())
+                .unwrap_or(60.0)
+                .clamp(0.0, 90.0)
+                .to_radians()
+                .cos(),
+        }
+    }
+}
+
+/// The lifted triangulation and what it was built from.
+pub struct Terrain {
+    pub vertices: Vec<f32>,
+    pub indices: Vec<u32>,
+    pub cell_size: f64,
+    /// Candidate heights, one per cell holding ground.
+    pub candidates: usize,
+    /// Largest height gap left between a candidate and the surface.
+    pub max_error: f64,
+}
+
+impl Terrain {
+    fn empty(cell_size: f64) -> Self {
+        Terrain {
+            vertices: vec![],
+            indices: vec![],
+            cell_size,
+            candidates: 0,
+            max_error: 0.0,
+        }
+    }
+}
+
+struct Triangle {
+    /// Corners, counter-clockwise in `(u, v)`.
+    v: [usize; 3],
+    /// Neighbour across edge `v[i] -> v[i + 1]`, or [`NONE`] on the hull.
+    nbr: [usize; 3],
+    alive: bool,
+    /// Candidates not yet inserted that lie inside.
+    points: Vec<usize>,
+    /// The worst of them: height error and candidate.
+    worst: Option<(f64, usize)>,
+}
+
+/// Twice the signed area of `abc` in `(u, v)`; positive when counter-clockwise.
+fn orient(a: [f64; 3], b: [f64; 3], c: [f64; 3]) -> f64 {
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+}
+
+/// Positive when `p` is inside the circumcircle of counter-clockwise `abc`.
+fn in_circle(a: [f64; 3], b: [f64; 3], c: [f64; 3], p: [f64; 3]) -> f64 {
+    let [ax, ay, bx, by, cx, cy] = [
+        a[0] - p[0],
+        a[1] - p[1],
+        b[0] - p[0],
+        b[1] - p[1],
+        c[0] - p[0],
+        c[1] - p[1],
+    ];
+    (ax * ax + ay * ay) * (bx * cy - cx * by) - (bx * bx + by * by) * (ax * cy - cx * ay)
+        + (cx * cx + cy * cy) * (ax * by - bx * ay)
+}
+
+struct Tin {
+    vertices: Vec<[f64; 3]>,
+    candidates: Vec<[f64; 3]>,
+    triangles: Vec<Triangle>,
+    heap: BinaryHeap<(u64, usize)>,
+}
+
+impl Tin {
+    /// How far inside triangle `t` the point is: the smallest of its
+    /// normalized barycentric weights, negative outside.
+    fn inside(&self, t: usize, p: [f64; 3]) -> f64 {
+        let [a, b, c] = self.triangles[t].v.map(|i| self.vertices[i]);
+        let area = orient(a, b, c);
+        (orient(b, c, p) / area)
+            .min(orient(c, a, p) / area)
+            .min(orient(a, b, p) / area)
+    }
+
+    /// Height of the surface of triangle `t` above `p`'s `(u, v)`.
+    fn height(&self, t: usize, p: [f64; 3]) -> f64 {
+        let [a, b, c] = self.triangles[t].v.map(|i| self.vertices[i]);
+        let area = orient(a, b, c);
+        (orient(b, c, p) * a[2] + orient(c, a, p) * b[2] + orient(a, b, p) * c[2]) / area
+    }
+
+    /// Hand `points` to the best containing triangle of `tris`.
+    fn distribute(&mut self, points: Vec<usize>, tris: &[usize]) {
+        for c in points {
+            let p = self.candidates[c];
+            let best = tris
+                .iter()
+                .copied()
+                .max_by(|&x, &y| self.inside(x, p).total_cmp(&self.inside(y, p)));
+            if let Some(best) = best {
+                self.triangles[best].points.push(c);
+            }
+        }
+        for &t in tris {
+            let worst = self.triangles[t]
+                .points
+                .iter()
+                .map(|&c| {
+                    let p = self.candidates[c];
+                    ((p[2] - self.height(t, p)).abs(), c)
+                })
+                .max_by(|x, y| x.0.total_cmp(&y.0));
+            self.triangles[t].worst = worst;
+            if let Some((error, _)) = worst {
+                self.heap.push((error.to_bits(), t));
+            }
+        }
+    }
+
+    /// Insert candidate `c`, which lies in triangle `start` (Bowyer-Watson).
+    fn insert(&mut self, c: usize, start: usize) {
+        let p = self.candidates[c];
+        let new_vertex = self.vertices.len();
+        self.vertices.push(p);
+
+        // The cavity: every triangle whose circumcircle holds p, which is a
+        // connected star around it.
+        let mut cavity = vec![start];
+        let mut in_cavity = HashSet::from([start]);
+        let mut i = 0;
+        while i < cavity.len() {
+            let t = cavity[i];
+            i += 1;
+            for n in self.triangles[t].nbr {
+                if n == NONE || in_cavity.contains(&n) {
+                    continue;
+                }
+                let [a, b, cc] = self.triangles[n].v.map(|k| self.vertices[k]);
+                if in_circle(a, b, cc, p) > 0.0 {
+                    in_cavity.insert(n);
+                    cavity.push(n);
+                }
+            }
+        }
+
+        // Fan p over the cavity boundary, wired to the outside neighbours and
+        // to each other.
+        let mut created = Vec::new();
+        let mut by_first = HashMap::new();
+        let mut by_second = HashMap::new();
+        for &t in &cavity {
+            for e in 0..3 {
+                let outer = self.triangles[t].nbr[e];
+                if outer != NONE && in_cavity.contains(&outer) {
+                    continue;
+                }
+                let (a, b) = (self.triangles[t].v[e], self.triangles[t].v[(e + 1) % 3]);
+                let id = self.triangles.len();
+                if outer != NONE {
+                    for slot in self.triangles[outer].nbr.iter_mut() {
+                        if *slot == t {
+                            *slot = id;
+                        }
+                    }
+                }
+                self.triangles.push(Triangle {
+                    v: [a, b, new_vertex],
+                    nbr: [outer, NONE, NONE],
+                    alive: true,
+                    points: Vec::new(),
+                    worst: None,
+                });
+                by_first.insert(a, id);
+                by_second.insert(b, id);
+                created.push(id);
+            }
+        }
+        for &id in &created {
+            let [a, b, _] = self.triangles[id].v;
+            self.triangles[id].nbr[1] = by_first[&b];
+            self.triangles[id].nbr[2] = by_second[&a];
+        }
+
+        let mut orphans = Vec::new();
+        for &t in &cavity {
+            self.triangles[t].alive = false;
+            orphans.extend(
+                std::mem::take(&mut self.triangles[t].points)
+                    .into_iter()
+                    .filter(|&o| o != c),
+            );
+        }
+        self.distribute(orphans, &created);
+    }
+}
+
+/// Triangulate the ground-facing `points` over the plane `normal . p + d = 0`.
+pub fn build(
+    points: &[PointNormal],
+    normal: Vector3<f64>,
+    d: f64,
+    options: &TerrainOptions,
+) -> Terrain {
+    // Heights are measured up, whichever way RANSAC returned the plane.
+    let (normal, d) = if normal.y < 0.0 {
+        (-normal, -d)
+    } else {
+        (normal, d)
+    };
+    let mut tangent = if normal.x.abs() < 0.9 {
+        Vector3::x()
+    } else {
+        Vector3::y()
+    };
+    tangent = (tangent - normal * normal.dot(&tangent)).normalize();
+    let bitangent = normal.cross(&tangent);
+
+    let projected: Vec<[f64; 3]> = points
+        .iter()
+        .filter(|p| p.normal.dot(&normal).abs() >= options.min_alignment)
+        .map(|p| {
+            let q = p.point.coords;
+            [q.dot(&tangent), q.dot(&bitangent), q.dot(&normal) + d]
+        })
+        .collect();
+    let (lo, hi) = projected.iter().fold(
+        ([f64::INFINITY; 2], [f64::NEG_INFINITY; 2]),
+        |(lo, hi), p| {
+            (
+                [lo[0].min(p[0]), lo[1].min(p[1])],
+                [hi[0].max(p[0]), hi[1].max(p[1])],
+            )
+        },
+    );
+    let extent = [(hi[0] - lo[0]).max(0.0), (hi[1] - lo[1]).max(0.0)];
+    let mut cell_size = options
+        .cell_size
+        .unwrap_or_else(|| options.resolution.cell_size(extent[0], extent[1]))
+        .clamp(0.01, 2.0);
+    if projected.len() < 3 {
+        return Terrain::empty(cell_size);
+    }
+    let dims = |cell: f64| {
+        [
+            (extent[0] / cell).floor() as usize + 1,
+            (extent[1] / cell).floor() as usize + 1,
+        ]
+    };
+    while dims(cell_size)[0] * dims(cell_size)[1] > MAX_CELLS {
+        cell_size *= 1.25;
+    }
+    let [cols, rows] = dims(cell_size);
+    let cell_of = |u: f64, v: f64| -> Option<usize> {
+        let (x, y) = ((u - lo[0]) / cell_size, (v - lo[1]) / cell_size);
+        (x >= 0.0 && y >= 0.0 && (x as usize) < cols && (y as usize) < rows)
+            .then(|| y as usize * cols + x as usize)
+    };
+
+    // One candidate per cell: its point of median height.
+    let mut binned: Vec<(usize, [f64; 3])> = projected
+        .iter()
+        .filter_map(|&p| cell_of(p[0], p[1]).map(|cell| (cell, p)))
+        .collect();
+    binned.sort_by(|x, y| x.0.cmp(&y.0).then(x.1[2].total_cmp(&y.1[2])));
+    let mut occupied = vec![false; cols * rows];
+    let mut candidates = Vec::new();
+    for group in binned.chunk_by(|x, y| x.0 == y.0) {
+        occupied[group[0].0] = true;
+        candidates.push(group[group.len() / 2].1);
+    }
+    // Non-finite points fall in no cell.
+    if candidates.is_empty() {
+        return Terrain::empty(cell_size);
+    }
+
+    // Corners half a cell outside the data, so no candidate lies on the hull,
+    // at the height of the nearest candidate.
+    let margin = cell_size / 2.0;
+    let corners = [
+        [lo[0] - margin, lo[1] - margin],
+        [hi[0] + margin, lo[1] - margin],
+        [hi[0] + margin, hi[1] + margin],
+        [lo[0] - margin, hi[1] + margin],
+    ]
+    .map(|[u, v]| {
+        let nearest = candidates.iter().min_by(|a, b| {
+            let da = (a[0] - u).powi(2) + (a[1] - v).powi(2);
+            let db = (b[0] - u).powi(2) + (b[1] - v).powi(2);
+            da.total_cmp(&db)
+        });
+        [u, v, nearest.map_or(0.0, |p| p[2])]
+    });
+    let candidate_count = candidates.len();
+    let mut tin = Tin {
+        vertices: corners.to_vec(),
+        candidates,
+        triangles: vec![
+            Triangle {
+                v: [0, 1, 2],
+                nbr: [NONE, NONE, 1],
+                alive: true,
+                points: Vec::new(),
+                worst: None,
+            },
+            Triangle {
+                v: [0, 2, 3],
+                nbr: [0, NONE, NONE],
+                alive: true,
+                points: Vec::new(),
+                worst: None,
+            },
+        ],
+        heap: BinaryHeap::new(),
+    };
+    tin.distribute((0..candidate_count).collect(), &[0, 1]);
+
+    // Error bits order like the errors: they are never negative.
+    let mut max_error = 0.0;
+    while let Some((bits, t)) = tin.heap.pop() {
+        if !tin.triangles[t].alive {
+            continue;
+        }
+        max_error = f64::from_bits(bits);
+        if max_error <= options.max_error || tin.vertices.len() >= options.max_vertices {
+            break;
+        }
+        let (_, c) = tin.triangles[t]
+            .worst
+            .expect("queued triangles have points");
+        tin.insert(c, t);
+        max_error = 0.0;
+    }
+
+    // Keep the triangles over ground and lift their corners back to 3D.
+    let mut remap = vec![u32::MAX; tin.vertices.len()];
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    for triangle in tin.triangles.iter().filter(|t| t.alive) {
+        let corners = triangle.v.map(|i| tin.vertices[i]);
+        let centroid = [0, 1].map(|k| corners.iter().map(|c| c[k]).sum::<f64>() / 3.0);
+        if !cell_of(centroid[0], centroid[1]).is_some_and(|cell| occupied[cell]) {
+            continue;
+        }
+        for i in triangle.v {
+            if remap[i] == u32::MAX {
+                remap[i] = (vertices.len() / 3) as u32;
+                let [u, v, h] = tin.vertices[i];
+                let p = tangent * u + bitangent * v + normal * (h - d);
+                vertices.extend([p.x as f32, p.y as f32, p.z as f32]);
+            }
+            indices.push(remap[i]);
+        }
+    }
+
+    Terrain {
+        vertices,
+        indices,
+        cell_size,
+        candidates: candidate_count,
+        max_error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::splat::test_splat;
+
+    #[test]
+    fn flat_ground_stays_coarse_and_a_ridge_gets_vertices() {
+        let options = TerrainOptions {
+            cell_size: Some(0.5),
+            resolution: Resolution::default(),
+            max_error: 0.05,
+            max_vertices: 10_000,
+            min_alignment: 0.5,
+        };
+        let grid = |height: &dyn Fn(f64) -> f64| -> Vec<PointNormal> {
+            (0..40)
+                .flat_map(|i| (0..40).map(move |j| (i as f64 * 0.25, j as f64 * 0.25)))
+                .map(|(x, z)| test_splat([x, height(x), z], [0.0, 1.0, 0.0]))
+                .collect()
+        };
+
+        let flat = build(&grid(&|_| 1.0), Vector3::y(), -1.0, &options);
+        assert_eq!(flat.candidates, 20 * 20);
+        assert!(flat.indices.len() / 3 <= 4);
+        assert!(flat.max_error <= 0.05);
+        assert!(flat
+            .vertices
+            .chunks_exact(3)
+            .all(|v| (v[1] - 1.0).abs() < 1e-6));
+
+        // A 1 m ridge along z at x = 5.
+        let ridge = build(
+            &grid(&|x| (1.0 - (x - 5.0).abs()).max(0.0)),
+            Vector3::y(),
+            0.0,
+            &options,
+        );
+        let faces = ridge.indices.len() / 3;
+        assert!(faces > 4 && faces < 200, "{} faces", faces);
+        assert!(ridge.max_error <= 0.05);
+        let peak = ridge
+            .vertices
+            .chunks_exact(3)
+            .map(|v| v[1])
+            .fold(0.0, f32::max);
+        assert!(peak > 0.9);
+        // Every face points up.
+        for f in ridge.indices.chunks_exact(3) {
+            let p = |i: u32| {
+                let i = i as usize * 3;
+                Vector3::new(
+                    ridge.vertices[i] as f64,
+                    ridge.vertices[i + 1] as f64,
+                    ridge.vertices[i + 2] as f64,
+                )
+            };
+            let n = (p(f[1]) - p(f[0])).cross(&(p(f[2]) - p(f[0])));
+            assert!(n.y > 0.0);
+        }
+    }
+
+    #[test]
+    fn non_finite_points_give_an_empty_terrain() {
+        let points = vec![test_splat([f64::NAN, 0.0, 0.0], [0.0, 1.0, 0.0]); 5];
+        let terrain = build(
+            &points,
+            Vector3::y(),
+            0.0,
+            &TerrainOptions {
+                cell_size: Some(0.5),
+                resolution: Resolution::default(),
+                max_error: 0.05,
+                max_vertices: 10_000,
+                min_alignment: 0.5,
+            },
+        );
+        assert_eq!(terrain.candidates, 0);
+        assert!(terrain.vertices.is_empty() && terrain.indices.is_empty());
+    }
+}
@@ -0,0 +1,295 @@
+//! Threaded bakes: what the build and the page allow, and the pool.
+//!
+//! Threaded WebAssembly shares its memory between workers, which needs a build
+//! compiled with atomics and a `SharedArrayBuffer`. Browsers only expose the
+//! latter on cross-origin-isolated pages (served with `COOP: same-origin` and
+//! `COEP: require-corp`), so the same bundle can land on a deployment that
+//! could never run it threaded. [`probe`] looks at the build and the page and
+//! reports what they support and why, so an app can warn about a
+//! non-isolated deployment. The detected environment is kept on the running
+//! `SplatwalkContext`, like the journal.
+//!
+//! Builds with the `threads` cargo feature (and atomics) carry a rayon pool
+//! from `wasm-bindgen-rayon`. [`start`] spawns it when the report allows
+//! threads, and from then on the loops in [`crate::parallel`] fan out over it
+//! and `execution` reads `"multi_thread"`. Every other build and environment
+//! keeps running bakes on the calling thread.
+
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use js_sys::Promise;
+use serde::Serialize;
+use wasm_bindgen::JsValue;
+
+use crate::context;
+
+/// Workers in the running pool; 0 until [`start`] has one up. The pool is
+/// global to the module instance, unlike the per-context environment.
+static POOL_THREADS: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// The first [`start`] call's promise, handed to every later caller.
+    static STARTED: RefCell<Option<Promise>> = const { RefCell::new(None) };
+}
+
+/// What the build and the page allow.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Environment {
+    /// The module was compiled with WebAssembly atomics and the `threads`
+    /// feature.
+    pub threaded_build: bool,
+    /// `globalThis.crossOriginIsolated`; `true` outside browsers, which do
+    /// not gate shared memory on it.
+    pub cross_origin_isolated: bool,
+    /// `SharedArrayBuffer` is defined.
+    pub shared_array_buffer: bool,
+    /// No `document` on `globalThis`: a worker or a non-browser host, where a
+    /// bake may block while the pool works. Browsers forbid that on the page.
+    pub can_block: bool,
+    /// `navigator.hardwareConcurrency`, at least 1.
+    pub hardware_concurrency: usize,
+    /// Workers in the running pool, 0 before [`start`].
+    pub pool_threads: usize,
+}
+
+/// What the environment supports and why, as `splatwalk_thread_support`
+/// returns it.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ThreadSupport {
+    /// How bakes run: `"multi_thread"` once [`start`] has a pool up, else
+    /// `"single_thread"`.
+    pub execution: &'static str,
+    /// `"multi_thread"` when the build and the page would allow threads,
+    /// else `"single_thread"`.
+    pub supported_mode: &'static str,
+    /// Threads the environment would allow: `hardware_concurrency` when
+    /// threads are supported, else 1.
+    pub usable_threads: usize,
+    /// `"full"`, or `"reduced"` when the build or the page keeps threads off
+    /// a multi-core device. An app warns on `"reduced"`.
+    pub performance_class: &'static str,
+    /// Why threads are unsupported: `"single_threaded_build"`,
+    /// `"no_shared_array_buffer"`, `"not_cross_origin_isolated"`,
+    /// `"main_thread"` or `"single_core"`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<&'static str>,
+    pub threaded_build: bool,
+    pub cross_origin_isolated: bool,
+    pub shared_array_buffer: bool,
+    pub hardware_concurrency: usize,
+    pub pool_threads: usize,
+}
+
+/// The support `env` gives. Threads need a threaded build, shared memory, a
+/// thread that may block and a second core; the first piece missing is the
+/// reason, build first.
+pub fn assess(env: Environment) -> ThreadSupport {
+    let reason = if !env.threaded_build {
+        Some("single_threaded_build")
+    } else if !env.shared_array_buffer {
+        Some("no_shared_array_buffer")
+    } else if !env.cross_origin_isolated {
+        Some("not_cross_origin_isolated")
+    } else if !env.can_block {
+        Some("main_thread")
+    } else if env.hardware_concurrency < 2 {
+        Some("single_core")
+    } else {
+        None
+    };
+    let threads = if reason.is_none() {
+        env.hardware_concurrency
+    } else {
+        1
+    };
+    ThreadSupport {
+        execution: if env.pool_threads > 1 {
+            "multi_thread"
+        } else {
+            "single_thread"
+        },
+        supported_mode: if threads > 1 {
+            "multi_thread"
+        } else {
+            "single_thread"
+        },
+        usable_threads: threads,
+        performance_class: if threads < env.hardware_concurrency {
+            "reduced"
+        } else {
+            "full"
+        },
+        reason,
+        threaded_build: env.threaded_build,
+        cross_origin_isolated: env.cross_origin_isolated,
+        shared_array_buffer: env.shared_array_buffer,
+        hardware_concurrency: env.hardware_concurrency,
+        pool_threads: env.pool_threads,
+    }
+}
+
+/// Read the build and `globalThis`.
+pub fn detect() -> Environment {
+    let get = |target: &JsValue, key: &str| {
+        js_sys::Reflect::get(target, &JsValue::from_str(key)).unwrap_or(JsValue::UNDEFINED)
+    };
+    let global: JsValue = js_sys::global().into();
+    let navigator = get(&global, "navigator");
+    let cores = if navigator.is_object() {
+        get(&navigator, "hardwareConcurrency").as_f64()
+    } else {
+        None
+    };
+    Environment {
+        threaded_build: cfg!(all(feature = "threads", target_feature = "atomics")),
+        cross_origin_isolated: get(&global, "crossOriginIsolated")
+            .as_bool()
+            .unwrap_or(true),
+        shared_array_buffer: get(&global, "SharedArrayBuffer").is_function(),
+        can_block: get(&global, "document").is_undefined(),
+        hardware_concurrency: cores
+            .filter(|n| n.is_finite() && *n >= 1.0)
+            .map_or(1, |n| n as usize),
+        pool_threads: pool_threads(),
+    }
+}
+
+/// Workers in the running pool, 0 before [`start`].
+pub fn pool_threads() -> usize {
+    POOL_THREADS.load(Ordering::Relaxed)
+}
+
+/// Detect support and record the environment on the running context.
+/// Calling it again re-detects.
+pub fn probe() -> ThreadSupport {
+    let env = detect();
+    *context::current().thread_support.borrow_mut() = Some(env);
+    assess(env)
+}
+
+/// The recorded support, probing first if `init_splatwalk` was skipped. The
+/// pool size is read live, so this reflects a [`start`] made since.
+pub fn current() -> ThreadSupport {
+    let recorded = *context::current().thread_support.borrow();
+    match recorded {
+        Some(env) => assess(Environment {
+            pool_threads: pool_threads(),
+            ..env
+        }),
+        None => probe(),
+    }
+}
+
+/// Spawn the pool with `usable_threads` workers if the report allows threads,
+/// and settle with the report once it is up. Without threads, or once
+/// started, it settles straight away. Later calls share the first one's
+/// promise, since the pool can only be built once.
+pub fn start() -> Promise {
+    if let Some(started) = STARTED.with(|started| started.borrow().clone()) {
+        return started;
+    }
+    let support = current();
+    let started = if support.supported_mode == "multi_thread" && pool_threads() == 0 {
+        spawn_pool(support.usable_threads)
+    } else {
+        Promise::resolve(&report())
+    };
+    STARTED.with(|slot| *slot.borrow_mut() = Some(started.clone()));
+    started
+}
+
+/// The current report as a JS value, `undefined` if it does not serialize.
+fn report() -> JsValue {
+    serde_wasm_bindgen::to_value(&current()).unwrap_or(JsValue::UNDEFINED)
+}
+
+#[cfg(all(feature = "threads", target_arch = "wasm32"))]
+fn spawn_pool(threads: usize) -> Promise {
+    use wasm_bindgen::closure::Closure;
+
+    let pending = wasm_bindgen_rayon::init_thread_pool(threads);
+    Promise::new(&mut |resolve, reject| {
+        let ready = Closure::once(move |_: JsValue| {
+            POOL_THREADS.store(threads, Ordering::Relaxed);
+            let _ = resolve.call1(&JsValue::NULL, &report());
+        });
+        let failed = Closure::once(move |err: JsValue| {
+            let _ = reject.call1(&JsValue::NULL, &err);
+        });
+        let _ = pending.then2(&ready, &failed);
+        ready.forget();
+        failed.forget();
+    })
+}
+
+/// Builds without the pool never report `multi_thread`, so never get here.
+#[cfg(not(all(feature = "threads", target_arch = "wasm32")))]
+fn spawn_pool(_threads: usize) -> Promise {
+    Promise::resolve(&report())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ISOLATED: Environment = Environment {
+        threaded_build: true,
+        cross_origin_isolated: true,
+        shared_array_buffer: true,
+        can_block: true,
+        hardware_concurrency: 8,
+        pool_threads: 0,
+    };
+
+    #[test]
+    fn isolated_threaded_builds_could_use_every_core() {
+        let support = assess(ISOLATED);
+        assert_eq!(support.supported_mode, "multi_thread");
+        assert_eq!(support.usable_threads, 8);
+        assert_eq!(support.performance_class, "full");
+        assert_eq!(support.reason, None);
+        // Bakes stay on the calling thread until the pool is up.
+        assert_eq!(support.execution, "single_thread");
+        let running = assess(Environment {
+            pool_threads: 8,
+            ..ISOLATED
+        });
+        assert_eq!(running.execution, "multi_thread");
+    }
+
+    #[test]
+    fn missing_pieces_fall_back_to_one_thread() {
+        let not_isolated = assess(Environment {
+            cross_origin_isolated: false,
+            ..ISOLATED
+        });
+        assert_eq!(not_isolated.supported_mode, "single_thread");
+        assert_eq!(not_isolated.usable_threads, 1);
+        assert_eq!(not_isolated.performance_class, "reduced");
+        assert_eq!(not_isolated.reason, Some("not_cross_origin_isolated"));
+
+        // The build is reported first: isolating the page would not help.
+        let unthreaded = assess(Environment {
+            threaded_build: false,
+            shared_array_buffer: false,
+            ..ISOLATED
+        });
+        assert_eq!(unthreaded.reason, Some("single_threaded_build"));
+
+        // The page itself may not block on a pool.
+        let page = assess(Environment {
+            can_block: false,
+            ..ISOLATED
+        });
+        assert_eq!(page.reason, Some("main_thread"));
+
+        // One core loses nothing by running on it.
+        let single_core = assess(Environment {
+            hardware_concurrency: 1,
+            ..ISOLATED
+        });
+        assert_eq!(single_core.reason, Some("single_core"));
+        assert_eq!(single_core.performance_class, "full");
+    }
+}
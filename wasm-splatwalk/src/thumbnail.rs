@@ -0,0 +1,398 @@
+//! Headless thumbnails of the output mesh (`emit_thumbnail`).
+//!
+//! A bake pipeline that keeps hundreds of scans wants a picture of each
+//! navmesh next to its files, and drawing one used to mean loading the GLB in
+//! a browser. [`render`] rasterizes `mesh` in software from an orbit or
+//! explicit camera into an RGBA image, plus a PNG of the same pixels to write
+//! straight to disk. Faces are lit from over the camera's shoulder and colored
+//! by the baked vertex colors, or by height along the `emit_preview` ramp when
+//! the mesh has none.
+
+use std::io::Write;
+
+use flate2::write::ZlibEncoder;
+use flate2::{Compression, Crc};
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+
+use crate::preview::{ramp, HEIGHT_RAMP};
+use crate::{MeshBuffers, MeshSettings};
+
+/// Largest width or height accepted, in pixels.
+pub const MAX_SIZE: usize = 4096;
+
+/// Share of the light that reaches faces turned away from it.
+const AMBIENT: f64 = 0.35;
+
+/// `emit_thumbnail`.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ThumbnailSettings {
+    /// Image size in pixels (default 256 x 256).
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    /// Orbit camera: degrees about `+Y` from `+Z` towards `+X` (default 45)
+    /// and above the horizon (default 35). The camera backs off until the
+    /// mesh bounds fit the frame.
+    pub azimuth: Option<f64>,
+    pub elevation: Option<f64>,
+    /// Vertical field of view in degrees (default 40).
+    pub fov: Option<f64>,
+    /// `[x, y, z]` camera position, replacing the orbit.
+    pub eye: Option<Vec<f64>>,
+    /// `[x, y, z]` the camera looks at (default the centre of the bounds).
+    pub target: Option<Vec<f64>>,
+    /// RGBA background, 0-255 (default transparent).
+    pub background: Option<[u8; 4]>,
+}
+
+/// Result `thumbnail`.
+#[derive(Serialize)]
+pub struct Thumbnail {
+    pub width: usize,
+    pub height: usize,
+    /// `width * height * 4` RGBA bytes, row-major from the top row.
+    pub pixels: serde_bytes::ByteBuf,
+    /// The same image as a PNG file.
+    pub png: serde_bytes::ByteBuf,
+    /// The camera, in `splatwalk_oriented`.
+    pub eye: [f64; 3],
+    pub target: [f64; 3],
+}
+
+/// Sizes must be 1 to [`MAX_SIZE`], the field of view between 0 and 180
+/// degrees, angles finite and points finite `[x, y, z]`.
+pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+    let Some(thumbnail) = &settings.emit_thumbnail else {
+        return Ok(());
+    };
+    for (name, size) in [("width", thumbnail.width), ("height", thumbnail.height)] {
+        if let Some(size) = size {
+            if !(1..=MAX_SIZE).contains(&size) {
+                return Err(format!(
+                    "Invalid emit_thumbnail.{}: {} (expected 1 to {})",
+                    name, size, MAX_SIZE
+                ));
+            }
+        }
+    }
+    if let Some(fov) = thumbnail.fov {
+        if !(fov > 0.0 && fov < 180.0) {
+            return Err(format!("Invalid emit_thumbnail.fov: {}", fov));
+        }
+    }
+    for (name, angle) in [
+        ("azimuth", thumbnail.azimuth),
+        ("elevation", thumbnail.elevation),
+    ] {
+        if angle.is_some_and(|a| !a.is_finite()) {
+            return Err(format!("Invalid emit_thumbnail.{}", name));
+        }
+    }
+    for (name, point) in [("eye", &thumbnail.eye), ("target", &thumbnail.target)] {
+        if let Some(point) = point {
+            if point.len() != 3 || point.iter().any(|c| !c.is_finite()) {
+                return Err(format!(
+                    "emit_thumbnail.{} must be a finite [x, y, z] point",
+                    name
+                ));
+            }
+        }
+    }
+    if thumbnail.eye.is_some() && thumbnail.eye == thumbnail.target {
+        return Err("emit_thumbnail.eye and target must differ".to_string());
+    }
+    Ok(())
+}
+
+/// A pinhole camera: `right`, `up` and `forward` are orthonormal.
+struct Camera {
+    eye: Vector3<f64>,
+    right: Vector3<f64>,
+    up: Vector3<f64>,
+    forward: Vector3<f64>,
+    /// `1 / tan(fov / 2)`.
+    focal: f64,
+    aspect: f64,
+}
+
+impl Camera {
+    fn looking_at(eye: Vector3<f64>, target: Vector3<f64>, fov: f64, aspect: f64) -> Self {
+        let forward = (target - eye).normalize();
+        // Straight down (or up) the world up is degenerate; use +Z instead.
+        let world_up = if forward.y.abs() > 0.999 {
+            Vector3::z()
+        } else {
+            Vector3::y()
+        };
+        let right = forward.cross(&world_up).normalize();
+        Camera {
+            eye,
+            right,
+            up: right.cross(&forward),
+            forward,
+            focal: 1.0 / (fov.to_radians() / 2.0).tan(),
+            aspect,
+        }
+    }
+
+    /// Pixel coordinates and view depth of `p`.
+    fn project(&self, p: Vector3<f64>, width: usize, height: usize) -> [f64; 3] {
+        let d = p - self.eye;
+        let depth = d.dot(&self.forward);
+        let x = self.focal * d.dot(&self.right) / depth / self.aspect;
+        let y = self.focal * d.dot(&self.up) / depth;
+        [
+            (x + 1.0) * 0.5 * width as f64,
+            (1.0 - y) * 0.5 * height as f64,
+            depth,
+        ]
+    }
+}
+
+/// Draw `mesh` (in `splatwalk_oriented`) as `thumbnail` asks.
+pub fn render(mesh: &MeshBuffers, thumbnail: &ThumbnailSettings) -> Result<Thumbnail, String> {
+    let width = thumbnail.width.unwrap_or(256);
+    let height = thumbnail.height.unwrap_or(256);
+    let fov = thumbnail.fov.unwrap_or(40.0);
+    let aspect = width as f64 / height as f64;
+
+    let positions: Vec<Vector3<f64>> = mesh
+        .vertices
+        .chunks_exact(3)
+        .map(|v| Vector3::new(v[0] as f64, v[1] as f64, v[2] as f64))
+        .collect();
+    let (min, max) = positions.iter().fold(
+        (
+            Vector3::repeat(f64::INFINITY),
+            Vector3::repeat(f64::NEG_INFINITY),
+        ),
+        |(lo, hi), p| (lo.inf(p), hi.sup(p)),
+    );
+    let (center, radius) = if positions.is_empty() {
+        (Vector3::zeros(), 1.0)
+    } else {
+        ((min + max) / 2.0, ((max - min).norm() / 2.0).max(1e-3))
+    };
+
+    let point = |c: &[f64]| Vector3::new(c[0], c[1], c[2]);
+    let target = thumbnail.target.as_deref().map_or(center, point);
+    let eye = match thumbnail.eye.as_deref() {
+        Some(eye) => point(eye),
+        None => {
+            let azimuth = thumbnail.azimuth.unwrap_or(45.0).to_radians();
+            let elevation = thumbnail.elevation.unwrap_or(35.0).to_radians();
+            let direction = Vector3::new(
+                elevation.cos() * azimuth.sin(),
+                elevation.sin(),
+                elevation.cos() * azimuth.cos(),
+            );
+            // Far enough that the bounding sphere fits the narrower field of
+            // view, counting an off-centre target.
+            let half_v = fov.to_radians() / 2.0;
+            let half_h = (half_v.tan() * aspect).atan();
+            let reach = radius + (target - center).norm();
+            target + direction * (reach / half_v.min(half_h).sin())
+        }
+    };
+    let camera = Camera::looking_at(eye, target, fov, aspect);
+
+    let (low, high) = (min.y, max.y);
+    let colors: Vec<[f64; 3]> = match &mesh.colors {
+        Some(colors) => colors
+            .chunks_exact(3)
+            .map(|c| [c[0] as f64, c[1] as f64, c[2] as f64])
+            .collect(),
+        None => positions
+            .iter()
+            .map(|p| {
+                let t = if high > low {
+                    (p.y - low) / (high - low)
+                } else {
+                    0.5
+                };
+                ramp(&HEIGHT_RAMP, t)
+            })
+            .collect(),
+    };
+    // From over the camera's left shoulder.
+    let light = (camera.up * 0.6 - camera.right * 0.3 - camera.forward).normalize();
+    let near = radius * 1e-3;
+
+    let background = thumbnail.background.unwrap_or([0; 4]);
+    let mut pixels: Vec<u8> = background.repeat(width * height);
+    // Inverse view depth per pixel: larger is nearer.
+    let mut nearest = vec![0.0f64; width * height];
+    for face in mesh.indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|k| face[k] as usize);
+        let normal = (positions[b] - positions[a]).cross(&(positions[c] - positions[a]));
+        if normal.norm() <= 0.0 {
+            continue;
+        }
+        // Lit the same from both sides.
+        let shade = AMBIENT + (1.0 - AMBIENT) * normal.normalize().dot(&light).abs();
+        let screen = [a, b, c].map(|i| camera.project(positions[i], width, height));
+        if screen.iter().any(|s| s[2] < near) {
+            continue;
+        }
+        let edge = |p: [f64; 3], q: [f64; 3], x: f64, y: f64| {
+            (q[0] - p[0]) * (y - p[1]) - (q[1] - p[1]) * (x - p[0])
+        };
+        let area = edge(screen[0], screen[1], screen[2][0], screen[2][1]);
+        if area.abs() < 1e-12 {
+            continue;
+        }
+        let x0 = screen
+            .iter()
+            .map(|s| s[0])
+            .fold(f64::INFINITY, f64::min)
+            .floor()
+            .max(0.0) as usize;
+        let x1 = (screen
+            .iter()
+            .map(|s| s[0])
+            .fold(f64::NEG_INFINITY, f64::max)
+            .ceil())
+        .min(width as f64) as usize;
+        let y0 = screen
+            .iter()
+            .map(|s| s[1])
+            .fold(f64::INFINITY, f64::min)
+            .floor()
+            .max(0.0) as usize;
+        let y1 = (screen
+            .iter()
+            .map(|s| s[1])
+            .fold(f64::NEG_INFINITY, f64::max)
+            .ceil())
+        .min(height as f64) as usize;
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let (px, py) = (x as f64 + 0.5, y as f64 + 0.5);
+                let weights = [
+                    edge(screen[1], screen[2], px, py) / area,
+                    edge(screen[2], screen[0], px, py) / area,
+                    edge(screen[0], screen[1], px, py) / area,
+                ];
+                if weights.iter().any(|&w| w < 0.0) {
+                    continue;
+                }
+                // 1 / depth is linear across the screen.
+                let inverse: f64 = (0..3).map(|k| weights[k] / screen[k][2]).sum();
+                let pixel = y * width + x;
+                if inverse <= nearest[pixel] {
+                    continue;
+                }
+                nearest[pixel] = inverse;
+                let rgba = &mut pixels[pixel * 4..pixel * 4 + 4];
+                for channel in 0..3 {
+                    let color: f64 = [a, b, c]
+                        .iter()
+                        .zip(weights)
+                        .map(|(&i, w)| colors[i][channel] * w)
+                        .sum();
+                    rgba[channel] = (color * shade * 255.0).round().clamp(0.0, 255.0) as u8;
+                }
+                rgba[3] = 255;
+            }
+        }
+    }
+
+    let png = encode_png(width, height, &pixels)?;
+    Ok(Thumbnail {
+        width,
+        height,
+        pixels: serde_bytes::ByteBuf::from(pixels),
+        png: serde_bytes::ByteBuf::from(png),
+        eye: eye.into(),
+        target: target.into(),
+    })
+}
+
+/// An 8-bit RGBA PNG: unfiltered rows in one zlib stream.
+pub fn encode_png(width: usize, height: usize, rgba: &[u8]) -> Result<Vec<u8>, String> {
+    // Color type 6: RGBA.
+    write_png(width, height, [8, 6], 4, rgba)
+}
+
+/// A 16-bit grayscale PNG of `samples`, row-major from the top row.
+pub fn encode_gray16_png(width: usize, height: usize, samples: &[u16]) -> Result<Vec<u8>, String> {
+    let bytes: Vec<u8> = samples.iter().flat_map(|s| s.to_be_bytes()).collect();
+    // Color type 0: grayscale.
+    write_png(width, height, [16, 0], 2, &bytes)
+}
+
+/// A PNG of `pixels` at `[bit depth, color type]` and `pixel_len` bytes per
+/// pixel, rows unfiltered in one zlib stream.
+fn write_png(
+    width: usize,
+    height: usize,
+    [depth, color]: [u8; 2],
+    pixel_len: usize,
+    pixels: &[u8],
+) -> Result<Vec<u8>, String> {
+    let mut rows = Vec::with_capacity((width * pixel_len + 1) * height);
+    for row in pixels.chunks_exact(width * pixel_len) {
+        rows.push(0);
+        rows.extend_from_slice(row);
+    }
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&rows).map_err(|e| e.to_string())?;
+    let data = encoder.finish().map_err(|e| e.to_string())?;
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // Deflate, no filter method, no interlace.
+    header.extend_from_slice(&[depth, color, 0, 0, 0]);
+
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    for (kind, body) in [
+        (b"IHDR", &header[..]),
+        (b"IDAT", &data[..]),
+        (b"IEND", &[][..]),
+    ] {
+        png.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        png.extend_from_slice(kind);
+        png.extend_from_slice(body);
+        let mut crc = Crc::new();
+        crc.update(kind);
+        crc.update(body);
+        png.extend_from_slice(&crc.sum().to_be_bytes());
+    }
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_floor_fills_the_middle_of_the_frame() {
+        // A 2 x 2 m floor seen from straight above.
+        let mesh = MeshBuffers::new(
+            vec![
+                -1.0, 0.0, -1.0, 1.0, 0.0, -1.0, 1.0, 0.0, 1.0, -1.0, 0.0, 1.0,
+            ],
+            vec![0, 2, 1, 0, 3, 2],
+        );
+        let settings = ThumbnailSettings {
+            width: Some(32),
+            height: Some(16),
+            elevation: Some(90.0),
+            ..Default::default()
+        };
+        let thumbnail = render(&mesh, &settings).unwrap();
+        assert_eq!(thumbnail.pixels.len(), 32 * 16 * 4);
+        let alpha = |x: usize, y: usize| thumbnail.pixels[(y * 32 + x) * 4 + 3];
+        assert_eq!(alpha(16, 8), 255);
+        // The frame is wider than tall, so the sides stay background.
+        assert_eq!((alpha(0, 8), alpha(31, 8)), (0, 0));
+        assert!(thumbnail.eye[1] > 1.0);
+
+        let png = &thumbnail.png;
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(u32::from_be_bytes(png[16..20].try_into().unwrap()), 32);
+        assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+    }
+}
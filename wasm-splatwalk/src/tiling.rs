@@ -0,0 +1,366 @@
+//! Tiled voxel navmeshes (`tile_size`).
+//!
+//! One dense collision grid over a city block either blows the voxel cap and
+//! coarsens until stairs vanish, or fails with `region_too_large`. With
+//! `tile_size`, mode 2 cuts the plan into square tiles and builds the collision
+//! mesh of each on its own grid, pinned to the tile grown by `tile_border` so
+//! the fill, erosion and cleanup near an edge still see the floor beyond it.
+//! Each tile keeps only the faces whose centroid falls in its own square, and
+//! the pieces are welded into one `mesh`. Tile edges sit on multiples of the
+//! voxel size, so neighbouring grids sample the same voxels and the pieces
+//! meet along the seams. The finished mesh is then split back into per-tile
+//! meshes, with a tile index, for engines that stream the navmesh.
+
+use nalgebra::Vector3;
+use serde::Serialize;
+
+use crate::mesh::ReconstructedMesh;
+use crate::reachability;
+use crate::{journal, MeshBuffers, MeshSettings};
+
+/// Default overlap around each tile, in metres.
+pub const DEFAULT_TILE_BORDER: f64 = 1.0;
+
+/// Most tiles a bake is cut into; `tile_size` is doubled until the scene fits.
+pub const MAX_TILES: usize = 4096;
+
+pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+    if let Some(size) = settings.tile_size {
+        if !(size.is_finite() && size > 0.0) {
+            return Err(format!(
+                "Invalid tile_size: {}. Expected a positive length in metres.",
+                size
+            ));
+        }
+        if settings.mode != 2 {
+            return Err("tile_size requires mode 2 (voxel navmesh)".to_string());
+        }
+        if settings.emit_poly_navmesh.unwrap_or(false)
+            || settings.emit_floor_layers.unwrap_or(false)
+            || settings.emit_occupancy_grid.unwrap_or(false)
+        {
+            return Err("tile_size cannot be combined with emit_poly_navmesh, \
+                 emit_floor_layers or emit_occupancy_grid"
+                .to_string());
+        }
+    }
+    match settings.tile_border {
+        Some(border) if !(border.is_finite() && border >= 0.0) => Err(format!(
+            "Invalid tile_border: {}. Expected a non-negative length in metres.",
+            border
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Collision voxel size the tile grid snaps to.
+fn voxel_size(settings: &MeshSettings) -> f64 {
+    settings
+        .collision_voxel_size
+        .filter(|v| v.is_finite() && *v > 0.0)
+        .unwrap_or(0.05)
+        .clamp(0.025, 0.5)
+}
+
+/// `tile_size` and `tile_border` snapped to the collision voxel size, before
+/// a whole-scene layout doubles the size to fit [`MAX_TILES`].
+pub fn anchored_metrics(settings: &MeshSettings) -> (f64, f64) {
+    let voxel = voxel_size(settings);
+    let requested = settings.tile_size.unwrap_or(f64::INFINITY);
+    let snapped = (requested / voxel).round().max(1.0) * voxel;
+    // Keep the requested size when it already is a voxel multiple.
+    let size = if (snapped - requested).abs() < 1e-9 {
+        requested
+    } else {
+        snapped
+    };
+    let border = settings.tile_border.unwrap_or(DEFAULT_TILE_BORDER);
+    (size, (border / voxel).round() * voxel)
+}
+
+/// How the plan is cut into tiles, in `splatwalk_oriented` `[x, z]`.
+#[derive(Clone, Copy)]
+pub struct TileLayout {
+    pub origin: [f64; 2],
+    pub size: f64,
+    pub border: f64,
+    pub columns: usize,
+    pub rows: usize,
+    /// Height range every tile's grid spans.
+    pub y_range: [f64; 2],
+}
+
+impl TileLayout {
+    /// Tiles covering the box `min`..`max`. The origin, tile size and border
+    /// are snapped to the requested collision voxel size.
+    pub fn new(min: Vector3<f64>, max: Vector3<f64>, settings: &MeshSettings) -> Self {
+        let voxel = voxel_size(settings);
+        let requested = settings.tile_size.unwrap_or(f64::INFINITY);
+        let origin = [
+            (min.x / voxel).floor() * voxel,
+            (min.z / voxel).floor() * voxel,
+        ];
+        let count = |size: f64, from: f64, to: f64| (((to - from) / size).ceil() as usize).max(1);
+        let (mut size, border) = anchored_metrics(settings);
+        while count(size, origin[0], max.x) * count(size, origin[1], max.z) > MAX_TILES {
+            size *= 2.0;
+        }
+        journal::clamped("tiles", "tile_size", requested, size);
+        TileLayout {
+            origin,
+            size,
+            border,
+            columns: count(size, origin[0], max.x),
+            rows: count(size, origin[1], max.z),
+            y_range: [min.y, max.y],
+        }
+    }
+
+    /// Tiles `first..=last` (`[column, row]`) of the layout anchored at the
+    /// plan origin, where anchored tile `(c, r)` covers `[c, c + 1)` tile
+    /// sizes along x and `[r, r + 1)` along z. A tile keeps its square however
+    /// far the scene grows, so streamed bakes can rebuild single tiles. The
+    /// layout's own tile `(0, 0)` is anchored tile `first`.
+    pub fn anchored(settings: &MeshSettings, first: [i64; 2], last: [i64; 2]) -> Self {
+        let (size, border) = anchored_metrics(settings);
+        TileLayout {
+            origin: [first[0] as f64 * size, first[1] as f64 * size],
+            size,
+            border,
+            columns: (last[0] - first[0] + 1).max(1) as usize,
+            rows: (last[1] - first[1] + 1).max(1) as usize,
+            y_range: [0.0, 0.0],
+        }
+    }
+
+    /// Column and row of the tile holding plan point `(x, z)`; points off the
+    /// layout go to the nearest edge tile.
+    pub fn tile_of(&self, x: f64, z: f64) -> (usize, usize) {
+        let cell = |v: f64, from: f64, count: usize| {
+            (((v - from) / self.size).floor().max(0.0) as usize).min(count - 1)
+        };
+        (
+            cell(x, self.origin[0], self.columns),
+            cell(z, self.origin[1], self.rows),
+        )
+    }
+
+    /// The tile's own square, `[x, z]` min and max.
+    pub fn square(&self, column: usize, row: usize) -> ([f64; 2], [f64; 2]) {
+        let min = [
+            self.origin[0] + column as f64 * self.size,
+            self.origin[1] + row as f64 * self.size,
+        ];
+        (min, [min[0] + self.size, min[1] + self.size])
+    }
+
+    /// The box a tile is reconstructed from: its square grown by the border,
+    /// over the full height range.
+    pub fn bounds(&self, column: usize, row: usize) -> (Vector3<f64>, Vector3<f64>) {
+        let (min, max) = self.square(column, row);
+        (
+            Vector3::new(min[0] - self.border, self.y_range[0], min[1] - self.border),
+            Vector3::new(max[0] + self.border, self.y_range[1], max[1] + self.border),
+        )
+    }
+
+    /// The faces of `mesh` whose centroid lies in tile `(column, row)`.
+    pub fn clip(&self, mesh: &ReconstructedMesh, column: usize, row: usize) -> ReconstructedMesh {
+        let positions: Vec<Vector3<f64>> = mesh
+            .vertices
+            .chunks_exact(3)
+            .map(|v| Vector3::new(v[0] as f64, v[1] as f64, v[2] as f64))
+            .collect();
+        let faces: Vec<[usize; 3]> = mesh
+            .indices
+            .chunks_exact(3)
+            .map(|f| [f[0] as usize, f[1] as usize, f[2] as usize])
+            .filter(|f| f.iter().all(|&i| i < positions.len()))
+            .collect();
+        let keep: Vec<usize> = (0..faces.len())
+            .filter(|&fi| {
+                let c = faces[fi]
+                    .iter()
+                    .map(|&i| positions[i])
+                    .sum::<Vector3<f64>>()
+                    / 3.0;
+                self.tile_of(c.x, c.z) == (column, row)
+            })
+            .collect();
+        reachability::submesh(&positions, &faces, &keep)
+    }
+}
+
+/// One tile of the finished mesh.
+#[derive(Serialize)]
+pub struct NavTile {
+    pub column: usize,
+    pub row: usize,
+    /// Bounding box of the tile: its square, over the height of its faces.
+    pub min: [f64; 3],
+    pub max: [f64; 3],
+    /// The tile's faces with their own vertices (and per-vertex attributes).
+    pub mesh: MeshBuffers,
+    /// Index in the result's `mesh` of each face of `mesh`, to look up
+    /// per-face outputs such as `hazards` or `path_costs`.
+    pub faces: Vec<u32>,
+}
+
+/// Result `tiles`: the layout and the tiles that hold faces.
+#[derive(Serialize)]
+pub struct TileIndex {
+    pub tile_size: f64,
+    pub tile_border: f64,
+    /// `[x, z]` corner of tile `(0, 0)` in `splatwalk_oriented`.
+    pub origin: [f64; 2],
+    pub columns: usize,
+    pub rows: usize,
+    pub tiles: Vec<NavTile>,
+}
+
+/// Split `mesh` into its tiles by face centroid, row by row.
+pub fn split(mesh: &MeshBuffers, layout: &TileLayout) -> TileIndex {
+    let vertex_count = mesh.vertices.len() / 3;
+    let position = |i: usize| {
+        Vector3::new(
+            mesh.vertices[i * 3] as f64,
+            mesh.vertices[i * 3 + 1] as f64,
+            mesh.vertices[i * 3 + 2] as f64,
+        )
+    };
+    let mut members = vec![Vec::new(); layout.columns * layout.rows];
+    for (fi, f) in mesh.indices.chunks_exact(3).enumerate() {
+        if f.iter().any(|&i| i as usize >= vertex_count) {
+            continue;
+        }
+        let c = f
+            .iter()
+            .map(|&i| position(i as usize))
+            .sum::<Vector3<f64>>()
+            / 3.0;
+        let (column, row) = layout.tile_of(c.x, c.z);
+        members[row * layout.columns + column].push(fi as u32);
+    }
+
+    let tiles = members
+        .into_iter()
+        .enumerate()
+        .filter(|(_, faces)| !faces.is_empty())
+        .map(|(tile, faces)| {
+            let (column, row) = (tile % layout.columns, tile / layout.columns);
+            let mut remap = vec![u32::MAX; vertex_count];
+            let mut order = Vec::new();
+            let mut indices = Vec::with_capacity(faces.len() * 3);
+            for &fi in &faces {
+                for &v in &mesh.indices[fi as usize * 3..fi as usize * 3 + 3] {
+                    if remap[v as usize] == u32::MAX {
+                        remap[v as usize] = order.len() as u32;
+                        order.push(v as usize);
+                    }
+                    indices.push(remap[v as usize]);
+                }
+            }
+            let gather = |values: &[f32], stride: usize| -> Vec<f32> {
+                order
+                    .iter()
+                    .flat_map(|&v| values[v * stride..v * stride + stride].iter().copied())
+                    .collect()
+            };
+            let attribute = |values: &Option<Vec<f32>>, stride: usize| {
+                values
+                    .as_deref()
+                    .filter(|values| values.len() == vertex_count * stride)
+                    .map(|values| gather(values, stride))
+            };
+            let mut tile_mesh = MeshBuffers::new(gather(&mesh.vertices, 3), indices);
+            // Seam vertices keep the whole mesh's normals, so tiles shade
+            // without creases where they meet.
+            if mesh.normals.len() == vertex_count * 3 {
+                tile_mesh.normals = gather(&mesh.normals, 3);
+            }
+            tile_mesh.colors = attribute(&mesh.colors, 3);
+            tile_mesh.vertex_alpha = attribute(&mesh.vertex_alpha, 1);
+            tile_mesh.vertex_confidence = attribute(&mesh.vertex_confidence, 1);
+            tile_mesh.uvs = attribute(&mesh.uvs, 2);
+            tile_mesh.vertex_deviation = attribute(&mesh.vertex_deviation, 1);
+            tile_mesh.deviation_colors = attribute(&mesh.deviation_colors, 3);
+
+            let heights = tile_mesh
+                .vertices
+                .iter()
+                .skip(1)
+                .step_by(3)
+                .map(|&y| y as f64);
+            let (low, high) = heights.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), y| {
+                (lo.min(y), hi.max(y))
+            });
+            let (square_min, square_max) = layout.square(column, row);
+            NavTile {
+                column,
+                row,
+                min: [square_min[0], low, square_min[1]],
+                max: [square_max[0], high, square_max[1]],
+                mesh: tile_mesh,
+                faces,
+            }
+        })
+        .collect();
+    TileIndex {
+        tile_size: layout.size,
+        tile_border: layout.border,
+        origin: layout.origin,
+        columns: layout.columns,
+        rows: layout.rows,
+        tiles,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiles_partition_the_faces() {
+        // A 3 x 1 strip of unit squares cut into 2 m tiles.
+        let layout = TileLayout {
+            origin: [0.0, 0.0],
+            size: 2.0,
+            border: 0.5,
+            columns: 2,
+            rows: 1,
+            y_range: [0.0, 1.0],
+        };
+        let mut vertices = Vec::new();
+        for x in 0..4 {
+            vertices.extend_from_slice(&[x as f32, 0.0, 0.0, x as f32, 0.0, 1.0]);
+        }
+        let mut indices = Vec::new();
+        for x in 0..3u32 {
+            let (a, b, c, d) = (2 * x, 2 * x + 2, 2 * x + 3, 2 * x + 1);
+            indices.extend_from_slice(&[a, d, c, a, c, b]);
+        }
+        let reconstructed = ReconstructedMesh {
+            vertices: vertices.clone(),
+            indices: indices.clone(),
+        };
+        assert_eq!(layout.clip(&reconstructed, 0, 0).indices.len(), 4 * 3);
+        assert_eq!(layout.clip(&reconstructed, 1, 0).indices.len(), 2 * 3);
+        assert_eq!(layout.bounds(1, 0).0, Vector3::new(1.5, 0.0, -0.5));
+
+        let mut mesh = MeshBuffers::new(vertices, indices);
+        mesh.vertex_alpha = Some((0..8).map(|i| i as f32).collect());
+        let index = split(&mesh, &layout);
+        assert_eq!(index.tiles.len(), 2);
+        let second = &index.tiles[1];
+        assert_eq!((second.column, second.row), (1, 0));
+        assert_eq!(second.faces, [4, 5]);
+        assert_eq!(second.mesh.vertex_count, 4);
+        assert_eq!(second.min, [2.0, 0.0, 0.0]);
+        // Attributes follow their vertices into the tile.
+        let alpha = second.mesh.vertex_alpha.as_ref().unwrap();
+        let xs: Vec<f32> = second.mesh.vertices.iter().step_by(3).copied().collect();
+        for (x, a) in xs.iter().zip(alpha) {
+            assert_eq!((*a as i32) / 2, *x as i32);
+        }
+    }
+}
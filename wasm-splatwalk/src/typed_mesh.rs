@@ -0,0 +1,99 @@
+//! Typed-array mesh output (`typed_buffers: true`).
+//!
+//! `serde_wasm_bindgen` turns a `Vec<f32>` into a JS `Array` of boxed
+//! numbers, one property write per float, which dominates the cost (and the
+//! JS heap) of returning a 1M+ vertex mesh. With `typed_buffers` the flat
+//! buffers are moved out of each [`MeshBuffers`] before the result is
+//! serialized and attached afterwards as `Float32Array` / `Uint32Array`: one
+//! `memcpy` out of linear memory per buffer, no per-element conversion.
+//!
+//! The arrays are copies, not views into WASM memory: a view would be detached
+//! the next time the heap grows, and the worker posts results across threads.
+//! Copies own their `ArrayBuffer`, so the worker can transfer them to the main
+//! thread without another copy.
+
+use js_sys::{Float32Array, Reflect, Uint32Array};
+use wasm_bindgen::JsValue;
+
+use crate::{MeshBuffers, MeshSettings};
+
+/// Flat buffers moved out of a [`MeshBuffers`] ahead of serialization.
+pub struct TypedMesh {
+    vertices: Vec<f32>,
+    indices: Vec<u32>,
+    normals: Vec<f32>,
+    vertex_alpha: Option<Vec<f32>>,
+    vertex_confidence: Option<Vec<f32>>,
+    colors: Option<Vec<f32>>,
+    uvs: Option<Vec<f32>>,
+    vertex_deviation: Option<Vec<f32>>,
+    deviation_colors: Option<Vec<f32>>,
+}
+
+impl TypedMesh {
+    /// Take the buffers out of `mesh` when `settings.typed_buffers` is set. The
+    /// emptied mesh still serializes its counts; `attach` fills the arrays in.
+    pub fn take(settings: &MeshSettings, mesh: &mut MeshBuffers) -> Option<Self> {
+        if !settings.typed_buffers.unwrap_or(false) {
+            return None;
+        }
+        Some(Self {
+            vertices: std::mem::take(&mut mesh.vertices),
+            indices: std::mem::take(&mut mesh.indices),
+            normals: std::mem::take(&mut mesh.normals),
+            vertex_alpha: mesh.vertex_alpha.take(),
+            vertex_confidence: mesh.vertex_confidence.take(),
+            colors: mesh.colors.take(),
+            uvs: mesh.uvs.take(),
+            vertex_deviation: mesh.vertex_deviation.take(),
+            deviation_colors: mesh.deviation_colors.take(),
+        })
+    }
+
+    /// Set the typed arrays on the serialized mesh object at `path` (property
+    /// names from the result root, e.g. `["void_mesh", "mesh"]`).
+    pub fn attach(self, value: &JsValue, path: &[&str]) -> Result<(), JsValue> {
+        let mut target = value.clone();
+        for key in path {
+            target = Reflect::get(&target, &JsValue::from_str(key))?;
+        }
+        let set = |key: &str, array: JsValue| Reflect::set(&target, &key.into(), &array);
+        set(
+            "vertices",
+            Float32Array::from(self.vertices.as_slice()).into(),
+        )?;
+        set("indices", Uint32Array::from(self.indices.as_slice()).into())?;
+        set(
+            "normals",
+            Float32Array::from(self.normals.as_slice()).into(),
+        )?;
+        if let Some(alpha) = self.vertex_alpha {
+            set("vertex_alpha", Float32Array::from(alpha.as_slice()).into())?;
+        }
+        if let Some(confidence) = self.vertex_confidence {
+            set(
+                "vertex_confidence",
+                Float32Array::from(confidence.as_slice()).into(),
+            )?;
+        }
+        if let Some(colors) = self.colors {
+            set("colors", Float32Array::from(colors.as_slice()).into())?;
+        }
+        if let Some(uvs) = self.uvs {
+            set("uvs", Float32Array::from(uvs.as_slice()).into())?;
+        }
+        if let Some(deviation) = self.vertex_deviation {
+            set(
+                "vertex_deviation",
+                Float32Array::from(deviation.as_slice()).into(),
+            )?;
+        }
+        if let Some(colors) = self.deviation_colors {
+            set(
+                "deviation_colors",
+                Float32Array::from(colors.as_slice()).into(),
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,230 @@
+//! Texture coordinates for `convert_splat_to_mesh` meshes.
+//!
+//! `generate_uvs` fills `mesh.uvs` so a host can put a tiling material on the
+//! bake or bake a texture onto it. `uv_projection` picks the mapping:
+//!
+//! - `"planar"`: project onto the ground frame's tangent and bitangent (X and
+//!   Z without a ground plane), `uv_scale` metres per unit.
+//! - `"box"`: project each vertex along the axis its normal is closest to,
+//!   `uv_scale` metres per unit, signed so no side is mirrored when seen from
+//!   the front.
+//! - `"grid"`: mode 2's voxel grid, `[0, 1]` across its footprint, so floors
+//!   map to one non-overlapping square a texture can be baked into.
+//! - `"auto"` (default): `"grid"` when the bake has a voxel grid, else `"box"`.
+//!
+//! Coordinates are per vertex and vertices are never split, so every other
+//! per-vertex output still lines up. A box-projected face whose corners chose
+//! different axes stretches across the seam.
+
+use crate::frame::GroundFrame;
+use crate::MeshSettings;
+
+/// `uv_scale` when unset: one texture repeat per metre.
+const DEFAULT_SCALE: f64 = 1.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Projection {
+    Planar,
+    Box,
+    Grid,
+}
+
+/// `uv_projection` must name a projection, `"grid"` needs an untiled mode 2
+/// bake, and `uv_scale` must be positive.
+pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+    match settings.uv_projection.as_deref() {
+        None | Some("auto" | "planar" | "box") => {}
+        Some("grid") => {
+            if settings.mode != 2 || settings.tile_size.is_some() {
+                return Err("uv_projection grid needs mode 2 without tile_size.".to_string());
+            }
+        }
+        Some(other) => {
+            return Err(format!(
+                "Invalid uv_projection: {}. Expected auto, planar, box or grid.",
+                other
+            ))
+        }
+    }
+    match settings.uv_scale {
+        Some(scale) if !(scale.is_finite() && scale > 0.0) => Err(format!(
+            "Invalid uv_scale: {}. Expected a positive number.",
+            scale
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Maps vertices to texture coordinates.
+pub struct UvMapper {
+    projection: Projection,
+    origin: [f64; 3],
+    tangent: [f64; 3],
+    bitangent: [f64; 3],
+    /// Metres per unit along `u` and `v`.
+    scale: [f64; 2],
+}
+
+impl UvMapper {
+    /// The mapper `settings` ask for, or `None` without `generate_uvs`.
+    /// `grid_extent` is the voxel grid's footprint in metres along the
+    /// frame's tangent and bitangent.
+    pub fn from_settings(
+        settings: &MeshSettings,
+        frame: &GroundFrame,
+        grid_extent: [f64; 2],
+    ) -> Option<Self> {
+        if !settings.generate_uvs.unwrap_or(false) {
+            return None;
+        }
+        let has_grid = frame.cell_size.is_some() && grid_extent.iter().all(|e| *e > 0.0);
+        let projection = match settings.uv_projection.as_deref() {
+            Some("planar") => Projection::Planar,
+            Some("box") => Projection::Box,
+            Some("grid") if has_grid => Projection::Grid,
+            // An untiled mode 2 bake whose grid failed has no mesh to map.
+            Some("grid") => Projection::Planar,
+            _ if has_grid => Projection::Grid,
+            _ => Projection::Box,
+        };
+        let scale = settings.uv_scale.unwrap_or(DEFAULT_SCALE);
+        let mut mapper = Self {
+            projection,
+            origin: [0.0; 3],
+            tangent: [1.0, 0.0, 0.0],
+            bitangent: [0.0, 0.0, 1.0],
+            scale: [scale; 2],
+        };
+        if projection != Projection::Box {
+            if let Some(basis) = &frame.basis {
+                mapper.origin = basis.origin;
+                mapper.tangent = basis.tangent;
+                mapper.bitangent = basis.bitangent;
+            }
+        }
+        if projection == Projection::Grid {
+            mapper.scale = grid_extent;
+        }
+        Some(mapper)
+    }
+
+    /// Two coordinates per vertex. `normals` are the mesh's vertex normals,
+    /// read only by the box projection.
+    pub fn map(&self, vertices: &[f32], normals: &[f32]) -> Vec<f32> {
+        let dot = |a: [f64; 3], b: [f64; 3]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+        let mut uvs = Vec::with_capacity(vertices.len() / 3 * 2);
+        for (i, p) in vertices.chunks_exact(3).enumerate() {
+            let p = [p[0] as f64, p[1] as f64, p[2] as f64];
+            let [u, v] = match self.projection {
+                Projection::Box => {
+                    let n = normals
+                        .get(i * 3..i * 3 + 3)
+                        .map_or([0.0, 1.0, 0.0], |n| [n[0], n[1], n[2]]);
+                    box_uv(p, n)
+                }
+                _ => {
+                    let d = [
+                        p[0] - self.origin[0],
+                        p[1] - self.origin[1],
+                        p[2] - self.origin[2],
+                    ];
+                    [dot(d, self.tangent), dot(d, self.bitangent)]
+                }
+            };
+            uvs.push((u / self.scale[0]) as f32);
+            uvs.push((v / self.scale[1]) as f32);
+        }
+        uvs
+    }
+}
+
+/// `p` projected along the axis `normal` is closest to. `u` runs to the
+/// right and `v` up as seen looking at that side, with north (`-Z`) up on
+/// floors and ceilings.
+fn box_uv(p: [f64; 3], normal: [f32; 3]) -> [f64; 2] {
+    let [x, y, z] = p;
+    let [nx, ny, nz] = normal.map(|c| c as f64);
+    if ny.abs() >= nx.abs() && ny.abs() >= nz.abs() {
+        if ny >= 0.0 {
+            [x, -z]
+        } else {
+            [-x, -z]
+        }
+    } else if nx.abs() >= nz.abs() {
+        if nx >= 0.0 {
+            [-z, y]
+        } else {
+            [z, y]
+        }
+    } else if nz >= 0.0 {
+        [x, y]
+    } else {
+        [-x, y]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FieldBasis;
+
+    fn settings(json: serde_json::Value) -> MeshSettings {
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn frame(grid: Option<(FieldBasis, f64)>) -> GroundFrame {
+        GroundFrame {
+            floor_plane: None,
+            cell_size: grid.as_ref().map(|(_, cell)| *cell),
+            basis: grid.map(|(basis, _)| basis),
+            to_splat: [0.0; 16],
+            from_splat: [0.0; 16],
+        }
+    }
+
+    #[test]
+    fn box_projection_tiles_each_side_by_the_metre() {
+        let s = settings(serde_json::json!({ "mode": 0, "generate_uvs": true, "uv_scale": 2.0 }));
+        let mapper = UvMapper::from_settings(&s, &frame(None), [0.0; 2]).unwrap();
+        assert_eq!(mapper.projection, Projection::Box);
+        // A floor vertex, a +X wall vertex and a -Z wall vertex.
+        let vertices = [2.0, 0.0, 4.0, 1.0, 2.0, 6.0, 4.0, 2.0, -1.0];
+        let normals = [0.0, 1.0, 0.0, 1.0, 0.1, 0.0, 0.0, 0.0, -1.0];
+        let uvs = mapper.map(&vertices, &normals);
+        assert_eq!(uvs, [1.0, -2.0, -3.0, 1.0, -2.0, 1.0]);
+    }
+
+    #[test]
+    fn grid_projection_spans_the_footprint() {
+        let basis = FieldBasis {
+            origin: [-1.0, 0.0, -2.0],
+            tangent: [1.0, 0.0, 0.0],
+            bitangent: [0.0, 0.0, 1.0],
+            up: [0.0, 1.0, 0.0],
+        };
+        let s = settings(serde_json::json!({ "mode": 2, "generate_uvs": true }));
+        let mapper = UvMapper::from_settings(&s, &frame(Some((basis, 0.1))), [4.0, 8.0]).unwrap();
+        assert_eq!(mapper.projection, Projection::Grid);
+        let uvs = mapper.map(&[-1.0, 0.5, -2.0, 3.0, 0.0, 6.0, 1.0, 0.0, 2.0], &[]);
+        assert_eq!(uvs, [0.0, 0.0, 1.0, 1.0, 0.5, 0.5]);
+
+        assert!(UvMapper::from_settings(
+            &settings(serde_json::json!({ "mode": 2 })),
+            &frame(None),
+            [0.0; 2]
+        )
+        .is_none());
+        assert!(validate(&settings(
+            serde_json::json!({ "mode": 2, "uv_projection": "grid" })
+        ))
+        .is_ok());
+        for bad in [
+            serde_json::json!({ "mode": 3, "uv_projection": "grid" }),
+            serde_json::json!({ "mode": 2, "tile_size": 8.0, "uv_projection": "grid" }),
+            serde_json::json!({ "mode": 0, "uv_projection": "cylinder" }),
+            serde_json::json!({ "mode": 0, "uv_scale": 0.0 }),
+        ] {
+            assert!(validate(&settings(bad)).is_err());
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! Per-vertex colors baked from nearby splats (`emit_colors`).
+//!
+//! Reconstructed meshes carry no texture, so a preview renders as flat grey.
+//! [`sample_vertex_colors`] gives each vertex the weighted mean base color
+//! (the SH DC term, see [`crate::splat::dc_to_rgb`]) of the splats around it,
+//! which is enough to recognise the scene on a coarse mesh.
+
+use std::collections::HashMap;
+
+use crate::splat::PointNormal;
+use crate::MeshSettings;
+
+/// Grid rings searched around a vertex before it falls back to grey.
+const MAX_RINGS: i64 = 4;
+
+/// Color of vertices with no splat within [`MAX_RINGS`] cells.
+const FALLBACK_GREY: f32 = 0.5;
+
+/// `color_sample_radius` must be a positive finite length when set.
+pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+    match settings.color_sample_radius {
+        Some(radius) if !(radius.is_finite() && radius > 0.0) => {
+            Err(format!("Invalid color_sample_radius: {}", radius))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Sampling radius for `points`: `color_sample_radius` when set, otherwise
+/// twice the mean point spacing of the cloud's bounding box, clamped to
+/// `[0.01, 1.0]` m.
+pub fn sample_radius(settings: &MeshSettings, points: &[PointNormal]) -> f64 {
+    if let Some(radius) = settings.color_sample_radius {
+        return radius;
+    }
+    let mut min = [f64::MAX; 3];
+    let mut max = [f64::MIN; 3];
+    for p in points {
+        let c = [p.point.x, p.point.y, p.point.z];
+        for axis in 0..3 {
+            min[axis] = min[axis].min(c[axis]);
+            max[axis] = max[axis].max(c[axis]);
+        }
+    }
+    if points.is_empty() {
+        return 0.1;
+    }
+    // Pad flat axes so a planar capture doesn't collapse the volume to zero.
+    let volume: f64 = (0..3)
+        .map(|axis| (max[axis] - min[axis]).max(0.01))
+        .product();
+    (2.0 * (volume / points.len() as f64).cbrt()).clamp(0.01, 1.0)
+}
+
+/// RGB triplets, one per vertex of `vertices` (xyz triplets), from the splats
+/// in `points`. Each splat within the searched cells contributes its color
+/// weighted by `sigmoid(opacity) * exp(-d^2 / (2 radius^2))`. The search
+/// starts with the cells next to the vertex and widens ring by ring (up to
+/// [`MAX_RINGS`]) until it finds a splat, so sparse regions and the corners
+/// of plane quads still pick up a color.
+pub fn sample_vertex_colors(vertices: &[f32], points: &[PointNormal], radius: f64) -> Vec<f32> {
+    let cell_of = |x: f64, y: f64, z: f64| {
+        (
+            (x / radius).floor() as i64,
+            (y / radius).floor() as i64,
+            (z / radius).floor() as i64,
+        )
+    };
+    let mut grid: HashMap<(i64, i64, i64), Vec<u32>> = HashMap::new();
+    for (i, p) in points.iter().enumerate() {
+        if p.point.x.is_finite() && p.point.y.is_finite() && p.point.z.is_finite() {
+            grid.entry(cell_of(p.point.x, p.point.y, p.point.z))
+                .or_default()
+                .push(i as u32);
+        }
+    }
+
+    let inv_two_sigma_sq = 1.0 / (2.0 * radius * radius);
+    let mut colors = Vec::with_capacity(vertices.len());
+    for v in vertices.chunks_exact(3) {
+        let (x, y, z) = (v[0] as f64, v[1] as f64, v[2] as f64);
+        let (cx, cy, cz) = cell_of(x, y, z);
+        let mut sum = [0.0_f64; 3];
+        let mut weight_sum = 0.0_f64;
+        for ring in 0..=MAX_RINGS {
+            for dx in -ring..=ring {
+                for dy in -ring..=ring {
+                    for dz in -ring..=ring {
+                        // Only the shell: inner cells were visited by earlier rings.
+                        if dx.abs().max(dy.abs()).max(dz.abs()) != ring {
+                            continue;
+                        }
+                        let Some(members) = grid.get(&(cx + dx, cy + dy, cz + dz)) else {
+                            continue;
+                        };
+                        for &i in members {
+                            let p = &points[i as usize];
+                            let d_sq = (p.point.x - x).powi(2)
+                                + (p.point.y - y).powi(2)
+                                + (p.point.z - z).powi(2);
+                            let alpha = 1.0 / (1.0 + (-p.opacity).exp());
+                            let w = alpha * (-d_sq * inv_two_sigma_sq).exp();
+                            for (s, c) in sum.iter_mut().zip(p.color) {
+                                *s += w * c as f64;
+                            }
+                            weight_sum += w;
+                        }
+                    }
+                }
+            }
+            // Ring 0 alone can miss a closer splat just across a cell face.
+            if ring >= 1 && weight_sum > 1e-12 {
+                break;
+            }
+        }
+        if weight_sum > 1e-12 {
+            colors.extend(sum.iter().map(|c| (c / weight_sum) as f32));
+        } else {
+            colors.extend_from_slice(&[FALLBACK_GREY; 3]);
+        }
+    }
+    colors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::splat::test_splat;
+
+    #[test]
+    fn vertices_take_the_nearby_splat_color() {
+        let red = [1.0, 0.0, 0.0];
+        let points: Vec<PointNormal> = [(0.0, red), (0.02, red), (2.0, [0.0, 0.0, 1.0])]
+            .into_iter()
+            .map(|(x, color)| PointNormal {
+                color,
+                ..test_splat([x, 0.0, 0.0], [0.0, 1.0, 0.0])
+            })
+            .collect();
+        let vertices: [f32; 12] = [
+            0.01, 0.0, 0.0, 2.0, 0.0, 0.0, 0.35, 0.0, 0.0, 50.0, 0.0, 0.0,
+        ];
+        let colors = sample_vertex_colors(&vertices, &points, 0.1);
+        assert_eq!(colors.len(), 12);
+        assert!((colors[0] - 1.0).abs() < 1e-6 && colors[2].abs() < 1e-6);
+        assert!(colors[3].abs() < 1e-6 && (colors[5] - 1.0).abs() < 1e-6);
+        // Three rings out, still red.
+        assert!(colors[6] > 0.99);
+        // Nothing within range: grey.
+        assert_eq!(&colors[9..12], &[FALLBACK_GREY; 3]);
+    }
+}
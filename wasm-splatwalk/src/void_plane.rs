@@ -0,0 +1,90 @@
+//! Room-floor catch geometry settings (`void_plane`).
+//!
+//! `build_room_floor_mesh` can add a flat `void_mesh` below floors whose edge
+//! drops off into nothing; `mesh::build_void_mesh` builds it. This module owns
+//! the settings side: the accepted modes, their defaults, and the validation
+//! `parse_settings` and every room-floor recovery step run before a bake.
+
+use crate::MeshSettings;
+
+/// Values `void_plane` may take.
+pub const MODES: &[&str] = &["kill_plane", "apron"];
+
+/// Widest kill-plane margin or apron, in field cells.
+pub const MAX_EXTENT_CELLS: f64 = 256.0;
+
+/// `void_plane` must name a mode, `void_plane_depth` must be finite and
+/// non-negative, and `void_plane_extent_cells` finite and at least one cell.
+pub fn validate(settings: &MeshSettings) -> Result<(), String> {
+    if let Some(mode) = settings.void_plane.as_deref() {
+        if !MODES.contains(&mode) {
+            return Err(format!(
+                "Invalid void_plane: {}. Expected \"kill_plane\" or \"apron\".",
+                mode
+            ));
+        }
+    }
+    if let Some(depth) = settings.void_plane_depth {
+        if !(depth.is_finite() && depth >= 0.0) {
+            return Err(format!("Invalid void_plane_depth: {}", depth));
+        }
+    }
+    if let Some(extent) = settings.void_plane_extent_cells {
+        if !(extent.is_finite() && extent >= 1.0) {
+            return Err(format!("Invalid void_plane_extent_cells: {}", extent));
+        }
+    }
+    Ok(())
+}
+
+/// Metres below the floor plane: 0 for an `apron` (a flush border), 2 for a
+/// `kill_plane`.
+pub fn depth(settings: &MeshSettings, mode: &str) -> f64 {
+    settings
+        .void_plane_depth
+        .unwrap_or(if mode == "apron" { 0.0 } else { 2.0 })
+}
+
+/// Kill-plane margin or apron width in whole cells (default 8), capped at
+/// [`MAX_EXTENT_CELLS`].
+pub fn extent_cells(settings: &MeshSettings) -> i64 {
+    settings
+        .void_plane_extent_cells
+        .unwrap_or(8.0)
+        .min(MAX_EXTENT_CELLS) as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(mut json: serde_json::Value) -> MeshSettings {
+        json["mode"] = 2.into();
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn rejects_unknown_modes_and_out_of_range_sizes() {
+        let check = |json| validate(&settings(json));
+        assert!(check(serde_json::json!({})).is_ok());
+        assert!(check(serde_json::json!({ "void_plane": "apron" })).is_ok());
+        let err = check(serde_json::json!({ "void_plane": "moat" })).unwrap_err();
+        assert!(err.contains("moat"));
+        assert!(check(serde_json::json!({ "void_plane_depth": -1.0 })).is_err());
+        assert!(check(serde_json::json!({ "void_plane_extent_cells": 0.5 })).is_err());
+    }
+
+    #[test]
+    fn defaults_depend_on_the_mode() {
+        let unset = settings(serde_json::json!({}));
+        assert_eq!(depth(&unset, "apron"), 0.0);
+        assert_eq!(depth(&unset, "kill_plane"), 2.0);
+        assert_eq!(extent_cells(&unset), 8);
+        let set = settings(serde_json::json!({
+            "void_plane_depth": 0.5,
+            "void_plane_extent_cells": 1000.0,
+        }));
+        assert_eq!(depth(&set, "apron"), 0.5);
+        assert_eq!(extent_cells(&set), 256);
+    }
+}
@@ -0,0 +1,316 @@
+//! Random-walk smoke test of a baked mesh.
+//!
+//! A bake can pass every check on its geometry and still not be playable:
+//! cracks an agent slips through, slivers it cannot move on, rooms no path
+//! reaches. [`simulate`] drops virtual agents on random walkable points and
+//! moves them with the queries a host's controller makes, so those show up
+//! before the bake ships:
+//!
+//! - each agent asks [`NavGraph::find_path`] for a route to a random target
+//!   on the mesh; no route is an unreachable target;
+//! - each step turns to a random heading and moves up to `step_length` with
+//!   [`NavGraph::raycast`], stopping short of any boundary it meets;
+//! - a step that ends with no walkable face under the agent within
+//!   `max_climb` is a fall, and the agent stops there;
+//! - an agent whose step is blocked, and that no other heading would move
+//!   either, is stuck and stops.
+//!
+//! Spawns and targets are spread over the walkable area, and the walk is
+//! seeded, so the same mesh and options give the same report.
+
+use nalgebra::Vector3;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::nav_query::NavGraph;
+use crate::ransac;
+
+const MAX_AGENTS: usize = 4096;
+const MAX_STEPS: usize = 100_000;
+/// How many incidents a report lists; the counts cover all of them.
+const MAX_INCIDENTS: usize = 64;
+/// How far short of a boundary a step stops, in metres.
+const WALL_MARGIN: f64 = 0.05;
+/// A step covering less than this fraction of `step_length` is blocked.
+const BLOCKED_FRACTION: f64 = 0.1;
+/// Headings tried around a blocked agent before it counts as stuck.
+const ESCAPE_HEADINGS: usize = 8;
+
+/// What `NavMeshQuery.simulate_agents` takes.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct WalkOptions {
+    /// Agents to drop (default 16).
+    pub agents: usize,
+    /// Steps each agent takes (default 64).
+    pub steps: usize,
+    /// Longest step in metres (default 0.5).
+    pub step_length: f64,
+    pub seed: u64,
+}
+
+impl Default for WalkOptions {
+    fn default() -> Self {
+        Self {
+            agents: 16,
+            steps: 64,
+            step_length: 0.5,
+            seed: ransac::DEFAULT_SEED,
+        }
+    }
+}
+
+impl WalkOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.agents == 0 || self.agents > MAX_AGENTS {
+            return Err(format!(
+                "Invalid agents: {}. Expected 1 to {}.",
+                self.agents, MAX_AGENTS
+            ));
+        }
+        if self.steps > MAX_STEPS {
+            return Err(format!(
+                "Invalid steps: {}. Expected at most {}.",
+                self.steps, MAX_STEPS
+            ));
+        }
+        if !(self.step_length.is_finite() && self.step_length > 0.0) {
+            return Err(format!(
+                "Invalid step_length: {}. Expected a positive number.",
+                self.step_length
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// One fall, stuck agent or unreachable target.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Incident {
+    pub agent: usize,
+    /// The step it happened on; 0 for an unreachable target, which is
+    /// checked before the walk.
+    pub step: usize,
+    /// `"fall"`, `"stuck"` or `"unreachable"`.
+    pub kind: &'static str,
+    /// Where the agent was.
+    pub position: [f64; 3],
+    /// The target no route reaches, for `"unreachable"`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<[f64; 3]>,
+}
+
+/// What `NavMeshQuery.simulate_agents` returns.
+#[derive(Clone, Debug, Serialize)]
+pub struct WalkReport {
+    pub agents: usize,
+    pub steps: usize,
+    /// No agent fell or got stuck and every target was reachable.
+    pub playable: bool,
+    pub falls: usize,
+    pub stuck: usize,
+    pub unreachable_targets: usize,
+    /// Mean plan-view distance an agent covered, in metres.
+    pub mean_distance: f64,
+    /// The first incidents, in the order they happened.
+    pub incidents: Vec<Incident>,
+}
+
+/// Walk `options.agents` agents over `graph`, or `None` when it has no
+/// walkable area. `max_climb` is the agent's tallest step.
+pub fn simulate(graph: &NavGraph, max_climb: f64, options: &WalkOptions) -> Option<WalkReport> {
+    let triangles: Vec<[Vector3<f64>; 3]> = graph
+        .walkable_triangles()
+        .into_iter()
+        .filter(|t| plan_area(t) > 1e-12)
+        .collect();
+    let mut cumulative = Vec::with_capacity(triangles.len());
+    let mut total = 0.0;
+    for t in &triangles {
+        total += plan_area(t);
+        cumulative.push(total);
+    }
+    if triangles.is_empty() {
+        return None;
+    }
+
+    let mut rng = StdRng::seed_from_u64(options.seed);
+    let sample = |rng: &mut StdRng| {
+        let at = rng.gen_range(0.0..total);
+        let [a, b, c] = triangles[cumulative
+            .partition_point(|&area| area <= at)
+            .min(triangles.len() - 1)];
+        let (mut u, mut v) = (rng.gen::<f64>(), rng.gen::<f64>());
+        if u + v > 1.0 {
+            (u, v) = (1.0 - u, 1.0 - v);
+        }
+        a + (b - a) * u + (c - a) * v
+    };
+
+    let mut report = WalkReport {
+        agents: options.agents,
+        steps: options.steps,
+        playable: true,
+        falls: 0,
+        stuck: 0,
+        unreachable_targets: 0,
+        mean_distance: 0.0,
+        incidents: Vec::new(),
+    };
+    let margin = WALL_MARGIN.min(options.step_length / 2.0);
+    let min_move = options.step_length * BLOCKED_FRACTION;
+    let mut distance = 0.0;
+    for agent in 0..options.agents {
+        let spawn = sample(&mut rng);
+        let target = sample(&mut rng);
+        if graph.find_path(spawn, target, false).is_none() {
+            report.unreachable_targets += 1;
+            record(
+                &mut report,
+                Incident {
+                    agent,
+                    step: 0,
+                    kind: "unreachable",
+                    position: spawn.into(),
+                    target: Some(target.into()),
+                },
+            );
+        }
+
+        let mut at = spawn;
+        for step in 1..=options.steps {
+            let angle = rng.gen_range(0.0..std::f64::consts::TAU);
+            let heading = Vector3::new(angle.cos(), 0.0, angle.sin());
+            let Some(ray) = graph.raycast(at, heading, options.step_length) else {
+                break;
+            };
+            let next = if ray.normal.is_some() {
+                let reach = (ray.distance - margin).max(0.0);
+                graph.raycast(at, heading, reach).map_or(at, |r| r.point)
+            } else {
+                ray.point
+            };
+            let moved = (next.x - at.x).hypot(next.z - at.z);
+            distance += moved;
+            at = next;
+
+            let kind = match graph.face_under(at) {
+                Some((_, y)) if (y - at.y).abs() <= max_climb => {
+                    if moved >= min_move || can_move(graph, at, min_move + margin) {
+                        continue;
+                    }
+                    report.stuck += 1;
+                    "stuck"
+                }
+                _ => {
+                    report.falls += 1;
+                    "fall"
+                }
+            };
+            record(
+                &mut report,
+                Incident {
+                    agent,
+                    step,
+                    kind,
+                    position: at.into(),
+                    target: None,
+                },
+            );
+            break;
+        }
+    }
+    report.mean_distance = distance / options.agents as f64;
+    report.playable = report.falls == 0 && report.stuck == 0 && report.unreachable_targets == 0;
+    Some(report)
+}
+
+/// Whether any of [`ESCAPE_HEADINGS`] headings from `at` runs `clearance`
+/// without meeting a boundary.
+fn can_move(graph: &NavGraph, at: Vector3<f64>, clearance: f64) -> bool {
+    (0..ESCAPE_HEADINGS).any(|k| {
+        let angle = std::f64::consts::TAU * k as f64 / ESCAPE_HEADINGS as f64;
+        let heading = Vector3::new(angle.cos(), 0.0, angle.sin());
+        graph
+            .raycast(at, heading, clearance)
+            .is_some_and(|ray| ray.normal.is_none())
+    })
+}
+
+fn record(report: &mut WalkReport, incident: Incident) {
+    if report.incidents.len() < MAX_INCIDENTS {
+        report.incidents.push(incident);
+    }
+}
+
+fn plan_area([a, b, c]: &[Vector3<f64>; 3]) -> f64 {
+    ((b.x - a.x) * (c.z - a.z) - (c.x - a.x) * (b.z - a.z)).abs() / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reachability::AgentLimits;
+
+    /// A flat `size` x `size` square of two triangles at `(x, z)`.
+    fn squares(squares: &[(f32, f32, f32)]) -> NavGraph {
+        let (mut vertices, mut indices) = (Vec::new(), Vec::new());
+        for &(x, z, size) in squares {
+            let base = (vertices.len() / 3) as u32;
+            for (dx, dz) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)] {
+                vertices.extend_from_slice(&[x + dx * size, 0.0, z + dz * size]);
+            }
+            indices.extend([0, 2, 1, 0, 3, 2].iter().map(|i| base + i));
+        }
+        let limits = AgentLimits {
+            max_climb: 0.5,
+            max_slope_deg: 40.0,
+            max_step: 0.0,
+        };
+        NavGraph::new(&vertices, &indices, &limits, 1e-3)
+    }
+
+    #[test]
+    fn an_open_floor_is_playable() {
+        let graph = squares(&[(0.0, 0.0, 4.0)]);
+        let options = WalkOptions::default();
+        let report = simulate(&graph, 0.5, &options).unwrap();
+        assert!(report.playable, "{:?}", report.incidents);
+        assert!(report.incidents.is_empty());
+        assert!(report.mean_distance > options.step_length * 4.0);
+        // Seeded: the same walk again.
+        let again = simulate(&graph, 0.5, &options).unwrap();
+        assert_eq!(again.mean_distance, report.mean_distance);
+    }
+
+    #[test]
+    fn islands_and_slivers_are_reported() {
+        // Two rooms with nothing joining them.
+        let graph = squares(&[(0.0, 0.0, 2.0), (5.0, 0.0, 2.0)]);
+        let report = simulate(&graph, 0.5, &WalkOptions::default()).unwrap();
+        assert!(!report.playable);
+        assert!(report.unreachable_targets > 0 && report.stuck == 0);
+        let first = &report.incidents[0];
+        assert_eq!(first.kind, "unreachable");
+        assert!((first.position[0] < 3.0) != (first.target.unwrap()[0] < 3.0));
+
+        // A 3 cm sliver holds agents but never lets them move.
+        let sliver = squares(&[(0.0, 0.0, 0.03)]);
+        let options = WalkOptions {
+            agents: 2,
+            ..WalkOptions::default()
+        };
+        let report = simulate(&sliver, 0.5, &options).unwrap();
+        assert_eq!(report.stuck, 2);
+        assert_eq!(report.incidents[0].step, 1);
+
+        assert!(simulate(&squares(&[]), 0.5, &options).is_none());
+        assert!(WalkOptions {
+            step_length: 0.0,
+            ..options
+        }
+        .validate()
+        .is_err());
+    }
+}
@@ -0,0 +1,109 @@
+//! Walkability texture (`emit_walkability_texture`).
+//!
+//! `vertex_confidence` resolves coverage only at quad corners, and a runtime
+//! that wants to shade "uncertain floor" (AR safety tint, a debug overlay)
+//! would rather sample a texture than rebuild a vertex attribute. [`bake`]
+//! writes the accumulated splat coverage of every ground-field cell into one
+//! grayscale texel, and the room floor mesh gains UVs into it ([`uv`]).
+
+use serde::Serialize;
+
+/// Coverage at which a texel saturates to white: this percentile of the floor
+/// cells' coverage, so a few very dense cells don't push the rest into grey.
+const SATURATION_PERCENTILE: f64 = 0.9;
+
+/// One grayscale byte per ground-field cell, row-major from row 0.
+#[derive(Serialize)]
+pub struct WalkabilityTexture {
+    pub width: usize,
+    pub height: usize,
+    /// `width * height` bytes: 255 is well-covered floor, 0 no coverage or
+    /// not floor.
+    pub pixels: serde_bytes::ByteBuf,
+    /// Coverage (accumulated splat weight) that maps to 255.
+    pub saturation: f64,
+}
+
+/// Bake `coverage` (one entry per cell, `None` off the floor) into a
+/// `width x height` texture. Floor texels scale linearly up to the
+/// [`SATURATION_PERCENTILE`] coverage. Off-floor texels next to the floor take
+/// the mean of their floor neighbours, so bilinear filtering at the floor's
+/// edge doesn't bleed black in.
+pub fn bake(width: usize, height: usize, coverage: &[Option<f64>]) -> WalkabilityTexture {
+    let mut floor: Vec<f64> = coverage
+        .iter()
+        .flatten()
+        .map(|c| if c.is_finite() { c.max(0.0) } else { 0.0 })
+        .collect();
+    floor.sort_by(|a, b| a.total_cmp(b));
+    let saturation = floor
+        .get((floor.len().saturating_sub(1) as f64 * SATURATION_PERCENTILE).round() as usize)
+        .copied()
+        .filter(|s| *s > 0.0)
+        .unwrap_or(1.0);
+
+    let texel = |c: f64| {
+        let c = if c.is_finite() { c.max(0.0) } else { 0.0 };
+        ((c / saturation).min(1.0) * 255.0).round() as u8
+    };
+    let mut pixels: Vec<u8> = coverage.iter().map(|c| c.map_or(0, texel)).collect();
+    pixels.resize(width * height, 0);
+
+    let baked = pixels.clone();
+    for row in 0..height {
+        for col in 0..width {
+            let idx = row * width + col;
+            if coverage.get(idx).is_some_and(Option::is_some) {
+                continue;
+            }
+            let (mut sum, mut n) = (0u32, 0u32);
+            for dr in -1isize..=1 {
+                for dc in -1isize..=1 {
+                    let (r, c) = (row as isize + dr, col as isize + dc);
+                    if r < 0 || c < 0 || r >= height as isize || c >= width as isize {
+                        continue;
+                    }
+                    let neighbour = r as usize * width + c as usize;
+                    if coverage.get(neighbour).is_some_and(Option::is_some) {
+                        sum += baked[neighbour] as u32;
+                        n += 1;
+                    }
+                }
+            }
+            if let Some(mean) = (sum + n / 2).checked_div(n) {
+                pixels[idx] = mean as u8;
+            }
+        }
+    }
+
+    WalkabilityTexture {
+        width,
+        height,
+        pixels: serde_bytes::ByteBuf::from(pixels),
+        saturation,
+    }
+}
+
+/// Texture coordinate of field corner (`col`, `row`): `u` along columns, `v`
+/// along rows, origin at the first pixel (glTF / WebGL without `flipY`).
+pub fn uv(col: f64, row: f64, width: usize, height: usize) -> [f32; 2] {
+    [
+        (col / width.max(1) as f64) as f32,
+        (row / height.max(1) as f64) as f32,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coverage_saturates_and_bleeds_past_the_floor_edge() {
+        // 4 x 1 strip: two floor cells, then two off-floor cells.
+        let texture = bake(4, 1, &[Some(2.0), Some(1.0), None, None]);
+        assert_eq!(texture.saturation, 2.0);
+        assert_eq!(texture.pixels.as_slice(), &[255, 128, 128, 0]);
+        assert_eq!(uv(4.0, 1.0, 4, 1), [1.0, 1.0]);
+        assert_eq!(uv(1.0, 0.0, 4, 1), [0.25, 0.0]);
+    }
+}
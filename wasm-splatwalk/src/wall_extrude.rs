@@ -0,0 +1,166 @@
+//! Wall detection and extrusion (`mode = 10`).
+//!
+//! Splat walls are fuzzy, a few centimetres deep, and holed wherever the scan
+//! saw a window, a mirror or nothing at all. Meshed directly they give bumpy
+//! collision that snags a player sliding along them. Mode 10 keeps only where
+//! the walls are: `build_floor_plan`'s line fit finds the wall segments among
+//! the splats whose normals lie roughly horizontal, and [`extrude`] turns each
+//! segment into a closed box `wall_thickness` deep, from the floor up to the
+//! ceiling.
+//!
+//! [`ceiling_height`] takes the ceiling as the median height of the splats
+//! facing up or down above the floor plan's sampling band. Scans with no
+//! ceiling (outdoors, or cropped at the top) fall back to the top of the wall
+//! cluster.
+
+use nalgebra::Vector3;
+
+use crate::cluster::NormalClusterOptions;
+use crate::floor_plan::WallSegment;
+use crate::splat::PointNormal;
+
+/// Fewest splats facing up or down that count as a ceiling.
+pub const MIN_CEILING_SPLATS: usize = 32;
+
+/// Corners of each box face, listed around the face.
+const FACES: [[usize; 4]; 6] = [
+    [0, 1, 2, 3],
+    [4, 5, 6, 7],
+    [0, 1, 5, 4],
+    [1, 2, 6, 5],
+    [2, 3, 7, 6],
+    [3, 0, 4, 7],
+];
+
+/// Median height of the splats at least `above` metres over `floor_y` whose
+/// normals are within the floor cone of vertical, or `None` when there are
+/// fewer than [`MIN_CEILING_SPLATS`].
+pub fn ceiling_height(
+    points: &[PointNormal],
+    floor_y: f64,
+    above: f64,
+    options: &NormalClusterOptions,
+) -> Option<f64> {
+    let mut heights: Vec<f64> = points
+        .iter()
+        .filter(|p| p.point.y >= floor_y + above)
+        .filter(|p| {
+            let len = p.normal.norm();
+            len > 0.0 && (p.normal.y / len).abs() >= options.floor_min_normal_y
+        })
+        .map(|p| p.point.y)
+        .collect();
+    if heights.len() < MIN_CEILING_SPLATS {
+        return None;
+    }
+    let middle = heights.len() / 2;
+    let (_, median, _) = heights.select_nth_unstable_by(middle, f64::total_cmp);
+    Some(*median)
+}
+
+/// One box per segment from `bottom` to `top`, `thickness` deep and centred
+/// on the segment. Each box reaches `thickness / 2` past both ends so walls
+/// meeting at a corner overlap instead of leaving a gap. Triangles face out
+/// of their box.
+pub fn extrude(
+    segments: &[WallSegment],
+    bottom: f64,
+    top: f64,
+    thickness: f64,
+) -> (Vec<f32>, Vec<u32>) {
+    let half = thickness * 0.5;
+    let mut vertices = Vec::with_capacity(segments.len() * 24);
+    let mut indices = Vec::with_capacity(segments.len() * 36);
+    for segment in segments {
+        let along = [
+            segment.end[0] - segment.start[0],
+            segment.end[1] - segment.start[1],
+        ];
+        let length = along[0].hypot(along[1]);
+        if length <= 0.0 {
+            continue;
+        }
+        let d = [along[0] / length * half, along[1] / length * half];
+        let n = [-d[1], d[0]];
+        let start = [segment.start[0] - d[0], segment.start[1] - d[1]];
+        let end = [segment.end[0] + d[0], segment.end[1] + d[1]];
+        let plan = [
+            [start[0] - n[0], start[1] - n[1]],
+            [end[0] - n[0], end[1] - n[1]],
+            [end[0] + n[0], end[1] + n[1]],
+            [start[0] + n[0], start[1] + n[1]],
+        ];
+        let corners: Vec<Vector3<f64>> = [bottom, top]
+            .iter()
+            .flat_map(|&y| plan.iter().map(move |p| Vector3::new(p[0], y, p[1])))
+            .collect();
+        let centre = corners.iter().sum::<Vector3<f64>>() / 8.0;
+
+        let base = (vertices.len() / 3) as u32;
+        vertices.extend(corners.iter().flat_map(|c| c.iter().map(|&v| v as f32)));
+        for face in FACES {
+            let [a, b, c, _] = face.map(|i| corners[i]);
+            let outward = (b - a).cross(&(c - a)).dot(&((a + c) * 0.5 - centre)) >= 0.0;
+            let [i, j, k, l] = face.map(|i| base + i as u32);
+            if outward {
+                indices.extend_from_slice(&[i, j, k, i, k, l]);
+            } else {
+                indices.extend_from_slice(&[i, k, j, i, l, k]);
+            }
+        }
+    }
+    (vertices, indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::splat::test_splat;
+
+    const UP: [f64; 3] = [0.0, 1.0, 0.0];
+
+    #[test]
+    fn segments_become_closed_outward_boxes_up_to_the_ceiling() {
+        let options = NormalClusterOptions {
+            floor_min_normal_y: 0.85,
+            wall_max_normal_y: 0.34,
+            floor_band_height: 0.3,
+        };
+        // Floor at 0, wall splats up to 2.6, a ceiling at 2.7 with a lamp
+        // hanging below it.
+        let mut points: Vec<PointNormal> = (0..40).map(|_| test_splat([0.0; 3], UP)).collect();
+        points.extend((0..40).map(|i| test_splat([0.0, i as f64 * 0.065, 0.0], [1.0, 0.0, 0.0])));
+        points.extend((0..40).map(|_| test_splat([0.0, 2.7, 0.0], [0.0, -1.0, 0.0])));
+        points.extend((0..10).map(|_| test_splat([0.0, 2.2, 0.0], UP)));
+        assert_eq!(ceiling_height(&points, 0.0, 2.0, &options), Some(2.7));
+        assert_eq!(ceiling_height(&points[..100], 0.0, 2.0, &options), None);
+
+        let segment = WallSegment {
+            start: [0.0, 0.0],
+            end: [4.0, 0.0],
+            length: 4.0,
+            support: 80,
+            snapped: true,
+        };
+        let (vertices, indices) = extrude(&[segment], 0.0, 2.7, 0.2);
+        assert_eq!((vertices.len(), indices.len()), (24, 36));
+        let corner = |i: u32| {
+            let v = &vertices[i as usize * 3..i as usize * 3 + 3];
+            Vector3::new(v[0] as f64, v[1] as f64, v[2] as f64)
+        };
+        let xs = (0..8).map(|i| corner(i).x);
+        assert!((xs.clone().fold(f64::MAX, f64::min) + 0.1).abs() < 1e-6);
+        assert!((xs.fold(f64::MIN, f64::max) - 4.1).abs() < 1e-6);
+        // Every triangle faces away from the box centre, and the signed
+        // volume of the closed box is its width x depth x height.
+        let centre = Vector3::new(2.0, 1.35, 0.0);
+        let mut volume = 0.0;
+        for t in indices.chunks_exact(3) {
+            let [a, b, c] = [corner(t[0]), corner(t[1]), corner(t[2])];
+            let normal = (b - a).cross(&(c - a));
+            assert!(normal.dot(&((a + b + c) / 3.0 - centre)) > 0.0);
+            volume += a.dot(&b.cross(&c)) / 6.0;
+        }
+        assert!((volume - 4.2 * 0.2 * 2.7).abs() < 1e-3);
+    }
+}